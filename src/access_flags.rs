@@ -76,6 +76,13 @@ impl ClassAccessFlags {
         flags
     }
 
+    /// Packs the flags back into the access_flags mask they were read from.
+    pub fn to_u16(flags: &[Self]) -> u16 {
+        flags
+            .iter()
+            .fold(0, |mask, flag| mask | flag.clone() as u16)
+    }
+
     pub fn get_string(&self) -> String {
         match self {
             ClassAccessFlags::None => "none",
@@ -179,6 +186,13 @@ impl MethodAccessFlags {
         flags
     }
 
+    /// Packs the flags back into the access_flags mask they were read from.
+    pub fn to_u16(flags: &[Self]) -> u16 {
+        flags
+            .iter()
+            .fold(0, |mask, flag| mask | flag.clone() as u16)
+    }
+
     pub fn get_string(&self) -> String {
         match self {
             MethodAccessFlags::None => "none",
@@ -200,7 +214,7 @@ impl MethodAccessFlags {
 }
 
 #[repr(u16)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FieldAccessFlags {
     None = 0x0000,
     /// Declared public; may be accessed from outside its package.
@@ -260,6 +274,13 @@ impl FieldAccessFlags {
         flags
     }
 
+    /// Packs the flags back into the access_flags mask they were read from.
+    pub fn to_u16(flags: &[Self]) -> u16 {
+        flags
+            .iter()
+            .fold(0, |mask, flag| mask | flag.clone() as u16)
+    }
+
     pub fn get_string(&self) -> String {
         match self {
             FieldAccessFlags::None => "none",
@@ -322,6 +343,14 @@ impl ParameterAccessFlags {
         }
         flags
     }
+
+    /// Packs the flags back into the mask they were read from.
+    pub fn to_u16(flags: &[Self]) -> u16 {
+        flags
+            .iter()
+            .fold(0, |mask, flag| mask | flag.clone() as u16)
+    }
+
     pub fn get_string(&self) -> String {
         match self {
             ParameterAccessFlags::AccFinal => "final",
@@ -332,6 +361,7 @@ impl ParameterAccessFlags {
     }
 }
 
+#[allow(non_snake_case)]
 pub mod ModuleFlags {
     #[repr(u16)]
     #[derive(Debug, Clone)]
@@ -371,6 +401,14 @@ pub mod ModuleFlags {
             }
             flags
         }
+
+        /// Packs the flags back into the mask they were read from.
+        pub fn to_u16(flags: &[Self]) -> u16 {
+            flags
+                .iter()
+                .fold(0, |mask, flag| mask | flag.clone() as u16)
+        }
+
         pub fn get_string(&self) -> String {
             match self {
                 ModuleAccessFlags::AccOpen => "open",
@@ -435,6 +473,14 @@ pub mod ModuleFlags {
             }
             flags
         }
+
+        /// Packs the flags back into the mask they were read from.
+        pub fn to_u16(flags: &[Self]) -> u16 {
+            flags
+                .iter()
+                .fold(0, |mask, flag| mask | flag.clone() as u16)
+        }
+
         pub fn get_string(&self) -> String {
             match self {
                 RequiresAccessFlags::AccTransistive => "trasnsistive",
@@ -478,6 +524,14 @@ pub mod ModuleFlags {
             }
             flags
         }
+
+        /// Packs the flags back into the mask they were read from.
+        pub fn to_u16(flags: &[Self]) -> u16 {
+            flags
+                .iter()
+                .fold(0, |mask, flag| mask | flag.clone() as u16)
+        }
+
         pub fn get_string(&self) -> String {
             match self {
                 ExportsAccessFlags::AccSynthetic => "synthetic",
@@ -518,6 +572,14 @@ pub mod ModuleFlags {
             }
             flags
         }
+
+        /// Packs the flags back into the mask they were read from.
+        pub fn to_u16(flags: &[Self]) -> u16 {
+            flags
+                .iter()
+                .fold(0, |mask, flag| mask | flag.clone() as u16)
+        }
+
         pub fn get_string(&self) -> String {
             match self {
                 OpensAccessFlags::AccSynthetic => "synthetic",
@@ -0,0 +1,1616 @@
+use std::{collections::HashMap, error::Error};
+
+use crate::access_flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::attributes::Code;
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::class_writer::ClassWriter;
+use crate::constants::{Tags, Utf8};
+use crate::errors::class_writing::{WritingCause, WritingError};
+use crate::instructions::*;
+
+/// The magic every class file starts with, left out of the text unless it differs.
+const MAGIC: u32 = 0xCAFEBABE;
+
+/**
+ * Disassembles `class` into the text read by [assemble], which turns it back into the same
+ * bytes as `class.to_bytes()`.
+ *
+ * Code gets labels at every branch target, handler and line number, and the attributes
+ * without a directive of their own are written as hex.
+ */
+pub fn disassemble(class: &ClassFile) -> Result<String, Box<dyn Error>> {
+    Disassembler {
+        class,
+        writer: ClassWriter::new(class),
+        output: String::new(),
+    }
+    .disassemble()
+}
+
+/**
+ * Assembles a class from text, one directive or instruction per line:
+ * ```text
+ * .version 49 0
+ * .constant_pool
+ *     #1 = Class #2
+ *     #2 = Utf8 "Hello"
+ *     #3 = Class #4
+ *     #4 = Utf8 "java/lang/Object"
+ *     #5 = Utf8 "main"
+ *     #6 = Utf8 "([Ljava/lang/String;)V"
+ *     #7 = Utf8 "Code"
+ * .end constant_pool
+ * .class public super #1
+ * .super #3
+ * .method public static #5 #6
+ *     .code 1 1
+ *     L0:
+ *         iconst_0
+ *         ifeq L0
+ *         return
+ *     .end code
+ * .end method
+ * ```
+ * Everything after a `;` outside of a string is a comment.
+ *
+ * Constants are given by their index, so the pool is exactly as written and the class may
+ * be malformed in ways a builder would not allow, such as references to the wrong kind of
+ * constant. Attribute names are the first Utf8 entry holding the name, as when writing a
+ * [ClassFile]. Instructions are encoded as written, with no widening of branches or loads.
+ *
+ * The result is bytes, which `ClassFile::from_bytes` reads or rejects.
+ */
+pub fn assemble(text: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut lines = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let tokens = tokenize(line).map_err(|msg| syntax(number + 1, &msg))?;
+        if !tokens.is_empty() {
+            lines.push(Line {
+                number: number + 1,
+                tokens,
+            });
+        }
+    }
+    Assembler {
+        lines: &lines,
+        position: 0,
+        utf8_indices: HashMap::new(),
+    }
+    .assemble()
+}
+
+struct Disassembler<'a> {
+    class: &'a ClassFile,
+    /// Writes the attributes that have no directive, and knows which were never read.
+    writer: ClassWriter<'a>,
+    output: String,
+}
+
+impl<'a> Disassembler<'a> {
+    fn disassemble(mut self) -> Result<String, Box<dyn Error>> {
+        let class = self.class;
+        if class.magic != MAGIC {
+            self.line(0, &format!(".magic {:#010x}", class.magic));
+        }
+        self.line(
+            0,
+            &format!(".version {} {}", class.major_version, class.minor_version),
+        );
+        self.line(0, ".constant_pool");
+        for index in 1..class.constant_pool_count {
+            let constant = &class.constant_pool[index as usize];
+            if !matches!(constant, ConstantPool::Unknown) {
+                let entry = format!("#{index} = {}", self.constant(constant));
+                let comment = match constant {
+                    ConstantPool::Utf8(_)
+                    | ConstantPool::Integer(_)
+                    | ConstantPool::Float(_)
+                    | ConstantPool::Long(_)
+                    | ConstantPool::Double(_) => String::new(),
+                    _ => self.describe(index),
+                };
+                self.commented(1, &entry, &comment);
+            }
+        }
+        self.line(0, ".end constant_pool");
+
+        let flags: Vec<String> = class.access_flags.iter().map(|f| f.get_string()).collect();
+        let this_class = words(&flags, &format!("#{}", class.this_class));
+        self.commented(
+            0,
+            &format!(".class {this_class}"),
+            &self.describe(class.this_class),
+        );
+        self.commented(
+            0,
+            &format!(".super #{}", class.super_class),
+            &self.describe(class.super_class),
+        );
+        for interface in &class.interfaces {
+            self.commented(
+                0,
+                &format!(".implements #{interface}"),
+                &self.describe(*interface),
+            );
+        }
+        for field in &class.fields {
+            let flags: Vec<String> = field.access_flags.iter().map(|f| f.get_string()).collect();
+            self.member(
+                ".field",
+                &flags,
+                field.name_index,
+                field.descriptor_index,
+                &field.attributes,
+            )?;
+        }
+        for method in &class.methods {
+            let flags: Vec<String> = method.access_flags.iter().map(|f| f.get_string()).collect();
+            self.member(
+                ".method",
+                &flags,
+                method.name_index,
+                method.descriptor_index,
+                &method.attributes,
+            )?;
+        }
+        self.attributes(0, &class.attributes)?;
+        Ok(self.output)
+    }
+
+    fn member(
+        &mut self,
+        directive: &str,
+        flags: &[String],
+        name_index: u16,
+        descriptor_index: u16,
+        attributes: &[AttributeInfo],
+    ) -> Result<(), Box<dyn Error>> {
+        let comment = format!(
+            "{}:{}",
+            self.describe(name_index),
+            self.describe(descriptor_index)
+        );
+        let declaration = words(flags, &format!("#{name_index} #{descriptor_index}"));
+        self.commented(0, &format!("{directive} {declaration}"), &comment);
+        self.attributes(1, attributes)?;
+        self.line(0, &format!(".end {}", &directive[1..]));
+        Ok(())
+    }
+
+    fn attributes(
+        &mut self,
+        indent: usize,
+        attributes: &[AttributeInfo],
+    ) -> Result<(), Box<dyn Error>> {
+        for attribute in attributes {
+            if self.writer.is_implicit(attribute) {
+                continue;
+            }
+            match attribute {
+                AttributeInfo::ConstantValue(value) => self.commented(
+                    indent,
+                    &format!(".constant_value #{}", value.constantvalue_index),
+                    &self.describe(value.constantvalue_index),
+                ),
+                AttributeInfo::Signature(signature) => self.commented(
+                    indent,
+                    &format!(".signature #{}", signature.signature_index),
+                    &self.describe(signature.signature_index),
+                ),
+                AttributeInfo::SourceFile(source_file) => self.commented(
+                    indent,
+                    &format!(".source_file #{}", source_file.sourcefile_index),
+                    &self.describe(source_file.sourcefile_index),
+                ),
+                AttributeInfo::Exceptions(exceptions) => {
+                    let indices: Vec<String> = exceptions
+                        .exception_index_table
+                        .iter()
+                        .map(|index| format!("#{index}"))
+                        .collect();
+                    self.line(indent, &words(&[".exceptions".into()], &indices.join(" ")));
+                }
+                AttributeInfo::Code(code) => self.code(indent, code)?,
+                _ => self.hex(indent, attribute)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn code(&mut self, indent: usize, code: &Code) -> Result<(), Box<dyn Error>> {
+        let instructions = decode(&code.code)?;
+        // Labels can be placed before any instruction and at the end of the code.
+        let mut boundaries: Vec<u32> = instructions.iter().map(|i| i.pc).collect();
+        boundaries.push(code.code.len() as u32);
+        let is_boundary = |pc: u32| boundaries.binary_search(&pc).is_ok();
+
+        let mut labels = vec![];
+        for instruction in &instructions {
+            match &instruction.operands {
+                Operands::Branch(target) => labels.push(*target),
+                Operands::TableSwitch {
+                    default, targets, ..
+                } => {
+                    labels.push(*default);
+                    labels.extend(targets);
+                }
+                Operands::LookupSwitch { default, pairs } => {
+                    labels.push(*default);
+                    labels.extend(pairs.iter().map(|(_, target)| *target));
+                }
+                _ => {}
+            }
+        }
+        for handler in &code.exception_tables {
+            for pc in [handler.start_pc, handler.end_pc, handler.handler_pc] {
+                if !is_boundary(pc as u32) {
+                    return Err(Box::new(WritingError::new(
+                        WritingCause::InvalidLabel(pc as usize),
+                        "exception handler does not start or end at an instruction",
+                    )));
+                }
+                labels.push(pc as u32);
+            }
+        }
+        // The tables of pcs that get labels rather than hex when all their pcs have one.
+        let mut tables = vec![false; code.attributes.len()];
+        for (attribute, labelled) in code.attributes.iter().zip(tables.iter_mut()) {
+            let pcs: Vec<u32> = match attribute {
+                AttributeInfo::LineNumberTable(table) => table
+                    .line_number_table
+                    .iter()
+                    .map(|line| line.start_pc as u32)
+                    .collect(),
+                AttributeInfo::LocalVariableTable(table) => table
+                    .local_variable_table
+                    .iter()
+                    .flat_map(|local| {
+                        [
+                            local.start_pc as u32,
+                            local.start_pc as u32 + local.length as u32,
+                        ]
+                    })
+                    .collect(),
+                AttributeInfo::LocalVariableTypeTable(table) => table
+                    .local_variable_type_table
+                    .iter()
+                    .flat_map(|local| {
+                        [
+                            local.start_pc as u32,
+                            local.start_pc as u32 + local.length as u32,
+                        ]
+                    })
+                    .collect(),
+                _ => continue,
+            };
+            if pcs.iter().all(|pc| is_boundary(*pc)) {
+                *labelled = true;
+                labels.extend(pcs);
+            }
+        }
+        labels.sort_unstable();
+        labels.dedup();
+        if let Some(pc) = labels.iter().find(|pc| !is_boundary(**pc)) {
+            return Err(Box::new(WritingError::new(
+                WritingCause::InvalidLabel(*pc as usize),
+                "branch target is not the start of an instruction",
+            )));
+        }
+
+        self.line(
+            indent,
+            &format!(".code {} {}", code.max_stack, code.max_locals),
+        );
+        let mut next_label = labels.iter().peekable();
+        for instruction in &instructions {
+            if next_label.next_if_eq(&&instruction.pc).is_some() {
+                self.line(indent, &format!("L{}:", instruction.pc));
+            }
+            let (text, comment) = self.instruction(instruction);
+            self.commented(indent + 1, &text, &comment);
+        }
+        if next_label.next_if_eq(&&(code.code.len() as u32)).is_some() {
+            self.line(indent, &format!("L{}:", code.code.len()));
+        }
+        for handler in &code.exception_tables {
+            let catch_type = match handler.catch_type {
+                0 => "any".to_string(),
+                index => format!("#{index}"),
+            };
+            self.commented(
+                indent + 1,
+                &format!(
+                    ".catch L{} L{} L{} {catch_type}",
+                    handler.start_pc, handler.end_pc, handler.handler_pc
+                ),
+                &self.describe(handler.catch_type),
+            );
+        }
+        for (attribute, labelled) in code.attributes.iter().zip(tables) {
+            if self.writer.is_implicit(attribute) {
+                continue;
+            }
+            match attribute {
+                AttributeInfo::LineNumberTable(table) if labelled => {
+                    self.line(indent + 1, ".line_numbers");
+                    for line in &table.line_number_table {
+                        self.line(
+                            indent + 2,
+                            &format!("L{} {}", line.start_pc, line.line_number),
+                        );
+                    }
+                    self.line(indent + 1, ".end line_numbers");
+                }
+                AttributeInfo::LocalVariableTable(table) if labelled => {
+                    self.line(indent + 1, ".local_variables");
+                    for local in &table.local_variable_table {
+                        self.commented(
+                            indent + 2,
+                            &format!(
+                                "L{} L{} {} #{} #{}",
+                                local.start_pc,
+                                local.start_pc as u32 + local.length as u32,
+                                local.index,
+                                local.name_index,
+                                local.descriptor_index
+                            ),
+                            &format!(
+                                "{} {}",
+                                self.describe(local.name_index),
+                                self.describe(local.descriptor_index)
+                            ),
+                        );
+                    }
+                    self.line(indent + 1, ".end local_variables");
+                }
+                AttributeInfo::LocalVariableTypeTable(table) if labelled => {
+                    self.line(indent + 1, ".local_variable_types");
+                    for local in &table.local_variable_type_table {
+                        self.commented(
+                            indent + 2,
+                            &format!(
+                                "L{} L{} {} #{} #{}",
+                                local.start_pc,
+                                local.start_pc as u32 + local.length as u32,
+                                local.index,
+                                local.name_index,
+                                local.signature_index
+                            ),
+                            &format!(
+                                "{} {}",
+                                self.describe(local.name_index),
+                                self.describe(local.signature_index)
+                            ),
+                        );
+                    }
+                    self.line(indent + 1, ".end local_variable_types");
+                }
+                _ => self.hex(indent + 1, attribute)?,
+            }
+        }
+        self.line(indent, ".end code");
+        Ok(())
+    }
+
+    /// An instruction and a comment describing the constant it uses, if any.
+    fn instruction(&self, instruction: &Instruction) -> (String, String) {
+        let mnemonic = instruction.get_mnemonic();
+        match &instruction.operands {
+            Operands::None => (mnemonic, String::new()),
+            Operands::Immediate(value) => (format!("{mnemonic} {value}"), String::new()),
+            Operands::Constant(index) => (format!("{mnemonic} #{index}"), self.describe(*index)),
+            Operands::Local(index) => (format!("{mnemonic} {index}"), String::new()),
+            Operands::Increment { index, value } => {
+                (format!("{mnemonic} {index} {value}"), String::new())
+            }
+            Operands::Branch(target) => (format!("{mnemonic} L{target}"), String::new()),
+            Operands::TableSwitch {
+                default,
+                low,
+                targets,
+                ..
+            } => {
+                let targets: Vec<String> = targets.iter().map(|pc| format!("L{pc}")).collect();
+                (
+                    format!("{mnemonic} {low} {} default L{default}", targets.join(" ")),
+                    String::new(),
+                )
+            }
+            Operands::LookupSwitch { default, pairs } => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, pc)| format!("{key} L{pc} "))
+                    .collect();
+                (
+                    format!("{mnemonic} {}default L{default}", pairs.concat()),
+                    String::new(),
+                )
+            }
+            Operands::InvokeInterface { index, count } => (
+                format!("{mnemonic} #{index} {count}"),
+                self.describe(*index),
+            ),
+            Operands::InvokeDynamic(index) => {
+                (format!("{mnemonic} #{index}"), self.describe(*index))
+            }
+            Operands::NewArray(atype) => match get_array_type_name(*atype) {
+                Some(name) => (format!("{mnemonic} {name}"), String::new()),
+                None => (format!("{mnemonic} {atype}"), String::new()),
+            },
+            Operands::MultiANewArray { index, dimensions } => (
+                format!("{mnemonic} #{index} {dimensions}"),
+                self.describe(*index),
+            ),
+        }
+    }
+
+    /// An attribute as its name and body in hex.
+    fn hex(&mut self, indent: usize, attribute: &AttributeInfo) -> Result<(), Box<dyn Error>> {
+        let body = self.writer.write_attribute_body(attribute)?;
+        let hex: String = body.iter().map(|byte| format!("{byte:02x}")).collect();
+        let name = quote(&attribute.get_name().encode_utf16().collect::<Vec<u16>>());
+        self.line(indent, &words(&[format!(".attribute {name}")], &hex));
+        Ok(())
+    }
+
+    /// A constant pool entry as it follows `#n = `.
+    fn constant(&self, constant: &ConstantPool) -> String {
+        match constant {
+            ConstantPool::Utf8(utf8) => {
+                let units = utf8.get_utf16();
+                if Utf8::from_utf16(&units).bytes == utf8.bytes {
+                    format!("Utf8 {}", quote(&units))
+                } else {
+                    // Bytes that are not valid modified UTF-8 are kept as they are.
+                    let hex: String = utf8.bytes.iter().map(|b| format!("{b:02x}")).collect();
+                    format!("Utf8 0x{hex}")
+                }
+            }
+            ConstantPool::Integer(integer) => format!("Integer {}", integer.get_value()),
+            ConstantPool::Float(float) if float.get_value().is_nan() => {
+                format!("Float {:#010x}", float.bytes)
+            }
+            ConstantPool::Float(float) => format!("Float {:?}", float.get_value()),
+            ConstantPool::Long(long) => format!("Long {}", long.get_value()),
+            ConstantPool::Double(double) if double.get_value().is_nan() => format!(
+                "Double {:#018x}",
+                (double.high_bytes as u64) << 32 | double.low_bytes as u64
+            ),
+            ConstantPool::Double(double) => format!("Double {:?}", double.get_value()),
+            ConstantPool::Class(class) => format!("Class #{}", class.name_index),
+            ConstantPool::String(string) => format!("String #{}", string.string_index),
+            ConstantPool::Fieldref(field) => format!(
+                "Fieldref #{} #{}",
+                field.class_index, field.name_and_type_index
+            ),
+            ConstantPool::Methodref(method) => format!(
+                "Methodref #{} #{}",
+                method.class_index, method.name_and_type_index
+            ),
+            ConstantPool::InterfaceMethodRef(method) => format!(
+                "InterfaceMethodref #{} #{}",
+                method.class_index, method.name_and_type_index
+            ),
+            ConstantPool::NameAndType(name_and_type) => format!(
+                "NameAndType #{} #{}",
+                name_and_type.name_index, name_and_type.descriptor_index
+            ),
+            ConstantPool::MethodHandle(handle) => format!(
+                "MethodHandle {} #{}",
+                handle.reference_kind, handle.reference_index
+            ),
+            ConstantPool::MethodType(method_type) => {
+                format!("MethodType #{}", method_type.descriptor_index)
+            }
+            ConstantPool::Dynamic(dynamic) => format!(
+                "Dynamic {} #{}",
+                dynamic.bootstrap_method_attr_index, dynamic.name_and_type_index
+            ),
+            ConstantPool::InvokeDynamic(dynamic) => format!(
+                "InvokeDynamic {} #{}",
+                dynamic.bootstrap_method_attr_index, dynamic.name_and_type_index
+            ),
+            ConstantPool::Module(module) => format!("Module #{}", module.name_index),
+            ConstantPool::Package(package) => format!("Package #{}", package.name_index),
+            ConstantPool::Unknown => String::new(),
+        }
+    }
+
+    /// What a constant refers to, for the comments that make the indices readable.
+    fn describe(&self, index: u16) -> String {
+        let pool = &self.class.constant_pool;
+        let text = match pool.get(index as usize) {
+            _ if index == 0 || index >= self.class.constant_pool_count => return String::new(),
+            Some(ConstantPool::Utf8(utf8)) => utf8.get_raw_string(),
+            Some(ConstantPool::Integer(integer)) => integer.get_value().to_string(),
+            Some(ConstantPool::Float(float)) => format!("{:?}f", float.get_value()),
+            Some(ConstantPool::Long(long)) => format!("{}l", long.get_value()),
+            Some(ConstantPool::Double(double)) => format!("{:?}d", double.get_value()),
+            Some(ConstantPool::Class(class)) => self.describe(class.name_index),
+            Some(ConstantPool::String(string)) => {
+                format!("\"{}\"", self.describe(string.string_index))
+            }
+            Some(ConstantPool::Module(module)) => self.describe(module.name_index),
+            Some(ConstantPool::Package(package)) => self.describe(package.name_index),
+            Some(ConstantPool::MethodType(method_type)) => {
+                self.describe(method_type.descriptor_index)
+            }
+            Some(ConstantPool::Fieldref(field)) => format!(
+                "{}.{}",
+                self.describe(field.class_index),
+                self.describe(field.name_and_type_index)
+            ),
+            Some(ConstantPool::Methodref(method)) => format!(
+                "{}.{}",
+                self.describe(method.class_index),
+                self.describe(method.name_and_type_index)
+            ),
+            Some(ConstantPool::InterfaceMethodRef(method)) => format!(
+                "{}.{}",
+                self.describe(method.class_index),
+                self.describe(method.name_and_type_index)
+            ),
+            Some(ConstantPool::NameAndType(name_and_type)) => format!(
+                "{}:{}",
+                self.describe(name_and_type.name_index),
+                self.describe(name_and_type.descriptor_index)
+            ),
+            Some(ConstantPool::MethodHandle(handle)) => format!(
+                "{} {}",
+                handle.get_kind_name(),
+                self.describe(handle.reference_index)
+            ),
+            Some(ConstantPool::Dynamic(dynamic)) => format!(
+                "{}:{}",
+                dynamic.bootstrap_method_attr_index,
+                self.describe(dynamic.name_and_type_index)
+            ),
+            Some(ConstantPool::InvokeDynamic(dynamic)) => format!(
+                "{}:{}",
+                dynamic.bootstrap_method_attr_index,
+                self.describe(dynamic.name_and_type_index)
+            ),
+            Some(ConstantPool::Unknown) | None => String::new(),
+        };
+        // A comment runs to the end of the line, so it cannot hold one.
+        text.replace(['\n', '\r'], " ")
+    }
+
+    fn line(&mut self, indent: usize, text: &str) {
+        self.output.push_str(&"    ".repeat(indent));
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    fn commented(&mut self, indent: usize, text: &str, comment: &str) {
+        if comment.is_empty() {
+            self.line(indent, text);
+        } else {
+            self.line(indent, &format!("{text} ; {comment}"));
+        }
+    }
+}
+
+/// `words` and then `last`, separated by spaces.
+fn words(words: &[String], last: &str) -> String {
+    let mut text = words.join(" ");
+    if !text.is_empty() && !last.is_empty() {
+        text.push(' ');
+    }
+    text.push_str(last);
+    text
+}
+
+/// Quotes UTF-16 text as a string token, escaping anything but printable characters.
+fn quote(units: &[u16]) -> String {
+    let mut quoted = String::from("\"");
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok('"') => quoted.push_str("\\\""),
+            Ok('\\') => quoted.push_str("\\\\"),
+            Ok('\n') => quoted.push_str("\\n"),
+            Ok('\t') => quoted.push_str("\\t"),
+            Ok('\r') => quoted.push_str("\\r"),
+            Ok(c) if !c.is_control() => quoted.push(c),
+            Ok(c) => {
+                let mut buffer = [0; 2];
+                for unit in c.encode_utf16(&mut buffer) {
+                    quoted.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+            // An unpaired surrogate, which only an escape can hold.
+            Err(error) => quoted.push_str(&format!("\\u{:04x}", error.unpaired_surrogate())),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    /// A string in double quotes, as UTF-16 so that it can hold unpaired surrogates.
+    Quoted(Vec<u16>),
+}
+
+/// Splits a line into words and quoted strings, dropping any comment.
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ';' => break,
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '"' => {
+                chars.next();
+                let mut units = vec![];
+                loop {
+                    match chars.next() {
+                        None => return Err("string is not closed".into()),
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => units.push('\n' as u16),
+                            Some('t') => units.push('\t' as u16),
+                            Some('r') => units.push('\r' as u16),
+                            Some(c @ ('"' | '\\')) => units.push(c as u16),
+                            Some('u') => {
+                                let hex: String = chars.by_ref().take(4).collect();
+                                let unit = u16::from_str_radix(&hex, 16)
+                                    .map_err(|_| format!("invalid escape \\u{hex}"))?;
+                                units.push(unit);
+                            }
+                            c => return Err(format!("invalid escape {c:?}")),
+                        },
+                        Some(c) => {
+                            let mut buffer = [0; 2];
+                            units.extend_from_slice(c.encode_utf16(&mut buffer));
+                        }
+                    }
+                }
+                tokens.push(Token::Quoted(units));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ';' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Line {
+    number: usize,
+    tokens: Vec<Token>,
+}
+
+impl Line {
+    /// The directive, mnemonic or label the line starts with.
+    fn keyword(&self) -> &str {
+        match &self.tokens[0] {
+            Token::Word(word) => word,
+            Token::Quoted(_) => "",
+        }
+    }
+
+    fn error(&self, msg: &str) -> Box<dyn Error> {
+        syntax(self.number, msg)
+    }
+
+    /// The operands after the keyword, with methods that consume them in order.
+    fn operands(&self) -> Args<'_> {
+        Args {
+            line: self,
+            position: 1,
+        }
+    }
+}
+
+struct Args<'a> {
+    line: &'a Line,
+    position: usize,
+}
+
+impl<'a> Args<'a> {
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.line.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        match self.line.tokens.get(self.position) {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<&'a str, Box<dyn Error>> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            _ => Err(self.line.error(&format!("expected {what}"))),
+        }
+    }
+
+    /// A number in decimal, or in hex after `0x`.
+    fn number<T: TryFrom<i64>>(&mut self, what: &str) -> Result<T, Box<dyn Error>> {
+        let word = self.word(what)?;
+        let value = match word.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16).ok(),
+            None => word.parse::<i64>().ok(),
+        };
+        value
+            .and_then(|value| T::try_from(value).ok())
+            .ok_or_else(|| self.line.error(&format!("{word} is not a valid {what}")))
+    }
+
+    /// A constant pool index, `#n`.
+    fn index(&mut self) -> Result<u16, Box<dyn Error>> {
+        let word = self.word("a constant pool index")?;
+        word.strip_prefix('#')
+            .and_then(|index| index.parse().ok())
+            .ok_or_else(|| {
+                self.line
+                    .error(&format!("{word} is not a constant pool index"))
+            })
+    }
+
+    fn quoted(&mut self, what: &str) -> Result<&'a [u16], Box<dyn Error>> {
+        match self.next() {
+            Some(Token::Quoted(units)) => Ok(units),
+            _ => Err(self.line.error(&format!("expected {what}"))),
+        }
+    }
+
+    /// The pc of a label, or 0 if it is not known yet.
+    fn label(&mut self, labels: Option<&HashMap<String, u32>>) -> Result<u32, Box<dyn Error>> {
+        let name = self.word("a label")?;
+        match labels {
+            None => Ok(0),
+            Some(labels) => labels
+                .get(name)
+                .copied()
+                .ok_or_else(|| self.line.error(&format!("label {name} is not defined"))),
+        }
+    }
+
+    fn end(&self) -> Result<(), Box<dyn Error>> {
+        if self.position < self.line.tokens.len() {
+            return Err(self
+                .line
+                .error("unexpected operands at the end of the line"));
+        }
+        Ok(())
+    }
+}
+
+struct Assembler<'a> {
+    lines: &'a [Line],
+    position: usize,
+    /// The index of the first Utf8 entry holding each string, for attribute names.
+    utf8_indices: HashMap<String, u16>,
+}
+
+impl<'a> Assembler<'a> {
+    fn assemble(mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut bytes = vec![];
+        let mut magic = MAGIC;
+        if self.peek_keyword() == Some(".magic") {
+            let line = self.next_line()?;
+            let mut operands = line.operands();
+            magic = operands.number("magic")?;
+            operands.end()?;
+        }
+        let line = self.expect(".version")?;
+        let mut operands = line.operands();
+        let major_version: u16 = operands.number("major version")?;
+        let minor_version: u16 = operands.number("minor version")?;
+        operands.end()?;
+        bytes.extend(magic.to_be_bytes());
+        bytes.extend(minor_version.to_be_bytes());
+        bytes.extend(major_version.to_be_bytes());
+        self.constant_pool(&mut bytes)?;
+
+        let line = self.expect(".class")?;
+        let mut operands = line.operands();
+        let flags = flags(&mut operands, &ClassAccessFlags::from_u16(u16::MAX), |f| {
+            (f.get_string(), f.clone() as u16)
+        })?;
+        let this_class = operands.index()?;
+        operands.end()?;
+        let line = self.expect(".super")?;
+        let mut operands = line.operands();
+        let super_class = match operands.peek() {
+            Some("#0") | None => {
+                operands.next();
+                0
+            }
+            _ => operands.index()?,
+        };
+        operands.end()?;
+        bytes.extend(flags.to_be_bytes());
+        bytes.extend(this_class.to_be_bytes());
+        bytes.extend(super_class.to_be_bytes());
+
+        let mut interfaces = vec![];
+        let mut fields = vec![];
+        let mut methods = vec![];
+        let mut attributes = vec![];
+        while self.position < self.lines.len() {
+            match self.peek_keyword() {
+                Some(".implements") => {
+                    let line = self.next_line()?;
+                    let mut operands = line.operands();
+                    interfaces.push(operands.index()?);
+                    operands.end()?;
+                }
+                Some(".field") => fields.push(self.member(false)?),
+                Some(".method") => methods.push(self.member(true)?),
+                _ => attributes.push(self.attribute(false)?),
+            }
+        }
+        write_count(&mut bytes, interfaces.len(), "interfaces")?;
+        for interface in interfaces {
+            bytes.extend(interface.to_be_bytes());
+        }
+        write_table(&mut bytes, fields, "fields")?;
+        write_table(&mut bytes, methods, "methods")?;
+        write_table(&mut bytes, attributes, "attributes")?;
+        Ok(bytes)
+    }
+
+    fn constant_pool(&mut self, bytes: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+        self.expect(".constant_pool")?;
+        let mut entries = vec![];
+        let mut count: u32 = 1;
+        loop {
+            let line = self.next_line()?;
+            if line.keyword() == ".end" {
+                expect_end(line, "constant_pool")?;
+                break;
+            }
+            let mut operands = line.operands();
+            let index = line.keyword();
+            if index != format!("#{count}") {
+                return Err(line.error(&format!("expected constant #{count}, not {index}")));
+            }
+            if operands.word("=")? != "=" {
+                return Err(line.error("expected = after the index"));
+            }
+            let kind = operands.word("a constant kind")?;
+            let (tag, mut entry) = match kind {
+                "Utf8" => {
+                    let utf8 = match operands.peek() {
+                        Some(word) if word.starts_with("0x") => {
+                            operands.next();
+                            let mut utf8 = Utf8::from_utf16(&[]);
+                            utf8.bytes = parse_hex(&word[2..])
+                                .ok_or_else(|| line.error(&format!("{word} is not valid hex")))?;
+                            utf8
+                        }
+                        _ => Utf8::from_utf16(operands.quoted("a string")?),
+                    };
+                    let length = u16::try_from(utf8.bytes.len())
+                        .map_err(|_| line.error("Utf8 constant is longer than 65535 bytes"))?;
+                    self.utf8_indices
+                        .entry(utf8.get_raw_string())
+                        .or_insert(count as u16);
+                    let mut entry = length.to_be_bytes().to_vec();
+                    entry.extend(utf8.bytes);
+                    (Tags::Utf8, entry)
+                }
+                "Integer" => (
+                    Tags::Integer,
+                    operands.number::<i32>("int")?.to_be_bytes().to_vec(),
+                ),
+                "Float" => (
+                    Tags::Float,
+                    float_bits(&mut operands)?.to_be_bytes().to_vec(),
+                ),
+                "Long" => (
+                    Tags::Long,
+                    operands.number::<i64>("long")?.to_be_bytes().to_vec(),
+                ),
+                "Double" => (
+                    Tags::Double,
+                    double_bits(&mut operands)?.to_be_bytes().to_vec(),
+                ),
+                "Class" => (Tags::Class, vec![]),
+                "String" => (Tags::String, vec![]),
+                "Fieldref" => (Tags::Fieldref, vec![]),
+                "Methodref" => (Tags::Methodref, vec![]),
+                "InterfaceMethodref" => (Tags::InterfaceMethodRef, vec![]),
+                "NameAndType" => (Tags::NameAndType, vec![]),
+                "MethodHandle" => (
+                    Tags::MethodHandle,
+                    vec![operands.number::<u8>("reference kind")?],
+                ),
+                "MethodType" => (Tags::MethodType, vec![]),
+                "Dynamic" => (
+                    Tags::Dynamic,
+                    operands
+                        .number::<u16>("bootstrap method")?
+                        .to_be_bytes()
+                        .to_vec(),
+                ),
+                "InvokeDynamic" => (
+                    Tags::InvokeDynamic,
+                    operands
+                        .number::<u16>("bootstrap method")?
+                        .to_be_bytes()
+                        .to_vec(),
+                ),
+                "Module" => (Tags::Module, vec![]),
+                "Package" => (Tags::Package, vec![]),
+                kind => return Err(line.error(&format!("unknown constant kind {kind}"))),
+            };
+            // The rest of an entry is constant pool indices.
+            while operands.position < line.tokens.len() {
+                entry.extend(operands.index()?.to_be_bytes());
+            }
+            // §4.4.5: a long or double takes two entries.
+            count += 1 + matches!(tag, Tags::Long | Tags::Double) as u32;
+            entries.push(tag as u8);
+            entries.extend(entry);
+            if count > u16::MAX as u32 {
+                return Err(line.error("a constant pool holds at most 65534 entries"));
+            }
+        }
+        bytes.extend((count as u16).to_be_bytes());
+        bytes.extend(entries);
+        Ok(())
+    }
+
+    /// A field or method, from its declaration to its `.end`.
+    fn member(&mut self, method: bool) -> Result<Vec<u8>, Box<dyn Error>> {
+        let line = self.next_line()?;
+        let mut operands = line.operands();
+        let flags = if method {
+            flags(&mut operands, &MethodAccessFlags::from_u16(u16::MAX), |f| {
+                (f.get_string(), f.clone() as u16)
+            })?
+        } else {
+            flags(&mut operands, &FieldAccessFlags::from_u16(u16::MAX), |f| {
+                (f.get_string(), f.clone() as u16)
+            })?
+        };
+        let name_index = operands.index()?;
+        let descriptor_index = operands.index()?;
+        operands.end()?;
+        let kind = if method { "method" } else { "field" };
+        let mut attributes = vec![];
+        loop {
+            match self.peek_keyword() {
+                Some(".end") => {
+                    expect_end(self.next_line()?, kind)?;
+                    break;
+                }
+                _ => attributes.push(self.attribute(method)?),
+            }
+        }
+        let mut bytes = vec![];
+        bytes.extend(flags.to_be_bytes());
+        bytes.extend(name_index.to_be_bytes());
+        bytes.extend(descriptor_index.to_be_bytes());
+        write_table(&mut bytes, attributes, "attributes")?;
+        Ok(bytes)
+    }
+
+    /// An attribute of a class, field or method, with its name and length.
+    fn attribute(&mut self, method: bool) -> Result<Vec<u8>, Box<dyn Error>> {
+        let line = self.next_line()?;
+        let mut operands = line.operands();
+        let (name, body) = match line.keyword() {
+            ".constant_value" => ("ConstantValue", operands.index()?.to_be_bytes().to_vec()),
+            ".signature" => ("Signature", operands.index()?.to_be_bytes().to_vec()),
+            ".source_file" => ("SourceFile", operands.index()?.to_be_bytes().to_vec()),
+            ".exceptions" => {
+                let mut indices = vec![];
+                while operands.position < line.tokens.len() {
+                    indices.push(operands.index()?);
+                }
+                let mut body = vec![];
+                write_count(&mut body, indices.len(), "exceptions")?;
+                for index in indices {
+                    body.extend(index.to_be_bytes());
+                }
+                ("Exceptions", body)
+            }
+            ".code" if method => {
+                let max_stack: u16 = operands.number("max_stack")?;
+                let max_locals: u16 = operands.number("max_locals")?;
+                operands.end()?;
+                let body = self.code(line.number, max_stack, max_locals)?;
+                return self.named_attribute(line, "Code", body);
+            }
+            ".attribute" => return self.hex_attribute(line),
+            keyword => return Err(line.error(&format!("unexpected {keyword}"))),
+        };
+        operands.end()?;
+        self.named_attribute(line, name, body)
+    }
+
+    /// The body of a Code attribute, up to `.end code`.
+    fn code(
+        &mut self,
+        start: usize,
+        max_stack: u16,
+        max_locals: u16,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let first = self.position;
+        let mut depth = 0;
+        // Finds the end of the block, so the instructions can be read twice.
+        loop {
+            let line = self
+                .lines
+                .get(self.position)
+                .ok_or_else(|| syntax(start, "code is missing .end code"))?;
+            self.position += 1;
+            match line.keyword() {
+                ".line_numbers" | ".local_variables" | ".local_variable_types" => depth += 1,
+                ".end" if depth > 0 => depth -= 1,
+                ".end" => {
+                    expect_end(line, "code")?;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let end = self.position - 1;
+
+        // The first pass places the labels, which needs only the size of each instruction.
+        let mut labels = HashMap::new();
+        let mut pc: u32 = 0;
+        let mut scratch = vec![];
+        let mut nested = false;
+        for line in &self.lines[first..end] {
+            let keyword = line.keyword();
+            if nested || keyword.starts_with('.') {
+                nested = match keyword {
+                    ".line_numbers" | ".local_variables" | ".local_variable_types" => true,
+                    ".end" => false,
+                    _ => nested,
+                };
+            } else if let Some(label) = keyword.strip_suffix(':') {
+                if line.tokens.len() > 1 {
+                    return Err(line.error("a label must be on a line of its own"));
+                }
+                if labels.insert(label.to_string(), pc).is_some() {
+                    return Err(line.error(&format!("label {label} is defined twice")));
+                }
+            } else {
+                scratch.clear();
+                encode(line, pc, None, &mut scratch)?;
+                pc += scratch.len() as u32;
+            }
+        }
+        if pc == 0 || pc > u16::MAX as u32 {
+            return Err(syntax(
+                start,
+                "the code array must be 1 to 65535 bytes long",
+            ));
+        }
+
+        let mut code = vec![];
+        let mut handlers = vec![];
+        let mut attributes = vec![];
+        let mut position = first;
+        while position < end {
+            let line = &self.lines[position];
+            position += 1;
+            let keyword = line.keyword();
+            let mut operands = line.operands();
+            match keyword {
+                ".catch" => {
+                    for _ in 0..3 {
+                        handlers.extend((operands.label(Some(&labels))? as u16).to_be_bytes());
+                    }
+                    let catch_type = match operands.peek() {
+                        Some("any") => {
+                            operands.next();
+                            0
+                        }
+                        _ => operands.index()?,
+                    };
+                    operands.end()?;
+                    handlers.extend(catch_type.to_be_bytes());
+                }
+                ".line_numbers" | ".local_variables" | ".local_variable_types" => {
+                    let mut entries = vec![];
+                    loop {
+                        let entry = &self.lines[position];
+                        position += 1;
+                        if entry.keyword() == ".end" {
+                            expect_end(entry, &keyword[1..])?;
+                            break;
+                        }
+                        let mut operands = entry.operands();
+                        operands.position = 0;
+                        let start = operands.label(Some(&labels))?;
+                        if keyword == ".line_numbers" {
+                            entries.extend((start as u16).to_be_bytes());
+                            entries.extend(operands.number::<u16>("line number")?.to_be_bytes());
+                        } else {
+                            let end = operands.label(Some(&labels))?;
+                            if end < start {
+                                return Err(entry.error("local variable ends before it starts"));
+                            }
+                            entries.extend((start as u16).to_be_bytes());
+                            entries.extend(((end - start) as u16).to_be_bytes());
+                            let index: u16 = operands.number("local variable index")?;
+                            entries.extend(operands.index()?.to_be_bytes());
+                            entries.extend(operands.index()?.to_be_bytes());
+                            entries.extend(index.to_be_bytes());
+                        }
+                        operands.end()?;
+                    }
+                    let size = if keyword == ".line_numbers" { 4 } else { 10 };
+                    let mut body = vec![];
+                    write_count(&mut body, entries.len() / size, &keyword[1..])?;
+                    body.extend(entries);
+                    let name = match keyword {
+                        ".line_numbers" => "LineNumberTable",
+                        ".local_variables" => "LocalVariableTable",
+                        _ => "LocalVariableTypeTable",
+                    };
+                    attributes.push(self.named_attribute(line, name, body)?);
+                }
+                ".attribute" => attributes.push(self.hex_attribute(line)?),
+                keyword if keyword.starts_with('.') => {
+                    return Err(line.error(&format!("unexpected {keyword} in code")))
+                }
+                keyword if keyword.ends_with(':') => {}
+                _ => encode(line, code.len() as u32, Some(&labels), &mut code)?,
+            }
+        }
+
+        let mut bytes = vec![];
+        bytes.extend(max_stack.to_be_bytes());
+        bytes.extend(max_locals.to_be_bytes());
+        bytes.extend((code.len() as u32).to_be_bytes());
+        bytes.extend(code);
+        write_count(&mut bytes, handlers.len() / 8, "exception_table")?;
+        bytes.extend(handlers);
+        write_table(&mut bytes, attributes, "attributes")?;
+        Ok(bytes)
+    }
+
+    /// `.attribute name hex`, where the name is a string or a constant pool index.
+    fn hex_attribute(&self, line: &Line) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut operands = line.operands();
+        let name_index = match line.tokens.get(1) {
+            Some(Token::Quoted(name)) => {
+                operands.next();
+                self.utf8_index(line, &String::from_utf16_lossy(name))?
+            }
+            _ => operands.index()?,
+        };
+        let body = match operands.next() {
+            Some(Token::Word(hex)) => {
+                parse_hex(hex).ok_or_else(|| line.error(&format!("{hex} is not valid hex")))?
+            }
+            None => vec![],
+            _ => return Err(line.error("expected the attribute in hex")),
+        };
+        operands.end()?;
+        attribute_bytes(line, name_index, body)
+    }
+
+    fn named_attribute(
+        &self,
+        line: &Line,
+        name: &str,
+        body: Vec<u8>,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        attribute_bytes(line, self.utf8_index(line, name)?, body)
+    }
+
+    fn utf8_index(&self, line: &Line, name: &str) -> Result<u16, Box<dyn Error>> {
+        self.utf8_indices.get(name).copied().ok_or_else(|| {
+            Box::new(WritingError::new(
+                WritingCause::MissingConstant(name.into()),
+                &format!(
+                    "line {}: attribute names must be in the constant pool",
+                    line.number
+                ),
+            )) as Box<dyn Error>
+        })
+    }
+
+    fn peek_keyword(&self) -> Option<&'a str> {
+        self.lines.get(self.position).map(Line::keyword)
+    }
+
+    /// Takes the next line, leaving it in place for later passes over a block.
+    fn next_line(&mut self) -> Result<&'a Line, Box<dyn Error>> {
+        let number = self.lines.last().map_or(0, |line| line.number);
+        let line = self
+            .lines
+            .get(self.position)
+            .ok_or_else(|| syntax(number, "unexpected end of input"))?;
+        self.position += 1;
+        Ok(line)
+    }
+
+    fn expect(&mut self, keyword: &str) -> Result<&'a Line, Box<dyn Error>> {
+        let line = self.next_line()?;
+        if line.keyword() != keyword {
+            return Err(line.error(&format!("expected {keyword}")));
+        }
+        Ok(line)
+    }
+}
+
+/// Appends an instruction at `pc`; without labels it only needs to be the right size.
+fn encode(
+    line: &Line,
+    pc: u32,
+    labels: Option<&HashMap<String, u32>>,
+    code: &mut Vec<u8>,
+) -> Result<(), Box<dyn Error>> {
+    let mnemonic = line.keyword();
+    // javap's names for wide loads, stores and iinc, e.g. iload_w.
+    let (opcode, wide) = match from_mnemonic(mnemonic) {
+        Some(opcode) => (opcode, false),
+        None => match mnemonic.strip_suffix("_w").and_then(from_mnemonic) {
+            Some(opcode @ (ILOAD..=ALOAD | ISTORE..=ASTORE | IINC | RET)) => (opcode, true),
+            _ => return Err(line.error(&format!("unknown instruction {mnemonic}"))),
+        },
+    };
+    let mut operands = line.operands();
+    // Offsets are left at 0 until the labels are known.
+    let offset = |target: u32| match labels {
+        Some(_) => target as i64 - pc as i64,
+        None => 0,
+    };
+    if wide {
+        code.push(WIDE);
+    }
+    code.push(opcode);
+    match opcode {
+        BIPUSH => code.extend(operands.number::<i8>("byte")?.to_be_bytes()),
+        SIPUSH => code.extend(operands.number::<i16>("short")?.to_be_bytes()),
+        LDC => {
+            let index = operands.index()?;
+            let index = u8::try_from(index)
+                .map_err(|_| line.error("ldc can only load constants #1 to #255"))?;
+            code.push(index);
+        }
+        ILOAD..=ALOAD | ISTORE..=ASTORE | RET if wide => code.extend(
+            operands
+                .number::<u16>("local variable index")?
+                .to_be_bytes(),
+        ),
+        ILOAD..=ALOAD | ISTORE..=ASTORE | RET => {
+            code.push(operands.number::<u8>("local variable index")?)
+        }
+        IINC if wide => {
+            code.extend(
+                operands
+                    .number::<u16>("local variable index")?
+                    .to_be_bytes(),
+            );
+            code.extend(operands.number::<i16>("increment")?.to_be_bytes());
+        }
+        IINC => {
+            code.push(operands.number::<u8>("local variable index")?);
+            code.extend(operands.number::<i8>("increment")?.to_be_bytes());
+        }
+        IFEQ..=JSR | IFNULL | IFNONNULL => {
+            let offset = offset(operands.label(labels)?);
+            let offset = i16::try_from(offset)
+                .map_err(|_| line.error("branch is too far for a 16-bit offset"))?;
+            code.extend(offset.to_be_bytes());
+        }
+        GOTO_W | JSR_W => code.extend((offset(operands.label(labels)?) as i32).to_be_bytes()),
+        TABLESWITCH | LOOKUPSWITCH => {
+            // Padding aligns the operands to a multiple of four from the start of the code.
+            code.resize(code.len() + (3 - pc % 4) as usize, 0);
+            let mut entries = vec![];
+            let low: i32 = if opcode == TABLESWITCH {
+                operands.number("low")?
+            } else {
+                0
+            };
+            while operands.peek() != Some("default") {
+                if opcode == LOOKUPSWITCH {
+                    entries.extend(operands.number::<i32>("key")?.to_be_bytes());
+                }
+                entries.extend((offset(operands.label(labels)?) as i32).to_be_bytes());
+            }
+            operands.next();
+            code.extend((offset(operands.label(labels)?) as i32).to_be_bytes());
+            if opcode == TABLESWITCH {
+                let high = low as i64 + entries.len() as i64 / 4 - 1;
+                let high = i32::try_from(high)
+                    .map_err(|_| line.error("tableswitch has more targets than keys"))?;
+                code.extend(low.to_be_bytes());
+                code.extend(high.to_be_bytes());
+            } else {
+                code.extend((entries.len() as i32 / 8).to_be_bytes());
+            }
+            code.extend(entries);
+        }
+        LDC_W | LDC2_W | GETSTATIC..=INVOKESTATIC | NEW | ANEWARRAY | CHECKCAST | INSTANCEOF => {
+            code.extend(operands.index()?.to_be_bytes())
+        }
+        INVOKEINTERFACE => {
+            code.extend(operands.index()?.to_be_bytes());
+            code.extend([operands.number("count")?, 0]);
+        }
+        INVOKEDYNAMIC => {
+            code.extend(operands.index()?.to_be_bytes());
+            code.extend([0, 0]);
+        }
+        NEWARRAY => {
+            let atype = match (4..=11).find(|atype| get_array_type_name(*atype) == operands.peek())
+            {
+                Some(atype) => {
+                    operands.next();
+                    atype
+                }
+                None => operands.number("array type")?,
+            };
+            code.push(atype);
+        }
+        MULTIANEWARRAY => {
+            code.extend(operands.index()?.to_be_bytes());
+            code.push(operands.number("dimensions")?);
+        }
+        WIDE..=u8::MAX => {
+            return Err(line.error(&format!("{mnemonic} cannot be written on its own")))
+        }
+        _ => {}
+    }
+    operands.end()
+}
+
+/// Access flags named as `get_string` names them, up to the first operand that is not one.
+fn flags<T>(
+    operands: &mut Args,
+    all: &[T],
+    flag: impl Fn(&T) -> (String, u16),
+) -> Result<u16, Box<dyn Error>> {
+    let mut mask = 0;
+    while let Some(word) = operands.peek().filter(|word| !word.starts_with('#')) {
+        let (_, value) = all
+            .iter()
+            .map(&flag)
+            .find(|(name, _)| name == word)
+            .ok_or_else(|| operands.line.error(&format!("unknown access flag {word}")))?;
+        mask |= value;
+        operands.next();
+    }
+    Ok(mask)
+}
+
+/// A float as a decimal, or as its raw bits after `0x` for the NaNs a decimal cannot keep.
+fn float_bits(operands: &mut Args) -> Result<u32, Box<dyn Error>> {
+    let word = operands.word("float")?;
+    match word.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => word.parse::<f32>().ok().map(f32::to_bits),
+    }
+    .ok_or_else(|| operands.line.error(&format!("{word} is not a valid float")))
+}
+
+fn double_bits(operands: &mut Args) -> Result<u64, Box<dyn Error>> {
+    let word = operands.word("double")?;
+    match word.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => word.parse::<f64>().ok().map(f64::to_bits),
+    }
+    .ok_or_else(|| {
+        operands
+            .line
+            .error(&format!("{word} is not a valid double"))
+    })
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn expect_end(line: &Line, block: &str) -> Result<(), Box<dyn Error>> {
+    match line.tokens.get(1) {
+        Some(Token::Word(word)) if word == block && line.tokens.len() == 2 => Ok(()),
+        _ => Err(line.error(&format!("expected .end {block}"))),
+    }
+}
+
+fn attribute_bytes(line: &Line, name_index: u16, body: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let length =
+        u32::try_from(body.len()).map_err(|_| line.error("attribute is longer than 4GiB"))?;
+    let mut bytes = name_index.to_be_bytes().to_vec();
+    bytes.extend(length.to_be_bytes());
+    bytes.extend(body);
+    Ok(bytes)
+}
+
+fn write_count(bytes: &mut Vec<u8>, count: usize, table: &str) -> Result<(), Box<dyn Error>> {
+    let count = u16::try_from(count).map_err(|_| {
+        WritingError::new(
+            WritingCause::TooLarge(table.into()),
+            &format!("{count} entries do not fit in the class file"),
+        )
+    })?;
+    bytes.extend(count.to_be_bytes());
+    Ok(())
+}
+
+/// A count followed by its entries.
+fn write_table(
+    bytes: &mut Vec<u8>,
+    entries: Vec<Vec<u8>>,
+    table: &str,
+) -> Result<(), Box<dyn Error>> {
+    write_count(bytes, entries.len(), table)?;
+    bytes.extend(entries.concat());
+    Ok(())
+}
+
+fn syntax(line: usize, msg: &str) -> Box<dyn Error> {
+    Box::new(WritingError::new(WritingCause::Syntax(line), msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access_flags::{ClassAccessFlags, MethodAccessFlags};
+    use crate::class_builder::tests::class;
+    use crate::class_builder::{CodeBuilder, Constant, Insn, MethodBuilder};
+
+    const HELLO: &str = r#"
+.version 49 0
+.constant_pool
+    #1 = Class #2
+    #2 = Utf8 "Hello"
+    #3 = Class #4
+    #4 = Utf8 "java/lang/Object"
+    #5 = Utf8 "count"
+    #6 = Utf8 "(I)I"
+    #7 = Utf8 "Code"
+    #8 = Long 10000000000
+    #10 = Utf8 "LineNumberTable"
+.end constant_pool
+.class public super #1 ; Hello
+.super #3
+.method public static #5 #6
+    .code 4 1
+    L0:
+        iload_0
+        tableswitch 1 L1 L1 default L2 ; 1 and 2
+    L1:
+        ldc2_w #8
+        pop2
+    L2:
+        iinc_w 0 1000
+        iload_0
+        ireturn
+        .line_numbers
+            L0 3
+            L2 4
+        .end line_numbers
+    .end code
+.end method
+"#;
+
+    fn code(class: &ClassFile) -> &Code {
+        match &class.methods[0].attributes[0] {
+            AttributeInfo::Code(code) => code,
+            _ => panic!("expected Code"),
+        }
+    }
+
+    #[test]
+    fn test_assemble() {
+        let class = ClassFile::from_bytes(&assemble(HELLO).unwrap()).unwrap();
+        assert_eq!(class.get_name(), "Hello");
+        assert_eq!(class.constant_pool_count, 11);
+        let instructions = code(&class).get_instructions().unwrap();
+        let mnemonics: Vec<String> = instructions.iter().map(|i| i.get_mnemonic()).collect();
+        assert_eq!(
+            mnemonics,
+            [
+                "iload_0",
+                "tableswitch",
+                "ldc2_w",
+                "pop2",
+                "iinc_w",
+                "iload_0",
+                "ireturn"
+            ]
+        );
+        assert_eq!(
+            instructions[1].operands,
+            Operands::TableSwitch {
+                default: 28,
+                low: 1,
+                high: 2,
+                targets: vec![24, 24],
+            }
+        );
+        assert_eq!(
+            instructions[4].operands,
+            Operands::Increment {
+                index: 0,
+                value: 1000
+            }
+        );
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let mut class = class(
+            &[ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper],
+            "Escapes",
+            "java/lang/Object",
+            &[],
+        );
+        class.set_source_file("Escapes.java").unwrap();
+        let mut code = CodeBuilder::new(2, 1);
+        let (start, end, handler) = (code.new_label(), code.new_label(), code.new_label());
+        code.push(Insn::Label(start));
+        code.add_line_number(start, 1);
+        code.push(Insn::Ldc(Constant::String(
+            "tab\t\"quote\" \u{1F600} \0".into(),
+        )));
+        code.push(Insn::Ldc(Constant::Float(f32::NAN)));
+        code.push(Insn::Simple(POP2));
+        code.push(Insn::Label(end));
+        code.push(Insn::Simple(RETURN));
+        code.push(Insn::Label(handler));
+        code.push(Insn::Simple(ATHROW));
+        code.add_exception_handler(start, end, handler, None);
+        let mut method = MethodBuilder::new(&[MethodAccessFlags::AccStatic], "escapes", "()V");
+        method.set_code(code);
+        method.add_exception("java/lang/Exception");
+        class.add_method(method).unwrap();
+        let class = class.build().unwrap();
+
+        let text = disassemble(&class).unwrap();
+        assert!(
+            text.contains(r#"Utf8 "tab\t\"quote\" 😀 \u0000""#),
+            "{text}"
+        );
+        assert!(text.contains(".catch L0 L5 L6 any"), "{text}");
+        assert_eq!(assemble(&text).unwrap(), class.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_assemble_malformed_classes() {
+        // A branch to the end of the code assembles, but is not a valid target.
+        let text = HELLO.replace("tableswitch 1 L1 L1 default L2 ; 1 and 2", "goto L3");
+        let text = text.replace("ireturn\n", "ireturn\n    L3:\n");
+        let class = ClassFile::from_bytes(&assemble(&text).unwrap()).unwrap();
+        assert!(code(&class).get_instructions().is_err());
+    }
+
+    #[test]
+    fn test_report_errors_by_line() {
+        let text = HELLO.replace("tableswitch 1 L1 L1 default L2", "iflt L9");
+        let error = assemble(&text).unwrap_err().to_string();
+        assert!(error.contains("line 20") && error.contains("L9"), "{error}");
+        let error = assemble(&HELLO.replace("#10 = Utf8 \"LineNumberTable\"", "#10 = Utf8 \"X\""))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("MissingConstant: LineNumberTable"),
+            "{error}"
+        );
+    }
+}
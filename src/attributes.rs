@@ -1,7 +1,10 @@
 #![allow(dead_code)]
+#![allow(clippy::doc_lazy_continuation)]
 
-use core::num;
-use std::{error::Error, io::Cursor};
+use std::{
+    error::Error,
+    io::{Cursor, Read},
+};
 
 use byteorder::{ReadBytesExt, BE};
 
@@ -45,16 +48,16 @@ pub struct ExceptionTable {
      *  instance initialization method, or static initializer (the size of any code array) to 65534
      *  bytes.
      */
-    start_pc: u16,
+    pub(crate) start_pc: u16,
     /// **end_pc**
-    end_pc: u16,
+    pub(crate) end_pc: u16,
     /**
      * **handler_pc**\
      *  The value of the handler_pc item indicates the start of the exception
      *  handler. The value of the item must be a valid index into the code array
      *  and must be the index of the opcode of an instruction.
      */
-    handler_pc: u16,
+    pub(crate) handler_pc: u16,
     /**
      * **catch_type**\
      *  If the value of the catch_type item is nonzero, it must be a valid index
@@ -70,7 +73,7 @@ pub struct ExceptionTable {
      *
      *  This is used to implement finally (§3.13).
      */
-    catch_type: u16,
+    pub(crate) catch_type: u16,
 }
 
 impl ExceptionTable {
@@ -261,6 +264,111 @@ impl Code {
     }
 }
 
+impl Code {
+    /// The source line of the instruction at `pc`, from this Code's LineNumberTable attributes.
+    pub fn line_for_pc(&self, pc: u16) -> Option<u16> {
+        self.get_line_numbers()
+            .filter(|line| line.start_pc <= pc)
+            .max_by_key(|line| line.start_pc)
+            .map(|line| line.line_number)
+    }
+
+    /// The code array offsets at which code for `line` begins, in ascending order.
+    pub fn pcs_for_line(&self, line: u16) -> Vec<u16> {
+        let mut pcs: Vec<u16> = self
+            .get_line_numbers()
+            .filter(|entry| entry.line_number == line)
+            .map(|entry| entry.start_pc)
+            .collect();
+        pcs.sort_unstable();
+        pcs.dedup();
+        pcs
+    }
+
+    /// The local variables that have a value at `pc`, ordered by their local variable index.
+    pub fn locals_at(&self, pc: u16, constant_pool: &[ConstantPool]) -> Vec<LocalVariableInfo> {
+        let mut locals = vec![];
+        for attribute in &self.attributes {
+            if let AttributeInfo::LocalVariableTable(table) = attribute {
+                for local in &table.local_variable_table {
+                    if !covers(local.start_pc, local.length, pc) {
+                        continue;
+                    }
+                    locals.push(LocalVariableInfo {
+                        name: crate::constants::get_utf8(constant_pool, local.name_index)
+                            .unwrap_or_default(),
+                        descriptor: crate::constants::get_raw_utf8(
+                            constant_pool,
+                            local.descriptor_index,
+                        )
+                        .unwrap_or_default(),
+                        signature: self.get_local_signature(local, constant_pool),
+                        index: local.index,
+                        start_pc: local.start_pc,
+                        length: local.length,
+                    });
+                }
+            }
+        }
+        locals.sort_by_key(|local| local.index);
+        locals
+    }
+
+    fn get_line_numbers(&self) -> impl Iterator<Item = &LineNumber> {
+        self.attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                AttributeInfo::LineNumberTable(table) => Some(table.line_number_table.iter()),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    /// The LocalVariableTypeTable entry describing the same variable as `local`, if any.
+    fn get_local_signature(
+        &self,
+        local: &LocalVariable,
+        constant_pool: &[ConstantPool],
+    ) -> Option<String> {
+        self.attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                AttributeInfo::LocalVariableTypeTable(table) => {
+                    Some(table.local_variable_type_table.iter())
+                }
+                _ => None,
+            })
+            .flatten()
+            .find(|local_type| {
+                local_type.index == local.index
+                    && local_type.start_pc == local.start_pc
+                    && local_type.length == local.length
+            })
+            .and_then(|local_type| {
+                crate::constants::get_raw_utf8(constant_pool, local_type.signature_index)
+            })
+    }
+}
+
+/// A local variable that is live at some point in the code array, resolved against the
+/// constant pool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalVariableInfo {
+    pub name: String,
+    /// The field descriptor of the variable, e.g. `[Ljava/lang/String;`.
+    pub descriptor: String,
+    /// The field signature from the LocalVariableTypeTable, if the variable has a generic type.
+    pub signature: Option<String>,
+    /// The index into the local variable array of the current frame.
+    pub index: u16,
+    pub start_pc: u16,
+    pub length: u16,
+}
+
+fn covers(start_pc: u16, length: u16, pc: u16) -> bool {
+    pc >= start_pc && (pc as u32) < start_pc as u32 + length as u32
+}
+
 /// [VerificationTypeInfo](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#page=129)
 /**
  * The Long_variable_info and Double_variable_info items indicate the
@@ -273,8 +381,8 @@ impl Code {
  *  - The next location closer to the top of the operand stack has the verification
  * type top.
  */
-#[derive(Clone, Debug)]
-enum VerificationTypeInfo {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationTypeInfo {
     TopVariable,
     IntegerVariable,
     FloatVariable,
@@ -310,7 +418,7 @@ enum VerificationTypeInfo {
 }
 
 impl VerificationTypeInfo {
-    fn get_tag(&self) -> u8 {
+    pub(crate) fn get_tag(&self) -> u8 {
         match self {
             VerificationTypeInfo::TopVariable => 0,
             VerificationTypeInfo::IntegerVariable => 1,
@@ -354,7 +462,7 @@ impl VerificationTypeInfo {
 
 /// [StackMapFrame](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#page=131)
 #[derive(Clone, Debug)]
-enum StackMapFrame {
+pub enum StackMapFrame {
     /**
      * **stack_frame**\
      *  The frame type same_frame is represented by tags in the range \[0-63\]. This frame
@@ -515,7 +623,7 @@ enum StackMapFrame {
 #[derive(Clone, Debug)]
 /// [StackMapTable](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A1597%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C142%2Cnull%5D)
 pub struct StackMapTable {
-    attribute_name_index: u16,
+    pub(crate) attribute_name_index: u16,
     attribute_length: u32,
     /**
      * **number_of_entries**\
@@ -529,7 +637,7 @@ pub struct StackMapTable {
      *  Each entry in the entries table describes one stack map frame of the method.
      *  The order of the [stack map frames](StackMapFrame) in the entries table is significant.
      */
-    entries: Vec<StackMapFrame>,
+    pub(crate) entries: Vec<StackMapFrame>,
 }
 
 impl StackMapTable {
@@ -546,17 +654,11 @@ impl StackMapTable {
             number_of_entries: entry_count,
             entries: {
                 let mut entries = Vec::with_capacity(entry_count as usize);
-                // What am I even doing with this?
-                let mut offset_delta: u16 = 0;
                 for _ in 0..entry_count {
                     let _type = cursor.read_u8()?;
                     entries.push(match _type {
-                        0..=63 => {
-                            offset_delta = _type as u16;
-                            StackMapFrame::SameFrame { frame_type: _type }
-                        }
+                        0..=63 => StackMapFrame::SameFrame { frame_type: _type },
                         64..=127 => {
-                            offset_delta = _type as u16 - 64;
                             let ver_tag = cursor.read_u8()?;
                             let data = if ver_tag == 7 || ver_tag == 8 {
                                 Some(cursor.read_u16::<BE>()?)
@@ -659,7 +761,46 @@ impl StackMapTable {
         }
         Ok(stackmap)
     }
-    fn implicit(index: u16) -> StackMapTable {
+
+    /// A StackMapTable holding `entries`, with its length worked out from them.
+    pub(crate) fn from_entries(
+        attribute_name_index: u16,
+        entries: Vec<StackMapFrame>,
+    ) -> StackMapTable {
+        let types_length = |types: &[VerificationTypeInfo]| -> u32 {
+            types
+                .iter()
+                .map(|info| match info {
+                    VerificationTypeInfo::ObjectVariable(_)
+                    | VerificationTypeInfo::UnintializedVariable(_) => 3,
+                    _ => 1,
+                })
+                .sum()
+        };
+        let attribute_length = 2 + entries
+            .iter()
+            .map(|frame| match frame {
+                StackMapFrame::SameFrame { .. } | StackMapFrame::Unused { .. } => 1,
+                StackMapFrame::SameLocals1StackItemFrame { stack, .. } => 1 + types_length(stack),
+                StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => {
+                    3 + types_length(stack)
+                }
+                StackMapFrame::ChopFrame { .. } | StackMapFrame::SameFrameExtended { .. } => 3,
+                StackMapFrame::AppendFrame { locals, .. } => 3 + types_length(locals),
+                StackMapFrame::FullFrame { locals, stack, .. } => {
+                    7 + types_length(locals) + types_length(stack)
+                }
+            })
+            .sum::<u32>();
+        StackMapTable {
+            attribute_name_index,
+            attribute_length,
+            number_of_entries: entries.len() as u16,
+            entries,
+        }
+    }
+
+    pub(crate) fn implicit(index: u16) -> StackMapTable {
         StackMapTable {
             attribute_name_index: index,
             attribute_length: 2,
@@ -672,14 +813,14 @@ impl StackMapTable {
 #[derive(Clone, Debug)]
 /// [Exceptions](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A865%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C590%2Cnull%5D)
 pub struct Exceptions {
-    attribute_name_index: u16,
-    attribute_length: u32,
+    pub(crate) attribute_name_index: u16,
+    pub(crate) attribute_length: u32,
     /**
      * *number_of_exceptions*\
      *  The value of the number_of_exceptions item indicates the number of entries
      *  in the exception_index_table.
      */
-    number_of_exceptions: u16,
+    pub(crate) number_of_exceptions: u16,
     /**
      * *exception_index_table*\
      *  Each value in the exception_index_table array must be a valid index into
@@ -693,7 +834,7 @@ pub struct Exceptions {
      *  - The exception is an instance of one of the exception classes specified in the exception_index_table just described, or one of their subclasses.
      *  These requirements are not enforced in the Java Virtual Machine; they are enforced only at compile time.
      */
-    exception_index_table: Vec<u16>,
+    pub(crate) exception_index_table: Vec<u16>,
 }
 
 impl Exceptions {
@@ -712,7 +853,7 @@ impl Exceptions {
                 let mut exceptions = Vec::with_capacity(exception_count as usize);
                 for _ in 0..exceptions.capacity() {
                     let index = cursor.read_u16::<BE>()?;
-                    if let ConstantPool::Class(class) = &constant_pool[index as usize] {
+                    if let ConstantPool::Class(_) = &constant_pool[index as usize] {
                         // if let ConstantPool::Utf8(name) = &constant_pool[class.1 as usize] {
                         //     match name.get_string() {
                         //         ""
@@ -739,7 +880,7 @@ pub struct InnerClassInfo {
      *  the constant_pool table. The constant_pool entry at that index must be
      *  a CONSTANT_Class_info structure representing C.
      */
-    inner_class_info_index: u16,
+    pub(crate) inner_class_info_index: u16,
     /**
      * *outer_class_info_index*\
      *  If C is not a member of a class or an interface - that is, if C is a top-level
@@ -752,7 +893,7 @@ pub struct InnerClassInfo {
      *  which C is a member. The value of the outer_class_info_index item
      *  must not equal the the value of the inner_class_info_index item.
      */
-    outer_class_info_index: u16,
+    pub(crate) outer_class_info_index: u16,
     /**
      * *inner_name_index*\
      *  If C is anonymous (JLS §15.9.5), the value of the inner_name_index item
@@ -762,7 +903,7 @@ pub struct InnerClassInfo {
      *  CONSTANT_Utf8_info structure that represents the original simple name of
      *  C, as given in the source code from which this class file was compiled.
      */
-    inner_name_index: u16,
+    pub(crate) inner_name_index: u16,
     /**
      * *inner_class_access_flags*\
      *  The value of the inner_class_access_flags item is a mask of flags used
@@ -771,7 +912,7 @@ pub struct InnerClassInfo {
      *  used by a compiler to recover the original information when source code
      *  is not available. The flags are specified in Table 4.7.6-A.
      */
-    inner_class_access_flags: u16,
+    pub(crate) inner_class_access_flags: u16,
 }
 
 impl InnerClassInfo {
@@ -789,14 +930,18 @@ impl InnerClassInfo {
                 inner_info
             );
         }
-        if let ConstantPool::Class(_) = &constant_pool[outer_info as usize] {
+        // Zero is allowed for both indexes below: top-level, local and anonymous classes
+        // have no outer class, and anonymous classes have no name.
+        if outer_info == 0 {
+        } else if let ConstantPool::Class(_) = &constant_pool[outer_info as usize] {
         } else {
             unreachable!(
                 "outer_class_info_index {} did not reference a class object",
                 inner_info
             );
         }
-        if let ConstantPool::Utf8(_) = &constant_pool[inner_name as usize] {
+        if inner_name == 0 {
+        } else if let ConstantPool::Utf8(_) = &constant_pool[inner_name as usize] {
         } else {
             unreachable!(
                 "inner_name_index {} did not reference a utf8 object",
@@ -829,7 +974,7 @@ pub struct InnerClasses {
      *  represents a class or interface C that is not a package member must have exactly
      *  one corresponding entry in the classes array.
      */
-    classes: Vec<InnerClassInfo>,
+    pub(crate) classes: Vec<InnerClassInfo>,
 }
 
 impl InnerClasses {
@@ -871,7 +1016,7 @@ pub struct EnclosingMethod {
      *  CONSTANT_Class_info structure (§4.4.1) representing the innermost class that
      *  encloses the declaration of the current class.
      */
-    class_index: u16,
+    pub(crate) class_index: u16,
     /**
      * *method_index*\
      *  If the current class is not immediately enclosed by a method or constructor,
@@ -889,7 +1034,7 @@ pub struct EnclosingMethod {
      *  method_index is indeed the closest lexically enclosing method of the class that contains
      *  this EnclosingMethod attribute.
      */
-    method_index: u16,
+    pub(crate) method_index: u16,
 }
 
 impl EnclosingMethod {
@@ -941,7 +1086,7 @@ pub struct Signature {
      *  signature if this Signature attribute is an attribute of a method_info structure;
      *  or a field signature otherwise.
      */
-    signature_index: u16,
+    pub(crate) signature_index: u16,
 }
 
 impl Signature {
@@ -956,6 +1101,10 @@ impl Signature {
             signature_index,
         })
     }
+
+    pub fn get_signature(&self, constant_pool: &[ConstantPool]) -> String {
+        crate::constants::get_utf8(constant_pool, self.signature_index).unwrap_or_default()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -1004,7 +1153,7 @@ pub struct SourceDebugExtension {
      *  Note that the debug_extension array may denote a string longer than that which can be
      *  represented with an instance of class String.
      */
-    debug_extension: String,
+    pub(crate) debug_extension: String,
 }
 
 impl SourceDebugExtension {
@@ -1023,25 +1172,37 @@ impl SourceDebugExtension {
             debug_extension: String::from_utf8(characters)?,
         })
     }
+
+    pub fn get_debug_extension(&self) -> &str {
+        &self.debug_extension
+    }
+
+    /// Parses the debug extension as a JSR-45 SMAP, `Ok(None)` if it holds something else.
+    pub fn get_smap(&self) -> Result<Option<crate::smap::Smap>, Box<dyn Error>> {
+        if !self.debug_extension.starts_with("SMAP") {
+            return Ok(None);
+        }
+        Ok(Some(crate::smap::Smap::parse(&self.debug_extension)?))
+    }
 }
 
 #[derive(Clone, Debug)]
-struct LineNumber {
-    start_pc: u16,
-    line_number: u16,
+pub struct LineNumber {
+    pub(crate) start_pc: u16,
+    pub(crate) line_number: u16,
 }
 
 impl LineNumber {
     pub fn new(start_pc: u16, line_number: u16) -> LineNumber {
         LineNumber {
-            /**
+            /*
              * **start_pc**\
              *  The value of the start_pc item must be a valid index into the code array
              *  of this Code attribute. The item indicates the index into the code array at
              *  which the code for a new line in the original source file begins.
              */
             start_pc,
-            /**
+            /*
              * **line_number**\
              *  The value of the line_number item gives the corresponding line number
              *  in the original source file.
@@ -1054,20 +1215,20 @@ impl LineNumber {
 #[derive(Clone, Debug)]
 /// [LineNumberTable](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A991%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C590%2Cnull%5D)
 pub struct LineNumberTable {
-    attribute_name_index: u16,
-    attribute_length: u32,
+    pub(crate) attribute_name_index: u16,
+    pub(crate) attribute_length: u32,
     /**
      * **line_number_table_length**\
      *  The value of the line_number_table_length item indicates the number of
      *  entries in the line_number_table array.
      */
-    line_number_table_length: u16,
+    pub(crate) line_number_table_length: u16,
     /**
      * **line_number_table**\
      *  Each entry in the [line_number_table](LineNumberTableContents) array indicates that the line number
      *  in the original source file changes at a given point in the code array.
      */
-    line_number_table: Vec<LineNumber>,
+    pub(crate) line_number_table: Vec<LineNumber>,
 }
 
 impl LineNumberTable {
@@ -1096,7 +1257,7 @@ impl LineNumberTable {
 }
 
 #[derive(Clone, Debug)]
-struct LocalVariable {
+pub struct LocalVariable {
     /**
      * *start_pc*\
      *  The value of the start_pc item must be a valid index into the code array
@@ -1109,8 +1270,8 @@ struct LocalVariable {
      *  + length), that is, between start_pc inclusive and start_pc + length
      *  exclusive.
      */
-    start_pc: u16,
-    length: u16,
+    pub(crate) start_pc: u16,
+    pub(crate) length: u16,
     /**
      * *name_index*\
      *  The value of the name_index item must be a valid index into the
@@ -1118,7 +1279,7 @@ struct LocalVariable {
      *  a CONSTANT_Utf8_info structure representing a valid unqualified name
      *  denoting a local variable (§4.2.2).
      */
-    name_index: u16,
+    pub(crate) name_index: u16,
     /**
      * *descriptor_index*\
      *  The value of the descriptor_index item must be a valid index into the
@@ -1126,7 +1287,7 @@ struct LocalVariable {
      *  a CONSTANT_Utf8_info structure representing a field descriptor which
      *  encodes the type of a local variable in the source program (§4.3.2).
      */
-    descriptor_index: u16,
+    pub(crate) descriptor_index: u16,
     /**
      * *index*\
      *  The value of the index item must be a valid index into the local variable
@@ -1135,7 +1296,7 @@ struct LocalVariable {
      *  If the given local variable is of type double or long, it occupies both index
      *  and index + 1.
      */
-    index: u16,
+    pub(crate) index: u16,
 }
 
 impl LocalVariable {
@@ -1174,7 +1335,7 @@ pub struct LocalVariableTable {
      *  the local variable array of the current frame at which that local variable can be
      *  found.
      */
-    local_variable_table: Vec<LocalVariable>,
+    pub(crate) local_variable_table: Vec<LocalVariable>,
 }
 
 impl LocalVariableTable {
@@ -1204,7 +1365,7 @@ impl LocalVariableTable {
 }
 
 #[derive(Clone, Debug)]
-struct LocalVariableType {
+pub struct LocalVariableType {
     /**
      * *start_pc*\
      *  The value of the start_pc item must be a valid index into the code array
@@ -1217,8 +1378,8 @@ struct LocalVariableType {
      *  + length), that is, between start_pc inclusive and start_pc + length
      *  exclusive.
      */
-    start_pc: u16,
-    length: u16,
+    pub(crate) start_pc: u16,
+    pub(crate) length: u16,
     /**
      * *name_index*\
      *  The value of the name_index item must be a valid index into the
@@ -1226,7 +1387,7 @@ struct LocalVariableType {
      *  a CONSTANT_Utf8_info structure representing a valid unqualified name
      *  denoting a local variable (§4.2.2).
      */
-    name_index: u16,
+    pub(crate) name_index: u16,
     /**
      * *signature_index*\
      *  The value of the signature_index item must be a valid index into the
@@ -1234,7 +1395,7 @@ struct LocalVariableType {
      *  a CONSTANT_Utf8_info structure representing a field signature which
      *  encodes the type of a local variable in the source program (§4.7.9.1).
      */
-    signature_index: u16,
+    pub(crate) signature_index: u16,
     /**
      * *index*\
      *  The value of the index item must be a valid index into the local variable
@@ -1243,7 +1404,7 @@ struct LocalVariableType {
      *  If the given local variable is of type double or long, it occupies both index
      *  and index + 1.
      */
-    index: u16,
+    pub(crate) index: u16,
 }
 
 impl LocalVariableType {
@@ -1282,7 +1443,7 @@ pub struct LocalVariableTypeTable {
      *  into the local variable array of the current frame at which that local variable
      *  can be found.
      */
-    local_variable_type_table: Vec<LocalVariableType>,
+    pub(crate) local_variable_type_table: Vec<LocalVariableType>,
 }
 
 impl LocalVariableTypeTable {
@@ -1332,7 +1493,7 @@ impl Deprecated {
 
 #[derive(Clone, Debug)]
 /// [ElementValueStructure](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A1041%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C367%2Cnull%5D)
-enum Element {
+pub enum Element {
     /**
      * *const_value_index*\
      *  The const_value_index item denotes a constant of either a primitive type or
@@ -1453,7 +1614,7 @@ impl Element {
 }
 
 #[derive(Clone, Debug)]
-struct ElementPairs {
+pub struct ElementPairs {
     /**
      * *element_name_index*\
      *  The value of the element_name_index item must be a valid index into
@@ -1464,13 +1625,13 @@ struct ElementPairs {
      *  In other words, the entry denotes an element of the annotation interface specified
      *  by type_index.
      */
-    element_name_index: u16,
+    pub(crate) element_name_index: u16,
     /**
      * *value*\
      *  The value of the value item represents the value of the element-value
      *  pair represented by this element_value_pairs entry.
      */
-    value: Element,
+    pub(crate) value: Element,
 }
 
 impl ElementPairs {
@@ -1486,7 +1647,7 @@ impl ElementPairs {
 }
 
 #[derive(Clone, Debug)]
-struct Annotation {
+pub struct Annotation {
     /**
      * *type_index*\
      *  The value of the type_index item must be a valid index into the
@@ -1495,20 +1656,20 @@ struct Annotation {
      *  (§4.3.2). The field descriptor denotes the type of the annotation represented
      *  by this annotation structure.
      */
-    type_index: u16,
+    pub(crate) type_index: u16,
     /**
      * *num_element_value_pairs*\
      *  The value of the num_element_value_pairs item gives the number of
      *  element-value pairs of the annotation represented by this annotation
      *  structure.
      */
-    num_element_value_pairs: u16,
+    pub(crate) num_element_value_pairs: u16,
     /**
      * *element_value_pairs*\
      *  Each value of the element_value_pairs table represents a single element-
      *  value pair in the annotation represented by this annotation structure.
      */
-    element_value_pairs: Vec<ElementPairs>,
+    pub(crate) element_value_pairs: Vec<ElementPairs>,
 }
 
 impl Annotation {
@@ -1527,6 +1688,22 @@ impl Annotation {
             element_value_pairs,
         })
     }
+
+    /// The field descriptor of the annotation interface, e.g. `Ljava/lang/Deprecated;`.
+    pub fn get_type(&self, constant_pool: &[ConstantPool]) -> String {
+        crate::constants::get_raw_utf8(constant_pool, self.type_index).unwrap_or_default()
+    }
+
+    /// The value of the element named `name`, if this annotation gives it one.
+    pub fn get_element(&self, name: &str, constant_pool: &[ConstantPool]) -> Option<&Element> {
+        self.element_value_pairs
+            .iter()
+            .find(|pair| {
+                crate::constants::get_utf8(constant_pool, pair.element_name_index).as_deref()
+                    == Some(name)
+            })
+            .map(|pair| &pair.value)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -1545,7 +1722,7 @@ pub struct RuntimeVisibleAnnotations {
      *  Each entry in the annotations table represents a single run-time visible
      *  annotation on a declaration.
      */
-    annotations: Vec<Annotation>,
+    pub(crate) annotations: Vec<Annotation>,
 }
 
 impl RuntimeVisibleAnnotations {
@@ -1588,7 +1765,7 @@ pub struct RuntimeInvisibleAnnotations {
      *  Each entry in the annotations table represents a single run-time visible
      *  annotation on a declaration.
      */
-    annotations: Vec<Annotation>,
+    pub(crate) annotations: Vec<Annotation>,
 }
 
 impl RuntimeInvisibleAnnotations {
@@ -1616,7 +1793,7 @@ impl RuntimeInvisibleAnnotations {
 }
 
 #[derive(Clone, Debug)]
-struct ParamAnnotation {
+pub struct ParamAnnotation {
     /**
      * *num_annotations*\
      *  The value of the num_annotations item indicates the number of run-
@@ -1631,7 +1808,7 @@ struct ParamAnnotation {
      *  parameter_annotations entry. The annotation structure is specified in
      *  §4.7.16.
      */
-    annotations: Vec<Annotation>,
+    pub(crate) annotations: Vec<Annotation>,
 }
 
 impl ParamAnnotation {
@@ -1672,7 +1849,7 @@ pub struct RuntimeVisibleParameterAnnotations {
      *  Each entry in the parameter_annotations table represents all of the run-
      *  time visible annotations on the declaration of a single formal parameter.
      */
-    parameter_annotations: Vec<ParamAnnotation>,
+    pub(crate) parameter_annotations: Vec<ParamAnnotation>,
 }
 
 impl RuntimeVisibleParameterAnnotations {
@@ -1713,7 +1890,7 @@ pub struct RuntimeInvisibleParameterAnnotations {
      *  Each entry in the parameter_annotations table represents all of the run-
      *  time visible annotations on the declaration of a single formal parameter.
      */
-    parameter_annotations: Vec<ParamAnnotation>,
+    pub(crate) parameter_annotations: Vec<ParamAnnotation>,
 }
 
 impl RuntimeInvisibleParameterAnnotations {
@@ -1743,15 +1920,15 @@ impl RuntimeInvisibleParameterAnnotations {
 *  variable has a value. It also indicates the index into the local variable array of
 *  the current frame at which that local variable can be found.
 */
-struct LocalVarTargetTable {
+pub struct LocalVarTargetTable {
     /**
      * *start_pc & length*\
      *  The given local variable has a value at indices into the code array in
      *  the interval [start_pc, start_pc + length), that is, between start_pc
      *  inclusive and start_pc + length exclusive.
      */
-    start_pc: u16,
-    length: u16,
+    pub(crate) start_pc: u16,
+    pub(crate) length: u16,
     /**
      * *index*\
      *  The given local variable must be at index in the local variable array of the
@@ -1769,7 +1946,7 @@ struct LocalVarTargetTable {
      *  does not record the type itself. The type may be found by inspecting the appropriate
      *  LocalVariableTable attribute.
      */
-    index: u16,
+    pub(crate) index: u16,
 }
 
 impl LocalVarTargetTable {
@@ -1790,7 +1967,7 @@ impl LocalVarTargetTable {
  *  type in a declaration or expression is annotated. The first item specifies not which
  *  type, but rather which declaration of a type parameter is annotated.
  */
-enum TargetInfo {
+pub enum TargetInfo {
     /**
      * *type_parameter_target*\
      *  The type_parameter_target item indicates that an annotation appears on the
@@ -2038,9 +2215,9 @@ impl TargetInfo {
 }
 
 #[derive(Clone, Debug)]
-struct PathDescriptor {
+pub struct PathDescriptor {
     /// [type_path_kind](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A1140%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C512%2Cnull%5D)
-    type_path_kind: u8,
+    pub(crate) type_path_kind: u8,
     /**
      * *type_argument_index*\
      *  If the value of the type_path_kind item is 0, 1, or 2, then the value of the
@@ -2051,7 +2228,7 @@ struct PathDescriptor {
      *  parameterized type is annotated, where 0 indicates the first type argument
      *  of a parameterized type.
      */
-    type_argument_index: u8,
+    pub(crate) type_argument_index: u8,
 }
 
 impl PathDescriptor {
@@ -2063,14 +2240,10 @@ impl PathDescriptor {
                 "Received type_path_kind > 3",
             )));
         }
-        let type_argument_index = if (0..3).contains(&type_path_kind) {
-            0
-        } else {
-            cursor.read_u8()?
-        };
+        // Every path entry has a type_argument_index, which is 0 unless type_path_kind is 3.
         Ok(PathDescriptor {
             type_path_kind,
-            type_argument_index,
+            type_argument_index: cursor.read_u8()?,
         })
     }
 }
@@ -2084,7 +2257,7 @@ impl PathDescriptor {
  *  type itself, but if the type is a reference type, then there are additional locations
  *  where an annotation may appear
  */
-struct TypePath {
+pub struct TypePath {
     /**
      * *path_length*\
      *  The value of the path_length item gives the number of entries in the path array:\
@@ -2101,7 +2274,7 @@ struct TypePath {
      *  reached.)
      */
     path_length: u8,
-    path: Vec<PathDescriptor>,
+    pub(crate) path: Vec<PathDescriptor>,
 }
 impl TypePath {
     fn new(cursor: &mut Cursor<&[u8]>) -> Result<TypePath, Box<dyn Error>> {
@@ -2121,13 +2294,13 @@ impl TypePath {
  *  Each entry in the annotations table represents a single run-time visible
  *  annotation on a type used in a declaration or expression.
  */
-struct TypeAnnotation {
-    target_type: u8,
-    target_info: TargetInfo,
-    target_path: TypePath,
-    type_index: u16,
+pub struct TypeAnnotation {
+    pub(crate) target_type: u8,
+    pub(crate) target_info: TargetInfo,
+    pub(crate) target_path: TypePath,
+    pub(crate) type_index: u16,
     num_element_value_pairs: u16,
-    element_value_pairs: Vec<ElementPairs>,
+    pub(crate) element_value_pairs: Vec<ElementPairs>,
 }
 
 impl TypeAnnotation {
@@ -2179,7 +2352,7 @@ pub struct RuntimeVisibleTypeAnnotations {
      *  Each entry in the type_annotations table represents a single run-time visible
      *  annotation on a type used in a declaration or expression.
      */
-    type_annotations: Vec<TypeAnnotation>,
+    pub(crate) type_annotations: Vec<TypeAnnotation>,
 }
 
 impl RuntimeVisibleTypeAnnotations {
@@ -2219,7 +2392,7 @@ pub struct RuntimeInvisibleTypeAnnotations {
      *  Each entry in the type_annotations table represents a single run-time visible
      *  annotation on a type used in a declaration or expression.
      */
-    type_annotations: Vec<TypeAnnotation>,
+    pub(crate) type_annotations: Vec<TypeAnnotation>,
 }
 
 impl RuntimeInvisibleTypeAnnotations {
@@ -2254,7 +2427,7 @@ pub struct AnnotationDefault {
      *  interface element represented by the method_info structure enclosing this
      *  AnnotationDefault attribute.
      */
-    default_value: Element,
+    pub(crate) default_value: Element,
 }
 
 impl AnnotationDefault {
@@ -2272,7 +2445,7 @@ impl AnnotationDefault {
 }
 
 #[derive(Clone, Debug)]
-struct Methods {
+pub struct Methods {
     /**
      * *bootstrap_method_ref*\
      *  The value of the bootstrap_method_ref item must be a valid index into
@@ -2284,20 +2457,20 @@ struct Methods {
      *  handle must be able to accept the array of arguments described in §5.4.3.6, or
      *  resolution will fail.
      */
-    bootstrap_method_ref: u16,
+    pub(crate) bootstrap_method_ref: u16,
     /**
      * *num_bootstrap_arguments*\
      *  The value of the num_bootstrap_arguments item gives the number of
      *  items in the bootstrap_arguments array.
      */
-    num_bootstrap_arguments: u16,
+    pub(crate) num_bootstrap_arguments: u16,
     /**
      * *bootstrap_arguments*\
      *  Each entry in the bootstrap_arguments array must be a valid index into
      *  the constant_pool table. The constant_pool entry at that index must be
      *  loadable (§4.4).
      */
-    bootstrap_arguments: Vec<u16>,
+    pub(crate) bootstrap_arguments: Vec<u16>,
 }
 
 impl Methods {
@@ -2321,14 +2494,14 @@ impl Methods {
 #[derive(Clone, Debug)]
 /// [BootstrapMethods](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A1179%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C590%2Cnull%5D)
 pub struct BootstrapMethods {
-    attribute_name_index: u16,
-    attribute_length: u32,
+    pub(crate) attribute_name_index: u16,
+    pub(crate) attribute_length: u32,
     /**
      * *num_bootstrap_methods*\
      *  The value of the num_bootstrap_methods item determines the number of
      *  bootstrap method specifiers in the bootstrap_methods array.
      */
-    num_bootstrap_methods: u16,
+    pub(crate) num_bootstrap_methods: u16,
     /**
      * *bootstrap_methods*\
      *  Each entry in the bootstrap_methods table contains an index to a
@@ -2336,7 +2509,7 @@ pub struct BootstrapMethods {
      *  and a sequence (perhaps empty) of indexes to static arguments for the bootstrap
      *  method.
      */
-    bootstrap_methods: Vec<Methods>,
+    pub(crate) bootstrap_methods: Vec<Methods>,
 }
 
 impl BootstrapMethods {
@@ -2364,7 +2537,7 @@ impl BootstrapMethods {
 }
 
 #[derive(Clone, Debug)]
-struct Parameters {
+pub struct Parameters {
     /**
      * *name_index*\
      *  The value of the name_index item must either be zero or a valid index into
@@ -2377,8 +2550,8 @@ struct Parameters {
      *  at that index must be a CONSTANT_Utf8_info structure representing a valid
      *  unqualified name denoting a formal parameter (§4.2.2).
      */
-    name_index: u16,
-    access_flags: Vec<ParameterAccessFlags>,
+    pub(crate) name_index: u16,
+    pub(crate) access_flags: Vec<ParameterAccessFlags>,
 }
 
 impl Parameters {
@@ -2420,7 +2593,7 @@ pub struct MethodParameters {
      *  the enclosing method's Signature attribute (if present), or to the i'th annotation in the
      *  enclosing method's parameter annotations.
      */
-    parameters: Vec<Parameters>,
+    pub(crate) parameters: Vec<Parameters>,
 }
 
 impl MethodParameters {
@@ -2445,7 +2618,7 @@ impl MethodParameters {
 }
 
 #[derive(Clone, Debug)]
-struct ModuleRequires {
+pub struct ModuleRequires {
     /**
      * *requires_index*\
      *  The value of the requires_index item must be a valid index into the
@@ -2456,14 +2629,14 @@ struct ModuleRequires {
      *  At most one entry in the requires table may specify a module of a given
      *  name with its requires_index item.
      */
-    requires_index: u16,
+    pub(crate) requires_index: u16,
     /**
      * *requires_flags*\
      *  If the current module is not java.base, and the class file version number
      *  is 54.0 or above, then neither ACC_TRANSITIVE nor ACC_STATIC_PHASE
      *  may be set in requires_flags.
      */
-    requires_flags: Vec<ModuleFlags::RequiresAccessFlags>,
+    pub(crate) requires_flags: Vec<ModuleFlags::RequiresAccessFlags>,
     /**
      * *requires_version_index*\
      *  The value of the requires_version_index item must be either zero or a
@@ -2473,7 +2646,7 @@ struct ModuleRequires {
      *  a CONSTANT_Utf8_info structure representing the version of the module
      *  specified by requires_index.
      */
-    requires_version_index: u16,
+    pub(crate) requires_version_index: u16,
 }
 
 impl ModuleRequires {
@@ -2487,7 +2660,7 @@ impl ModuleRequires {
 }
 
 #[derive(Clone, Debug)]
-struct ModuleExports {
+pub struct ModuleExports {
     /**
      * *exports_index*\
      *  The value of the exports_index item must be a valid index into the
@@ -2498,8 +2671,8 @@ struct ModuleExports {
      *  At most one entry in the exports table may specify a package of a given
      *  name with its exports_index item.
      */
-    exports_index: u16,
-    exports_flags: Vec<ModuleFlags::ExportsAccessFlags>,
+    pub(crate) exports_index: u16,
+    pub(crate) exports_flags: Vec<ModuleFlags::ExportsAccessFlags>,
     /**
      * *exports_to_count*\
      *  The value of the exports_to_count indicates the number of entries in the
@@ -2525,7 +2698,7 @@ struct ModuleExports {
      *  For each entry in the exports table, at most one entry in its
      *  exports_to_index table may specify a module of a given name.
      */
-    exports_to_index: Vec<u16>,
+    pub(crate) exports_to_index: Vec<u16>,
 }
 
 impl ModuleExports {
@@ -2548,7 +2721,7 @@ impl ModuleExports {
 }
 
 #[derive(Clone, Debug)]
-struct ModuleOpens {
+pub struct ModuleOpens {
     /**
      * *opens_index*\
      *  The value of the opens_index item must be a valid index into the
@@ -2559,11 +2732,11 @@ struct ModuleOpens {
      *  At most one entry in the opens table may specify a package of a given
      *  name with its opens_index item.
      */
-    opens_index: u16,
+    pub(crate) opens_index: u16,
     /**
      * *opens_flags*
      */
-    opens_flags: Vec<ModuleFlags::OpensAccessFlags>,
+    pub(crate) opens_flags: Vec<ModuleFlags::OpensAccessFlags>,
     /**
      * *opens_to_count*\
      *  The value of the opens_to_count indicates the number of entries in the
@@ -2589,7 +2762,7 @@ struct ModuleOpens {
      *  For each entry in the opens table, at most one entry in its opens_to_index
      *  table may specify a module of a given name.
      */
-    opens_to_index: Vec<u16>,
+    pub(crate) opens_to_index: Vec<u16>,
 }
 
 impl ModuleOpens {
@@ -2612,7 +2785,7 @@ impl ModuleOpens {
 }
 
 #[derive(Clone, Debug)]
-struct ModuleProvides {
+pub struct ModuleProvides {
     /**
      * *provides_index*\
      *  The value of the provides_index item must be a valid index into the
@@ -2624,7 +2797,7 @@ struct ModuleProvides {
      *  a given name with its provides_index item.
      */
     // FIXME: Anything that is an index into the constant pool should maybe just be a constant pool object
-    provides_index: u16,
+    pub(crate) provides_index: u16,
     /**
      * *provides_with_count*\
      *  The value of the provides_with_count indicates the number of entries in
@@ -2643,7 +2816,7 @@ struct ModuleProvides {
      *  provides_with_index table may specify a service implementation of a
      *  given name.
      */
-    provides_with_index: Vec<u16>,
+    pub(crate) provides_with_index: Vec<u16>,
 }
 
 impl ModuleProvides {
@@ -2674,8 +2847,8 @@ pub struct Module {
      *  constant_pool table. The constant_pool entry at that index must be a
      *  CONSTANT_Module_info structure (§4.4.11) denoting the current module.
      */
-    module_name_index: u16,
-    module_flags: Vec<ModuleFlags::ModuleAccessFlags>,
+    pub(crate) module_name_index: u16,
+    pub(crate) module_flags: Vec<ModuleFlags::ModuleAccessFlags>,
     /**
      * *module_version_index*\
      *  The value of the module_version_index item must be either zero or a valid
@@ -2684,7 +2857,7 @@ pub struct Module {
      *  the item is nonzero, then the constant_pool entry at that index must be a
      *  CONSTANT_Utf8_info structure representing the version of the current module.
      */
-    module_version_index: u16,
+    pub(crate) module_version_index: u16,
 
     /**
      * *requires_count*\
@@ -2703,7 +2876,7 @@ pub struct Module {
      *  table must have both a requires_index item which indicates java.base and
      *  a requires_flags item which has the ACC_SYNTHETIC flag not set.
      */
-    requires: Vec<ModuleRequires>,
+    pub(crate) requires: Vec<ModuleRequires>,
 
     /**
      * *exports_count*\
@@ -2718,7 +2891,7 @@ pub struct Module {
      *  public and protected members, may be accessed from outside the current
      *  module, possibly from a limited set of "friend" modules.
      */
-    exports: Vec<ModuleExports>,
+    pub(crate) exports: Vec<ModuleExports>,
 
     /**
      * *opens_count*\
@@ -2733,7 +2906,7 @@ pub struct Module {
      *  outside the current module via the reflection libraries of the Java SE Platform,
      *  possibly from a limited set of "friend" modules.
      */
-    opens: Vec<ModuleOpens>,
+    pub(crate) opens: Vec<ModuleOpens>,
 
     /**
      * *uses_count*\
@@ -2751,7 +2924,7 @@ pub struct Module {
      *  At most one entry in the uses_index table may specify a service interface of
      *  a given name.
      */
-    uses_index: Vec<u16>,
+    pub(crate) uses_index: Vec<u16>,
 
     /**
      * *provides_count*\
@@ -2764,7 +2937,7 @@ pub struct Module {
      *  Each entry in the provides table represents a service implementation for a
      *  given service interface.
      */
-    provides: Vec<ModuleProvides>,
+    pub(crate) provides: Vec<ModuleProvides>,
 }
 
 impl Module {
@@ -2853,7 +3026,7 @@ pub struct ModulePackages {
      *  At most one entry in the package_index table may specify a package of a
      *  given name.
      */
-    package_index: Vec<u16>,
+    pub(crate) package_index: Vec<u16>,
 }
 
 impl ModulePackages {
@@ -2889,7 +3062,7 @@ pub struct ModuleMainClass {
      *  CONSTANT_Class_info structure (§4.4.1) representing the main class of the
      *  current module.
      */
-    main_class_index: u16,
+    pub(crate) main_class_index: u16,
 }
 
 impl ModuleMainClass {
@@ -2922,7 +3095,7 @@ pub struct NestHost {
      *  or interface, or does not authorize nest membership for the current class or interface, then
      *  an error may occur during access control (§5.4.4).
      */
-    host_class_index: u16,
+    pub(crate) host_class_index: u16,
 }
 
 impl NestHost {
@@ -2962,7 +3135,7 @@ pub struct NestMembers {
      *  attributes which reference the current class or interface. Array items that do not meet these
      *  criteria are ignored by access control.
      */
-    classes: Vec<u16>,
+    pub(crate) classes: Vec<u16>,
 }
 
 impl NestMembers {
@@ -2987,7 +3160,7 @@ impl NestMembers {
 }
 
 #[derive(Clone, Debug)]
-struct RecordComponentInfo {
+pub struct RecordComponentInfo {
     /**
      * *name_index*\
      *  The value of the name_index item must be a valid index into the
@@ -2995,7 +3168,7 @@ struct RecordComponentInfo {
      *  CONSTANT_Utf8_info structure (§4.4.7) representing a valid unqualified
      *  name denoting the record component (§4.2.2).
      */
-    name_index: u16,
+    pub(crate) name_index: u16,
     /**
      * *descriptor_index*\
      *  The value of the descriptor_index item must be a valid index into the
@@ -3003,13 +3176,13 @@ struct RecordComponentInfo {
      *  a CONSTANT_Utf8_info structure (§4.4.7) representing a field descriptor
      *  which encodes the type of the record component (§4.3.2).
      */
-    descriptor_index: u16,
+    pub(crate) descriptor_index: u16,
     /**
      * *attributes_count*\
      *  The value of the attributes_count item indicates the number of
      *  additional attributes of this record component.
      */
-    attributes_count: u16,
+    pub(crate) attributes_count: u16,
     /**
      * *attributes*\
      *  Each value of the attributes table must be an attribute_info structure
@@ -3023,7 +3196,7 @@ struct RecordComponentInfo {
      *  The rules concerning non-predefined attributes in the attributes table of
      *  a record_component_info structure are given in §4.7.1.
      */
-    attributes: Vec<crate::class_file::AttributeInfo>,
+    pub(crate) attributes: Vec<crate::class_file::AttributeInfo>,
 }
 
 impl RecordComponentInfo {
@@ -3065,7 +3238,7 @@ pub struct Record {
      *  Each entry in the components table specifies a record component of the
      *  current class, in the order the record components were declared.
      */
-    components: Vec<RecordComponentInfo>,
+    pub(crate) components: Vec<RecordComponentInfo>,
 }
 
 impl Record {
@@ -3116,7 +3289,7 @@ pub struct PermittedSubclasses {
      *  classes or interfaces which do not attempt to directly extend or implement the current class
      *  or interface are ignored.
      */
-    classes: Vec<u16>,
+    pub(crate) classes: Vec<u16>,
 }
 
 impl PermittedSubclasses {
@@ -3140,10 +3313,305 @@ impl PermittedSubclasses {
     }
 }
 
+/// [Scala Signature](https://github.com/scala/scala/blob/2.13.x/src/reflect/scala/reflect/internal/pickling/PickleFormat.scala)
+/**
+ * Marks a class whose Scala 2 signature is pickled into this class file.\
+ *  Since Scala 2.8 the attribute only holds the pickle version followed by an empty
+ *  entry count, and the pickle itself is in a `scala.reflect.ScalaSignature` or
+ *  `scala.reflect.ScalaLongSignature` annotation. Older compilers put the whole pickle here.
+ */
+#[derive(Clone, Debug)]
+pub struct ScalaSig {
+    attribute_name_index: u16,
+    attribute_length: u32,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl ScalaSig {
+    pub fn new(
+        attribute_name_index: u16,
+        attribute_length: u32,
+        cursor: &mut Cursor<&[u8]>,
+    ) -> Result<ScalaSig, Box<dyn Error>> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(attribute_length as usize);
+        for _ in 0..attribute_length {
+            bytes.push(cursor.read_u8()?);
+        }
+        Ok(ScalaSig {
+            attribute_name_index,
+            attribute_length,
+            bytes,
+        })
+    }
+
+    /// The major and minor version of the pickle format.
+    pub fn get_version(&self) -> (u8, u8) {
+        (
+            *self.bytes.first().unwrap_or(&0),
+            *self.bytes.get(1).unwrap_or(&0),
+        )
+    }
+
+    /// Whether the pickle is in this attribute rather than in a `ScalaSignature` annotation.
+    pub fn has_pickle(&self) -> bool {
+        self.bytes.len() > 3
+    }
+}
+
+/**
+ * Marks a class compiled by scalac that has no pickle of its own, such as the module
+ * class `test$` of an object whose signature is pickled into the mirror class `test`.
+ */
+#[derive(Clone, Debug)]
+pub struct Scala {
+    attribute_name_index: u16,
+    attribute_length: u32,
+}
+
+impl Scala {
+    pub fn new(
+        attribute_name_index: u16,
+        attribute_length: u32,
+        cursor: &mut Cursor<&[u8]>,
+    ) -> Result<Scala, Box<dyn Error>> {
+        cursor.set_position(cursor.position() + attribute_length as u64);
+        Ok(Scala {
+            attribute_name_index,
+            attribute_length,
+        })
+    }
+}
+
+/// Inlining information the Scala 2.12+ optimizer records for a method.
+#[derive(Clone, Debug)]
+pub struct ScalaMethodInlineInfo {
+    pub(crate) name_index: u16,
+    pub(crate) descriptor_index: u16,
+    /**
+     * *flags*\
+     *  Bit 0 is set when the method is effectively final, bit 2 when it is annotated
+     *  `@inline` and bit 3 when it is annotated `@noinline`.
+     */
+    pub(crate) flags: u8,
+}
+
+impl ScalaMethodInlineInfo {
+    pub fn is_effectively_final(&self) -> bool {
+        self.flags & 0x1 != 0
+    }
+
+    pub fn is_annotated_inline(&self) -> bool {
+        self.flags & 0x4 != 0
+    }
+
+    pub fn is_annotated_no_inline(&self) -> bool {
+        self.flags & 0x8 != 0
+    }
+}
+
+/// [ScalaInlineInfo](https://github.com/scala/scala/blob/2.13.x/src/compiler/scala/tools/nsc/backend/jvm/opt/InlineInfoAttribute.scala)
+#[derive(Clone, Debug)]
+pub struct ScalaInlineInfo {
+    attribute_name_index: u16,
+    attribute_length: u32,
+    pub(crate) version: u8,
+    /**
+     * *flags*\
+     *  Bit 0 is set when the class is effectively final, bit 1 when a self type follows
+     *  (only written by 2.12 milestones) and bit 2 when the class is a SAM type.
+     */
+    pub(crate) flags: u8,
+    /// The constant pool index of the self type, if bit 1 is set.
+    pub(crate) self_type: Option<u16>,
+    /// The name and descriptor index of the single abstract method, if bit 2 is set.
+    pub(crate) sam: Option<(u16, u16)>,
+    pub(crate) methods: Vec<ScalaMethodInlineInfo>,
+}
+
+impl ScalaInlineInfo {
+    pub fn new(
+        attribute_name_index: u16,
+        attribute_length: u32,
+        cursor: &mut Cursor<&[u8]>,
+    ) -> Result<ScalaInlineInfo, Box<dyn Error>> {
+        let end = cursor.position() + attribute_length as u64;
+        let version = cursor.read_u8()?;
+        let flags = cursor.read_u8()?;
+        let self_type = if flags & 0x2 != 0 {
+            Some(cursor.read_u16::<BE>()?)
+        } else {
+            None
+        };
+        let sam = if flags & 0x4 != 0 {
+            Some((cursor.read_u16::<BE>()?, cursor.read_u16::<BE>()?))
+        } else {
+            None
+        };
+        let count = cursor.read_u16::<BE>()?;
+        let mut methods = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            methods.push(ScalaMethodInlineInfo {
+                name_index: cursor.read_u16::<BE>()?,
+                descriptor_index: cursor.read_u16::<BE>()?,
+                flags: cursor.read_u8()?,
+            });
+        }
+        cursor.set_position(end);
+        Ok(ScalaInlineInfo {
+            attribute_name_index,
+            attribute_length,
+            version,
+            flags,
+            self_type,
+            sam,
+            methods,
+        })
+    }
+
+    pub fn is_effectively_final(&self) -> bool {
+        self.flags & 0x1 != 0
+    }
+
+    /// The name and descriptor of the single abstract method, e.g. `apply(I)I`.
+    pub fn get_sam(&self, constant_pool: &[ConstantPool]) -> Option<String> {
+        let (name, descriptor) = self.sam?;
+        Some(format!(
+            "{}{}",
+            crate::constants::get_utf8(constant_pool, name)?,
+            crate::constants::get_utf8(constant_pool, descriptor)?
+        ))
+    }
+}
+
+/// [TASTy](https://docs.scala-lang.org/scala3/guides/tasty-overview.html)
+/**
+ * Written by Scala 3 on classes whose typed trees are in a `.tasty` file next to the class
+ * file. The attribute holds the UUID of that file so the two can be matched up.
+ */
+#[derive(Clone, Debug)]
+pub struct Tasty {
+    attribute_name_index: u16,
+    attribute_length: u32,
+    pub(crate) uuid_high: u64,
+    pub(crate) uuid_low: u64,
+}
+
+impl Tasty {
+    pub fn new(
+        attribute_name_index: u16,
+        attribute_length: u32,
+        cursor: &mut Cursor<&[u8]>,
+    ) -> Result<Tasty, Box<dyn Error>> {
+        let end = cursor.position() + attribute_length as u64;
+        let uuid_high = cursor.read_u64::<BE>()?;
+        let uuid_low = cursor.read_u64::<BE>()?;
+        cursor.set_position(end);
+        Ok(Tasty {
+            attribute_name_index,
+            attribute_length,
+            uuid_high,
+            uuid_low,
+        })
+    }
+
+    /// The UUID in its canonical form, e.g. `123e4567-e89b-12d3-a456-426614174000`.
+    pub fn get_uuid(&self) -> String {
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            self.uuid_high >> 32,
+            (self.uuid_high >> 16) & 0xFFFF,
+            self.uuid_high & 0xFFFF,
+            self.uuid_low >> 48,
+            self.uuid_low & 0xFFFF_FFFF_FFFF
+        )
+    }
+}
+
+/// An attribute this crate does not parse, kept as it was read so it can be written back.
 #[derive(Clone, Debug)]
 pub struct Unknown {
     attribute_name_index: u16,
     attribute_length: u32,
+    pub(crate) name: String,
+    pub(crate) info: Vec<u8>,
+}
+
+impl Unknown {
+    pub fn new(
+        attribute_name_index: u16,
+        attribute_length: u32,
+        name: String,
+        cursor: &mut Cursor<&[u8]>,
+    ) -> Result<Unknown, Box<dyn Error>> {
+        let mut info = vec![0; attribute_length as usize];
+        cursor.read_exact(&mut info)?;
+        Ok(Unknown {
+            attribute_name_index,
+            attribute_length,
+            name,
+            info,
+        })
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl crate::class_file::AttributeInfo {
+    /// The attribute_name_index, which is private to each attribute's structure.
+    pub(crate) fn name_index_mut(&mut self) -> &mut u16 {
+        use crate::class_file::AttributeInfo;
+        match self {
+            AttributeInfo::ConstantValue(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Code(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::StackMapTable(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Exceptions(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::InnerClasses(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::EnclosingMethod(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Synthetic(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Signature(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::SourceFile(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::SourceDebugExtension(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::LineNumberTable(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::LocalVariableTable(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::LocalVariableTypeTable(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Deprecated(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::RuntimeVisibleAnnotations(attribute) => {
+                &mut attribute.attribute_name_index
+            }
+            AttributeInfo::RuntimeInvisibleAnnotations(attribute) => {
+                &mut attribute.attribute_name_index
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations(attribute) => {
+                &mut attribute.attribute_name_index
+            }
+            AttributeInfo::RuntimeInvisibleParameterAnnotations(attribute) => {
+                &mut attribute.attribute_name_index
+            }
+            AttributeInfo::RuntimeVisibleTypeAnnotations(attribute) => {
+                &mut attribute.attribute_name_index
+            }
+            AttributeInfo::RuntimeInvisibleTypeAnnotations(attribute) => {
+                &mut attribute.attribute_name_index
+            }
+            AttributeInfo::AnnotationDefault(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::BootstrapMethods(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::MethodParameters(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Module(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::ModulePackages(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::ModuleMainClass(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::NestHost(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::NestMembers(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Record(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::PermittedSubclasses(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::ScalaSig(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Scala(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::ScalaInlineInfo(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Tasty(attribute) => &mut attribute.attribute_name_index,
+            AttributeInfo::Unknown(attribute) => &mut attribute.attribute_name_index,
+        }
+    }
 }
 
 pub(crate) fn read_attributes(
@@ -3331,10 +3799,18 @@ pub(crate) fn read_attributes(
                 "PermittedSubclasses" => AttributeInfo::PermittedSubclasses(
                     PermittedSubclasses::new(name_index, length, cursor.read_u16::<BE>()?, cursor)?,
                 ),
-                _ => {
-                    cursor.set_position(cursor.position() + length as u64);
-                    AttributeInfo::Unknown(n.get_string())
-                }
+                "ScalaSig" => AttributeInfo::ScalaSig(ScalaSig::new(name_index, length, cursor)?),
+                "Scala" => AttributeInfo::Scala(Scala::new(name_index, length, cursor)?),
+                "ScalaInlineInfo" => AttributeInfo::ScalaInlineInfo(ScalaInlineInfo::new(
+                    name_index, length, cursor,
+                )?),
+                "TASTY" => AttributeInfo::Tasty(Tasty::new(name_index, length, cursor)?),
+                _ => AttributeInfo::Unknown(Unknown::new(
+                    name_index,
+                    length,
+                    n.get_raw_string(),
+                    cursor,
+                )?),
             };
             attributes.push(attribute);
         } else {
@@ -3347,3 +3823,106 @@ pub(crate) fn read_attributes(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::Utf8;
+
+    fn debug_code() -> (Code, Vec<ConstantPool>) {
+        let constant_pool = vec![
+            ConstantPool::Unknown,
+            ConstantPool::Utf8(Utf8::from("args")),
+            ConstantPool::Utf8(Utf8::from("[Ljava/lang/String;")),
+            ConstantPool::Utf8(Utf8::from("list")),
+            ConstantPool::Utf8(Utf8::from("Ljava/util/List;")),
+            ConstantPool::Utf8(Utf8::from("Ljava/util/List<Ljava/lang/String;>;")),
+        ];
+        let line_numbers = LineNumberTable {
+            attribute_name_index: 0,
+            attribute_length: 14,
+            line_number_table_length: 3,
+            line_number_table: vec![
+                LineNumber::new(0, 10),
+                LineNumber::new(8, 11),
+                LineNumber::new(12, 10),
+            ],
+        };
+        let locals = LocalVariableTable {
+            attribute_name_index: 0,
+            attribute_length: 22,
+            local_variable_table_length: 2,
+            local_variable_table: vec![
+                LocalVariable::new(0, 16, 1, 2, 0).unwrap(),
+                LocalVariable::new(8, 8, 3, 4, 1).unwrap(),
+            ],
+        };
+        let local_types = LocalVariableTypeTable {
+            attribute_name_index: 0,
+            attribute_length: 12,
+            local_variable_type_table_length: 1,
+            local_variable_type_table: vec![LocalVariableType::new(8, 8, 3, 5, 1).unwrap()],
+        };
+        let code = Code {
+            attribute_name_index: 0,
+            attribute_length: 0,
+            max_stack: 1,
+            max_locals: 2,
+            code_length: 16,
+            code: vec![0; 16],
+            exception_table_length: 0,
+            exception_tables: vec![],
+            attributes_count: 3,
+            attributes: vec![
+                AttributeInfo::LineNumberTable(line_numbers),
+                AttributeInfo::LocalVariableTable(locals),
+                AttributeInfo::LocalVariableTypeTable(local_types),
+            ],
+        };
+        (code, constant_pool)
+    }
+
+    #[test]
+    fn test_line_queries() {
+        let (code, _) = debug_code();
+        assert_eq!(code.line_for_pc(0), Some(10));
+        assert_eq!(code.line_for_pc(9), Some(11));
+        assert_eq!(code.line_for_pc(15), Some(10));
+        assert_eq!(code.pcs_for_line(10), vec![0, 12]);
+        assert!(code.pcs_for_line(42).is_empty());
+    }
+
+    #[test]
+    fn test_locals_at() {
+        let (code, constant_pool) = debug_code();
+        let locals = code.locals_at(4, &constant_pool);
+        assert_eq!(locals.len(), 1);
+        assert_eq!(locals[0].name, "args");
+        let locals = code.locals_at(8, &constant_pool);
+        assert_eq!(locals.len(), 2);
+        assert_eq!(locals[1].name, "list");
+        assert_eq!(locals[1].descriptor, "Ljava/util/List;");
+        assert_eq!(
+            locals[1].signature.as_deref(),
+            Some("Ljava/util/List<Ljava/lang/String;>;")
+        );
+        assert!(code.locals_at(16, &constant_pool).is_empty());
+    }
+
+    #[test]
+    fn test_type_path() {
+        // An array step, then the second type argument, then a trailing byte.
+        let bytes: &[u8] = &[2, 0, 0, 3, 1, 0xFF];
+        let mut cursor = Cursor::new(bytes);
+        let type_path = TypePath::new(&mut cursor).unwrap();
+        let path: Vec<(u8, u8)> = type_path
+            .path
+            .iter()
+            .map(|entry| (entry.type_path_kind, entry.type_argument_index))
+            .collect();
+        assert_eq!(path, vec![(0, 0), (3, 1)]);
+        assert_eq!(cursor.position(), 5);
+        let bytes: &[u8] = &[1, 4, 0];
+        assert!(TypePath::new(&mut Cursor::new(bytes)).is_err());
+    }
+}
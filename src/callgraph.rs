@@ -0,0 +1,879 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Write};
+use std::rc::Rc;
+
+use crate::access_flags::{ClassAccessFlags, MethodAccessFlags};
+use crate::attributes::Code;
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool, MethodInfo};
+use crate::class_path::ClassPath;
+use crate::constants::get_raw_utf8;
+use crate::graphs::{Edge, Graph, Node};
+use crate::hierarchy::HierarchyIndex;
+use crate::instructions::*;
+
+const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+
+/// How the targets of virtual and interface calls are found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Analysis {
+    /// Class hierarchy analysis: any class below the receiver's type may be the receiver.
+    ClassHierarchy,
+    /**
+     * Rapid type analysis: only the classes that reachable code instantiates may be, which
+     * is more precise but misses objects created by reflection or outside the classes
+     * analysed.
+     */
+    RapidType,
+}
+
+impl Analysis {
+    pub fn parse(analysis: &str) -> Option<Analysis> {
+        match analysis {
+            "cha" => Some(Analysis::ClassHierarchy),
+            "rta" => Some(Analysis::RapidType),
+            _ => None,
+        }
+    }
+}
+
+/// A method by its class, name and descriptor, written like `java/lang/System.exit(I)V`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MethodRef {
+    /// The internal name of the class.
+    pub owner: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+impl MethodRef {
+    pub fn new(owner: &str, name: &str, descriptor: &str) -> MethodRef {
+        MethodRef {
+            owner: owner.into(),
+            name: name.into(),
+            descriptor: descriptor.into(),
+        }
+    }
+
+    /// Parses a method as it is written, also taking a dotted class name.
+    pub fn parse(method: &str) -> Option<MethodRef> {
+        let (member, parameters) = method.split_at(method.find('(')?);
+        let (owner, name) = member.rsplit_once('.')?;
+        if owner.is_empty() || name.is_empty() || !parameters.contains(')') {
+            return None;
+        }
+        Some(MethodRef::new(&owner.replace('.', "/"), name, parameters))
+    }
+}
+
+impl Display for MethodRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}{}", self.owner, self.name, self.descriptor)
+    }
+}
+
+/// How one method comes to run another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CallKind {
+    /// invokestatic.
+    Static,
+    /// invokespecial, for constructors, private methods and `super` calls.
+    Special,
+    /// invokevirtual, to one of the methods the receiver may select.
+    Virtual,
+    /// invokeinterface, to one of the methods the receiver may select.
+    Interface,
+    /// invokedynamic, to the method a lambda or method reference is implemented by, or to
+    /// the bootstrap method of any other call site.
+    Dynamic,
+    /// The static initializer a class runs when an instruction first uses it.
+    Initialize,
+}
+
+impl CallKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            CallKind::Static => "static",
+            CallKind::Special => "special",
+            CallKind::Virtual => "virtual",
+            CallKind::Interface => "interface",
+            CallKind::Dynamic => "dynamic",
+            CallKind::Initialize => "initialize",
+        }
+    }
+}
+
+/// A call from the instruction at `pc` in one method to a method it may run.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Call {
+    pub caller: MethodRef,
+    pub pc: u32,
+    pub callee: MethodRef,
+    pub kind: CallKind,
+}
+
+/**
+ * The methods that can run once some entry points are called, and the calls between them.\
+ *  The code of the classes analysed is followed from the entry points. The methods of other
+ *  classes, such as those of the JDK, are reached but not followed, and neither are calls
+ *  back into the classes analysed from them, such as a `toString` called by a library.
+ */
+pub struct CallGraph {
+    pub(crate) entry_points: Vec<MethodRef>,
+    /// Every method reached, including those that were not followed.
+    pub(crate) methods: BTreeSet<MethodRef>,
+    /// The methods whose code was followed.
+    pub(crate) followed: BTreeSet<MethodRef>,
+    pub(crate) calls: BTreeSet<Call>,
+}
+
+impl CallGraph {
+    /**
+     * Builds the call graph of `classes` from `entry_points`. Classes outside of `classes`,
+     * such as supertypes from the JDK, are looked up on `class_path`.
+     */
+    pub fn new(
+        classes: &[ClassFile],
+        class_path: &ClassPath,
+        analysis: Analysis,
+        entry_points: &[MethodRef],
+    ) -> Result<CallGraph, Box<dyn Error>> {
+        let mut builder = Builder {
+            class_path,
+            classes: classes
+                .iter()
+                .map(|class| (get_this_name(class), Some(Rc::new(class.clone()))))
+                .collect(),
+            analysed: classes.iter().map(get_this_name).collect(),
+            hierarchy: HierarchyIndex::new(classes),
+            instantiated: BTreeSet::new(),
+            sites: vec![],
+            queue: VecDeque::new(),
+            graph: CallGraph {
+                entry_points: entry_points.to_vec(),
+                methods: BTreeSet::new(),
+                followed: BTreeSet::new(),
+                calls: BTreeSet::new(),
+            },
+        };
+        if analysis == Analysis::ClassHierarchy {
+            builder.instantiated = classes
+                .iter()
+                .filter(|class| is_concrete(class))
+                .map(get_this_name)
+                .collect();
+        }
+        for entry_point in entry_points {
+            builder.reach(entry_point.clone());
+            builder.initialize(None, 0, &entry_point.owner)?;
+        }
+        while let Some(method) = builder.queue.pop_front() {
+            builder.follow(&method)?;
+        }
+        Ok(builder.graph)
+    }
+
+    /// The methods that can run, sorted.
+    pub fn get_methods(&self) -> &BTreeSet<MethodRef> {
+        &self.methods
+    }
+
+    /**
+     * Each method that can run followed by the calls it makes, as `pc: kind method`. The
+     * methods that were not followed are marked `(external)`.
+     */
+    pub fn to_text(&self) -> String {
+        let mut calls: BTreeMap<&MethodRef, Vec<&Call>> = BTreeMap::new();
+        for call in &self.calls {
+            calls.entry(&call.caller).or_default().push(call);
+        }
+        let mut text = String::new();
+        for method in &self.methods {
+            let _ = match self.followed.contains(method) {
+                true => writeln!(text, "{method}"),
+                false => writeln!(text, "{method} (external)"),
+            };
+            for call in calls.get(method).into_iter().flatten() {
+                let kind = call.kind.get_name();
+                let _ = writeln!(text, "  {}: {kind} {}", call.pc, call.callee);
+            }
+        }
+        text
+    }
+
+    /**
+     * The call graph with a `method` node for each method followed, an `external` one for
+     * each method that was not, and an edge of each kind of call between two methods.
+     */
+    pub fn to_graph(&self, name: &str) -> Graph {
+        let nodes = self
+            .methods
+            .iter()
+            .map(|method| Node {
+                id: method.to_string(),
+                label: method.to_string(),
+                kind: match self.followed.contains(method) {
+                    true => "method",
+                    false => "external",
+                },
+                lines: match self.entry_points.contains(method) {
+                    true => vec!["entry point".into()],
+                    false => vec![],
+                },
+            })
+            .collect();
+        let edges: BTreeSet<_> = self
+            .calls
+            .iter()
+            .map(|call| (&call.caller, &call.callee, call.kind))
+            .collect();
+        Graph {
+            name: name.into(),
+            nodes,
+            edges: edges
+                .into_iter()
+                .map(|(caller, callee, kind)| Edge {
+                    from: caller.to_string(),
+                    to: callee.to_string(),
+                    kind: kind.get_name(),
+                    label: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The `public static void main(String[])` methods of `classes`.
+pub fn get_main_methods(classes: &[ClassFile]) -> Vec<MethodRef> {
+    get_methods(classes, |class, method| {
+        let pool = &class.constant_pool;
+        get_raw_utf8(pool, method.name_index).unwrap_or_default() == "main"
+            && get_raw_utf8(pool, method.descriptor_index).unwrap_or_default()
+                == "([Ljava/lang/String;)V"
+            && method.access_flags.contains(&MethodAccessFlags::AccPublic)
+            && method.access_flags.contains(&MethodAccessFlags::AccStatic)
+    })
+}
+
+/// The methods of `classes` annotated with an annotation interface, given by binary name.
+pub fn get_annotated_methods(classes: &[ClassFile], annotation: &str) -> Vec<MethodRef> {
+    let descriptor = format!("L{};", annotation.replace('.', "/"));
+    get_methods(classes, |class, method| {
+        method
+            .attributes
+            .iter()
+            .flat_map(|attribute| match attribute {
+                AttributeInfo::RuntimeVisibleAnnotations(annotations) => {
+                    annotations.annotations.as_slice()
+                }
+                AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+                    annotations.annotations.as_slice()
+                }
+                _ => &[],
+            })
+            .any(|annotation| {
+                get_raw_utf8(&class.constant_pool, annotation.type_index).unwrap_or_default()
+                    == descriptor
+            })
+    })
+}
+
+fn get_methods(
+    classes: &[ClassFile],
+    predicate: impl Fn(&ClassFile, &MethodInfo) -> bool,
+) -> Vec<MethodRef> {
+    let mut methods = vec![];
+    for class in classes {
+        let pool = &class.constant_pool;
+        for method in class
+            .methods
+            .iter()
+            .filter(|method| predicate(class, method))
+        {
+            methods.push(MethodRef::new(
+                &get_this_name(class),
+                &get_raw_utf8(pool, method.name_index).unwrap_or_default(),
+                &get_raw_utf8(pool, method.descriptor_index).unwrap_or_default(),
+            ));
+        }
+    }
+    methods
+}
+
+/// A virtual or interface call, kept so the classes instantiated later can be dispatched on.
+#[derive(Clone)]
+struct Site {
+    caller: MethodRef,
+    pc: u32,
+    method: MethodRef,
+    kind: CallKind,
+}
+
+struct Builder<'a> {
+    class_path: &'a ClassPath,
+    /// Every class looked at so far by internal name, `None` for those that are not found.
+    classes: HashMap<String, Option<Rc<ClassFile>>>,
+    /// The classes whose code is followed.
+    analysed: BTreeSet<String>,
+    /// The hierarchy of every class loaded so far.
+    hierarchy: HierarchyIndex,
+    /// The classes analysed that may be receivers: all concrete ones for class hierarchy
+    /// analysis, and those reachable code instantiates for rapid type analysis.
+    instantiated: BTreeSet<String>,
+    sites: Vec<Site>,
+    /// The methods reached but not yet followed.
+    queue: VecDeque<MethodRef>,
+    graph: CallGraph,
+}
+
+impl Builder<'_> {
+    fn reach(&mut self, method: MethodRef) {
+        if self.graph.methods.insert(method.clone()) {
+            self.queue.push_back(method);
+        }
+    }
+
+    fn add_call(&mut self, caller: &MethodRef, pc: u32, callee: MethodRef, kind: CallKind) {
+        self.graph.calls.insert(Call {
+            caller: caller.clone(),
+            pc,
+            callee: callee.clone(),
+            kind,
+        });
+        self.reach(callee);
+    }
+
+    /// Follows the calls in the code of a method of one of the classes analysed.
+    fn follow(&mut self, method: &MethodRef) -> Result<(), Box<dyn Error>> {
+        if !self.analysed.contains(&method.owner) {
+            return Ok(());
+        }
+        let Some(class) = self.load(&method.owner)? else {
+            return Ok(());
+        };
+        let Some(code) = find_method(&class, &method.name, &method.descriptor).and_then(get_code)
+        else {
+            return Ok(());
+        };
+        self.graph.followed.insert(method.clone());
+        let pool = &class.constant_pool;
+        for instruction in code.get_instructions()? {
+            let pc = instruction.pc;
+            let index = match instruction.operands {
+                Operands::Constant(index)
+                | Operands::InvokeInterface { index, .. }
+                | Operands::InvokeDynamic(index) => index,
+                _ => continue,
+            };
+            match instruction.opcode {
+                INVOKESTATIC | INVOKESPECIAL => {
+                    let Some(target) = get_member(pool, index) else {
+                        continue;
+                    };
+                    let kind = match instruction.opcode {
+                        INVOKESTATIC => {
+                            self.initialize(Some(method), pc, &target.owner)?;
+                            CallKind::Static
+                        }
+                        _ => CallKind::Special,
+                    };
+                    let callee = self.resolve(&target)?;
+                    self.add_call(method, pc, callee, kind);
+                }
+                INVOKEVIRTUAL | INVOKEINTERFACE => {
+                    let Some(target) = get_member(pool, index) else {
+                        continue;
+                    };
+                    let kind = match instruction.opcode {
+                        INVOKEVIRTUAL => CallKind::Virtual,
+                        _ => CallKind::Interface,
+                    };
+                    self.add_site(Site {
+                        caller: method.clone(),
+                        pc,
+                        method: target,
+                        kind,
+                    })?;
+                }
+                INVOKEDYNAMIC => self.add_call_site(&class, method, pc, index)?,
+                NEW => {
+                    let Some(owner) = get_class(pool, index) else {
+                        continue;
+                    };
+                    self.initialize(Some(method), pc, &owner)?;
+                    self.instantiate(&owner)?;
+                }
+                GETSTATIC | PUTSTATIC => {
+                    if let Some(field) = get_member(pool, index) {
+                        self.initialize(Some(method), pc, &field.owner)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Adds a virtual or interface call, to what each class that may be the receiver selects.
+     * A call on a class that is not analysed also reaches the method it resolves to, as the
+     * receiver may be an object of a class that is not.
+     */
+    fn add_site(&mut self, site: Site) -> Result<(), Box<dyn Error>> {
+        if !self.analysed.contains(&site.method.owner) {
+            let callee = self.resolve(&site.method)?;
+            self.add_call(&site.caller, site.pc, callee, site.kind);
+        }
+        for receiver in self.instantiated.clone() {
+            self.dispatch(&site, &receiver)?;
+        }
+        self.sites.push(site);
+        Ok(())
+    }
+
+    /// Adds the call a site makes when `receiver` is the class of the object it is made on.
+    fn dispatch(&mut self, site: &Site, receiver: &str) -> Result<(), Box<dyn Error>> {
+        if self.is_subtype(receiver, &site.method.owner)? {
+            if let Some(callee) = self.select(receiver, &site.method)? {
+                self.add_call(&site.caller, site.pc, callee, site.kind);
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks a class as instantiated, dispatching the calls seen so far on it.
+    fn instantiate(&mut self, class: &str) -> Result<(), Box<dyn Error>> {
+        let is_concrete = match self.load(class)? {
+            Some(class_file) => is_concrete(&class_file),
+            None => false,
+        };
+        if !is_concrete || !self.analysed.contains(class) {
+            return Ok(());
+        }
+        if self.instantiated.insert(class.into()) {
+            for site in self.sites.clone() {
+                self.dispatch(&site, class)?;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Adds the static initializers of a class and its superclasses, which its first use runs,
+     * as far as they are among the classes analysed. The code of a class only runs once the
+     * class and its superclasses are initialized, so its caller needs none of those.
+     */
+    fn initialize(
+        &mut self,
+        caller: Option<&MethodRef>,
+        pc: u32,
+        class: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let initialized = match caller {
+            Some(caller) => {
+                let mut supertypes = self.get_supertypes(&caller.owner)?;
+                supertypes.insert(caller.owner.clone());
+                supertypes
+            }
+            None => BTreeSet::new(),
+        };
+        let mut current = Some(class.to_string());
+        while let Some(name) =
+            current.filter(|name| self.analysed.contains(name) && !initialized.contains(name))
+        {
+            let Some(class_file) = self.load(&name)? else {
+                break;
+            };
+            if find_method(&class_file, "<clinit>", "()V").is_some() {
+                let initializer = MethodRef::new(&name, "<clinit>", "()V");
+                match caller {
+                    Some(caller) => self.add_call(caller, pc, initializer, CallKind::Initialize),
+                    None => self.reach(initializer),
+                }
+            }
+            current = class_file
+                .get_super_name()
+                .map(|name| name.replace('.', "/"));
+        }
+        Ok(())
+    }
+
+    /**
+     * Adds the call an invokedynamic makes: for a lambda or method reference, to the method
+     * that implements it, and otherwise to the bootstrap method of the call site.
+     */
+    fn add_call_site(
+        &mut self,
+        class: &ClassFile,
+        caller: &MethodRef,
+        pc: u32,
+        index: u16,
+    ) -> Result<(), Box<dyn Error>> {
+        let pool = &class.constant_pool;
+        let Some(ConstantPool::InvokeDynamic(call_site)) = pool.get(index as usize) else {
+            return Ok(());
+        };
+        let Some(bootstrap) = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::BootstrapMethods(methods) => methods
+                    .bootstrap_methods
+                    .get(call_site.bootstrap_method_attr_index as usize),
+                _ => None,
+            })
+        else {
+            return Ok(());
+        };
+        let Some((_, bootstrap_method)) = get_method_handle(pool, bootstrap.bootstrap_method_ref)
+        else {
+            return Ok(());
+        };
+        // The metafactories take the implementation method as their second static argument.
+        let implementation = match bootstrap.bootstrap_arguments.get(1) {
+            Some(&argument) if bootstrap_method.owner == LAMBDA_METAFACTORY => {
+                get_method_handle(pool, argument)
+            }
+            _ => None,
+        };
+        match implementation {
+            Some((kind, method)) => {
+                if kind == REF_NEW_INVOKE_SPECIAL {
+                    self.instantiate(&method.owner)?;
+                }
+                let callee = self.resolve(&method)?;
+                self.add_call(caller, pc, callee, CallKind::Dynamic);
+            }
+            None => {
+                let callee = self.resolve(&bootstrap_method)?;
+                self.add_call(caller, pc, callee, CallKind::Dynamic);
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * The method a reference resolves to: the first declared by its class or a superclass, or
+     * else by a superinterface. The reference itself if none is found, such as when its class
+     * is missing.
+     */
+    fn resolve(&mut self, method: &MethodRef) -> Result<MethodRef, Box<dyn Error>> {
+        let mut current = Some(method.owner.clone());
+        while let Some(name) = current {
+            let Some(class) = self.load(&name)? else {
+                break;
+            };
+            if find_method(&class, &method.name, &method.descriptor).is_some() {
+                return Ok(MethodRef::new(&name, &method.name, &method.descriptor));
+            }
+            current = class.get_super_name().map(|name| name.replace('.', "/"));
+        }
+        for interface in self.get_supertypes(&method.owner)? {
+            if let Some(class) = self.load(&interface)? {
+                if find_method(&class, &method.name, &method.descriptor).is_some() {
+                    return Ok(MethodRef::new(&interface, &method.name, &method.descriptor));
+                }
+            }
+        }
+        Ok(method.clone())
+    }
+
+    /**
+     * The method a call selects on an object of class `receiver`: the first that is not
+     * abstract in it or a superclass, or else a default method of a superinterface.
+     */
+    fn select(
+        &mut self,
+        receiver: &str,
+        method: &MethodRef,
+    ) -> Result<Option<MethodRef>, Box<dyn Error>> {
+        let is_selectable = |info: &MethodInfo| {
+            !info.access_flags.contains(&MethodAccessFlags::AccAbstract)
+                && !info.access_flags.contains(&MethodAccessFlags::AccStatic)
+        };
+        let mut current = Some(receiver.to_string());
+        while let Some(name) = current {
+            let Some(class) = self.load(&name)? else {
+                break;
+            };
+            let found = find_method(&class, &method.name, &method.descriptor);
+            if found.is_some_and(is_selectable) {
+                return Ok(Some(MethodRef::new(
+                    &name,
+                    &method.name,
+                    &method.descriptor,
+                )));
+            }
+            current = class.get_super_name().map(|name| name.replace('.', "/"));
+        }
+        for interface in self.get_supertypes(receiver)? {
+            if let Some(class) = self.load(&interface)? {
+                let found = find_method(&class, &method.name, &method.descriptor);
+                if class.access_flags.contains(&ClassAccessFlags::AccInterface)
+                    && found.is_some_and(is_selectable)
+                {
+                    let selected = MethodRef::new(&interface, &method.name, &method.descriptor);
+                    return Ok(Some(selected));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn is_subtype(&mut self, class: &str, supertype: &str) -> Result<bool, Box<dyn Error>> {
+        self.get_supertypes(class)?;
+        Ok(self.hierarchy.is_assignable(class, supertype) == Some(true))
+    }
+
+    /// The superclasses and superinterfaces of a class, as far as they are found.
+    fn get_supertypes(&mut self, class: &str) -> Result<BTreeSet<String>, Box<dyn Error>> {
+        self.load(class)?;
+        // Each class loaded can name supertypes of its own, until none are left to load.
+        loop {
+            let supertypes = self.hierarchy.get_supertypes(class);
+            let unloaded: Vec<String> = supertypes
+                .iter()
+                .filter(|supertype| !self.classes.contains_key(*supertype))
+                .cloned()
+                .collect();
+            if unloaded.is_empty() {
+                return Ok(supertypes.into_iter().collect());
+            }
+            for supertype in unloaded {
+                self.load(&supertype)?;
+            }
+        }
+    }
+
+    fn load(&mut self, class: &str) -> Result<Option<Rc<ClassFile>>, Box<dyn Error>> {
+        if let Some(loaded) = self.classes.get(class) {
+            return Ok(loaded.clone());
+        }
+        let loaded = self.class_path.load_class(class)?.map(Rc::new);
+        if let Some(class_file) = &loaded {
+            self.hierarchy.add(class_file);
+        }
+        self.classes.insert(class.into(), loaded.clone());
+        Ok(loaded)
+    }
+}
+
+fn is_concrete(class: &ClassFile) -> bool {
+    !class.access_flags.contains(&ClassAccessFlags::AccInterface)
+        && !class.access_flags.contains(&ClassAccessFlags::AccAbstract)
+}
+
+fn find_method<'a>(class: &'a ClassFile, name: &str, descriptor: &str) -> Option<&'a MethodInfo> {
+    let pool = &class.constant_pool;
+    class.methods.iter().find(|method| {
+        get_raw_utf8(pool, method.name_index).unwrap_or_default() == name
+            && get_raw_utf8(pool, method.descriptor_index).unwrap_or_default() == descriptor
+    })
+}
+
+fn get_code(method: &MethodInfo) -> Option<&Code> {
+    method
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            AttributeInfo::Code(code) => Some(code),
+            _ => None,
+        })
+}
+
+/// The class, name and descriptor of a field or method reference.
+fn get_member(pool: &[ConstantPool], index: u16) -> Option<MethodRef> {
+    let (class_index, name_and_type_index) = match pool.get(index as usize)? {
+        ConstantPool::Fieldref(field) => (field.class_index, field.name_and_type_index),
+        ConstantPool::Methodref(method) => (method.class_index, method.name_and_type_index),
+        ConstantPool::InterfaceMethodRef(method) => {
+            (method.class_index, method.name_and_type_index)
+        }
+        _ => return None,
+    };
+    let ConstantPool::NameAndType(name_and_type) = pool.get(name_and_type_index as usize)? else {
+        return None;
+    };
+    Some(MethodRef::new(
+        &get_class(pool, class_index)?,
+        &get_raw_utf8(pool, name_and_type.name_index).unwrap_or_default(),
+        &get_raw_utf8(pool, name_and_type.descriptor_index).unwrap_or_default(),
+    ))
+}
+
+/// The kind of a method handle and the method it refers to.
+fn get_method_handle(pool: &[ConstantPool], index: u16) -> Option<(u8, MethodRef)> {
+    match pool.get(index as usize)? {
+        ConstantPool::MethodHandle(handle) => Some((
+            handle.reference_kind,
+            get_member(pool, handle.reference_index)?,
+        )),
+        _ => None,
+    }
+}
+
+/// The internal name of a class entry.
+fn get_class(pool: &[ConstantPool], index: u16) -> Option<String> {
+    match pool.get(index as usize)? {
+        ConstantPool::Class(class) => {
+            Some(get_raw_utf8(pool, class.name_index).unwrap_or_default())
+        }
+        _ => None,
+    }
+}
+
+fn get_this_name(class: &ClassFile) -> String {
+    get_class(&class.constant_pool, class.this_class).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_builder::tests::{class, read_back};
+    use crate::class_builder::{CodeBuilder, Constant, Handle, Insn, MethodBuilder};
+
+    const OBJECT: &str = "java/lang/Object";
+
+    /// A method with `code`, which is given a return at its end.
+    fn method(flags: &[MethodAccessFlags], name: &str, mut code: CodeBuilder) -> MethodBuilder {
+        code.push(Insn::Simple(RETURN));
+        let mut method = MethodBuilder::new(flags, name, "()V");
+        method.set_code(code);
+        method
+    }
+
+    /// A concrete class implementing `test/Shape`, with a constructor and a `draw` method.
+    fn shape(name: &str) -> ClassFile {
+        let mut class = class(&[ClassAccessFlags::AccSuper], name, OBJECT, &["test/Shape"]);
+        let mut code = CodeBuilder::new(1, 1);
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Method {
+            opcode: INVOKESPECIAL,
+            owner: OBJECT.into(),
+            name: "<init>".into(),
+            descriptor: "()V".into(),
+            interface: false,
+        });
+        let public = [MethodAccessFlags::AccPublic];
+        class.add_method(method(&public, "<init>", code)).unwrap();
+        class
+            .add_method(method(&public, "draw", CodeBuilder::new(0, 1)))
+            .unwrap();
+        read_back(class)
+    }
+
+    /// `test/Shape`, `test/Circle` and `test/Square`, and `test/Main` whose static `run` draws a
+    /// new circle and calls a lambda.
+    fn program() -> Vec<ClassFile> {
+        let interface_flags = [
+            ClassAccessFlags::AccInterface,
+            ClassAccessFlags::AccAbstract,
+        ];
+        let mut interface = class(&interface_flags, "test/Shape", OBJECT, &[]);
+        let abstract_flags = [MethodAccessFlags::AccPublic, MethodAccessFlags::AccAbstract];
+        interface
+            .add_method(MethodBuilder::new(&abstract_flags, "draw", "()V"))
+            .unwrap();
+
+        let mut main = class(&[ClassAccessFlags::AccSuper], "test/Main", OBJECT, &[]);
+        let metafactory = Handle {
+            kind: 6,
+            owner: LAMBDA_METAFACTORY.into(),
+            name: "metafactory".into(),
+            descriptor: "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;".into(),
+            interface: false,
+        };
+        let implementation = Handle {
+            kind: 6,
+            owner: "test/Main".into(),
+            name: "lambda$run$0".into(),
+            descriptor: "()V".into(),
+            interface: false,
+        };
+        let bootstrap = main
+            .add_bootstrap_method(
+                &metafactory,
+                &[
+                    Constant::MethodType("()V".into()),
+                    Constant::MethodHandle(implementation),
+                    Constant::MethodType("()V".into()),
+                ],
+            )
+            .unwrap();
+        let mut code = CodeBuilder::new(2, 0);
+        code.push(Insn::Type(NEW, "test/Circle".into()));
+        code.push(Insn::Simple(DUP));
+        code.push(Insn::Method {
+            opcode: INVOKESPECIAL,
+            owner: "test/Circle".into(),
+            name: "<init>".into(),
+            descriptor: "()V".into(),
+            interface: false,
+        });
+        code.push(Insn::Method {
+            opcode: INVOKEINTERFACE,
+            owner: "test/Shape".into(),
+            name: "draw".into(),
+            descriptor: "()V".into(),
+            interface: true,
+        });
+        code.push(Insn::InvokeDynamic {
+            bootstrap,
+            name: "run".into(),
+            descriptor: "()Ljava/lang/Runnable;".into(),
+        });
+        code.push(Insn::Simple(POP));
+        let static_flags = [MethodAccessFlags::AccStatic];
+        main.add_method(method(&static_flags, "run", code)).unwrap();
+        let lambda_flags = [MethodAccessFlags::AccPrivate, MethodAccessFlags::AccStatic];
+        main.add_method(method(
+            &lambda_flags,
+            "lambda$run$0",
+            CodeBuilder::new(0, 0),
+        ))
+        .unwrap();
+
+        vec![
+            read_back(interface),
+            shape("test/Circle"),
+            shape("test/Square"),
+            read_back(main),
+        ]
+    }
+
+    fn reachable(analysis: Analysis) -> Vec<String> {
+        let entry_point = MethodRef::parse("test.Main.run()V").unwrap();
+        let call_graph = CallGraph::new(&program(), &ClassPath::new(), analysis, &[entry_point]);
+        let call_graph = call_graph.unwrap();
+        call_graph
+            .get_methods()
+            .iter()
+            .map(|method| method.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_rapid_type_analysis() {
+        // Only the circle is instantiated, so the interface call dispatches to it alone.
+        assert_eq!(
+            reachable(Analysis::RapidType),
+            [
+                "java/lang/Object.<init>()V",
+                "test/Circle.<init>()V",
+                "test/Circle.draw()V",
+                "test/Main.lambda$run$0()V",
+                "test/Main.run()V",
+            ][..]
+        );
+    }
+
+    #[test]
+    fn test_class_hierarchy_analysis() {
+        // The interface call dispatches to every implementation, instantiated or not.
+        let methods = reachable(Analysis::ClassHierarchy);
+        assert!(methods.contains(&"test/Circle.draw()V".to_string()));
+        assert!(methods.contains(&"test/Square.draw()V".to_string()));
+        assert!(!methods.contains(&"test/Square.<init>()V".to_string()));
+    }
+}
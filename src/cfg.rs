@@ -0,0 +1,599 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::ops::Range;
+
+use crate::attributes::Code;
+use crate::class_file::ConstantPool;
+use crate::constants::get_raw_class_name;
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+use crate::instructions::*;
+
+/// How control reaches the target of an [Edge].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Execution continues with the next instruction.
+    FallThrough,
+    /// goto, a conditional branch that is taken, or jsr to its subroutine.
+    Jump,
+    /// A case of a tableswitch or lookupswitch by its key, `None` for the default.
+    Switch(Option<i32>),
+    /// An exception thrown in a try range, by the class caught, `None` for any as finally.
+    Exception(Option<String>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    /// The index of the block control goes to.
+    pub(crate) target: usize,
+    pub(crate) kind: EdgeKind,
+}
+
+/// A run of instructions that is only entered at its first and only left after its last.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub(crate) start_pc: u32,
+    /// The pc after the last instruction of the block.
+    pub(crate) end_pc: u32,
+    /// The instructions of the block, as a range of the graph's instructions.
+    pub(crate) instructions: Range<usize>,
+    /// The edges leaving the block, in the order the branches and handlers list them.
+    pub(crate) successors: Vec<Edge>,
+    /// The blocks with an edge to this one, each listed once.
+    pub(crate) predecessors: Vec<usize>,
+}
+
+/**
+ * The control flow graph of a method body, whose blocks are in pc order with the entry
+ * block first.\
+ *  A block inside a try range has an edge to each handler covering it, as any of its
+ *  instructions may throw. jsr has an edge to its subroutine and one to the instruction
+ *  after it, where the subroutine's ret returns; ret itself has none, like a return.
+ */
+#[derive(Clone, Debug)]
+pub struct ControlFlowGraph {
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    pub fn new(
+        code: &Code,
+        constant_pool: &[ConstantPool],
+    ) -> Result<ControlFlowGraph, Box<dyn Error>> {
+        let instructions = decode(&code.code)?;
+        let code_length = code.code.len() as u32;
+        let is_boundary = |pc: u32| {
+            pc == code_length
+                || instructions
+                    .binary_search_by_key(&pc, |instruction| instruction.pc)
+                    .is_ok()
+        };
+
+        let mut leaders = BTreeSet::new();
+        if !instructions.is_empty() {
+            leaders.insert(0);
+        }
+        for instruction in &instructions {
+            let (targets, _) = targets(instruction);
+            leaders.extend(targets.into_iter().map(|(target, _)| target));
+            if ends_block(instruction) {
+                leaders.insert(next_pc(&instructions, instruction, code_length));
+            }
+        }
+        for entry in &code.exception_tables {
+            let (start, end, handler) = (
+                entry.start_pc as u32,
+                entry.end_pc as u32,
+                entry.handler_pc as u32,
+            );
+            if start >= end || end > code_length {
+                return Err(invalid(
+                    start,
+                    "exception range is empty or outside of the code",
+                ));
+            }
+            for pc in [start, end, handler] {
+                if !is_boundary(pc) || pc == code_length && pc != end {
+                    return Err(invalid(pc, "exception table pc is not an instruction"));
+                }
+            }
+            leaders.extend([start, end, handler]);
+        }
+        leaders.remove(&code_length);
+
+        let leaders: Vec<u32> = leaders.into_iter().collect();
+        let mut blocks = vec![];
+        let mut first = 0;
+        for (index, start_pc) in leaders.iter().enumerate() {
+            let end_pc = leaders.get(index + 1).copied().unwrap_or(code_length);
+            let last = first
+                + instructions[first..]
+                    .iter()
+                    .take_while(|instruction| instruction.pc < end_pc)
+                    .count();
+            blocks.push(BasicBlock {
+                start_pc: *start_pc,
+                end_pc,
+                instructions: first..last,
+                successors: vec![],
+                predecessors: vec![],
+            });
+            first = last;
+        }
+
+        let block_at = |pc: u32| leaders.binary_search(&pc).ok();
+        for index in 0..blocks.len() {
+            let block = &blocks[index];
+            let last = &instructions[block.instructions.end - 1];
+            let (targets, falls_through) = targets(last);
+            let mut successors: Vec<Edge> = targets
+                .into_iter()
+                .filter_map(|(pc, kind)| {
+                    Some(Edge {
+                        target: block_at(pc)?,
+                        kind,
+                    })
+                })
+                .collect();
+            if falls_through && index + 1 < blocks.len() {
+                successors.push(Edge {
+                    target: index + 1,
+                    kind: EdgeKind::FallThrough,
+                });
+            }
+            for entry in &code.exception_tables {
+                if (entry.start_pc as u32..entry.end_pc as u32).contains(&block.start_pc) {
+                    let catch_type = match entry.catch_type {
+                        0 => None,
+                        index => {
+                            Some(get_raw_class_name(constant_pool, index).ok_or_else(|| {
+                                invalid(entry.handler_pc as u32, "catch type is not a Class entry")
+                            })?)
+                        }
+                    };
+                    successors.push(Edge {
+                        target: block_at(entry.handler_pc as u32).unwrap_or_default(),
+                        kind: EdgeKind::Exception(catch_type),
+                    });
+                }
+            }
+            for edge in &successors {
+                let predecessors = &mut blocks[edge.target].predecessors;
+                if !predecessors.contains(&index) {
+                    predecessors.push(index);
+                }
+            }
+            blocks[index].successors = successors;
+        }
+        Ok(ControlFlowGraph {
+            instructions,
+            blocks,
+        })
+    }
+
+    pub fn get_blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    pub fn get_instructions(&self, block: usize) -> &[Instruction] {
+        &self.instructions[self.blocks[block].instructions.clone()]
+    }
+
+    /// The index of the block containing the instruction at `pc`.
+    pub fn get_block_at(&self, pc: u32) -> Option<usize> {
+        let index = self.blocks.partition_point(|block| block.end_pc <= pc);
+        self.blocks
+            .get(index)
+            .filter(|block| block.start_pc <= pc)
+            .map(|_| index)
+    }
+
+    /// The blocks reachable from the entry block, each before its successors except along
+    /// the back edges of loops.
+    pub fn get_reverse_postorder(&self) -> Vec<usize> {
+        let mut order = vec![];
+        if self.blocks.is_empty() {
+            return order;
+        }
+        let mut visited = vec![false; self.blocks.len()];
+        // Each block with the number of its successors visited so far.
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        while let Some((block, next)) = stack.last_mut() {
+            match self.blocks[*block].successors.get(*next) {
+                Some(edge) => {
+                    *next += 1;
+                    if !visited[edge.target] {
+                        visited[edge.target] = true;
+                        stack.push((edge.target, 0));
+                    }
+                }
+                None => {
+                    order.push(*block);
+                    stack.pop();
+                }
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /**
+     * The dominator tree of the graph, with
+     * [the algorithm of Cooper, Harvey and Kennedy](https://www.cs.rice.edu/~keith/EMBED/dom.pdf).
+     */
+    pub fn get_dominators(&self) -> Dominators {
+        let order = self.get_reverse_postorder();
+        let mut position = vec![usize::MAX; self.blocks.len()];
+        for (index, block) in order.iter().enumerate() {
+            position[*block] = index;
+        }
+        let mut immediate = vec![None; self.blocks.len()];
+        if let Some(entry) = order.first() {
+            immediate[*entry] = Some(*entry);
+        }
+        let intersect = |immediate: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while position[a] > position[b] {
+                    a = immediate[a].unwrap_or_default();
+                }
+                while position[b] > position[a] {
+                    b = immediate[b].unwrap_or_default();
+                }
+            }
+            a
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in order.iter().skip(1) {
+                let mut dominator = None;
+                for predecessor in &self.blocks[*block].predecessors {
+                    if immediate[*predecessor].is_none() {
+                        continue;
+                    }
+                    dominator = Some(match dominator {
+                        None => *predecessor,
+                        Some(dominator) => intersect(&immediate, *predecessor, dominator),
+                    });
+                }
+                if dominator.is_some() && immediate[*block] != dominator {
+                    immediate[*block] = dominator;
+                    changed = true;
+                }
+            }
+        }
+        Dominators { immediate }
+    }
+
+    /**
+     * The natural loops of the graph, outer loops before the loops nested in them.\
+     *  A loop is entered at its header, which dominates every block of the loop, and the
+     *  back edges to the header come from its latches. The loops of an irreducible graph,
+     *  entered at more than one block, are not found.
+     */
+    pub fn get_loops(&self) -> Vec<Loop> {
+        let dominators = self.get_dominators();
+        let mut loops: Vec<Loop> = vec![];
+        for header in self.get_reverse_postorder() {
+            let latches: Vec<usize> = self.blocks[header]
+                .predecessors
+                .iter()
+                .copied()
+                .filter(|latch| dominators.dominates(header, *latch))
+                .collect();
+            if latches.is_empty() {
+                continue;
+            }
+            let mut blocks = BTreeSet::from([header]);
+            let mut pending = latches.clone();
+            while let Some(block) = pending.pop() {
+                if blocks.insert(block) {
+                    pending.extend(
+                        self.blocks[block]
+                            .predecessors
+                            .iter()
+                            .filter(|predecessor| dominators.is_reachable(**predecessor)),
+                    );
+                }
+            }
+            // Loops found earlier have headers earlier in reverse postorder, so the
+            // innermost loop enclosing this one is the last to contain its header.
+            let parent = loops
+                .iter()
+                .rposition(|outer| outer.blocks.contains(&header));
+            loops.push(Loop {
+                header,
+                latches,
+                blocks: blocks.into_iter().collect(),
+                parent,
+            });
+        }
+        loops
+    }
+}
+
+/// The immediate dominator of each block of a [ControlFlowGraph].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dominators {
+    /// The entry block is its own immediate dominator, and unreachable blocks have none.
+    immediate: Vec<Option<usize>>,
+}
+
+impl Dominators {
+    /// The closest block every path from the entry to `block` goes through.
+    pub fn get_immediate_dominator(&self, block: usize) -> Option<usize> {
+        self.immediate[block].filter(|dominator| *dominator != block)
+    }
+
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.immediate[block].is_some()
+    }
+
+    /// Whether every path from the entry to `block` goes through `dominator`, which is the
+    /// case for `block` itself.
+    pub fn dominates(&self, dominator: usize, block: usize) -> bool {
+        let mut current = block;
+        loop {
+            if current == dominator {
+                return true;
+            }
+            match self.get_immediate_dominator(current) {
+                Some(next) => current = next,
+                None => return false,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Loop {
+    pub(crate) header: usize,
+    /// The blocks with a back edge to the header.
+    pub(crate) latches: Vec<usize>,
+    /// The blocks of the loop in pc order, including those of nested loops.
+    pub(crate) blocks: Vec<usize>,
+    /// The index of the innermost loop this one is nested in.
+    pub(crate) parent: Option<usize>,
+}
+
+/// Whether `instruction` is the last of its block.
+fn ends_block(instruction: &Instruction) -> bool {
+    matches!(
+        instruction.opcode,
+        IFEQ..=RET | TABLESWITCH..=RETURN | ATHROW | IFNULL..=JSR_W
+    )
+}
+
+/// The branch targets of `instruction` and whether execution can continue after it.
+fn targets(instruction: &Instruction) -> (Vec<(u32, EdgeKind)>, bool) {
+    let falls_through = !matches!(
+        instruction.opcode,
+        GOTO | GOTO_W | RET | TABLESWITCH | LOOKUPSWITCH | IRETURN..=RETURN | ATHROW
+    );
+    let targets = match &instruction.operands {
+        Operands::Branch(target) => vec![(*target, EdgeKind::Jump)],
+        Operands::TableSwitch {
+            default,
+            low,
+            high,
+            targets,
+        } => (*low..=*high)
+            .zip(targets)
+            .map(|(key, target)| (*target, EdgeKind::Switch(Some(key))))
+            .chain([(*default, EdgeKind::Switch(None))])
+            .collect(),
+        Operands::LookupSwitch { default, pairs } => pairs
+            .iter()
+            .map(|(key, target)| (*target, EdgeKind::Switch(Some(*key))))
+            .chain([(*default, EdgeKind::Switch(None))])
+            .collect(),
+        _ => vec![],
+    };
+    (targets, falls_through)
+}
+
+fn next_pc(instructions: &[Instruction], instruction: &Instruction, code_length: u32) -> u32 {
+    instructions
+        .iter()
+        .find(|next| next.pc > instruction.pc)
+        .map_or(code_length, |next| next.pc)
+}
+
+fn invalid(pc: u32, msg: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(LoadingCause::InvalidInstruction(pc), msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_builder::tests::{single_method, sum_loop};
+    use crate::class_builder::{CodeBuilder, Insn};
+    use crate::class_file::{AttributeInfo, ClassFile};
+
+    /// Builds a class with one static method and the graph of its code.
+    fn build(descriptor: &str, code: CodeBuilder) -> ControlFlowGraph {
+        let class = single_method("test/Graph", descriptor, code);
+        let class = ClassFile::from_bytes(&class.into_bytes().unwrap()).unwrap();
+        let code = class.methods[0]
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Code(code) => Some(code),
+                _ => None,
+            })
+            .unwrap();
+        ControlFlowGraph::new(code, &class.constant_pool).unwrap()
+    }
+
+    fn edges(graph: &ControlFlowGraph, block: usize) -> Vec<(usize, EdgeKind)> {
+        graph.blocks[block]
+            .successors
+            .iter()
+            .map(|edge| (edge.target, edge.kind.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_blocks_at_branches() {
+        let graph = build("(I)I", sum_loop());
+
+        let ranges: Vec<_> = graph
+            .blocks
+            .iter()
+            .map(|block| (block.start_pc, block.end_pc))
+            .collect();
+        assert_eq!(ranges, [(0, 6), (6, 13), (13, 26), (26, 29)]);
+        assert_eq!(edges(&graph, 0), [(1, EdgeKind::FallThrough)]);
+        assert_eq!(
+            edges(&graph, 1),
+            [(3, EdgeKind::Jump), (2, EdgeKind::FallThrough)]
+        );
+        assert_eq!(edges(&graph, 2), [(1, EdgeKind::Jump)]);
+        assert!(edges(&graph, 3).is_empty());
+        assert_eq!(graph.blocks[1].predecessors, [0, 2]);
+        assert_eq!(graph.get_block_at(14), Some(2));
+        assert_eq!(graph.get_instructions(3).len(), 2);
+
+        let dominators = graph.get_dominators();
+        let immediate: Vec<_> = (0..4)
+            .map(|block| dominators.get_immediate_dominator(block))
+            .collect();
+        assert_eq!(immediate, [None, Some(0), Some(1), Some(1)]);
+        assert!(dominators.dominates(1, 3) && !dominators.dominates(2, 3));
+        assert_eq!(
+            graph.get_loops(),
+            [Loop {
+                header: 1,
+                latches: vec![2],
+                blocks: vec![1, 2],
+                parent: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_edges_to_exception_handlers_and_switch_cases() {
+        // static void run(int key) { try { switch (key) { case 1: work(); } }
+        // catch (Exception e) {} }
+        let mut code = CodeBuilder::new(1, 1);
+        let (start, case, end, handler) = (
+            code.new_label(),
+            code.new_label(),
+            code.new_label(),
+            code.new_label(),
+        );
+        code.push(Insn::Label(start));
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::LookupSwitch {
+            default: end,
+            pairs: vec![(1, case)],
+        });
+        code.push(Insn::Label(case));
+        code.push(Insn::Method {
+            opcode: INVOKESTATIC,
+            owner: "test/Graph".into(),
+            name: "work".into(),
+            descriptor: "()V".into(),
+            interface: false,
+        });
+        code.push(Insn::Label(end));
+        code.push(Insn::Simple(RETURN));
+        code.push(Insn::Label(handler));
+        code.push(Insn::Local(ASTORE, 0));
+        code.push(Insn::Simple(RETURN));
+        code.add_exception_handler(start, end, handler, Some("java/lang/Exception"));
+        let graph = build("(I)V", code);
+
+        assert_eq!(graph.blocks.len(), 4);
+        let catch = EdgeKind::Exception(Some("java/lang/Exception".into()));
+        assert_eq!(
+            edges(&graph, 0),
+            [
+                (1, EdgeKind::Switch(Some(1))),
+                (2, EdgeKind::Switch(None)),
+                (3, catch.clone()),
+            ]
+        );
+        assert_eq!(edges(&graph, 1), [(2, EdgeKind::FallThrough), (3, catch)]);
+        assert!(edges(&graph, 2).is_empty());
+        assert_eq!(graph.get_reverse_postorder()[0], 0);
+        assert_eq!(graph.get_dominators().get_immediate_dominator(3), Some(0));
+        assert!(graph.get_loops().is_empty());
+    }
+
+    #[test]
+    fn test_switch_keys_up_to_max() {
+        // static void run(int key) { switch (key) { case MAX - 1: case MAX: } }
+        let mut code = CodeBuilder::new(1, 1);
+        let (case, end) = (code.new_label(), code.new_label());
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::TableSwitch {
+            default: end,
+            low: i32::MAX - 1,
+            targets: vec![case, case],
+        });
+        code.push(Insn::Label(case));
+        code.push(Insn::Label(end));
+        code.push(Insn::Simple(RETURN));
+        let graph = build("(I)V", code);
+
+        assert_eq!(
+            edges(&graph, 0),
+            [
+                (1, EdgeKind::Switch(Some(i32::MAX - 1))),
+                (1, EdgeKind::Switch(Some(i32::MAX))),
+                (1, EdgeKind::Switch(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nest_loops() {
+        // static void run() { while (true) { while (work()) {} } }
+        let mut code = CodeBuilder::new(1, 0);
+        let (outer, inner) = (code.new_label(), code.new_label());
+        code.push(Insn::Label(outer));
+        code.push(Insn::Label(inner));
+        code.push(Insn::Method {
+            opcode: INVOKESTATIC,
+            owner: "test/Graph".into(),
+            name: "work".into(),
+            descriptor: "()Z".into(),
+            interface: false,
+        });
+        code.push(Insn::Jump(IFNE, inner));
+        code.push(Insn::Simple(NOP));
+        code.push(Insn::Jump(GOTO, outer));
+        let graph = build("()V", code);
+
+        assert_eq!(graph.blocks.len(), 2);
+        let loops = graph.get_loops();
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].latches, [0, 1]);
+
+        // With a block before it, the inner loop gets a header of its own.
+        let mut code = CodeBuilder::new(1, 0);
+        let (outer, inner) = (code.new_label(), code.new_label());
+        code.push(Insn::Label(outer));
+        code.push(Insn::Simple(NOP));
+        code.push(Insn::Label(inner));
+        code.push(Insn::Method {
+            opcode: INVOKESTATIC,
+            owner: "test/Graph".into(),
+            name: "work".into(),
+            descriptor: "()Z".into(),
+            interface: false,
+        });
+        code.push(Insn::Jump(IFNE, inner));
+        code.push(Insn::Jump(GOTO, outer));
+        let graph = build("()V", code);
+
+        let loops = graph.get_loops();
+        assert_eq!(loops.len(), 2);
+        assert_eq!((loops[0].header, loops[0].parent), (0, None));
+        assert_eq!(loops[0].blocks, [0, 1, 2]);
+        assert_eq!((loops[1].header, loops[1].parent), (1, Some(0)));
+        assert_eq!(loops[1].blocks, [1]);
+    }
+}
@@ -0,0 +1,1401 @@
+use std::{collections::HashMap, error::Error};
+
+use crate::access_flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::attributes::{
+    BootstrapMethods, Code, ConstantValue, ExceptionTable, Exceptions, LineNumber, LineNumberTable,
+    Methods, Signature, SourceFile,
+};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool, FieldInfo, MethodInfo};
+use crate::class_writer::{utf8_length, write_constant};
+use crate::constants::{self, Tags};
+use crate::descriptors::MethodDescriptor;
+use crate::errors::class_writing::{WritingCause, WritingError};
+use crate::instructions::*;
+
+/// A method handle (§4.4.8): how the handle behaves and the member it refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Handle {
+    /// The reference_kind, from 1 (REF_getField) to 9 (REF_invokeInterface).
+    pub kind: u8,
+    /// The binary name of the class declaring the member, e.g. `java/lang/String`.
+    pub owner: String,
+    pub name: String,
+    pub descriptor: String,
+    /// Whether `owner` is an interface, so a method is an InterfaceMethodref.
+    pub interface: bool,
+}
+
+/// A loadable constant, as pushed by ldc and passed to bootstrap methods.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(String),
+    /// A class by its binary name, or an array class by its descriptor.
+    Class(String),
+    /// A method descriptor.
+    MethodType(String),
+    MethodHandle(Handle),
+    /// A dynamically computed constant, by the index of its bootstrap method.
+    Dynamic {
+        bootstrap: u16,
+        name: String,
+        descriptor: String,
+    },
+}
+
+impl Constant {
+    /// Whether the constant takes two slots, so it is loaded with ldc2_w.
+    fn is_wide(&self) -> bool {
+        match self {
+            Constant::Long(_) | Constant::Double(_) => true,
+            Constant::Dynamic { descriptor, .. } => descriptor == "J" || descriptor == "D",
+            _ => false,
+        }
+    }
+}
+
+/**
+ * Interns constants into a new constant pool, so each distinct constant is added once and
+ * every reference to it shares an index.
+ */
+#[derive(Clone, Debug)]
+pub struct ConstantPoolBuilder {
+    /// The entries by index; entry 0 is unusable, as in `ClassFile::constant_pool`.
+    constants: Vec<ConstantPool>,
+    /// The index of each entry, keyed by its encoding in the class file.
+    indices: HashMap<Vec<u8>, u16>,
+}
+
+impl Default for ConstantPoolBuilder {
+    fn default() -> Self {
+        ConstantPoolBuilder {
+            constants: vec![ConstantPool::Unknown],
+            indices: HashMap::new(),
+        }
+    }
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> ConstantPoolBuilder {
+        ConstantPoolBuilder::default()
+    }
+
+    /// Adds `constant` unless an equal entry is already in the pool, returning its index.
+    pub fn add(&mut self, constant: ConstantPool) -> Result<u16, Box<dyn Error>> {
+        let mut key = Vec::new();
+        write_constant(&constant, &mut key)?;
+        if let Some(index) = self.indices.get(&key) {
+            return Ok(*index);
+        }
+        let index = self.constants.len();
+        // §4.4.5: a long or double takes two entries.
+        let wide = matches!(constant, ConstantPool::Long(_) | ConstantPool::Double(_));
+        if index + wide as usize >= u16::MAX as usize {
+            return Err(Box::new(WritingError::new(
+                WritingCause::TooLarge("constant_pool".into()),
+                "a constant pool holds at most 65534 entries",
+            )));
+        }
+        self.constants.push(constant);
+        if wide {
+            self.constants.push(ConstantPool::Unknown);
+        }
+        self.indices.insert(key, index as u16);
+        Ok(index as u16)
+    }
+
+    pub fn add_utf8(&mut self, value: &str) -> Result<u16, Box<dyn Error>> {
+        let utf8 = constants::Utf8::from(value);
+        utf8_length(utf8.bytes.len())?;
+        self.add(ConstantPool::Utf8(utf8))
+    }
+
+    pub fn add_integer(&mut self, value: i32) -> Result<u16, Box<dyn Error>> {
+        self.add(ConstantPool::Integer(constants::Integer::new(
+            Tags::Integer,
+            value as u32,
+        )))
+    }
+
+    pub fn add_float(&mut self, value: f32) -> Result<u16, Box<dyn Error>> {
+        self.add(ConstantPool::Float(constants::Float::new(
+            Tags::Float,
+            value.to_bits(),
+        )))
+    }
+
+    pub fn add_long(&mut self, value: i64) -> Result<u16, Box<dyn Error>> {
+        let bits = value as u64;
+        self.add(ConstantPool::Long(constants::Long::new(
+            Tags::Long,
+            (bits >> 32) as u32,
+            bits as u32,
+        )))
+    }
+
+    pub fn add_double(&mut self, value: f64) -> Result<u16, Box<dyn Error>> {
+        let bits = value.to_bits();
+        self.add(ConstantPool::Double(constants::Double::new(
+            Tags::Double,
+            (bits >> 32) as u32,
+            bits as u32,
+        )))
+    }
+
+    /// A class by its binary name, e.g. `java/lang/Object`, or an array class by its descriptor.
+    pub fn add_class(&mut self, name: &str) -> Result<u16, Box<dyn Error>> {
+        let name_index = self.add_utf8(name)?;
+        self.add(ConstantPool::Class(constants::Class::new(
+            Tags::Class,
+            name_index,
+        )))
+    }
+
+    pub fn add_string(&mut self, value: &str) -> Result<u16, Box<dyn Error>> {
+        let string_index = self.add_utf8(value)?;
+        self.add(ConstantPool::String(constants::String::new(
+            Tags::String,
+            string_index,
+        )))
+    }
+
+    pub fn add_name_and_type(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, Box<dyn Error>> {
+        let name_index = self.add_utf8(name)?;
+        let descriptor_index = self.add_utf8(descriptor)?;
+        self.add(ConstantPool::NameAndType(constants::NameAndType::new(
+            Tags::NameAndType,
+            name_index,
+            descriptor_index,
+        )))
+    }
+
+    pub fn add_field_ref(
+        &mut self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, Box<dyn Error>> {
+        let class_index = self.add_class(owner)?;
+        let name_and_type_index = self.add_name_and_type(name, descriptor)?;
+        self.add(ConstantPool::Fieldref(constants::Fieldref::new(
+            Tags::Fieldref,
+            class_index,
+            name_and_type_index,
+        )))
+    }
+
+    /// A Methodref, or an InterfaceMethodref if `owner` is an interface.
+    pub fn add_method_ref(
+        &mut self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+        interface: bool,
+    ) -> Result<u16, Box<dyn Error>> {
+        let class_index = self.add_class(owner)?;
+        let name_and_type_index = self.add_name_and_type(name, descriptor)?;
+        self.add(if interface {
+            ConstantPool::InterfaceMethodRef(constants::InterfaceMethodref::new(
+                Tags::InterfaceMethodRef,
+                class_index,
+                name_and_type_index,
+            ))
+        } else {
+            ConstantPool::Methodref(constants::Methodref::new(
+                Tags::Methodref,
+                class_index,
+                name_and_type_index,
+            ))
+        })
+    }
+
+    pub fn add_method_handle(&mut self, handle: &Handle) -> Result<u16, Box<dyn Error>> {
+        let reference_index = if handle.kind <= 4 {
+            self.add_field_ref(&handle.owner, &handle.name, &handle.descriptor)?
+        } else {
+            self.add_method_ref(
+                &handle.owner,
+                &handle.name,
+                &handle.descriptor,
+                handle.interface,
+            )?
+        };
+        self.add(ConstantPool::MethodHandle(constants::MethodHandle::new(
+            Tags::MethodHandle,
+            handle.kind,
+            reference_index,
+        )))
+    }
+
+    pub fn add_method_type(&mut self, descriptor: &str) -> Result<u16, Box<dyn Error>> {
+        let descriptor_index = self.add_utf8(descriptor)?;
+        self.add(ConstantPool::MethodType(constants::MethodType::new(
+            Tags::MethodType,
+            descriptor_index,
+        )))
+    }
+
+    pub fn add_dynamic(
+        &mut self,
+        bootstrap: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, Box<dyn Error>> {
+        let name_and_type_index = self.add_name_and_type(name, descriptor)?;
+        self.add(ConstantPool::Dynamic(constants::Dynamic::new(
+            Tags::Dynamic,
+            bootstrap,
+            name_and_type_index,
+        )))
+    }
+
+    pub fn add_invoke_dynamic(
+        &mut self,
+        bootstrap: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, Box<dyn Error>> {
+        let name_and_type_index = self.add_name_and_type(name, descriptor)?;
+        self.add(ConstantPool::InvokeDynamic(constants::InvokeDynamic::new(
+            Tags::InvokeDynamic,
+            bootstrap,
+            name_and_type_index,
+        )))
+    }
+
+    pub fn add_module(&mut self, name: &str) -> Result<u16, Box<dyn Error>> {
+        let name_index = self.add_utf8(name)?;
+        self.add(ConstantPool::Module(constants::Module::new(
+            Tags::Module,
+            name_index,
+        )))
+    }
+
+    /// A package by its internal name, e.g. `java/lang`.
+    pub fn add_package(&mut self, name: &str) -> Result<u16, Box<dyn Error>> {
+        let name_index = self.add_utf8(name)?;
+        self.add(ConstantPool::Package(constants::Package::new(
+            Tags::Package,
+            name_index,
+        )))
+    }
+
+    pub fn add_constant(&mut self, constant: &Constant) -> Result<u16, Box<dyn Error>> {
+        match constant {
+            Constant::Integer(value) => self.add_integer(*value),
+            Constant::Float(value) => self.add_float(*value),
+            Constant::Long(value) => self.add_long(*value),
+            Constant::Double(value) => self.add_double(*value),
+            Constant::String(value) => self.add_string(value),
+            Constant::Class(name) => self.add_class(name),
+            Constant::MethodType(descriptor) => self.add_method_type(descriptor),
+            Constant::MethodHandle(handle) => self.add_method_handle(handle),
+            Constant::Dynamic {
+                bootstrap,
+                name,
+                descriptor,
+            } => self.add_dynamic(*bootstrap, name, descriptor),
+        }
+    }
+
+    pub fn get_constants(&self) -> &[ConstantPool] {
+        &self.constants
+    }
+}
+
+/// A position in the code of a method, placed with `Insn::Label` and used as a branch target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Label(usize);
+
+/**
+ * An instruction to assemble. Branches refer to labels and constants are given by value,
+ * so offsets and constant pool indices are worked out when the method is added to a class.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Insn {
+    /// Places a label before the next instruction; it takes no space in the code array.
+    Label(Label),
+    /// An instruction without operands, such as `aload_0`, `iadd` or `return`.
+    Simple(u8),
+    /// bipush or sipush.
+    Push(u8, i32),
+    /// ldc, ldc_w or ldc2_w, whichever the constant's index and size need.
+    Ldc(Constant),
+    /// A load, store or ret of a local variable, prefixed with wide if the index needs it.
+    Local(u8, u16),
+    /// iinc, prefixed with wide if the index or increment needs it.
+    Increment { index: u16, value: i16 },
+    /**
+     * A branch. When the target is too far for a 16-bit offset, goto and jsr become goto_w
+     * and jsr_w, and a conditional branch is inverted to jump over a goto_w.
+     */
+    Jump(u8, Label),
+    TableSwitch {
+        default: Label,
+        low: i32,
+        targets: Vec<Label>,
+    },
+    /// A lookupswitch; the pairs are sorted by key when they are written.
+    LookupSwitch {
+        default: Label,
+        pairs: Vec<(i32, Label)>,
+    },
+    /// getstatic, putstatic, getfield or putfield.
+    Field {
+        opcode: u8,
+        owner: String,
+        name: String,
+        descriptor: String,
+    },
+    /// invokevirtual, invokespecial, invokestatic or invokeinterface.
+    Method {
+        opcode: u8,
+        owner: String,
+        name: String,
+        descriptor: String,
+        interface: bool,
+    },
+    /// An invokedynamic call site, by the index of its bootstrap method.
+    InvokeDynamic {
+        bootstrap: u16,
+        name: String,
+        descriptor: String,
+    },
+    /// new, anewarray, checkcast or instanceof, with the class they operate on.
+    Type(u8, String),
+    /// newarray, with the atype of the primitive array.
+    NewArray(u8),
+    /// multianewarray, with the array class and the number of dimensions to create.
+    MultiANewArray(String, u8),
+}
+
+/// An exception handler, by the labels of the range it covers and of the handler code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Handler {
+    start: Label,
+    end: Label,
+    handler: Label,
+    /// The binary name of the class caught, or `None` for any exception as with finally.
+    catch_type: Option<String>,
+}
+
+/// The code of a method, as a list of instructions to be assembled into a Code attribute.
+#[derive(Clone, Debug, Default)]
+pub struct CodeBuilder {
+    max_stack: u16,
+    max_locals: u16,
+    instructions: Vec<Insn>,
+    labels: usize,
+    handlers: Vec<Handler>,
+    line_numbers: Vec<(Label, u16)>,
+}
+
+impl CodeBuilder {
+    pub fn new(max_stack: u16, max_locals: u16) -> CodeBuilder {
+        CodeBuilder {
+            max_stack,
+            max_locals,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels += 1;
+        Label(self.labels - 1)
+    }
+
+    pub fn push(&mut self, instruction: Insn) {
+        self.instructions.push(instruction);
+    }
+
+    pub fn get_instructions(&self) -> &[Insn] {
+        &self.instructions
+    }
+
+    /// Covers the code from `start` up to `end` with a handler at `handler`.
+    pub fn add_exception_handler(
+        &mut self,
+        start: Label,
+        end: Label,
+        handler: Label,
+        catch_type: Option<&str>,
+    ) {
+        self.handlers.push(Handler {
+            start,
+            end,
+            handler,
+            catch_type: catch_type.map(String::from),
+        });
+    }
+
+    /// Records that the code from `label` on was compiled from `line` of the source file.
+    pub fn add_line_number(&mut self, label: Label, line: u16) {
+        self.line_numbers.push((label, line));
+    }
+}
+
+/// A field to add to a class.
+#[derive(Clone, Debug)]
+pub struct FieldBuilder {
+    access_flags: Vec<FieldAccessFlags>,
+    name: String,
+    descriptor: String,
+    constant_value: Option<Constant>,
+    signature: Option<String>,
+}
+
+impl FieldBuilder {
+    pub fn new(access_flags: &[FieldAccessFlags], name: &str, descriptor: &str) -> FieldBuilder {
+        FieldBuilder {
+            access_flags: access_flags.to_vec(),
+            name: name.into(),
+            descriptor: descriptor.into(),
+            constant_value: None,
+            signature: None,
+        }
+    }
+
+    /// The value a static final field is initialized to, as a ConstantValue attribute.
+    pub fn set_constant_value(&mut self, value: Constant) {
+        self.constant_value = Some(value);
+    }
+
+    pub fn set_signature(&mut self, signature: &str) {
+        self.signature = Some(signature.into());
+    }
+}
+
+/// A method to add to a class.
+#[derive(Clone, Debug)]
+pub struct MethodBuilder {
+    access_flags: Vec<MethodAccessFlags>,
+    name: String,
+    descriptor: String,
+    code: Option<CodeBuilder>,
+    exceptions: Vec<String>,
+    signature: Option<String>,
+}
+
+impl MethodBuilder {
+    pub fn new(access_flags: &[MethodAccessFlags], name: &str, descriptor: &str) -> MethodBuilder {
+        MethodBuilder {
+            access_flags: access_flags.to_vec(),
+            name: name.into(),
+            descriptor: descriptor.into(),
+            code: None,
+            exceptions: vec![],
+            signature: None,
+        }
+    }
+
+    /// The body of the method; abstract and native methods have none.
+    pub fn set_code(&mut self, code: CodeBuilder) {
+        self.code = Some(code);
+    }
+
+    /// Declares that the method throws the class called `name`, in its Exceptions attribute.
+    pub fn add_exception(&mut self, name: &str) {
+        self.exceptions.push(name.into());
+    }
+
+    pub fn set_signature(&mut self, signature: &str) {
+        self.signature = Some(signature.into());
+    }
+}
+
+/**
+ * Builds a [ClassFile] from scratch, interning every name, descriptor and constant into a
+ * new constant pool as fields, methods and attributes are added.
+ *
+ * Classes are version 49.0 unless `set_version` says otherwise, since classes from version
+ * 50.0 on need a StackMapTable for code that branches; `frames::compute_frames` adds one.
+ */
+#[derive(Clone, Debug)]
+pub struct ClassBuilder {
+    constant_pool: ConstantPoolBuilder,
+    minor_version: u16,
+    major_version: u16,
+    access_flags: Vec<ClassAccessFlags>,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<FieldInfo>,
+    methods: Vec<MethodInfo>,
+    attributes: Vec<AttributeInfo>,
+    bootstrap_methods: Vec<Methods>,
+}
+
+impl ClassBuilder {
+    /**
+     * Starts a class with the binary name `name`, such as `com/example/Main`, that extends
+     * `super_name`. Only `java/lang/Object` and modules have no superclass.
+     */
+    pub fn new(
+        access_flags: &[ClassAccessFlags],
+        name: &str,
+        super_name: Option<&str>,
+    ) -> Result<ClassBuilder, Box<dyn Error>> {
+        let mut constant_pool = ConstantPoolBuilder::new();
+        let this_class = constant_pool.add_class(name)?;
+        let super_class = match super_name {
+            Some(super_name) => constant_pool.add_class(super_name)?,
+            None => 0,
+        };
+        Ok(ClassBuilder {
+            constant_pool,
+            minor_version: 0,
+            major_version: 49,
+            access_flags: access_flags.to_vec(),
+            this_class,
+            super_class,
+            interfaces: vec![],
+            fields: vec![],
+            methods: vec![],
+            attributes: vec![],
+            bootstrap_methods: vec![],
+        })
+    }
+
+    pub fn set_version(&mut self, major_version: u16, minor_version: u16) {
+        self.major_version = major_version;
+        self.minor_version = minor_version;
+    }
+
+    pub fn add_interface(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        let interface = self.constant_pool.add_class(name)?;
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    /// The pool constants are interned into, for attributes added with `add_attribute`.
+    pub fn get_constant_pool(&mut self) -> &mut ConstantPoolBuilder {
+        &mut self.constant_pool
+    }
+
+    pub fn add_field(&mut self, field: FieldBuilder) -> Result<(), Box<dyn Error>> {
+        let mut attributes = vec![];
+        if let Some(value) = &field.constant_value {
+            let name_index = self.constant_pool.add_utf8("ConstantValue")?;
+            let value_index = self.constant_pool.add_constant(value)?;
+            attributes.push(AttributeInfo::ConstantValue(ConstantValue::new(
+                name_index,
+                2,
+                value_index,
+            )));
+        }
+        if let Some(signature) = &field.signature {
+            attributes.push(self.signature(signature)?);
+        }
+        self.fields.push(FieldInfo {
+            access_flags: field.access_flags,
+            name_index: self.constant_pool.add_utf8(&field.name)?,
+            descriptor_index: self.constant_pool.add_utf8(&field.descriptor)?,
+            attributes_count: attributes.len() as u16,
+            attributes,
+        });
+        Ok(())
+    }
+
+    /// Adds a method, assembling its code if it has any.
+    pub fn add_method(&mut self, method: MethodBuilder) -> Result<(), Box<dyn Error>> {
+        let mut attributes = vec![];
+        if let Some(code) = &method.code {
+            attributes.push(AttributeInfo::Code(self.assemble(code)?));
+        }
+        if !method.exceptions.is_empty() {
+            let name_index = self.constant_pool.add_utf8("Exceptions")?;
+            let mut exception_index_table = Vec::with_capacity(method.exceptions.len());
+            for exception in &method.exceptions {
+                exception_index_table.push(self.constant_pool.add_class(exception)?);
+            }
+            attributes.push(AttributeInfo::Exceptions(Exceptions {
+                attribute_name_index: name_index,
+                attribute_length: 2 + 2 * exception_index_table.len() as u32,
+                number_of_exceptions: exception_index_table.len() as u16,
+                exception_index_table,
+            }));
+        }
+        if let Some(signature) = &method.signature {
+            attributes.push(self.signature(signature)?);
+        }
+        self.methods.push(MethodInfo {
+            access_flags: method.access_flags,
+            name_index: self.constant_pool.add_utf8(&method.name)?,
+            descriptor_index: self.constant_pool.add_utf8(&method.descriptor)?,
+            attributes_count: attributes.len() as u16,
+            attributes,
+        });
+        Ok(())
+    }
+
+    /**
+     * Adds an entry to the BootstrapMethods attribute, returning its index for use by
+     * `Insn::InvokeDynamic` and `Constant::Dynamic`. Adding the same entry twice returns the
+     * index it was first given.
+     */
+    pub fn add_bootstrap_method(
+        &mut self,
+        handle: &Handle,
+        arguments: &[Constant],
+    ) -> Result<u16, Box<dyn Error>> {
+        let bootstrap_method_ref = self.constant_pool.add_method_handle(handle)?;
+        let mut bootstrap_arguments = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            bootstrap_arguments.push(self.constant_pool.add_constant(argument)?);
+        }
+        if let Some(index) = self.bootstrap_methods.iter().position(|method| {
+            method.bootstrap_method_ref == bootstrap_method_ref
+                && method.bootstrap_arguments == bootstrap_arguments
+        }) {
+            return Ok(index as u16);
+        }
+        self.bootstrap_methods.push(Methods {
+            bootstrap_method_ref,
+            num_bootstrap_arguments: bootstrap_arguments.len() as u16,
+            bootstrap_arguments,
+        });
+        Ok(self.bootstrap_methods.len() as u16 - 1)
+    }
+
+    /// Names the source file the class was compiled from, e.g. `Main.java`.
+    pub fn set_source_file(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        let name_index = self.constant_pool.add_utf8("SourceFile")?;
+        let sourcefile_index = self.constant_pool.add_utf8(name)?;
+        self.attributes
+            .push(AttributeInfo::SourceFile(SourceFile::new(
+                name_index,
+                2,
+                sourcefile_index,
+            )?));
+        Ok(())
+    }
+
+    pub fn set_signature(&mut self, signature: &str) -> Result<(), Box<dyn Error>> {
+        let signature = self.signature(signature)?;
+        self.attributes.push(signature);
+        Ok(())
+    }
+
+    /**
+     * Adds an attribute to the class as it is. Its name and any constants it refers to
+     * must already be in the pool from `get_constant_pool`.
+     */
+    pub fn add_attribute(&mut self, attribute: AttributeInfo) {
+        self.attributes.push(attribute);
+    }
+
+    pub fn build(mut self) -> Result<ClassFile, Box<dyn Error>> {
+        if !self.bootstrap_methods.is_empty() {
+            let name_index = self.constant_pool.add_utf8("BootstrapMethods")?;
+            let attribute_length = 2 + self
+                .bootstrap_methods
+                .iter()
+                .map(|method| 4 + 2 * method.bootstrap_arguments.len() as u32)
+                .sum::<u32>();
+            self.attributes
+                .push(AttributeInfo::BootstrapMethods(BootstrapMethods {
+                    attribute_name_index: name_index,
+                    attribute_length,
+                    num_bootstrap_methods: self.bootstrap_methods.len() as u16,
+                    bootstrap_methods: self.bootstrap_methods,
+                }));
+        }
+        Ok(ClassFile {
+            magic: 0xCAFEBABE,
+            minor_version: self.minor_version,
+            major_version: self.major_version,
+            constant_pool_count: self.constant_pool.constants.len() as u16,
+            constant_pool: self.constant_pool.constants,
+            access_flags: self.access_flags,
+            this_class: self.this_class,
+            super_class: self.super_class,
+            interfaces_count: self.interfaces.len() as u16,
+            interfaces: self.interfaces,
+            field_count: self.fields.len() as u16,
+            fields: self.fields,
+            methods_count: self.methods.len() as u16,
+            methods: self.methods,
+            attributes_count: self.attributes.len() as u16,
+            attributes: self.attributes,
+        })
+    }
+
+    /// Builds the class and writes it in the class file format.
+    pub fn into_bytes(self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.build()?.to_bytes()
+    }
+
+    fn signature(&mut self, signature: &str) -> Result<AttributeInfo, Box<dyn Error>> {
+        let name_index = self.constant_pool.add_utf8("Signature")?;
+        let signature_index = self.constant_pool.add_utf8(signature)?;
+        Ok(AttributeInfo::Signature(Signature::new(
+            name_index,
+            2,
+            signature_index,
+        )?))
+    }
+
+    /// Assembles the instructions of `code` into a Code attribute.
+    fn assemble(&mut self, code: &CodeBuilder) -> Result<Code, Box<dyn Error>> {
+        // Constants are interned first, since the size of ldc depends on the index.
+        let mut indices = Vec::with_capacity(code.instructions.len());
+        for instruction in &code.instructions {
+            indices.push(self.intern_operand(instruction)?);
+        }
+        // Branches start out short and are widened until every offset fits; widening only
+        // ever moves code further apart, so this settles.
+        let mut widened = vec![false; code.instructions.len()];
+        let (pcs, labels) = loop {
+            let (pcs, labels) = layout(code, &indices, &widened)?;
+            let mut settled = true;
+            for (i, instruction) in code.instructions.iter().enumerate() {
+                if let Insn::Jump(opcode, label) = instruction {
+                    let offset = labels[label.0] as i64 - pcs[i] as i64;
+                    if !widened[i]
+                        && !matches!(*opcode, GOTO_W | JSR_W)
+                        && offset as i16 as i64 != offset
+                    {
+                        widened[i] = true;
+                        settled = false;
+                    }
+                }
+            }
+            if settled {
+                break (pcs, labels);
+            }
+        };
+
+        let mut bytes = Vec::new();
+        for (i, instruction) in code.instructions.iter().enumerate() {
+            encode(
+                instruction,
+                pcs[i],
+                indices[i],
+                widened[i],
+                &labels,
+                &mut bytes,
+            )?;
+        }
+
+        let mut exception_tables = Vec::with_capacity(code.handlers.len());
+        for handler in &code.handlers {
+            let catch_type = match &handler.catch_type {
+                Some(name) => self.constant_pool.add_class(name)?,
+                None => 0,
+            };
+            exception_tables.push(ExceptionTable::new(
+                labels[handler.start.0] as u16,
+                labels[handler.end.0] as u16,
+                labels[handler.handler.0] as u16,
+                catch_type,
+            ));
+        }
+        let mut attributes = vec![];
+        if !code.line_numbers.is_empty() {
+            let line_number_table: Vec<LineNumber> = code
+                .line_numbers
+                .iter()
+                .map(|(label, line)| LineNumber::new(labels[label.0] as u16, *line))
+                .collect();
+            attributes.push(AttributeInfo::LineNumberTable(LineNumberTable {
+                attribute_name_index: self.constant_pool.add_utf8("LineNumberTable")?,
+                attribute_length: 2 + 4 * line_number_table.len() as u32,
+                line_number_table_length: line_number_table.len() as u16,
+                line_number_table,
+            }));
+        }
+        let attributes_length: u32 = attributes
+            .iter()
+            .map(|attribute| match attribute {
+                AttributeInfo::LineNumberTable(table) => 6 + table.attribute_length,
+                _ => 0,
+            })
+            .sum();
+        Ok(Code {
+            attribute_name_index: self.constant_pool.add_utf8("Code")?,
+            attribute_length: 12
+                + bytes.len() as u32
+                + 8 * exception_tables.len() as u32
+                + attributes_length,
+            max_stack: code.max_stack,
+            max_locals: code.max_locals,
+            code_length: bytes.len() as u32,
+            code: bytes,
+            exception_table_length: exception_tables.len() as u16,
+            exception_tables,
+            attributes_count: attributes.len() as u16,
+            attributes,
+        })
+    }
+
+    /// The constant pool index an instruction refers to, or 0 if it has none.
+    fn intern_operand(&mut self, instruction: &Insn) -> Result<u16, Box<dyn Error>> {
+        let pool = &mut self.constant_pool;
+        Ok(match instruction {
+            Insn::Ldc(constant) => pool.add_constant(constant)?,
+            Insn::Field {
+                opcode,
+                owner,
+                name,
+                descriptor,
+            } => {
+                check_opcode(*opcode, matches!(*opcode, GETSTATIC..=PUTFIELD))?;
+                pool.add_field_ref(owner, name, descriptor)?
+            }
+            Insn::Method {
+                opcode,
+                owner,
+                name,
+                descriptor,
+                interface,
+            } => {
+                check_opcode(*opcode, matches!(*opcode, INVOKEVIRTUAL..=INVOKEINTERFACE))?;
+                pool.add_method_ref(owner, name, descriptor, *interface)?
+            }
+            Insn::InvokeDynamic {
+                bootstrap,
+                name,
+                descriptor,
+            } => pool.add_invoke_dynamic(*bootstrap, name, descriptor)?,
+            Insn::Type(opcode, name) => {
+                check_opcode(
+                    *opcode,
+                    matches!(*opcode, NEW | ANEWARRAY | CHECKCAST | INSTANCEOF),
+                )?;
+                pool.add_class(name)?
+            }
+            Insn::MultiANewArray(name, _) => pool.add_class(name)?,
+            Insn::Simple(opcode) => {
+                check_opcode(*opcode, *opcode <= JSR_W && !has_operands(*opcode))?;
+                0
+            }
+            Insn::Push(opcode, value) => {
+                let fits = match *opcode {
+                    BIPUSH => i8::try_from(*value).is_ok(),
+                    SIPUSH => i16::try_from(*value).is_ok(),
+                    _ => false,
+                };
+                check_opcode(*opcode, fits)?;
+                0
+            }
+            Insn::Local(opcode, _) => {
+                check_opcode(
+                    *opcode,
+                    matches!(*opcode, ILOAD..=ALOAD | ISTORE..=ASTORE | RET),
+                )?;
+                0
+            }
+            Insn::Jump(opcode, _) => {
+                check_opcode(
+                    *opcode,
+                    matches!(*opcode, IFEQ..=JSR | IFNULL | IFNONNULL | GOTO_W | JSR_W),
+                )?;
+                0
+            }
+            _ => 0,
+        })
+    }
+}
+
+/// Works out the pc of every instruction and label.
+fn layout(
+    code: &CodeBuilder,
+    indices: &[u16],
+    widened: &[bool],
+) -> Result<(Vec<u32>, Vec<u32>), Box<dyn Error>> {
+    let mut pcs = Vec::with_capacity(code.instructions.len());
+    let mut labels: Vec<Option<u32>> = vec![None; code.labels];
+    let mut pc: u32 = 0;
+    for (i, instruction) in code.instructions.iter().enumerate() {
+        pcs.push(pc);
+        // The padding that aligns the operands of a switch to a multiple of four.
+        let padding = 3 - pc % 4;
+        pc += match instruction {
+            Insn::Label(label) => {
+                match labels.get_mut(label.0) {
+                    Some(position @ None) => *position = Some(pc),
+                    _ => return Err(invalid_label(*label, "label is placed twice")),
+                }
+                0
+            }
+            Insn::Simple(_) => 1,
+            Insn::Push(opcode, _) => 1 + (*opcode == SIPUSH) as u32 + 1,
+            Insn::Ldc(constant) => 2 + (constant.is_wide() || indices[i] > 0xff) as u32,
+            Insn::Local(_, index) => 2 + 2 * (*index > 0xff) as u32,
+            Insn::Increment { index, value } => {
+                3 + 3 * (*index > 0xff || i8::try_from(*value).is_err()) as u32
+            }
+            Insn::Jump(opcode, _) => match *opcode {
+                GOTO_W | JSR_W => 5,
+                GOTO | JSR if widened[i] => 5,
+                _ if widened[i] => 8,
+                _ => 3,
+            },
+            Insn::TableSwitch { targets, .. } => 1 + padding + 12 + 4 * targets.len() as u32,
+            Insn::LookupSwitch { pairs, .. } => 1 + padding + 8 + 8 * pairs.len() as u32,
+            Insn::Method { opcode, .. } if *opcode == INVOKEINTERFACE => 5,
+            Insn::Field { .. } | Insn::Method { .. } | Insn::Type(..) => 3,
+            Insn::InvokeDynamic { .. } => 5,
+            Insn::NewArray(_) => 2,
+            Insn::MultiANewArray(..) => 4,
+        };
+        if pc > u16::MAX as u32 {
+            return Err(Box::new(WritingError::new(
+                WritingCause::TooLarge("code".into()),
+                "the code of a method must be shorter than 65536 bytes",
+            )));
+        }
+    }
+    let mut positions = Vec::with_capacity(labels.len());
+    for (label, position) in labels.into_iter().enumerate() {
+        positions
+            .push(position.ok_or_else(|| invalid_label(Label(label), "label is never placed"))?);
+    }
+    // Every label used must have been placed, which also covers labels from another builder.
+    for instruction in &code.instructions {
+        let used: Vec<Label> = match instruction {
+            Insn::Jump(_, label) => vec![*label],
+            Insn::TableSwitch {
+                default, targets, ..
+            } => std::iter::once(*default)
+                .chain(targets.iter().copied())
+                .collect(),
+            Insn::LookupSwitch { default, pairs } => std::iter::once(*default)
+                .chain(pairs.iter().map(|(_, label)| *label))
+                .collect(),
+            _ => vec![],
+        };
+        if let Some(label) = used.iter().find(|label| label.0 >= positions.len()) {
+            return Err(invalid_label(*label, "label does not belong to this code"));
+        }
+    }
+    Ok((pcs, positions))
+}
+
+/// Appends the bytes of an instruction at `pc`.
+fn encode(
+    instruction: &Insn,
+    pc: u32,
+    index: u16,
+    widened: bool,
+    labels: &[u32],
+    bytes: &mut Vec<u8>,
+) -> Result<(), Box<dyn Error>> {
+    let offset = |label: &Label| labels[label.0] as i32 - pc as i32;
+    match instruction {
+        Insn::Label(_) => {}
+        Insn::Simple(opcode) => bytes.push(*opcode),
+        Insn::Push(opcode, value) => {
+            bytes.push(*opcode);
+            if *opcode == BIPUSH {
+                bytes.push(*value as u8);
+            } else {
+                bytes.extend_from_slice(&(*value as i16).to_be_bytes());
+            }
+        }
+        Insn::Ldc(constant) => {
+            if constant.is_wide() {
+                bytes.push(LDC2_W);
+                bytes.extend_from_slice(&index.to_be_bytes());
+            } else if index > 0xff {
+                bytes.push(LDC_W);
+                bytes.extend_from_slice(&index.to_be_bytes());
+            } else {
+                bytes.extend_from_slice(&[LDC, index as u8]);
+            }
+        }
+        Insn::Local(opcode, local) => {
+            if *local > 0xff {
+                bytes.extend_from_slice(&[WIDE, *opcode]);
+                bytes.extend_from_slice(&local.to_be_bytes());
+            } else {
+                bytes.extend_from_slice(&[*opcode, *local as u8]);
+            }
+        }
+        Insn::Increment { index, value } => {
+            if *index > 0xff || i8::try_from(*value).is_err() {
+                bytes.extend_from_slice(&[WIDE, IINC]);
+                bytes.extend_from_slice(&index.to_be_bytes());
+                bytes.extend_from_slice(&value.to_be_bytes());
+            } else {
+                bytes.extend_from_slice(&[IINC, *index as u8, *value as u8]);
+            }
+        }
+        Insn::Jump(opcode, label) => match *opcode {
+            GOTO_W | JSR_W => {
+                bytes.push(*opcode);
+                bytes.extend_from_slice(&offset(label).to_be_bytes());
+            }
+            GOTO | JSR if widened => {
+                bytes.push(if *opcode == GOTO { GOTO_W } else { JSR_W });
+                bytes.extend_from_slice(&offset(label).to_be_bytes());
+            }
+            _ if widened => {
+                // if<not cond> over the goto_w that follows it.
+                bytes.push(invert(*opcode));
+                bytes.extend_from_slice(&8i16.to_be_bytes());
+                bytes.push(GOTO_W);
+                bytes.extend_from_slice(&(offset(label) - 3).to_be_bytes());
+            }
+            _ => {
+                bytes.push(*opcode);
+                bytes.extend_from_slice(&(offset(label) as i16).to_be_bytes());
+            }
+        },
+        Insn::TableSwitch {
+            default,
+            low,
+            targets,
+        } => {
+            bytes.push(TABLESWITCH);
+            bytes.resize(bytes.len() + (3 - pc % 4) as usize, 0);
+            let high = *low as i64 + targets.len() as i64 - 1;
+            let high = i32::try_from(high).map_err(|_| {
+                WritingError::new(
+                    WritingCause::InvalidInstruction(TABLESWITCH),
+                    "tableswitch has more targets than keys above low",
+                )
+            })?;
+            bytes.extend_from_slice(&offset(default).to_be_bytes());
+            bytes.extend_from_slice(&low.to_be_bytes());
+            bytes.extend_from_slice(&high.to_be_bytes());
+            for target in targets {
+                bytes.extend_from_slice(&offset(target).to_be_bytes());
+            }
+        }
+        Insn::LookupSwitch { default, pairs } => {
+            bytes.push(LOOKUPSWITCH);
+            bytes.resize(bytes.len() + (3 - pc % 4) as usize, 0);
+            bytes.extend_from_slice(&offset(default).to_be_bytes());
+            bytes.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+            let mut pairs = pairs.clone();
+            pairs.sort_by_key(|(key, _)| *key);
+            for (key, target) in &pairs {
+                bytes.extend_from_slice(&key.to_be_bytes());
+                bytes.extend_from_slice(&offset(target).to_be_bytes());
+            }
+        }
+        Insn::Field { opcode, .. } | Insn::Type(opcode, _) => {
+            bytes.push(*opcode);
+            bytes.extend_from_slice(&index.to_be_bytes());
+        }
+        Insn::Method {
+            opcode, descriptor, ..
+        } => {
+            bytes.push(*opcode);
+            bytes.extend_from_slice(&index.to_be_bytes());
+            if *opcode == INVOKEINTERFACE {
+                // The count is the size of the arguments including the receiver.
+                let count = MethodDescriptor::from_descriptor(descriptor)?.get_parameter_size() + 1;
+                bytes.extend_from_slice(&[count as u8, 0]);
+            }
+        }
+        Insn::InvokeDynamic { .. } => {
+            bytes.push(INVOKEDYNAMIC);
+            bytes.extend_from_slice(&index.to_be_bytes());
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        Insn::NewArray(atype) => bytes.extend_from_slice(&[NEWARRAY, *atype]),
+        Insn::MultiANewArray(_, dimensions) => {
+            bytes.push(MULTIANEWARRAY);
+            bytes.extend_from_slice(&index.to_be_bytes());
+            bytes.push(*dimensions);
+        }
+    }
+    Ok(())
+}
+
+/// The conditional branch taken exactly when `opcode` is not.
+fn invert(opcode: u8) -> u8 {
+    match opcode {
+        // ifeq/ifne, iflt/ifge, ..., if_acmpeq/if_acmpne come in pairs from ifeq on.
+        IFEQ..=IF_ACMPNE => ((opcode - IFEQ) ^ 1) + IFEQ,
+        // ifnull and ifnonnull.
+        _ => opcode ^ 1,
+    }
+}
+
+/// Whether an opcode is followed by operands in the code array.
+fn has_operands(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        BIPUSH..=ALOAD
+            | ISTORE..=ASTORE
+            | IINC
+            | IFEQ..=LOOKUPSWITCH
+            | GETSTATIC..=NEWARRAY
+            | ANEWARRAY
+            | CHECKCAST
+            | INSTANCEOF
+            | WIDE..=JSR_W
+    )
+}
+
+fn check_opcode(opcode: u8, valid: bool) -> Result<(), Box<dyn Error>> {
+    if valid {
+        return Ok(());
+    }
+    Err(Box::new(WritingError::new(
+        WritingCause::InvalidInstruction(opcode),
+        &format!(
+            "{} cannot be written with these operands",
+            get_mnemonic(opcode).unwrap_or("unknown opcode")
+        ),
+    )))
+}
+
+fn invalid_label(label: Label, msg: &str) -> Box<dyn Error> {
+    Box::new(WritingError::new(WritingCause::InvalidLabel(label.0), msg))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const OBJECT: &str = "java/lang/Object";
+    const PUBLIC: &[ClassAccessFlags] = &[ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper];
+
+    /**
+     * A class called `name` that extends `super_name` and implements `interfaces`.\
+     *  `java/lang/Object` itself is given no superclass.
+     */
+    pub(crate) fn class(
+        flags: &[ClassAccessFlags],
+        name: &str,
+        super_name: &str,
+        interfaces: &[&str],
+    ) -> ClassBuilder {
+        let super_name = Some(super_name).filter(|_| name != "java/lang/Object");
+        let mut class = ClassBuilder::new(flags, name, super_name).unwrap();
+        for interface in interfaces {
+            class.add_interface(interface).unwrap();
+        }
+        class
+    }
+
+    /// Builds `class` and reads it back from its bytes.
+    pub(crate) fn read_back(class: ClassBuilder) -> ClassFile {
+        ClassFile::from_bytes(&class.into_bytes().unwrap()).unwrap()
+    }
+
+    /// A public class `name` whose only method is `static run` with `descriptor` and `code`.
+    pub(crate) fn single_method(name: &str, descriptor: &str, code: CodeBuilder) -> ClassBuilder {
+        let mut class = class(PUBLIC, name, OBJECT, &[]);
+        let mut method = MethodBuilder::new(&[MethodAccessFlags::AccStatic], "run", descriptor);
+        method.set_code(code);
+        class.add_method(method).unwrap();
+        class
+    }
+
+    /**
+     * The code of `static int run(int n) { int sum = 0; for (int i = 0; i < n; i++) sum += i;
+     * return sum; }`, with the loop test at the top.
+     */
+    pub(crate) fn sum_loop() -> CodeBuilder {
+        let mut code = CodeBuilder::new(2, 3);
+        let (test, exit) = (code.new_label(), code.new_label());
+        code.push(Insn::Simple(ICONST_0));
+        code.push(Insn::Local(ISTORE, 1));
+        code.push(Insn::Simple(ICONST_0));
+        code.push(Insn::Local(ISTORE, 2));
+        code.push(Insn::Label(test));
+        code.push(Insn::Local(ILOAD, 2));
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::Jump(IF_ICMPGE, exit));
+        code.push(Insn::Local(ILOAD, 1));
+        code.push(Insn::Local(ILOAD, 2));
+        code.push(Insn::Simple(IADD));
+        code.push(Insn::Local(ISTORE, 1));
+        code.push(Insn::Increment { index: 2, value: 1 });
+        code.push(Insn::Jump(GOTO, test));
+        code.push(Insn::Label(exit));
+        code.push(Insn::Local(ILOAD, 1));
+        code.push(Insn::Simple(IRETURN));
+        code
+    }
+
+    /// The code of the first method called `name`, decoded.
+    fn code_of(class: &ClassFile, name: &str) -> (Code, Vec<Instruction>) {
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.get_name(&class.constant_pool) == name)
+            .unwrap();
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Code(code) => Some(code.clone()),
+                _ => None,
+            })
+            .unwrap();
+        let instructions = decode(&code.code).unwrap();
+        (code, instructions)
+    }
+
+    #[test]
+    fn test_build_class() {
+        let mut class = class(PUBLIC, "com/example/Counter", OBJECT, &[]);
+        class.set_source_file("Counter.java").unwrap();
+        let mut limit = FieldBuilder::new(
+            &[
+                FieldAccessFlags::AccPublic,
+                FieldAccessFlags::AccStatic,
+                FieldAccessFlags::AccFinal,
+            ],
+            "LIMIT",
+            "I",
+        );
+        limit.set_constant_value(Constant::Integer(100_000));
+        class.add_field(limit).unwrap();
+
+        // static int count() { int i = 0; try { while (i < LIMIT) i++; } catch (Error e) {}
+        // return i; }
+        let mut code = CodeBuilder::new(2, 2);
+        let (start, test, end, handler, exit) = (
+            code.new_label(),
+            code.new_label(),
+            code.new_label(),
+            code.new_label(),
+            code.new_label(),
+        );
+        code.push(Insn::Simple(ICONST_0));
+        code.push(Insn::Local(ISTORE, 1));
+        code.push(Insn::Label(start));
+        code.add_line_number(start, 3);
+        code.push(Insn::Jump(GOTO, test));
+        let body = code.new_label();
+        code.push(Insn::Label(body));
+        code.push(Insn::Increment { index: 1, value: 1 });
+        code.push(Insn::Label(test));
+        code.push(Insn::Local(ILOAD, 1));
+        code.push(Insn::Ldc(Constant::Integer(100_000)));
+        code.push(Insn::Jump(IF_ICMPLT, body));
+        code.push(Insn::Label(end));
+        code.push(Insn::Jump(GOTO, exit));
+        code.push(Insn::Label(handler));
+        code.push(Insn::Simple(POP));
+        code.push(Insn::Label(exit));
+        code.push(Insn::Local(ILOAD, 1));
+        code.push(Insn::Simple(IRETURN));
+        code.add_exception_handler(start, end, handler, Some("java/lang/Error"));
+        let mut method = MethodBuilder::new(
+            &[MethodAccessFlags::AccPublic, MethodAccessFlags::AccStatic],
+            "count",
+            "()I",
+        );
+        method.set_code(code);
+        method.add_exception("java/lang/Exception");
+        class.add_method(method).unwrap();
+
+        let bytes = class.into_bytes().unwrap();
+        let class = ClassFile::from_bytes(&bytes).unwrap();
+        assert_eq!(class.get_name(), "com.example.Counter");
+        assert_eq!(class.to_bytes().unwrap(), bytes);
+        assert!(matches!(
+            class.fields[0].attributes[0],
+            AttributeInfo::ConstantValue(_)
+        ));
+
+        let (code, instructions) = code_of(&class, "count");
+        let opcodes: Vec<u8> = instructions.iter().map(|i| i.opcode).collect();
+        assert_eq!(
+            opcodes,
+            [ICONST_0, ISTORE, GOTO, IINC, ILOAD, LDC, IF_ICMPLT, GOTO, POP, ILOAD, IRETURN]
+        );
+        // The loop's back edge goes to the iinc, and the forward goto to the test.
+        assert_eq!(instructions[2].operands, Operands::Branch(9));
+        assert_eq!(instructions[6].operands, Operands::Branch(6));
+        assert_eq!(code.exception_tables.len(), 1);
+        assert!(matches!(
+            code.attributes[0],
+            AttributeInfo::LineNumberTable(_)
+        ));
+    }
+
+    #[test]
+    fn test_widen_far_branches() {
+        let mut class = class(PUBLIC, "Far", OBJECT, &[]);
+        let mut code = CodeBuilder::new(1, 1);
+        let end = code.new_label();
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::Jump(IFEQ, end));
+        for _ in 0..40_000 {
+            code.push(Insn::Simple(NOP));
+        }
+        code.push(Insn::Label(end));
+        code.push(Insn::Local(ILOAD, 300));
+        code.push(Insn::Simple(IRETURN));
+        let mut method = MethodBuilder::new(&[MethodAccessFlags::AccStatic], "far", "(I)I");
+        method.set_code(code);
+        class.add_method(method).unwrap();
+
+        let class = class.build().unwrap();
+        let (_, instructions) = code_of(&class, "far");
+        // ifne skips the goto_w that replaces the ifeq.
+        assert_eq!(instructions[1].opcode, IFNE);
+        assert_eq!(instructions[1].operands, Operands::Branch(2 + 8));
+        assert_eq!(instructions[2].opcode, GOTO_W);
+        assert_eq!(instructions[2].operands, Operands::Branch(10 + 40_000));
+        let load = &instructions[instructions.len() - 2];
+        assert!(load.wide);
+        assert_eq!(load.operands, Operands::Local(300));
+    }
+
+    #[test]
+    fn test_reject_bad_instructions() {
+        let mut class = class(PUBLIC, "Bad", OBJECT, &[]);
+        let mut code = CodeBuilder::new(1, 1);
+        code.push(Insn::Push(BIPUSH, 1000));
+        let mut method = MethodBuilder::new(&[], "bad", "()V");
+        method.set_code(code);
+        assert!(class.add_method(method).is_err());
+
+        let mut code = CodeBuilder::new(1, 1);
+        let label = code.new_label();
+        code.push(Insn::Jump(GOTO, label));
+        let mut method = MethodBuilder::new(&[], "unplaced", "()V");
+        method.set_code(code);
+        assert!(class.add_method(method).is_err());
+    }
+
+    #[test]
+    fn test_intern_constants() {
+        let mut pool = ConstantPoolBuilder::new();
+        let method = pool
+            .add_method_ref("java/lang/Object", "<init>", "()V", false)
+            .unwrap();
+        assert_eq!(
+            pool.add_method_ref("java/lang/Object", "<init>", "()V", false)
+                .unwrap(),
+            method
+        );
+        let long = pool.add_long(1).unwrap();
+        // A long takes two entries, so the next constant skips one.
+        assert_eq!(pool.add_integer(1).unwrap(), long + 2);
+        assert_eq!(pool.add_long(1).unwrap(), long);
+    }
+
+    #[test]
+    fn test_utf8_encoding() {
+        // NUL takes two bytes and a supplementary character is a surrogate pair of three each.
+        let utf8 = constants::Utf8::from("a\0é😀");
+        assert_eq!(
+            utf8.bytes,
+            vec![0x61, 0xC0, 0x80, 0xC3, 0xA9, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]
+        );
+        assert_eq!(utf8.length as usize, utf8.bytes.len());
+        assert_eq!(String::from_utf16_lossy(&utf8.get_utf16()), "a\0é😀");
+        // 65535 bytes fit in the length item, one more does not.
+        let mut pool = ConstantPoolBuilder::new();
+        assert!(pool.add_utf8(&"a".repeat(65535)).is_ok());
+        assert!(pool.add_utf8(&"a".repeat(65536)).is_err());
+    }
+}
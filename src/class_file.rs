@@ -8,7 +8,7 @@ use crate::attributes;
 use crate::constants::{self, Utf8};
 use crate::errors::{
     class_format_check::{FormatCause, FormatError},
-    class_loading::{LoadingCause, LoadingError},
+    class_writing::{WritingCause, WritingError},
 };
 
 /// [The Constant Pool](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A2201%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C256%2Cnull%5D)
@@ -67,7 +67,58 @@ pub enum AttributeInfo {
     NestMembers(attributes::NestMembers),
     Record(attributes::Record),
     PermittedSubclasses(attributes::PermittedSubclasses),
-    Unknown(String),
+    ScalaSig(attributes::ScalaSig),
+    Scala(attributes::Scala),
+    ScalaInlineInfo(attributes::ScalaInlineInfo),
+    Tasty(attributes::Tasty),
+    Unknown(attributes::Unknown),
+}
+
+impl AttributeInfo {
+    /// The attribute_name the attribute is stored under in the class file.
+    pub fn get_name(&self) -> &str {
+        match self {
+            AttributeInfo::ConstantValue(_) => "ConstantValue",
+            AttributeInfo::Code(_) => "Code",
+            AttributeInfo::StackMapTable(_) => "StackMapTable",
+            AttributeInfo::Exceptions(_) => "Exceptions",
+            AttributeInfo::InnerClasses(_) => "InnerClasses",
+            AttributeInfo::EnclosingMethod(_) => "EnclosingMethod",
+            AttributeInfo::Synthetic(_) => "Synthetic",
+            AttributeInfo::Signature(_) => "Signature",
+            AttributeInfo::SourceFile(_) => "SourceFile",
+            AttributeInfo::SourceDebugExtension(_) => "SourceDebugExtension",
+            AttributeInfo::LineNumberTable(_) => "LineNumberTable",
+            AttributeInfo::LocalVariableTable(_) => "LocalVariableTable",
+            AttributeInfo::LocalVariableTypeTable(_) => "LocalVariableTypeTable",
+            AttributeInfo::Deprecated(_) => "Deprecated",
+            AttributeInfo::RuntimeVisibleAnnotations(_) => "RuntimeVisibleAnnotations",
+            AttributeInfo::RuntimeInvisibleAnnotations(_) => "RuntimeInvisibleAnnotations",
+            AttributeInfo::RuntimeVisibleParameterAnnotations(_) => {
+                "RuntimeVisibleParameterAnnotations"
+            }
+            AttributeInfo::RuntimeInvisibleParameterAnnotations(_) => {
+                "RuntimeInvisibleParameterAnnotations"
+            }
+            AttributeInfo::RuntimeVisibleTypeAnnotations(_) => "RuntimeVisibleTypeAnnotations",
+            AttributeInfo::RuntimeInvisibleTypeAnnotations(_) => "RuntimeInvisibleTypeAnnotations",
+            AttributeInfo::AnnotationDefault(_) => "AnnotationDefault",
+            AttributeInfo::BootstrapMethods(_) => "BootstrapMethods",
+            AttributeInfo::MethodParameters(_) => "MethodParameters",
+            AttributeInfo::Module(_) => "Module",
+            AttributeInfo::ModulePackages(_) => "ModulePackages",
+            AttributeInfo::ModuleMainClass(_) => "ModuleMainClass",
+            AttributeInfo::NestHost(_) => "NestHost",
+            AttributeInfo::NestMembers(_) => "NestMembers",
+            AttributeInfo::Record(_) => "Record",
+            AttributeInfo::PermittedSubclasses(_) => "PermittedSubclasses",
+            AttributeInfo::ScalaSig(_) => "ScalaSig",
+            AttributeInfo::Scala(_) => "Scala",
+            AttributeInfo::ScalaInlineInfo(_) => "ScalaInlineInfo",
+            AttributeInfo::Tasty(_) => "TASTY",
+            AttributeInfo::Unknown(unknown) => unknown.get_name(),
+        }
+    }
 }
 
 /// [Fields](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A721%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C564%2Cnull%5D)
@@ -76,6 +127,8 @@ pub struct FieldInfo {
     pub(crate) access_flags: Vec<FieldAccessFlags>,
     pub(crate) name_index: u16,
     pub(crate) descriptor_index: u16,
+    /// As read; the writer counts `attributes` instead.
+    #[allow(dead_code)]
     pub(crate) attributes_count: u16,
     pub(crate) attributes: Vec<AttributeInfo>,
 }
@@ -100,6 +153,15 @@ impl FieldInfo {
         })
     }
 
+    pub fn get_name(&self, constant_pool: &[ConstantPool]) -> String {
+        constants::get_utf8(constant_pool, self.name_index).unwrap_or_default()
+    }
+
+    /// The descriptor, with class names in internal form, e.g. `Ljava/lang/String;`.
+    pub fn get_descriptor(&self, constant_pool: &[ConstantPool]) -> String {
+        constants::get_raw_utf8(constant_pool, self.descriptor_index).unwrap_or_default()
+    }
+
     pub fn get_type(&self, constant_pool: &[ConstantPool]) -> String {
         let mut descriptor = if let ConstantPool::Utf8(desc) =
             constant_pool[self.descriptor_index as usize].clone()
@@ -157,9 +219,18 @@ impl MethodInfo {
         })
     }
 
+    pub fn get_name(&self, constant_pool: &[ConstantPool]) -> String {
+        constants::get_utf8(constant_pool, self.name_index).unwrap_or_default()
+    }
+
+    /// The descriptor, with class names in internal form, e.g. `([Ljava/lang/String;)V`.
+    pub fn get_descriptor(&self, constant_pool: &[ConstantPool]) -> String {
+        constants::get_raw_utf8(constant_pool, self.descriptor_index).unwrap_or_default()
+    }
+
     pub fn to_pretty_fmt(self, constant_pool: &[ConstantPool]) -> String {
         let mut output = String::new();
-        output.push_str(&format!("MethodInfo {{\n"));
+        output.push_str("MethodInfo {\n");
         output.push_str(&format!("\tFlags: {:?}\n", self.access_flags));
         output.push_str(&format!(
             "\tName: {:?}\n",
@@ -171,7 +242,7 @@ impl MethodInfo {
         ));
         output.push_str(&format!("\tAttribute Count: {:?}\n", self.attributes_count));
         output.push_str(&format!("\tAttributes: {:#?}\n", self.attributes));
-        output.push_str(&format!("}}\n"));
+        output.push_str("}\n");
 
         output
     }
@@ -429,9 +500,9 @@ impl ClassFile {
         let major_version = cursor.read_u16::<BE>()?;
         let constant_pool_count = cursor.read_u16::<BE>()?;
         let constant_pool = {
-            let mut pool = Vec::with_capacity((constant_pool_count - 1) as usize);
+            let mut pool = Vec::with_capacity(constant_pool_count as usize + 1);
             pool.push(ConstantPool::Unknown);
-            constants::read_constant_pool(&mut pool, &mut cursor)?;
+            constants::read_constant_pool(&mut pool, constant_pool_count, &mut cursor)?;
             pool.push(ConstantPool::Utf8(Utf8::from("StackMapTable")));
             pool
         };
@@ -488,8 +559,7 @@ impl ClassFile {
             )?;
             attribs
         };
-        //FIXME: This isn't ideal, is_empty is nightly and requires a feature flag
-        if !cursor.is_empty() {
+        if cursor.position() < cursor.get_ref().len() as u64 {
             return Err(Box::new(FormatError::new(
                 FormatCause::ExtraBytes,
                 "class file has leftover bytes",
@@ -520,6 +590,86 @@ impl ClassFile {
         }
     }
 
+    /// Serializes the class file, the inverse of `from_bytes`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        crate::class_writer::ClassWriter::new(self).write()
+    }
+
+    /// Serializes the class file with its constant pool compacted, see `constant_pool::compact`.
+    pub fn to_compact_bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut class = self.clone();
+        crate::constant_pool::compact(&mut class)?;
+        class.to_bytes()
+    }
+
+    /**
+     * The binary name of the class or interface defined by this class file, in the dotted form
+     * of JLS 13.1 such as `java.util.Map$Entry`.\
+     *  The class file stores it in internal form, `java/util/Map$Entry`, which
+     *  [get_raw_class_name](constants::get_raw_class_name) gives.
+     */
+    pub fn get_name(&self) -> String {
+        constants::get_class_name(&self.constant_pool, self.this_class).unwrap_or_default()
+    }
+
+    /// The binary name of the direct superclass in dotted form, as [get_name](Self::get_name)
+    /// gives it, `None` for `java.lang.Object` and modules.
+    pub fn get_super_name(&self) -> Option<String> {
+        if self.super_class == 0 {
+            return None;
+        }
+        constants::get_class_name(&self.constant_pool, self.super_class)
+    }
+
+    /// The binary names of the direct superinterfaces in dotted form, in declaration order.
+    pub fn get_interface_names(&self) -> Vec<String> {
+        self.interfaces
+            .iter()
+            .filter_map(|index| constants::get_class_name(&self.constant_pool, *index))
+            .collect()
+    }
+
+    /**
+     * Appends `constant` to the constant pool, returning its index.\
+     *  The implicit StackMapTable attributes the reader adds, whose name index lies past the
+     *  end of the pool, are moved along with it.
+     */
+    pub(crate) fn add_constant(&mut self, constant: ConstantPool) -> Result<u16, Box<dyn Error>> {
+        let index = self.constant_pool_count;
+        // §4.4.5: a long or double takes two entries.
+        let size = match constant {
+            ConstantPool::Long(_) | ConstantPool::Double(_) => 2,
+            _ => 1,
+        };
+        if index as u32 + size as u32 > u16::MAX as u32 {
+            return Err(Box::new(WritingError::new(
+                WritingCause::TooLarge("constant_pool".into()),
+                "a constant pool holds at most 65534 entries",
+            )));
+        }
+        let at = (index as usize).min(self.constant_pool.len());
+        self.constant_pool.insert(at, constant);
+        if size == 2 {
+            self.constant_pool.insert(at + 1, ConstantPool::Unknown);
+        }
+        self.constant_pool_count += size;
+        for method in &mut self.methods {
+            for attribute in &mut method.attributes {
+                if let AttributeInfo::Code(code) = attribute {
+                    for attribute in &mut code.attributes {
+                        if let AttributeInfo::StackMapTable(table) = attribute {
+                            if table.attribute_name_index >= index {
+                                table.attribute_name_index =
+                                    table.attribute_name_index.saturating_add(size);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(index)
+    }
+
     // TODO: Improve to_pretty_fmt to provide the value from index into constant pool
     pub fn to_pretty_fmt(&self) -> String {
         let mut output = String::new();
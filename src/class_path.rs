@@ -0,0 +1,518 @@
+use std::{
+    cell::OnceCell,
+    collections::{BTreeMap, HashMap},
+    env,
+    error::Error,
+    fs::{self, File},
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::class_file::ClassFile;
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+use crate::jar::{Jar, JarEntry};
+use crate::jimage::{ImageLocation, JImage};
+
+/// One place classes are looked up in, in the order they appear on the class path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClassPathEntry {
+    /// A directory of loose resources, with classes at their internal name plus `.class`.
+    Directory(PathBuf),
+    /// A jar or zip archive.
+    Jar(PathBuf),
+    /// A jimage, such as a JDK's `lib/modules`, searched across all of its modules.
+    Image(PathBuf),
+}
+
+impl ClassPathEntry {
+    /// Classifies a path by what is on disk: a directory, a jimage by its magic, or a jar.
+    pub fn from_path(path: &Path) -> ClassPathEntry {
+        if path.is_dir() {
+            return ClassPathEntry::Directory(path.into());
+        }
+        let mut magic = [0; 4];
+        let has_magic = File::open(path).and_then(|mut file| file.read_exact(&mut magic));
+        // A jimage is written in the byte order of the platform that linked it.
+        if has_magic.is_ok()
+            && [[0xda, 0xda, 0xfe, 0xca], [0xca, 0xfe, 0xda, 0xda]].contains(&magic)
+        {
+            ClassPathEntry::Image(path.into())
+        } else {
+            ClassPathEntry::Jar(path.into())
+        }
+    }
+
+    pub fn get_path(&self) -> &Path {
+        match self {
+            ClassPathEntry::Directory(path)
+            | ClassPathEntry::Jar(path)
+            | ClassPathEntry::Image(path) => path,
+        }
+    }
+}
+
+/// Where a resource was found and its contents.
+#[derive(Clone, Debug)]
+pub struct Resource {
+    /// The index of the class path entry the resource was found in.
+    pub entry: usize,
+    /// A URL for the resource, such as `jar:file:/lib/a.jar!/A.class` or `jrt:/java.base/...`.
+    pub location: String,
+    pub bytes: Vec<u8>,
+}
+
+/// A resource that is found in more than one entry, so only the first can ever be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shadowed {
+    pub name: String,
+    /// The entry that is used.
+    pub entry: usize,
+    /// The later entries that are hidden by it.
+    pub shadowed: Vec<usize>,
+}
+
+/// An entry once opened, with its resources indexed by name.
+#[derive(Debug)]
+enum Archive {
+    Directory,
+    Jar(Jar, HashMap<String, JarEntry>),
+    Image(JImage, HashMap<String, ImageLocation>),
+}
+
+/**
+ * An ordered list of directories, jars and jimages that resources are looked up in, as
+ * `-classpath` is for the java launcher. Archives are opened the first time they are
+ * searched and kept open after that.
+ */
+#[derive(Debug, Default)]
+pub struct ClassPath {
+    entries: Vec<ClassPathEntry>,
+    /// The Java version multi-release jars are read for; all of their entries are seen as
+    /// they are if this is not set.
+    release: Option<u16>,
+    /// The opened archives or the reason they could not be opened, by entry.
+    archives: Vec<OnceCell<Result<Archive, String>>>,
+}
+
+impl ClassPath {
+    pub fn new() -> ClassPath {
+        ClassPath::default()
+    }
+
+    /**
+     * Parses a class path separated the way the platform separates `PATH`. Entries that do
+     * not exist are left out, as the java launcher ignores them.
+     */
+    pub fn parse(class_path: &str) -> ClassPath {
+        let mut parsed = ClassPath::new();
+        for path in env::split_paths(class_path) {
+            if !path.as_os_str().is_empty() && path.exists() {
+                parsed.push(ClassPathEntry::from_path(&path));
+            }
+        }
+        parsed
+    }
+
+    pub fn push(&mut self, entry: ClassPathEntry) {
+        self.entries.push(entry);
+        self.archives.push(OnceCell::new());
+    }
+
+    pub fn set_release(&mut self, release: u16) {
+        self.release = Some(release);
+        // Multi-release jars are indexed for a single release.
+        self.archives = self.entries.iter().map(|_| OnceCell::new()).collect();
+    }
+
+    pub fn get_entries(&self) -> &[ClassPathEntry] {
+        &self.entries
+    }
+
+    /// The first resource called `name` on the class path, such as `java/lang/String.class`.
+    pub fn find_resource(&self, name: &str) -> Result<Option<Resource>, Box<dyn Error>> {
+        for entry in 0..self.entries.len() {
+            if let Some(resource) = self.find_in_entry(entry, name)? {
+                return Ok(Some(resource));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The class file for an internal name like `java/lang/String`.
+    pub fn find_class(&self, internal_name: &str) -> Result<Option<Resource>, Box<dyn Error>> {
+        self.find_resource(&format!("{internal_name}.class"))
+    }
+
+    pub fn load_class(&self, internal_name: &str) -> Result<Option<ClassFile>, Box<dyn Error>> {
+        match self.find_class(internal_name)? {
+            Some(resource) => Ok(Some(ClassFile::from_bytes(&resource.bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Every class in an entry, failing on the first that cannot be read.
+    pub fn load_classes(&self, entry: usize) -> Result<Vec<ClassFile>, Box<dyn Error>> {
+        let mut classes = vec![];
+        for name in self.get_resource_names(entry)? {
+            let Some(internal_name) = name.strip_suffix(".class") else {
+                continue;
+            };
+            if internal_name.ends_with("module-info") || internal_name.starts_with("META-INF/") {
+                continue;
+            }
+            if let Some(resource) = self.find_in_entry(entry, &name)? {
+                let class = ClassFile::from_bytes(&resource.bytes)
+                    .map_err(|error| format!("{}: {error}", resource.location))?;
+                classes.push(class);
+            }
+        }
+        Ok(classes)
+    }
+
+    /**
+     * The descriptor of a module in the first jimage that has it, which the class path cannot
+     * find by name since every module has a `module-info.class`.
+     */
+    pub fn load_module_info(&self, module: &str) -> Result<Option<ClassFile>, Box<dyn Error>> {
+        for entry in 0..self.entries.len() {
+            if let Archive::Image(image, _) = self.get_archive(entry)? {
+                if let Some(class) = image.get_class(module, "module-info")? {
+                    return Ok(Some(class));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// The names of the resources in an entry, sorted.
+    pub fn get_resource_names(&self, entry: usize) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut names: Vec<String> = match self.get_archive(entry)? {
+            Archive::Directory => {
+                let root = self.entries[entry].get_path();
+                let mut names = Vec::new();
+                list_directory(root, root, &mut names)?;
+                names
+            }
+            Archive::Jar(_, index) => index
+                .iter()
+                .filter(|(_, jar_entry)| !jar_entry.is_directory())
+                .map(|(name, _)| name.clone())
+                .collect(),
+            Archive::Image(_, index) => index.keys().cloned().collect(),
+        };
+        names.sort();
+        Ok(names)
+    }
+
+    /// Every resource that more than one entry provides, sorted by name.
+    pub fn get_shadowed(&self) -> Result<Vec<Shadowed>, Box<dyn Error>> {
+        let mut providers: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for entry in 0..self.entries.len() {
+            for name in self.get_resource_names(entry)? {
+                providers.entry(name).or_default().push(entry);
+            }
+        }
+        Ok(providers
+            .into_iter()
+            .filter(|(_, entries)| entries.len() > 1)
+            .map(|(name, entries)| Shadowed {
+                name,
+                entry: entries[0],
+                shadowed: entries[1..].to_vec(),
+            })
+            .collect())
+    }
+
+    fn find_in_entry(&self, entry: usize, name: &str) -> Result<Option<Resource>, Box<dyn Error>> {
+        let path = self.entries[entry].get_path();
+        let (location, bytes) = match self.get_archive(entry)? {
+            Archive::Directory => {
+                // Only plain relative names, so that a lookup cannot leave the directory.
+                let is_relative = Path::new(name)
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)));
+                let file = path.join(name);
+                if !is_relative || !file.is_file() {
+                    return Ok(None);
+                }
+                (file.display().to_string(), fs::read(&file)?)
+            }
+            Archive::Jar(jar, index) => match index.get(name) {
+                Some(jar_entry) => (
+                    format!("jar:file:{}!/{}", path.display(), jar_entry.get_name()),
+                    jar.read_entry(jar_entry)?,
+                ),
+                None => return Ok(None),
+            },
+            Archive::Image(image, index) => match index.get(name) {
+                Some(location) => (
+                    format!("jrt:{}", location.get_full_name()),
+                    image.read_resource(location)?,
+                ),
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(Resource {
+            entry,
+            location,
+            bytes,
+        }))
+    }
+
+    fn get_archive(&self, entry: usize) -> Result<&Archive, Box<dyn Error>> {
+        let archive = self.archives[entry].get_or_init(|| {
+            self.open(&self.entries[entry])
+                .map_err(|error| error.to_string())
+        });
+        archive.as_ref().map_err(|msg| {
+            let path = self.entries[entry].get_path().display().to_string();
+            Box::new(LoadingError::new(LoadingCause::InvalidClassPath(path), msg)).into()
+        })
+    }
+
+    fn open(&self, entry: &ClassPathEntry) -> Result<Archive, Box<dyn Error>> {
+        match entry {
+            ClassPathEntry::Directory(_) => Ok(Archive::Directory),
+            ClassPathEntry::Jar(path) => {
+                let jar = Jar::open(path)?;
+                let index = match self.release {
+                    Some(version) => jar
+                        .get_versioned_entries(version)?
+                        .into_iter()
+                        .map(|(name, entry)| (name.to_string(), entry.clone()))
+                        .collect(),
+                    None => jar
+                        .get_entries()
+                        .iter()
+                        .map(|entry| (entry.get_name().to_string(), entry.clone()))
+                        .collect(),
+                };
+                Ok(Archive::Jar(jar, index))
+            }
+            ClassPathEntry::Image(path) => {
+                let image = JImage::open(path)?;
+                let mut index = HashMap::new();
+                for location in image.get_locations()? {
+                    // Packages belong to a single module, so names only collide for the
+                    // module-info.class every module has, which load_module_info finds.
+                    let name = location.get_resource_name();
+                    if location.is_resource() && name != "module-info.class" {
+                        index.insert(name, location);
+                    }
+                }
+                Ok(Archive::Image(image, index))
+            }
+        }
+    }
+}
+
+/**
+ * Collects the files below `directory` as `/` separated names relative to `root`. Symbolic
+ * links to directories are not followed, since one may link back to a directory above it.
+ */
+fn list_directory(
+    root: &Path,
+    directory: &Path,
+    names: &mut Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    for dir_entry in fs::read_dir(directory)? {
+        let dir_entry = dir_entry?;
+        let path = dir_entry.path();
+        if dir_entry.file_type()?.is_dir() {
+            list_directory(root, &path, names)?;
+        } else if path.is_dir() {
+            continue;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let parts: Vec<String> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            names.push(parts.join("/"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jar::tests::{write_zip, EMPTY_CLASS};
+    use crate::jimage::tests::write_image;
+
+    fn scratch_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("jvm_rs_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn write(path: &Path, bytes: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_in_order() {
+        let root = scratch_directory("class_path");
+        let first = root.join("first");
+        let second = root.join("second");
+        write(&first.join("a/A.class"), b"first A");
+        write(&second.join("a/A.class"), b"second A");
+        write(&second.join("a/B.class"), b"second B");
+        write(&second.join("a/config.properties"), b"key=value");
+        fs::write(root.join("missing.jar"), b"not a zip").unwrap();
+
+        let mut class_path = ClassPath::new();
+        class_path.push(ClassPathEntry::from_path(&first));
+        class_path.push(ClassPathEntry::from_path(&second));
+        assert_eq!(
+            class_path.get_entries()[1],
+            ClassPathEntry::Directory(second.clone())
+        );
+
+        let a = class_path.find_class("a/A").unwrap().unwrap();
+        assert_eq!((a.entry, a.bytes.as_slice()), (0, &b"first A"[..]));
+        let b = class_path.find_class("a/B").unwrap().unwrap();
+        assert_eq!(b.entry, 1);
+        let properties = class_path.find_resource("a/config.properties").unwrap();
+        assert_eq!(properties.unwrap().bytes, b"key=value");
+        assert!(class_path.find_class("a/C").unwrap().is_none());
+
+        let shadowed = class_path.get_shadowed().unwrap();
+        assert_eq!(
+            shadowed,
+            [Shadowed {
+                name: "a/A.class".into(),
+                entry: 0,
+                shadowed: vec![1],
+            }]
+        );
+
+        // A broken archive is reported when it is reached, and on every later lookup.
+        class_path.push(ClassPathEntry::from_path(&root.join("missing.jar")));
+        assert!(class_path.find_class("a/A").unwrap().is_some());
+        assert!(class_path.find_class("a/C").is_err());
+        assert!(class_path.find_class("a/C").is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_find_in_jar() {
+        let root = scratch_directory("class_path_jar");
+        let jar = root.join("lib.jar");
+        // Stored and deflated entries, with a directory entry that is not a resource.
+        let zip = write_zip(
+            &[
+                ("META-INF/", 0, b""),
+                ("A.class", 0, &EMPTY_CLASS),
+                ("a/config.properties", 8, b"key=value"),
+            ],
+            false,
+        );
+        write(&jar, &zip);
+
+        // The missing entry is left out rather than failing every lookup.
+        let paths = [jar.clone(), root.join("missing.jar")];
+        let mut class_path = ClassPath::parse(env::join_paths(paths).unwrap().to_str().unwrap());
+        assert_eq!(class_path.get_entries(), [ClassPathEntry::Jar(jar.clone())]);
+
+        let a = class_path.find_class("A").unwrap().unwrap();
+        assert_eq!(a.location, format!("jar:file:{}!/A.class", jar.display()));
+        assert_eq!((a.entry, a.bytes.as_slice()), (0, &EMPTY_CLASS[..]));
+        let properties = class_path.find_resource("a/config.properties").unwrap();
+        assert_eq!(properties.unwrap().bytes, b"key=value");
+        assert_eq!(
+            class_path.get_resource_names(0).unwrap(),
+            ["A.class", "a/config.properties"]
+        );
+        let classes = class_path.load_classes(0).unwrap();
+        assert_eq!(classes.len(), 1);
+        assert_eq!(classes[0].get_name(), "A");
+
+        // The jar was read once, and is only read again when the release changes.
+        fs::remove_file(&jar).unwrap();
+        assert!(class_path.find_class("A").unwrap().is_some());
+        class_path.set_release(17);
+        assert!(class_path.find_class("A").is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_find_in_image() {
+        let root = scratch_directory("class_path_image");
+        let modules = root.join("modules");
+        let image = write_image(&[
+            (
+                "java.base",
+                "java/lang",
+                "Object",
+                "class",
+                &EMPTY_CLASS,
+                false,
+            ),
+            ("java.base", "", "module-info", "class", &EMPTY_CLASS, false),
+            ("java.sql", "java/sql", "Date", "class", &EMPTY_CLASS, true),
+            ("java.sql", "java/sql", "Time", "class", &EMPTY_CLASS, false),
+            ("java.sql", "", "module-info", "class", &EMPTY_CLASS, true),
+        ]);
+        write(&modules, &image);
+
+        let mut class_path = ClassPath::new();
+        class_path.push(ClassPathEntry::from_path(&modules));
+        assert_eq!(class_path.get_entries(), [ClassPathEntry::Image(modules)]);
+
+        let object = class_path.find_class("java/lang/Object").unwrap().unwrap();
+        assert_eq!(object.location, "jrt:/java.base/java/lang/Object.class");
+        assert_eq!(object.bytes, EMPTY_CLASS);
+        let date = class_path.find_class("java/sql/Date").unwrap().unwrap();
+        assert_eq!(date.bytes, EMPTY_CLASS);
+        // Every module has a module-info.class, so it is looked up by module instead.
+        assert_eq!(
+            class_path.get_resource_names(0).unwrap(),
+            [
+                "java/lang/Object.class",
+                "java/sql/Date.class",
+                "java/sql/Time.class"
+            ]
+        );
+        assert!(class_path
+            .find_resource("module-info.class")
+            .unwrap()
+            .is_none());
+        assert!(class_path.load_module_info("java.sql").unwrap().is_some());
+        assert!(class_path.load_module_info("java.xml").unwrap().is_none());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_stay_inside_directories() {
+        let root = scratch_directory("class_path_names");
+        let directory = root.join("classes");
+        write(&directory.join("a/A.class"), b"A");
+        write(&root.join("Secret.class"), b"secret");
+
+        let mut class_path = ClassPath::new();
+        class_path.push(ClassPathEntry::Directory(directory));
+        assert!(class_path.find_class("a/A").unwrap().is_some());
+        assert!(class_path.find_class("../Secret").unwrap().is_none());
+        assert!(class_path.find_class("a/../../Secret").unwrap().is_none());
+        let absolute = root.join("Secret.class").display().to_string();
+        assert!(class_path.find_resource(&absolute).unwrap().is_none());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_skip_linked_directories() {
+        let root = scratch_directory("class_path_links");
+        write(&root.join("a/A.class"), b"A");
+        write(&root.join("B.class"), b"B");
+        std::os::unix::fs::symlink(&root, root.join("a/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("B.class"), root.join("a/B.class")).unwrap();
+
+        let mut class_path = ClassPath::new();
+        class_path.push(ClassPathEntry::Directory(root.clone()));
+        let names = class_path.get_resource_names(0).unwrap();
+        assert_eq!(names, ["B.class", "a/A.class", "a/B.class"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
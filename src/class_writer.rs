@@ -0,0 +1,696 @@
+use std::{collections::HashMap, error::Error};
+
+use crate::access_flags::{
+    ClassAccessFlags, FieldAccessFlags, MethodAccessFlags, ModuleFlags, ParameterAccessFlags,
+};
+use crate::attributes::{
+    Annotation, Element, StackMapFrame, TargetInfo, TypeAnnotation, VerificationTypeInfo,
+};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::constants::Tags;
+use crate::errors::class_writing::{WritingCause, WritingError};
+
+/**
+ * Serializes a [ClassFile] into the class file format, the inverse of `ClassFile::from_bytes`.
+ *
+ * Counts and attribute lengths are computed from what is being written rather than taken
+ * from the structures, so a class can be changed before it is written. Attribute names are
+ * looked up in the constant pool, which must hold a Utf8 entry for each of them.
+ */
+pub struct ClassWriter<'a> {
+    class: &'a ClassFile,
+    /// The index of the first Utf8 entry holding each string.
+    utf8_indices: HashMap<String, u16>,
+    bytes: Vec<u8>,
+}
+
+impl<'a> ClassWriter<'a> {
+    pub fn new(class: &'a ClassFile) -> ClassWriter<'a> {
+        let mut utf8_indices = HashMap::new();
+        for index in 1..class.constant_pool_count {
+            if let Some(ConstantPool::Utf8(utf8)) = class.constant_pool.get(index as usize) {
+                utf8_indices.entry(utf8.get_raw_string()).or_insert(index);
+            }
+        }
+        ClassWriter {
+            class,
+            utf8_indices,
+            bytes: Vec::new(),
+        }
+    }
+
+    pub fn write(mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let class = self.class;
+        self.u4(class.magic);
+        self.u2(class.minor_version);
+        self.u2(class.major_version);
+        self.u2(class.constant_pool_count);
+        // Entry 0 and the entries following a long or double are not written.
+        for constant in class
+            .constant_pool
+            .iter()
+            .take(class.constant_pool_count as usize)
+            .skip(1)
+        {
+            write_constant(constant, &mut self.bytes)?;
+        }
+        self.u2(ClassAccessFlags::to_u16(&class.access_flags));
+        self.u2(class.this_class);
+        self.u2(class.super_class);
+        self.count(class.interfaces.len(), "interfaces")?;
+        for interface in &class.interfaces {
+            self.u2(*interface);
+        }
+        self.count(class.fields.len(), "fields")?;
+        for field in &class.fields {
+            self.u2(FieldAccessFlags::to_u16(&field.access_flags));
+            self.u2(field.name_index);
+            self.u2(field.descriptor_index);
+            self.write_attributes(&field.attributes)?;
+        }
+        self.count(class.methods.len(), "methods")?;
+        for method in &class.methods {
+            self.u2(MethodAccessFlags::to_u16(&method.access_flags));
+            self.u2(method.name_index);
+            self.u2(method.descriptor_index);
+            self.write_attributes(&method.attributes)?;
+        }
+        self.write_attributes(&class.attributes)?;
+        Ok(self.bytes)
+    }
+
+    fn write_attributes(&mut self, attributes: &[AttributeInfo]) -> Result<(), Box<dyn Error>> {
+        let attributes: Vec<&AttributeInfo> = attributes
+            .iter()
+            .filter(|attribute| !self.is_implicit(attribute))
+            .collect();
+        self.count(attributes.len(), "attributes")?;
+        for attribute in attributes {
+            let name = attribute.get_name();
+            let name_index = *self.utf8_indices.get(name).ok_or_else(|| {
+                WritingError::new(
+                    WritingCause::MissingConstant(name.into()),
+                    "attribute names must be in the constant pool",
+                )
+            })?;
+            self.u2(name_index);
+            let length_at = self.bytes.len();
+            self.u4(0);
+            self.write_attribute(attribute)?;
+            let length = (self.bytes.len() - length_at - 4) as u32;
+            self.bytes[length_at..length_at + 4].copy_from_slice(&length.to_be_bytes());
+        }
+        Ok(())
+    }
+
+    /// The body of `attribute`, everything after its attribute_length, as `write` writes it.
+    pub(crate) fn write_attribute_body(
+        &mut self,
+        attribute: &AttributeInfo,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.write_attribute(attribute)?;
+        Ok(std::mem::take(&mut self.bytes))
+    }
+
+    /// The empty StackMapTable the reader adds to Code that has none, which was never in the
+    /// class file.
+    pub(crate) fn is_implicit(&self, attribute: &AttributeInfo) -> bool {
+        matches!(attribute, AttributeInfo::StackMapTable(table)
+            if table.attribute_name_index >= self.class.constant_pool_count)
+    }
+
+    fn write_attribute(&mut self, attribute: &AttributeInfo) -> Result<(), Box<dyn Error>> {
+        match attribute {
+            AttributeInfo::ConstantValue(value) => self.u2(value.constantvalue_index),
+            AttributeInfo::Code(code) => {
+                self.u2(code.max_stack);
+                self.u2(code.max_locals);
+                if code.code.len() > u16::MAX as usize {
+                    return Err(too_large("code", code.code.len()));
+                }
+                self.u4(code.code.len() as u32);
+                self.bytes.extend_from_slice(&code.code);
+                self.count(code.exception_tables.len(), "exception_table")?;
+                for handler in &code.exception_tables {
+                    self.u2(handler.start_pc);
+                    self.u2(handler.end_pc);
+                    self.u2(handler.handler_pc);
+                    self.u2(handler.catch_type);
+                }
+                self.write_attributes(&code.attributes)?;
+            }
+            AttributeInfo::StackMapTable(table) => {
+                self.count(table.entries.len(), "entries")?;
+                for frame in &table.entries {
+                    self.write_frame(frame)?;
+                }
+            }
+            AttributeInfo::Exceptions(exceptions) => {
+                self.u2_list(&exceptions.exception_index_table, "exception_index_table")?;
+            }
+            AttributeInfo::InnerClasses(inner_classes) => {
+                self.count(inner_classes.classes.len(), "classes")?;
+                for class in &inner_classes.classes {
+                    self.u2(class.inner_class_info_index);
+                    self.u2(class.outer_class_info_index);
+                    self.u2(class.inner_name_index);
+                    self.u2(class.inner_class_access_flags);
+                }
+            }
+            AttributeInfo::EnclosingMethod(enclosing) => {
+                self.u2(enclosing.class_index);
+                self.u2(enclosing.method_index);
+            }
+            AttributeInfo::Synthetic(_)
+            | AttributeInfo::Deprecated(_)
+            | AttributeInfo::Scala(_) => {}
+            AttributeInfo::Signature(signature) => self.u2(signature.signature_index),
+            AttributeInfo::SourceFile(source_file) => self.u2(source_file.sourcefile_index),
+            AttributeInfo::SourceDebugExtension(extension) => self
+                .bytes
+                .extend_from_slice(extension.debug_extension.as_bytes()),
+            AttributeInfo::LineNumberTable(table) => {
+                self.count(table.line_number_table.len(), "line_number_table")?;
+                for line in &table.line_number_table {
+                    self.u2(line.start_pc);
+                    self.u2(line.line_number);
+                }
+            }
+            AttributeInfo::LocalVariableTable(table) => {
+                self.count(table.local_variable_table.len(), "local_variable_table")?;
+                for local in &table.local_variable_table {
+                    self.u2(local.start_pc);
+                    self.u2(local.length);
+                    self.u2(local.name_index);
+                    self.u2(local.descriptor_index);
+                    self.u2(local.index);
+                }
+            }
+            AttributeInfo::LocalVariableTypeTable(table) => {
+                self.count(
+                    table.local_variable_type_table.len(),
+                    "local_variable_type_table",
+                )?;
+                for local in &table.local_variable_type_table {
+                    self.u2(local.start_pc);
+                    self.u2(local.length);
+                    self.u2(local.name_index);
+                    self.u2(local.signature_index);
+                    self.u2(local.index);
+                }
+            }
+            AttributeInfo::RuntimeVisibleAnnotations(annotations) => {
+                self.write_annotations(&annotations.annotations)?;
+            }
+            AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+                self.write_annotations(&annotations.annotations)?;
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations(annotations) => {
+                self.u1_count(annotations.parameter_annotations.len(), "parameters")?;
+                for parameter in &annotations.parameter_annotations {
+                    self.write_annotations(&parameter.annotations)?;
+                }
+            }
+            AttributeInfo::RuntimeInvisibleParameterAnnotations(annotations) => {
+                self.u1_count(annotations.parameter_annotations.len(), "parameters")?;
+                for parameter in &annotations.parameter_annotations {
+                    self.write_annotations(&parameter.annotations)?;
+                }
+            }
+            AttributeInfo::RuntimeVisibleTypeAnnotations(annotations) => {
+                self.write_type_annotations(&annotations.type_annotations)?;
+            }
+            AttributeInfo::RuntimeInvisibleTypeAnnotations(annotations) => {
+                self.write_type_annotations(&annotations.type_annotations)?;
+            }
+            AttributeInfo::AnnotationDefault(default) => {
+                self.write_element(&default.default_value)?;
+            }
+            AttributeInfo::BootstrapMethods(bootstrap) => {
+                self.count(bootstrap.bootstrap_methods.len(), "bootstrap_methods")?;
+                for method in &bootstrap.bootstrap_methods {
+                    self.u2(method.bootstrap_method_ref);
+                    self.u2_list(&method.bootstrap_arguments, "bootstrap_arguments")?;
+                }
+            }
+            AttributeInfo::MethodParameters(parameters) => {
+                self.u1_count(parameters.parameters.len(), "parameters")?;
+                for parameter in &parameters.parameters {
+                    self.u2(parameter.name_index);
+                    self.u2(ParameterAccessFlags::to_u16(&parameter.access_flags));
+                }
+            }
+            AttributeInfo::Module(module) => {
+                self.u2(module.module_name_index);
+                self.u2(ModuleFlags::ModuleAccessFlags::to_u16(&module.module_flags));
+                self.u2(module.module_version_index);
+                self.count(module.requires.len(), "requires")?;
+                for requires in &module.requires {
+                    self.u2(requires.requires_index);
+                    self.u2(ModuleFlags::RequiresAccessFlags::to_u16(
+                        &requires.requires_flags,
+                    ));
+                    self.u2(requires.requires_version_index);
+                }
+                self.count(module.exports.len(), "exports")?;
+                for exports in &module.exports {
+                    self.u2(exports.exports_index);
+                    self.u2(ModuleFlags::ExportsAccessFlags::to_u16(
+                        &exports.exports_flags,
+                    ));
+                    self.u2_list(&exports.exports_to_index, "exports_to")?;
+                }
+                self.count(module.opens.len(), "opens")?;
+                for opens in &module.opens {
+                    self.u2(opens.opens_index);
+                    self.u2(ModuleFlags::OpensAccessFlags::to_u16(&opens.opens_flags));
+                    self.u2_list(&opens.opens_to_index, "opens_to")?;
+                }
+                self.u2_list(&module.uses_index, "uses")?;
+                self.count(module.provides.len(), "provides")?;
+                for provides in &module.provides {
+                    self.u2(provides.provides_index);
+                    self.u2_list(&provides.provides_with_index, "provides_with")?;
+                }
+            }
+            AttributeInfo::ModulePackages(packages) => {
+                self.u2_list(&packages.package_index, "package_index")?;
+            }
+            AttributeInfo::ModuleMainClass(main_class) => self.u2(main_class.main_class_index),
+            AttributeInfo::NestHost(host) => self.u2(host.host_class_index),
+            AttributeInfo::NestMembers(members) => self.u2_list(&members.classes, "classes")?,
+            AttributeInfo::Record(record) => {
+                self.count(record.components.len(), "components")?;
+                for component in &record.components {
+                    self.u2(component.name_index);
+                    self.u2(component.descriptor_index);
+                    self.write_attributes(&component.attributes)?;
+                }
+            }
+            AttributeInfo::PermittedSubclasses(permitted) => {
+                self.u2_list(&permitted.classes, "classes")?;
+            }
+            AttributeInfo::ScalaSig(signature) => self.bytes.extend_from_slice(&signature.bytes),
+            AttributeInfo::ScalaInlineInfo(inline_info) => {
+                self.u1(inline_info.version);
+                self.u1(inline_info.flags);
+                if let Some(self_type) = inline_info.self_type {
+                    self.u2(self_type);
+                }
+                if let Some((name, descriptor)) = inline_info.sam {
+                    self.u2(name);
+                    self.u2(descriptor);
+                }
+                self.count(inline_info.methods.len(), "methods")?;
+                for method in &inline_info.methods {
+                    self.u2(method.name_index);
+                    self.u2(method.descriptor_index);
+                    self.u1(method.flags);
+                }
+            }
+            AttributeInfo::Tasty(tasty) => {
+                self.bytes.extend_from_slice(&tasty.uuid_high.to_be_bytes());
+                self.bytes.extend_from_slice(&tasty.uuid_low.to_be_bytes());
+            }
+            AttributeInfo::Unknown(unknown) => self.bytes.extend_from_slice(&unknown.info),
+        }
+        Ok(())
+    }
+
+    fn write_frame(&mut self, frame: &StackMapFrame) -> Result<(), Box<dyn Error>> {
+        match frame {
+            StackMapFrame::SameFrame { frame_type } | StackMapFrame::Unused { frame_type } => {
+                self.u1(*frame_type);
+            }
+            StackMapFrame::SameLocals1StackItemFrame { frame_type, stack } => {
+                self.u1(*frame_type);
+                self.write_verification_types(stack);
+            }
+            StackMapFrame::SameLocals1StackItemFrameExtended {
+                frame_type,
+                offset_delta,
+                stack,
+            } => {
+                self.u1(*frame_type);
+                self.u2(*offset_delta);
+                self.write_verification_types(stack);
+            }
+            StackMapFrame::ChopFrame {
+                frame_type,
+                offset_delta,
+            }
+            | StackMapFrame::SameFrameExtended {
+                frame_type,
+                offset_delta,
+            } => {
+                self.u1(*frame_type);
+                self.u2(*offset_delta);
+            }
+            StackMapFrame::AppendFrame {
+                frame_type,
+                offset_delta,
+                locals,
+            } => {
+                self.u1(*frame_type);
+                self.u2(*offset_delta);
+                self.write_verification_types(locals);
+            }
+            StackMapFrame::FullFrame {
+                frame_type,
+                offset_delta,
+                locals,
+                stack,
+                ..
+            } => {
+                self.u1(*frame_type);
+                self.u2(*offset_delta);
+                self.count(locals.len(), "locals")?;
+                self.write_verification_types(locals);
+                self.count(stack.len(), "stack")?;
+                self.write_verification_types(stack);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_verification_types(&mut self, types: &[VerificationTypeInfo]) {
+        for verification_type in types {
+            self.u1(verification_type.get_tag());
+            if let VerificationTypeInfo::ObjectVariable(index)
+            | VerificationTypeInfo::UnintializedVariable(index) = verification_type
+            {
+                self.u2(*index);
+            }
+        }
+    }
+
+    fn write_annotations(&mut self, annotations: &[Annotation]) -> Result<(), Box<dyn Error>> {
+        self.count(annotations.len(), "annotations")?;
+        for annotation in annotations {
+            self.write_annotation(annotation)?;
+        }
+        Ok(())
+    }
+
+    fn write_annotation(&mut self, annotation: &Annotation) -> Result<(), Box<dyn Error>> {
+        self.u2(annotation.type_index);
+        self.count(annotation.element_value_pairs.len(), "element_value_pairs")?;
+        for pair in &annotation.element_value_pairs {
+            self.u2(pair.element_name_index);
+            self.write_element(&pair.value)?;
+        }
+        Ok(())
+    }
+
+    fn write_type_annotations(
+        &mut self,
+        annotations: &[TypeAnnotation],
+    ) -> Result<(), Box<dyn Error>> {
+        self.count(annotations.len(), "annotations")?;
+        for annotation in annotations {
+            self.u1(annotation.target_type);
+            match &annotation.target_info {
+                TargetInfo::TypeParameterTarget {
+                    type_parameter_index,
+                } => self.u1(*type_parameter_index),
+                TargetInfo::SupertypeTarget { supertype_index } => self.u2(*supertype_index),
+                TargetInfo::TypeParameterBoundTarget {
+                    type_parameter_index,
+                    bound_index,
+                } => {
+                    self.u1(*type_parameter_index);
+                    self.u1(*bound_index);
+                }
+                TargetInfo::EmptyTarget => {}
+                TargetInfo::FormalParameterTarget {
+                    formal_parameter_index,
+                } => self.u1(*formal_parameter_index),
+                TargetInfo::ThrowsTarget { throws_type_index } => self.u2(*throws_type_index),
+                TargetInfo::LocalvarTarget { table, .. } => {
+                    self.count(table.len(), "localvar_target")?;
+                    for local in table {
+                        self.u2(local.start_pc);
+                        self.u2(local.length);
+                        self.u2(local.index);
+                    }
+                }
+                TargetInfo::CatchTarget {
+                    exception_table_index,
+                } => self.u2(*exception_table_index),
+                TargetInfo::OffsetTarget { offset } => self.u2(*offset),
+                TargetInfo::TypeArgumentTarget {
+                    offset,
+                    type_argument_index,
+                } => {
+                    self.u2(*offset);
+                    self.u1(*type_argument_index);
+                }
+            }
+            self.u1_count(annotation.target_path.path.len(), "type_path")?;
+            for step in &annotation.target_path.path {
+                self.u1(step.type_path_kind);
+                self.u1(step.type_argument_index);
+            }
+            self.u2(annotation.type_index);
+            self.count(annotation.element_value_pairs.len(), "element_value_pairs")?;
+            for pair in &annotation.element_value_pairs {
+                self.u2(pair.element_name_index);
+                self.write_element(&pair.value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_element(&mut self, element: &Element) -> Result<(), Box<dyn Error>> {
+        match element {
+            Element::ConstValueIndex(tag, index) => {
+                self.u1(*tag as u8);
+                self.u2(*index);
+            }
+            Element::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => {
+                self.u1(b'e');
+                self.u2(*type_name_index);
+                self.u2(*const_name_index);
+            }
+            Element::ClassInfoIndex(index) => {
+                self.u1(b'c');
+                self.u2(*index);
+            }
+            Element::Annotation(annotation) => {
+                self.u1(b'@');
+                self.write_annotation(annotation)?;
+            }
+            Element::ArrayValue { values, .. } => {
+                self.u1(b'[');
+                self.count(values.len(), "values")?;
+                for value in values {
+                    self.write_element(value)?;
+                }
+            }
+            Element::Unknown(tag) => {
+                return Err(Box::new(WritingError::new(
+                    WritingCause::InvalidElementTag(*tag),
+                    "the element value was not read, so it cannot be written",
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn u1(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u2(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u4(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Writes the u2 count of a table, which must fit in it.
+    fn count(&mut self, count: usize, table: &str) -> Result<(), Box<dyn Error>> {
+        let count = u16::try_from(count).map_err(|_| too_large(table, count))?;
+        self.u2(count);
+        Ok(())
+    }
+
+    /// Writes the u1 count of a table, as used by the parameter tables.
+    fn u1_count(&mut self, count: usize, table: &str) -> Result<(), Box<dyn Error>> {
+        let count = u8::try_from(count).map_err(|_| too_large(table, count))?;
+        self.u1(count);
+        Ok(())
+    }
+
+    fn u2_list(&mut self, values: &[u16], table: &str) -> Result<(), Box<dyn Error>> {
+        self.count(values.len(), table)?;
+        for value in values {
+            self.u2(*value);
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Appends a constant pool entry, tag first, as it is stored in the class file.\
+ *  Fails for a Utf8 entry longer than the 65535 bytes its length item can count.
+ */
+pub(crate) fn write_constant(
+    constant: &ConstantPool,
+    bytes: &mut Vec<u8>,
+) -> Result<(), Box<dyn Error>> {
+    let u2 = |bytes: &mut Vec<u8>, value: u16| bytes.extend_from_slice(&value.to_be_bytes());
+    match constant {
+        ConstantPool::Utf8(utf8) => {
+            bytes.push(Tags::Utf8 as u8);
+            u2(bytes, utf8_length(utf8.bytes.len())?);
+            bytes.extend_from_slice(&utf8.bytes);
+        }
+        ConstantPool::Integer(integer) => {
+            bytes.push(Tags::Integer as u8);
+            bytes.extend_from_slice(&integer.bytes.to_be_bytes());
+        }
+        ConstantPool::Float(float) => {
+            bytes.push(Tags::Float as u8);
+            bytes.extend_from_slice(&float.bytes.to_be_bytes());
+        }
+        ConstantPool::Long(long) => {
+            bytes.push(Tags::Long as u8);
+            bytes.extend_from_slice(&long.high_bytes.to_be_bytes());
+            bytes.extend_from_slice(&long.low_bytes.to_be_bytes());
+        }
+        ConstantPool::Double(double) => {
+            bytes.push(Tags::Double as u8);
+            bytes.extend_from_slice(&double.high_bytes.to_be_bytes());
+            bytes.extend_from_slice(&double.low_bytes.to_be_bytes());
+        }
+        ConstantPool::Class(class) => {
+            bytes.push(Tags::Class as u8);
+            u2(bytes, class.name_index);
+        }
+        ConstantPool::String(string) => {
+            bytes.push(Tags::String as u8);
+            u2(bytes, string.string_index);
+        }
+        ConstantPool::Fieldref(field) => {
+            bytes.push(Tags::Fieldref as u8);
+            u2(bytes, field.class_index);
+            u2(bytes, field.name_and_type_index);
+        }
+        ConstantPool::Methodref(method) => {
+            bytes.push(Tags::Methodref as u8);
+            u2(bytes, method.class_index);
+            u2(bytes, method.name_and_type_index);
+        }
+        ConstantPool::InterfaceMethodRef(method) => {
+            bytes.push(Tags::InterfaceMethodRef as u8);
+            u2(bytes, method.class_index);
+            u2(bytes, method.name_and_type_index);
+        }
+        ConstantPool::NameAndType(name_and_type) => {
+            bytes.push(Tags::NameAndType as u8);
+            u2(bytes, name_and_type.name_index);
+            u2(bytes, name_and_type.descriptor_index);
+        }
+        ConstantPool::MethodHandle(handle) => {
+            bytes.push(Tags::MethodHandle as u8);
+            bytes.push(handle.reference_kind);
+            u2(bytes, handle.reference_index);
+        }
+        ConstantPool::MethodType(method_type) => {
+            bytes.push(Tags::MethodType as u8);
+            u2(bytes, method_type.descriptor_index);
+        }
+        ConstantPool::Dynamic(dynamic) => {
+            bytes.push(Tags::Dynamic as u8);
+            u2(bytes, dynamic.bootstrap_method_attr_index);
+            u2(bytes, dynamic.name_and_type_index);
+        }
+        ConstantPool::InvokeDynamic(dynamic) => {
+            bytes.push(Tags::InvokeDynamic as u8);
+            u2(bytes, dynamic.bootstrap_method_attr_index);
+            u2(bytes, dynamic.name_and_type_index);
+        }
+        ConstantPool::Module(module) => {
+            bytes.push(Tags::Module as u8);
+            u2(bytes, module.name_index);
+        }
+        ConstantPool::Package(package) => {
+            bytes.push(Tags::Package as u8);
+            u2(bytes, package.name_index);
+        }
+        // The unusable entries: index 0 and the one following a long or double.
+        ConstantPool::Unknown => {}
+    }
+    Ok(())
+}
+
+/// The length item of a Utf8 entry of `length` bytes.
+pub(crate) fn utf8_length(length: usize) -> Result<u16, Box<dyn Error>> {
+    u16::try_from(length).map_err(|_| -> Box<dyn Error> {
+        Box::new(WritingError::new(
+            WritingCause::TooLarge("Utf8".into()),
+            &format!("a Utf8 constant of {length} bytes is longer than 65535 bytes"),
+        ))
+    })
+}
+
+fn too_large(table: &str, count: usize) -> Box<dyn Error> {
+    Box::new(WritingError::new(
+        WritingCause::TooLarge(table.into()),
+        &format!("{count} entries do not fit in the class file"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PATH: &str = "test_verified_output/class_writer_test/";
+
+    /// Reads a class compiled from `Sample.java` and writes it back unchanged.
+    fn round_trip(name: &str) -> (Vec<u8>, Vec<u8>) {
+        let bytes = std::fs::read(format!("{TEST_PATH}{name}.class")).unwrap();
+        let class = ClassFile::from_bytes(&bytes).unwrap();
+        let written = ClassWriter::new(&class).write().unwrap();
+        (bytes, written)
+    }
+
+    #[test]
+    fn test_write_class() {
+        // Stack maps, a lookup switch, a multi-catch, a lambda, enum, class and nested
+        // annotation elements and type annotations with type paths.
+        let (bytes, written) = round_trip("Sample");
+        assert_eq!(written, bytes);
+    }
+
+    #[test]
+    fn test_write_annotation_types() {
+        // Element values as annotation defaults, including arrays.
+        for name in ["Sample$Checked", "Sample$Tagged"] {
+            let (bytes, written) = round_trip(name);
+            assert_eq!(written, bytes, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_write_record() {
+        let (bytes, written) = round_trip("Sample$Point");
+        assert_eq!(written, bytes);
+    }
+
+    #[test]
+    fn test_reject_long_utf8() {
+        let bytes = std::fs::read(format!("{TEST_PATH}Sample.class")).unwrap();
+        let mut class = ClassFile::from_bytes(&bytes).unwrap();
+        class
+            .add_constant(ConstantPool::Utf8("a".repeat(65536).as_str().into()))
+            .unwrap();
+        let error = ClassWriter::new(&class).write().unwrap_err();
+        assert!(error.to_string().contains("TooLarge: Utf8"), "{error}");
+    }
+}
@@ -0,0 +1,1006 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::access_flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::class_file::{AttributeInfo, ClassFile, FieldInfo, MethodInfo};
+use crate::class_path::ClassPath;
+use crate::constants::{get_class_name, get_raw_utf8};
+use crate::javap::{Javap, JavapOptions};
+use crate::records::RecordModel;
+
+/// The public and protected methods of `java.lang.Object`, for when no JDK is on the class path.
+const OBJECT_METHODS: [(&str, &str); 11] = [
+    ("clone", "()Ljava/lang/Object;"),
+    ("equals", "(Ljava/lang/Object;)Z"),
+    ("finalize", "()V"),
+    ("getClass", "()Ljava/lang/Class;"),
+    ("hashCode", "()I"),
+    ("notify", "()V"),
+    ("notifyAll", "()V"),
+    ("toString", "()Ljava/lang/String;"),
+    ("wait", "()V"),
+    ("wait", "(J)V"),
+    ("wait", "(JI)V"),
+];
+
+/// What a change means for code built against the old version, after JLS chapter 13.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compatibility {
+    /// Binaries built against the old version may fail to link or run, e.g. with a
+    /// NoSuchMethodError.
+    BinaryIncompatible,
+    /**
+     * Binaries keep linking, but sources that compiled against the old version may not
+     * compile against the new one, such as implementations of a new abstract method.
+     */
+    SourceIncompatible,
+    /// Binaries keep linking but keep the old behavior, such as an inlined constant.
+    BehaviorChanged,
+}
+
+impl Display for Compatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Compatibility::BinaryIncompatible => "binary incompatible",
+            Compatibility::SourceIncompatible => "source incompatible",
+            Compatibility::BehaviorChanged => "behavior changed",
+        })
+    }
+}
+
+/// [Binary Compatibility](https://docs.oracle.com/javase/specs/jls/se17/html/jls-13.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A public or protected class is gone.
+    ClassRemoved,
+    /// A class became less accessible, e.g. from `public` to package access.
+    ClassAccessNarrowed {
+        old: String,
+        new: String,
+    },
+    /// A class became an interface, an enum became a class, and so on.
+    ClassKindChanged {
+        old: String,
+        new: String,
+    },
+    ClassBecameAbstract,
+    ClassBecameFinal,
+    ClassBecameSealed,
+    /// A sealed class or interface no longer permits a subclass.
+    PermittedSubclassRemoved {
+        subclass: String,
+    },
+    /// A superclass or superinterface, direct or not, is no longer one.
+    SupertypeRemoved {
+        supertype: String,
+    },
+    RecordComponentsChanged {
+        old: String,
+        new: String,
+    },
+    EnumConstantRemoved {
+        name: String,
+    },
+    /// A public or protected member became less accessible.
+    MemberAccessNarrowed {
+        old: String,
+        new: String,
+    },
+    FieldRemoved,
+    FieldTypeChanged {
+        old: String,
+        new: String,
+    },
+    FieldStaticChanged {
+        is_static: bool,
+    },
+    FieldBecameFinal,
+    /**
+     * A constant variable has another value, or stopped being a constant. Code compiled
+     * against the old version inlined the old value and keeps using it.
+     */
+    ConstantValueChanged {
+        old: String,
+        new: Option<String>,
+    },
+    MethodRemoved,
+    /// A method is gone, but the class has methods of the same name with other descriptors.
+    MethodDescriptorChanged {
+        new: Vec<String>,
+    },
+    MethodStaticChanged {
+        is_static: bool,
+    },
+    MethodBecameAbstract,
+    MethodBecameFinal,
+    /// An abstract method was added, which subclasses and implementations must now declare.
+    AbstractMethodAdded,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeKind::ClassRemoved => write!(f, "ClassRemoved: the class was removed"),
+            ChangeKind::ClassAccessNarrowed { old, new } => {
+                write!(f, "ClassAccessNarrowed: access changed from {old} to {new}")
+            }
+            ChangeKind::ClassKindChanged { old, new } => {
+                write!(f, "ClassKindChanged: changed from {old} to {new}")
+            }
+            ChangeKind::ClassBecameAbstract => write!(f, "ClassBecameAbstract: is now abstract"),
+            ChangeKind::ClassBecameFinal => write!(f, "ClassBecameFinal: is now final"),
+            ChangeKind::ClassBecameSealed => write!(f, "ClassBecameSealed: is now sealed"),
+            ChangeKind::PermittedSubclassRemoved { subclass } => {
+                write!(f, "PermittedSubclassRemoved: no longer permits {subclass}")
+            }
+            ChangeKind::SupertypeRemoved { supertype } => {
+                write!(f, "SupertypeRemoved: no longer a subtype of {supertype}")
+            }
+            ChangeKind::RecordComponentsChanged { old, new } => {
+                write!(
+                    f,
+                    "RecordComponentsChanged: components changed from {old} to {new}"
+                )
+            }
+            ChangeKind::EnumConstantRemoved { name } => {
+                write!(f, "EnumConstantRemoved: the constant {name} was removed")
+            }
+            ChangeKind::MemberAccessNarrowed { old, new } => {
+                write!(
+                    f,
+                    "MemberAccessNarrowed: access changed from {old} to {new}"
+                )
+            }
+            ChangeKind::FieldRemoved => write!(f, "FieldRemoved: the field was removed"),
+            ChangeKind::FieldTypeChanged { old, new } => {
+                write!(f, "FieldTypeChanged: type changed from {old} to {new}")
+            }
+            ChangeKind::FieldStaticChanged { is_static } => {
+                write!(f, "FieldStaticChanged: {}", static_text(*is_static))
+            }
+            ChangeKind::FieldBecameFinal => write!(f, "FieldBecameFinal: is now final"),
+            ChangeKind::ConstantValueChanged { old, new } => match new {
+                Some(new) => write!(f, "ConstantValueChanged: value changed from {old} to {new}"),
+                None => write!(f, "ConstantValueChanged: no longer the constant {old}"),
+            },
+            ChangeKind::MethodRemoved => write!(f, "MethodRemoved: the method was removed"),
+            ChangeKind::MethodDescriptorChanged { new } => {
+                write!(
+                    f,
+                    "MethodDescriptorChanged: now declared as {}",
+                    new.join(", ")
+                )
+            }
+            ChangeKind::MethodStaticChanged { is_static } => {
+                write!(f, "MethodStaticChanged: {}", static_text(*is_static))
+            }
+            ChangeKind::MethodBecameAbstract => write!(f, "MethodBecameAbstract: is now abstract"),
+            ChangeKind::MethodBecameFinal => write!(f, "MethodBecameFinal: is now final"),
+            ChangeKind::AbstractMethodAdded => {
+                write!(f, "AbstractMethodAdded: a new abstract method")
+            }
+        }
+    }
+}
+
+fn static_text(is_static: bool) -> &'static str {
+    match is_static {
+        true => "is now static",
+        false => "is no longer static",
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiChange {
+    /// The binary name of the class, e.g. `java.util.Map$Entry`.
+    pub class: String,
+    /// The field as `name:descriptor` or the method as `name(descriptor)`, `None` for changes
+    /// to the class itself.
+    pub member: Option<String>,
+    pub kind: ChangeKind,
+    /// Whether the class is an interface, as JLS 13.5 rather than 13.4 covers its evolution.
+    is_interface: bool,
+}
+
+impl ApiChange {
+    pub fn get_compatibility(&self) -> Compatibility {
+        match self.kind {
+            ChangeKind::AbstractMethodAdded => Compatibility::SourceIncompatible,
+            ChangeKind::ConstantValueChanged { .. } => Compatibility::BehaviorChanged,
+            _ => Compatibility::BinaryIncompatible,
+        }
+    }
+
+    /// The section of JLS chapter 13 that describes the change, e.g. `13.4.12`.
+    pub fn get_section(&self) -> &'static str {
+        let (class, interface) = match self.kind {
+            ChangeKind::ClassRemoved => ("13.3", "13.3"),
+            ChangeKind::ClassAccessNarrowed { .. } => ("13.4.3", "13.5.1"),
+            ChangeKind::ClassKindChanged { .. } => ("13.4", "13.5"),
+            ChangeKind::ClassBecameAbstract => ("13.4.1", "13.4.1"),
+            ChangeKind::ClassBecameFinal => ("13.4.2", "13.4.2"),
+            ChangeKind::ClassBecameSealed | ChangeKind::PermittedSubclassRemoved { .. } => {
+                ("13.4.2", "13.5.2")
+            }
+            ChangeKind::SupertypeRemoved { .. } => ("13.4.4", "13.5.3"),
+            ChangeKind::RecordComponentsChanged { .. } => ("13.4.27", "13.4.27"),
+            ChangeKind::EnumConstantRemoved { .. } => ("13.4.26", "13.4.26"),
+            ChangeKind::MemberAccessNarrowed { .. } => ("13.4.7", "13.5.4"),
+            ChangeKind::FieldRemoved | ChangeKind::FieldTypeChanged { .. } => ("13.4.8", "13.5.6"),
+            ChangeKind::FieldStaticChanged { .. } => ("13.4.10", "13.5.6"),
+            ChangeKind::FieldBecameFinal | ChangeKind::ConstantValueChanged { .. } => {
+                ("13.4.9", "13.5.6")
+            }
+            ChangeKind::MethodRemoved => ("13.4.12", "13.5.7"),
+            ChangeKind::MethodDescriptorChanged { .. } => ("13.4.14", "13.5.7"),
+            ChangeKind::MethodStaticChanged { .. } => ("13.4.19", "13.5.7"),
+            ChangeKind::MethodBecameAbstract | ChangeKind::AbstractMethodAdded => {
+                ("13.4.16", "13.5.4")
+            }
+            ChangeKind::MethodBecameFinal => ("13.4.17", "13.4.17"),
+        };
+        match self.is_interface {
+            true => interface,
+            false => class,
+        }
+    }
+}
+
+impl Display for ApiChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.member {
+            Some(member) => write!(f, "{}.{member}", self.class)?,
+            None => write!(f, "{}", self.class)?,
+        }
+        write!(
+            f,
+            ": {} ({}, JLS {})",
+            self.kind,
+            self.get_compatibility(),
+            self.get_section()
+        )
+    }
+}
+
+/**
+ * The changes to the API of a set of classes between two versions that can affect code built
+ * against the old one.\
+ *  The API is the public and protected classes and their public members, and their protected
+ *  members unless the class is final. Additions that cannot break anything, and changes to
+ *  what is private or package access, are not reported. Supertypes that neither version
+ *  has are looked up on the class path, and a change that depends on one that cannot be
+ *  found is not reported.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct ApiDiff {
+    pub changes: Vec<ApiChange>,
+}
+
+impl ApiDiff {
+    pub fn new(
+        old: &[ClassFile],
+        new: &[ClassFile],
+        class_path: &ClassPath,
+    ) -> Result<ApiDiff, Box<dyn Error>> {
+        let old_classes = by_name(old);
+        let new_classes = by_name(new);
+        let mut diff = ApiDiff::default();
+        for (name, old_class) in &old_classes {
+            let old_flags = get_declared_flags(old_class);
+            if Access::of_class(&old_flags) < Access::Protected {
+                continue;
+            }
+            let is_interface = old_flags.contains(&ClassAccessFlags::AccInterface);
+            let mut report = |member: Option<String>, kind: ChangeKind| {
+                diff.changes.push(ApiChange {
+                    class: name.clone(),
+                    member,
+                    kind,
+                    is_interface,
+                });
+            };
+            let Some(new_class) = new_classes.get(name) else {
+                report(None, ChangeKind::ClassRemoved);
+                continue;
+            };
+            let (old_access, new_access) = (
+                Access::of_class(&old_flags),
+                Access::of_class(&get_declared_flags(new_class)),
+            );
+            if new_access < old_access {
+                report(
+                    None,
+                    ChangeKind::ClassAccessNarrowed {
+                        old: old_access.get_name().into(),
+                        new: new_access.get_name().into(),
+                    },
+                );
+                continue;
+            }
+            let old_supertypes = get_supertypes(old_class, &old_classes, class_path)?;
+            let new_supertypes = get_supertypes(new_class, &new_classes, class_path)?;
+            let supertypes = (&old_supertypes, &new_supertypes);
+            for change in compare_classes(old_class, new_class, supertypes) {
+                report(change.0, change.1);
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Whether code built against the old version still links and compiles against the new.
+    pub fn is_compatible(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|change| change.get_compatibility() == Compatibility::BehaviorChanged)
+    }
+}
+
+type Change = (Option<String>, ChangeKind);
+
+fn compare_classes(
+    old: &ClassFile,
+    new: &ClassFile,
+    (old_supertypes, new_supertypes): (&Supertypes, &Supertypes),
+) -> Vec<Change> {
+    let mut changes = vec![];
+    let old_flags = get_declared_flags(old);
+    let new_flags = get_declared_flags(new);
+    let (old_kind, new_kind) = (get_kind(old, &old_flags), get_kind(new, &new_flags));
+    if old_kind != new_kind {
+        let kind = ChangeKind::ClassKindChanged {
+            old: old_kind.into(),
+            new: new_kind.into(),
+        };
+        changes.push((None, kind));
+        return changes;
+    }
+    // Interfaces and enums have these flags whether or not they may be extended.
+    let is_class = old_kind == "class";
+    let became = |flag: ClassAccessFlags| !old_flags.contains(&flag) && new_flags.contains(&flag);
+    if is_class && became(ClassAccessFlags::AccAbstract) {
+        changes.push((None, ChangeKind::ClassBecameAbstract));
+    }
+    if is_class && became(ClassAccessFlags::AccFinal) {
+        changes.push((None, ChangeKind::ClassBecameFinal));
+    }
+    let is_final = old_flags.contains(&ClassAccessFlags::AccFinal);
+    match (
+        old.get_permitted_subclasses(),
+        new.get_permitted_subclasses(),
+    ) {
+        (None, Some(_)) if !is_final && old_kind != "enum" => {
+            changes.push((None, ChangeKind::ClassBecameSealed));
+        }
+        (Some(old_permitted), Some(new_permitted)) => {
+            for subclass in old_permitted {
+                if !new_permitted.contains(&subclass) {
+                    changes.push((None, ChangeKind::PermittedSubclassRemoved { subclass }));
+                }
+            }
+        }
+        _ => {}
+    }
+    // Without all the supertypes of the new version, one missing from it may still be there.
+    if new_supertypes.resolved {
+        for supertype in &old_supertypes.names {
+            // Every class and interface is a subtype of Object, whatever its class file says.
+            if supertype != "java.lang.Object" && !new_supertypes.names.contains(supertype) {
+                let supertype = supertype.clone();
+                changes.push((None, ChangeKind::SupertypeRemoved { supertype }));
+            }
+        }
+    }
+    if let (Some(old_record), Some(new_record)) = (old.get_record(), new.get_record()) {
+        let components = |record: &RecordModel| {
+            let components: Vec<String> = record
+                .components
+                .iter()
+                .map(|component| format!("{}:{}", component.name, component.descriptor))
+                .collect();
+            format!("({})", components.join(", "))
+        };
+        let (old_components, new_components) = (components(&old_record), components(&new_record));
+        if old_components != new_components {
+            let kind = ChangeKind::RecordComponentsChanged {
+                old: old_components,
+                new: new_components,
+            };
+            changes.push((None, kind));
+        }
+    }
+
+    compare_fields(old, new, is_final, &mut changes);
+    compare_methods(old, new, is_final, new_supertypes, &mut changes);
+    changes
+}
+
+fn compare_fields(old: &ClassFile, new: &ClassFile, is_final: bool, changes: &mut Vec<Change>) {
+    for field in &old.fields {
+        let flags = &field.access_flags;
+        let is_synthetic = flags.contains(&FieldAccessFlags::AccSynthetic);
+        if !is_member_visible(Access::of_field(flags), is_synthetic, is_final) {
+            continue;
+        }
+        let name = get_raw_utf8(&old.constant_pool, field.name_index).unwrap_or_default();
+        let descriptor =
+            get_raw_utf8(&old.constant_pool, field.descriptor_index).unwrap_or_default();
+        let member = Some(format!("{name}:{descriptor}"));
+        let same_name = new.fields.iter().find(|other| {
+            get_raw_utf8(&new.constant_pool, other.name_index).unwrap_or_default() == name
+        });
+        let Some(other) = same_name else {
+            let kind = match flags.contains(&FieldAccessFlags::AccEnum) {
+                true => ChangeKind::EnumConstantRemoved { name },
+                false => ChangeKind::FieldRemoved,
+            };
+            changes.push((member, kind));
+            continue;
+        };
+        let new_descriptor =
+            get_raw_utf8(&new.constant_pool, other.descriptor_index).unwrap_or_default();
+        if new_descriptor != descriptor {
+            let kind = ChangeKind::FieldTypeChanged {
+                old: descriptor,
+                new: new_descriptor,
+            };
+            changes.push((member, kind));
+            continue;
+        }
+        let new_flags = &other.access_flags;
+        let (access, new_access) = (Access::of_field(flags), Access::of_field(new_flags));
+        if new_access < access {
+            let kind = ChangeKind::MemberAccessNarrowed {
+                old: access.get_name().into(),
+                new: new_access.get_name().into(),
+            };
+            changes.push((member, kind));
+            continue;
+        }
+        let is_static = new_flags.contains(&FieldAccessFlags::AccStatic);
+        if flags.contains(&FieldAccessFlags::AccStatic) != is_static {
+            changes.push((member.clone(), ChangeKind::FieldStaticChanged { is_static }));
+        }
+        let final_flag = FieldAccessFlags::AccFinal;
+        if !flags.contains(&final_flag) && new_flags.contains(&final_flag) {
+            changes.push((member.clone(), ChangeKind::FieldBecameFinal));
+        }
+        if let Some(value) = get_constant_value(old, field, &descriptor) {
+            let new_value = get_constant_value(new, other, &descriptor);
+            if new_value.as_ref() != Some(&value) {
+                let kind = ChangeKind::ConstantValueChanged {
+                    old: value,
+                    new: new_value,
+                };
+                changes.push((member, kind));
+            }
+        }
+    }
+}
+
+fn compare_methods(
+    old: &ClassFile,
+    new: &ClassFile,
+    is_final: bool,
+    inherited: &Supertypes,
+    changes: &mut Vec<Change>,
+) {
+    let new_methods: Vec<(String, String, &[MethodAccessFlags])> = new
+        .methods
+        .iter()
+        .map(|method| {
+            let (name, descriptor) = name_and_descriptor(new, method);
+            (name, descriptor, method.access_flags.as_slice())
+        })
+        .filter(|(name, _, flags)| name != "<clinit>" && !is_synthetic_method(flags))
+        .collect();
+    let is_method_visible = |flags: &[MethodAccessFlags]| {
+        is_member_visible(
+            Access::of_method(flags),
+            is_synthetic_method(flags),
+            is_final,
+        )
+    };
+    let mut old_methods = BTreeSet::new();
+    for method in &old.methods {
+        let flags = method.access_flags.as_slice();
+        let (name, descriptor) = name_and_descriptor(old, method);
+        old_methods.insert((name.clone(), descriptor.clone()));
+        if !is_method_visible(flags) || name == "<clinit>" {
+            continue;
+        }
+        let member = Some(format!("{name}{descriptor}"));
+        let same = new_methods
+            .iter()
+            .find(|(other, other_descriptor, _)| *other == name && *other_descriptor == descriptor);
+        let Some((_, _, new_flags)) = same else {
+            // Code built against the old version links to the method a supertype now declares.
+            let key = (name.clone(), descriptor.clone());
+            if name != "<init>" && (inherited.methods.contains(&key) || !inherited.resolved) {
+                continue;
+            }
+            let overloads: Vec<String> = new_methods
+                .iter()
+                .filter(|(other, _, flags)| *other == name && is_method_visible(flags))
+                .map(|(other, descriptor, _)| format!("{other}{descriptor}"))
+                .collect();
+            let kind = match overloads.is_empty() {
+                true => ChangeKind::MethodRemoved,
+                false => ChangeKind::MethodDescriptorChanged { new: overloads },
+            };
+            changes.push((member, kind));
+            continue;
+        };
+        let (access, new_access) = (Access::of_method(flags), Access::of_method(new_flags));
+        if new_access < access {
+            let kind = ChangeKind::MemberAccessNarrowed {
+                old: access.get_name().into(),
+                new: new_access.get_name().into(),
+            };
+            changes.push((member, kind));
+            continue;
+        }
+        let became = |flag: MethodAccessFlags| !flags.contains(&flag) && new_flags.contains(&flag);
+        let was_static = flags.contains(&MethodAccessFlags::AccStatic);
+        let is_static = new_flags.contains(&MethodAccessFlags::AccStatic);
+        if was_static != is_static {
+            changes.push((
+                member.clone(),
+                ChangeKind::MethodStaticChanged { is_static },
+            ));
+        }
+        if became(MethodAccessFlags::AccAbstract) {
+            changes.push((member.clone(), ChangeKind::MethodBecameAbstract));
+        }
+        // Static methods are hidden rather than overridden, and constructors neither.
+        let can_override = !is_final && !was_static && name != "<init>";
+        if can_override && became(MethodAccessFlags::AccFinal) {
+            changes.push((member, ChangeKind::MethodBecameFinal));
+        }
+    }
+    for (name, descriptor, flags) in &new_methods {
+        let is_new = !old_methods.contains(&(name.clone(), descriptor.clone()));
+        if is_new && flags.contains(&MethodAccessFlags::AccAbstract) && !is_final {
+            changes.push((
+                Some(format!("{name}{descriptor}")),
+                ChangeKind::AbstractMethodAdded,
+            ));
+        }
+    }
+}
+
+fn by_name(classes: &[ClassFile]) -> BTreeMap<String, &ClassFile> {
+    classes
+        .iter()
+        .map(|class| (class.get_name(), class))
+        .collect()
+}
+
+/// The flags of a class as declared in source, which for a member class InnerClasses gives.
+fn get_declared_flags(class: &ClassFile) -> Vec<ClassAccessFlags> {
+    let pool = &class.constant_pool;
+    let name = class.get_name();
+    class
+        .attributes
+        .iter()
+        .filter_map(|attribute| match attribute {
+            AttributeInfo::InnerClasses(inner) => Some(&inner.classes),
+            _ => None,
+        })
+        .flatten()
+        .find(|info| get_class_name(pool, info.inner_class_info_index).as_deref() == Some(&name))
+        .map(|info| ClassAccessFlags::from_u16(info.inner_class_access_flags))
+        .unwrap_or_else(|| class.access_flags.clone())
+}
+
+fn get_kind(class: &ClassFile, flags: &[ClassAccessFlags]) -> &'static str {
+    if flags.contains(&ClassAccessFlags::AccAnnotation) {
+        "annotation interface"
+    } else if flags.contains(&ClassAccessFlags::AccInterface) {
+        "interface"
+    } else if flags.contains(&ClassAccessFlags::AccEnum) {
+        "enum"
+    } else if class.is_record() {
+        "record"
+    } else {
+        "class"
+    }
+}
+
+/// The supertypes of a class and the methods it inherits from them.
+struct Supertypes {
+    /// Every superclass and superinterface, direct or not, by binary name.
+    names: BTreeSet<String>,
+    /// The name and descriptor of the methods that the supertypes declare and pass on.
+    methods: BTreeSet<(String, String)>,
+    /// Whether every supertype was found, so that `names` and `methods` are complete.
+    resolved: bool,
+}
+
+/**
+ * The supertypes of `class`, each found among the `classes` of the same version, else on the
+ * class path. `java.lang.Object` is known without it.
+ */
+fn get_supertypes(
+    class: &ClassFile,
+    classes: &BTreeMap<String, &ClassFile>,
+    class_path: &ClassPath,
+) -> Result<Supertypes, Box<dyn Error>> {
+    let mut supertypes = Supertypes {
+        names: BTreeSet::new(),
+        methods: BTreeSet::new(),
+        resolved: true,
+    };
+    let direct = |class: &ClassFile| {
+        let mut direct = class.get_interface_names();
+        direct.extend(class.get_super_name());
+        direct
+    };
+    let mut work = direct(class);
+    while let Some(name) = work.pop() {
+        if !supertypes.names.insert(name.clone()) {
+            continue;
+        }
+        let loaded;
+        let supertype = match classes.get(&name) {
+            Some(supertype) => *supertype,
+            None => match class_path.load_class(&name.replace('.', "/"))? {
+                Some(supertype) => {
+                    loaded = supertype;
+                    &loaded
+                }
+                None if name == "java.lang.Object" => {
+                    let methods = OBJECT_METHODS
+                        .iter()
+                        .map(|(name, descriptor)| (name.to_string(), descriptor.to_string()));
+                    supertypes.methods.extend(methods);
+                    continue;
+                }
+                None => {
+                    supertypes.resolved = false;
+                    continue;
+                }
+            },
+        };
+        let is_interface = supertype
+            .access_flags
+            .contains(&ClassAccessFlags::AccInterface);
+        for method in &supertype.methods {
+            let flags = &method.access_flags;
+            let (name, descriptor) = name_and_descriptor(supertype, method);
+            // Constructors are not inherited, nor private methods or the static methods of
+            // interfaces.
+            let is_private = flags.contains(&MethodAccessFlags::AccPrivate);
+            let is_static = flags.contains(&MethodAccessFlags::AccStatic);
+            let is_hidden = is_private || is_interface && is_static || name.starts_with('<');
+            if !is_hidden {
+                supertypes.methods.insert((name, descriptor));
+            }
+        }
+        work.extend(direct(supertype));
+    }
+    Ok(supertypes)
+}
+
+/// The access of a class or member, ordered from private to public.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Access {
+    Private,
+    Package,
+    Protected,
+    Public,
+}
+
+impl Access {
+    fn new(is_public: bool, is_protected: bool, is_private: bool) -> Access {
+        match (is_public, is_protected, is_private) {
+            (true, _, _) => Access::Public,
+            (_, true, _) => Access::Protected,
+            (_, _, true) => Access::Private,
+            _ => Access::Package,
+        }
+    }
+
+    fn of_class(flags: &[ClassAccessFlags]) -> Access {
+        Access::new(
+            flags.contains(&ClassAccessFlags::AccPublic),
+            flags.contains(&ClassAccessFlags::AccProtected),
+            flags.contains(&ClassAccessFlags::AccPrivate),
+        )
+    }
+
+    fn of_field(flags: &[FieldAccessFlags]) -> Access {
+        Access::new(
+            flags.contains(&FieldAccessFlags::AccPublic),
+            flags.contains(&FieldAccessFlags::AccProtected),
+            flags.contains(&FieldAccessFlags::AccPrivate),
+        )
+    }
+
+    fn of_method(flags: &[MethodAccessFlags]) -> Access {
+        Access::new(
+            flags.contains(&MethodAccessFlags::AccPublic),
+            flags.contains(&MethodAccessFlags::AccProtected),
+            flags.contains(&MethodAccessFlags::AccPrivate),
+        )
+    }
+
+    fn get_name(self) -> &'static str {
+        match self {
+            Access::Public => "public",
+            Access::Protected => "protected",
+            Access::Package => "package",
+            Access::Private => "private",
+        }
+    }
+}
+
+/// Whether a member is part of the API: public, or protected in a class that can be extended.
+fn is_member_visible(access: Access, is_synthetic: bool, is_final: bool) -> bool {
+    !is_synthetic && (access == Access::Public || access == Access::Protected && !is_final)
+}
+
+fn is_synthetic_method(flags: &[MethodAccessFlags]) -> bool {
+    flags.contains(&MethodAccessFlags::AccSynthetic)
+        || flags.contains(&MethodAccessFlags::AccBridge)
+}
+
+fn get_constant_value(class: &ClassFile, field: &FieldInfo, descriptor: &str) -> Option<String> {
+    let index = field
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            AttributeInfo::ConstantValue(value) => Some(value.constantvalue_index),
+            _ => None,
+        })?;
+    let options = JavapOptions::default();
+    Some(Javap::new(class, &options).get_constant_value(descriptor, index))
+}
+
+fn name_and_descriptor(class: &ClassFile, method: &MethodInfo) -> (String, String) {
+    let pool = &class.constant_pool;
+    (
+        get_raw_utf8(pool, method.name_index).unwrap_or_default(),
+        get_raw_utf8(pool, method.descriptor_index).unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::class_builder::tests::{class, read_back};
+    use crate::class_builder::{
+        ClassBuilder, CodeBuilder, Constant, FieldBuilder, Insn, MethodBuilder,
+    };
+    use crate::class_path::ClassPathEntry;
+    use crate::instructions::*;
+
+    const OBJECT: &str = "java/lang/Object";
+
+    /// A class with `methods` given by flags, name and descriptor, with empty bodies.
+    fn with_methods(
+        name: &str,
+        super_name: &str,
+        flags: &[ClassAccessFlags],
+        methods: &[(&[MethodAccessFlags], &str, &str)],
+    ) -> ClassBuilder {
+        let mut class = class(flags, name, super_name, &[]);
+        for (flags, name, descriptor) in methods {
+            let mut method = MethodBuilder::new(flags, name, descriptor);
+            if !flags.contains(&MethodAccessFlags::AccAbstract) {
+                let mut code = CodeBuilder::new(1, 4);
+                if descriptor.ends_with('V') {
+                    code.push(Insn::Simple(RETURN));
+                } else {
+                    code.push(Insn::Simple(ACONST_NULL));
+                    code.push(Insn::Simple(ARETURN));
+                }
+                method.set_code(code);
+            }
+            class.add_method(method).unwrap();
+        }
+        class
+    }
+
+    fn constant(value: i32) -> FieldBuilder {
+        let flags = [
+            FieldAccessFlags::AccPublic,
+            FieldAccessFlags::AccStatic,
+            FieldAccessFlags::AccFinal,
+        ];
+        let mut field = FieldBuilder::new(&flags, "LIMIT", "I");
+        field.set_constant_value(Constant::Integer(value));
+        field
+    }
+
+    #[test]
+    fn test_changed_members() {
+        use MethodAccessFlags::{AccProtected, AccPublic, AccStatic};
+        let public = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper];
+        let mut old = with_methods(
+            "test/Api",
+            OBJECT,
+            &public,
+            &[
+                (&[AccPublic], "run", "()V"),
+                (&[AccPublic], "size", "(I)Ljava/lang/Object;"),
+                (&[AccProtected], "hook", "()V"),
+                (&[AccPublic, AccStatic], "make", "()V"),
+                (&[], "internal", "()V"),
+            ],
+        );
+        old.add_field(constant(1)).unwrap();
+        let mut new = with_methods(
+            "test/Api",
+            OBJECT,
+            &public,
+            &[
+                (&[AccPublic], "size", "(J)Ljava/lang/Object;"),
+                (&[], "hook", "()V"),
+                (&[AccPublic], "make", "()V"),
+            ],
+        );
+        new.add_field(constant(2)).unwrap();
+        let diff = ApiDiff::new(&[read_back(old)], &[read_back(new)], &ClassPath::new()).unwrap();
+
+        let changes: Vec<String> = diff
+            .changes
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                "test.Api.LIMIT:I: ConstantValueChanged: value changed from 1 to 2 \
+                 (behavior changed, JLS 13.4.9)",
+                "test.Api.run()V: MethodRemoved: the method was removed \
+                 (binary incompatible, JLS 13.4.12)",
+                "test.Api.size(I)Ljava/lang/Object;: MethodDescriptorChanged: now declared as \
+                 size(J)Ljava/lang/Object; (binary incompatible, JLS 13.4.14)",
+                "test.Api.hook()V: MemberAccessNarrowed: access changed from protected to \
+                 package (binary incompatible, JLS 13.4.7)",
+                "test.Api.make()V: MethodStaticChanged: is no longer static \
+                 (binary incompatible, JLS 13.4.19)",
+            ]
+        );
+        assert!(!diff.is_compatible());
+    }
+
+    #[test]
+    fn test_interface_additions() {
+        use MethodAccessFlags::{AccAbstract, AccPublic};
+        let interface = [
+            ClassAccessFlags::AccPublic,
+            ClassAccessFlags::AccInterface,
+            ClassAccessFlags::AccAbstract,
+        ];
+        let on: (&[MethodAccessFlags], &str, &str) = (&[AccPublic, AccAbstract], "on", "()V");
+        let old = read_back(with_methods("test/Listener", OBJECT, &interface, &[on]));
+        let with_default = read_back(with_methods(
+            "test/Listener",
+            OBJECT,
+            &interface,
+            &[on, (&[AccPublic], "describe", "()V")],
+        ));
+        let with_abstract = read_back(with_methods(
+            "test/Listener",
+            OBJECT,
+            &interface,
+            &[on, (&[AccPublic, AccAbstract], "off", "()V")],
+        ));
+
+        let class_path = ClassPath::new();
+        let diff = ApiDiff::new(std::slice::from_ref(&old), &[with_default], &class_path).unwrap();
+        assert!(diff.changes.is_empty());
+        let diff = ApiDiff::new(&[old], &[with_abstract], &class_path).unwrap();
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].kind, ChangeKind::AbstractMethodAdded);
+        assert_eq!(
+            diff.changes[0].get_compatibility(),
+            Compatibility::SourceIncompatible
+        );
+        assert_eq!(diff.changes[0].get_section(), "13.5.4");
+        assert!(!diff.is_compatible());
+    }
+
+    #[test]
+    fn test_removed_classes_and_supertypes() {
+        let public = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper];
+        let mut old_class = with_methods("test/Model", OBJECT, &public, &[]);
+        old_class.add_interface("java/io/Serializable").unwrap();
+        let old = [
+            read_back(old_class),
+            read_back(with_methods("test/Helper", OBJECT, &public, &[])),
+            read_back(with_methods(
+                "test/Internal",
+                OBJECT,
+                &[ClassAccessFlags::AccSuper],
+                &[],
+            )),
+        ];
+        let new = [read_back(with_methods("test/Model", OBJECT, &public, &[]))];
+        let diff = ApiDiff::new(&old, &new, &ClassPath::new()).unwrap();
+
+        let kinds: Vec<(&str, &ChangeKind)> = diff
+            .changes
+            .iter()
+            .map(|change| (change.class.as_str(), &change.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("test.Helper", &ChangeKind::ClassRemoved),
+                (
+                    "test.Model",
+                    &ChangeKind::SupertypeRemoved {
+                        supertype: "java.io.Serializable".into()
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_supertypes_on_the_class_path() {
+        let public = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper];
+        let mut old_class = with_methods("test/Model", OBJECT, &public, &[]);
+        old_class.add_interface("java/io/Serializable").unwrap();
+        let old = [read_back(old_class)];
+        let new = [read_back(with_methods(
+            "test/Model",
+            "lib/Base",
+            &public,
+            &[],
+        ))];
+
+        // Without lib/Base, its supertypes are unknown rather than removed.
+        let diff = ApiDiff::new(&old, &new, &ClassPath::new()).unwrap();
+        assert_eq!(diff.changes, []);
+
+        let directory = env::temp_dir().join(format!("jvm_rs_compat_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("lib")).unwrap();
+        let base = with_methods("lib/Base", OBJECT, &public, &[]);
+        fs::write(directory.join("lib/Base.class"), base.into_bytes().unwrap()).unwrap();
+        let mut class_path = ClassPath::new();
+        class_path.push(ClassPathEntry::Directory(directory.clone()));
+        let diff = ApiDiff::new(&old, &new, &class_path).unwrap();
+        let _ = fs::remove_dir_all(&directory);
+
+        let kinds: Vec<&ChangeKind> = diff.changes.iter().map(|change| &change.kind).collect();
+        assert_eq!(
+            kinds,
+            [&ChangeKind::SupertypeRemoved {
+                supertype: "java.io.Serializable".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_inherited_methods_are_not_removed() {
+        use MethodAccessFlags::AccPublic;
+        let public = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper];
+        let run: (&[MethodAccessFlags], &str, &str) = (&[AccPublic], "run", "()V");
+        let stop: (&[MethodAccessFlags], &str, &str) = (&[AccPublic], "stop", "()V");
+        let old = [read_back(with_methods(
+            "test/Api",
+            OBJECT,
+            &public,
+            &[run, stop],
+        ))];
+        let new = [
+            read_back(with_methods("test/Api", "test/Base", &public, &[])),
+            read_back(with_methods("test/Base", OBJECT, &public, &[run])),
+        ];
+        let diff = ApiDiff::new(&old, &new, &ClassPath::new()).unwrap();
+
+        let changes: Vec<(Option<&str>, &ChangeKind)> = diff
+            .changes
+            .iter()
+            .map(|change| (change.member.as_deref(), &change.kind))
+            .collect();
+        assert_eq!(changes, [(Some("stop()V"), &ChangeKind::MethodRemoved)]);
+    }
+}
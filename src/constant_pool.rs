@@ -0,0 +1,544 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::attributes::{Annotation, Element, ElementPairs, StackMapFrame, VerificationTypeInfo};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::class_writer::write_constant;
+use crate::errors::class_writing::{WritingCause, WritingError};
+use crate::instructions::{decode, Operands, LDC};
+
+/// Called with each constant pool index in a class, which it may replace.
+type Visitor<'a> = dyn FnMut(&mut u16) -> Result<(), Box<dyn Error>> + 'a;
+
+/**
+ * Rebuilds the constant pool of `class` from what the class refers to: entries nothing
+ * refers to are dropped, identical entries are merged, and every index in the class, its
+ * members, their attributes and the operands of their code is renumbered to match.\
+ *  The entries that remain keep their order, so no index grows and an ldc operand still
+ *  fits in its byte. A class with an attribute this crate does not parse is rejected, as
+ *  the indices inside it cannot be renumbered.
+ */
+pub fn compact(class: &mut ClassFile) -> Result<(), Box<dyn Error>> {
+    let count = class.constant_pool_count as usize;
+
+    // Everything the class refers to directly, then whatever those entries refer to.
+    let mut live = vec![false; count];
+    let mut pending = vec![];
+    visit_class(class, &mut |index| {
+        let index = *index as usize;
+        // Indices past the pool are the reader's implicit StackMapTable names.
+        if index != 0 && index < count && !live[index] {
+            live[index] = true;
+            pending.push(index);
+        }
+        Ok(())
+    })?;
+    while let Some(index) = pending.pop() {
+        let constant = &mut class.constant_pool[index];
+        for child in constant_indices(constant) {
+            let child = *child as usize;
+            if child == 0 || child >= count {
+                return Err(missing_constant(index, child));
+            }
+            if !live[child] {
+                live[child] = true;
+                pending.push(child);
+            }
+        }
+    }
+
+    // Entries are equal when their encodings are once their own references are merged,
+    // so entries are compared after the ones they refer to.
+    let mut canonical: Vec<u16> = (0..count as u16).collect();
+    for rank in 0..=3 {
+        let mut first: HashMap<Vec<u8>, u16> = HashMap::new();
+        for index in (1..count).filter(|&index| live[index]) {
+            if get_rank(&class.constant_pool[index]) != rank {
+                continue;
+            }
+            let mut constant = class.constant_pool[index].clone();
+            for child in constant_indices(&mut constant) {
+                *child = canonical[*child as usize];
+            }
+            let mut key = vec![];
+            write_constant(&constant, &mut key)?;
+            canonical[index] = *first.entry(key).or_insert(index as u16);
+        }
+    }
+
+    let mut renumbered = vec![0; count];
+    let mut pool = vec![ConstantPool::Unknown];
+    for index in 1..count {
+        if !live[index] || canonical[index] as usize != index {
+            continue;
+        }
+        renumbered[index] = pool.len() as u16;
+        let constant = class.constant_pool[index].clone();
+        // §4.4.5: a long or double takes two entries.
+        let wide = matches!(constant, ConstantPool::Long(_) | ConstantPool::Double(_));
+        pool.push(constant);
+        if wide {
+            pool.push(ConstantPool::Unknown);
+        }
+    }
+    for index in (1..count).filter(|&index| live[index]) {
+        renumbered[index] = renumbered[canonical[index] as usize];
+    }
+    for constant in &mut pool {
+        for child in constant_indices(constant) {
+            *child = renumbered[*child as usize];
+        }
+    }
+
+    let new_count = pool.len() as u16;
+    // Keeps whatever the reader appended past the pool, such as the implicit StackMapTable name.
+    pool.extend(
+        class
+            .constant_pool
+            .drain(count.min(class.constant_pool.len())..),
+    );
+    visit_class(class, &mut |index| {
+        if *index as usize >= count {
+            *index = *index - count as u16 + new_count;
+        } else if *index != 0 {
+            *index = renumbered[*index as usize];
+        }
+        Ok(())
+    })?;
+    class.constant_pool = pool;
+    class.constant_pool_count = new_count;
+    Ok(())
+}
+
+/// Merging proceeds from the entries referring to nothing up to method handles.
+fn get_rank(constant: &ConstantPool) -> u8 {
+    match constant {
+        ConstantPool::Class(_)
+        | ConstantPool::String(_)
+        | ConstantPool::NameAndType(_)
+        | ConstantPool::MethodType(_)
+        | ConstantPool::Module(_)
+        | ConstantPool::Package(_) => 1,
+        ConstantPool::Fieldref(_)
+        | ConstantPool::Methodref(_)
+        | ConstantPool::InterfaceMethodRef(_)
+        | ConstantPool::Dynamic(_)
+        | ConstantPool::InvokeDynamic(_) => 2,
+        ConstantPool::MethodHandle(_) => 3,
+        _ => 0,
+    }
+}
+
+/// The constant pool indices held by a constant pool entry.
+fn constant_indices(constant: &mut ConstantPool) -> Vec<&mut u16> {
+    match constant {
+        ConstantPool::Class(class) => vec![&mut class.name_index],
+        ConstantPool::String(string) => vec![&mut string.string_index],
+        ConstantPool::Fieldref(field) => {
+            vec![&mut field.class_index, &mut field.name_and_type_index]
+        }
+        ConstantPool::Methodref(method) => {
+            vec![&mut method.class_index, &mut method.name_and_type_index]
+        }
+        ConstantPool::InterfaceMethodRef(method) => {
+            vec![&mut method.class_index, &mut method.name_and_type_index]
+        }
+        ConstantPool::NameAndType(name_and_type) => vec![
+            &mut name_and_type.name_index,
+            &mut name_and_type.descriptor_index,
+        ],
+        ConstantPool::MethodHandle(handle) => vec![&mut handle.reference_index],
+        ConstantPool::MethodType(method_type) => vec![&mut method_type.descriptor_index],
+        // The bootstrap_method_attr_index is an index into BootstrapMethods, not the pool.
+        ConstantPool::Dynamic(dynamic) => vec![&mut dynamic.name_and_type_index],
+        ConstantPool::InvokeDynamic(dynamic) => vec![&mut dynamic.name_and_type_index],
+        ConstantPool::Module(module) => vec![&mut module.name_index],
+        ConstantPool::Package(package) => vec![&mut package.name_index],
+        _ => vec![],
+    }
+}
+
+/// Visits every constant pool index outside the pool itself, including zeros.
+fn visit_class(class: &mut ClassFile, visit: &mut Visitor) -> Result<(), Box<dyn Error>> {
+    visit(&mut class.this_class)?;
+    visit(&mut class.super_class)?;
+    for interface in &mut class.interfaces {
+        visit(interface)?;
+    }
+    for field in &mut class.fields {
+        visit(&mut field.name_index)?;
+        visit(&mut field.descriptor_index)?;
+        visit_attributes(&mut field.attributes, visit)?;
+    }
+    for method in &mut class.methods {
+        visit(&mut method.name_index)?;
+        visit(&mut method.descriptor_index)?;
+        visit_attributes(&mut method.attributes, visit)?;
+    }
+    visit_attributes(&mut class.attributes, visit)
+}
+
+fn visit_attributes(
+    attributes: &mut [AttributeInfo],
+    visit: &mut Visitor,
+) -> Result<(), Box<dyn Error>> {
+    for attribute in attributes {
+        visit(attribute.name_index_mut())?;
+        visit_attribute(attribute, visit)?;
+    }
+    Ok(())
+}
+
+fn visit_attribute(
+    attribute: &mut AttributeInfo,
+    visit: &mut Visitor,
+) -> Result<(), Box<dyn Error>> {
+    match attribute {
+        AttributeInfo::ConstantValue(value) => visit(&mut value.constantvalue_index)?,
+        AttributeInfo::Code(code) => {
+            for handler in &mut code.exception_tables {
+                visit(&mut handler.catch_type)?;
+            }
+            visit_code(&mut code.code, visit)?;
+            visit_attributes(&mut code.attributes, visit)?;
+        }
+        AttributeInfo::StackMapTable(table) => {
+            for frame in &mut table.entries {
+                let types: Vec<&mut VerificationTypeInfo> = match frame {
+                    StackMapFrame::SameLocals1StackItemFrame { stack, .. }
+                    | StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => {
+                        stack.iter_mut().collect()
+                    }
+                    StackMapFrame::AppendFrame { locals, .. } => locals.iter_mut().collect(),
+                    StackMapFrame::FullFrame { locals, stack, .. } => {
+                        locals.iter_mut().chain(stack.iter_mut()).collect()
+                    }
+                    _ => vec![],
+                };
+                for verification_type in types {
+                    if let VerificationTypeInfo::ObjectVariable(index) = verification_type {
+                        visit(index)?;
+                    }
+                }
+            }
+        }
+        AttributeInfo::Exceptions(exceptions) => {
+            for exception in &mut exceptions.exception_index_table {
+                visit(exception)?;
+            }
+        }
+        AttributeInfo::InnerClasses(inner_classes) => {
+            for class in &mut inner_classes.classes {
+                visit(&mut class.inner_class_info_index)?;
+                visit(&mut class.outer_class_info_index)?;
+                visit(&mut class.inner_name_index)?;
+            }
+        }
+        AttributeInfo::EnclosingMethod(method) => {
+            visit(&mut method.class_index)?;
+            visit(&mut method.method_index)?;
+        }
+        AttributeInfo::Signature(signature) => visit(&mut signature.signature_index)?,
+        AttributeInfo::SourceFile(source) => visit(&mut source.sourcefile_index)?,
+        AttributeInfo::LocalVariableTable(table) => {
+            for local in &mut table.local_variable_table {
+                visit(&mut local.name_index)?;
+                visit(&mut local.descriptor_index)?;
+            }
+        }
+        AttributeInfo::LocalVariableTypeTable(table) => {
+            for local in &mut table.local_variable_type_table {
+                visit(&mut local.name_index)?;
+                visit(&mut local.signature_index)?;
+            }
+        }
+        AttributeInfo::RuntimeVisibleAnnotations(annotations) => {
+            visit_annotations(&mut annotations.annotations, visit)?
+        }
+        AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+            visit_annotations(&mut annotations.annotations, visit)?
+        }
+        AttributeInfo::RuntimeVisibleParameterAnnotations(parameters) => {
+            for parameter in &mut parameters.parameter_annotations {
+                visit_annotations(&mut parameter.annotations, visit)?;
+            }
+        }
+        AttributeInfo::RuntimeInvisibleParameterAnnotations(parameters) => {
+            for parameter in &mut parameters.parameter_annotations {
+                visit_annotations(&mut parameter.annotations, visit)?;
+            }
+        }
+        AttributeInfo::RuntimeVisibleTypeAnnotations(annotations) => {
+            for annotation in &mut annotations.type_annotations {
+                visit(&mut annotation.type_index)?;
+                visit_pairs(&mut annotation.element_value_pairs, visit)?;
+            }
+        }
+        AttributeInfo::RuntimeInvisibleTypeAnnotations(annotations) => {
+            for annotation in &mut annotations.type_annotations {
+                visit(&mut annotation.type_index)?;
+                visit_pairs(&mut annotation.element_value_pairs, visit)?;
+            }
+        }
+        AttributeInfo::AnnotationDefault(default) => {
+            visit_element(&mut default.default_value, visit)?
+        }
+        AttributeInfo::BootstrapMethods(bootstrap) => {
+            for method in &mut bootstrap.bootstrap_methods {
+                visit(&mut method.bootstrap_method_ref)?;
+                for argument in &mut method.bootstrap_arguments {
+                    visit(argument)?;
+                }
+            }
+        }
+        AttributeInfo::MethodParameters(parameters) => {
+            for parameter in &mut parameters.parameters {
+                visit(&mut parameter.name_index)?;
+            }
+        }
+        AttributeInfo::Module(module) => {
+            visit(&mut module.module_name_index)?;
+            visit(&mut module.module_version_index)?;
+            for requires in &mut module.requires {
+                visit(&mut requires.requires_index)?;
+                visit(&mut requires.requires_version_index)?;
+            }
+            for exports in &mut module.exports {
+                visit(&mut exports.exports_index)?;
+                for index in &mut exports.exports_to_index {
+                    visit(index)?;
+                }
+            }
+            for opens in &mut module.opens {
+                visit(&mut opens.opens_index)?;
+                for index in &mut opens.opens_to_index {
+                    visit(index)?;
+                }
+            }
+            for uses in &mut module.uses_index {
+                visit(uses)?;
+            }
+            for provides in &mut module.provides {
+                visit(&mut provides.provides_index)?;
+                for index in &mut provides.provides_with_index {
+                    visit(index)?;
+                }
+            }
+        }
+        AttributeInfo::ModulePackages(packages) => {
+            for package in &mut packages.package_index {
+                visit(package)?;
+            }
+        }
+        AttributeInfo::ModuleMainClass(main) => visit(&mut main.main_class_index)?,
+        AttributeInfo::NestHost(host) => visit(&mut host.host_class_index)?,
+        AttributeInfo::NestMembers(members) => {
+            for member in &mut members.classes {
+                visit(member)?;
+            }
+        }
+        AttributeInfo::Record(record) => {
+            for component in &mut record.components {
+                visit(&mut component.name_index)?;
+                visit(&mut component.descriptor_index)?;
+                visit_attributes(&mut component.attributes, visit)?;
+            }
+        }
+        AttributeInfo::PermittedSubclasses(subclasses) => {
+            for subclass in &mut subclasses.classes {
+                visit(subclass)?;
+            }
+        }
+        AttributeInfo::ScalaInlineInfo(info) => {
+            if let Some(self_type) = &mut info.self_type {
+                visit(self_type)?;
+            }
+            if let Some((name, descriptor)) = &mut info.sam {
+                visit(name)?;
+                visit(descriptor)?;
+            }
+            for method in &mut info.methods {
+                visit(&mut method.name_index)?;
+                visit(&mut method.descriptor_index)?;
+            }
+        }
+        AttributeInfo::Unknown(unknown) => {
+            return Err(Box::new(WritingError::new(
+                WritingCause::UnknownAttribute(unknown.get_name().into()),
+                "the constant pool indices in an unparsed attribute cannot be renumbered",
+            )))
+        }
+        AttributeInfo::Synthetic(_)
+        | AttributeInfo::SourceDebugExtension(_)
+        | AttributeInfo::LineNumberTable(_)
+        | AttributeInfo::Deprecated(_)
+        | AttributeInfo::ScalaSig(_)
+        | AttributeInfo::Scala(_)
+        | AttributeInfo::Tasty(_) => {}
+    }
+    Ok(())
+}
+
+/// Visits the operands of the instructions that take a constant pool index, in place.
+fn visit_code(code: &mut [u8], visit: &mut Visitor) -> Result<(), Box<dyn Error>> {
+    for instruction in decode(code)? {
+        let mut index = match instruction.operands {
+            Operands::Constant(index)
+            | Operands::InvokeInterface { index, .. }
+            | Operands::InvokeDynamic(index)
+            | Operands::MultiANewArray { index, .. } => index,
+            _ => continue,
+        };
+        visit(&mut index)?;
+        let at = instruction.pc as usize + 1;
+        if instruction.opcode == LDC {
+            code[at] = u8::try_from(index).map_err(|_| {
+                WritingError::new(
+                    WritingCause::TooLarge("ldc".into()),
+                    &format!("constant {index} at pc {} needs ldc_w", instruction.pc),
+                )
+            })?;
+        } else {
+            code[at..at + 2].copy_from_slice(&index.to_be_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn visit_annotations(
+    annotations: &mut [Annotation],
+    visit: &mut Visitor,
+) -> Result<(), Box<dyn Error>> {
+    for annotation in annotations {
+        visit(&mut annotation.type_index)?;
+        visit_pairs(&mut annotation.element_value_pairs, visit)?;
+    }
+    Ok(())
+}
+
+fn visit_pairs(pairs: &mut [ElementPairs], visit: &mut Visitor) -> Result<(), Box<dyn Error>> {
+    for pair in pairs {
+        visit(&mut pair.element_name_index)?;
+        visit_element(&mut pair.value, visit)?;
+    }
+    Ok(())
+}
+
+fn visit_element(element: &mut Element, visit: &mut Visitor) -> Result<(), Box<dyn Error>> {
+    match element {
+        Element::ConstValueIndex(_, index) | Element::ClassInfoIndex(index) => visit(index),
+        Element::EnumConstValue {
+            type_name_index,
+            const_name_index,
+        } => {
+            visit(type_name_index)?;
+            visit(const_name_index)
+        }
+        Element::Annotation(annotation) => {
+            visit_annotations(std::slice::from_mut(annotation), visit)
+        }
+        Element::ArrayValue { values, .. } => {
+            for value in values {
+                visit_element(value, visit)?;
+            }
+            Ok(())
+        }
+        Element::Unknown(_) => Ok(()),
+    }
+}
+
+fn missing_constant(index: usize, child: usize) -> Box<dyn Error> {
+    Box::new(WritingError::new(
+        WritingCause::MissingConstant(child.to_string()),
+        &format!("constant {index} refers to an entry outside the constant pool"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::access_flags::{ClassAccessFlags, MethodAccessFlags};
+    use crate::attributes::Unknown;
+    use crate::class_builder::{ClassBuilder, CodeBuilder, Constant, Insn, MethodBuilder};
+    use crate::constants::{self, Utf8};
+    use crate::instructions::RETURN;
+
+    /// A class whose only method loads a string, with `pad` adding constants before it.
+    fn build(pad: impl Fn(&mut ClassBuilder)) -> ClassFile {
+        let mut class =
+            ClassBuilder::new(&[ClassAccessFlags::AccPublic], "test/Compact", None).unwrap();
+        pad(&mut class);
+        let mut code = CodeBuilder::new(1, 0);
+        code.push(Insn::Ldc(Constant::String("hello".into())));
+        code.push(Insn::Simple(RETURN));
+        let mut method = MethodBuilder::new(&[MethodAccessFlags::AccStatic], "run", "()V");
+        method.set_code(code);
+        class.add_method(method).unwrap();
+        class.build().unwrap()
+    }
+
+    fn loaded_string(class: &ClassFile) -> String {
+        let AttributeInfo::Code(code) = &class.methods[0].attributes[0] else {
+            panic!("expected the code of run");
+        };
+        let instructions = decode(&code.code).unwrap();
+        let Operands::Constant(index) = instructions[0].operands else {
+            panic!("expected ldc");
+        };
+        let Some(ConstantPool::String(string)) = class.constant_pool.get(index as usize) else {
+            panic!("ldc does not load a string");
+        };
+        constants::get_utf8(&class.constant_pool, string.string_index).unwrap()
+    }
+
+    #[test]
+    fn test_drop_unused_and_duplicate_constants() {
+        let mut clean = build(|_| {});
+        clean.interfaces = vec![clean.this_class; 2];
+        let mut class = build(|class| {
+            let pool = class.get_constant_pool();
+            pool.add_utf8("unused").unwrap();
+            pool.add_long(7).unwrap();
+            pool.add_class("test/Unused").unwrap();
+        });
+        // The same references as the clean class, the second through copies of the entries.
+        let name = class
+            .add_constant(ConstantPool::Utf8(Utf8::from("test/Compact")))
+            .unwrap();
+        let mut copy = class.constant_pool[class.this_class as usize].clone();
+        if let ConstantPool::Class(copy) = &mut copy {
+            copy.name_index = name;
+        }
+        class.interfaces = vec![class.this_class, class.add_constant(copy).unwrap()];
+        assert_eq!(class.constant_pool_count, clean.constant_pool_count + 7);
+
+        compact(&mut class).unwrap();
+        assert_eq!(class.constant_pool_count, clean.constant_pool_count);
+        assert_eq!(class.interfaces, clean.interfaces);
+        let class = ClassFile::from_bytes(&class.to_bytes().unwrap()).unwrap();
+        assert_eq!(class.get_name(), "test.Compact");
+        assert_eq!(loaded_string(&class), "hello");
+        assert_eq!(class.to_bytes().unwrap(), clean.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_keep_compact_pool_unchanged() {
+        let mut class = build(|_| {});
+        let bytes = class.to_bytes().unwrap();
+        compact(&mut class).unwrap();
+        assert_eq!(class.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_reject_unknown_attributes() {
+        let mut class = build(|_| {});
+        let name = class
+            .add_constant(ConstantPool::Utf8(Utf8::from("Custom")))
+            .unwrap();
+        let unknown = Unknown::new(name, 0, "Custom".into(), &mut Cursor::new(&[][..])).unwrap();
+        class.attributes.push(AttributeInfo::Unknown(unknown));
+        assert!(compact(&mut class).is_err());
+    }
+}
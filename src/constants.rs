@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use std::{error::Error, io::Cursor, str::from_utf8};
 
 use byteorder::{ReadBytesExt, BE};
@@ -69,16 +71,41 @@ pub struct Utf8 {
 }
 
 impl From<&str> for Utf8 {
+    /// Encodes `input` as modified UTF-8 (§4.4.7).
     fn from(input: &str) -> Self {
+        Utf8::from_utf16(&input.encode_utf16().collect::<Vec<u16>>())
+    }
+}
+
+impl Utf8 {
+    /**
+     * Encodes UTF-16 code units as modified UTF-8 (§4.4.7), the inverse of `get_utf16`.\
+     *  Unpaired surrogates are encoded like any other unit, as the JVM allows.
+     */
+    pub fn from_utf16(units: &[u16]) -> Utf8 {
+        let mut bytes = Vec::with_capacity(units.len());
+        for &unit in units {
+            match unit {
+                0x0001..=0x007F => bytes.push(unit as u8),
+                0x0000 | 0x0080..=0x07FF => {
+                    bytes.push(0xC0 | (unit >> 6) as u8);
+                    bytes.push(0x80 | (unit & 0x3F) as u8);
+                }
+                _ => {
+                    bytes.push(0xE0 | (unit >> 12) as u8);
+                    bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                    bytes.push(0x80 | (unit & 0x3F) as u8);
+                }
+            }
+        }
+        // A longer string has no valid length item, and writing it fails on its bytes.
         Utf8 {
             tag: 1,
-            length: input.len() as u16,
-            bytes: input.as_bytes().to_vec(),
+            length: u16::try_from(bytes.len()).unwrap_or(u16::MAX),
+            bytes,
         }
     }
-}
 
-impl Utf8 {
     pub fn new(tag: Tags, cursor: &mut Cursor<&[u8]>) -> Utf8 {
         let length = cursor.read_u16::<BE>().unwrap();
         Utf8 {
@@ -94,6 +121,37 @@ impl Utf8 {
         }
     }
 
+    /**
+     * Decodes the modified UTF-8 bytes (§4.4.7) into UTF-16 code units.\
+     *  Unlike standard UTF-8, the null character is encoded in two bytes and supplementary
+     *  characters are encoded as surrogate pairs of three bytes each, so this never fails.
+     */
+    pub fn get_utf16(&self) -> Vec<u16> {
+        let mut chars = Vec::with_capacity(self.bytes.len());
+        let mut index = 0;
+        while index < self.bytes.len() {
+            let x = self.bytes[index] as u16;
+            let y = *self.bytes.get(index + 1).unwrap_or(&0) as u16;
+            let z = *self.bytes.get(index + 2).unwrap_or(&0) as u16;
+            if x & 0x80 == 0 {
+                chars.push(x);
+                index += 1;
+            } else if x & 0xE0 == 0xC0 {
+                chars.push(((x & 0x1F) << 6) | (y & 0x3F));
+                index += 2;
+            } else {
+                chars.push(((x & 0x0F) << 12) | ((y & 0x3F) << 6) | (z & 0x3F));
+                index += 3;
+            }
+        }
+        chars
+    }
+
+    /// The string exactly as stored, without the `/` to `.` conversion done by `get_string`.
+    pub fn get_raw_string(&self) -> std::string::String {
+        std::string::String::from_utf16_lossy(&self.get_utf16())
+    }
+
     pub fn get_string(&self) -> std::string::String {
         let output = std::string::String::from(
             from_utf8(&self.bytes).unwrap_or("Could not create from utf8"),
@@ -129,6 +187,10 @@ impl Integer {
             bytes,
         }
     }
+
+    pub fn get_value(&self) -> i32 {
+        self.bytes as i32
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -166,6 +228,10 @@ impl Float {
             bytes,
         }
     }
+
+    pub fn get_value(&self) -> f32 {
+        f32::from_bits(self.bytes)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -218,6 +284,10 @@ impl Long {
             low_bytes,
         }
     }
+
+    pub fn get_value(&self) -> i64 {
+        ((self.high_bytes as u64) << 32 | self.low_bytes as u64) as i64
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -270,6 +340,10 @@ impl Double {
             low_bytes,
         }
     }
+
+    pub fn get_value(&self) -> f64 {
+        f64::from_bits((self.high_bytes as u64) << 32 | self.low_bytes as u64)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -542,6 +616,22 @@ impl MethodHandle {
             reference_index,
         }
     }
+
+    /// The name of the reference_kind as given in Table 5.4.3.5-A, e.g. `REF_invokeStatic`.
+    pub fn get_kind_name(&self) -> &'static str {
+        match self.reference_kind {
+            1 => "REF_getField",
+            2 => "REF_getStatic",
+            3 => "REF_putField",
+            4 => "REF_putStatic",
+            5 => "REF_invokeVirtual",
+            6 => "REF_invokeStatic",
+            7 => "REF_invokeSpecial",
+            8 => "REF_newInvokeSpecial",
+            9 => "REF_invokeInterface",
+            _ => "REF_unknown",
+        }
+    }
 }
 
 /// [Dynamic Constant](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A3782%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C370.8%2Cnull%5D)
@@ -678,10 +768,11 @@ impl Package {
 
 pub fn read_constant_pool(
     pool: &mut Vec<crate::class_file::ConstantPool>,
+    constant_pool_count: u16,
     cursor: &mut Cursor<&[u8]>,
 ) -> Result<(), Box<dyn Error>> {
     use crate::class_file::ConstantPool;
-    for _ in 0..pool.capacity() {
+    while pool.len() < constant_pool_count as usize {
         let tag = cursor.read_u8()?;
         pool.push(match Tags::from(tag) {
             Tags::Utf8 => ConstantPool::Utf8(Utf8::new(Tags::from(tag), cursor)),
@@ -758,6 +849,62 @@ pub fn read_constant_pool(
                 )))
             }
         });
+        // §4.4.5: the entry following a long or double is valid but unusable.
+        if let Some(ConstantPool::Long(_) | ConstantPool::Double(_)) = pool.last() {
+            pool.push(ConstantPool::Unknown);
+        }
     }
     Ok(())
 }
+
+/// Resolves a CONSTANT_Utf8_info entry, returning `None` if the index does not point at one.
+pub fn get_utf8(
+    constant_pool: &[crate::class_file::ConstantPool],
+    index: u16,
+) -> Option<std::string::String> {
+    use crate::class_file::ConstantPool;
+    match constant_pool.get(index as usize) {
+        Some(ConstantPool::Utf8(utf8)) => Some(utf8.get_string()),
+        _ => None,
+    }
+}
+
+/// Resolves the name of a CONSTANT_Class_info entry, returning `None` if the index does not
+/// point at one.
+pub fn get_class_name(
+    constant_pool: &[crate::class_file::ConstantPool],
+    index: u16,
+) -> Option<std::string::String> {
+    use crate::class_file::ConstantPool;
+    match constant_pool.get(index as usize) {
+        Some(ConstantPool::Class(class)) => get_utf8(constant_pool, class.name_index),
+        _ => None,
+    }
+}
+
+/**
+ * Resolves a CONSTANT_Utf8_info entry as stored, keeping the `/`s of internal names that
+ * [get_utf8] turns into `.`s. `None` if the index does not point at one.
+ */
+pub fn get_raw_utf8(
+    constant_pool: &[crate::class_file::ConstantPool],
+    index: u16,
+) -> Option<std::string::String> {
+    use crate::class_file::ConstantPool;
+    match constant_pool.get(index as usize) {
+        Some(ConstantPool::Utf8(utf8)) => Some(utf8.get_raw_string()),
+        _ => None,
+    }
+}
+
+/// Resolves the internal name of a CONSTANT_Class_info entry, such as `java/lang/String`.
+pub fn get_raw_class_name(
+    constant_pool: &[crate::class_file::ConstantPool],
+    index: u16,
+) -> Option<std::string::String> {
+    use crate::class_file::ConstantPool;
+    match constant_pool.get(index as usize) {
+        Some(ConstantPool::Class(class)) => get_raw_utf8(constant_pool, class.name_index),
+        _ => None,
+    }
+}
@@ -0,0 +1,5674 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::error::Error;
+
+use crate::access_flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::attributes::{Annotation, Code, Element, InnerClassInfo};
+use crate::cfg::{ControlFlowGraph, EdgeKind};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool, FieldInfo, MethodInfo};
+use crate::constants::{get_raw_class_name, get_raw_utf8};
+use crate::descriptors::{FieldType, MethodDescriptor};
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+use crate::floating_decimal::{java_double, java_float};
+use crate::instructions::*;
+use crate::javap::{
+    get_modifiers, quote, Javap, JavapOptions, FIELD_MODIFIERS, INNER_CLASS_MODIFIERS,
+    METHOD_MODIFIERS,
+};
+use crate::records::RecordModel;
+use crate::signatures::{
+    ClassSignature, ClassTypeSignature, MethodSignature, TypeArgument, TypeParameter, TypeSignature,
+};
+
+/// The indentation of one level of nesting.
+const INDENT: &str = "    ";
+
+/// What a class is declared as in source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Class,
+    Interface,
+    Annotation,
+    Enum,
+    Record,
+}
+
+/**
+ * Java-like source for a class file, from its declarations, signatures, local variable
+ * tables and method bodies.\
+ *  Bodies are structured into if, while, for, switch and try statements wherever the control
+ *  flow allows it, with lambdas and string concatenation shown as they are written. A body
+ *  that cannot be decompiled is replaced by its disassembly in comments, so one odd method
+ *  does not hide the rest of the class.
+ */
+pub struct Decompiler<'a> {
+    class: &'a ClassFile,
+    /// The internal name of the class, e.g. `java/util/Map$Entry`.
+    name: String,
+    /// The internal name of the package, empty for the unnamed package.
+    package: String,
+    /// How many lambda bodies deep the method body being decompiled is.
+    depth: Cell<usize>,
+    /// Set to render a stub rather than decompile the bodies.
+    stub: Option<Stub>,
+}
+
+/**
+ * What rendering a class as a stub needs from the classes around it, see
+ * [StubGenerator](crate::stubs::StubGenerator).\
+ *  A stub keeps the declarations of the class that other code compiles against, with bodies
+ *  that throw, and drops what only the class itself can see.
+ */
+pub(crate) struct Stub {
+    /// The stubs of the member classes, declared inside the class.
+    pub(crate) members: Vec<String>,
+    /// Whether a superclass or interface is sealed, so the class must say it is `non-sealed`.
+    pub(crate) non_sealed: bool,
+}
+
+impl<'a> Decompiler<'a> {
+    pub fn new(class: &'a ClassFile) -> Decompiler<'a> {
+        let name = get_raw_class_name(&class.constant_pool, class.this_class).unwrap_or_default();
+        let package = match name.rsplit_once('/') {
+            Some((package, _)) => package.into(),
+            None => String::new(),
+        };
+        Decompiler {
+            class,
+            name,
+            package,
+            depth: Cell::new(0),
+            stub: None,
+        }
+    }
+
+    pub(crate) fn new_stub(class: &'a ClassFile, stub: Stub) -> Decompiler<'a> {
+        Decompiler {
+            stub: Some(stub),
+            ..Decompiler::new(class)
+        }
+    }
+
+    pub fn decompile(&self) -> Result<String, Box<dyn Error>> {
+        let class = self.class;
+        let flags = match self.get_inner_class_info() {
+            Some(info) => ClassAccessFlags::from_u16(info.inner_class_access_flags),
+            None => class.access_flags.clone(),
+        };
+        let kind = if flags.contains(&ClassAccessFlags::AccAnnotation) {
+            Kind::Annotation
+        } else if flags.contains(&ClassAccessFlags::AccInterface) {
+            Kind::Interface
+        } else if flags.contains(&ClassAccessFlags::AccEnum)
+            && self.get_super_name() == "java/lang/Enum"
+        {
+            Kind::Enum
+        } else if class.is_record() {
+            Kind::Record
+        } else {
+            Kind::Class
+        };
+        let record = class.get_record().filter(|_| kind == Kind::Record);
+
+        let mut source = String::new();
+        // The stub of a member class is declared inside the stub of its enclosing class.
+        let is_nested_stub = self.stub.is_some() && self.is_member_class();
+        if !self.package.is_empty() && !is_nested_stub {
+            source.push_str(&format!("package {};\n\n", self.package.replace('/', ".")));
+        }
+        for annotation in self.annotations(&class.attributes) {
+            source.push_str(&annotation);
+            source.push('\n');
+        }
+        source.push_str(&self.header(&flags, kind, record.as_ref()));
+        source.push_str(" {\n");
+
+        let mut members = vec![];
+        let mut static_init = class
+            .methods
+            .iter()
+            .filter(|_| self.stub.is_none())
+            .find(|method| self.utf8(method.name_index) == "<clinit>")
+            .map(|method| (method, MethodBody::new(self, method, &[])));
+        if kind == Kind::Enum {
+            let mut arguments = HashMap::new();
+            if let Some((_, Ok(body))) = &mut static_init {
+                arguments = body.take_enum_constants();
+            }
+            if self.stub.is_some() {
+                arguments = self.stub_enum_arguments();
+            }
+            let constants: Vec<String> = class
+                .fields
+                .iter()
+                .filter(|field| field.access_flags.contains(&FieldAccessFlags::AccEnum))
+                .map(|field| {
+                    let name = self.utf8(field.name_index);
+                    let constant = match arguments.get(&name) {
+                        Some(arguments) if !arguments.is_empty() => {
+                            format!("{INDENT}{name}({})", arguments.join(", "))
+                        }
+                        _ => format!("{INDENT}{name}"),
+                    };
+                    constant + &self.stub_enum_body()
+                })
+                .collect();
+            members.push(format!("{};", constants.join(",\n")));
+        }
+        let fields: Vec<String> = class
+            .fields
+            .iter()
+            .filter_map(|field| self.field(field, kind, record.as_ref()))
+            .collect();
+        if !fields.is_empty() {
+            members.push(fields.join("\n"));
+        }
+        if let Some((method, body)) = static_init {
+            match body {
+                Ok(body) if body.is_empty() => {}
+                Ok(body) => {
+                    members.push(format!("{INDENT}static {{\n{}{INDENT}}}", body.render(2)))
+                }
+                Err(error) => members.push(format!(
+                    "{INDENT}static {{\n{}{INDENT}}}",
+                    self.failure(method, error.as_ref())
+                )),
+            }
+        }
+        for method in &class.methods {
+            if self.utf8(method.name_index) != "<clinit>" {
+                members.extend(self.method(method, kind, record.as_ref(), false));
+            }
+        }
+        if let Some(stub) = &self.stub {
+            members.extend(stub.members.iter().map(|member| indent(member.trim_end())));
+        }
+        source.push_str(&members.join("\n\n"));
+        if !members.is_empty() {
+            source.push('\n');
+        }
+        source.push_str("}\n");
+        Ok(source)
+    }
+
+    /// `public final class Name<T> extends Base implements Interface`, without the body.
+    fn header(
+        &self,
+        flags: &[ClassAccessFlags],
+        kind: Kind,
+        record: Option<&RecordModel>,
+    ) -> String {
+        let class = self.class;
+        let implicit = match kind {
+            Kind::Class => vec![],
+            Kind::Interface | Kind::Annotation => {
+                vec![ClassAccessFlags::AccAbstract, ClassAccessFlags::AccStatic]
+            }
+            Kind::Enum => vec![
+                ClassAccessFlags::AccAbstract,
+                ClassAccessFlags::AccStatic,
+                ClassAccessFlags::AccFinal,
+            ],
+            Kind::Record => vec![ClassAccessFlags::AccStatic, ClassAccessFlags::AccFinal],
+        };
+        let modifiers = ClassAccessFlags::to_u16(flags) & !ClassAccessFlags::to_u16(&implicit);
+        let mut header = get_modifiers(modifiers, &INNER_CLASS_MODIFIERS);
+        // An enum whose constants have bodies is sealed by them, implicitly in source.
+        let is_sealed = class.is_sealed() && kind != Kind::Enum;
+        if is_sealed {
+            header.push_str("sealed ");
+        } else if self.stub.as_ref().is_some_and(|stub| stub.non_sealed)
+            && !flags.contains(&ClassAccessFlags::AccFinal)
+        {
+            header.push_str("non-sealed ");
+        }
+        header.push_str(match kind {
+            Kind::Class => "class ",
+            Kind::Interface => "interface ",
+            Kind::Annotation => "@interface ",
+            Kind::Enum => "enum ",
+            Kind::Record => "record ",
+        });
+        header.push_str(&self.get_simple_name());
+
+        let signature = get_signature(&class.constant_pool, &class.attributes)
+            .and_then(|signature| ClassSignature::parse(&signature).ok());
+        let (superclass, interfaces) = match &signature {
+            Some(signature) => {
+                header.push_str(&self.type_parameters(&signature.type_parameters));
+                let interfaces = signature.interfaces.iter();
+                (
+                    self.class_type(&signature.superclass),
+                    interfaces
+                        .map(|interface| self.class_type(interface))
+                        .collect(),
+                )
+            }
+            None => {
+                let interfaces = class.interfaces.iter();
+                (
+                    self.type_name(&self.get_super_name()),
+                    interfaces
+                        .map(|index| {
+                            self.type_name(
+                                &get_raw_class_name(&class.constant_pool, *index)
+                                    .unwrap_or_default(),
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            }
+        };
+        if let Some(record) = record {
+            let components: Vec<String> = record
+                .components
+                .iter()
+                .map(|component| {
+                    let java_type = match &component.signature {
+                        Some(signature) => {
+                            self.signature_or_descriptor(signature, &component.descriptor)
+                        }
+                        None => self.descriptor_type(&component.descriptor),
+                    };
+                    format!("{java_type} {}", component.name)
+                })
+                .collect();
+            header.push_str(&format!("({})", components.join(", ")));
+        }
+        let interfaces: Vec<String> = interfaces
+            .into_iter()
+            .filter(|interface| {
+                kind != Kind::Annotation || interface != "java.lang.annotation.Annotation"
+            })
+            .collect();
+        let implied_super = match kind {
+            Kind::Class => "Object",
+            Kind::Enum => "Enum",
+            Kind::Record => "Record",
+            Kind::Interface | Kind::Annotation => "",
+        };
+        if !implied_super.is_empty() && !superclass.starts_with(implied_super) {
+            header.push_str(&format!(" extends {superclass}"));
+        }
+        if !interfaces.is_empty() {
+            let keyword = match kind {
+                Kind::Interface | Kind::Annotation => "extends",
+                _ => "implements",
+            };
+            header.push_str(&format!(" {keyword} {}", interfaces.join(", ")));
+        }
+        if let Some(permitted) = class.get_permitted_subclasses().filter(|_| is_sealed) {
+            let permitted: Vec<String> = permitted
+                .iter()
+                .map(|name| self.type_name(&name.replace('.', "/")))
+                .collect();
+            header.push_str(&format!(" permits {}", permitted.join(", ")));
+        }
+        header
+    }
+
+    /// A field declaration, `None` for the fields source does not declare.
+    fn field(&self, field: &FieldInfo, kind: Kind, record: Option<&RecordModel>) -> Option<String> {
+        let flags = &field.access_flags;
+        let name = self.utf8(field.name_index);
+        let is_component = record.is_some_and(|record| {
+            record
+                .components
+                .iter()
+                .any(|component| component.name == name)
+        });
+        if flags.contains(&FieldAccessFlags::AccSynthetic)
+            || kind == Kind::Enum && flags.contains(&FieldAccessFlags::AccEnum)
+            || is_component && !flags.contains(&FieldAccessFlags::AccStatic)
+            || self.stub.is_some() && flags.contains(&FieldAccessFlags::AccPrivate)
+        {
+            return None;
+        }
+        let implicit = match kind {
+            Kind::Interface | Kind::Annotation => vec![
+                FieldAccessFlags::AccPublic,
+                FieldAccessFlags::AccStatic,
+                FieldAccessFlags::AccFinal,
+            ],
+            _ => vec![],
+        };
+        let descriptor = self.utf8(field.descriptor_index);
+        let mut line = String::new();
+        for annotation in self.annotations(&field.attributes) {
+            line.push_str(&format!("{INDENT}{annotation}\n"));
+        }
+        line.push_str(INDENT);
+        let modifiers = FieldAccessFlags::to_u16(flags) & !FieldAccessFlags::to_u16(&implicit);
+        line.push_str(&get_modifiers(modifiers, &FIELD_MODIFIERS));
+        match get_signature(&self.class.constant_pool, &field.attributes) {
+            Some(signature) => {
+                line.push_str(&self.signature_or_descriptor(&signature, &descriptor))
+            }
+            None => line.push_str(&self.descriptor_type(&descriptor)),
+        }
+        line.push(' ');
+        line.push_str(&name);
+        let constant = field
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::ConstantValue(value) => Some(value.constantvalue_index),
+                _ => None,
+            });
+        let field_type = FieldType::from_descriptor(&descriptor).ok();
+        let value = match (constant, &field_type) {
+            (Some(index), Some(field_type)) => self.constant_literal(index, field_type),
+            _ => None,
+        };
+        let is_static_final = flags.contains(&FieldAccessFlags::AccStatic)
+            && flags.contains(&FieldAccessFlags::AccFinal);
+        match (value, field_type) {
+            (Some(value), _) => line.push_str(&format!(" = {value}")),
+            // Stubs have no static initializer to assign the field, and a constant would be
+            // inlined into the code compiled against them.
+            (None, Some(field_type))
+                if self.stub.is_some() && (is_static_final || !implicit.is_empty()) =>
+            {
+                line.push_str(&format!(" = {}", self.stub_value(&field_type, false)));
+            }
+            _ => {}
+        }
+        line.push(';');
+        Some(line)
+    }
+
+    /**
+     * A method declaration with its body, `None` for the methods source does not declare.\
+     *  With `implement`, an abstract method is declared with a stub body, for the body of an
+     *  enum constant.
+     */
+    fn method(
+        &self,
+        method: &MethodInfo,
+        kind: Kind,
+        record: Option<&RecordModel>,
+        implement: bool,
+    ) -> Option<String> {
+        let flags = &method.access_flags;
+        let name = self.utf8(method.name_index);
+        let descriptor_text = self.utf8(method.descriptor_index);
+        let descriptor = MethodDescriptor::from_descriptor(&descriptor_text).ok()?;
+        let is_constructor = name == "<init>";
+        if flags.contains(&MethodAccessFlags::AccSynthetic)
+            || flags.contains(&MethodAccessFlags::AccBridge)
+            || kind == Kind::Enum
+                && (name == "values" && descriptor_text == format!("()[L{};", self.name)
+                    || name == "valueOf"
+                        && descriptor_text == format!("(Ljava/lang/String;)L{};", self.name))
+        {
+            return None;
+        }
+        if self.stub.is_some()
+            && (flags.contains(&MethodAccessFlags::AccPrivate) && !is_constructor
+                || is_constructor
+                    && record
+                        .is_some_and(|record| record.get_canonical_descriptor() == descriptor_text))
+        {
+            return None;
+        }
+
+        let mut body = get_code(method)
+            .filter(|_| self.stub.is_none())
+            .map(|_| MethodBody::new(self, method, &[]));
+        if let Some(Ok(body)) = &mut body {
+            if is_constructor {
+                body.remove_super_call(kind == Kind::Enum);
+            }
+            if let Some(record) = record {
+                if body.is_generated_record_member(record, method, &name) {
+                    return None;
+                }
+            }
+        }
+
+        let mut text = String::new();
+        for annotation in self.annotations(&method.attributes) {
+            text.push_str(&format!("{INDENT}{annotation}\n"));
+        }
+        let mut implicit = vec![];
+        if implement {
+            implicit.push(MethodAccessFlags::AccAbstract);
+        }
+        let mut default = "";
+        if matches!(kind, Kind::Interface | Kind::Annotation) {
+            implicit.push(MethodAccessFlags::AccPublic);
+            if flags.contains(&MethodAccessFlags::AccAbstract) {
+                implicit.push(MethodAccessFlags::AccAbstract);
+            } else if !flags.contains(&MethodAccessFlags::AccStatic)
+                && !flags.contains(&MethodAccessFlags::AccPrivate)
+            {
+                default = "default ";
+            }
+        }
+        if kind == Kind::Enum && is_constructor {
+            implicit.push(MethodAccessFlags::AccPrivate);
+        }
+        let modifiers = MethodAccessFlags::to_u16(flags) & !MethodAccessFlags::to_u16(&implicit);
+        text.push_str(INDENT);
+        text.push_str(&get_modifiers(modifiers, &METHOD_MODIFIERS));
+        text.push_str(default);
+
+        let signature = get_signature(&self.class.constant_pool, &method.attributes)
+            .and_then(|signature| MethodSignature::parse(&signature).ok());
+        if let Some(signature) = &signature {
+            let parameters = self.type_parameters(&signature.type_parameters);
+            if !parameters.is_empty() {
+                text.push_str(&parameters);
+                text.push(' ');
+            }
+        }
+        if is_constructor {
+            text.push_str(&self.get_simple_name());
+        } else {
+            let return_type = match &signature {
+                Some(signature) => signature
+                    .return_type
+                    .as_ref()
+                    .map(|t| self.signature_type(t)),
+                None => descriptor.return_type.as_ref().map(|t| self.field_type(t)),
+            };
+            text.push_str(&return_type.unwrap_or_else(|| "void".into()));
+            text.push(' ');
+            text.push_str(&name);
+        }
+
+        // Enum constructors take the name and ordinal of the constant first, and the
+        // constructors of inner classes the enclosing instance.
+        let skipped = if kind == Kind::Enum && is_constructor {
+            2.min(descriptor.parameters.len())
+        } else if self.stub.is_some() && is_constructor && self.is_inner_class() {
+            1.min(descriptor.parameters.len())
+        } else {
+            0
+        };
+        let names = match &body {
+            Some(Ok(body)) => body.get_parameter_names(),
+            _ => self.parameter_names(method, &descriptor),
+        };
+        let mut types: Vec<String> = descriptor.parameters[skipped..]
+            .iter()
+            .map(|parameter| self.field_type(parameter))
+            .collect();
+        if let Some(signature) = &signature {
+            if signature.parameters.len() == types.len() {
+                types = signature
+                    .parameters
+                    .iter()
+                    .map(|t| self.signature_type(t))
+                    .collect();
+            }
+        }
+        if flags.contains(&MethodAccessFlags::AccVarArgs) {
+            if let Some(last) = types.last_mut() {
+                if let Some(component) = last.strip_suffix("[]") {
+                    *last = format!("{component}...");
+                }
+            }
+        }
+        let parameters: Vec<String> = types
+            .iter()
+            .zip(names.iter().skip(skipped))
+            .map(|(java_type, name)| format!("{java_type} {name}"))
+            .collect();
+        text.push_str(&format!("({})", parameters.join(", ")));
+
+        let throws: Vec<String> = match &signature {
+            Some(signature) if !signature.throws.is_empty() => signature
+                .throws
+                .iter()
+                .map(|t| self.signature_type(t))
+                .collect(),
+            _ => method
+                .attributes
+                .iter()
+                .filter_map(|attribute| match attribute {
+                    AttributeInfo::Exceptions(exceptions) => {
+                        Some(&exceptions.exception_index_table)
+                    }
+                    _ => None,
+                })
+                .flatten()
+                .map(|index| {
+                    self.type_name(
+                        &get_raw_class_name(&self.class.constant_pool, *index).unwrap_or_default(),
+                    )
+                })
+                .collect(),
+        };
+        if !throws.is_empty() {
+            text.push_str(&format!(" throws {}", throws.join(", ")));
+        }
+
+        if self.stub.is_some() && (get_code(method).is_some() || implement) {
+            let indent = INDENT.repeat(2);
+            text.push_str(" {\n");
+            if is_constructor {
+                if let Some(call) = self.stub_constructor_call(method, kind) {
+                    text.push_str(&format!("{indent}{call}\n"));
+                }
+            }
+            text.push_str(&format!(
+                "{indent}throw new RuntimeException(\"Stub!\");\n{INDENT}}}"
+            ));
+            return Some(text);
+        }
+        match body {
+            None => {
+                let default_value =
+                    method
+                        .attributes
+                        .iter()
+                        .find_map(|attribute| match attribute {
+                            AttributeInfo::AnnotationDefault(default) => {
+                                Some(self.element(&default.default_value))
+                            }
+                            _ => None,
+                        });
+                if let Some(value) = default_value {
+                    text.push_str(&format!(" default {value}"));
+                }
+                text.push(';');
+            }
+            Some(Ok(body)) => text.push_str(&format!(" {{\n{}{INDENT}}}", body.render(2))),
+            Some(Err(error)) => text.push_str(&format!(
+                " {{\n{}{INDENT}}}",
+                self.failure(method, error.as_ref())
+            )),
+        }
+        Some(text)
+    }
+
+    /**
+     * The `super(...)` or `this(...)` call a stub constructor starts with, so that it calls the
+     * same constructor as the real one, with default arguments. `None` when that constructor
+     * takes no arguments, as source can leave it out.
+     */
+    fn stub_constructor_call(&self, method: &MethodInfo, kind: Kind) -> Option<String> {
+        if kind == Kind::Enum {
+            return None;
+        }
+        let pool = &self.class.constant_pool;
+        let instructions = decode(&get_code(method)?.code).ok()?;
+        // Each new is initialized before the constructor call that encloses it returns.
+        let mut pending = 0;
+        for instruction in instructions {
+            let index = match (instruction.opcode, &instruction.operands) {
+                (NEW, _) => {
+                    pending += 1;
+                    continue;
+                }
+                (INVOKESPECIAL, Operands::Constant(index)) => *index,
+                _ => continue,
+            };
+            let Some(ConstantPool::Methodref(reference)) = pool.get(index as usize) else {
+                continue;
+            };
+            let (name, descriptor) = name_and_type(pool, reference.name_and_type_index)?;
+            if name != "<init>" {
+                continue;
+            }
+            if pending > 0 {
+                pending -= 1;
+                continue;
+            }
+            let descriptor = MethodDescriptor::from_descriptor(&descriptor).ok()?;
+            let is_this =
+                get_raw_class_name(pool, reference.class_index).unwrap_or_default() == self.name;
+            // The enclosing instance is passed implicitly, as the stub constructors do not
+            // declare it.
+            let skipped = (is_this && self.is_inner_class()) as usize;
+            let arguments: Vec<String> = descriptor
+                .parameters
+                .iter()
+                .skip(skipped)
+                .map(|parameter| self.stub_value(parameter, true))
+                .collect();
+            if arguments.is_empty() {
+                return None;
+            }
+            let keyword = if is_this { "this" } else { "super" };
+            return Some(format!("{keyword}({});", arguments.join(", ")));
+        }
+        None
+    }
+
+    /**
+     * A value for a stub to pass or assign: an argument cast to its type so that it picks the
+     * right overload, or else an initializer that is not a constant, so that code compiled
+     * against the stub does not inline it.
+     */
+    fn stub_value(&self, field_type: &FieldType, argument: bool) -> String {
+        let (literal, boxed) = match field_type {
+            FieldType::Object(_) | FieldType::Array(_) if argument => {
+                return format!("({}) null", self.field_type(field_type));
+            }
+            FieldType::Object(_) | FieldType::Array(_) => return "null".into(),
+            FieldType::Boolean => ("false", "Boolean"),
+            FieldType::Byte => ("(byte) 0", "Byte"),
+            FieldType::Short => ("(short) 0", "Short"),
+            FieldType::Char => ("(char) 0", "Character"),
+            FieldType::Int => ("0", "Integer"),
+            FieldType::Long => ("0L", "Long"),
+            FieldType::Float => ("0.0F", "Float"),
+            FieldType::Double => ("0.0", "Double"),
+        };
+        match argument {
+            true => literal.into(),
+            false => format!("{boxed}.valueOf({literal})"),
+        }
+    }
+
+    /// The arguments of each enum constant in a stub, the defaults of the first constructor.
+    fn stub_enum_arguments(&self) -> HashMap<String, Vec<String>> {
+        let constructor = self.class.methods.iter().find(|method| {
+            !method
+                .access_flags
+                .contains(&MethodAccessFlags::AccSynthetic)
+                && self.utf8(method.name_index) == "<init>"
+        });
+        let descriptor = constructor.and_then(|method| {
+            MethodDescriptor::from_descriptor(&self.utf8(method.descriptor_index)).ok()
+        });
+        let arguments: Vec<String> = descriptor
+            .iter()
+            .flat_map(|descriptor| descriptor.parameters.iter().skip(2))
+            .map(|parameter| self.stub_value(parameter, true))
+            .collect();
+        self.class
+            .fields
+            .iter()
+            .filter(|field| field.access_flags.contains(&FieldAccessFlags::AccEnum))
+            .map(|field| (self.utf8(field.name_index), arguments.clone()))
+            .collect()
+    }
+
+    /**
+     * The body of each constant of an enum in a stub, which implements the abstract methods of
+     * the enum. Empty when the enum has none, or for a decompiled class, which shows the bodies
+     * as classes of their own.
+     */
+    fn stub_enum_body(&self) -> String {
+        let is_abstract = self
+            .class
+            .access_flags
+            .contains(&ClassAccessFlags::AccAbstract);
+        if self.stub.is_none() || !is_abstract {
+            return String::new();
+        }
+        let methods: Vec<String> = self
+            .class
+            .methods
+            .iter()
+            .filter(|method| {
+                method
+                    .access_flags
+                    .contains(&MethodAccessFlags::AccAbstract)
+            })
+            .filter_map(|method| self.method(method, Kind::Class, None, true))
+            .map(|method| indent(&method))
+            .collect();
+        format!(" {{\n{}\n{INDENT}}}", methods.join("\n\n"))
+    }
+
+    /// The disassembly of a body that could not be decompiled, as comments.
+    fn failure(&self, method: &MethodInfo, error: &dyn Error) -> String {
+        let indent = INDENT.repeat(2);
+        let mut text = format!("{indent}// Could not decompile: {error}\n");
+        let options = JavapOptions::default();
+        let javap = Javap::new(self.class, &options);
+        let instructions = get_code(method).and_then(|code| decode(&code.code).ok());
+        for instruction in instructions.unwrap_or_default() {
+            let line = javap.format_instruction(&instruction);
+            text.push_str(&format!("{indent}// {}\n", line.trim_end()));
+        }
+        text
+    }
+
+    /**
+     * The names of a method's parameters, from the local variable table, else the
+     * MethodParameters attribute, else `arg0`, `arg1` and so on.
+     */
+    fn parameter_names(&self, method: &MethodInfo, descriptor: &MethodDescriptor) -> Vec<String> {
+        let pool = &self.class.constant_pool;
+        let is_static = method.access_flags.contains(&MethodAccessFlags::AccStatic);
+        let names = get_code(method)
+            .map(|code| local_names(pool, code))
+            .unwrap_or_default();
+        let declared: Vec<String> = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::MethodParameters(parameters) => Some(
+                    parameters
+                        .parameters
+                        .iter()
+                        .map(|parameter| {
+                            get_raw_utf8(pool, parameter.name_index).unwrap_or_default()
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let mut slot = !is_static as u16;
+        let mut parameters = vec![];
+        for (index, parameter) in descriptor.parameters.iter().enumerate() {
+            let local = names
+                .iter()
+                .find(|name| name.slot == slot && name.start == 0);
+            parameters.push(match (local, declared.get(index)) {
+                (Some(local), _) => local.name.clone(),
+                (None, Some(name)) if !name.is_empty() => name.clone(),
+                _ => format!("arg{index}"),
+            });
+            slot += parameter.get_size();
+        }
+        parameters
+    }
+
+    /// Whether the class is declared in another class, rather than in a method or a package.
+    fn is_member_class(&self) -> bool {
+        self.get_inner_class_info()
+            .is_some_and(|info| info.outer_class_info_index != 0 && info.inner_name_index != 0)
+    }
+
+    /// Whether the class is a member class with an enclosing instance.
+    fn is_inner_class(&self) -> bool {
+        self.is_member_class()
+            && self.get_inner_class_info().is_some_and(|info| {
+                !ClassAccessFlags::from_u16(info.inner_class_access_flags)
+                    .contains(&ClassAccessFlags::AccStatic)
+            })
+    }
+
+    fn get_inner_class_info(&self) -> Option<&InnerClassInfo> {
+        let pool = &self.class.constant_pool;
+        self.class
+            .attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                AttributeInfo::InnerClasses(inner) => Some(&inner.classes),
+                _ => None,
+            })
+            .flatten()
+            .find(|info| {
+                get_raw_class_name(pool, info.inner_class_info_index).unwrap_or_default()
+                    == self.name
+            })
+    }
+
+    /// The name the class is declared with, without its package or enclosing classes.
+    fn get_simple_name(&self) -> String {
+        match self.get_inner_class_info() {
+            Some(info) if info.inner_name_index != 0 => self.utf8(info.inner_name_index),
+            _ => self.name.rsplit('/').next().unwrap_or_default().into(),
+        }
+    }
+
+    fn get_super_name(&self) -> String {
+        match self.class.super_class {
+            0 => String::new(),
+            index => get_raw_class_name(&self.class.constant_pool, index).unwrap_or_default(),
+        }
+    }
+
+    fn utf8(&self, index: u16) -> String {
+        get_raw_utf8(&self.class.constant_pool, index).unwrap_or_default()
+    }
+
+    /**
+     * A class as it is written in source, e.g. `java.util.Map.Entry`.\
+     *  Classes of `java.lang` and of the class's own package are left unqualified.
+     */
+    fn type_name(&self, internal_name: &str) -> String {
+        if internal_name.starts_with('[') {
+            return self.descriptor_type(internal_name);
+        }
+        let (package, simple) = internal_name
+            .rsplit_once('/')
+            .unwrap_or(("", internal_name));
+        // Member classes are joined by `$`, which anonymous and local classes keep.
+        let mut name = String::new();
+        let mut chars = simple.chars().peekable();
+        while let Some(c) = chars.next() {
+            let member =
+                c == '$' && !name.is_empty() && chars.peek().is_some_and(|c| c.is_alphabetic());
+            name.push(if member { '.' } else { c });
+        }
+        if package == "java/lang" || package == self.package {
+            name
+        } else {
+            format!("{}.{name}", package.replace('/', "."))
+        }
+    }
+
+    fn field_type(&self, field_type: &FieldType) -> String {
+        match field_type {
+            FieldType::Object(name) => self.type_name(name),
+            FieldType::Array(component) => format!("{}[]", self.field_type(component)),
+            primitive => primitive.get_java_name(),
+        }
+    }
+
+    fn descriptor_type(&self, descriptor: &str) -> String {
+        match FieldType::from_descriptor(descriptor) {
+            Ok(field_type) => self.field_type(&field_type),
+            Err(_) => descriptor.into(),
+        }
+    }
+
+    fn signature_or_descriptor(&self, signature: &str, descriptor: &str) -> String {
+        match TypeSignature::parse(signature) {
+            Ok(signature) => self.signature_type(&signature),
+            Err(_) => self.descriptor_type(descriptor),
+        }
+    }
+
+    fn signature_type(&self, signature: &TypeSignature) -> String {
+        match signature {
+            TypeSignature::Base(base) => base.get_java_name(),
+            TypeSignature::Class(class) => self.class_type(class),
+            TypeSignature::TypeVariable(name) => name.clone(),
+            TypeSignature::Array(component) => format!("{}[]", self.signature_type(component)),
+        }
+    }
+
+    /// `Outer<K>.Inner<V>`, with the outer class named like [Decompiler::type_name].
+    fn class_type(&self, class: &ClassTypeSignature) -> String {
+        let mut text = self.type_name(&class.class.name);
+        text.push_str(&self.type_arguments(&class.class.type_arguments));
+        for inner in &class.inner {
+            text.push('.');
+            text.push_str(&inner.name);
+            text.push_str(&self.type_arguments(&inner.type_arguments));
+        }
+        text
+    }
+
+    fn type_arguments(&self, arguments: &[TypeArgument]) -> String {
+        if arguments.is_empty() {
+            return String::new();
+        }
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|argument| match argument {
+                TypeArgument::Any => "?".into(),
+                TypeArgument::Exact(bound) => self.signature_type(bound),
+                TypeArgument::Extends(bound) => format!("? extends {}", self.signature_type(bound)),
+                TypeArgument::Super(bound) => format!("? super {}", self.signature_type(bound)),
+            })
+            .collect();
+        format!("<{}>", arguments.join(", "))
+    }
+
+    /// `<T extends Comparable<T>, U>`, leaving out bounds of `Object`.
+    fn type_parameters(&self, parameters: &[TypeParameter]) -> String {
+        if parameters.is_empty() {
+            return String::new();
+        }
+        let parameters: Vec<String> = parameters
+            .iter()
+            .map(|parameter| {
+                let bounds: Vec<String> = parameter
+                    .class_bound
+                    .iter()
+                    .chain(&parameter.interface_bounds)
+                    .map(|bound| self.signature_type(bound))
+                    .filter(|bound| bound != "Object")
+                    .collect();
+                if bounds.is_empty() {
+                    parameter.name.clone()
+                } else {
+                    format!("{} extends {}", parameter.name, bounds.join(" & "))
+                }
+            })
+            .collect();
+        format!("<{}>", parameters.join(", "))
+    }
+
+    /// The annotations of a class or member, visible or not, as `@Name(key = value)`.
+    fn annotations(&self, attributes: &[AttributeInfo]) -> Vec<String> {
+        attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                AttributeInfo::RuntimeVisibleAnnotations(annotations) => {
+                    Some(&annotations.annotations)
+                }
+                AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+                    Some(&annotations.annotations)
+                }
+                _ => None,
+            })
+            .flatten()
+            .map(|annotation| self.annotation(annotation))
+            .collect()
+    }
+
+    fn annotation(&self, annotation: &Annotation) -> String {
+        let name = self.descriptor_type(&self.utf8(annotation.type_index));
+        let pairs = &annotation.element_value_pairs;
+        match pairs.as_slice() {
+            [] => format!("@{name}"),
+            [pair] if self.utf8(pair.element_name_index) == "value" => {
+                format!("@{name}({})", self.element(&pair.value))
+            }
+            _ => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|pair| {
+                        format!(
+                            "{} = {}",
+                            self.utf8(pair.element_name_index),
+                            self.element(&pair.value)
+                        )
+                    })
+                    .collect();
+                format!("@{name}({})", pairs.join(", "))
+            }
+        }
+    }
+
+    fn element(&self, element: &Element) -> String {
+        match element {
+            Element::ConstValueIndex(tag, index) => {
+                let field_type = match tag {
+                    's' => FieldType::Object("java/lang/String".into()),
+                    tag => FieldType::from_descriptor(&tag.to_string()).unwrap_or(FieldType::Int),
+                };
+                // Annotation strings are stored as Utf8 entries rather than String entries.
+                match (&field_type, self.class.constant_pool.get(*index as usize)) {
+                    (FieldType::Object(_), Some(ConstantPool::Utf8(text))) => {
+                        string_literal(&text.get_raw_string())
+                    }
+                    _ => self
+                        .constant_literal(*index, &field_type)
+                        .unwrap_or_default(),
+                }
+            }
+            Element::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => format!(
+                "{}.{}",
+                self.descriptor_type(&self.utf8(*type_name_index)),
+                self.utf8(*const_name_index)
+            ),
+            Element::ClassInfoIndex(index) => match self.utf8(*index).as_str() {
+                "V" => "void.class".into(),
+                descriptor => format!("{}.class", self.descriptor_type(descriptor)),
+            },
+            Element::Annotation(annotation) => self.annotation(annotation),
+            Element::ArrayValue { values, .. } => {
+                let values: Vec<String> = values.iter().map(|value| self.element(value)).collect();
+                format!("{{{}}}", values.join(", "))
+            }
+            Element::Unknown(tag) => format!("/* unknown element {tag} */"),
+        }
+    }
+
+    /// A constant of the pool as a literal of `field_type`.
+    fn constant_literal(&self, index: u16, field_type: &FieldType) -> Option<String> {
+        Some(match self.class.constant_pool.get(index as usize)? {
+            ConstantPool::Integer(integer) => match field_type {
+                FieldType::Boolean => (integer.get_value() != 0).to_string(),
+                FieldType::Char => char_literal(integer.get_value() as u16),
+                _ => integer.get_value().to_string(),
+            },
+            ConstantPool::Long(long) => format!("{}L", long.get_value()),
+            ConstantPool::Float(float) => float_literal(float.get_value()),
+            ConstantPool::Double(double) => double_literal(double.get_value()),
+            ConstantPool::String(string) => string_literal(&self.utf8(string.string_index)),
+            _ => return None,
+        })
+    }
+}
+
+/// Where a field access or method call is made.
+#[derive(Clone, Debug, PartialEq)]
+enum Target {
+    /// A static member of the class, by its internal name.
+    Static(String),
+    Object(Box<Expr>),
+    /// `super.name`, `super.name()` or `super()`.
+    Super,
+    /// `this()`, a constructor calling another constructor of its class.
+    This,
+}
+
+/// An expression of a method body, as it is evaluated on the operand stack.
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Null,
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    Char(u16),
+    Str(String),
+    Class(FieldType),
+    /// A constant without a source form, such as a MethodHandle, as a comment.
+    Text(String),
+    Var(usize),
+    Field {
+        target: Target,
+        name: String,
+        field_type: FieldType,
+    },
+    Element {
+        array: Box<Expr>,
+        index: Box<Expr>,
+        element_type: FieldType,
+    },
+    Length(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Cast(FieldType, Box<Expr>),
+    InstanceOf(Box<Expr>, FieldType),
+    Call {
+        target: Target,
+        name: String,
+        parameters: Vec<FieldType>,
+        return_type: Option<FieldType>,
+        arguments: Vec<Expr>,
+    },
+    New {
+        class: String,
+        parameters: Vec<FieldType>,
+        arguments: Vec<Expr>,
+    },
+    NewArray {
+        array_type: FieldType,
+        dimensions: Vec<Expr>,
+    },
+    ArrayInit {
+        array_type: FieldType,
+        values: Vec<Expr>,
+    },
+    Assign(Box<Expr>, Box<Expr>),
+    /// `i++` or `i--`, the value of a local before iinc.
+    Increment(Box<Expr>, i32),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// lcmp, fcmpl, fcmpg, dcmpl or dcmpg, until a branch compares its result with zero.
+    Compare(Box<Expr>, Box<Expr>),
+    Lambda {
+        parameters: Vec<String>,
+        /// The body as source, lines after the first indented relative to the statement.
+        body: String,
+    },
+    MethodRef(Target, String),
+    /// An invokedynamic call site of any other bootstrap method, by its owner and name.
+    Dynamic {
+        bootstrap: String,
+        name: String,
+        return_type: Option<FieldType>,
+        arguments: Vec<Expr>,
+    },
+    /// The result of new until its constructor is called, by an id and the class.
+    Uninitialized(u32, String),
+    /// A value copied by dup, so that storing one copy can become an assignment expression.
+    Dup(u32, Box<Expr>),
+}
+
+impl Expr {
+    fn children(&self) -> Vec<&Expr> {
+        fn target(target: &Target) -> Option<&Expr> {
+            match target {
+                Target::Object(object) => Some(object),
+                _ => None,
+            }
+        }
+        match self {
+            Expr::Field { target: t, .. } | Expr::MethodRef(t, _) => {
+                target(t).into_iter().collect()
+            }
+            Expr::Element { array, index, .. } => vec![array, index],
+            Expr::Length(e) | Expr::Not(e) | Expr::Negate(e) | Expr::Cast(_, e) => vec![e],
+            Expr::InstanceOf(e, _) | Expr::Increment(e, _) | Expr::Dup(_, e) => vec![e],
+            Expr::Binary(_, a, b) | Expr::Assign(a, b) | Expr::Compare(a, b) => vec![a, b],
+            Expr::Conditional(c, a, b) => vec![c, a, b],
+            Expr::Call {
+                target: t,
+                arguments,
+                ..
+            } => target(t).into_iter().chain(arguments).collect(),
+            Expr::New { arguments, .. } | Expr::Dynamic { arguments, .. } => {
+                arguments.iter().collect()
+            }
+            Expr::NewArray { dimensions, .. } => dimensions.iter().collect(),
+            Expr::ArrayInit { values, .. } => values.iter().collect(),
+            _ => vec![],
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        fn target(target: &mut Target) -> Option<&mut Expr> {
+            match target {
+                Target::Object(object) => Some(object),
+                _ => None,
+            }
+        }
+        match self {
+            Expr::Field { target: t, .. } | Expr::MethodRef(t, _) => {
+                target(t).into_iter().collect()
+            }
+            Expr::Element { array, index, .. } => vec![array, index],
+            Expr::Length(e) | Expr::Not(e) | Expr::Negate(e) | Expr::Cast(_, e) => vec![e],
+            Expr::InstanceOf(e, _) | Expr::Increment(e, _) | Expr::Dup(_, e) => vec![e],
+            Expr::Binary(_, a, b) | Expr::Assign(a, b) | Expr::Compare(a, b) => vec![a, b],
+            Expr::Conditional(c, a, b) => vec![c, a, b],
+            Expr::Call {
+                target: t,
+                arguments,
+                ..
+            } => target(t).into_iter().chain(arguments).collect(),
+            Expr::New { arguments, .. } | Expr::Dynamic { arguments, .. } => {
+                arguments.iter_mut().collect()
+            }
+            Expr::NewArray { dimensions, .. } => dimensions.iter_mut().collect(),
+            Expr::ArrayInit { values, .. } => values.iter_mut().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Whether evaluating the expression can change state or observably run code.
+    fn has_effects(&self) -> bool {
+        match self {
+            Expr::Call { .. }
+            | Expr::New { .. }
+            | Expr::Assign(..)
+            | Expr::Increment(..)
+            | Expr::Dynamic { .. } => true,
+            e => e.children().into_iter().any(Expr::has_effects),
+        }
+    }
+
+    /// Whether the value may change when memory, or the local `variable`, is written.
+    fn reads(&self, variable: Option<usize>, memory: bool) -> bool {
+        match self {
+            Expr::Var(v) => Some(*v) == variable,
+            Expr::Field { .. } | Expr::Element { .. } if memory => true,
+            e => e
+                .children()
+                .into_iter()
+                .any(|child| child.reads(variable, memory)),
+        }
+    }
+
+    fn uses(&self, variable: usize) -> bool {
+        self.reads(Some(variable), false)
+    }
+
+    /// Whether dup may copy the expression rather than share its single evaluation.
+    fn is_simple(&self) -> bool {
+        matches!(
+            self,
+            Expr::Null
+                | Expr::Int(_)
+                | Expr::Long(_)
+                | Expr::Float(_)
+                | Expr::Double(_)
+                | Expr::Bool(_)
+                | Expr::Char(_)
+                | Expr::Str(_)
+                | Expr::Class(_)
+                | Expr::Var(_)
+                | Expr::Uninitialized(..)
+                | Expr::Dup(..)
+        )
+    }
+
+    /// Replaces every use of `variable` by `value`.
+    fn substitute(&mut self, variable: usize, value: &Expr) {
+        if *self == Expr::Var(variable) {
+            *self = value.clone();
+            return;
+        }
+        for child in self.children_mut() {
+            child.substitute(variable, value);
+        }
+    }
+
+    /// Replaces every copy of the value duplicated as `id` by `value`.
+    fn replace_dup(&mut self, id: u32, value: &Expr) {
+        if matches!(self, Expr::Dup(dup, _) if *dup == id) {
+            *self = value.clone();
+            return;
+        }
+        for child in self.children_mut() {
+            child.replace_dup(id, value);
+        }
+    }
+
+    fn count_uses(&self, counts: &mut [usize]) {
+        if let Expr::Var(v) = self {
+            counts[*v] += 1;
+        }
+        for child in self.children() {
+            child.count_uses(counts);
+        }
+    }
+
+    fn is_zero_or_one(&self) -> bool {
+        matches!(self, Expr::Int(0 | 1))
+    }
+}
+
+/// A catch clause of a try statement.
+#[derive(Clone, Debug, PartialEq)]
+struct Catch {
+    /// The internal names of the exceptions caught, `None` for any as finally catches it.
+    types: Vec<Option<String>>,
+    variable: usize,
+    body: Vec<Stmt>,
+}
+
+/// A statement of a method body; loops and switches have an optional label.
+#[derive(Clone, Debug, PartialEq)]
+enum Stmt {
+    Expr(Expr),
+    /// The declaration of a local variable, with its initializer if it has one.
+    Local(usize, Option<Expr>),
+    Return(Option<Expr>),
+    Throw(Expr),
+    /// monitorenter or monitorexit, which only the `synchronized` statement may contain.
+    Monitor(bool, Expr),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Option<String>, Expr, Vec<Stmt>),
+    DoWhile(Option<String>, Vec<Stmt>, Expr),
+    For {
+        label: Option<String>,
+        init: Option<Box<Stmt>>,
+        condition: Expr,
+        update: Vec<Expr>,
+        body: Vec<Stmt>,
+    },
+    /// The cases by their keys, `None` for default, in the order they are laid out.
+    Switch(Option<String>, Expr, Vec<(Vec<Option<i32>>, Vec<Stmt>)>),
+    Try(Vec<Stmt>, Vec<Catch>),
+    Break(Option<String>),
+    Continue(Option<String>),
+    Comment(String),
+}
+
+impl Stmt {
+    /// The expressions evaluated by the statement itself, outside of any nested statements.
+    fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            Stmt::Expr(e) | Stmt::Throw(e) | Stmt::Monitor(_, e) => vec![e],
+            Stmt::Local(_, e) | Stmt::Return(e) => e.iter().collect(),
+            Stmt::If(e, ..) | Stmt::While(_, e, _) | Stmt::DoWhile(_, _, e) => vec![e],
+            Stmt::Switch(_, e, _) => vec![e],
+            Stmt::For {
+                init,
+                condition,
+                update,
+                ..
+            } => {
+                let init = init.iter().flat_map(|init| init.exprs());
+                init.chain(std::iter::once(condition))
+                    .chain(update)
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Stmt::Expr(e) | Stmt::Throw(e) | Stmt::Monitor(_, e) => vec![e],
+            Stmt::Local(_, e) | Stmt::Return(e) => e.iter_mut().collect(),
+            Stmt::If(e, ..) | Stmt::While(_, e, _) | Stmt::DoWhile(_, _, e) => vec![e],
+            Stmt::Switch(_, e, _) => vec![e],
+            Stmt::For {
+                init,
+                condition,
+                update,
+                ..
+            } => {
+                let init = init.iter_mut().flat_map(|init| init.exprs_mut());
+                init.chain(std::iter::once(condition))
+                    .chain(update)
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The nested statement lists, in the order they are written.
+    fn blocks(&self) -> Vec<&Vec<Stmt>> {
+        match self {
+            Stmt::If(_, then, otherwise) => vec![then, otherwise],
+            Stmt::While(_, _, body) | Stmt::DoWhile(_, body, _) => vec![body],
+            Stmt::For { body, .. } => vec![body],
+            Stmt::Switch(_, _, cases) => cases.iter().map(|(_, body)| body).collect(),
+            Stmt::Try(body, catches) => std::iter::once(body)
+                .chain(catches.iter().map(|c| &c.body))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn blocks_mut(&mut self) -> Vec<&mut Vec<Stmt>> {
+        match self {
+            Stmt::If(_, then, otherwise) => vec![then, otherwise],
+            Stmt::While(_, _, body) | Stmt::DoWhile(_, body, _) => vec![body],
+            Stmt::For { body, .. } => vec![body],
+            Stmt::Switch(_, _, cases) => cases.iter_mut().map(|(_, body)| body).collect(),
+            Stmt::Try(body, catches) => std::iter::once(body)
+                .chain(catches.iter_mut().map(|c| &mut c.body))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The label of a loop or switch, which `break` and `continue` may name.
+    fn label(&self) -> Option<&String> {
+        match self {
+            Stmt::While(label, ..) | Stmt::DoWhile(label, ..) | Stmt::Switch(label, ..) => {
+                label.as_ref()
+            }
+            Stmt::For { label, .. } => label.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn is_loop(&self) -> bool {
+        matches!(self, Stmt::While(..) | Stmt::DoWhile(..) | Stmt::For { .. })
+    }
+}
+
+/// A local variable of a method body, or a stack value that had to be kept in one.
+#[derive(Clone, Debug)]
+struct Variable {
+    name: String,
+    /// The declared type with its type arguments, from the LocalVariableTypeTable.
+    java_type: Option<String>,
+    field_type: Option<FieldType>,
+    /// Whether the type was read from the class file rather than guessed from the code.
+    typed: bool,
+    /// Whether the variable is declared where it is introduced, as parameters are.
+    declared: bool,
+    /// Whether the variable holds a stack value, which may be inlined where it is used.
+    temporary: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum VarKey {
+    /// A local variable by its entry in the LocalVariableTable.
+    Named(usize),
+    /// A local variable without an entry, by its slot and computational type.
+    Slot(u16, char),
+    /// The stack values at one depth on entry to a group of blocks.
+    Stack(usize),
+    /// A stack value evaluated ahead of a statement that could change it.
+    Spill(usize),
+    /// The exception caught by the handler starting the block.
+    Catch(usize),
+}
+
+/// An entry of the LocalVariableTable, with its signature from the LocalVariableTypeTable.
+#[derive(Clone, Debug)]
+struct LocalName {
+    start: u32,
+    end: u32,
+    slot: u16,
+    name: String,
+    descriptor: String,
+    signature: Option<String>,
+}
+
+/// How a block of a method body is left.
+#[derive(Clone, Debug)]
+enum Exit {
+    /// By return or athrow.
+    End,
+    Goto(usize),
+    /// To the first block when the condition holds, else to the second.
+    Branch(Expr, usize, usize),
+    /// To the block of the value's key, else to the default.
+    Switch(Expr, Vec<(i32, usize)>, usize),
+}
+
+impl Exit {
+    fn targets(&self) -> Vec<usize> {
+        let mut targets = match self {
+            Exit::End => vec![],
+            Exit::Goto(target) => vec![*target],
+            Exit::Branch(_, taken, next) => vec![*taken, *next],
+            Exit::Switch(_, cases, default) => cases
+                .iter()
+                .map(|(_, target)| *target)
+                .chain([*default])
+                .collect(),
+        };
+        targets.dedup();
+        targets
+    }
+}
+
+/// A block of a method body, its instructions decompiled to statements.
+#[derive(Clone, Debug)]
+struct Node {
+    start_pc: u32,
+    statements: Vec<Stmt>,
+    exit: Exit,
+    /// The handlers of the try ranges covering the block.
+    handlers: Vec<usize>,
+    /// Whether the block is reachable and was not merged into the condition of another.
+    live: bool,
+}
+
+/// The operand stack and the statements of a block as it is decompiled.
+#[derive(Default)]
+struct State {
+    /// Each value, with whether it takes two slots.
+    stack: Vec<(Expr, bool)>,
+    statements: Vec<Stmt>,
+}
+
+/// What a statement may change, for the stack values that have to be evaluated before it.
+#[derive(Clone, Copy)]
+enum Effect {
+    Local(usize),
+    Memory,
+}
+
+/// The computational types by the order of the typed load and store instructions.
+const KINDS: [char; 5] = ['I', 'L', 'F', 'D', 'A'];
+const COMPARISONS: [&str; 6] = ["==", "!=", "<", ">=", ">", "<="];
+
+/// Decompiles the blocks of a method body one at a time, simulating the operand stack.
+struct Builder<'b, 'd, 'a> {
+    decompiler: &'d Decompiler<'a>,
+    pool: &'a [ConstantPool],
+    cfg: &'b ControlFlowGraph,
+    descriptor: &'b MethodDescriptor,
+    is_static: bool,
+    /// The names of the parameters a lambda captures, which its body is shown with.
+    captured: &'b [String],
+    locals: Vec<LocalName>,
+    /// The entry of `locals` that stands for each entry, by [`merge_split_locals`].
+    local_groups: Vec<usize>,
+    declared_names: Vec<String>,
+    is_handler: Vec<bool>,
+    variables: Vec<Variable>,
+    keys: HashMap<VarKey, usize>,
+    names: HashSet<String>,
+    parameters: Vec<usize>,
+    this: Option<usize>,
+    /// The catch variable of each handler block.
+    caught: HashMap<usize, usize>,
+    /// Union-find over the stack values at a depth on entry to a block.
+    groups: Vec<usize>,
+    group_ids: HashMap<(usize, usize), usize>,
+    /// Whether groups were joined after their variables were made, so the build is redone.
+    regroup: bool,
+    /// Whether each stack value on entry to a block takes two slots, once a predecessor is built.
+    entries: Vec<Option<Vec<bool>>>,
+    /// Objects that cross a block boundary before their constructor is called.
+    carried: HashMap<(usize, usize), Expr>,
+    nodes: Vec<Node>,
+    next_id: u32,
+}
+
+impl<'b, 'd, 'a> Builder<'b, 'd, 'a> {
+    fn new(
+        decompiler: &'d Decompiler<'a>,
+        method: &MethodInfo,
+        code: &Code,
+        cfg: &'b ControlFlowGraph,
+        descriptor: &'b MethodDescriptor,
+        captured: &'b [String],
+    ) -> Builder<'b, 'd, 'a> {
+        let pool = &decompiler.class.constant_pool[..];
+        let mut is_handler = vec![false; cfg.blocks.len()];
+        for block in &cfg.blocks {
+            for edge in &block.successors {
+                if matches!(edge.kind, EdgeKind::Exception(_)) {
+                    is_handler[edge.target] = true;
+                }
+            }
+        }
+        let declared_names = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::MethodParameters(parameters) => Some(
+                    parameters
+                        .parameters
+                        .iter()
+                        .map(|parameter| {
+                            get_raw_utf8(pool, parameter.name_index).unwrap_or_default()
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let locals = local_names(pool, code);
+        Builder {
+            decompiler,
+            pool,
+            cfg,
+            descriptor,
+            is_static: method.access_flags.contains(&MethodAccessFlags::AccStatic),
+            captured,
+            local_groups: merge_split_locals(cfg, &locals),
+            locals,
+            declared_names,
+            is_handler,
+            variables: vec![],
+            keys: HashMap::new(),
+            names: HashSet::new(),
+            parameters: vec![],
+            this: None,
+            caught: HashMap::new(),
+            groups: vec![],
+            group_ids: HashMap::new(),
+            regroup: false,
+            entries: vec![],
+            carried: HashMap::new(),
+            nodes: vec![],
+            next_id: 0,
+        }
+    }
+
+    fn build(&mut self) -> Result<(), Box<dyn Error>> {
+        let cfg = self.cfg;
+        self.variables.clear();
+        self.keys.clear();
+        self.parameters.clear();
+        self.caught.clear();
+        self.carried.clear();
+        self.regroup = false;
+        self.next_id = 0;
+        self.entries = vec![None; cfg.blocks.len()];
+        self.names = self.locals.iter().map(|local| local.name.clone()).collect();
+        self.names.extend(self.captured.iter().cloned());
+        self.names.extend(self.declared_names.iter().cloned());
+        self.nodes = cfg
+            .blocks
+            .iter()
+            .map(|block| Node {
+                start_pc: block.start_pc,
+                statements: vec![],
+                exit: Exit::End,
+                handlers: vec![],
+                live: false,
+            })
+            .collect();
+
+        let mut slot = 0;
+        if !self.is_static {
+            let this = self.local(0, 'A', 0, 0, None);
+            let variable = &mut self.variables[this];
+            variable.name = "this".into();
+            variable.field_type = Some(FieldType::Object(self.decompiler.name.clone()));
+            variable.declared = true;
+            self.this = Some(this);
+            slot = 1;
+        }
+        for (index, parameter) in self.descriptor.parameters.iter().enumerate() {
+            let kind = KINDS[kind_index(parameter)];
+            let variable = self.local(slot, kind, 0, 0, Some(parameter));
+            let named = matches!(
+                self.keys.iter().find(|(_, v)| **v == variable),
+                Some((VarKey::Named(_), _))
+            );
+            let variable_info = &mut self.variables[variable];
+            if !named {
+                variable_info.name =
+                    match (self.captured.get(index), self.declared_names.get(index)) {
+                        (Some(name), _) => name.clone(),
+                        (None, Some(name)) if !name.is_empty() => name.clone(),
+                        _ => format!("arg{index}"),
+                    };
+                variable_info.field_type = Some(parameter.clone());
+                variable_info.java_type = None;
+            }
+            variable_info.typed = true;
+            variable_info.declared = true;
+            self.parameters.push(variable);
+            slot += parameter.get_size();
+        }
+
+        for block in cfg.get_reverse_postorder() {
+            self.build_block(block)?;
+        }
+        Ok(())
+    }
+
+    fn build_block(&mut self, block: usize) -> Result<(), Box<dyn Error>> {
+        let cfg = self.cfg;
+        let basic = &cfg.blocks[block];
+        let mut state = State::default();
+        if self.is_handler[block] {
+            let caught = basic_catch_type(cfg, block);
+            let variable = self.variable(VarKey::Catch(block), Some(&caught), 'A');
+            self.variables[variable].declared = true;
+            self.caught.insert(block, variable);
+            state.stack.push((Expr::Var(variable), false));
+        } else if let Some(entry) = self.entries[block].clone() {
+            for (depth, wide) in entry.into_iter().enumerate() {
+                let value = match self.carried.get(&(block, depth)) {
+                    Some(value) => value.clone(),
+                    None => Expr::Var(self.stack_variable(block, depth, None)),
+                };
+                state.stack.push((value, wide));
+            }
+        }
+        let entry_stack: Vec<Expr> = state.stack.iter().map(|(value, _)| value.clone()).collect();
+
+        let instructions = cfg.get_instructions(block);
+        let mut exit = None;
+        for (index, instruction) in instructions.iter().enumerate() {
+            let next_pc = instructions
+                .get(index + 1)
+                .map_or(basic.end_pc, |next| next.pc);
+            exit = self.instruction(block, &mut state, instruction, next_pc)?;
+        }
+        let exit = match exit {
+            Some(exit) => exit,
+            None => match edge_target(cfg, block, |kind| *kind == EdgeKind::FallThrough) {
+                Some(next) => Exit::Goto(next),
+                None => Exit::End,
+            },
+        };
+
+        // Values left on the stack are passed to the successors in variables.
+        let targets = exit.targets();
+        for depth in 0..state.stack.len() {
+            for pair in targets.windows(2) {
+                self.union((pair[0], depth), (pair[1], depth));
+            }
+            // A value passed through untouched stays in the variable it came in.
+            let passed =
+                matches!(entry_stack.get(depth), Some(Expr::Var(_)) if !self.is_handler[block]);
+            if passed && entry_stack.get(depth) == Some(&state.stack[depth].0) {
+                for target in &targets {
+                    self.union((block, depth), (*target, depth));
+                }
+            }
+        }
+        let wide: Vec<bool> = state.stack.iter().map(|(_, wide)| *wide).collect();
+        for target in &targets {
+            match &self.entries[*target] {
+                Some(entry) if entry.len() != wide.len() => {
+                    return Err(invalid(
+                        cfg.blocks[*target].start_pc,
+                        "the stack differs between predecessors",
+                    ));
+                }
+                Some(_) => {}
+                None => self.entries[*target] = Some(wide.clone()),
+            }
+        }
+        if let Some(first) = targets.first() {
+            for (depth, (value, _)) in std::mem::take(&mut state.stack).into_iter().enumerate() {
+                if let Expr::Uninitialized(..) = value {
+                    for target in &targets {
+                        self.carried.insert((*target, depth), value.clone());
+                    }
+                    continue;
+                }
+                let variable = self.stack_variable(*first, depth, Some(&value));
+                if value != Expr::Var(variable) {
+                    let value = unshare(value);
+                    state.statements.push(assign(Expr::Var(variable), value));
+                }
+            }
+        }
+
+        let handlers = basic
+            .successors
+            .iter()
+            .filter(|edge| matches!(edge.kind, EdgeKind::Exception(_)))
+            .map(|edge| edge.target)
+            .collect();
+        self.nodes[block] = Node {
+            start_pc: basic.start_pc,
+            statements: state.statements,
+            exit,
+            handlers,
+            live: true,
+        };
+        Ok(())
+    }
+
+    fn instruction(
+        &mut self,
+        block: usize,
+        state: &mut State,
+        instruction: &Instruction,
+        next_pc: u32,
+    ) -> Result<Option<Exit>, Box<dyn Error>> {
+        let pc = instruction.pc;
+        let opcode = instruction.opcode;
+        let local = match instruction.operands {
+            Operands::Local(index) | Operands::Increment { index, .. } => index,
+            _ => 0,
+        };
+        match opcode {
+            NOP | GOTO | GOTO_W => {
+                if opcode != NOP {
+                    let target = edge_target(self.cfg, block, |kind| *kind == EdgeKind::Jump);
+                    return Ok(target.map(Exit::Goto));
+                }
+            }
+            ACONST_NULL => state.stack.push((Expr::Null, false)),
+            ICONST_M1..=ICONST_5 => {
+                let value = opcode as i32 - ICONST_0 as i32;
+                state.stack.push((Expr::Int(value), false));
+            }
+            LCONST_0 | LCONST_1 => state
+                .stack
+                .push((Expr::Long((opcode - LCONST_0) as i64), true)),
+            FCONST_0..=FCONST_2 => state
+                .stack
+                .push((Expr::Float((opcode - FCONST_0) as f32), false)),
+            DCONST_0 | DCONST_1 => state
+                .stack
+                .push((Expr::Double((opcode - DCONST_0) as f64), true)),
+            BIPUSH | SIPUSH => {
+                let Operands::Immediate(value) = instruction.operands else {
+                    return Err(invalid(pc, "push without a value"));
+                };
+                state.stack.push((Expr::Int(value), false));
+            }
+            LDC | LDC_W | LDC2_W => {
+                let Operands::Constant(index) = instruction.operands else {
+                    return Err(invalid(pc, "ldc without a constant"));
+                };
+                let constant = self.constant(index, pc)?;
+                state.stack.push(constant);
+            }
+            ILOAD..=ALOAD | ILOAD_0..=ALOAD_3 => {
+                let (slot, kind) = slot_and_kind(opcode, ILOAD, ILOAD_0, local);
+                let variable = self.local(slot, kind, pc, pc, None);
+                state
+                    .stack
+                    .push((Expr::Var(variable), matches!(kind, 'L' | 'D')));
+            }
+            IALOAD..=SALOAD => {
+                let index = pop(state, pc)?;
+                let array = pop(state, pc)?;
+                let element_type = match expr_type(&self.variables, &array) {
+                    Some(FieldType::Array(component)) => *component,
+                    _ => [
+                        FieldType::Int,
+                        FieldType::Long,
+                        FieldType::Float,
+                        FieldType::Double,
+                        FieldType::Object("java/lang/Object".into()),
+                        FieldType::Byte,
+                        FieldType::Char,
+                        FieldType::Short,
+                    ][(opcode - IALOAD) as usize]
+                        .clone(),
+                };
+                let wide = matches!(opcode, LALOAD | DALOAD);
+                let element = Expr::Element {
+                    array: Box::new(array),
+                    index: Box::new(index),
+                    element_type,
+                };
+                state.stack.push((element, wide));
+            }
+            ISTORE..=ASTORE | ISTORE_0..=ASTORE_3 => {
+                let (slot, kind) = slot_and_kind(opcode, ISTORE, ISTORE_0, local);
+                let value = pop(state, pc)?;
+                let hint = expr_type(&self.variables, &value);
+                let variable = self.local(slot, kind, pc, next_pc, hint.as_ref());
+                self.store(state, Expr::Var(variable), value, Effect::Local(variable));
+            }
+            IASTORE..=SASTORE => {
+                let value = pop(state, pc)?;
+                let index = pop(state, pc)?;
+                let array = pop(state, pc)?;
+                if let Some(value) = self.initialize_element(state, &array, &index, value)? {
+                    let element_type = match expr_type(&self.variables, &array) {
+                        Some(FieldType::Array(component)) => *component,
+                        _ => FieldType::Int,
+                    };
+                    let element = Expr::Element {
+                        array: Box::new(array),
+                        index: Box::new(index),
+                        element_type,
+                    };
+                    self.store(state, element, value, Effect::Memory);
+                }
+            }
+            POP | POP2 => {
+                for (value, _) in pop_slots(state, (opcode - POP + 1) as usize, pc)? {
+                    self.discard(state, value);
+                }
+            }
+            DUP..=DUP2_X2 => {
+                // The slots that are copied and those they are inserted under.
+                let (copied, under) = match opcode {
+                    DUP => (1, 0),
+                    DUP_X1 => (1, 1),
+                    DUP_X2 => (1, 2),
+                    DUP2 => (2, 0),
+                    DUP2_X1 => (2, 1),
+                    _ => (2, 2),
+                };
+                let top = pop_slots(state, copied, pc)?;
+                let below = pop_slots(state, under, pc)?;
+                let top: Vec<(Expr, bool)> = top
+                    .into_iter()
+                    .map(|(value, wide)| (self.share(value), wide))
+                    .collect();
+                state
+                    .stack
+                    .extend(top.clone().into_iter().chain(below).chain(top));
+            }
+            SWAP => {
+                let top = pop_slots(state, 1, pc)?;
+                let below = pop_slots(state, 1, pc)?;
+                state.stack.extend(top.into_iter().chain(below));
+            }
+            IADD..=DREM => {
+                let right = pop(state, pc)?;
+                let left = pop(state, pc)?;
+                let operator = ["+", "-", "*", "/", "%"][((opcode - IADD) / 4) as usize];
+                let wide = (opcode - IADD) % 2 == 1;
+                state.stack.push((binary(operator, left, right), wide));
+            }
+            INEG..=DNEG => {
+                let value = pop(state, pc)?;
+                let wide = (opcode - INEG) % 2 == 1;
+                state.stack.push((Expr::Negate(Box::new(value)), wide));
+            }
+            ISHL..=LXOR => {
+                let right = pop(state, pc)?;
+                let left = pop(state, pc)?;
+                let operator = ["<<", ">>", ">>>", "&", "|", "^"][((opcode - ISHL) / 2) as usize];
+                let wide = (opcode - ISHL) % 2 == 1;
+                state.stack.push((binary(operator, left, right), wide));
+            }
+            IINC => {
+                let Operands::Increment { index, value } = instruction.operands else {
+                    return Err(invalid(pc, "iinc without an increment"));
+                };
+                let variable = self.local(index, 'I', pc, next_pc, Some(&FieldType::Int));
+                let delta = value as i32;
+                let loaded = Expr::Var(variable);
+                let (last, below) = match state.stack.split_last() {
+                    Some((last, below)) => (Some(&last.0), below),
+                    None => (None, &[][..]),
+                };
+                if last == Some(&loaded)
+                    && delta.abs() == 1
+                    && !below.iter().any(|(value, _)| value.uses(variable))
+                {
+                    let top = state.stack.len() - 1;
+                    state.stack[top].0 = Expr::Increment(Box::new(loaded), delta);
+                } else {
+                    let update = match delta {
+                        delta if delta < 0 => binary("-", loaded.clone(), Expr::Int(-delta)),
+                        delta => binary("+", loaded.clone(), Expr::Int(delta)),
+                    };
+                    self.emit(state, assign(loaded, update), Effect::Local(variable));
+                }
+            }
+            I2L..=I2S => {
+                let value = pop(state, pc)?;
+                let target = match opcode {
+                    I2L | F2L | D2L => FieldType::Long,
+                    I2F | L2F | D2F => FieldType::Float,
+                    I2D | L2D | F2D => FieldType::Double,
+                    I2B => FieldType::Byte,
+                    I2C => FieldType::Char,
+                    I2S => FieldType::Short,
+                    _ => FieldType::Int,
+                };
+                let wide = target.get_size() == 2;
+                state
+                    .stack
+                    .push((Expr::Cast(target, Box::new(value)), wide));
+            }
+            LCMP..=DCMPG => {
+                let right = pop(state, pc)?;
+                let left = pop(state, pc)?;
+                state
+                    .stack
+                    .push((Expr::Compare(Box::new(left), Box::new(right)), false));
+            }
+            IFEQ..=IFLE | IFNULL | IFNONNULL => {
+                let value = pop(state, pc)?;
+                let condition = match (opcode, value) {
+                    (IFNULL, value) => binary("==", value, Expr::Null),
+                    (IFNONNULL, value) => binary("!=", value, Expr::Null),
+                    (_, Expr::Compare(left, right)) => {
+                        binary(COMPARISONS[(opcode - IFEQ) as usize], *left, *right)
+                    }
+                    (_, value) => {
+                        binary(COMPARISONS[(opcode - IFEQ) as usize], value, Expr::Int(0))
+                    }
+                };
+                return Ok(Some(self.branch(block, condition)));
+            }
+            IF_ICMPEQ..=IF_ACMPNE => {
+                let right = pop(state, pc)?;
+                let left = pop(state, pc)?;
+                let operator = COMPARISONS[((opcode - IF_ICMPEQ) % 6) as usize];
+                return Ok(Some(self.branch(block, binary(operator, left, right))));
+            }
+            TABLESWITCH | LOOKUPSWITCH => {
+                let value = pop(state, pc)?;
+                let mut cases = vec![];
+                let mut default = None;
+                for edge in &self.cfg.blocks[block].successors {
+                    match edge.kind {
+                        EdgeKind::Switch(Some(key)) => cases.push((key, edge.target)),
+                        EdgeKind::Switch(None) => default = Some(edge.target),
+                        _ => {}
+                    }
+                }
+                let default = default.ok_or_else(|| invalid(pc, "switch without a default"))?;
+                return Ok(Some(Exit::Switch(value, cases, default)));
+            }
+            JSR | RET | JSR_W => return Err(invalid(pc, "subroutines are not supported")),
+            IRETURN..=ARETURN => {
+                let value = unshare(pop(state, pc)?);
+                state.statements.push(Stmt::Return(Some(value)));
+                return Ok(Some(Exit::End));
+            }
+            RETURN => {
+                state.statements.push(Stmt::Return(None));
+                return Ok(Some(Exit::End));
+            }
+            ATHROW => {
+                let value = unshare(pop(state, pc)?);
+                state.statements.push(Stmt::Throw(value));
+                return Ok(Some(Exit::End));
+            }
+            GETSTATIC | GETFIELD | PUTSTATIC | PUTFIELD => {
+                let Operands::Constant(index) = instruction.operands else {
+                    return Err(invalid(pc, "field access without a field"));
+                };
+                let (owner, name, descriptor) = self.member(index, pc)?;
+                let field_type = FieldType::from_descriptor(&descriptor)?;
+                let value = match opcode {
+                    PUTSTATIC | PUTFIELD => Some(pop(state, pc)?),
+                    _ => None,
+                };
+                let target = match opcode {
+                    GETFIELD | PUTFIELD => Target::Object(Box::new(pop(state, pc)?)),
+                    _ => Target::Static(owner),
+                };
+                let wide = field_type.get_size() == 2;
+                let field = Expr::Field {
+                    target,
+                    name,
+                    field_type,
+                };
+                match value {
+                    Some(value) => self.store(state, field, value, Effect::Memory),
+                    None => state.stack.push((field, wide)),
+                }
+            }
+            INVOKEVIRTUAL..=INVOKEINTERFACE => self.invoke(state, instruction)?,
+            INVOKEDYNAMIC => {
+                let Operands::InvokeDynamic(index) = instruction.operands else {
+                    return Err(invalid(pc, "invokedynamic without a call site"));
+                };
+                self.invoke_dynamic(state, index, pc)?;
+            }
+            NEW => {
+                let Operands::Constant(index) = instruction.operands else {
+                    return Err(invalid(pc, "new without a class"));
+                };
+                self.next_id += 1;
+                let class = get_raw_class_name(self.pool, index).unwrap_or_default();
+                state
+                    .stack
+                    .push((Expr::Uninitialized(self.next_id, class), false));
+            }
+            NEWARRAY | ANEWARRAY => {
+                let length = pop(state, pc)?;
+                let element = match instruction.operands {
+                    Operands::NewArray(atype) => match atype {
+                        4 => FieldType::Boolean,
+                        5 => FieldType::Char,
+                        6 => FieldType::Float,
+                        7 => FieldType::Double,
+                        8 => FieldType::Byte,
+                        9 => FieldType::Short,
+                        10 => FieldType::Int,
+                        11 => FieldType::Long,
+                        _ => return Err(invalid(pc, "unknown array type")),
+                    },
+                    Operands::Constant(index) => self.class_type(index),
+                    _ => return Err(invalid(pc, "anewarray without a class")),
+                };
+                let array = Expr::NewArray {
+                    array_type: FieldType::Array(Box::new(element)),
+                    dimensions: vec![length],
+                };
+                state.stack.push((array, false));
+            }
+            MULTIANEWARRAY => {
+                let Operands::MultiANewArray { index, dimensions } = instruction.operands else {
+                    return Err(invalid(pc, "multianewarray without dimensions"));
+                };
+                let mut lengths = vec![];
+                for _ in 0..dimensions {
+                    lengths.push(pop(state, pc)?);
+                }
+                lengths.reverse();
+                let array = Expr::NewArray {
+                    array_type: self.class_type(index),
+                    dimensions: lengths,
+                };
+                state.stack.push((array, false));
+            }
+            ARRAYLENGTH => {
+                let array = pop(state, pc)?;
+                state.stack.push((Expr::Length(Box::new(array)), false));
+            }
+            CHECKCAST | INSTANCEOF => {
+                let Operands::Constant(index) = instruction.operands else {
+                    return Err(invalid(pc, "type check without a class"));
+                };
+                let value = Box::new(pop(state, pc)?);
+                let class = self.class_type(index);
+                state.stack.push(match opcode {
+                    CHECKCAST => (Expr::Cast(class, value), false),
+                    _ => (Expr::InstanceOf(value, class), false),
+                });
+            }
+            MONITORENTER | MONITOREXIT => {
+                let mut value = unshare(pop(state, pc)?);
+                if let Expr::Assign(target, _) = &value {
+                    let target = *target.clone();
+                    self.emit(state, Stmt::Expr(value), Effect::Memory);
+                    value = target;
+                }
+                state
+                    .statements
+                    .push(Stmt::Monitor(opcode == MONITORENTER, value));
+            }
+            _ => return Err(invalid(pc, "unknown opcode")),
+        }
+        Ok(None)
+    }
+
+    fn branch(&self, block: usize, condition: Expr) -> Exit {
+        let next = edge_target(self.cfg, block, |kind| *kind == EdgeKind::FallThrough);
+        let taken = edge_target(self.cfg, block, |kind| *kind == EdgeKind::Jump);
+        match (taken, next) {
+            (Some(taken), Some(next)) => Exit::Branch(condition, taken, next),
+            (Some(target), None) | (None, Some(target)) => Exit::Goto(target),
+            (None, None) => Exit::End,
+        }
+    }
+
+    /// Stores a value, as an assignment expression where dup left a copy of it on the stack.
+    fn store(&mut self, state: &mut State, target: Expr, value: Expr, effect: Effect) {
+        if let Expr::Dup(id, shared) = &value {
+            let copy = state
+                .stack
+                .iter()
+                .position(|(other, _)| matches!(other, Expr::Dup(other, _) if other == id));
+            if let Some(copy) = copy {
+                state.stack[copy].0 = Expr::Assign(Box::new(target), shared.clone());
+                return;
+            }
+        }
+        self.emit(state, assign(target, unshare(value)), effect);
+    }
+
+    /**
+     * Folds a store into the array initializer it belongs to, returning the value back if it
+     * does not: the array is a copy of a new array of constant length still on the stack.
+     */
+    fn initialize_element(
+        &mut self,
+        state: &mut State,
+        array: &Expr,
+        index: &Expr,
+        value: Expr,
+    ) -> Result<Option<Expr>, Box<dyn Error>> {
+        let (Expr::Dup(id, shared), Expr::Int(index)) = (array, index) else {
+            return Ok(Some(value));
+        };
+        let copy = state
+            .stack
+            .iter()
+            .position(|(other, _)| matches!(other, Expr::Dup(other, _) if other == id));
+        let (Some(copy), Ok(index)) = (copy, usize::try_from(*index)) else {
+            return Ok(Some(value));
+        };
+        let (array_type, mut values) = match shared.as_ref() {
+            Expr::NewArray {
+                array_type: FieldType::Array(component),
+                dimensions,
+            } => match dimensions.as_slice() {
+                [Expr::Int(length)] if (0..=4096).contains(length) => (
+                    FieldType::Array(component.clone()),
+                    vec![default_value(component); *length as usize],
+                ),
+                _ => return Ok(Some(value)),
+            },
+            Expr::ArrayInit { array_type, values } => (array_type.clone(), values.clone()),
+            _ => return Ok(Some(value)),
+        };
+        if index >= values.len() {
+            return Ok(Some(value));
+        }
+        values[index] = unshare(value);
+        state.stack[copy].0 = Expr::ArrayInit { array_type, values };
+        Ok(None)
+    }
+
+    /// Drops a popped value, keeping it as a statement if evaluating it does something.
+    fn discard(&mut self, state: &mut State, value: Expr) {
+        let has_copy = |id: u32| {
+            state
+                .stack
+                .iter()
+                .any(|(other, _)| matches!(other, Expr::Dup(other, _) if *other == id))
+        };
+        match value {
+            Expr::Dup(id, _) if has_copy(id) => {}
+            // The null check of a receiver captured by a method reference or inner class.
+            Expr::Call {
+                target: Target::Static(owner),
+                name,
+                arguments,
+                ..
+            } if owner == "java/util/Objects"
+                && name == "requireNonNull"
+                && matches!(arguments.as_slice(), [Expr::Dup(id, _)] if has_copy(*id)) => {}
+            Expr::Call {
+                target: Target::Object(receiver),
+                name,
+                ..
+            } if name == "getClass" && matches!(*receiver, Expr::Dup(id, _) if has_copy(id)) => {}
+            value if value.has_effects() => {
+                self.emit(state, Stmt::Expr(unshare(value)), Effect::Memory)
+            }
+            _ => {}
+        }
+    }
+
+    /// A copy of a value for dup, shared when evaluating it twice would not be the same.
+    fn share(&mut self, value: Expr) -> Expr {
+        if value.is_simple() {
+            value
+        } else {
+            self.next_id += 1;
+            Expr::Dup(self.next_id, Box::new(value))
+        }
+    }
+
+    /// Adds a statement, first keeping the stack values it could change in variables.
+    fn emit(&mut self, state: &mut State, mut statement: Stmt, effect: Effect) {
+        let (variable, memory) = match effect {
+            Effect::Local(variable) => (Some(variable), false),
+            Effect::Memory => (None, true),
+        };
+        for depth in 0..state.stack.len() {
+            let value = &state.stack[depth].0;
+            if !value.has_effects() && !value.reads(variable, memory) {
+                continue;
+            }
+            let value = value.clone();
+            let spill = self.keys.len();
+            let spill = self.variable(
+                VarKey::Spill(spill),
+                expr_type(&self.variables, &value).as_ref(),
+                'A',
+            );
+            self.variables[spill].temporary = true;
+            let replacement = Expr::Var(spill);
+            match &value {
+                Expr::Dup(id, _) => {
+                    for (other, _) in &mut state.stack {
+                        other.replace_dup(*id, &replacement);
+                    }
+                    for expr in statement.exprs_mut() {
+                        expr.replace_dup(*id, &replacement);
+                    }
+                }
+                _ => state.stack[depth].0 = replacement.clone(),
+            }
+            state.statements.push(assign(replacement, unshare(value)));
+        }
+        state.statements.push(statement);
+    }
+
+    fn invoke(
+        &mut self,
+        state: &mut State,
+        instruction: &Instruction,
+    ) -> Result<(), Box<dyn Error>> {
+        let pc = instruction.pc;
+        let index = match instruction.operands {
+            Operands::Constant(index) | Operands::InvokeInterface { index, .. } => index,
+            _ => return Err(invalid(pc, "invoke without a method")),
+        };
+        let (owner, name, descriptor) = self.member(index, pc)?;
+        let descriptor = MethodDescriptor::from_descriptor(&descriptor)?;
+        let mut arguments = vec![];
+        for _ in &descriptor.parameters {
+            arguments.push(pop(state, pc)?);
+        }
+        arguments.reverse();
+        let target = match instruction.opcode {
+            INVOKESTATIC => Target::Static(owner),
+            opcode => {
+                let receiver = pop(state, pc)?;
+                let is_this = matches!(receiver, Expr::Var(v) if Some(v) == self.this);
+                if name == "<init>" {
+                    if let Expr::Uninitialized(id, class) = &receiver {
+                        let new = Expr::New {
+                            class: class.clone(),
+                            parameters: descriptor.parameters,
+                            arguments,
+                        };
+                        let mut created = false;
+                        for (value, _) in &mut state.stack {
+                            if matches!(value, Expr::Uninitialized(other, _) if other == id) {
+                                *value = new.clone();
+                                created = true;
+                            }
+                        }
+                        if !created {
+                            self.emit(state, Stmt::Expr(new), Effect::Memory);
+                        }
+                        return Ok(());
+                    }
+                    if is_this {
+                        let target = match owner == self.decompiler.name {
+                            true => Target::This,
+                            false => Target::Super,
+                        };
+                        let call = Expr::Call {
+                            target,
+                            name,
+                            parameters: descriptor.parameters,
+                            return_type: None,
+                            arguments,
+                        };
+                        self.emit(state, Stmt::Expr(call), Effect::Memory);
+                        return Ok(());
+                    }
+                }
+                if opcode == INVOKESPECIAL && is_this && owner != self.decompiler.name {
+                    Target::Super
+                } else {
+                    Target::Object(Box::new(receiver))
+                }
+            }
+        };
+        let call = Expr::Call {
+            target,
+            name,
+            parameters: descriptor.parameters,
+            return_type: descriptor.return_type.clone(),
+            arguments,
+        };
+        match descriptor.return_type {
+            Some(return_type) => state.stack.push((call, return_type.get_size() == 2)),
+            None => self.emit(state, Stmt::Expr(call), Effect::Memory),
+        }
+        Ok(())
+    }
+
+    fn invoke_dynamic(
+        &mut self,
+        state: &mut State,
+        index: u16,
+        pc: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        let (bootstrap_index, name, descriptor) = match self.pool.get(index as usize) {
+            Some(ConstantPool::InvokeDynamic(call_site)) => {
+                let (name, descriptor) = name_and_type(self.pool, call_site.name_and_type_index)
+                    .ok_or_else(|| invalid(pc, "the call site has no name and type"))?;
+                (call_site.bootstrap_method_attr_index, name, descriptor)
+            }
+            _ => {
+                return Err(invalid(
+                    pc,
+                    "invokedynamic of a constant that is not a call site",
+                ))
+            }
+        };
+        let descriptor = MethodDescriptor::from_descriptor(&descriptor)?;
+        let mut arguments = vec![];
+        for _ in &descriptor.parameters {
+            arguments.push(pop(state, pc)?);
+        }
+        arguments.reverse();
+        let (owner, method, static_arguments) = self
+            .bootstrap(bootstrap_index)
+            .ok_or_else(|| invalid(pc, "the call site has no bootstrap method"))?;
+        let value = match (owner.as_str(), method.as_str()) {
+            ("java/lang/invoke/LambdaMetafactory", _) => {
+                self.lambda(&static_arguments, arguments, pc)?
+            }
+            ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants") => {
+                let mut constants = static_arguments
+                    .iter()
+                    .map(|index| self.constant_text(*index));
+                let recipe = constants.next().unwrap_or_default();
+                self.concat(Some(&recipe), constants.collect(), arguments)
+            }
+            ("java/lang/invoke/StringConcatFactory", "makeConcat") => {
+                self.concat(None, vec![], arguments)
+            }
+            _ => Expr::Dynamic {
+                bootstrap: format!("{owner}.{method}"),
+                name,
+                return_type: descriptor.return_type.clone(),
+                arguments,
+            },
+        };
+        match descriptor.return_type {
+            Some(return_type) => state.stack.push((value, return_type.get_size() == 2)),
+            None => self.emit(state, Stmt::Expr(value), Effect::Memory),
+        }
+        Ok(())
+    }
+
+    /**
+     * A lambda whose body is the synthetic method implementing it, or a method reference.\
+     *  The second static argument of LambdaMetafactory is the handle of the implementation.
+     */
+    fn lambda(
+        &mut self,
+        static_arguments: &[u16],
+        arguments: Vec<Expr>,
+        pc: u32,
+    ) -> Result<Expr, Box<dyn Error>> {
+        let (kind, owner, name, descriptor) =
+            match static_arguments.get(1).map(|i| self.pool.get(*i as usize)) {
+                Some(Some(ConstantPool::MethodHandle(handle))) => {
+                    let (owner, name, descriptor) = self.member(handle.reference_index, pc)?;
+                    (handle.reference_kind, owner, name, descriptor)
+                }
+                _ => return Err(invalid(pc, "the lambda has no implementation method")),
+            };
+        const REF_INVOKE_STATIC: u8 = 6;
+        const REF_INVOKE_SPECIAL: u8 = 7;
+        const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+        let receiver = kind != REF_INVOKE_STATIC && kind != REF_NEW_INVOKE_SPECIAL;
+        let decompiler = self.decompiler;
+        if owner == decompiler.name && name.starts_with("lambda$") && decompiler.depth.get() < 8 {
+            let method = decompiler.class.methods.iter().find(|method| {
+                decompiler.utf8(method.name_index) == name
+                    && decompiler.utf8(method.descriptor_index) == descriptor
+            });
+            let captured: Option<Vec<String>> = arguments
+                .iter()
+                .skip(receiver as usize)
+                .map(|argument| match argument {
+                    Expr::Var(v) => Some(self.variables[*v].name.clone()),
+                    _ => None,
+                })
+                .collect();
+            if let (Some(method), Some(captured)) = (method, captured) {
+                decompiler.depth.set(decompiler.depth.get() + 1);
+                let body = MethodBody::new(decompiler, method, &captured);
+                decompiler.depth.set(decompiler.depth.get() - 1);
+                if let Ok(body) = body {
+                    let parameters = body.parameters[captured.len().min(body.parameters.len())..]
+                        .iter()
+                        .map(|parameter| body.variables[*parameter].name.clone())
+                        .collect();
+                    return Ok(Expr::Lambda {
+                        parameters,
+                        body: body.render_lambda(),
+                    });
+                }
+            }
+        }
+        let mut arguments = arguments.into_iter();
+        Ok(match kind {
+            REF_NEW_INVOKE_SPECIAL => Expr::MethodRef(Target::Static(owner), "new".into()),
+            REF_INVOKE_STATIC => Expr::MethodRef(Target::Static(owner), name),
+            REF_INVOKE_SPECIAL if owner != decompiler.name => Expr::MethodRef(Target::Super, name),
+            _ => match arguments.next() {
+                Some(receiver) => Expr::MethodRef(Target::Object(Box::new(receiver)), name),
+                None => Expr::MethodRef(Target::Static(owner), name),
+            },
+        })
+    }
+
+    /**
+     * String concatenation by a StringConcatFactory recipe, in which `\1` stands for the next
+     * argument and `\2` for the next constant.
+     */
+    fn concat(&self, recipe: Option<&str>, constants: Vec<String>, arguments: Vec<Expr>) -> Expr {
+        let mut parts = vec![];
+        match recipe {
+            Some(recipe) => {
+                let mut arguments = arguments.into_iter();
+                let mut constants = constants.into_iter();
+                let mut text = String::new();
+                for c in recipe.chars() {
+                    match c {
+                        '\u{1}' => {
+                            if !text.is_empty() {
+                                parts.push(Expr::Str(std::mem::take(&mut text)));
+                            }
+                            parts.extend(arguments.next());
+                        }
+                        '\u{2}' => text.push_str(&constants.next().unwrap_or_default()),
+                        c => text.push(c),
+                    }
+                }
+                if !text.is_empty() {
+                    parts.push(Expr::Str(text));
+                }
+            }
+            None => parts = arguments,
+        }
+        concatenation(&self.variables, parts)
+    }
+
+    /// The class, name and arguments of a bootstrap method of the class.
+    fn bootstrap(&self, index: u16) -> Option<(String, String, Vec<u16>)> {
+        let method =
+            self.decompiler
+                .class
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    AttributeInfo::BootstrapMethods(methods) => {
+                        methods.bootstrap_methods.get(index as usize)
+                    }
+                    _ => None,
+                })?;
+        let reference = match self.pool.get(method.bootstrap_method_ref as usize)? {
+            ConstantPool::MethodHandle(handle) => handle.reference_index,
+            _ => return None,
+        };
+        let (owner, name, _) = self.member(reference, 0).ok()?;
+        Some((owner, name, method.bootstrap_arguments.clone()))
+    }
+
+    /// The text of a constant that a string concatenation recipe inserts.
+    fn constant_text(&self, index: u16) -> String {
+        match self.pool.get(index as usize) {
+            Some(ConstantPool::String(string)) => {
+                get_raw_utf8(self.pool, string.string_index).unwrap_or_default()
+            }
+            Some(ConstantPool::Integer(integer)) => integer.get_value().to_string(),
+            Some(ConstantPool::Long(long)) => long.get_value().to_string(),
+            Some(ConstantPool::Float(float)) => java_float(float.get_value()),
+            Some(ConstantPool::Double(double)) => java_double(double.get_value()),
+            _ => String::new(),
+        }
+    }
+
+    fn constant(&self, index: u16, pc: u32) -> Result<(Expr, bool), Box<dyn Error>> {
+        Ok(match self.pool.get(index as usize) {
+            Some(ConstantPool::Integer(integer)) => (Expr::Int(integer.get_value()), false),
+            Some(ConstantPool::Float(float)) => (Expr::Float(float.get_value()), false),
+            Some(ConstantPool::Long(long)) => (Expr::Long(long.get_value()), true),
+            Some(ConstantPool::Double(double)) => (Expr::Double(double.get_value()), true),
+            Some(ConstantPool::String(string)) => (
+                Expr::Str(get_raw_utf8(self.pool, string.string_index).unwrap_or_default()),
+                false,
+            ),
+            Some(ConstantPool::Class(_)) => (Expr::Class(self.class_type(index)), false),
+            Some(ConstantPool::MethodType(method_type)) => {
+                let descriptor =
+                    get_raw_utf8(self.pool, method_type.descriptor_index).unwrap_or_default();
+                (
+                    Expr::Text(format!("/* MethodType {descriptor} */ null")),
+                    false,
+                )
+            }
+            Some(ConstantPool::MethodHandle(handle)) => {
+                let (owner, name, _) = self.member(handle.reference_index, pc)?;
+                let owner = self.decompiler.type_name(&owner);
+                (
+                    Expr::Text(format!("/* MethodHandle {owner}::{name} */ null")),
+                    false,
+                )
+            }
+            Some(ConstantPool::Dynamic(dynamic)) => {
+                let (name, descriptor) = name_and_type(self.pool, dynamic.name_and_type_index)
+                    .ok_or_else(|| invalid(pc, "the dynamic constant has no name and type"))?;
+                let wide = matches!(descriptor.as_str(), "J" | "D");
+                (
+                    Expr::Text(format!("/* dynamic constant {name} */ null")),
+                    wide,
+                )
+            }
+            _ => return Err(invalid(pc, "ldc of a constant that is not loadable")),
+        })
+    }
+
+    /// The class of a Class entry as a type, arrays being named by their descriptor.
+    fn class_type(&self, index: u16) -> FieldType {
+        let name = get_raw_class_name(self.pool, index).unwrap_or_default();
+        if name.starts_with('[') {
+            if let Ok(field_type) = FieldType::from_descriptor(&name) {
+                return field_type;
+            }
+        }
+        FieldType::Object(name)
+    }
+
+    /// The class, name and descriptor of a field or method reference.
+    fn member(&self, index: u16, pc: u32) -> Result<(String, String, String), Box<dyn Error>> {
+        let (class_index, name_and_type_index) = match self.pool.get(index as usize) {
+            Some(ConstantPool::Fieldref(field)) => (field.class_index, field.name_and_type_index),
+            Some(ConstantPool::Methodref(method)) => {
+                (method.class_index, method.name_and_type_index)
+            }
+            Some(ConstantPool::InterfaceMethodRef(method)) => {
+                (method.class_index, method.name_and_type_index)
+            }
+            _ => return Err(invalid(pc, "the operand is not a member reference")),
+        };
+        let (name, descriptor) = name_and_type(self.pool, name_and_type_index)
+            .ok_or_else(|| invalid(pc, "the member has no name and type"))?;
+        Ok((
+            get_raw_class_name(self.pool, class_index).unwrap_or_default(),
+            name,
+            descriptor,
+        ))
+    }
+
+    /**
+     * The variable a load or store uses, by the LocalVariableTable entry covering it.\
+     *  A store starts the range of a new variable, which begins after the instruction, so
+     *  stores give the following pc as `next_pc` and loads give their own.
+     */
+    fn local(
+        &mut self,
+        slot: u16,
+        kind: char,
+        pc: u32,
+        next_pc: u32,
+        hint: Option<&FieldType>,
+    ) -> usize {
+        let covers = |local: &LocalName, pc: u32| {
+            local.slot == slot
+                && local.start <= pc
+                && pc < local.end
+                && KINDS[descriptor_kind(&local.descriptor)] == kind
+        };
+        let entry = [next_pc, pc]
+            .iter()
+            .find_map(|pc| self.locals.iter().position(|local| covers(local, *pc)));
+        match entry {
+            Some(entry) => self.variable(VarKey::Named(self.local_groups[entry]), None, kind),
+            None => self.variable(VarKey::Slot(slot, kind), hint, kind),
+        }
+    }
+
+    fn variable(&mut self, key: VarKey, hint: Option<&FieldType>, kind: char) -> usize {
+        if let Some(variable) = self.keys.get(&key) {
+            return *variable;
+        }
+        let variable = match &key {
+            VarKey::Named(entry) => {
+                let local = &self.locals[*entry];
+                let java_type = match &local.signature {
+                    Some(signature) => self
+                        .decompiler
+                        .signature_or_descriptor(signature, &local.descriptor),
+                    None => self.decompiler.descriptor_type(&local.descriptor),
+                };
+                Variable {
+                    name: local.name.clone(),
+                    java_type: Some(java_type),
+                    field_type: FieldType::from_descriptor(&local.descriptor).ok(),
+                    typed: true,
+                    declared: false,
+                    temporary: false,
+                }
+            }
+            key => {
+                let field_type = match (hint, kind) {
+                    (Some(hint), _) => Some(hint.clone()),
+                    (None, 'I') => Some(FieldType::Int),
+                    (None, 'L') => Some(FieldType::Long),
+                    (None, 'F') => Some(FieldType::Float),
+                    (None, 'D') => Some(FieldType::Double),
+                    (None, _) => None,
+                };
+                let base = match (key, &field_type) {
+                    (VarKey::Catch(_), _) => "e".into(),
+                    (_, Some(field_type)) => base_name(field_type),
+                    (_, None) => "obj".into(),
+                };
+                Variable {
+                    name: self.new_name(&base),
+                    java_type: None,
+                    field_type,
+                    typed: false,
+                    declared: false,
+                    temporary: matches!(key, VarKey::Stack(_) | VarKey::Spill(_)),
+                }
+            }
+        };
+        self.variables.push(variable);
+        self.keys.insert(key, self.variables.len() - 1);
+        self.variables.len() - 1
+    }
+
+    /// `base`, or `base2`, `base3` and so on if a variable of the method already has the name.
+    fn new_name(&mut self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut number = 2;
+        while self.names.contains(&name) {
+            name = format!("{base}{number}");
+            number += 1;
+        }
+        self.names.insert(name.clone());
+        name
+    }
+
+    fn stack_variable(&mut self, block: usize, depth: usize, value: Option<&Expr>) -> usize {
+        let group = self.group(block, depth);
+        let hint = value.and_then(|value| expr_type(&self.variables, value));
+        self.variable(VarKey::Stack(group), hint.as_ref(), 'A')
+    }
+
+    fn group(&mut self, block: usize, depth: usize) -> usize {
+        let next = self.groups.len();
+        let id = *self.group_ids.entry((block, depth)).or_insert(next);
+        if id == next {
+            self.groups.push(id);
+        }
+        self.find(id)
+    }
+
+    fn find(&mut self, mut id: usize) -> usize {
+        while self.groups[id] != id {
+            self.groups[id] = self.groups[self.groups[id]];
+            id = self.groups[id];
+        }
+        id
+    }
+
+    fn union(&mut self, first: (usize, usize), second: (usize, usize)) {
+        let first = self.group(first.0, first.1);
+        let second = self.group(second.0, second.1);
+        if first == second {
+            return;
+        }
+        // The group keeps the variable already made for either side, unless both have one.
+        let has_variable = |group| self.keys.contains_key(&VarKey::Stack(group));
+        match (has_variable(first), has_variable(second)) {
+            (true, true) => {
+                self.regroup = true;
+                self.groups[second] = first;
+            }
+            (false, true) => self.groups[first] = second,
+            _ => self.groups[second] = first,
+        }
+    }
+}
+
+fn name_and_type(pool: &[ConstantPool], index: u16) -> Option<(String, String)> {
+    match pool.get(index as usize)? {
+        ConstantPool::NameAndType(name_and_type) => Some((
+            get_raw_utf8(pool, name_and_type.name_index).unwrap_or_default(),
+            get_raw_utf8(pool, name_and_type.descriptor_index).unwrap_or_default(),
+        )),
+        _ => None,
+    }
+}
+
+fn get_signature(pool: &[ConstantPool], attributes: &[AttributeInfo]) -> Option<String> {
+    attributes.iter().find_map(|attribute| match attribute {
+        AttributeInfo::Signature(signature) => {
+            Some(get_raw_utf8(pool, signature.signature_index).unwrap_or_default())
+        }
+        _ => None,
+    })
+}
+
+fn get_code(method: &MethodInfo) -> Option<&Code> {
+    method
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            AttributeInfo::Code(code) => Some(code),
+            _ => None,
+        })
+}
+
+/// The LocalVariableTable of a body, with the signatures of the LocalVariableTypeTable.
+fn local_names(pool: &[ConstantPool], code: &Code) -> Vec<LocalName> {
+    let mut names = vec![];
+    let mut signatures = HashMap::new();
+    for attribute in &code.attributes {
+        match attribute {
+            AttributeInfo::LocalVariableTable(table) => {
+                names.extend(table.local_variable_table.iter().map(|local| LocalName {
+                    start: local.start_pc as u32,
+                    end: local.start_pc as u32 + local.length as u32,
+                    slot: local.index,
+                    name: get_raw_utf8(pool, local.name_index).unwrap_or_default(),
+                    descriptor: get_raw_utf8(pool, local.descriptor_index).unwrap_or_default(),
+                    signature: None,
+                }))
+            }
+            AttributeInfo::LocalVariableTypeTable(table) => {
+                for local in &table.local_variable_type_table {
+                    let key = (local.start_pc, local.length, local.index);
+                    signatures.insert(
+                        key,
+                        get_raw_utf8(pool, local.signature_index).unwrap_or_default(),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    for name in &mut names {
+        let key = (name.start as u16, (name.end - name.start) as u16, name.slot);
+        name.signature = signatures.get(&key).cloned();
+    }
+    names.retain(|name| is_identifier(&name.name));
+    names
+}
+
+/**
+ * The entry of `locals` that stands for each entry.\
+ *  javac gives a variable that is assigned on several paths before it is read a range from
+ *  each assignment, so entries of the same slot, name and type are one variable when a load
+ *  in one can read a store in another.
+ */
+fn merge_split_locals(cfg: &ControlFlowGraph, locals: &[LocalName]) -> Vec<usize> {
+    let mut groups: Vec<usize> = (0..locals.len()).collect();
+    let same = |a: &LocalName, b: &LocalName| {
+        a.slot == b.slot && a.name == b.name && a.descriptor == b.descriptor
+    };
+    let tracked: HashSet<u16> = locals
+        .iter()
+        .enumerate()
+        .filter(|(i, local)| locals[..*i].iter().any(|other| same(local, other)))
+        .map(|(_, local)| local.slot)
+        .collect();
+    if tracked.is_empty() {
+        return groups;
+    }
+    let covering = |slot: u16, pc: u32| {
+        locals
+            .iter()
+            .position(|local| local.slot == slot && local.start <= pc && pc < local.end)
+    };
+    // The entries of the stores that may reach a point, by slot.
+    type Reaching = BTreeMap<u16, BTreeSet<usize>>;
+    let transfer =
+        |block: usize, state: &mut Reaching, loads: &mut Vec<(usize, BTreeSet<usize>)>| {
+            let instructions = cfg.get_instructions(block);
+            for (index, instruction) in instructions.iter().enumerate() {
+                let next_pc = instructions
+                    .get(index + 1)
+                    .map_or(cfg.blocks[block].end_pc, |next| next.pc);
+                let opcode = instruction.opcode;
+                let operand = match instruction.operands {
+                    Operands::Local(index) | Operands::Increment { index, .. } => index,
+                    _ => 0,
+                };
+                let (slot, load, store) = match opcode {
+                    ILOAD..=ALOAD | ILOAD_0..=ALOAD_3 => (
+                        slot_and_kind(opcode, ILOAD, ILOAD_0, operand).0,
+                        true,
+                        false,
+                    ),
+                    ISTORE..=ASTORE | ISTORE_0..=ASTORE_3 => (
+                        slot_and_kind(opcode, ISTORE, ISTORE_0, operand).0,
+                        false,
+                        true,
+                    ),
+                    IINC => (operand, true, true),
+                    _ => continue,
+                };
+                if !tracked.contains(&slot) {
+                    continue;
+                }
+                if load {
+                    if let Some(entry) = covering(slot, instruction.pc) {
+                        loads.push((entry, state.get(&slot).cloned().unwrap_or_default()));
+                    }
+                }
+                if store {
+                    let entry = covering(slot, next_pc).or_else(|| covering(slot, instruction.pc));
+                    state.insert(slot, entry.into_iter().collect());
+                }
+            }
+        };
+    let count = cfg.blocks.len();
+    let mut entry_states: Vec<Reaching> = vec![Reaching::new(); count];
+    let mut work: Vec<usize> = (0..count).rev().collect();
+    while let Some(block) = work.pop() {
+        let mut state = entry_states[block].clone();
+        let before = state.clone();
+        transfer(block, &mut state, &mut vec![]);
+        for edge in &cfg.blocks[block].successors {
+            // A handler may be entered before or after the stores of the block.
+            let incoming = match edge.kind {
+                EdgeKind::Exception(_) => [&before, &state],
+                _ => [&state, &state],
+            };
+            let target = &mut entry_states[edge.target];
+            let mut changed = false;
+            for (slot, entries) in incoming.into_iter().flatten() {
+                let reaching = target.entry(*slot).or_default();
+                for entry in entries {
+                    changed |= reaching.insert(*entry);
+                }
+            }
+            if changed && !work.contains(&edge.target) {
+                work.push(edge.target);
+            }
+        }
+    }
+    let mut loads = vec![];
+    for (block, state) in entry_states.iter().enumerate() {
+        transfer(block, &mut state.clone(), &mut loads);
+    }
+    let find = |groups: &[usize], mut entry: usize| {
+        while groups[entry] != entry {
+            entry = groups[entry];
+        }
+        entry
+    };
+    for (load, stores) in loads {
+        for store in stores {
+            if store != load && same(&locals[load], &locals[store]) {
+                let (a, b) = (find(&groups, load), find(&groups, store));
+                groups[a.max(b)] = a.min(b);
+            }
+        }
+    }
+    (0..locals.len())
+        .map(|entry| find(&groups, entry))
+        .collect()
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        && !KEYWORDS.contains(&name)
+}
+
+const KEYWORDS: [&str; 53] = [
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "try",
+    "void",
+    "volatile",
+    "while",
+    "true",
+    "false",
+    "null",
+];
+
+/// The name a variable of a type is given when the class file does not name it.
+fn base_name(field_type: &FieldType) -> String {
+    let name = match field_type {
+        FieldType::Int => "i".into(),
+        FieldType::Long => "l".into(),
+        FieldType::Float => "f".into(),
+        FieldType::Double => "d".into(),
+        FieldType::Boolean => "flag".into(),
+        FieldType::Char => "c".into(),
+        FieldType::Byte => "b".into(),
+        FieldType::Short => "s".into(),
+        FieldType::Object(name) => {
+            let simple = name.rsplit(['/', '$']).next().unwrap_or_default();
+            let mut chars = simple.chars();
+            match chars.next() {
+                Some(first) if first.is_alphabetic() => first.to_lowercase().chain(chars).collect(),
+                _ => "obj".into(),
+            }
+        }
+        FieldType::Array(component) => match component.as_ref() {
+            FieldType::Object(_) | FieldType::Array(_) => format!("{}s", base_name(component)),
+            _ => format!("{}Array", component.get_java_name()),
+        },
+    };
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("{name}Value")
+    } else {
+        name
+    }
+}
+
+/// The index into [KINDS] of the computational type of a value.
+fn kind_index(field_type: &FieldType) -> usize {
+    match field_type {
+        FieldType::Long => 1,
+        FieldType::Float => 2,
+        FieldType::Double => 3,
+        FieldType::Object(_) | FieldType::Array(_) => 4,
+        _ => 0,
+    }
+}
+
+fn descriptor_kind(descriptor: &str) -> usize {
+    match descriptor.chars().next() {
+        Some('J') => 1,
+        Some('F') => 2,
+        Some('D') => 3,
+        Some('L' | '[') => 4,
+        _ => 0,
+    }
+}
+
+/// The slot and computational type of a load or store, by its opcode.
+fn slot_and_kind(opcode: u8, first: u8, first_short: u8, operand: u16) -> (u16, char) {
+    if opcode >= first_short {
+        let index = opcode - first_short;
+        ((index % 4) as u16, KINDS[(index / 4) as usize])
+    } else {
+        (operand, KINDS[(opcode - first) as usize])
+    }
+}
+
+/// The target of the first edge of a kind leaving a block.
+fn edge_target(
+    cfg: &ControlFlowGraph,
+    block: usize,
+    kind: impl Fn(&EdgeKind) -> bool,
+) -> Option<usize> {
+    cfg.blocks[block]
+        .successors
+        .iter()
+        .find(|edge| kind(&edge.kind))
+        .map(|edge| edge.target)
+}
+
+/// The type of the exception a handler catches, Throwable when it catches any.
+fn basic_catch_type(cfg: &ControlFlowGraph, handler: usize) -> FieldType {
+    let caught = cfg
+        .blocks
+        .iter()
+        .flat_map(|block| &block.successors)
+        .find_map(|edge| match &edge.kind {
+            EdgeKind::Exception(Some(class)) if edge.target == handler => Some(class.clone()),
+            _ => None,
+        });
+    FieldType::Object(caught.unwrap_or_else(|| "java/lang/Throwable".into()))
+}
+
+fn invalid(pc: u32, message: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(
+        LoadingCause::InvalidInstruction(pc),
+        message,
+    ))
+}
+
+fn pop(state: &mut State, pc: u32) -> Result<Expr, Box<dyn Error>> {
+    match state.stack.pop() {
+        Some((value, _)) => Ok(value),
+        None => Err(invalid(pc, "the operand stack underflows")),
+    }
+}
+
+/// Pops values taking up `slots` stack slots, in the order they were pushed.
+fn pop_slots(
+    state: &mut State,
+    slots: usize,
+    pc: u32,
+) -> Result<Vec<(Expr, bool)>, Box<dyn Error>> {
+    let mut values = vec![];
+    let mut popped = 0;
+    while popped < slots {
+        let value = state
+            .stack
+            .pop()
+            .ok_or_else(|| invalid(pc, "the operand stack underflows"))?;
+        popped += if value.1 { 2 } else { 1 };
+        values.push(value);
+    }
+    if popped != slots {
+        return Err(invalid(pc, "the instruction splits a long or double"));
+    }
+    values.reverse();
+    Ok(values)
+}
+
+/// The value a dup shared, for where it is used only once after all.
+fn unshare(value: Expr) -> Expr {
+    match value {
+        Expr::Dup(_, value) => unshare(*value),
+        value => value,
+    }
+}
+
+fn assign(target: Expr, value: Expr) -> Stmt {
+    Stmt::Expr(Expr::Assign(Box::new(target), Box::new(value)))
+}
+
+fn binary(operator: &'static str, left: Expr, right: Expr) -> Expr {
+    Expr::Binary(operator, Box::new(left), Box::new(right))
+}
+
+fn default_value(field_type: &FieldType) -> Expr {
+    match field_type {
+        FieldType::Boolean => Expr::Bool(false),
+        FieldType::Char => Expr::Char(0),
+        FieldType::Long => Expr::Long(0),
+        FieldType::Float => Expr::Float(0.0),
+        FieldType::Double => Expr::Double(0.0),
+        FieldType::Object(_) | FieldType::Array(_) => Expr::Null,
+        _ => Expr::Int(0),
+    }
+}
+
+/// The type of an expression, where the class file gives it.
+fn expr_type(variables: &[Variable], expr: &Expr) -> Option<FieldType> {
+    let object = |name: &str| Some(FieldType::Object(name.into()));
+    match expr {
+        Expr::Int(_) => Some(FieldType::Int),
+        Expr::Long(_) => Some(FieldType::Long),
+        Expr::Float(_) => Some(FieldType::Float),
+        Expr::Double(_) => Some(FieldType::Double),
+        Expr::Bool(_) => Some(FieldType::Boolean),
+        Expr::Char(_) => Some(FieldType::Char),
+        Expr::Str(_) => object("java/lang/String"),
+        Expr::Class(_) => object("java/lang/Class"),
+        Expr::Var(v) => variables.get(*v).and_then(|v| v.field_type.clone()),
+        Expr::Field { field_type, .. } => Some(field_type.clone()),
+        Expr::Element { element_type, .. } => Some(element_type.clone()),
+        Expr::Length(_) | Expr::Compare(..) => Some(FieldType::Int),
+        Expr::Binary(operator, left, right) => match *operator {
+            "==" | "!=" | "<" | ">=" | ">" | "<=" | "&&" | "||" => Some(FieldType::Boolean),
+            "<<" | ">>" | ">>>" => expr_type(variables, left),
+            "+" if [left, right]
+                .iter()
+                .any(|e| expr_type(variables, e) == object("java/lang/String")) =>
+            {
+                object("java/lang/String")
+            }
+            _ => {
+                let left = expr_type(variables, left);
+                let right = expr_type(variables, right);
+                match (&left, &right) {
+                    (Some(FieldType::Boolean), Some(FieldType::Boolean)) => left,
+                    (Some(l), Some(r)) => {
+                        Some([l, r].into_iter().max_by_key(|t| numeric_rank(t))?.clone())
+                    }
+                    _ => left.or(right),
+                }
+            }
+        },
+        Expr::Not(_) | Expr::InstanceOf(..) => Some(FieldType::Boolean),
+        Expr::Negate(e) | Expr::Increment(e, _) | Expr::Dup(_, e) => expr_type(variables, e),
+        Expr::Cast(field_type, _) => Some(field_type.clone()),
+        Expr::Call { return_type, .. } | Expr::Dynamic { return_type, .. } => return_type.clone(),
+        Expr::New { class, .. } | Expr::Uninitialized(_, class) => object(class),
+        Expr::NewArray { array_type, .. } | Expr::ArrayInit { array_type, .. } => {
+            Some(array_type.clone())
+        }
+        Expr::Assign(target, _) => expr_type(variables, target),
+        Expr::Conditional(_, a, b) => expr_type(variables, a).or_else(|| expr_type(variables, b)),
+        _ => None,
+    }
+}
+
+/// How wide a primitive is under binary numeric promotion.
+fn numeric_rank(field_type: &FieldType) -> u8 {
+    match field_type {
+        FieldType::Double => 4,
+        FieldType::Float => 3,
+        FieldType::Long => 2,
+        _ => 1,
+    }
+}
+
+/// The condition that holds exactly when `condition` does not.
+fn negate(condition: Expr) -> Expr {
+    match condition {
+        Expr::Bool(value) => Expr::Bool(!value),
+        Expr::Not(inner) => *inner,
+        Expr::Binary(operator, left, right) => {
+            let opposite = match operator {
+                "==" => "!=",
+                "!=" => "==",
+                "<" => ">=",
+                ">=" => "<",
+                ">" => "<=",
+                "<=" => ">",
+                "&&" => return binary("||", negate(*left), negate(*right)),
+                "||" => return binary("&&", negate(*left), negate(*right)),
+                _ => return Expr::Not(Box::new(Expr::Binary(operator, left, right))),
+            };
+            Expr::Binary(opposite, left, right)
+        }
+        condition => Expr::Not(Box::new(condition)),
+    }
+}
+
+/**
+ * The immediate dominator of each node reachable from `entry`, by the iterative algorithm of
+ * Cooper, Harvey and Kennedy. The entry is its own immediate dominator.
+ */
+fn immediate_dominators(
+    count: usize,
+    entry: usize,
+    successors: &[Vec<usize>],
+) -> Vec<Option<usize>> {
+    let mut order = vec![];
+    let mut visited = vec![false; count];
+    let mut stack = vec![(entry, 0)];
+    visited[entry] = true;
+    while let Some((node, next)) = stack.pop() {
+        match successors[node].get(next) {
+            Some(&successor) => {
+                stack.push((node, next + 1));
+                if !visited[successor] {
+                    visited[successor] = true;
+                    stack.push((successor, 0));
+                }
+            }
+            None => order.push(node),
+        }
+    }
+    order.reverse();
+    let mut position = vec![usize::MAX; count];
+    for (index, node) in order.iter().enumerate() {
+        position[*node] = index;
+    }
+    let mut predecessors = vec![vec![]; count];
+    for (node, targets) in successors.iter().enumerate() {
+        for target in targets {
+            predecessors[*target].push(node);
+        }
+    }
+    let mut dominators: Vec<Option<usize>> = vec![None; count];
+    dominators[entry] = Some(entry);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for node in order.iter().skip(1) {
+            let mut new = None;
+            for &predecessor in &predecessors[*node] {
+                if dominators[predecessor].is_none() {
+                    continue;
+                }
+                new = Some(match new {
+                    None => predecessor,
+                    Some(mut other) => {
+                        let mut finger = predecessor;
+                        while finger != other {
+                            while position[finger] > position[other] {
+                                finger = dominators[finger].unwrap_or(entry);
+                            }
+                            while position[other] > position[finger] {
+                                other = dominators[other].unwrap_or(entry);
+                            }
+                        }
+                        finger
+                    }
+                });
+            }
+            if new.is_some() && dominators[*node] != new {
+                dominators[*node] = new;
+                changed = true;
+            }
+        }
+    }
+    dominators
+}
+
+fn string_literal(text: &str) -> String {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    format!("\"{}\"", quote(&units, '"'))
+}
+
+fn char_literal(value: u16) -> String {
+    format!("'{}'", quote(&[value], '\''))
+}
+
+fn float_literal(value: f32) -> String {
+    match value {
+        value if value.is_nan() => "Float.NaN".into(),
+        f32::INFINITY => "Float.POSITIVE_INFINITY".into(),
+        f32::NEG_INFINITY => "Float.NEGATIVE_INFINITY".into(),
+        value => format!("{}F", java_float(value)),
+    }
+}
+
+fn double_literal(value: f64) -> String {
+    match value {
+        value if value.is_nan() => "Double.NaN".into(),
+        f64::INFINITY => "Double.POSITIVE_INFINITY".into(),
+        f64::NEG_INFINITY => "Double.NEGATIVE_INFINITY".into(),
+        value => java_double(value),
+    }
+}
+
+/**
+ * Sends the jumps to a block that does nothing but jump on straight to where it jumps, and
+ * drops the block, so that it does not stand between a statement and what follows it.
+ */
+fn thread_jumps(nodes: &mut [Node]) {
+    let forward = |nodes: &[Node], mut target: usize| {
+        for _ in 0..nodes.len() {
+            let node = &nodes[target];
+            match node.exit {
+                Exit::Goto(next) if target != 0 && node.live && node.statements.is_empty() => {
+                    target = next
+                }
+                _ => break,
+            }
+        }
+        target
+    };
+    for node in 0..nodes.len() {
+        if !nodes[node].live {
+            continue;
+        }
+        let mut exit = nodes[node].exit.clone();
+        match &mut exit {
+            Exit::End => {}
+            Exit::Goto(target) => *target = forward(nodes, *target),
+            Exit::Branch(_, taken, next) => {
+                *taken = forward(nodes, *taken);
+                *next = forward(nodes, *next);
+            }
+            Exit::Switch(_, cases, default) => {
+                for (_, target) in cases {
+                    *target = forward(nodes, *target);
+                }
+                *default = forward(nodes, *default);
+            }
+        }
+        nodes[node].exit = exit;
+    }
+    let mut targeted = vec![false; nodes.len()];
+    targeted[0] = true;
+    for node in nodes.iter().filter(|node| node.live) {
+        for target in node.exit.targets().into_iter().chain(node.handlers.clone()) {
+            targeted[target] = true;
+        }
+    }
+    for (node, targeted) in nodes.iter_mut().zip(targeted) {
+        node.live &= targeted;
+    }
+}
+
+/**
+ * Merges a branch into the branch of the block before it where the two make up one `&&` or
+ * `||` condition: the second block has no statements and no other predecessor.
+ */
+fn merge_conditions(nodes: &mut [Node]) {
+    loop {
+        let mut predecessors = vec![0; nodes.len()];
+        for node in nodes.iter().filter(|node| node.live) {
+            for target in node.exit.targets() {
+                predecessors[target] += 1;
+            }
+            for handler in &node.handlers {
+                predecessors[*handler] += 1;
+            }
+        }
+        let mut merged = false;
+        for first in 0..nodes.len() {
+            let Exit::Branch(condition, taken, next) = &nodes[first].exit else {
+                continue;
+            };
+            if !nodes[first].live {
+                continue;
+            }
+            let (condition, taken, next) = (condition.clone(), *taken, *next);
+            for second in [next, taken] {
+                let node = &nodes[second];
+                if second == first
+                    || predecessors[second] != 1
+                    || !node.statements.is_empty()
+                    || node.handlers != nodes[first].handlers
+                {
+                    continue;
+                }
+                let Exit::Branch(inner, inner_taken, inner_next) = &node.exit else {
+                    continue;
+                };
+                let (inner, inner_taken, inner_next) = (inner.clone(), *inner_taken, *inner_next);
+                let exit = if second == next && inner_taken == taken {
+                    Exit::Branch(binary("||", condition.clone(), inner), taken, inner_next)
+                } else if second == next && inner_next == taken {
+                    Exit::Branch(
+                        binary("&&", negate(condition.clone()), inner),
+                        inner_taken,
+                        taken,
+                    )
+                } else if second == taken && inner_next == next {
+                    Exit::Branch(binary("&&", condition.clone(), inner), inner_taken, next)
+                } else if second == taken && inner_taken == next {
+                    Exit::Branch(
+                        binary("&&", condition.clone(), negate(inner)),
+                        inner_next,
+                        next,
+                    )
+                } else {
+                    continue;
+                };
+                nodes[first].exit = exit;
+                nodes[second].live = false;
+                merged = true;
+                break;
+            }
+        }
+        if !merged {
+            return;
+        }
+    }
+}
+
+/// A loop of a method body, by the blocks dominated by its header that reach back to it.
+struct Loop {
+    body: Vec<bool>,
+    latches: Vec<usize>,
+    /// The block the loop exits to, if it has one.
+    follow: Option<usize>,
+}
+
+/// The blocks a group of exception table entries with the same handlers cover.
+struct TryRegion {
+    start: usize,
+    blocks: Vec<bool>,
+    /// Each handler block, with the classes of the exceptions it catches.
+    handlers: Vec<(usize, Vec<Option<String>>)>,
+}
+
+/// A loop or switch that the structurer is inside, which `break` or `continue` may leave.
+struct Context {
+    /// The header of a loop, `None` for a switch.
+    header: Option<usize>,
+    follow: Option<usize>,
+    label: String,
+    labeled: bool,
+    /// The block whose branch is the condition of a do-while loop.
+    latch: Option<usize>,
+    /// The case of a switch that the case being structured falls through to.
+    next_case: Option<usize>,
+}
+
+/**
+ * Turns the blocks of a method body into nested statements.\
+ *  Each branch is structured up to its immediate post-dominator, loops by their headers and
+ *  try statements by the exception table; flow that does not fit becomes `break`,
+ *  `continue` or, as a last resort, a `// goto` comment.
+ */
+struct Structurer {
+    nodes: Vec<Node>,
+    successors: Vec<Vec<usize>>,
+    dominators: Vec<Option<usize>>,
+    post_dominators: Vec<Option<usize>>,
+    loops: HashMap<usize, Loop>,
+    tries: Vec<TryRegion>,
+    opened_loops: HashSet<usize>,
+    opened_tries: Vec<bool>,
+    emitted: Vec<bool>,
+    contexts: Vec<Context>,
+    labels: usize,
+    caught: HashMap<usize, usize>,
+}
+
+impl Structurer {
+    fn new(
+        nodes: Vec<Node>,
+        code: &Code,
+        pool: &[ConstantPool],
+        cfg: &ControlFlowGraph,
+        caught: HashMap<usize, usize>,
+    ) -> Structurer {
+        let count = nodes.len();
+        let successors: Vec<Vec<usize>> = nodes
+            .iter()
+            .map(|node| match node.live {
+                true => node.exit.targets(),
+                false => vec![],
+            })
+            .collect();
+        let all: Vec<Vec<usize>> = nodes
+            .iter()
+            .zip(&successors)
+            .map(|(node, targets)| match node.live {
+                true => targets.iter().chain(&node.handlers).copied().collect(),
+                false => vec![],
+            })
+            .collect();
+        let dominators = immediate_dominators(count, 0, &all);
+        // The post-dominators are the dominators of the reversed graph from a virtual exit.
+        let mut reverse = vec![vec![]; count + 1];
+        for (node, targets) in successors.iter().enumerate() {
+            if nodes[node].live && dominators[node].is_some() {
+                for target in targets {
+                    reverse[*target].push(node);
+                }
+                // Paths that end in a throw do not hold back the join of the other paths.
+                let throws = matches!(nodes[node].statements.last(), Some(Stmt::Throw(_)));
+                if targets.is_empty() && !throws {
+                    reverse[count].push(node);
+                }
+            }
+        }
+        let post_dominators = immediate_dominators(count + 1, count, &reverse);
+        let mut structurer = Structurer {
+            nodes,
+            successors,
+            dominators,
+            post_dominators,
+            loops: HashMap::new(),
+            tries: vec![],
+            opened_loops: HashSet::new(),
+            opened_tries: vec![],
+            emitted: vec![false; count],
+            contexts: vec![],
+            labels: 0,
+            caught,
+        };
+        structurer.find_loops(&all);
+        structurer.find_tries(code, pool, cfg);
+        structurer
+    }
+
+    fn dominates(&self, dominator: usize, mut node: usize) -> bool {
+        loop {
+            if node == dominator {
+                return true;
+            }
+            match self.dominators[node] {
+                Some(next) if next != node => node = next,
+                _ => return false,
+            }
+        }
+    }
+
+    fn post_dominates(&self, dominator: usize, mut node: usize) -> bool {
+        loop {
+            if node == dominator {
+                return true;
+            }
+            match self.post_dominators[node] {
+                Some(next) if next != node => node = next,
+                _ => return false,
+            }
+        }
+    }
+
+    fn find_loops(&mut self, all: &[Vec<usize>]) {
+        let count = self.nodes.len();
+        let mut predecessors = vec![vec![]; count];
+        for (node, targets) in all.iter().enumerate() {
+            for target in targets {
+                predecessors[*target].push(node);
+            }
+        }
+        let mut latches: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for node in 0..count {
+            if self.dominators[node].is_none() {
+                continue;
+            }
+            for target in &self.successors[node] {
+                if self.dominates(*target, node) {
+                    latches.entry(*target).or_default().push(node);
+                }
+            }
+        }
+        for (header, latches) in latches {
+            let mut body = vec![false; count];
+            body[header] = true;
+            let mut work = latches.clone();
+            while let Some(node) = work.pop() {
+                if body[node] || !self.dominates(header, node) {
+                    continue;
+                }
+                body[node] = true;
+                work.extend(&predecessors[node]);
+            }
+            let outside = |target: &usize| !body[*target];
+            let exit_of = |node: usize| match &self.nodes[node].exit {
+                Exit::Branch(_, taken, next) if body[*taken] != body[*next] => {
+                    [*taken, *next].into_iter().find(outside)
+                }
+                _ => None,
+            };
+            let follow = exit_of(header)
+                .or_else(|| match latches.as_slice() {
+                    [latch] => exit_of(*latch),
+                    _ => None,
+                })
+                .or_else(|| {
+                    let last = (0..count)
+                        .filter(|node| body[*node])
+                        .map(|node| self.nodes[node].start_pc)
+                        .max()?;
+                    (0..count)
+                        .filter(|node| body[*node])
+                        .flat_map(|node| &self.successors[node])
+                        .filter(|target| outside(target) && self.nodes[**target].start_pc > last)
+                        .min_by_key(|target| self.nodes[**target].start_pc)
+                        .copied()
+                });
+            self.loops.insert(
+                header,
+                Loop {
+                    body,
+                    latches,
+                    follow,
+                },
+            );
+        }
+    }
+
+    fn find_tries(&mut self, code: &Code, pool: &[ConstantPool], cfg: &ControlFlowGraph) {
+        let count = self.nodes.len();
+        let mut handlers: Vec<(usize, Vec<bool>, Vec<Option<String>>)> = vec![];
+        for entry in &code.exception_tables {
+            let Some(handler) = cfg.get_block_at(entry.handler_pc as u32) else {
+                continue;
+            };
+            if !self.nodes[handler].live {
+                continue;
+            }
+            let catch_type = match entry.catch_type {
+                0 => None,
+                index => Some(get_raw_class_name(pool, index).unwrap_or_default()),
+            };
+            let range = entry.start_pc as u32..entry.end_pc as u32;
+            let blocks: Vec<bool> = (0..count)
+                .map(|node| {
+                    self.nodes[node].live
+                        && self.dominators[node].is_some()
+                        && range.contains(&self.nodes[node].start_pc)
+                        && !self.dominates(handler, node)
+                })
+                .collect();
+            match handlers.iter_mut().find(|(other, ..)| *other == handler) {
+                Some((_, covered, types)) => {
+                    for (covered, block) in covered.iter_mut().zip(blocks) {
+                        *covered |= block;
+                    }
+                    if !types.contains(&catch_type) {
+                        types.push(catch_type);
+                    }
+                }
+                None => handlers.push((handler, blocks, vec![catch_type])),
+            }
+        }
+        for (handler, blocks, types) in handlers {
+            let Some(start) = blocks.iter().position(|block| *block) else {
+                continue;
+            };
+            match self.tries.iter_mut().find(|region| region.blocks == blocks) {
+                Some(region) => region.handlers.push((handler, types)),
+                None => self.tries.push(TryRegion {
+                    start,
+                    blocks,
+                    handlers: vec![(handler, types)],
+                }),
+            }
+        }
+        for region in &mut self.tries {
+            region.start = (0..count)
+                .filter(|node| region.blocks[*node])
+                .min_by_key(|node| self.nodes[*node].start_pc)
+                .unwrap_or(region.start);
+            region
+                .handlers
+                .sort_by_key(|(handler, _)| self.nodes[*handler].start_pc);
+        }
+        self.tries
+            .sort_by_key(|region| std::cmp::Reverse(region.blocks.iter().filter(|b| **b).count()));
+        self.opened_tries = vec![false; self.tries.len()];
+    }
+
+    /// Structures the blocks from `node` until `stop`, or until the flow ends or jumps out.
+    fn structure(
+        &mut self,
+        mut node: usize,
+        stop: Option<usize>,
+        mut enter: bool,
+        out: &mut Vec<Stmt>,
+    ) {
+        loop {
+            if Some(node) == stop {
+                return;
+            }
+            if !enter {
+                if let Some(jump) = self.jump(node) {
+                    out.extend(jump);
+                    return;
+                }
+            }
+            enter = false;
+            if self.emitted[node] && self.is_small_exit(node) {
+                out.extend(self.nodes[node].statements.iter().cloned());
+                return;
+            }
+            if self.emitted[node] && self.is_small_jump(node) {
+                if let Exit::Goto(target) = self.nodes[node].exit {
+                    if let Some(jump) = self.jump(target) {
+                        out.extend(self.nodes[node].statements.iter().cloned());
+                        out.extend(jump);
+                        return;
+                    }
+                }
+            }
+            if self.emitted[node] || !self.nodes[node].live {
+                out.push(Stmt::Comment(format!("goto {}", self.nodes[node].start_pc)));
+                return;
+            }
+            let is_header = self.loops.contains_key(&node) && !self.opened_loops.contains(&node);
+            let region = (0..self.tries.len())
+                .find(|region| !self.opened_tries[*region] && self.tries[*region].start == node)
+                .filter(|region| {
+                    let blocks = &self.tries[*region].blocks;
+                    !is_header
+                        || self.loops[&node]
+                            .body
+                            .iter()
+                            .zip(blocks)
+                            .all(|(body, block)| !body || *block)
+                });
+            let next = if let Some(region) = region {
+                self.structure_try(region, stop, out)
+            } else if is_header {
+                self.structure_loop(node, out)
+            } else {
+                self.emitted[node] = true;
+                out.extend(self.nodes[node].statements.iter().cloned());
+                let is_latch = self
+                    .contexts
+                    .iter()
+                    .any(|context| context.latch == Some(node));
+                match self.nodes[node].exit.clone() {
+                    _ if is_latch => None,
+                    Exit::End => None,
+                    Exit::Goto(target) => Some(target),
+                    Exit::Branch(condition, taken, next) => {
+                        self.structure_if(node, condition, taken, next, stop, out)
+                    }
+                    Exit::Switch(value, cases, default) => {
+                        self.structure_switch(node, value, cases, default, stop, out)
+                    }
+                }
+            };
+            match next {
+                Some(next) => node = next,
+                None => return,
+            }
+        }
+    }
+
+    /// Whether a block only returns or throws after a few statements, so it may be repeated.
+    fn is_small_exit(&self, node: usize) -> bool {
+        let node = &self.nodes[node];
+        matches!(node.exit, Exit::End) && node.statements.len() <= 3 && node.handlers.is_empty()
+    }
+
+    /**
+     * Whether a block only jumps on after a few statements, so that it may be repeated where
+     * the jump is a `break` or `continue`, as the update of a loop reached from a catch is.
+     */
+    fn is_small_jump(&self, node: usize) -> bool {
+        let node = &self.nodes[node];
+        matches!(node.exit, Exit::Goto(_)) && node.statements.len() <= 3 && node.handlers.is_empty()
+    }
+
+    /**
+     * The statement that leaves the loops and switches being structured for `node`, with a
+     * label if it does not leave the innermost. An empty statement falls through to a case.
+     */
+    fn jump(&mut self, node: usize) -> Option<Option<Stmt>> {
+        let count = self.contexts.len();
+        for index in (0..count).rev() {
+            let context = &self.contexts[index];
+            if context.next_case == Some(node) && index == count - 1 {
+                return Some(None);
+            }
+            let inner = &self.contexts[index + 1..];
+            let (statement, needs_label) = if context.header == Some(node) {
+                let inner_loop = inner.iter().any(|context| context.header.is_some());
+                (Stmt::Continue as fn(Option<String>) -> Stmt, inner_loop)
+            } else if context.follow == Some(node) {
+                (Stmt::Break as fn(Option<String>) -> Stmt, !inner.is_empty())
+            } else {
+                continue;
+            };
+            let context = &mut self.contexts[index];
+            context.labeled |= needs_label;
+            let label = needs_label.then(|| context.label.clone());
+            return Some(Some(statement(label)));
+        }
+        None
+    }
+
+    fn push_context(&mut self, header: Option<usize>, follow: Option<usize>) {
+        self.labels += 1;
+        self.contexts.push(Context {
+            header,
+            follow,
+            label: format!("label{}", self.labels),
+            labeled: false,
+            latch: None,
+            next_case: None,
+        });
+    }
+
+    /// Pops the innermost context, returning its label if a jump named it.
+    fn pop_context(&mut self) -> Option<String> {
+        let context = self.contexts.pop()?;
+        context.labeled.then_some(context.label)
+    }
+
+    /**
+     * A follow to structure a branch or switch up to: not the virtual exit, within the region
+     * ending at `stop`, and within the innermost loop.
+     */
+    fn constrain(&self, follow: Option<usize>, stop: Option<usize>) -> Option<usize> {
+        let follow = follow.filter(|follow| *follow < self.nodes.len())?;
+        if let Some(stop) = stop {
+            if !self.post_dominates(stop, follow) {
+                return None;
+            }
+        }
+        let innermost = self
+            .contexts
+            .iter()
+            .rev()
+            .find_map(|context| context.header);
+        if let Some(header) = innermost {
+            if !self.loops[&header].body[follow] && follow != header {
+                return None;
+            }
+        }
+        Some(follow)
+    }
+
+    /// Whether the flow from `from` can reach `to` without leaving the region being structured.
+    fn reaches(&self, from: usize, to: usize, stop: Option<usize>) -> bool {
+        from == to || self.reachable(from, stop).contains(&to)
+    }
+
+    /**
+     * The blocks the flow from `from` can reach without leaving the region being structured,
+     * which ends at `stop` and at the targets of `break` and `continue`.
+     */
+    fn reachable(&self, from: usize, stop: Option<usize>) -> HashSet<usize> {
+        let mut visited = HashSet::new();
+        let mut work = vec![from];
+        while let Some(node) = work.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            let is_jump = self.contexts.iter().any(|context| {
+                context.header == Some(node)
+                    || context.follow == Some(node)
+                    || context.next_case == Some(node)
+            });
+            if Some(node) != stop && !is_jump && !self.emitted[node] {
+                work.extend(&self.successors[node]);
+            }
+        }
+        visited
+    }
+
+    /**
+     * Where the branches of an if meet when one of them returns early, so that the node has
+     * no post-dominator: the first block both can reach that is not a jump.
+     */
+    fn join(&self, first: usize, second: usize, stop: Option<usize>) -> Option<usize> {
+        let reachable = self.reachable(first, stop);
+        let other = self.reachable(second, stop);
+        let is_jump = |node: usize| {
+            self.contexts.iter().any(|context| {
+                context.header == Some(node)
+                    || context.follow == Some(node)
+                    || context.next_case == Some(node)
+            })
+        };
+        let join = reachable
+            .intersection(&other)
+            .filter(|node| !is_jump(**node) && !self.emitted[**node] && !self.is_small_exit(**node))
+            .min_by_key(|node| self.nodes[**node].start_pc)
+            .copied();
+        self.constrain(join, stop)
+            .filter(|join| Some(*join) != stop)
+    }
+
+    fn structure_if(
+        &mut self,
+        node: usize,
+        condition: Expr,
+        taken: usize,
+        next: usize,
+        stop: Option<usize>,
+        out: &mut Vec<Stmt>,
+    ) -> Option<usize> {
+        let follow = self.constrain(self.post_dominators[node], stop);
+        if let Some(follow) = follow.or_else(|| self.join(taken, next, stop)) {
+            let mut then = vec![];
+            if next == follow {
+                self.structure(taken, Some(follow), false, &mut then);
+                out.push(Stmt::If(condition, then, vec![]));
+            } else if taken == follow {
+                self.structure(next, Some(follow), false, &mut then);
+                out.push(Stmt::If(negate(condition), then, vec![]));
+            } else {
+                let mut otherwise = vec![];
+                self.structure(next, Some(follow), false, &mut then);
+                self.structure(taken, Some(follow), false, &mut otherwise);
+                out.push(Stmt::If(negate(condition), then, otherwise));
+            }
+            return Some(follow);
+        }
+        for (branch, other, negated) in [(taken, next, false), (next, taken, true)] {
+            let escapes = stop.is_some_and(|stop| self.reaches(branch, stop, Some(stop)));
+            if !escapes && !self.reaches(branch, other, stop) {
+                let mut then = vec![];
+                self.structure(branch, stop, false, &mut then);
+                let condition = match negated {
+                    true => negate(condition),
+                    false => condition,
+                };
+                out.push(Stmt::If(condition, then, vec![]));
+                return Some(other);
+            }
+        }
+        let (mut then, mut otherwise) = (vec![], vec![]);
+        self.structure(next, stop, false, &mut then);
+        self.structure(taken, stop, false, &mut otherwise);
+        out.push(Stmt::If(negate(condition), then, otherwise));
+        None
+    }
+
+    fn structure_switch(
+        &mut self,
+        node: usize,
+        value: Expr,
+        cases: Vec<(i32, usize)>,
+        default: usize,
+        stop: Option<usize>,
+        out: &mut Vec<Stmt>,
+    ) -> Option<usize> {
+        let all_targets: Vec<usize> = cases
+            .iter()
+            .map(|(_, target)| *target)
+            .chain([default])
+            .collect();
+        let follow = self
+            .constrain(self.post_dominators[node], stop)
+            .or_else(|| self.switch_join(&all_targets, stop));
+        let mut targets: Vec<(usize, Vec<Option<i32>>)> = vec![];
+        let keys = cases.into_iter().map(|(key, target)| (Some(key), target));
+        for (key, target) in keys.chain([(None, default)]) {
+            // The gaps of a tableswitch go to the default.
+            if Some(target) == follow && Some(default) == follow
+                || key.is_some() && target == default
+            {
+                continue;
+            }
+            match targets.iter_mut().find(|(other, _)| *other == target) {
+                Some((_, keys)) => keys.push(key),
+                None => targets.push((target, vec![key])),
+            }
+        }
+        targets.sort_by_key(|(target, _)| self.nodes[*target].start_pc);
+        self.push_context(None, follow);
+        let mut bodies = vec![];
+        for index in 0..targets.len() {
+            let (target, keys) = targets[index].clone();
+            let mut body = vec![];
+            if Some(target) == follow {
+                body.push(Stmt::Break(None));
+            } else {
+                let next_case = targets.get(index + 1).map(|(target, _)| *target);
+                if let Some(context) = self.contexts.last_mut() {
+                    context.next_case = next_case.filter(|next| Some(*next) != follow);
+                }
+                // Reaching the follow is a jump, so that the case ends with a break.
+                let stop = match follow {
+                    Some(_) => None,
+                    None => stop,
+                };
+                self.structure(target, stop, true, &mut body);
+            }
+            bodies.push((keys, body));
+        }
+        // The last case needs no break to leave the switch.
+        if let Some((_, body)) = bodies.last_mut() {
+            if body.last() == Some(&Stmt::Break(None)) {
+                body.pop();
+            }
+        }
+        let label = self.pop_context();
+        out.push(Stmt::Switch(label, value, bodies));
+        follow
+    }
+
+    /**
+     * Where the cases of a switch meet when one of them returns, so that the switch has no
+     * post-dominator: the first block that two of the cases reach and that is not a case.
+     */
+    fn switch_join(&self, targets: &[usize], stop: Option<usize>) -> Option<usize> {
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        let mut distinct = targets.to_vec();
+        distinct.sort_unstable();
+        distinct.dedup();
+        for target in &distinct {
+            for node in self.reachable(*target, stop) {
+                *counts.entry(node).or_default() += 1;
+            }
+        }
+        let is_jump = |node: usize| {
+            self.contexts
+                .iter()
+                .any(|context| context.header == Some(node) || context.follow == Some(node))
+        };
+        let join = counts
+            .into_iter()
+            .filter(|(node, count)| {
+                *count > 1 && !distinct.contains(node) && !is_jump(*node) && !self.emitted[*node]
+            })
+            .map(|(node, _)| node)
+            .min_by_key(|node| self.nodes[*node].start_pc);
+        self.constrain(join, stop)
+            .filter(|join| Some(*join) != stop)
+    }
+
+    fn structure_loop(&mut self, header: usize, out: &mut Vec<Stmt>) -> Option<usize> {
+        self.opened_loops.insert(header);
+        let follow = self.loops[&header].follow;
+        let single_latch = match self.loops[&header].latches.as_slice() {
+            [latch] => Some(*latch),
+            _ => None,
+        };
+        self.push_context(Some(header), follow);
+        let mut body = vec![];
+        let statement = match self.nodes[header].exit.clone() {
+            Exit::Branch(condition, taken, next)
+                if self.nodes[header].statements.is_empty()
+                    && follow.is_some()
+                    && (Some(taken) == follow || Some(next) == follow) =>
+            {
+                self.emitted[header] = true;
+                let (condition, inner) = match Some(taken) == follow {
+                    true => (negate(condition), next),
+                    false => (condition, taken),
+                };
+                self.structure(inner, None, false, &mut body);
+                Stmt::While(self.pop_context(), condition, body)
+            }
+            _ => {
+                let latch_exit = single_latch.map(|latch| (latch, self.nodes[latch].exit.clone()));
+                match latch_exit {
+                    Some((latch, Exit::Branch(condition, taken, next)))
+                        if (taken == header || next == header)
+                            && Some(if taken == header { next } else { taken }) == follow =>
+                    {
+                        if let Some(context) = self.contexts.last_mut() {
+                            context.latch = Some(latch);
+                        }
+                        self.structure(header, None, true, &mut body);
+                        let condition = match taken == header {
+                            true => condition,
+                            false => negate(condition),
+                        };
+                        Stmt::DoWhile(self.pop_context(), body, condition)
+                    }
+                    _ => {
+                        self.structure(header, None, true, &mut body);
+                        Stmt::While(self.pop_context(), Expr::Bool(true), body)
+                    }
+                }
+            }
+        };
+        out.push(statement);
+        follow
+    }
+
+    fn structure_try(
+        &mut self,
+        region: usize,
+        stop: Option<usize>,
+        out: &mut Vec<Stmt>,
+    ) -> Option<usize> {
+        self.opened_tries[region] = true;
+        let start = self.tries[region].start;
+        let handlers = self.tries[region].handlers.clone();
+        // The first block after the region on every path from its start, else from a handler.
+        let after = |structurer: &Structurer, mut node: usize, inside: &dyn Fn(usize) -> bool| loop {
+            match structurer.post_dominators[node] {
+                Some(next) if next != node && next < structurer.nodes.len() => {
+                    node = next;
+                    if !inside(node) {
+                        return Some(node);
+                    }
+                }
+                _ => return None,
+            }
+        };
+        let blocks = self.tries[region].blocks.clone();
+        let follow = after(self, start, &|node| blocks[node]).or_else(|| {
+            handlers
+                .iter()
+                .filter_map(|(handler, _)| {
+                    after(self, *handler, &|node| self.dominates(*handler, node))
+                })
+                .min_by_key(|node| self.nodes[*node].start_pc)
+        });
+        let follow = self.constrain(follow, stop);
+        let mut body = vec![];
+        self.structure(start, follow, true, &mut body);
+        let mut catches = vec![];
+        for (handler, types) in handlers {
+            let mut statements = vec![];
+            self.structure(handler, follow, true, &mut statements);
+            let caught = self.caught.get(&handler).copied().unwrap_or_default();
+            let variable = match statements.first() {
+                Some(Stmt::Expr(Expr::Assign(target, value))) if **value == Expr::Var(caught) => {
+                    match **target {
+                        Expr::Var(variable) => Some(variable),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            if variable.is_some() {
+                statements.remove(0);
+            }
+            catches.push(Catch {
+                types,
+                variable: variable.unwrap_or(caught),
+                body: statements,
+            });
+        }
+        out.push(Stmt::Try(body, catches));
+        follow
+    }
+
+    /// Comments for the blocks that structuring left out, so that no code is silently lost.
+    fn uncovered(&self) -> Vec<Stmt> {
+        (0..self.nodes.len())
+            .filter(|node| {
+                self.nodes[*node].live && !self.emitted[*node] && self.dominators[*node].is_some()
+            })
+            .map(|node| {
+                Stmt::Comment(format!(
+                    "block at {} was not decompiled",
+                    self.nodes[node].start_pc
+                ))
+            })
+            .collect()
+    }
+}
+
+/// The statements of a decompiled method body, with its variables.
+struct MethodBody<'d, 'a> {
+    decompiler: &'d Decompiler<'a>,
+    variables: Vec<Variable>,
+    /// The parameters in order, without `this`.
+    parameters: Vec<usize>,
+    return_type: Option<FieldType>,
+    statements: Vec<Stmt>,
+}
+
+impl<'d, 'a> MethodBody<'d, 'a> {
+    /**
+     * Decompiles the body of a method.\
+     *  The body of a lambda is decompiled with the names of the variables it captures, which
+     *  come first among the parameters of the method implementing it.
+     */
+    fn new(
+        decompiler: &'d Decompiler<'a>,
+        method: &MethodInfo,
+        captured: &[String],
+    ) -> Result<MethodBody<'d, 'a>, Box<dyn Error>> {
+        let pool = &decompiler.class.constant_pool;
+        let code = get_code(method).ok_or("the method has no Code attribute")?;
+        let descriptor =
+            MethodDescriptor::from_descriptor(&decompiler.utf8(method.descriptor_index))?;
+        let cfg = ControlFlowGraph::new(code, pool)?;
+        if cfg.blocks.is_empty() {
+            return Err(invalid(0, "the method has no instructions"));
+        }
+        let mut builder = Builder::new(decompiler, method, code, &cfg, &descriptor, captured);
+        builder.build()?;
+        for _ in 0..4 {
+            if !builder.regroup {
+                break;
+            }
+            builder.build()?;
+        }
+        let mut nodes = std::mem::take(&mut builder.nodes);
+        thread_jumps(&mut nodes);
+        merge_conditions(&mut nodes);
+        let caught = std::mem::take(&mut builder.caught);
+        let mut structurer = Structurer::new(nodes, code, pool, &cfg, caught);
+        let mut statements = vec![];
+        structurer.structure(0, None, true, &mut statements);
+        statements.extend(structurer.uncovered());
+        let mut body = MethodBody {
+            decompiler,
+            variables: builder.variables,
+            parameters: builder.parameters,
+            return_type: descriptor.return_type.clone(),
+            statements,
+        };
+        body.simplify();
+        Ok(body)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    fn get_parameter_names(&self) -> Vec<String> {
+        let names = self.parameters.iter();
+        names
+            .map(|parameter| self.variables[*parameter].name.clone())
+            .collect()
+    }
+
+    /**
+     * Removes the statements of a static initializer that create the constants of its enum,
+     * returning the arguments each constant is created with, after its name and ordinal.
+     */
+    fn take_enum_constants(&mut self) -> HashMap<String, Vec<String>> {
+        let own = &self.decompiler.name;
+        let mut constants = HashMap::new();
+        let mut kept = vec![];
+        for statement in std::mem::take(&mut self.statements) {
+            if let Stmt::Expr(Expr::Assign(target, value)) = &statement {
+                if let Expr::Field {
+                    target: Target::Static(owner),
+                    name,
+                    ..
+                } = target.as_ref()
+                {
+                    match value.as_ref() {
+                        Expr::New {
+                            class, arguments, ..
+                        } if owner == own
+                            && (class == own || class.starts_with(&format!("{own}$"))) =>
+                        {
+                            let arguments = arguments.iter().skip(2).map(|a| self.expr(a, 0));
+                            constants.insert(name.clone(), arguments.collect());
+                            continue;
+                        }
+                        _ if owner == own && name == "$VALUES" => continue,
+                        _ => {}
+                    }
+                }
+            }
+            kept.push(statement);
+        }
+        self.statements = kept;
+        constants
+    }
+
+    /// Removes the call of the superclass constructor that source leaves implicit.
+    fn remove_super_call(&mut self, is_enum: bool) {
+        if let Some(Stmt::Expr(Expr::Call {
+            target: Target::Super,
+            name,
+            arguments,
+            ..
+        })) = self.statements.first()
+        {
+            if name == "<init>" && (arguments.is_empty() || is_enum) {
+                self.statements.remove(0);
+            }
+        }
+    }
+
+    /**
+     * Whether the method is one javac derives for a record and source leaves out: a plain
+     * accessor, `toString`, `hashCode` or `equals` by ObjectMethods, or a canonical
+     * constructor that only assigns the fields.
+     */
+    fn is_generated_record_member(
+        &self,
+        record: &RecordModel,
+        method: &MethodInfo,
+        name: &str,
+    ) -> bool {
+        let descriptor = self.decompiler.utf8(method.descriptor_index);
+        let this_field = |expr: &Expr| match expr {
+            Expr::Field {
+                target: Target::Object(object),
+                name,
+                ..
+            } if matches!(object.as_ref(), Expr::Var(v) if self.variables[*v].name == "this") => {
+                Some(name.clone())
+            }
+            _ => None,
+        };
+        if name == "<init>" {
+            return descriptor == record.get_canonical_descriptor()
+                && self.statements.iter().all(|statement| match statement {
+                    Stmt::Expr(Expr::Assign(target, value)) => match value.as_ref() {
+                        Expr::Var(v) => {
+                            this_field(target).as_ref() == Some(&self.variables[*v].name)
+                        }
+                        _ => false,
+                    },
+                    _ => false,
+                });
+        }
+        match self.statements.as_slice() {
+            [Stmt::Return(Some(Expr::Dynamic { bootstrap, .. }))] => {
+                matches!(name, "toString" | "hashCode" | "equals")
+                    && bootstrap == "java/lang/runtime/ObjectMethods.bootstrap"
+            }
+            [Stmt::Return(Some(value))] => record.components.iter().any(|component| {
+                component.name == name
+                    && descriptor == format!("(){}", component.descriptor)
+                    && this_field(value).as_deref() == Some(name)
+            }),
+            _ => false,
+        }
+    }
+
+    /// Turns the structured statements into what a programmer would have written.
+    fn simplify(&mut self) {
+        let mut statements = std::mem::take(&mut self.statements);
+        let variables = &self.variables;
+        loop {
+            let mut counts = vec![0; variables.len()];
+            count_statements(&statements, &mut counts);
+            let mut changed = false;
+            visit_blocks(&mut statements, &mut |block| {
+                changed |= inline_temporaries(block, &counts, variables);
+            });
+            if !changed {
+                break;
+            }
+        }
+        strip_trailing(&mut statements, &|statement| {
+            *statement == Stmt::Return(None)
+        });
+        visit_blocks(&mut statements, &mut |block| {
+            for statement in block.iter_mut() {
+                if statement.is_loop() {
+                    let label = statement.label().cloned();
+                    let is_continue = |statement: &Stmt| matches!(statement, Stmt::Continue(target) if target.is_none() || *target == label);
+                    if let Some(body) = statement.blocks_mut().pop() {
+                        strip_trailing(body, &is_continue);
+                    }
+                }
+                if let Stmt::If(condition, then, otherwise) = statement {
+                    if then.is_empty() && !otherwise.is_empty() {
+                        *condition = negate(condition.clone());
+                        std::mem::swap(then, otherwise);
+                    }
+                }
+            }
+            make_for_loops(block);
+        });
+        self.infer_booleans(&statements);
+        self.retype_block(&mut statements);
+        visit_blocks(&mut statements, &mut |block| {
+            for statement in block.iter_mut() {
+                for expr in statement.exprs_mut() {
+                    fold_builders(expr, &self.variables);
+                }
+            }
+        });
+        self.declare(&mut statements);
+        self.statements = statements;
+    }
+
+    /**
+     * Gives the boolean type to int variables without a declared type that are only assigned
+     * booleans, or only assigned 0 and 1 and compared with 0.
+     */
+    fn infer_booleans(&mut self, statements: &[Stmt]) {
+        let count = self.variables.len();
+        let mut counts = vec![0; count];
+        count_statements(statements, &mut counts);
+        let mut assigned = vec![0; count];
+        let mut boolean = vec![true; count];
+        let mut computed = vec![false; count];
+        let mut tested = vec![0; count];
+        let variables = &self.variables;
+        fn is_boolean(variables: &[Variable], value: &Expr) -> bool {
+            match value {
+                Expr::Conditional(_, a, b) => is_boolean(variables, a) && is_boolean(variables, b),
+                value => {
+                    value.is_zero_or_one()
+                        || expr_type(variables, value) == Some(FieldType::Boolean)
+                }
+            }
+        }
+        walk_exprs(statements, &mut |expr| match expr {
+            Expr::Assign(target, value) => {
+                if let Expr::Var(v) = target.as_ref() {
+                    assigned[*v] += 1;
+                    boolean[*v] &= is_boolean(variables, value);
+                    computed[*v] |= !value.is_zero_or_one() && is_boolean(variables, value);
+                }
+            }
+            Expr::Binary("==" | "!=", left, right) => {
+                if let (Expr::Var(v), Expr::Int(0)) = (left.as_ref(), right.as_ref()) {
+                    tested[*v] += 1;
+                }
+            }
+            _ => {}
+        });
+        for (v, variable) in self.variables.iter_mut().enumerate() {
+            if variable.typed
+                || variable.field_type != Some(FieldType::Int)
+                || assigned[v] == 0
+                || !boolean[v]
+            {
+                continue;
+            }
+            if computed[v] || tested[v] > 0 && counts[v] == assigned[v] + tested[v] {
+                variable.field_type = Some(FieldType::Boolean);
+            }
+        }
+    }
+
+    fn retype_block(&self, statements: &mut [Stmt]) {
+        let boolean = Some(&FieldType::Boolean);
+        for statement in statements.iter_mut() {
+            match statement {
+                Stmt::If(condition, ..)
+                | Stmt::While(_, condition, _)
+                | Stmt::DoWhile(_, _, condition) => self.retype(condition, boolean),
+                Stmt::For {
+                    init,
+                    condition,
+                    update,
+                    ..
+                } => {
+                    if let Some(init) = init {
+                        self.retype_block(std::slice::from_mut(init.as_mut()));
+                    }
+                    self.retype(condition, boolean);
+                    for update in update {
+                        self.retype(update, None);
+                    }
+                }
+                Stmt::Return(Some(value)) => self.retype(value, self.return_type.as_ref()),
+                _ => {
+                    for expr in statement.exprs_mut() {
+                        self.retype(expr, None);
+                    }
+                }
+            }
+            for block in statement.blocks_mut() {
+                self.retype_block(block);
+            }
+        }
+    }
+
+    /// Writes int constants as booleans or chars where the context expects one.
+    fn retype(&self, expr: &mut Expr, expected: Option<&FieldType>) {
+        let boolean = FieldType::Boolean;
+        let variables = &self.variables;
+        match expr {
+            Expr::Assign(target, value) => {
+                self.retype(target, None);
+                let target_type = expr_type(variables, target);
+                self.retype(value, target_type.as_ref());
+            }
+            Expr::Call {
+                target,
+                parameters,
+                arguments,
+                ..
+            } => {
+                if let Target::Object(object) = target {
+                    self.retype(object, None);
+                }
+                for (argument, parameter) in arguments.iter_mut().zip(parameters.iter()) {
+                    self.retype(argument, Some(parameter));
+                }
+            }
+            Expr::New {
+                parameters,
+                arguments,
+                ..
+            } => {
+                for (argument, parameter) in arguments.iter_mut().zip(parameters.iter()) {
+                    self.retype(argument, Some(parameter));
+                }
+            }
+            Expr::Binary("&&" | "||", left, right) => {
+                self.retype(left, Some(&boolean));
+                self.retype(right, Some(&boolean));
+            }
+            Expr::Not(value) => self.retype(value, Some(&boolean)),
+            Expr::Binary("==" | "!=" | "<" | ">=" | ">" | "<=" | "&" | "|" | "^", left, right) => {
+                let narrow = |expr: &Expr| {
+                    expr_type(variables, expr)
+                        .filter(|t| matches!(t, FieldType::Boolean | FieldType::Char))
+                };
+                let (left_type, right_type) = (narrow(right), narrow(left));
+                self.retype(left, left_type.as_ref());
+                self.retype(right, right_type.as_ref());
+            }
+            Expr::Conditional(condition, a, b) => {
+                self.retype(condition, Some(&boolean));
+                self.retype(a, expected);
+                self.retype(b, expected);
+            }
+            Expr::ArrayInit {
+                array_type: FieldType::Array(component),
+                values,
+            } => {
+                for value in values {
+                    self.retype(value, Some(component));
+                }
+            }
+            _ => {
+                for child in expr.children_mut() {
+                    self.retype(child, None);
+                }
+            }
+        }
+
+        match (expected, &*expr) {
+            (Some(FieldType::Boolean), Expr::Int(value @ (0 | 1))) => {
+                *expr = Expr::Bool(*value == 1)
+            }
+            (Some(FieldType::Char), Expr::Int(value)) if (0..=0xffff).contains(value) => {
+                *expr = Expr::Char(*value as u16)
+            }
+            (_, Expr::Cast(FieldType::Char, value)) => {
+                if let Expr::Int(value @ 0..=0xffff) = value.as_ref() {
+                    *expr = Expr::Char(*value as u16);
+                }
+            }
+            (_, Expr::Binary(operator @ ("==" | "!="), left, right)) => {
+                if let Expr::Bool(value) = right.as_ref() {
+                    if expr_type(variables, left) == Some(FieldType::Boolean) {
+                        let left = left.as_ref().clone();
+                        *expr = match (*operator == "==") == *value {
+                            true => left,
+                            false => negate(left),
+                        };
+                    }
+                }
+            }
+            _ => {}
+        }
+        if expected == Some(&boolean) {
+            if let Expr::Conditional(condition, a, b) = expr {
+                let condition = condition.as_ref().clone();
+                let (a, b) = (a.as_ref().clone(), b.as_ref().clone());
+                *expr = match (a, b) {
+                    (Expr::Bool(true), Expr::Bool(false)) => condition,
+                    (Expr::Bool(false), Expr::Bool(true)) => negate(condition),
+                    (a, Expr::Bool(false)) => binary("&&", condition, a),
+                    (Expr::Bool(true), b) => binary("||", condition, b),
+                    (Expr::Bool(false), b) => binary("&&", negate(condition), b),
+                    (a, Expr::Bool(true)) => binary("||", negate(condition), a),
+                    (a, b) => Expr::Conditional(Box::new(condition), Box::new(a), Box::new(b)),
+                };
+            }
+        }
+    }
+
+    /**
+     * Declares the variables that are not parameters or caught exceptions in the innermost
+     * block enclosing all their uses, at their first assignment where it is a statement there.
+     */
+    fn declare(&self, statements: &mut Vec<Stmt>) {
+        let count = self.variables.len();
+        let mut declared: Vec<bool> = self.variables.iter().map(|v| v.declared).collect();
+        let mut paths = vec![vec![]; count];
+        collect_paths(statements, &mut vec![], &mut paths, &mut declared);
+        let mut insertions = vec![];
+        for (v, paths) in paths.iter().enumerate() {
+            let Some(first) = paths.first() else {
+                continue;
+            };
+            if declared[v] {
+                continue;
+            }
+            let common = paths.iter().fold(first.len(), |common, path| {
+                common.min(first.iter().zip(path).take_while(|(a, b)| a == b).count())
+            });
+            let scope = common / 2 * 2;
+            let index = first[scope];
+            let block = block_at(statements, &first[..scope]);
+            let statement = &mut block[index];
+            match statement {
+                Stmt::Expr(Expr::Assign(target, value))
+                    if **target == Expr::Var(v) && first.len() == scope + 1 && !value.uses(v) =>
+                {
+                    *statement = Stmt::Local(v, Some(value.as_ref().clone()));
+                }
+                Stmt::For {
+                    init: Some(init), ..
+                } if common > scope => match init.as_mut() {
+                    Stmt::Expr(Expr::Assign(target, value))
+                        if **target == Expr::Var(v) && !value.uses(v) =>
+                    {
+                        **init = Stmt::Local(v, Some(value.as_ref().clone()));
+                    }
+                    _ => insertions.push((first[..scope].to_vec(), index, v)),
+                },
+                _ => insertions.push((first[..scope].to_vec(), index, v)),
+            }
+        }
+        // Inserting later positions first leaves the paths of the others valid.
+        insertions.sort_by(|(a, i, _), (b, j, _)| {
+            let a = a.iter().chain([i]);
+            b.iter().chain([j]).cmp(a)
+        });
+        for (path, index, v) in insertions {
+            block_at(statements, &path).insert(index, Stmt::Local(v, None));
+        }
+    }
+
+    fn render(&self, level: usize) -> String {
+        let mut text = String::new();
+        self.render_block(&self.statements, level, &mut text);
+        text
+    }
+
+    /// The body of a lambda: the expression it evaluates, else a block.
+    fn render_lambda(&self) -> String {
+        match self.statements.as_slice() {
+            [Stmt::Return(Some(value))] => self.expr(value, 1),
+            [Stmt::Expr(value)] if self.return_type.is_none() => self.statement_expr(value),
+            _ => format!("{{\n{}}}", self.render(1)),
+        }
+    }
+
+    fn render_block(&self, statements: &[Stmt], level: usize, out: &mut String) {
+        for statement in statements {
+            self.render_statement(statement, level, out);
+        }
+    }
+
+    fn render_statement(&self, statement: &Stmt, level: usize, out: &mut String) {
+        let mut line = |text: String| {
+            for line in text.split('\n') {
+                out.push_str(&INDENT.repeat(level));
+                out.push_str(line);
+                out.push('\n');
+            }
+        };
+        let label = |label: &Option<String>| match label {
+            Some(label) => format!("{label}: "),
+            None => String::new(),
+        };
+        match statement {
+            Stmt::Expr(value) => line(format!("{};", self.statement_expr(value))),
+            Stmt::Local(v, value) => line(format!("{};", self.local(*v, value.as_ref()))),
+            Stmt::Return(None) => line("return;".into()),
+            Stmt::Return(Some(value)) => line(format!("return {};", self.expr(value, 0))),
+            Stmt::Throw(value) => line(format!("throw {};", self.expr(value, 0))),
+            Stmt::Monitor(enter, value) => {
+                let instruction = if *enter {
+                    "monitorenter"
+                } else {
+                    "monitorexit"
+                };
+                line(format!("// {instruction} {}", self.expr(value, 0)))
+            }
+            Stmt::If(condition, then, otherwise) => {
+                self.render_if(condition, then, otherwise, level, out, "")
+            }
+            Stmt::While(name, condition, body) => {
+                line(format!(
+                    "{}while ({}) {{",
+                    label(name),
+                    self.expr(condition, 0)
+                ));
+                self.render_block(body, level + 1, out);
+                out.push_str(&format!("{}}}\n", INDENT.repeat(level)));
+            }
+            Stmt::DoWhile(name, body, condition) => {
+                line(format!("{}do {{", label(name)));
+                self.render_block(body, level + 1, out);
+                let condition = self.expr(condition, 0);
+                out.push_str(&format!(
+                    "{}}} while ({condition});\n",
+                    INDENT.repeat(level)
+                ));
+            }
+            Stmt::For {
+                label: name,
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                let init = match init.as_deref() {
+                    Some(Stmt::Local(v, value)) => self.local(*v, value.as_ref()),
+                    Some(Stmt::Expr(value)) => self.statement_expr(value),
+                    _ => String::new(),
+                };
+                let update: Vec<String> = update
+                    .iter()
+                    .map(|value| self.statement_expr(value))
+                    .collect();
+                let condition = self.expr(condition, 0);
+                line(format!(
+                    "{}for ({init}; {condition}; {}) {{",
+                    label(name),
+                    update.join(", ")
+                ));
+                self.render_block(body, level + 1, out);
+                out.push_str(&format!("{}}}\n", INDENT.repeat(level)));
+            }
+            Stmt::Switch(name, value, cases) => {
+                let is_char = expr_type(&self.variables, value) == Some(FieldType::Char);
+                line(format!(
+                    "{}switch ({}) {{",
+                    label(name),
+                    self.expr(value, 0)
+                ));
+                let indent = INDENT.repeat(level + 1);
+                for (keys, body) in cases {
+                    for key in keys {
+                        match key {
+                            Some(key) if is_char && (0x20..0x7f).contains(key) => out.push_str(
+                                &format!("{indent}case {}:\n", char_literal(*key as u16)),
+                            ),
+                            Some(key) => out.push_str(&format!("{indent}case {key}:\n")),
+                            None => out.push_str(&format!("{indent}default:\n")),
+                        }
+                    }
+                    self.render_block(body, level + 2, out);
+                }
+                out.push_str(&format!("{}}}\n", INDENT.repeat(level)));
+            }
+            Stmt::Try(body, catches) => {
+                line("try {".into());
+                self.render_block(body, level + 1, out);
+                for catch in catches {
+                    let mut types: Vec<String> = catch
+                        .types
+                        .iter()
+                        .map(|class| match class {
+                            Some(class) => self.decompiler.type_name(class),
+                            None => "Throwable".into(),
+                        })
+                        .collect();
+                    types.dedup();
+                    let name = &self.variables[catch.variable].name;
+                    let indent = INDENT.repeat(level);
+                    out.push_str(&format!(
+                        "{indent}}} catch ({} {name}) {{\n",
+                        types.join(" | ")
+                    ));
+                    self.render_block(&catch.body, level + 1, out);
+                }
+                out.push_str(&format!("{}}}\n", INDENT.repeat(level)));
+            }
+            Stmt::Break(None) => line("break;".into()),
+            Stmt::Break(Some(label)) => line(format!("break {label};")),
+            Stmt::Continue(None) => line("continue;".into()),
+            Stmt::Continue(Some(label)) => line(format!("continue {label};")),
+            Stmt::Comment(text) => line(format!("// {text}")),
+        }
+    }
+
+    /// An if statement, continuing a chain of `else if` after `prefix`.
+    fn render_if(
+        &self,
+        condition: &Expr,
+        then: &[Stmt],
+        otherwise: &[Stmt],
+        level: usize,
+        out: &mut String,
+        prefix: &str,
+    ) {
+        let indent = INDENT.repeat(level);
+        out.push_str(&format!(
+            "{indent}{prefix}if ({}) {{\n",
+            self.expr(condition, 0)
+        ));
+        self.render_block(then, level + 1, out);
+        match otherwise {
+            [] => out.push_str(&format!("{indent}}}\n")),
+            [Stmt::If(condition, then, otherwise)] => {
+                self.render_if(condition, then, otherwise, level, out, "} else ")
+            }
+            otherwise => {
+                out.push_str(&format!("{indent}}} else {{\n"));
+                self.render_block(otherwise, level + 1, out);
+                out.push_str(&format!("{indent}}}\n"));
+            }
+        }
+    }
+
+    /// The declaration of a local variable, without the semicolon.
+    fn local(&self, v: usize, value: Option<&Expr>) -> String {
+        let variable = &self.variables[v];
+        let java_type = match (&variable.java_type, &variable.field_type) {
+            (Some(java_type), _) => java_type.clone(),
+            (None, Some(field_type)) => self.decompiler.field_type(field_type),
+            (None, None) => "Object".into(),
+        };
+        match value {
+            Some(value) => format!("{java_type} {} = {}", variable.name, self.value(v, value)),
+            None => format!("{java_type} {}", variable.name),
+        }
+    }
+
+    /**
+     * The value assigned to a local variable, with a cast to the erasure of its generic type
+     * written as a cast to the generic type, as in `T item = (T) iterator.next()`.
+     */
+    fn value(&self, v: usize, value: &Expr) -> String {
+        let variable = &self.variables[v];
+        match (&variable.java_type, value) {
+            (Some(java_type), Expr::Cast(field_type, value))
+                if variable.field_type.as_ref() == Some(field_type) =>
+            {
+                format!("({java_type}) {}", self.expr(value, 14))
+            }
+            _ => self.expr(value, 0),
+        }
+    }
+
+    /// An expression evaluated for its effect, with `x += 1` written as `x++`.
+    fn statement_expr(&self, expr: &Expr) -> String {
+        if let Expr::Assign(target, value) = expr {
+            if let Expr::Binary(operator @ ("+" | "-"), left, right) = value.as_ref() {
+                let is_one = match expr_type(&self.variables, target) {
+                    Some(FieldType::Int | FieldType::Short | FieldType::Byte | FieldType::Char) => {
+                        **right == Expr::Int(1)
+                    }
+                    Some(FieldType::Long) => **right == Expr::Long(1),
+                    _ => false,
+                };
+                if left == target && is_one {
+                    return format!("{}{operator}{operator}", self.expr(target, 15));
+                }
+            }
+        }
+        self.expr(expr, 0)
+    }
+
+    /// An expression, in parentheses if it binds less tightly than `precedence`.
+    fn expr(&self, expr: &Expr, precedence: u8) -> String {
+        let text = self.expr_text(expr);
+        if expr_precedence(expr) < precedence {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+
+    fn expr_text(&self, expr: &Expr) -> String {
+        let decompiler = self.decompiler;
+        let arguments = |arguments: &[Expr]| {
+            let arguments: Vec<String> = arguments
+                .iter()
+                .map(|argument| self.expr(argument, 0))
+                .collect();
+            arguments.join(", ")
+        };
+        match expr {
+            Expr::Null => "null".into(),
+            Expr::Int(value) => value.to_string(),
+            Expr::Long(value) => format!("{value}L"),
+            Expr::Float(value) => float_literal(*value),
+            Expr::Double(value) => double_literal(*value),
+            Expr::Bool(value) => value.to_string(),
+            Expr::Char(value) => char_literal(*value),
+            Expr::Str(text) => string_literal(text),
+            Expr::Class(class) => format!("{}.class", decompiler.field_type(class)),
+            Expr::Text(text) => text.clone(),
+            Expr::Var(v) => self.variables[*v].name.clone(),
+            Expr::Field { target, name, .. } => match target {
+                Target::Static(owner) if *owner == decompiler.name => name.clone(),
+                target => format!("{}.{name}", self.target(target)),
+            },
+            Expr::Element { array, index, .. } => {
+                format!("{}[{}]", self.expr(array, 16), self.expr(index, 0))
+            }
+            Expr::Length(array) => format!("{}.length", self.expr(array, 16)),
+            Expr::Binary(operator, left, right) => {
+                let precedence = binary_precedence(operator);
+                let left = self.expr(left, precedence);
+                format!("{left} {operator} {}", self.expr(right, precedence + 1))
+            }
+            Expr::Not(value) => format!("!{}", self.expr(value, 14)),
+            Expr::Negate(value) => match self.expr(value, 14) {
+                value if value.starts_with('-') => format!("-({value})"),
+                value => format!("-{value}"),
+            },
+            Expr::Cast(field_type, value) => {
+                format!(
+                    "({}) {}",
+                    decompiler.field_type(field_type),
+                    self.expr(value, 14)
+                )
+            }
+            Expr::InstanceOf(value, class) => {
+                format!(
+                    "{} instanceof {}",
+                    self.expr(value, 9),
+                    decompiler.field_type(class)
+                )
+            }
+            Expr::Call {
+                target,
+                name,
+                arguments: values,
+                ..
+            } => match (target, name.as_str()) {
+                (Target::Super, "<init>") => format!("super({})", arguments(values)),
+                (Target::This, "<init>") => format!("this({})", arguments(values)),
+                (Target::Static(owner), _) if *owner == decompiler.name => {
+                    format!("{name}({})", arguments(values))
+                }
+                (target, _) => format!("{}.{name}({})", self.target(target), arguments(values)),
+            },
+            Expr::New {
+                class,
+                arguments: values,
+                ..
+            } => format!("new {}({})", decompiler.type_name(class), arguments(values)),
+            Expr::NewArray {
+                array_type,
+                dimensions,
+            } => {
+                let mut element = array_type;
+                let mut depth = 0;
+                while let FieldType::Array(component) = element {
+                    element = component;
+                    depth += 1;
+                }
+                let mut text = format!("new {}", decompiler.field_type(element));
+                for dimension in dimensions {
+                    text.push_str(&format!("[{}]", self.expr(dimension, 0)));
+                }
+                text.push_str(&"[]".repeat(depth.max(dimensions.len()) - dimensions.len()));
+                text
+            }
+            Expr::ArrayInit { array_type, values } => {
+                format!(
+                    "new {}{{{}}}",
+                    decompiler.field_type(array_type),
+                    arguments(values)
+                )
+            }
+            Expr::Assign(target, value) => {
+                let target_text = self.expr(target, 15);
+                if let Expr::Binary(operator, left, right) = value.as_ref() {
+                    let compound = matches!(
+                        *operator,
+                        "+" | "-" | "*" | "/" | "%" | "<<" | ">>" | ">>>" | "&" | "|" | "^"
+                    );
+                    if compound && left == target {
+                        return format!("{target_text} {operator}= {}", self.expr(right, 1));
+                    }
+                }
+                match target.as_ref() {
+                    Expr::Var(v) => format!("{target_text} = {}", self.value(*v, value)),
+                    _ => format!("{target_text} = {}", self.expr(value, 0)),
+                }
+            }
+            Expr::Increment(value, delta) => {
+                let operator = if *delta < 0 { "--" } else { "++" };
+                format!("{}{operator}", self.expr(value, 15))
+            }
+            Expr::Conditional(condition, a, b) => format!(
+                "{} ? {} : {}",
+                self.expr(condition, 3),
+                self.expr(a, 3),
+                self.expr(b, 2)
+            ),
+            Expr::Compare(left, right) => {
+                let class = match expr_type(&self.variables, left) {
+                    Some(FieldType::Long) => "Long",
+                    Some(FieldType::Float) => "Float",
+                    Some(FieldType::Double) => "Double",
+                    _ => "Integer",
+                };
+                format!(
+                    "{class}.compare({}, {})",
+                    self.expr(left, 0),
+                    self.expr(right, 0)
+                )
+            }
+            Expr::Lambda { parameters, body } => match parameters.as_slice() {
+                [parameter] => format!("{parameter} -> {body}"),
+                parameters => format!("({}) -> {body}", parameters.join(", ")),
+            },
+            Expr::MethodRef(target, name) => format!("{}::{name}", self.target(target)),
+            Expr::Dynamic {
+                bootstrap,
+                name,
+                arguments: values,
+                ..
+            } => {
+                let bootstrap = match bootstrap.rsplit_once('.') {
+                    Some((owner, method)) => format!("{}.{method}", decompiler.type_name(owner)),
+                    None => bootstrap.clone(),
+                };
+                format!("/* {bootstrap} */ {name}({})", arguments(values))
+            }
+            Expr::Uninitialized(_, class) => {
+                format!("/* new {} */ null", decompiler.type_name(class))
+            }
+            Expr::Dup(_, value) => self.expr_text(value),
+        }
+    }
+
+    /// What a member is accessed on, without the dot.
+    fn target(&self, target: &Target) -> String {
+        match target {
+            Target::Static(owner) => self.decompiler.type_name(owner),
+            Target::Object(object) => self.expr(object, 16),
+            Target::Super => "super".into(),
+            Target::This => "this".into(),
+        }
+    }
+}
+
+fn binary_precedence(operator: &str) -> u8 {
+    match operator {
+        "*" | "/" | "%" => 12,
+        "+" | "-" => 11,
+        "<<" | ">>" | ">>>" => 10,
+        "<" | ">" | "<=" | ">=" => 9,
+        "==" | "!=" => 8,
+        "&" => 7,
+        "^" => 6,
+        "|" => 5,
+        "&&" => 4,
+        _ => 3,
+    }
+}
+
+/// How tightly an expression binds, from 16 for primaries down to 0 for lambdas.
+fn expr_precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Binary(operator, ..) => binary_precedence(operator),
+        Expr::Int(value) if *value < 0 => 14,
+        Expr::Long(value) if *value < 0 => 14,
+        Expr::Float(value) if value.is_sign_negative() && value.is_finite() => 14,
+        Expr::Double(value) if value.is_sign_negative() && value.is_finite() => 14,
+        Expr::Not(_) | Expr::Negate(_) | Expr::Cast(..) => 14,
+        Expr::Increment(..) | Expr::NewArray { .. } | Expr::ArrayInit { .. } => 15,
+        Expr::InstanceOf(..) => 9,
+        Expr::Conditional(..) => 2,
+        Expr::Assign(..) => 1,
+        Expr::Lambda { .. } => 0,
+        Expr::Dup(_, value) => expr_precedence(value),
+        _ => 16,
+    }
+}
+
+/// Indents every line of `text` that is not empty by one level.
+fn indent(text: &str) -> String {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| match line.is_empty() {
+            true => String::new(),
+            false => format!("{INDENT}{line}"),
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// The parts of a string concatenation joined with `+`, starting with `""` if needed for a string.
+fn concatenation(variables: &[Variable], mut parts: Vec<Expr>) -> Expr {
+    let string = Some(FieldType::Object("java/lang/String".into()));
+    let is_string =
+        |part: Option<&Expr>| part.is_some_and(|part| expr_type(variables, part) == string);
+    if !is_string(parts.first()) && !is_string(parts.get(1)) {
+        parts.insert(0, Expr::Str(String::new()));
+    }
+    let mut parts = parts.into_iter();
+    let first = parts.next().unwrap_or(Expr::Str(String::new()));
+    parts.fold(first, |left, right| binary("+", left, right))
+}
+
+/**
+ * Replaces `new StringBuilder().append(a).append(b).toString()`, the way javac compiled string
+ * concatenation before Java 9 and still does in some cases, with `a + b`.
+ */
+fn fold_builders(expr: &mut Expr, variables: &[Variable]) {
+    for child in expr.children_mut() {
+        fold_builders(child, variables);
+    }
+    let Expr::Call {
+        target: Target::Object(receiver),
+        name,
+        arguments,
+        ..
+    } = expr
+    else {
+        return;
+    };
+    if name != "toString" || !arguments.is_empty() {
+        return;
+    }
+    let string = Some(FieldType::Object("java/lang/String".into()));
+    let mut parts = vec![];
+    let mut current = receiver.as_ref();
+    loop {
+        match current {
+            Expr::Call {
+                target: Target::Object(receiver),
+                name,
+                arguments,
+                return_type: Some(FieldType::Object(class)),
+                ..
+            } if name == "append" && arguments.len() == 1 && class == "java/lang/StringBuilder" => {
+                parts.push(arguments[0].clone());
+                current = receiver;
+            }
+            Expr::New {
+                class, arguments, ..
+            } if class == "java/lang/StringBuilder" => {
+                match arguments.as_slice() {
+                    [] => {}
+                    [first] if expr_type(variables, first) == string => parts.push(first.clone()),
+                    _ => return,
+                }
+                break;
+            }
+            _ => return,
+        }
+    }
+    if parts.is_empty() {
+        return;
+    }
+    parts.reverse();
+    *expr = concatenation(variables, parts);
+}
+
+/// Applies `f` to every statement list of a body, the innermost first.
+fn visit_blocks(statements: &mut Vec<Stmt>, f: &mut dyn FnMut(&mut Vec<Stmt>)) {
+    for statement in statements.iter_mut() {
+        for block in statement.blocks_mut() {
+            visit_blocks(block, f);
+        }
+    }
+    f(statements);
+}
+
+/// Calls `f` with every expression of a body and all their subexpressions.
+fn walk_exprs(statements: &[Stmt], f: &mut dyn FnMut(&Expr)) {
+    for statement in statements {
+        for expr in statement.exprs() {
+            walk_expr(expr, f);
+        }
+        for block in statement.blocks() {
+            walk_exprs(block, f);
+        }
+    }
+}
+
+fn walk_expr(expr: &Expr, f: &mut dyn FnMut(&Expr)) {
+    f(expr);
+    for child in expr.children() {
+        walk_expr(child, f);
+    }
+}
+
+fn count_statements(statements: &[Stmt], counts: &mut [usize]) {
+    for statement in statements {
+        for expr in statement.exprs() {
+            expr.count_uses(counts);
+        }
+        for block in statement.blocks() {
+            count_statements(block, counts);
+        }
+    }
+}
+
+/**
+ * Folds the stack variables of a block into where they are used: `t = a` and `t = b` in the
+ * branches of an if become `t = c ? a : b`, and a variable assigned and then used once in the
+ * next statement is replaced by its value, where that keeps the order of evaluation.
+ */
+fn inline_temporaries(block: &mut Vec<Stmt>, counts: &[usize], variables: &[Variable]) -> bool {
+    let temporary = |expr: &Expr| match expr {
+        Expr::Var(v) if variables[*v].temporary => Some(*v),
+        _ => None,
+    };
+    let mut changed = false;
+    let mut index = 0;
+    while index < block.len() {
+        if let Stmt::If(condition, then, otherwise) = &block[index] {
+            if let ([Stmt::Expr(Expr::Assign(a, x))], [Stmt::Expr(Expr::Assign(b, y))]) =
+                (then.as_slice(), otherwise.as_slice())
+            {
+                if a == b && temporary(a).is_some() {
+                    let condition = Box::new(condition.clone());
+                    let value = Expr::Conditional(condition, x.clone(), y.clone());
+                    block[index] = assign(a.as_ref().clone(), value);
+                    changed = true;
+                }
+            }
+        }
+        if let (Stmt::Expr(Expr::Assign(target, value)), Some(next)) =
+            (&block[index], block.get(index + 1))
+        {
+            let inlinable = matches!(
+                next,
+                Stmt::Expr(_)
+                    | Stmt::Return(_)
+                    | Stmt::Throw(_)
+                    | Stmt::If(..)
+                    | Stmt::Switch(..)
+                    | Stmt::Monitor(..)
+            );
+            if let Some(v) =
+                temporary(target).filter(|v| counts[*v] == 2 && inlinable && !value.uses(*v))
+            {
+                let pure = !value.has_effects() && !value.reads(None, true);
+                let value = value.as_ref().clone();
+                let mut inlined = false;
+                for expr in block[index + 1].exprs_mut() {
+                    match inline_position(expr, v) {
+                        Some(safe) if safe || pure => {
+                            expr.substitute(v, &value);
+                            inlined = true;
+                            break;
+                        }
+                        Some(_) => break,
+                        None => {}
+                    }
+                }
+                if inlined {
+                    block.remove(index);
+                    changed = true;
+                    continue;
+                }
+            }
+        }
+        index += 1;
+    }
+    changed
+}
+
+/**
+ * Whether a use of `variable` in `expr` is evaluated before anything that has effects or
+ * reads memory, and is always evaluated; `None` if the expression does not use it.
+ */
+fn inline_position(expr: &Expr, variable: usize) -> Option<bool> {
+    if *expr == Expr::Var(variable) {
+        return Some(true);
+    }
+    let is_conditional = matches!(expr, Expr::Conditional(..) | Expr::Binary("&&" | "||", ..));
+    // Storing evaluates what the target is on, not the target itself.
+    let children = match expr {
+        Expr::Assign(target, value) => target
+            .children()
+            .into_iter()
+            .chain([value.as_ref()])
+            .collect(),
+        expr => expr.children(),
+    };
+    let mut clean = true;
+    for (index, child) in children.into_iter().enumerate() {
+        if is_conditional && index > 0 {
+            clean = false;
+        }
+        match inline_position(child, variable) {
+            Some(safe) => return Some(safe && clean),
+            None if child.has_effects() || child.reads(None, true) => clean = false,
+            None => {}
+        }
+    }
+    None
+}
+
+/// Removes statements the flow would skip anyway from the end of a block and its last branches.
+fn strip_trailing(block: &mut Vec<Stmt>, redundant: &dyn Fn(&Stmt) -> bool) {
+    match block.last_mut() {
+        Some(last) if redundant(last) => {
+            block.pop();
+        }
+        Some(Stmt::If(_, then, otherwise)) => {
+            strip_trailing(then, redundant);
+            strip_trailing(otherwise, redundant);
+        }
+        Some(Stmt::Try(body, catches)) => {
+            strip_trailing(body, redundant);
+            for catch in catches {
+                strip_trailing(&mut catch.body, redundant);
+            }
+        }
+        _ => {}
+    }
+}
+
+/**
+ * Turns `i = a; while (c) { ...; i++; }` into a for loop where the condition uses the
+ * variable and the body has no `continue` that would skip the update.
+ */
+fn make_for_loops(block: &mut Vec<Stmt>) {
+    let mut index = 1;
+    while index < block.len() {
+        let (before, after) = block.split_at_mut(index);
+        let (Stmt::Expr(init @ Expr::Assign(..)), Stmt::While(label, condition, body)) =
+            (&before[index - 1], &mut after[0])
+        else {
+            index += 1;
+            continue;
+        };
+        let Expr::Assign(variable, _) = init else {
+            unreachable!()
+        };
+        let updates = matches!(
+            body.last(),
+            Some(Stmt::Expr(Expr::Assign(target, _) | Expr::Increment(target, _))) if target == variable
+        );
+        let Expr::Var(v) = variable.as_ref() else {
+            index += 1;
+            continue;
+        };
+        if !updates || !condition.uses(*v) || has_continue(body, label.as_ref(), true) {
+            index += 1;
+            continue;
+        }
+        let Some(Stmt::Expr(update)) = body.pop() else {
+            unreachable!()
+        };
+        let statement = Stmt::For {
+            label: label.clone(),
+            init: Some(Box::new(before[index - 1].clone())),
+            condition: condition.clone(),
+            update: vec![update],
+            body: std::mem::take(body),
+        };
+        block[index] = statement;
+        block.remove(index - 1);
+    }
+}
+
+/// Whether a loop body has a `continue` of the loop, at `top` if it is directly in the body.
+fn has_continue(statements: &[Stmt], label: Option<&String>, top: bool) -> bool {
+    statements.iter().any(|statement| match statement {
+        Stmt::Continue(None) => top,
+        Stmt::Continue(Some(target)) => Some(target) == label,
+        statement => {
+            let top = top && !statement.is_loop();
+            statement
+                .blocks()
+                .into_iter()
+                .any(|block| has_continue(block, label, top))
+        }
+    })
+}
+
+/// The path of every use of each variable, as statement and block indices, in order.
+fn collect_paths(
+    statements: &[Stmt],
+    prefix: &mut Vec<usize>,
+    paths: &mut [Vec<Vec<usize>>],
+    declared: &mut [bool],
+) {
+    for (index, statement) in statements.iter().enumerate() {
+        prefix.push(index);
+        if let Stmt::Try(_, catches) = statement {
+            for catch in catches {
+                declared[catch.variable] = true;
+            }
+        }
+        let mut used = vec![];
+        for expr in statement.exprs() {
+            walk_expr(expr, &mut |expr| {
+                if let Expr::Var(v) = expr {
+                    used.push(*v);
+                }
+            });
+        }
+        for v in used {
+            if paths[v].last() != Some(prefix) {
+                paths[v].push(prefix.clone());
+            }
+        }
+        for (block_index, block) in statement.blocks().into_iter().enumerate() {
+            prefix.push(block_index);
+            collect_paths(block, prefix, paths, declared);
+            prefix.pop();
+        }
+        prefix.pop();
+    }
+}
+
+/// The statement list at a path of statement and block indices.
+fn block_at<'s>(statements: &'s mut Vec<Stmt>, path: &[usize]) -> &'s mut Vec<Stmt> {
+    match path {
+        [statement, block, rest @ ..] => {
+            let inner = statements[*statement].blocks_mut().into_iter().nth(*block);
+            block_at(inner.expect("the path leads to a block"), rest)
+        }
+        _ => statements,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_builder::tests::single_method;
+    use crate::class_builder::{CodeBuilder, Insn};
+
+    /// Builds a class with one static method and decompiles it.
+    fn decompile(descriptor: &str, code: CodeBuilder) -> String {
+        let class = single_method("test/Demo", descriptor, code);
+        let class = ClassFile::from_bytes(&class.into_bytes().unwrap()).unwrap();
+        Decompiler::new(&class).decompile().unwrap()
+    }
+
+    #[test]
+    fn test_decompile_for_loops() {
+        // static int run(int[] a) { int sum = 0; for (int i = 0; i < a.length; i++)
+        // if (a[i] > 0) sum += a[i]; return sum; }
+        let mut code = CodeBuilder::new(3, 3);
+        let labels: Vec<_> = (0..3).map(|_| code.new_label()).collect();
+        let (test, next, exit) = (labels[0], labels[1], labels[2]);
+        code.push(Insn::Simple(ICONST_0));
+        code.push(Insn::Local(ISTORE, 1));
+        code.push(Insn::Simple(ICONST_0));
+        code.push(Insn::Local(ISTORE, 2));
+        code.push(Insn::Label(test));
+        code.push(Insn::Local(ILOAD, 2));
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Simple(ARRAYLENGTH));
+        code.push(Insn::Jump(IF_ICMPGE, exit));
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Local(ILOAD, 2));
+        code.push(Insn::Simple(IALOAD));
+        code.push(Insn::Jump(IFLE, next));
+        code.push(Insn::Local(ILOAD, 1));
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Local(ILOAD, 2));
+        code.push(Insn::Simple(IALOAD));
+        code.push(Insn::Simple(IADD));
+        code.push(Insn::Local(ISTORE, 1));
+        code.push(Insn::Label(next));
+        code.push(Insn::Increment { index: 2, value: 1 });
+        code.push(Insn::Jump(GOTO, test));
+        code.push(Insn::Label(exit));
+        code.push(Insn::Local(ILOAD, 1));
+        code.push(Insn::Simple(IRETURN));
+
+        assert_eq!(
+            decompile("([I)I", code),
+            "\
+package test;
+
+public class Demo {
+    static int run(int[] arg0) {
+        int i = 0;
+        for (int i2 = 0; i2 < arg0.length; i2++) {
+            if (arg0[i2] > 0) {
+                i += arg0[i2];
+            }
+        }
+        return i;
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_fold_string_builders_and_conditionals() {
+        // static String run(boolean b, String s) { return new StringBuilder().append(s)
+        // .append(b ? 'y' : 'n').toString(); }
+        let mut code = CodeBuilder::new(3, 2);
+        let (other, join) = (code.new_label(), code.new_label());
+        let builder = "java/lang/StringBuilder";
+        code.push(Insn::Type(NEW, builder.into()));
+        code.push(Insn::Simple(DUP));
+        code.push(Insn::Method {
+            opcode: INVOKESPECIAL,
+            owner: builder.into(),
+            name: "<init>".into(),
+            descriptor: "()V".into(),
+            interface: false,
+        });
+        code.push(Insn::Local(ALOAD, 1));
+        code.push(Insn::Method {
+            opcode: INVOKEVIRTUAL,
+            owner: builder.into(),
+            name: "append".into(),
+            descriptor: "(Ljava/lang/String;)Ljava/lang/StringBuilder;".into(),
+            interface: false,
+        });
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::Jump(IFEQ, other));
+        code.push(Insn::Push(BIPUSH, 'y' as i32));
+        code.push(Insn::Jump(GOTO, join));
+        code.push(Insn::Label(other));
+        code.push(Insn::Push(BIPUSH, 'n' as i32));
+        code.push(Insn::Label(join));
+        code.push(Insn::Method {
+            opcode: INVOKEVIRTUAL,
+            owner: builder.into(),
+            name: "append".into(),
+            descriptor: "(C)Ljava/lang/StringBuilder;".into(),
+            interface: false,
+        });
+        code.push(Insn::Method {
+            opcode: INVOKEVIRTUAL,
+            owner: builder.into(),
+            name: "toString".into(),
+            descriptor: "()Ljava/lang/String;".into(),
+            interface: false,
+        });
+        code.push(Insn::Simple(ARETURN));
+
+        let source = decompile("(ZLjava/lang/String;)Ljava/lang/String;", code);
+        assert!(
+            source.contains("        return arg1 + (arg0 ? 'y' : 'n');\n"),
+            "{source}"
+        );
+    }
+
+    #[test]
+    fn test_fall_back_to_the_disassembly() {
+        // A subroutine, which javac has not emitted since Java 6.
+        let mut code = CodeBuilder::new(1, 2);
+        let subroutine = code.new_label();
+        code.push(Insn::Jump(JSR, subroutine));
+        code.push(Insn::Simple(RETURN));
+        code.push(Insn::Label(subroutine));
+        code.push(Insn::Local(ASTORE, 1));
+        code.push(Insn::Local(RET, 1));
+
+        let source = decompile("()V", code);
+        assert!(source.contains("    static void run() {\n"), "{source}");
+        assert!(
+            source.contains("        // Could not decompile: "),
+            "{source}"
+        );
+        assert!(source.contains("        //    0: jsr"), "{source}");
+    }
+
+    /// Decompiles the javac-compiled class in the verified output, built from `Demo.java`.
+    fn decompile_javac_demo() -> String {
+        let bytes = std::fs::read("test_verified_output/decompile_java_test/Demo.class").unwrap();
+        let class = ClassFile::from_bytes(&bytes).unwrap();
+        Decompiler::new(&class).decompile().unwrap()
+    }
+
+    #[test]
+    fn test_decompile_javac_switch() {
+        let source = decompile_javac_demo();
+        let classify = "    static int classify(int value) {
+        int result;
+        switch (value) {
+            case 0:
+                result = 10;
+                break;
+            case 1:
+            case 2:
+                result = 20;
+                break;
+            case 7:
+                return 7;
+            default:
+                result = -1;
+        }
+        return result + 1;
+    }
+";
+        assert!(source.contains(classify), "{source}");
+        let name = "    static String name(String key) {
+        String string = key;
+        int i = -1;
+        switch (string.hashCode()) {
+            case 97:
+                if (string.equals(\"a\")) {
+                    i = 0;
+                }
+                break;
+            case 98:
+                if (string.equals(\"b\")) {
+                    i = 1;
+                }
+        }
+        switch (i) {
+            case 0:
+                return \"alpha\";
+            case 1:
+                return \"beta\";
+            default:
+                return \"other\";
+        }
+    }
+";
+        assert!(source.contains(name), "{source}");
+    }
+
+    #[test]
+    fn test_decompile_javac_try_catch() {
+        let source = decompile_javac_demo();
+        let parse = "    static int parse(String text) {
+        int value;
+        try {
+            value = Integer.parseInt(text);
+        } catch (NumberFormatException e) {
+            value = -1;
+        }
+        System.out.println(value);
+        return value;
+    }
+";
+        assert!(source.contains(parse), "{source}");
+        let main = "    public static void main(String[] args) {
+        String[] strings = args;
+        int i = strings.length;
+        int i2 = 0;
+        while (i2 < i) {
+            String arg = strings[i2];
+            try {
+                try {
+                    System.out.println(classify(Integer.parseInt(arg)));
+                } catch (NumberFormatException e) {
+                    System.out.println(name(arg));
+                    System.out.println(\"done\");
+                    i2++;
+                    continue;
+                }
+            } catch (Throwable throwable) {
+                System.out.println(\"done\");
+                throw throwable;
+            }
+            System.out.println(\"done\");
+            i2++;
+        }
+        run();
+    }
+";
+        assert!(source.contains(main), "{source}");
+    }
+
+    #[test]
+    fn test_decompile_javac_lambdas() {
+        let source = decompile_javac_demo();
+        let run = "    static void run() {
+        Runnable r = () -> System.out.println(\"hi\");
+        r.run();
+        java.util.function.Function<String, Integer> length = String::length;
+        System.out.println(length.apply(\"abc\"));
+    }
+";
+        assert!(source.contains(run), "{source}");
+        let max = "    static <T extends Comparable<T>> T max(java.util.List<T> items) {
+        T best = null;
+        java.util.Iterator iterator = items.iterator();
+        while (iterator.hasNext()) {
+            T item = (T) iterator.next();
+            if (best == null || item.compareTo(best) > 0) {
+                best = item;
+            }
+        }
+        return best;
+    }
+";
+        assert!(source.contains(max), "{source}");
+        let constant = "    static <T> java.util.function.Supplier<T> constant(T value) {
+        java.util.function.Supplier<T> supplier = () -> value;
+        return supplier;
+    }
+";
+        assert!(source.contains(constant), "{source}");
+    }
+}
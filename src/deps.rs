@@ -0,0 +1,753 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Write};
+
+use crate::attributes::{Annotation, Element};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::class_path::{ClassPath, ClassPathEntry};
+use crate::constants::{get_raw_class_name, get_raw_utf8};
+use crate::descriptors::{FieldType, MethodDescriptor};
+use crate::graphs::{Edge, Graph, Node};
+use crate::signatures::{
+    ClassSignature, ClassTypeSignature, MethodSignature, TypeArgument, TypeParameter, TypeSignature,
+};
+
+/// The module named for a JDK class when no jimage is on the class path to tell its module.
+const UNKNOWN_MODULE: &str = "JDK";
+
+/// The packages, by internal name, whose classes come with the JDK.
+const JDK_PACKAGES: [&str; 5] = ["java/", "javax/", "jdk/", "sun/", "com/sun/"];
+
+/// How finely dependencies are grouped: between classes, their packages, or their modules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DependencyLevel {
+    Class,
+    Package,
+    /// Between the classes analysed, as one module, and the modules, jars and directories
+    /// the classes they use come from.
+    Module,
+}
+
+impl DependencyLevel {
+    pub fn parse(level: &str) -> Option<DependencyLevel> {
+        match level {
+            "class" => Some(DependencyLevel::Class),
+            "package" => Some(DependencyLevel::Package),
+            "module" => Some(DependencyLevel::Module),
+            _ => None,
+        }
+    }
+}
+
+/// Where a class that is depended on was found.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    /// Among the classes analysed.
+    Analysed,
+    /// In a directory or jar on the class path, by its path.
+    ClassPath(String),
+    /**
+     * In a module of the JDK, with whether the module exports the class's package to every
+     * module. The packages it does not export are JDK internal API, which the module system
+     * keeps other code from using.\
+     *  Without a jimage on the class path, a class is taken to be in the JDK by its package,
+     *  with `JDK` for its module, and to be internal if it is in `sun` or an `internal`
+     *  package.
+     */
+    Jdk { module: String, exported: bool },
+    /// Nowhere, so the reference cannot be resolved.
+    NotFound,
+}
+
+impl Origin {
+    /// Whether the dependency is on a class that cannot be found or is internal to the JDK.
+    pub fn is_flagged(&self) -> bool {
+        matches!(
+            self,
+            Origin::NotFound
+                | Origin::Jdk {
+                    exported: false,
+                    ..
+                }
+        )
+    }
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Analysed => Ok(()),
+            Origin::ClassPath(path) => f.write_str(path),
+            Origin::Jdk {
+                module,
+                exported: true,
+            } => f.write_str(module),
+            Origin::Jdk {
+                module,
+                exported: false,
+            } if module == UNKNOWN_MODULE => f.write_str("JDK internal API"),
+            Origin::Jdk {
+                module,
+                exported: false,
+            } => write!(f, "JDK internal API ({module})"),
+            Origin::NotFound => f.write_str("not found"),
+        }
+    }
+}
+
+/// A dependency of a class, package or module on another, by dotted name.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Dependency {
+    pub from: String,
+    pub to: String,
+    pub origin: Origin,
+}
+
+/**
+ * The classes each of a set of classes depends on, as `jdeps` reports them.\
+ *  A class depends on every class its constant pool refers to, and on the classes in its
+ *  descriptors, generic signatures and annotations, which the constant pool only holds as
+ *  text. The classes depended on are looked up on a class path, whose jimages stand for the
+ *  modules of the JDK.
+ */
+pub struct Dependencies {
+    /// What the classes analysed are called as a whole, such as the path of their jar.
+    name: String,
+    /// The classes each class analysed depends on with where they were found, by binary name.
+    classes: BTreeMap<String, BTreeMap<String, Origin>>,
+}
+
+impl Dependencies {
+    pub fn new(
+        name: &str,
+        classes: &[ClassFile],
+        class_path: &ClassPath,
+    ) -> Result<Dependencies, Box<dyn Error>> {
+        let get_this_name = |class: &ClassFile| {
+            get_raw_class_name(&class.constant_pool, class.this_class).unwrap_or_default()
+        };
+        let analysed: HashSet<String> = classes.iter().map(get_this_name).collect();
+        let mut origins = HashMap::new();
+        let mut exports = HashMap::new();
+        let mut dependencies = BTreeMap::new();
+        for class in classes {
+            let this_name = get_this_name(class);
+            let mut references = References::default();
+            references.add_class(class);
+            let mut depends_on = BTreeMap::new();
+            for name in references.names {
+                if name == this_name {
+                    continue;
+                }
+                if !origins.contains_key(&name) {
+                    let origin = match analysed.contains(&name) {
+                        true => Origin::Analysed,
+                        false => find_origin(&name, class_path, &mut exports)?,
+                    };
+                    origins.insert(name.clone(), origin);
+                }
+                depends_on.insert(name.replace('/', "."), origins[&name].clone());
+            }
+            dependencies.insert(this_name.replace('/', "."), depends_on);
+        }
+        Ok(Dependencies {
+            name: name.into(),
+            classes: dependencies,
+        })
+    }
+
+    /**
+     * The dependencies between classes, packages or modules, sorted. Those within a package or
+     * within the classes analysed are left out at the package and module levels, and a
+     * dependency on a module is flagged if any of the classes used from it is internal.
+     */
+    pub fn get_dependencies(&self, level: DependencyLevel) -> Vec<Dependency> {
+        let mut dependencies: BTreeMap<(String, String), Origin> = BTreeMap::new();
+        for (class, depends_on) in &self.classes {
+            for (target, origin) in depends_on {
+                let (from, to) = match level {
+                    DependencyLevel::Class => (class.clone(), target.clone()),
+                    DependencyLevel::Package => (get_package(class), get_package(target)),
+                    DependencyLevel::Module => (self.name.clone(), self.get_module(origin)),
+                };
+                if from == to {
+                    continue;
+                }
+                let merged = dependencies.entry((from, to)).or_insert(origin.clone());
+                if origin.is_flagged() {
+                    *merged = origin.clone();
+                }
+            }
+        }
+        dependencies
+            .into_iter()
+            .map(|((from, to), origin)| Dependency { from, to, origin })
+            .collect()
+    }
+
+    /**
+     * A report like that of `jdeps`: the modules depended on, each as `name -> module`,
+     * followed at the class and package levels by a line for each dependency, naming where
+     * its target was found.
+     */
+    pub fn to_text(&self, level: DependencyLevel) -> String {
+        let mut text = String::new();
+        let modules = self.get_dependencies(DependencyLevel::Module);
+        for dependency in &modules {
+            let _ = writeln!(text, "{} -> {}", dependency.from, dependency.to);
+        }
+        if level != DependencyLevel::Module {
+            for dependency in self.get_dependencies(level) {
+                let origin = match dependency.origin {
+                    Origin::Analysed => self.name.clone(),
+                    origin => origin.to_string(),
+                };
+                let _ = writeln!(
+                    text,
+                    "   {:<40} -> {:<40} {origin}",
+                    dependency.from, dependency.to
+                );
+            }
+        }
+        text
+    }
+
+    /**
+     * The dependencies as a graph. The classes, packages or module analysed are drawn with
+     * the kind of the level, and what they depend on as `external`, `internal` for JDK
+     * internal API, or `missing` for what was not found, labelled with where it was found.
+     */
+    pub fn to_graph(&self, level: DependencyLevel) -> Graph {
+        let kind = match level {
+            DependencyLevel::Class => "class",
+            DependencyLevel::Package => "package",
+            DependencyLevel::Module => "module",
+        };
+        let mut nodes = BTreeMap::new();
+        let mut edges = vec![];
+        for dependency in self.get_dependencies(level) {
+            nodes.entry(dependency.from.clone()).or_insert(Node {
+                id: dependency.from.clone(),
+                label: dependency.from.clone(),
+                kind,
+                lines: vec![],
+            });
+            let (kind, lines) = match &dependency.origin {
+                Origin::Analysed => (kind, vec![]),
+                Origin::NotFound => ("missing", vec![dependency.origin.to_string()]),
+                origin if origin.is_flagged() => ("internal", vec![origin.to_string()]),
+                _ if level == DependencyLevel::Module => ("external", vec![]),
+                origin => ("external", vec![origin.to_string()]),
+            };
+            nodes.entry(dependency.to.clone()).or_insert(Node {
+                id: dependency.to.clone(),
+                label: dependency.to.clone(),
+                kind,
+                lines,
+            });
+            edges.push(Edge {
+                from: dependency.from,
+                to: dependency.to,
+                kind: "uses",
+                label: None,
+            });
+        }
+        Graph {
+            name: self.name.clone(),
+            nodes: nodes.into_values().collect(),
+            edges,
+        }
+    }
+
+    /// The module a class was found in, or the jar or directory it was found in.
+    fn get_module(&self, origin: &Origin) -> String {
+        match origin {
+            Origin::Analysed => self.name.clone(),
+            Origin::ClassPath(path) => path.clone(),
+            Origin::Jdk { module, .. } => module.clone(),
+            Origin::NotFound => origin.to_string(),
+        }
+    }
+}
+
+/**
+ * Looks a class up on the class path by its internal name. A class found in a jimage comes
+ * from the module its location names, as in `jrt:/java.base/java/lang/Object.class`, and its
+ * package is checked against what the module exports, which are kept by module in `exports`.
+ * With no jimage on the class path, the classes of the JDK are told by their package instead.
+ */
+fn find_origin(
+    name: &str,
+    class_path: &ClassPath,
+    exports: &mut HashMap<String, HashSet<String>>,
+) -> Result<Origin, Box<dyn Error>> {
+    let Some(resource) = class_path.find_class(name)? else {
+        let has_image = class_path
+            .get_entries()
+            .iter()
+            .any(|entry| matches!(entry, ClassPathEntry::Image(_)));
+        return Ok(match has_image {
+            true => Origin::NotFound,
+            false => get_jdk_origin(name),
+        });
+    };
+    let entry = &class_path.get_entries()[resource.entry];
+    let ClassPathEntry::Image(_) = entry else {
+        return Ok(Origin::ClassPath(entry.get_path().display().to_string()));
+    };
+    let module = resource
+        .location
+        .trim_start_matches("jrt:/")
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    if !exports.contains_key(&module) {
+        let exported = match class_path.load_module_info(&module)? {
+            Some(module_info) => get_exported_packages(&module_info),
+            None => HashSet::new(),
+        };
+        exports.insert(module.clone(), exported);
+    }
+    let package = name.rsplit_once('/').map_or("", |(package, _)| package);
+    let exported = exports[&module].contains(package);
+    Ok(Origin::Jdk { module, exported })
+}
+
+/// The origin of a class that is not on a class path without a jimage, from its package.
+fn get_jdk_origin(name: &str) -> Origin {
+    if !JDK_PACKAGES.iter().any(|package| name.starts_with(package)) {
+        return Origin::NotFound;
+    }
+    let is_internal = name.starts_with("sun/") || name.contains("/internal/");
+    Origin::Jdk {
+        module: UNKNOWN_MODULE.into(),
+        exported: !is_internal,
+    }
+}
+
+/// The packages a module descriptor exports to every module, by internal name.
+fn get_exported_packages(module_info: &ClassFile) -> HashSet<String> {
+    let pool = &module_info.constant_pool;
+    module_info
+        .attributes
+        .iter()
+        .filter_map(|attribute| match attribute {
+            AttributeInfo::Module(module) => Some(&module.exports),
+            _ => None,
+        })
+        .flatten()
+        .filter(|exports| exports.exports_to_index.is_empty())
+        .filter_map(|exports| match pool.get(exports.exports_index as usize) {
+            Some(ConstantPool::Package(package)) => {
+                Some(get_raw_utf8(pool, package.name_index).unwrap_or_default())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The package of a dotted class name, `<unnamed>` for the unnamed package as `jdeps` has it.
+fn get_package(class: &str) -> String {
+    match class.rsplit_once('.') {
+        Some((package, _)) => package.into(),
+        None => "<unnamed>".into(),
+    }
+}
+
+/**
+ * The internal names of the classes a class refers to. Descriptors and signatures that do not
+ * parse are skipped, since the classes they name cannot be told.
+ */
+#[derive(Default)]
+struct References {
+    names: BTreeSet<String>,
+}
+
+impl References {
+    fn add_class(&mut self, class: &ClassFile) {
+        let pool = &class.constant_pool;
+        for constant in pool {
+            match constant {
+                ConstantPool::Class(class) => {
+                    let name = get_raw_utf8(pool, class.name_index).unwrap_or_default();
+                    match name.starts_with('[') {
+                        true => self.add_field_descriptor(&name),
+                        false => self.add_name(name),
+                    }
+                }
+                // Covers the types of the fields and methods referred to, including those of
+                // invokedynamic call sites.
+                ConstantPool::NameAndType(name_and_type) => self.add_descriptor(
+                    &get_raw_utf8(pool, name_and_type.descriptor_index).unwrap_or_default(),
+                ),
+                ConstantPool::MethodType(method_type) => self.add_descriptor(
+                    &get_raw_utf8(pool, method_type.descriptor_index).unwrap_or_default(),
+                ),
+                _ => {}
+            }
+        }
+        for field in &class.fields {
+            self.add_descriptor(&get_raw_utf8(pool, field.descriptor_index).unwrap_or_default());
+            self.add_attributes(pool, &field.attributes, Kind::Field);
+        }
+        for method in &class.methods {
+            self.add_descriptor(&get_raw_utf8(pool, method.descriptor_index).unwrap_or_default());
+            self.add_attributes(pool, &method.attributes, Kind::Method);
+        }
+        self.add_attributes(pool, &class.attributes, Kind::Class);
+    }
+
+    fn add_attributes(&mut self, pool: &[ConstantPool], attributes: &[AttributeInfo], kind: Kind) {
+        for attribute in attributes {
+            match attribute {
+                AttributeInfo::Signature(signature) => self.add_signature(
+                    &get_raw_utf8(pool, signature.signature_index).unwrap_or_default(),
+                    kind,
+                ),
+                AttributeInfo::RuntimeVisibleAnnotations(annotations) => {
+                    self.add_annotations(pool, &annotations.annotations)
+                }
+                AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+                    self.add_annotations(pool, &annotations.annotations)
+                }
+                AttributeInfo::RuntimeVisibleParameterAnnotations(parameters) => {
+                    for parameter in &parameters.parameter_annotations {
+                        self.add_annotations(pool, &parameter.annotations);
+                    }
+                }
+                AttributeInfo::RuntimeInvisibleParameterAnnotations(parameters) => {
+                    for parameter in &parameters.parameter_annotations {
+                        self.add_annotations(pool, &parameter.annotations);
+                    }
+                }
+                AttributeInfo::RuntimeVisibleTypeAnnotations(annotations) => {
+                    for annotation in &annotations.type_annotations {
+                        self.add_descriptor(
+                            &get_raw_utf8(pool, annotation.type_index).unwrap_or_default(),
+                        );
+                        for pair in &annotation.element_value_pairs {
+                            self.add_element(pool, &pair.value);
+                        }
+                    }
+                }
+                AttributeInfo::RuntimeInvisibleTypeAnnotations(annotations) => {
+                    for annotation in &annotations.type_annotations {
+                        self.add_descriptor(
+                            &get_raw_utf8(pool, annotation.type_index).unwrap_or_default(),
+                        );
+                        for pair in &annotation.element_value_pairs {
+                            self.add_element(pool, &pair.value);
+                        }
+                    }
+                }
+                AttributeInfo::AnnotationDefault(default) => {
+                    self.add_element(pool, &default.default_value)
+                }
+                AttributeInfo::Record(record) => {
+                    for component in &record.components {
+                        self.add_descriptor(
+                            &get_raw_utf8(pool, component.descriptor_index).unwrap_or_default(),
+                        );
+                        self.add_attributes(pool, &component.attributes, Kind::Field);
+                    }
+                }
+                // Type annotations on the code of a method are kept with its Code attribute.
+                AttributeInfo::Code(code) => self.add_attributes(pool, &code.attributes, kind),
+                _ => {}
+            }
+        }
+    }
+
+    fn add_annotations(&mut self, pool: &[ConstantPool], annotations: &[Annotation]) {
+        for annotation in annotations {
+            self.add_descriptor(&get_raw_utf8(pool, annotation.type_index).unwrap_or_default());
+            for pair in &annotation.element_value_pairs {
+                self.add_element(pool, &pair.value);
+            }
+        }
+    }
+
+    fn add_element(&mut self, pool: &[ConstantPool], element: &Element) {
+        match element {
+            Element::EnumConstValue {
+                type_name_index, ..
+            } => self.add_descriptor(&get_raw_utf8(pool, *type_name_index).unwrap_or_default()),
+            // A return descriptor, which is `V` for `void.class`.
+            Element::ClassInfoIndex(index) => {
+                self.add_descriptor(&get_raw_utf8(pool, *index).unwrap_or_default())
+            }
+            Element::Annotation(annotation) => {
+                self.add_annotations(pool, std::slice::from_ref(annotation))
+            }
+            Element::ArrayValue { values, .. } => {
+                for value in values {
+                    self.add_element(pool, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds the classes of a field or method descriptor.
+    fn add_descriptor(&mut self, descriptor: &str) {
+        if descriptor.starts_with('(') {
+            if let Ok(method) = MethodDescriptor::from_descriptor(descriptor) {
+                for parameter in method.parameters.iter().chain(&method.return_type) {
+                    self.add_field_type(parameter);
+                }
+            }
+        } else {
+            self.add_field_descriptor(descriptor);
+        }
+    }
+
+    fn add_field_descriptor(&mut self, descriptor: &str) {
+        if let Ok(field_type) = FieldType::from_descriptor(descriptor) {
+            self.add_field_type(&field_type);
+        }
+    }
+
+    fn add_field_type(&mut self, field_type: &FieldType) {
+        match field_type {
+            FieldType::Object(name) => self.add_name(name.clone()),
+            FieldType::Array(component) => self.add_field_type(component),
+            _ => {}
+        }
+    }
+
+    fn add_signature(&mut self, signature: &str, kind: Kind) {
+        match kind {
+            Kind::Class => {
+                if let Ok(class) = ClassSignature::parse(signature) {
+                    self.add_type_parameters(&class.type_parameters);
+                    for supertype in std::iter::once(&class.superclass).chain(&class.interfaces) {
+                        self.add_class_type_signature(supertype);
+                    }
+                }
+            }
+            Kind::Field => {
+                if let Ok(field) = TypeSignature::parse(signature) {
+                    self.add_type_signature(&field);
+                }
+            }
+            Kind::Method => {
+                if let Ok(method) = MethodSignature::parse(signature) {
+                    self.add_type_parameters(&method.type_parameters);
+                    let types = method.parameters.iter().chain(&method.return_type);
+                    for signature in types.chain(&method.throws) {
+                        self.add_type_signature(signature);
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_type_parameters(&mut self, type_parameters: &[TypeParameter]) {
+        for parameter in type_parameters {
+            let bounds = parameter.class_bound.iter();
+            for bound in bounds.chain(&parameter.interface_bounds) {
+                self.add_type_signature(bound);
+            }
+        }
+    }
+
+    fn add_type_signature(&mut self, signature: &TypeSignature) {
+        match signature {
+            TypeSignature::Class(class) => self.add_class_type_signature(class),
+            TypeSignature::Array(component) => self.add_type_signature(component),
+            TypeSignature::Base(_) | TypeSignature::TypeVariable(_) => {}
+        }
+    }
+
+    fn add_class_type_signature(&mut self, class: &ClassTypeSignature) {
+        self.add_name(class.get_binary_name());
+        for simple in std::iter::once(&class.class).chain(&class.inner) {
+            for argument in &simple.type_arguments {
+                match argument {
+                    TypeArgument::Exact(signature)
+                    | TypeArgument::Extends(signature)
+                    | TypeArgument::Super(signature) => self.add_type_signature(signature),
+                    TypeArgument::Any => {}
+                }
+            }
+        }
+    }
+
+    fn add_name(&mut self, name: String) {
+        if !name.is_empty() {
+            self.names.insert(name);
+        }
+    }
+}
+
+/// What declares a Signature attribute, which decides how the signature is parsed.
+#[derive(Clone, Copy)]
+enum Kind {
+    Class,
+    Field,
+    Method,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::access_flags::{FieldAccessFlags, MethodAccessFlags};
+    use crate::class_builder::tests::{read_back, single_method};
+    use crate::class_builder::{ClassBuilder, CodeBuilder, FieldBuilder, Insn, MethodBuilder};
+    use crate::instructions::*;
+
+    /// A class with a static method `run` that calls each of `calls`, given as owner and name.
+    fn caller(name: &str, calls: &[(&str, &str)]) -> ClassBuilder {
+        let mut code = CodeBuilder::new(1, 0);
+        for (owner, name) in calls {
+            code.push(Insn::Method {
+                opcode: INVOKESTATIC,
+                owner: owner.to_string(),
+                name: name.to_string(),
+                descriptor: "()V".into(),
+                interface: false,
+            });
+        }
+        code.push(Insn::Simple(RETURN));
+        single_method(name, "()V", code)
+    }
+
+    #[test]
+    fn test_classes_in_descriptors_and_signatures() {
+        let mut app = caller("test/App", &[]);
+        let mut field =
+            FieldBuilder::new(&[FieldAccessFlags::AccPrivate], "data", "Ljava/util/List;");
+        field.set_signature("Ljava/util/List<Ljava/util/Map<Ljava/lang/String;[Ltest/Value;>;>;");
+        app.add_field(field).unwrap();
+        let flags = [MethodAccessFlags::AccPublic, MethodAccessFlags::AccAbstract];
+        let mut method = MethodBuilder::new(&flags, "load", "([Ltest/Key;)Ljava/lang/Object;");
+        method.set_signature("<T:Ltest/Bound;>([Ltest/Key;)TT;");
+        method.add_exception("test/Failure");
+        app.add_method(method).unwrap();
+        let classes = [read_back(app)];
+
+        let dependencies = Dependencies::new("app", &classes, &ClassPath::new()).unwrap();
+        let targets: Vec<_> = dependencies
+            .get_dependencies(DependencyLevel::Class)
+            .into_iter()
+            .map(|dependency| (dependency.to, dependency.origin))
+            .collect();
+        let jdk = [
+            "java.lang.Object",
+            "java.lang.String",
+            "java.util.List",
+            "java.util.Map",
+        ];
+        let missing = ["test.Bound", "test.Failure", "test.Key", "test.Value"];
+        let origin = Origin::Jdk {
+            module: "JDK".into(),
+            exported: true,
+        };
+        let expected: Vec<_> = jdk
+            .iter()
+            .map(|name| (name.to_string(), origin.clone()))
+            .chain(
+                missing
+                    .iter()
+                    .map(|name| (name.to_string(), Origin::NotFound)),
+            )
+            .collect();
+        assert_eq!(targets, expected);
+    }
+
+    #[test]
+    fn test_jdk_classes_without_image() {
+        let calls = [
+            ("com/sun/net/httpserver/HttpServer", "create"),
+            ("javax/swing/JFrame", "run"),
+            ("jdk/internal/misc/VM", "initLevel"),
+            ("sun/misc/Unsafe", "getUnsafe"),
+            ("sunny/Day", "run"),
+        ];
+        let classes = [read_back(caller("test/App", &calls))];
+        let dependencies = Dependencies::new("app", &classes, &ClassPath::new()).unwrap();
+
+        let text = dependencies.to_text(DependencyLevel::Class);
+        let lines: Vec<_> = text.lines().skip(2).map(str::trim_end).collect();
+        let line = |to: &str, origin: &str| format!("   {:<40} -> {to:<40} {origin}", "test.App");
+        assert_eq!(
+            lines,
+            [
+                line("com.sun.net.httpserver.HttpServer", "JDK"),
+                line("java.lang.Object", "JDK"),
+                line("javax.swing.JFrame", "JDK"),
+                line("jdk.internal.misc.VM", "JDK internal API"),
+                line("sun.misc.Unsafe", "JDK internal API"),
+                line("sunny.Day", "not found"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_package_and_module() {
+        let directory = env::temp_dir().join(format!("jvm_rs_deps_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("lib")).unwrap();
+        let library = caller("lib/Library", &[]).into_bytes().unwrap();
+        fs::write(directory.join("lib/Library.class"), library).unwrap();
+        let mut class_path = ClassPath::new();
+        class_path.push(ClassPathEntry::Directory(directory.clone()));
+
+        let classes = [
+            read_back(caller(
+                "a/First",
+                &[
+                    ("a/Second", "run"),
+                    ("b/Third", "run"),
+                    ("lib/Library", "run"),
+                ],
+            )),
+            read_back(caller("a/Second", &[("gone/Missing", "run")])),
+            read_back(caller("b/Third", &[])),
+        ];
+        let dependencies = Dependencies::new("app.jar", &classes, &class_path).unwrap();
+        let _ = fs::remove_dir_all(&directory);
+
+        let path = directory.display().to_string();
+        let modules = dependencies.get_dependencies(DependencyLevel::Module);
+        let modules: Vec<_> = modules.iter().map(|module| module.to.as_str()).collect();
+        assert_eq!(modules, [path.as_str(), "JDK", "not found"]);
+        let text = dependencies.to_text(DependencyLevel::Package);
+        let lines: Vec<_> = text.lines().map(str::trim_end).collect();
+        let line =
+            |from: &str, to: &str, origin: &str| format!("   {from:<40} -> {to:<40} {origin}");
+        assert_eq!(
+            lines,
+            [
+                format!("app.jar -> {path}"),
+                "app.jar -> JDK".into(),
+                "app.jar -> not found".into(),
+                line("a", "b", "app.jar"),
+                line("a", "gone", "not found"),
+                line("a", "java.lang", "JDK"),
+                line("a", "lib", &path),
+                line("b", "java.lang", "JDK"),
+            ]
+        );
+
+        let graph = dependencies.to_graph(DependencyLevel::Package);
+        let kinds: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.id.as_str(), node.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("a", "package"),
+                ("b", "package"),
+                ("gone", "missing"),
+                ("java.lang", "external"),
+                ("lib", "external"),
+            ]
+        );
+    }
+}
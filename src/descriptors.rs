@@ -0,0 +1,222 @@
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::errors::class_format_check::{FormatCause, FormatError};
+
+/// [Field Descriptors](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#page=99)
+/**
+ * A field descriptor represents the type of a class, instance, or local variable.\
+ * FieldType:\
+ *  BaseType\
+ *  ObjectType\
+ *  ArrayType
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// B, signed byte
+    Byte,
+    /// C, Unicode character code point in the Basic Multilingual Plane, encoded with UTF-16
+    Char,
+    /// D, double-precision floating-point value
+    Double,
+    /// F, single-precision floating-point value
+    Float,
+    /// I, integer
+    Int,
+    /// J, long integer
+    Long,
+    /// S, signed short
+    Short,
+    /// Z, true or false
+    Boolean,
+    /// L ClassName ; an instance of class ClassName
+    Object(String),
+    /// [ one array dimension
+    Array(Box<FieldType>),
+}
+
+impl FieldType {
+    pub fn from_descriptor(descriptor: &str) -> Result<FieldType, FormatError> {
+        let mut chars = descriptor.chars().peekable();
+        let field_type = FieldType::read(&mut chars, descriptor)?;
+        if chars.next().is_some() {
+            return Err(invalid(descriptor));
+        }
+        Ok(field_type)
+    }
+
+    fn read(chars: &mut Peekable<Chars>, descriptor: &str) -> Result<FieldType, FormatError> {
+        Ok(match chars.next() {
+            Some('B') => FieldType::Byte,
+            Some('C') => FieldType::Char,
+            Some('D') => FieldType::Double,
+            Some('F') => FieldType::Float,
+            Some('I') => FieldType::Int,
+            Some('J') => FieldType::Long,
+            Some('S') => FieldType::Short,
+            Some('Z') => FieldType::Boolean,
+            Some('L') => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some(';') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(invalid(descriptor)),
+                    }
+                }
+                if name.is_empty() {
+                    return Err(invalid(descriptor));
+                }
+                FieldType::Object(name)
+            }
+            Some('[') => FieldType::Array(Box::new(FieldType::read(chars, descriptor)?)),
+            _ => return Err(invalid(descriptor)),
+        })
+    }
+
+    /// The type as it would be written in Java source, e.g. `java.lang.String[]`.
+    pub fn get_java_name(&self) -> String {
+        match self {
+            FieldType::Byte => "byte".into(),
+            FieldType::Char => "char".into(),
+            FieldType::Double => "double".into(),
+            FieldType::Float => "float".into(),
+            FieldType::Int => "int".into(),
+            FieldType::Long => "long".into(),
+            FieldType::Short => "short".into(),
+            FieldType::Boolean => "boolean".into(),
+            FieldType::Object(name) => name.replace('/', "."),
+            FieldType::Array(component) => format!("{}[]", component.get_java_name()),
+        }
+    }
+
+    pub fn to_descriptor(&self) -> String {
+        match self {
+            FieldType::Byte => "B".into(),
+            FieldType::Char => "C".into(),
+            FieldType::Double => "D".into(),
+            FieldType::Float => "F".into(),
+            FieldType::Int => "I".into(),
+            FieldType::Long => "J".into(),
+            FieldType::Short => "S".into(),
+            FieldType::Boolean => "Z".into(),
+            FieldType::Object(name) => format!("L{name};"),
+            FieldType::Array(component) => format!("[{}", component.to_descriptor()),
+        }
+    }
+
+    /// The number of local variable or operand stack slots a value of this type occupies.
+    pub fn get_size(&self) -> u16 {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_java_name())
+    }
+}
+
+/// [Method Descriptors](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#page=101)
+/**
+ * A method descriptor contains zero or more parameter descriptors, representing the
+ * types of parameters that the method takes, and a return descriptor, representing the
+ * type of the value (if any) that the method returns.\
+ * MethodDescriptor:\
+ *  ( {ParameterDescriptor} ) ReturnDescriptor
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    /// `None` represents the VoidDescriptor `V`.
+    pub return_type: Option<FieldType>,
+}
+
+impl MethodDescriptor {
+    pub fn from_descriptor(descriptor: &str) -> Result<MethodDescriptor, FormatError> {
+        let mut chars = descriptor.chars().peekable();
+        if chars.next() != Some('(') {
+            return Err(invalid(descriptor));
+        }
+        let mut parameters = vec![];
+        loop {
+            match chars.peek() {
+                Some(')') => {
+                    chars.next();
+                    break;
+                }
+                Some(_) => parameters.push(FieldType::read(&mut chars, descriptor)?),
+                None => return Err(invalid(descriptor)),
+            }
+        }
+        let return_type = if chars.peek() == Some(&'V') {
+            chars.next();
+            None
+        } else {
+            Some(FieldType::read(&mut chars, descriptor)?)
+        };
+        if chars.next().is_some() {
+            return Err(invalid(descriptor));
+        }
+        Ok(MethodDescriptor {
+            parameters,
+            return_type,
+        })
+    }
+
+    pub fn to_descriptor(&self) -> String {
+        let mut output = String::from("(");
+        for parameter in &self.parameters {
+            output.push_str(&parameter.to_descriptor());
+        }
+        output.push(')');
+        match &self.return_type {
+            Some(return_type) => output.push_str(&return_type.to_descriptor()),
+            None => output.push('V'),
+        }
+        output
+    }
+
+    pub fn get_return_java_name(&self) -> String {
+        match &self.return_type {
+            Some(return_type) => return_type.get_java_name(),
+            None => "void".into(),
+        }
+    }
+
+    /// The number of local variable slots taken by the parameters, excluding `this`.
+    pub fn get_parameter_size(&self) -> u16 {
+        self.parameters.iter().map(FieldType::get_size).sum()
+    }
+}
+
+impl Display for MethodDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parameters: Vec<String> = self
+            .parameters
+            .iter()
+            .map(FieldType::get_java_name)
+            .collect();
+        write!(
+            f,
+            "{} ({})",
+            self.get_return_java_name(),
+            parameters.join(", ")
+        )
+    }
+}
+
+fn invalid(descriptor: &str) -> FormatError {
+    FormatError::new(
+        FormatCause::InvalidDescriptor(descriptor.into()),
+        "descriptor does not match the grammar in §4.3",
+    )
+}
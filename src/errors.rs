@@ -6,6 +6,8 @@ pub mod class_format_check {
     pub enum FormatCause {
         IncorrectMagic(u32),
         ExtraBytes,
+        InvalidDescriptor(String),
+        InvalidSignature(String),
     }
 
     impl Display for FormatCause {
@@ -13,6 +15,8 @@ pub mod class_format_check {
             match self {
                 FormatCause::IncorrectMagic(t) => write!(f, "MagicIncorrect: {:02X?}", t),
                 FormatCause::ExtraBytes => write!(f, "ExtraBytes"),
+                FormatCause::InvalidDescriptor(t) => write!(f, "InvalidDescriptor: {t}"),
+                FormatCause::InvalidSignature(t) => write!(f, "InvalidSignature: {t}"),
             }
         }
     }
@@ -47,6 +51,8 @@ pub mod class_loading {
 
     use crate::class_file::ConstantPool;
 
+    // The causes are named as they are displayed.
+    #[allow(clippy::enum_variant_names)]
     #[derive(Debug)]
     pub enum LoadingCause {
         InvalidConstantTag(u8),
@@ -54,6 +60,15 @@ pub mod class_loading {
         InvalidTargetInfoValue(u8),
         InvalidTargetTypeValue(u8),
         InvalidTypePathKind(u8),
+        InvalidSmap(usize),
+        InvalidKotlinMetadata(String),
+        InvalidScalaSignature(String),
+        InvalidInstruction(u32),
+        InvalidArchive(String),
+        InvalidManifest(usize),
+        InvalidImage(String),
+        InvalidClassPath(String),
+        InvalidMappings(usize),
     }
 
     impl Display for LoadingCause {
@@ -72,6 +87,19 @@ pub mod class_loading {
                 LoadingCause::InvalidTypePathKind(t) => {
                     write!(f, "InvalidTypePathKind: {t}")
                 }
+                LoadingCause::InvalidSmap(t) => write!(f, "InvalidSmap: line {t}"),
+                LoadingCause::InvalidKotlinMetadata(t) => {
+                    write!(f, "InvalidKotlinMetadata: {t}")
+                }
+                LoadingCause::InvalidScalaSignature(t) => {
+                    write!(f, "InvalidScalaSignature: {t}")
+                }
+                LoadingCause::InvalidInstruction(t) => write!(f, "InvalidInstruction: pc {t}"),
+                LoadingCause::InvalidArchive(t) => write!(f, "InvalidArchive: {t}"),
+                LoadingCause::InvalidManifest(t) => write!(f, "InvalidManifest: line {t}"),
+                LoadingCause::InvalidImage(t) => write!(f, "InvalidImage: {t}"),
+                LoadingCause::InvalidClassPath(t) => write!(f, "InvalidClassPath: {t}"),
+                LoadingCause::InvalidMappings(t) => write!(f, "InvalidMappings: line {t}"),
             }
         }
     }
@@ -99,3 +127,62 @@ pub mod class_loading {
         }
     }
 }
+
+pub mod class_writing {
+    use std::error::Error;
+    use std::fmt::Display;
+
+    #[derive(Debug)]
+    pub enum WritingCause {
+        TooLarge(String),
+        MissingConstant(String),
+        InvalidInstruction(u8),
+        InvalidLabel(usize),
+        InvalidElementTag(char),
+        Syntax(usize),
+        InvalidFrame(u32),
+        MissingClass(String),
+        UnknownAttribute(String),
+    }
+
+    impl Display for WritingCause {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                WritingCause::TooLarge(t) => write!(f, "TooLarge: {t}"),
+                WritingCause::MissingConstant(t) => write!(f, "MissingConstant: {t}"),
+                WritingCause::InvalidInstruction(t) => {
+                    write!(f, "InvalidInstruction: {t:#04x}")
+                }
+                WritingCause::InvalidLabel(t) => write!(f, "InvalidLabel: {t}"),
+                WritingCause::InvalidElementTag(t) => write!(f, "InvalidElementTag: {t}"),
+                WritingCause::Syntax(t) => write!(f, "Syntax: line {t}"),
+                WritingCause::InvalidFrame(t) => write!(f, "InvalidFrame: pc {t}"),
+                WritingCause::MissingClass(t) => write!(f, "MissingClass: {t}"),
+                WritingCause::UnknownAttribute(t) => write!(f, "UnknownAttribute: {t}"),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct WritingError {
+        cause: WritingCause,
+        msg: String,
+    }
+
+    impl WritingError {
+        pub fn new(cause: WritingCause, msg: &str) -> WritingError {
+            WritingError {
+                cause,
+                msg: msg.into(),
+            }
+        }
+    }
+
+    impl Error for WritingError {}
+
+    impl Display for WritingError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "WritingError: {}, {}", self.cause, self.msg)
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use std::cmp::Ordering;
+
+/// Formats like Java's Double.toString, e.g. `3.14159`, `1.0E-5` or `NaN`.
+pub fn java_double(value: f64) -> String {
+    let bits = value.to_bits();
+    let exponent = (bits >> 52) as i32 & 0x7ff;
+    java_decimal(
+        value.is_sign_negative(),
+        bits & ((1 << 52) - 1),
+        exponent,
+        52,
+        1023,
+    )
+}
+
+/// Formats like Java's Float.toString, e.g. `1.5` or `1.07374182E9`.
+pub fn java_float(value: f32) -> String {
+    let bits = value.to_bits();
+    let exponent = (bits >> 23) as i32 & 0xff;
+    let fraction = (bits & ((1 << 23) - 1)) as u64;
+    java_decimal(value.is_sign_negative(), fraction, exponent, 23, 127)
+}
+
+/**
+ * Formats a float or double from the fields of its encoding as Java 17's FloatingDecimal
+ * does.\
+ *  Its digits are not always the shortest that read back as the same value: an integer
+ *  keeps the digits a long holds, down to the precision of the type, and other values stop
+ *  within half an ulp by a test that is not quite symmetric. javap prints the constant pool
+ *  with these digits, so they are reproduced exactly.
+ */
+fn java_decimal(
+    negative: bool,
+    fraction: u64,
+    exponent: i32,
+    fraction_bits: u32,
+    bias: i32,
+) -> String {
+    let sign = if negative { "-" } else { "" };
+    if exponent == 2 * bias + 1 {
+        return match fraction {
+            0 => format!("{sign}Infinity"),
+            _ => "NaN".into(),
+        };
+    }
+    if exponent == 0 && fraction == 0 {
+        return format!("{sign}0.0");
+    }
+    let (fraction, binary_exponent, significant_bits) = if exponent == 0 {
+        // Subnormals are normalized, keeping the bits they have.
+        let shift = fraction.leading_zeros() - (63 - fraction_bits);
+        let significant_bits = 64 - fraction.leading_zeros() as i32;
+        (fraction << shift, 1 - shift as i32, significant_bits)
+    } else {
+        let significant_bits = fraction_bits as i32 + 1;
+        (fraction | 1 << fraction_bits, exponent, significant_bits)
+    };
+    let (digits, decimal_exponent) = floating_decimal_digits(
+        binary_exponent - bias,
+        fraction << (52 - fraction_bits),
+        significant_bits,
+    );
+    let digits: String = digits
+        .iter()
+        .map(|digit| char::from(b'0' + digit))
+        .collect();
+    let text = match decimal_exponent {
+        1..=7 => {
+            let split = digits.len().min(decimal_exponent as usize);
+            let (whole, decimals) = digits.split_at(split);
+            let zeros = "0".repeat(decimal_exponent as usize - split);
+            let decimals = if decimals.is_empty() { "0" } else { decimals };
+            format!("{whole}{zeros}.{decimals}")
+        }
+        -2..=0 => format!("0.{}{digits}", "0".repeat(-decimal_exponent as usize)),
+        _ => {
+            let (first, rest) = digits.split_at(1);
+            let rest = if rest.is_empty() { "0" } else { rest };
+            format!("{first}.{rest}E{}", decimal_exponent - 1)
+        }
+    };
+    format!("{sign}{text}")
+}
+
+/**
+ * The decimal digits of `fraction`, a 53 bit significand with its leading bit set, times
+ * 2^(`binary_exponent` - 52), with the exponent that puts the decimal point before the first
+ * digit. `significant_bits` is the precision of the type the value came from.
+ */
+fn floating_decimal_digits(
+    binary_exponent: i32,
+    fraction: u64,
+    significant_bits: i32,
+) -> (Vec<u8>, i32) {
+    /// How many of the low digits of an integer below 2^(n + 1) a precision below it hides.
+    const INSIGNIFICANT_DIGITS: [u32; 64] = [
+        0, 0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 8, 8, 8,
+        9, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 12, 12, 13, 13, 13, 14, 14, 14, 15, 15, 15, 15,
+        16, 16, 16, 17, 17, 17, 18, 18, 18, 19,
+    ];
+    let tail_zeros = fraction.trailing_zeros() as i32;
+    let fraction_count = 53 - tail_zeros;
+    let tiny_bits = (fraction_count - binary_exponent - 1).max(0);
+    if tiny_bits == 0 && (-21..=62).contains(&binary_exponent) {
+        // An integer that fits in a long, whose digits below the precision are rounded off.
+        let insignificant = match binary_exponent - significant_bits - 1 {
+            power @ 2..=63 => INSIGNIFICANT_DIGITS[power as usize],
+            _ => 0,
+        };
+        let mut value = match binary_exponent >= 52 {
+            true => fraction << (binary_exponent - 52),
+            false => fraction >> (52 - binary_exponent),
+        };
+        if insignificant > 0 {
+            let power = 10u64.pow(insignificant);
+            let residue = value % power;
+            value /= power;
+            if residue >= power / 2 {
+                value += 1;
+            }
+        }
+        let text = value.to_string();
+        let digits: Vec<u8> = text
+            .trim_end_matches('0')
+            .bytes()
+            .map(|digit| digit - b'0')
+            .collect();
+        return (digits, insignificant as i32 + text.len() as i32);
+    }
+
+    // The value is B / S * 10^exponent with 1 <= B / S < 10, and M is half an ulp scaled
+    // like B. Digits are taken off B / S until what is left is within M of either end.
+    // Java's truncated log10(2), whose floor the digits depend on.
+    let estimate = (fraction & ((1 << 52) - 1)) as f64 / (1u64 << 52) as f64 + 1.0;
+    #[allow(clippy::approx_constant)]
+    let estimate =
+        (estimate - 1.5) * 0.289529654 + 0.176091259 + binary_exponent as f64 * 0.301029995663981;
+    let mut exponent = estimate.floor() as i32;
+    let b5 = (-exponent).max(0);
+    let mut b2 = b5 + tiny_bits + binary_exponent;
+    let s5 = exponent.max(0);
+    let mut s2 = s5 + tiny_bits;
+    let m5 = b5;
+    let mut m2 = b2 - significant_bits;
+    b2 -= fraction_count - 1;
+    let common = b2.min(s2);
+    b2 -= common;
+    s2 -= common;
+    m2 -= common;
+    // Below a power of two the next value is only half as far away.
+    if fraction_count == 1 {
+        m2 -= 1;
+    }
+    if m2 < 0 {
+        b2 -= m2;
+        s2 -= m2;
+        m2 = 0;
+    }
+    // Java works in an int or a long when B and 10S fit one, and its digits follow the
+    // overflows of that arithmetic; only the arbitrary precision path counts reaching 10S.
+    let five_bits = |power: i32| match power {
+        0..=26 => 64 - 5u64.pow(power as u32).leading_zeros() as i32,
+        _ => power * 3,
+    };
+    let b_bits = fraction_count + b2 + five_bits(b5);
+    let ten_s_bits = s2 + 1 + five_bits(s5 + 1);
+    let width = match b_bits.max(ten_s_bits) {
+        0..=31 => Some(32),
+        32..=63 => Some(64),
+        _ => None,
+    };
+    let b = Natural::from(fraction >> tail_zeros)
+        .times_pow5(b5)
+        .shifted(b2);
+    let s = Natural::from(1).times_pow5(s5).shifted(s2);
+    let m = Natural::from(1).times_pow5(m5).shifted(m2);
+    let (digits, last, low, high) = match width {
+        Some(width) => {
+            fixed_width_digits(b.to_i128(), s.to_i128(), m.to_i128(), width, &mut exponent)
+        }
+        None => natural_digits(b, &s, m, &mut exponent),
+    };
+    let mut digits = digits;
+    let mut decimal_exponent = exponent + 1;
+    let round_up = match (low, high) {
+        (_, false) => false,
+        (false, true) => true,
+        (true, true) => match last {
+            Ordering::Less => false,
+            Ordering::Equal => digits.last().is_some_and(|digit| digit % 2 == 1),
+            Ordering::Greater => true,
+        },
+    };
+    if round_up {
+        match digits.iter().rposition(|digit| *digit != 9) {
+            Some(last) => {
+                digits[last] += 1;
+                digits[last + 1..].fill(0);
+            }
+            None => {
+                // Java carries into a 1 and leaves the zeros after it.
+                digits.fill(0);
+                digits[0] = 1;
+                decimal_exponent += 1;
+            }
+        }
+    }
+    (digits, decimal_exponent)
+}
+
+/**
+ * The digits of B / S as Java takes them in an int or a long of `width` bits, with whether the
+ * remainder ended within M of the lower or the upper end and how twice the remainder compares
+ * to 10S. `exponent` drops by one when the estimate was one too high.
+ */
+fn fixed_width_digits(
+    mut b: i128,
+    s: i128,
+    mut m: i128,
+    width: u32,
+    exponent: &mut i32,
+) -> (Vec<u8>, Ordering, bool, bool) {
+    let wrap = |value: i128| (value << (128 - width)) >> (128 - width);
+    let ten_s = s * 10;
+    let mut digits = vec![];
+    let digit = (b / s) as u8;
+    b = wrap(10 * (b % s));
+    m = wrap(m * 10);
+    let mut low = b < m;
+    let mut high = wrap(b + m) > ten_s;
+    if digit == 0 && !high {
+        // The estimate was one too high.
+        *exponent -= 1;
+    } else {
+        digits.push(digit);
+    }
+    // Scientific notation always has a digit after the point.
+    if !(-3..8).contains(exponent) {
+        low = false;
+        high = false;
+    }
+    while !low && !high {
+        let digit = (b / s) as u8;
+        b = wrap(10 * (b % s));
+        m = wrap(m * 10);
+        // Once M overflows it is certainly past both ends.
+        (low, high) = match m > 0 {
+            true => (b < m, wrap(b + m) > ten_s),
+            false => (true, true),
+        };
+        digits.push(digit);
+    }
+    let last = wrap(wrap(b << 1) - ten_s).cmp(&0);
+    (digits, last, low, high)
+}
+
+/** The digits of B / S as [`fixed_width_digits`] takes them, without overflow. */
+fn natural_digits(
+    mut b: Natural,
+    s: &Natural,
+    mut m: Natural,
+    exponent: &mut i32,
+) -> (Vec<u8>, Ordering, bool, bool) {
+    let ten_s = s.times(10);
+    let is_high = |b: &Natural, m: &Natural| b.plus(m) >= ten_s;
+    let mut digits = vec![];
+    let digit = b.next_digit(s);
+    m = m.times(10);
+    let mut low = b < m;
+    let mut high = is_high(&b, &m);
+    if digit == 0 && !high {
+        *exponent -= 1;
+    } else {
+        digits.push(digit);
+    }
+    if !(-3..8).contains(exponent) {
+        low = false;
+        high = false;
+    }
+    while !low && !high {
+        let digit = b.next_digit(s);
+        m = m.times(10);
+        low = b < m;
+        high = is_high(&b, &m);
+        digits.push(digit);
+    }
+    let last = b.times(2).cmp(&ten_s);
+    (digits, last, low, high)
+}
+
+/// An unsigned integer of any size, as 32 bit words from the least significant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Natural(Vec<u32>);
+
+impl Natural {
+    fn from(value: u64) -> Natural {
+        Natural(vec![value as u32, (value >> 32) as u32]).trimmed()
+    }
+
+    fn to_i128(&self) -> i128 {
+        self.0
+            .iter()
+            .rev()
+            .fold(0, |value, word| value << 32 | *word as i128)
+    }
+
+    fn trimmed(mut self) -> Natural {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+        self
+    }
+
+    fn times(&self, factor: u32) -> Natural {
+        let mut carry = 0u64;
+        let mut words: Vec<u32> = self
+            .0
+            .iter()
+            .map(|word| {
+                let product = *word as u64 * factor as u64 + carry;
+                carry = product >> 32;
+                product as u32
+            })
+            .collect();
+        words.push(carry as u32);
+        Natural(words).trimmed()
+    }
+
+    fn times_pow5(self, power: i32) -> Natural {
+        (0..power).fold(self, |value, _| value.times(5))
+    }
+
+    fn shifted(&self, bits: i32) -> Natural {
+        let mut words = vec![0; bits as usize / 32];
+        let bits = bits as u32 % 32;
+        let mut carry = 0;
+        for word in &self.0 {
+            words.push(word << bits | carry);
+            carry = if bits == 0 { 0 } else { word >> (32 - bits) };
+        }
+        words.push(carry);
+        Natural(words).trimmed()
+    }
+
+    fn plus(&self, other: &Natural) -> Natural {
+        let mut words = vec![];
+        let mut carry = 0u64;
+        for i in 0..self.0.len().max(other.0.len()) {
+            let sum =
+                *self.0.get(i).unwrap_or(&0) as u64 + *other.0.get(i).unwrap_or(&0) as u64 + carry;
+            words.push(sum as u32);
+            carry = sum >> 32;
+        }
+        words.push(carry as u32);
+        Natural(words).trimmed()
+    }
+
+    /// Subtracts a value that is not larger.
+    fn minus(&self, other: &Natural) -> Natural {
+        let mut borrow = 0i64;
+        let words = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let mut difference = *word as i64 - *other.0.get(i).unwrap_or(&0) as i64 - borrow;
+                borrow = (difference < 0) as i64;
+                if difference < 0 {
+                    difference += 1 << 32;
+                }
+                difference as u32
+            })
+            .collect();
+        Natural(words).trimmed()
+    }
+
+    /// Divides by `divisor`, which must go less than ten times, keeping ten times the rest.
+    fn next_digit(&mut self, divisor: &Natural) -> u8 {
+        let mut digit = 0;
+        while *self >= *divisor {
+            *self = self.minus(divisor);
+            digit += 1;
+        }
+        *self = self.times(10);
+        digit
+    }
+}
+
+impl PartialOrd for Natural {
+    fn partial_cmp(&self, other: &Natural) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Natural {
+    fn cmp(&self, other: &Natural) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_java_number_formatting() {
+        assert_eq!(java_double(1234.5), "1234.5");
+        assert_eq!(java_double(1e10), "1.0E10");
+        assert_eq!(java_double(1e-5), "1.0E-5");
+        assert_eq!(java_double(-0.0), "-0.0");
+        assert_eq!(java_double(f64::NEG_INFINITY), "-Infinity");
+        assert_eq!(java_float(1.5), "1.5");
+        assert_eq!(java_float(1e20), "1.0E20");
+        assert_eq!(java_double(f64::MAX), "1.7976931348623157E308");
+        assert_eq!(java_double(f64::from_bits(1)), "4.9E-324");
+        assert_eq!(java_float(0.1), "0.1");
+        assert_eq!(java_float(1073741824.0), "1.07374182E9");
+        assert_eq!(java_float(f32::from_bits(0x6a3bb0e1)), "5.6726103E25");
+    }
+}
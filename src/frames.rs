@@ -0,0 +1,1295 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::access_flags::{ClassAccessFlags, MethodAccessFlags};
+use crate::attributes::{Code, ExceptionTable, StackMapFrame, StackMapTable, VerificationTypeInfo};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool, MethodInfo};
+use crate::class_path::ClassPath;
+use crate::class_writer::ClassWriter;
+use crate::constants::{self, get_raw_class_name, get_raw_utf8, Tags, Utf8};
+use crate::descriptors::{FieldType, MethodDescriptor};
+use crate::errors::class_writing::{WritingCause, WritingError};
+use crate::instructions::*;
+
+/**
+ * Answers the questions about other classes that merging reference types needs, the way
+ * ASM's `ClassWriter.getCommonSuperClass` asks a class loader.\
+ *  Classes are named by their binary names in internal form, such as `java/lang/String`.
+ */
+pub trait ClassHierarchy {
+    /// The direct superclass of `name`, `None` for `java/lang/Object`.
+    fn get_super_class(&self, name: &str) -> Result<Option<String>, Box<dyn Error>>;
+
+    fn is_interface(&self, name: &str) -> Result<bool, Box<dyn Error>>;
+}
+
+impl ClassHierarchy for ClassPath {
+    fn get_super_class(&self, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let class = load(self, name)?;
+        Ok(get_raw_class_name(&class.constant_pool, class.super_class))
+    }
+
+    fn is_interface(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        let class = load(self, name)?;
+        Ok(class.access_flags.contains(&ClassAccessFlags::AccInterface))
+    }
+}
+
+fn load(class_path: &ClassPath, name: &str) -> Result<ClassFile, Box<dyn Error>> {
+    class_path
+        .load_class(name)?
+        .ok_or_else(|| missing_class(name, "the class is not on the class path"))
+}
+
+/**
+ * Recomputes `max_stack`, `max_locals` and the StackMapTable of every method's Code, as
+ * ASM's COMPUTE_FRAMES does.\
+ *  The frames are found by a data flow analysis of each method that merges reference
+ *  types through `hierarchy`, and are written in their most compact encoding. Code that
+ *  cannot be reached is replaced by `nop`s ending in `athrow` and taken out of the
+ *  exception handlers, since no frame would describe it. Before version 50 only the maximums
+ *  are changed. Subroutines (`jsr` and `ret`) are not supported.
+ */
+pub fn compute_frames(
+    class: &mut ClassFile,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), Box<dyn Error>> {
+    let this_class = get_raw_class_name(&class.constant_pool, class.this_class).unwrap_or_default();
+    let mut hierarchy = Hierarchy {
+        oracle: hierarchy,
+        classes: HashMap::new(),
+    };
+    // The class being analyzed may not be where the hierarchy looks yet.
+    hierarchy.classes.insert(
+        this_class.clone(),
+        (
+            get_raw_class_name(&class.constant_pool, class.super_class),
+            class.access_flags.contains(&ClassAccessFlags::AccInterface),
+        ),
+    );
+    let mut class_indices = HashMap::new();
+    for index in 1..class.constant_pool_count {
+        if let Some(name) = get_raw_class_name(&class.constant_pool, index) {
+            class_indices.entry(name).or_insert(index);
+        }
+    }
+    for method in 0..class.methods.len() {
+        let Some(attribute) = class.methods[method]
+            .attributes
+            .iter()
+            .position(|attribute| matches!(attribute, AttributeInfo::Code(_)))
+        else {
+            continue;
+        };
+        let analysis = {
+            let info = &class.methods[method];
+            let AttributeInfo::Code(code) = &info.attributes[attribute] else {
+                unreachable!()
+            };
+            Analyzer::new(&class.constant_pool, &this_class, info, code)?.run(&mut hierarchy)?
+        };
+        let stack_map = if class.major_version >= 50 {
+            let mut entries = vec![];
+            let mut previous = compact(&analysis.initial.locals, true);
+            let mut previous_pc = None;
+            for (pc, frame) in &analysis.frames {
+                let offset_delta = match previous_pc {
+                    Some(previous_pc) => pc - previous_pc - 1,
+                    None => *pc,
+                } as u16;
+                let locals = compact(&frame.locals, true);
+                let stack = compact(&frame.stack, false);
+                let entry = encode_frame(
+                    class,
+                    &mut class_indices,
+                    offset_delta,
+                    &previous,
+                    &locals,
+                    &stack,
+                )?;
+                entries.push(entry);
+                previous = locals;
+                previous_pc = Some(*pc);
+            }
+            let table = if entries.is_empty() {
+                StackMapTable::implicit(class.constant_pool.len() as u16)
+            } else {
+                StackMapTable::from_entries(utf8_index(class, "StackMapTable")?, entries)
+            };
+            Some(AttributeInfo::StackMapTable(table))
+        } else {
+            None
+        };
+
+        let AttributeInfo::Code(code) = &mut class.methods[method].attributes[attribute] else {
+            unreachable!()
+        };
+        code.max_stack = analysis.max_stack;
+        code.max_locals = analysis.max_locals;
+        if let Some(stack_map) = stack_map {
+            code.code_length = analysis.code.len() as u32;
+            code.code = analysis.code;
+            code.exception_table_length = analysis.exception_tables.len() as u16;
+            code.exception_tables = analysis.exception_tables;
+            match code
+                .attributes
+                .iter()
+                .position(|attribute| matches!(attribute, AttributeInfo::StackMapTable(_)))
+            {
+                Some(index) => code.attributes[index] = stack_map,
+                None => code.attributes.push(stack_map),
+            }
+            code.attributes_count = code.attributes.len() as u16;
+        }
+    }
+    // The lengths can only be worked out once every constant the frames need has been added.
+    for method in 0..class.methods.len() {
+        for attribute in 0..class.methods[method].attributes.len() {
+            let attribute_info = &class.methods[method].attributes[attribute];
+            if let AttributeInfo::Code(_) = attribute_info {
+                let length = ClassWriter::new(class)
+                    .write_attribute_body(attribute_info)?
+                    .len() as u32;
+                if let AttributeInfo::Code(code) = &mut class.methods[method].attributes[attribute]
+                {
+                    code.attribute_length = length;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A verification type (§4.10.1.2) as the analysis tracks it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Type {
+    Top,
+    Int,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    /// A class by its internal name, or an array class by its descriptor.
+    Object(String),
+    /// An object created by the `new` at this pc that has not been initialized yet.
+    Uninitialized(u32),
+}
+
+impl Type {
+    fn from_field_type(field_type: &FieldType) -> Type {
+        match field_type {
+            FieldType::Byte
+            | FieldType::Char
+            | FieldType::Short
+            | FieldType::Boolean
+            | FieldType::Int => Type::Int,
+            FieldType::Float => Type::Float,
+            FieldType::Long => Type::Long,
+            FieldType::Double => Type::Double,
+            FieldType::Object(name) => Type::Object(name.clone()),
+            FieldType::Array(_) => Type::Object(field_type.to_descriptor()),
+        }
+    }
+
+    fn is_wide(&self) -> bool {
+        matches!(self, Type::Long | Type::Double)
+    }
+}
+
+/**
+ * The types in the local variables and on the operand stack before an instruction.\
+ *  Like the verifier, a long or double takes two slots, the second of which is top.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Frame {
+    locals: Vec<Type>,
+    stack: Vec<Type>,
+}
+
+impl Frame {
+    fn push(&mut self, value: Type) {
+        let wide = value.is_wide();
+        self.stack.push(value);
+        if wide {
+            self.stack.push(Type::Top);
+        }
+    }
+
+    fn pop(&mut self, slots: usize, pc: u32) -> Result<(), Box<dyn Error>> {
+        if slots > self.stack.len() {
+            return Err(invalid_frame(pc, "the operand stack underflows"));
+        }
+        self.stack.truncate(self.stack.len() - slots);
+        Ok(())
+    }
+
+    fn pop_value(&mut self, pc: u32) -> Result<Type, Box<dyn Error>> {
+        self.stack
+            .pop()
+            .ok_or_else(|| invalid_frame(pc, "the operand stack underflows"))
+    }
+
+    /// Copies the top `count` slots below the `depth` slots under them, as dup_x2 and the
+    /// other dup instructions do.
+    fn dup(&mut self, count: usize, depth: usize, pc: u32) -> Result<(), Box<dyn Error>> {
+        let length = self.stack.len();
+        if count + depth > length {
+            return Err(invalid_frame(pc, "the operand stack underflows"));
+        }
+        let values = self.stack[length - count..].to_vec();
+        let at = length - count - depth;
+        self.stack.splice(at..at, values);
+        Ok(())
+    }
+
+    fn get_local(&self, index: u16, pc: u32) -> Result<Type, Box<dyn Error>> {
+        self.locals
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| invalid_frame(pc, "the local variable is out of range"))
+    }
+
+    fn set_local(&mut self, index: u16, value: Type, pc: u32) -> Result<(), Box<dyn Error>> {
+        let index = index as usize;
+        let size = if value.is_wide() { 2 } else { 1 };
+        if index + size > self.locals.len() {
+            return Err(invalid_frame(pc, "the local variable is out of range"));
+        }
+        // Overwriting the second half of a long or double leaves the first half unusable.
+        if index > 0 && self.locals[index - 1].is_wide() {
+            self.locals[index - 1] = Type::Top;
+        }
+        self.locals[index] = value;
+        if size == 2 {
+            self.locals[index + 1] = Type::Top;
+        }
+        Ok(())
+    }
+
+    /// Replaces every occurrence of an uninitialized type once its constructor has run.
+    fn initialize(&mut self, uninitialized: &Type, initialized: Type) {
+        for value in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if value == uninitialized {
+                *value = initialized.clone();
+            }
+        }
+    }
+}
+
+/// What the analysis of a method found, to be written back to its Code.
+struct Analysis {
+    max_stack: u16,
+    max_locals: u16,
+    /// The frame the method starts with, implied by its descriptor.
+    initial: Frame,
+    /// The frames the StackMapTable must hold, by pc.
+    frames: Vec<(u32, Frame)>,
+    /// The code with its unreachable instructions replaced.
+    code: Vec<u8>,
+    exception_tables: Vec<ExceptionTable>,
+}
+
+/// The types of the other classes merging references has looked up, cached by name.
+struct Hierarchy<'a> {
+    oracle: &'a dyn ClassHierarchy,
+    /// The superclass of each class and whether it is an interface.
+    classes: HashMap<String, (Option<String>, bool)>,
+}
+
+impl Hierarchy<'_> {
+    fn get(&mut self, name: &str) -> Result<&(Option<String>, bool), Box<dyn Error>> {
+        if !self.classes.contains_key(name) {
+            let super_class = self.oracle.get_super_class(name)?;
+            let is_interface = self.oracle.is_interface(name)?;
+            self.classes
+                .insert(name.into(), (super_class, is_interface));
+        }
+        Ok(&self.classes[name])
+    }
+
+    /**
+     * The most specific type both `a` and `b` can be assigned to, as the verifier sees it.\
+     *  Interfaces are treated like `java/lang/Object`, and arrays of references merge by
+     *  their element types.
+     */
+    fn merge(&mut self, a: &str, b: &str) -> Result<String, Box<dyn Error>> {
+        if a == b {
+            return Ok(a.into());
+        }
+        let (a_dimensions, a_element) = split_array(a);
+        let (b_dimensions, b_element) = split_array(b);
+        if a_dimensions == 0 && b_dimensions == 0 {
+            return self.common_super_class(a, b);
+        }
+        let is_reference = |element: &str| element.starts_with('L');
+        if a_dimensions == b_dimensions && is_reference(a_element) && is_reference(b_element) {
+            let element = self.merge(
+                &a_element[1..a_element.len() - 1],
+                &b_element[1..b_element.len() - 1],
+            )?;
+            return Ok(format!("{}L{element};", "[".repeat(a_dimensions)));
+        }
+        // Otherwise the arrays only share being arrays of objects, to as many dimensions
+        // as both have objects as components.
+        let mut dimensions = a_dimensions.min(b_dimensions);
+        if dimensions > 0
+            && ((a_dimensions == dimensions && !is_reference(a_element))
+                || (b_dimensions == dimensions && !is_reference(b_element)))
+        {
+            dimensions -= 1;
+        }
+        Ok(match dimensions {
+            0 => OBJECT.into(),
+            _ => format!("{}L{OBJECT};", "[".repeat(dimensions)),
+        })
+    }
+
+    fn common_super_class(&mut self, a: &str, b: &str) -> Result<String, Box<dyn Error>> {
+        if self.get(a)?.1 || self.get(b)?.1 {
+            return Ok(OBJECT.into());
+        }
+        let mut supers = vec![a.to_string()];
+        while let Some(super_class) = self.get(supers.last().unwrap())?.0.clone() {
+            if supers.contains(&super_class) {
+                return Err(missing_class(
+                    &super_class,
+                    "the class is its own superclass",
+                ));
+            }
+            supers.push(super_class);
+        }
+        let mut class = Some(b.to_string());
+        let mut steps = 0;
+        while let Some(name) = class {
+            if supers.contains(&name) {
+                return Ok(name);
+            }
+            steps += 1;
+            if steps > self.classes.len() {
+                return Err(missing_class(&name, "the class is its own superclass"));
+            }
+            class = self.get(&name)?.0.clone();
+        }
+        Ok(OBJECT.into())
+    }
+}
+
+const OBJECT: &str = "java/lang/Object";
+const THROWABLE: &str = "java/lang/Throwable";
+
+/// The number of dimensions of an array descriptor and its element type.
+fn split_array(name: &str) -> (usize, &str) {
+    let dimensions = name.bytes().take_while(|byte| *byte == b'[').count();
+    (dimensions, &name[dimensions..])
+}
+
+fn merge_types(a: &Type, b: &Type, hierarchy: &mut Hierarchy) -> Result<Type, Box<dyn Error>> {
+    Ok(match (a, b) {
+        _ if a == b => a.clone(),
+        (Type::Null, Type::Object(_)) => b.clone(),
+        (Type::Object(_), Type::Null) => a.clone(),
+        (Type::Object(a), Type::Object(b)) => Type::Object(hierarchy.merge(a, b)?),
+        _ => Type::Top,
+    })
+}
+
+/// The instructions of one method, the frames found for them so far and the work left.
+struct Analyzer<'a> {
+    constant_pool: &'a [ConstantPool],
+    this_class: &'a str,
+    code: &'a Code,
+    instructions: Vec<Instruction>,
+    /// The instruction starting at each pc, `usize::MAX` inside an instruction.
+    indices: Vec<usize>,
+    frames: Vec<Option<Frame>>,
+    worklist: Vec<usize>,
+    initial: Frame,
+    max_locals: u16,
+    max_stack: usize,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(
+        constant_pool: &'a [ConstantPool],
+        this_class: &'a str,
+        method: &MethodInfo,
+        code: &'a Code,
+    ) -> Result<Analyzer<'a>, Box<dyn Error>> {
+        let instructions = decode(&code.code)?;
+        let mut indices = vec![usize::MAX; code.code.len()];
+        for (index, instruction) in instructions.iter().enumerate() {
+            indices[instruction.pc as usize] = index;
+        }
+        let name = get_raw_utf8(constant_pool, method.name_index).unwrap_or_default();
+        let descriptor = MethodDescriptor::from_descriptor(
+            &get_raw_utf8(constant_pool, method.descriptor_index).unwrap_or_default(),
+        )?;
+        let is_static = method.access_flags.contains(&MethodAccessFlags::AccStatic);
+
+        let mut max_locals = descriptor.get_parameter_size() + !is_static as u16;
+        for instruction in &instructions {
+            let end = match instruction.opcode {
+                LLOAD | DLOAD | LSTORE | DSTORE => local_index(instruction) + 2,
+                LLOAD_0..=LLOAD_3
+                | DLOAD_0..=DLOAD_3
+                | LSTORE_0..=LSTORE_3
+                | DSTORE_0..=DSTORE_3 => local_index(instruction) + 2,
+                ILOAD..=ALOAD_3 | ISTORE..=ASTORE_3 | IINC | RET => local_index(instruction) + 1,
+                _ => 0,
+            };
+            max_locals = max_locals.max(end);
+        }
+
+        let mut initial = Frame {
+            locals: vec![Type::Top; max_locals as usize],
+            stack: vec![],
+        };
+        let mut local = 0;
+        if !is_static {
+            initial.locals[0] = if name == "<init>" && this_class != OBJECT {
+                Type::UninitializedThis
+            } else {
+                Type::Object(this_class.into())
+            };
+            local = 1;
+        }
+        for parameter in &descriptor.parameters {
+            initial.set_local(local, Type::from_field_type(parameter), 0)?;
+            local += parameter.get_size();
+        }
+
+        let mut frames = vec![None; instructions.len()];
+        let mut worklist = vec![];
+        if !instructions.is_empty() {
+            frames[0] = Some(initial.clone());
+            worklist.push(0);
+        }
+        Ok(Analyzer {
+            constant_pool,
+            this_class,
+            code,
+            instructions,
+            indices,
+            frames,
+            worklist,
+            initial,
+            max_locals,
+            max_stack: 0,
+        })
+    }
+
+    fn run(mut self, hierarchy: &mut Hierarchy) -> Result<Analysis, Box<dyn Error>> {
+        let code = self.code;
+        while let Some(index) = self.worklist.pop() {
+            let frame = self.frames[index].clone().unwrap();
+            let instruction = self.instructions[index].clone();
+            let pc = instruction.pc;
+            let mut next = frame.clone();
+            self.execute(&mut next, &instruction)?;
+            self.max_stack = self.max_stack.max(frame.stack.len()).max(next.stack.len());
+
+            let changes_locals = next.locals != frame.locals;
+            for handler in &code.exception_tables {
+                if !(handler.start_pc as u32 <= pc && pc < handler.end_pc as u32) {
+                    continue;
+                }
+                let caught = match handler.catch_type {
+                    0 => THROWABLE.into(),
+                    index => get_raw_class_name(self.constant_pool, index)
+                        .ok_or_else(|| invalid_frame(pc, "the catch type is not a class"))?,
+                };
+                let target = self.index_of(handler.handler_pc as u32, pc)?;
+                let mut locals = vec![frame.locals.clone()];
+                // Stores are checked against the handlers both before and after they change
+                // the locals.
+                if changes_locals {
+                    locals.push(next.locals.clone());
+                }
+                for locals in locals {
+                    let handler_frame = Frame {
+                        locals,
+                        stack: vec![Type::Object(caught.clone())],
+                    };
+                    self.merge_into(target, handler_frame, hierarchy)?;
+                }
+            }
+
+            let (targets, falls_through) = successors(&instruction);
+            for target in targets {
+                let target = self.index_of(target, pc)?;
+                self.merge_into(target, next.clone(), hierarchy)?;
+            }
+            if falls_through {
+                if index + 1 == self.instructions.len() {
+                    return Err(invalid_frame(pc, "execution falls off the end of the code"));
+                }
+                self.merge_into(index + 1, next, hierarchy)?;
+            }
+        }
+        Ok(self.finish())
+    }
+
+    fn index_of(&self, target: u32, pc: u32) -> Result<usize, Box<dyn Error>> {
+        match self.indices.get(target as usize) {
+            Some(index) if *index != usize::MAX => Ok(*index),
+            _ => Err(invalid_frame(
+                pc,
+                "the target is not the start of an instruction",
+            )),
+        }
+    }
+
+    fn merge_into(
+        &mut self,
+        index: usize,
+        frame: Frame,
+        hierarchy: &mut Hierarchy,
+    ) -> Result<(), Box<dyn Error>> {
+        let Some(old) = &self.frames[index] else {
+            self.frames[index] = Some(frame);
+            self.worklist.push(index);
+            return Ok(());
+        };
+        if old.stack.len() != frame.stack.len() {
+            return Err(invalid_frame(
+                self.instructions[index].pc,
+                "the operand stack heights differ between paths",
+            ));
+        }
+        let mut merged = Frame {
+            locals: Vec::with_capacity(old.locals.len()),
+            stack: Vec::with_capacity(old.stack.len()),
+        };
+        for (a, b) in old.locals.iter().zip(&frame.locals) {
+            merged.locals.push(merge_types(a, b, hierarchy)?);
+        }
+        for (a, b) in old.stack.iter().zip(&frame.stack) {
+            merged.stack.push(merge_types(a, b, hierarchy)?);
+        }
+        if &merged != old {
+            self.frames[index] = Some(merged);
+            self.worklist.push(index);
+        }
+        Ok(())
+    }
+
+    /// Finds where frames are needed and replaces the code nothing reaches.
+    fn finish(self) -> Analysis {
+        let mut code = self.code.code.clone();
+        let mut unreachable = vec![];
+        let mut needs_frame = vec![false; self.instructions.len()];
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            let reachable = self.frames[index].is_some();
+            if !reachable {
+                let end = self
+                    .instructions
+                    .get(index + 1)
+                    .map_or(code.len() as u32, |next| next.pc);
+                match unreachable.last_mut() {
+                    Some((_, last_end)) if *last_end == instruction.pc => *last_end = end,
+                    _ => {
+                        unreachable.push((instruction.pc, end));
+                        needs_frame[index] = true;
+                    }
+                }
+                continue;
+            }
+            if let Some(previous) = index.checked_sub(1) {
+                let (_, falls_through) = successors(&self.instructions[previous]);
+                if !falls_through || self.frames[previous].is_none() {
+                    needs_frame[index] = true;
+                }
+            }
+            for target in successors(instruction).0 {
+                needs_frame[self.indices[target as usize]] = true;
+            }
+        }
+        for (start, end) in &unreachable {
+            code[*start as usize..*end as usize - 1].fill(NOP);
+            code[*end as usize - 1] = ATHROW;
+        }
+
+        // The replaced code throws without a frame that would match any handler.
+        let mut exception_tables = vec![];
+        for handler in &self.code.exception_tables {
+            let mut ranges = vec![(handler.start_pc as u32, handler.end_pc as u32)];
+            for (start, end) in &unreachable {
+                ranges = ranges
+                    .into_iter()
+                    .flat_map(|(from, to)| [(from, to.min(*start)), (from.max(*end), to)])
+                    .filter(|(from, to)| from < to)
+                    .collect();
+            }
+            for (start_pc, end_pc) in ranges {
+                exception_tables.push(ExceptionTable::new(
+                    start_pc as u16,
+                    end_pc as u16,
+                    handler.handler_pc,
+                    handler.catch_type,
+                ));
+                let index = self.indices[handler.handler_pc as usize];
+                if self.frames[index].is_some() {
+                    needs_frame[index] = true;
+                }
+            }
+        }
+
+        let mut frames = vec![];
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            if !needs_frame[index] {
+                continue;
+            }
+            let frame = self.frames[index].clone().unwrap_or_else(|| Frame {
+                locals: vec![],
+                stack: vec![Type::Object(THROWABLE.into())],
+            });
+            frames.push((instruction.pc, frame));
+        }
+        let mut max_stack = self.max_stack;
+        if !unreachable.is_empty() {
+            max_stack = max_stack.max(1);
+        }
+        Analysis {
+            max_stack: max_stack as u16,
+            max_locals: self.max_locals,
+            initial: self.initial,
+            frames,
+            code,
+            exception_tables,
+        }
+    }
+
+    /// Applies `instruction` to `frame`, leaving the frame after it.
+    fn execute(&self, frame: &mut Frame, instruction: &Instruction) -> Result<(), Box<dyn Error>> {
+        let pc = instruction.pc;
+        match instruction.opcode {
+            NOP | GOTO | GOTO_W | RETURN | IINC => {}
+            ACONST_NULL => frame.push(Type::Null),
+            ICONST_M1..=ICONST_5 | BIPUSH | SIPUSH => frame.push(Type::Int),
+            LCONST_0 | LCONST_1 => frame.push(Type::Long),
+            FCONST_0..=FCONST_2 => frame.push(Type::Float),
+            DCONST_0 | DCONST_1 => frame.push(Type::Double),
+            LDC | LDC_W | LDC2_W => {
+                let value = self.constant_type(constant_index(instruction), pc)?;
+                frame.push(value);
+            }
+            ILOAD | ILOAD_0..=ILOAD_3 => frame.push(Type::Int),
+            LLOAD | LLOAD_0..=LLOAD_3 => frame.push(Type::Long),
+            FLOAD | FLOAD_0..=FLOAD_3 => frame.push(Type::Float),
+            DLOAD | DLOAD_0..=DLOAD_3 => frame.push(Type::Double),
+            ALOAD | ALOAD_0..=ALOAD_3 => {
+                let value = frame.get_local(local_index(instruction), pc)?;
+                frame.push(value);
+            }
+            IALOAD | BALOAD | CALOAD | SALOAD => pop_push(frame, 2, Type::Int, pc)?,
+            LALOAD => pop_push(frame, 2, Type::Long, pc)?,
+            FALOAD => pop_push(frame, 2, Type::Float, pc)?,
+            DALOAD => pop_push(frame, 2, Type::Double, pc)?,
+            AALOAD => {
+                frame.pop(1, pc)?;
+                let element = match frame.pop_value(pc)? {
+                    Type::Object(array) if array.starts_with('[') => match &array[1..] {
+                        element if element.starts_with('L') => {
+                            Type::Object(element[1..element.len() - 1].into())
+                        }
+                        element if element.starts_with('[') => Type::Object(element.into()),
+                        _ => return Err(invalid_frame(pc, "aaload of a primitive array")),
+                    },
+                    Type::Null => Type::Null,
+                    _ => return Err(invalid_frame(pc, "aaload of a value that is not an array")),
+                };
+                frame.push(element);
+            }
+            ISTORE | ISTORE_0..=ISTORE_3 => store(frame, instruction, Type::Int, 1)?,
+            LSTORE | LSTORE_0..=LSTORE_3 => store(frame, instruction, Type::Long, 2)?,
+            FSTORE | FSTORE_0..=FSTORE_3 => store(frame, instruction, Type::Float, 1)?,
+            DSTORE | DSTORE_0..=DSTORE_3 => store(frame, instruction, Type::Double, 2)?,
+            ASTORE | ASTORE_0..=ASTORE_3 => {
+                let value = frame.pop_value(pc)?;
+                frame.set_local(local_index(instruction), value, pc)?;
+            }
+            IASTORE | FASTORE | AASTORE | BASTORE | CASTORE | SASTORE => frame.pop(3, pc)?,
+            LASTORE | DASTORE => frame.pop(4, pc)?,
+            POP => frame.pop(1, pc)?,
+            POP2 => frame.pop(2, pc)?,
+            DUP => frame.dup(1, 0, pc)?,
+            DUP_X1 => frame.dup(1, 1, pc)?,
+            DUP_X2 => frame.dup(1, 2, pc)?,
+            DUP2 => frame.dup(2, 0, pc)?,
+            DUP2_X1 => frame.dup(2, 1, pc)?,
+            DUP2_X2 => frame.dup(2, 2, pc)?,
+            SWAP => {
+                let first = frame.pop_value(pc)?;
+                let second = frame.pop_value(pc)?;
+                frame.stack.push(first);
+                frame.stack.push(second);
+            }
+            IADD | ISUB | IMUL | IDIV | IREM | ISHL | ISHR | IUSHR | IAND | IOR | IXOR => {
+                pop_push(frame, 2, Type::Int, pc)?
+            }
+            LADD | LSUB | LMUL | LDIV | LREM | LAND | LOR | LXOR => {
+                pop_push(frame, 4, Type::Long, pc)?
+            }
+            LSHL | LSHR | LUSHR => pop_push(frame, 3, Type::Long, pc)?,
+            FADD | FSUB | FMUL | FDIV | FREM => pop_push(frame, 2, Type::Float, pc)?,
+            DADD | DSUB | DMUL | DDIV | DREM => pop_push(frame, 4, Type::Double, pc)?,
+            INEG | I2B | I2C | I2S => pop_push(frame, 1, Type::Int, pc)?,
+            LNEG => pop_push(frame, 2, Type::Long, pc)?,
+            FNEG => pop_push(frame, 1, Type::Float, pc)?,
+            DNEG => pop_push(frame, 2, Type::Double, pc)?,
+            I2L => pop_push(frame, 1, Type::Long, pc)?,
+            I2F => pop_push(frame, 1, Type::Float, pc)?,
+            I2D => pop_push(frame, 1, Type::Double, pc)?,
+            L2I => pop_push(frame, 2, Type::Int, pc)?,
+            L2F => pop_push(frame, 2, Type::Float, pc)?,
+            L2D => pop_push(frame, 2, Type::Double, pc)?,
+            F2I => pop_push(frame, 1, Type::Int, pc)?,
+            F2L => pop_push(frame, 1, Type::Long, pc)?,
+            F2D => pop_push(frame, 1, Type::Double, pc)?,
+            D2I => pop_push(frame, 2, Type::Int, pc)?,
+            D2L => pop_push(frame, 2, Type::Long, pc)?,
+            D2F => pop_push(frame, 2, Type::Float, pc)?,
+            LCMP | DCMPL | DCMPG => pop_push(frame, 4, Type::Int, pc)?,
+            FCMPL | FCMPG => pop_push(frame, 2, Type::Int, pc)?,
+            IFEQ..=IFLE | IFNULL | IFNONNULL | TABLESWITCH | LOOKUPSWITCH => frame.pop(1, pc)?,
+            IF_ICMPEQ..=IF_ACMPNE => frame.pop(2, pc)?,
+            IRETURN | FRETURN | ARETURN | ATHROW | MONITORENTER | MONITOREXIT => {
+                frame.pop(1, pc)?
+            }
+            LRETURN | DRETURN => frame.pop(2, pc)?,
+            JSR | JSR_W | RET => {
+                return Err(invalid_frame(pc, "subroutines are not supported"));
+            }
+            GETSTATIC | PUTSTATIC | GETFIELD | PUTFIELD => {
+                let (_, _, descriptor) = self.member(constant_index(instruction), pc)?;
+                let field_type = FieldType::from_descriptor(&descriptor)?;
+                match instruction.opcode {
+                    GETSTATIC => {}
+                    PUTSTATIC => frame.pop(field_type.get_size() as usize, pc)?,
+                    GETFIELD => frame.pop(1, pc)?,
+                    _ => frame.pop(field_type.get_size() as usize + 1, pc)?,
+                }
+                if matches!(instruction.opcode, GETSTATIC | GETFIELD) {
+                    frame.push(Type::from_field_type(&field_type));
+                }
+            }
+            INVOKEVIRTUAL..=INVOKEDYNAMIC => {
+                let (owner, name, descriptor) = self.member(constant_index(instruction), pc)?;
+                let descriptor = MethodDescriptor::from_descriptor(&descriptor)?;
+                frame.pop(descriptor.get_parameter_size() as usize, pc)?;
+                if !matches!(instruction.opcode, INVOKESTATIC | INVOKEDYNAMIC) {
+                    let receiver = frame.pop_value(pc)?;
+                    if instruction.opcode == INVOKESPECIAL && name == "<init>" {
+                        let initialized = match receiver {
+                            Type::UninitializedThis => Type::Object(self.this_class.into()),
+                            Type::Uninitialized(_) => Type::Object(owner),
+                            _ => {
+                                return Err(invalid_frame(
+                                    pc,
+                                    "<init> is called on an initialized object",
+                                ))
+                            }
+                        };
+                        frame.initialize(&receiver, initialized);
+                    }
+                }
+                if let Some(return_type) = &descriptor.return_type {
+                    frame.push(Type::from_field_type(return_type));
+                }
+            }
+            NEW => frame.push(Type::Uninitialized(pc)),
+            NEWARRAY => {
+                let Operands::NewArray(atype) = instruction.operands else {
+                    unreachable!()
+                };
+                let element = match atype {
+                    4 => 'Z',
+                    5 => 'C',
+                    6 => 'F',
+                    7 => 'D',
+                    8 => 'B',
+                    9 => 'S',
+                    10 => 'I',
+                    11 => 'J',
+                    _ => return Err(invalid_frame(pc, "newarray has an invalid atype")),
+                };
+                pop_push(frame, 1, Type::Object(format!("[{element}")), pc)?;
+            }
+            ANEWARRAY => {
+                let element = self.class(constant_index(instruction), pc)?;
+                let array = match element.starts_with('[') {
+                    true => format!("[{element}"),
+                    false => format!("[L{element};"),
+                };
+                pop_push(frame, 1, Type::Object(array), pc)?;
+            }
+            ARRAYLENGTH | INSTANCEOF => pop_push(frame, 1, Type::Int, pc)?,
+            CHECKCAST => {
+                let class = self.class(constant_index(instruction), pc)?;
+                pop_push(frame, 1, Type::Object(class), pc)?;
+            }
+            MULTIANEWARRAY => {
+                let Operands::MultiANewArray { index, dimensions } = instruction.operands else {
+                    unreachable!()
+                };
+                let class = self.class(index, pc)?;
+                pop_push(frame, dimensions as usize, Type::Object(class), pc)?;
+            }
+            opcode => {
+                return Err(invalid_frame(pc, &format!("invalid opcode {opcode:#04x}")));
+            }
+        }
+        Ok(())
+    }
+
+    /// The type ldc pushes for the constant at `index`.
+    fn constant_type(&self, index: u16, pc: u32) -> Result<Type, Box<dyn Error>> {
+        Ok(match self.constant_pool.get(index as usize) {
+            Some(ConstantPool::Integer(_)) => Type::Int,
+            Some(ConstantPool::Float(_)) => Type::Float,
+            Some(ConstantPool::Long(_)) => Type::Long,
+            Some(ConstantPool::Double(_)) => Type::Double,
+            Some(ConstantPool::String(_)) => Type::Object("java/lang/String".into()),
+            Some(ConstantPool::Class(_)) => Type::Object("java/lang/Class".into()),
+            Some(ConstantPool::MethodType(_)) => Type::Object("java/lang/invoke/MethodType".into()),
+            Some(ConstantPool::MethodHandle(_)) => {
+                Type::Object("java/lang/invoke/MethodHandle".into())
+            }
+            Some(ConstantPool::Dynamic(_)) => {
+                let (_, _, descriptor) = self.member(index, pc)?;
+                Type::from_field_type(&FieldType::from_descriptor(&descriptor)?)
+            }
+            _ => return Err(invalid_frame(pc, "ldc of a constant that is not loadable")),
+        })
+    }
+
+    /// The class, name and descriptor of a field, method or call site reference.
+    fn member(&self, index: u16, pc: u32) -> Result<(String, String, String), Box<dyn Error>> {
+        let (class_index, name_and_type_index) = match self.constant_pool.get(index as usize) {
+            Some(ConstantPool::Fieldref(field)) => (field.class_index, field.name_and_type_index),
+            Some(ConstantPool::Methodref(method)) => {
+                (method.class_index, method.name_and_type_index)
+            }
+            Some(ConstantPool::InterfaceMethodRef(method)) => {
+                (method.class_index, method.name_and_type_index)
+            }
+            Some(ConstantPool::InvokeDynamic(call_site)) => (0, call_site.name_and_type_index),
+            Some(ConstantPool::Dynamic(dynamic)) => (0, dynamic.name_and_type_index),
+            _ => return Err(invalid_frame(pc, "the operand is not a member reference")),
+        };
+        let Some(ConstantPool::NameAndType(name_and_type)) =
+            self.constant_pool.get(name_and_type_index as usize)
+        else {
+            return Err(invalid_frame(pc, "the member has no name and type"));
+        };
+        Ok((
+            get_raw_class_name(self.constant_pool, class_index).unwrap_or_default(),
+            get_raw_utf8(self.constant_pool, name_and_type.name_index).unwrap_or_default(),
+            get_raw_utf8(self.constant_pool, name_and_type.descriptor_index).unwrap_or_default(),
+        ))
+    }
+
+    fn class(&self, index: u16, pc: u32) -> Result<String, Box<dyn Error>> {
+        get_raw_class_name(self.constant_pool, index)
+            .ok_or_else(|| invalid_frame(pc, "the operand is not a class"))
+    }
+}
+
+fn pop_push(frame: &mut Frame, slots: usize, value: Type, pc: u32) -> Result<(), Box<dyn Error>> {
+    frame.pop(slots, pc)?;
+    frame.push(value);
+    Ok(())
+}
+
+fn store(
+    frame: &mut Frame,
+    instruction: &Instruction,
+    value: Type,
+    slots: usize,
+) -> Result<(), Box<dyn Error>> {
+    frame.pop(slots, instruction.pc)?;
+    frame.set_local(local_index(instruction), value, instruction.pc)
+}
+
+/// The local variable a load, store, iinc or ret uses, including the `_0` to `_3` forms.
+fn local_index(instruction: &Instruction) -> u16 {
+    match (instruction.opcode, &instruction.operands) {
+        (ILOAD_0..=ALOAD_3, _) => ((instruction.opcode - ILOAD_0) % 4) as u16,
+        (ISTORE_0..=ASTORE_3, _) => ((instruction.opcode - ISTORE_0) % 4) as u16,
+        (_, Operands::Local(index)) => *index,
+        (_, Operands::Increment { index, .. }) => *index,
+        _ => 0,
+    }
+}
+
+fn constant_index(instruction: &Instruction) -> u16 {
+    match instruction.operands {
+        Operands::Constant(index)
+        | Operands::InvokeDynamic(index)
+        | Operands::InvokeInterface { index, .. } => index,
+        _ => 0,
+    }
+}
+
+/// The branch targets of `instruction` and whether execution can continue after it.
+fn successors(instruction: &Instruction) -> (Vec<u32>, bool) {
+    let falls_through = !matches!(
+        instruction.opcode,
+        GOTO | GOTO_W | TABLESWITCH | LOOKUPSWITCH | IRETURN..=RETURN | ATHROW
+    );
+    let targets = match &instruction.operands {
+        Operands::Branch(target) => vec![*target],
+        Operands::TableSwitch {
+            default, targets, ..
+        } => [*default]
+            .into_iter()
+            .chain(targets.iter().copied())
+            .collect(),
+        Operands::LookupSwitch { default, pairs } => [*default]
+            .into_iter()
+            .chain(pairs.iter().map(|(_, target)| *target))
+            .collect(),
+        _ => vec![],
+    };
+    (targets, falls_through)
+}
+
+/**
+ * The types of a frame as a StackMapTable lists them: a long or double is one entry
+ * covering both of its slots, and locals end at the last one that is not top.
+ */
+fn compact(slots: &[Type], trim: bool) -> Vec<Type> {
+    let mut types = vec![];
+    let mut index = 0;
+    while index < slots.len() {
+        types.push(slots[index].clone());
+        index += if slots[index].is_wide() { 2 } else { 1 };
+    }
+    if trim {
+        while types.last() == Some(&Type::Top) {
+            types.pop();
+        }
+    }
+    types
+}
+
+/// Picks the smallest encoding of a frame given the locals of the frame before it.
+fn encode_frame(
+    class: &mut ClassFile,
+    class_indices: &mut HashMap<String, u16>,
+    offset_delta: u16,
+    previous: &[Type],
+    locals: &[Type],
+    stack: &[Type],
+) -> Result<StackMapFrame, Box<dyn Error>> {
+    let mut verification_types = |types: &[Type]| -> Result<Vec<_>, Box<dyn Error>> {
+        types
+            .iter()
+            .map(|value| verification_type(class, class_indices, value))
+            .collect()
+    };
+    let same_locals = locals == previous;
+    Ok(if same_locals && stack.is_empty() {
+        match offset_delta {
+            0..=63 => StackMapFrame::SameFrame {
+                frame_type: offset_delta as u8,
+            },
+            _ => StackMapFrame::SameFrameExtended {
+                frame_type: 251,
+                offset_delta,
+            },
+        }
+    } else if same_locals && stack.len() == 1 {
+        let stack = verification_types(stack)?;
+        match offset_delta {
+            0..=63 => StackMapFrame::SameLocals1StackItemFrame {
+                frame_type: 64 + offset_delta as u8,
+                stack,
+            },
+            _ => StackMapFrame::SameLocals1StackItemFrameExtended {
+                frame_type: 247,
+                offset_delta,
+                stack,
+            },
+        }
+    } else if stack.is_empty()
+        && locals.len() > previous.len()
+        && locals.len() - previous.len() <= 3
+        && locals.starts_with(previous)
+    {
+        StackMapFrame::AppendFrame {
+            frame_type: (251 + locals.len() - previous.len()) as u8,
+            offset_delta,
+            locals: verification_types(&locals[previous.len()..])?,
+        }
+    } else if stack.is_empty()
+        && locals.len() < previous.len()
+        && previous.len() - locals.len() <= 3
+        && previous.starts_with(locals)
+    {
+        StackMapFrame::ChopFrame {
+            frame_type: (251 - (previous.len() - locals.len())) as u8,
+            offset_delta,
+        }
+    } else {
+        StackMapFrame::FullFrame {
+            frame_type: 255,
+            offset_delta,
+            number_of_locals: locals.len() as u16,
+            locals: verification_types(locals)?,
+            number_of_stack_items: stack.len() as u16,
+            stack: verification_types(stack)?,
+        }
+    })
+}
+
+fn verification_type(
+    class: &mut ClassFile,
+    class_indices: &mut HashMap<String, u16>,
+    value: &Type,
+) -> Result<VerificationTypeInfo, Box<dyn Error>> {
+    Ok(match value {
+        Type::Top => VerificationTypeInfo::TopVariable,
+        Type::Int => VerificationTypeInfo::IntegerVariable,
+        Type::Float => VerificationTypeInfo::FloatVariable,
+        Type::Long => VerificationTypeInfo::LongVariable,
+        Type::Double => VerificationTypeInfo::DoubleVariable,
+        Type::Null => VerificationTypeInfo::NullVariable,
+        Type::UninitializedThis => VerificationTypeInfo::UnitializedThisVariable,
+        Type::Uninitialized(pc) => VerificationTypeInfo::UnintializedVariable(*pc as u16),
+        Type::Object(name) => {
+            let index = match class_indices.get(name) {
+                Some(index) => *index,
+                None => {
+                    let name_index = utf8_index(class, name)?;
+                    let index = class.add_constant(ConstantPool::Class(constants::Class::new(
+                        Tags::Class,
+                        name_index,
+                    )))?;
+                    class_indices.insert(name.clone(), index);
+                    index
+                }
+            };
+            VerificationTypeInfo::ObjectVariable(index)
+        }
+    })
+}
+
+/// The index of a Utf8 entry holding `value`, added to the constant pool if there is none.
+fn utf8_index(class: &mut ClassFile, value: &str) -> Result<u16, Box<dyn Error>> {
+    for index in 1..class.constant_pool_count {
+        if get_raw_utf8(&class.constant_pool, index).as_deref() == Some(value) {
+            return Ok(index);
+        }
+    }
+    class.add_constant(ConstantPool::Utf8(Utf8::from(value)))
+}
+
+fn invalid_frame(pc: u32, msg: &str) -> Box<dyn Error> {
+    Box::new(WritingError::new(WritingCause::InvalidFrame(pc), msg))
+}
+
+fn missing_class(name: &str, msg: &str) -> Box<dyn Error> {
+    Box::new(WritingError::new(
+        WritingCause::MissingClass(name.into()),
+        msg,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_builder::tests::{single_method, sum_loop};
+    use crate::class_builder::{CodeBuilder, Insn};
+
+    /// A hierarchy of the classes the tests use, by their superclass and whether they are
+    /// interfaces.
+    struct Classes(HashMap<&'static str, (Option<&'static str>, bool)>);
+
+    impl ClassHierarchy for Classes {
+        fn get_super_class(&self, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+            match self.0.get(name) {
+                Some((super_class, _)) => Ok(super_class.map(String::from)),
+                None => Err(missing_class(name, "not in the test hierarchy")),
+            }
+        }
+
+        fn is_interface(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+            match self.0.get(name) {
+                Some((_, is_interface)) => Ok(*is_interface),
+                None => Err(missing_class(name, "not in the test hierarchy")),
+            }
+        }
+    }
+
+    fn classes() -> Classes {
+        Classes(HashMap::from([
+            (OBJECT, (None, false)),
+            (THROWABLE, (Some(OBJECT), false)),
+            ("test/Base", (Some(OBJECT), false)),
+            ("test/A", (Some("test/Base"), false)),
+            ("test/B", (Some("test/Base"), false)),
+            ("test/Named", (None, true)),
+        ]))
+    }
+
+    /// Builds a version 52 class with one static method and computes its frames.
+    fn compute(descriptor: &str, code: CodeBuilder) -> Result<ClassFile, Box<dyn Error>> {
+        let mut class = single_method("test/Frames", descriptor, code);
+        class.set_version(52, 0);
+        let mut class = class.build()?;
+        compute_frames(&mut class, &classes())?;
+        // What is computed must survive being written and read back.
+        ClassFile::from_bytes(&class.to_bytes()?)
+    }
+
+    fn code_of(class: &ClassFile) -> &Code {
+        class.methods[0]
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Code(code) => Some(code),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn stack_map(class: &ClassFile) -> Vec<StackMapFrame> {
+        code_of(class)
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::StackMapTable(table) => Some(table.entries.clone()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn object(class: &ClassFile, name: &str) -> VerificationTypeInfo {
+        let index = (1..class.constant_pool_count)
+            .find(|index| get_raw_class_name(&class.constant_pool, *index).as_deref() == Some(name))
+            .unwrap();
+        VerificationTypeInfo::ObjectVariable(index)
+    }
+
+    #[test]
+    fn test_frames_for_loops() {
+        let class = compute("(I)I", sum_loop()).unwrap();
+
+        assert_eq!(
+            (code_of(&class).max_stack, code_of(&class).max_locals),
+            (2, 3)
+        );
+        let frames = stack_map(&class);
+        assert_eq!(frames.len(), 2);
+        assert!(matches!(
+            &frames[0],
+            StackMapFrame::AppendFrame { frame_type: 253, offset_delta: 6, locals }
+                if locals == &[VerificationTypeInfo::IntegerVariable, VerificationTypeInfo::IntegerVariable]
+        ));
+        // The exit is at 26, 19 past the frame before it.
+        assert!(matches!(
+            frames[1],
+            StackMapFrame::SameFrame { frame_type: 19 }
+        ));
+    }
+
+    #[test]
+    fn test_merge_references_to_common_superclass() {
+        // static Base run(boolean b, A a, B other) { return b ? a : other; }
+        let mut code = CodeBuilder::new(0, 0);
+        let (other, join) = (code.new_label(), code.new_label());
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::Jump(IFEQ, other));
+        code.push(Insn::Local(ALOAD, 1));
+        code.push(Insn::Jump(GOTO, join));
+        code.push(Insn::Label(other));
+        code.push(Insn::Local(ALOAD, 2));
+        code.push(Insn::Label(join));
+        code.push(Insn::Simple(ARETURN));
+        let class = compute("(ZLtest/A;Ltest/B;)Ltest/Base;", code).unwrap();
+
+        let frames = stack_map(&class);
+        assert!(matches!(
+            frames[0],
+            StackMapFrame::SameFrame { frame_type: 10 }
+        ));
+        assert!(matches!(
+            &frames[1],
+            StackMapFrame::SameLocals1StackItemFrame { frame_type: 65, stack }
+                if stack == &[object(&class, "test/Base")]
+        ));
+
+        let classes = classes();
+        let mut hierarchy = Hierarchy {
+            oracle: &classes,
+            classes: HashMap::new(),
+        };
+        let mut merge = |a: &str, b: &str| hierarchy.merge(a, b).unwrap();
+        assert_eq!(merge("[[Ltest/A;", "[[Ltest/B;"), "[[Ltest/Base;");
+        assert_eq!(merge("[Ltest/A;", "[[Ltest/B;"), "[Ljava/lang/Object;");
+        assert_eq!(merge("[[I", "[Ljava/lang/String;"), "[Ljava/lang/Object;");
+        assert_eq!(merge("[[I", "[[J"), "[Ljava/lang/Object;");
+        assert_eq!(merge("[[I", "[J"), OBJECT);
+        assert_eq!(merge("[I", "[J"), OBJECT);
+        assert_eq!(merge("[I", "test/A"), OBJECT);
+        assert_eq!(merge("test/A", "test/Named"), OBJECT);
+    }
+
+    #[test]
+    fn test_replace_unreachable_code() {
+        // static void run(int i) { try { return; } catch (Throwable t) { return; } }, with dead code after the first
+        // return that is still covered by the handler.
+        let mut code = CodeBuilder::new(0, 0);
+        let (start, end, handler) = (code.new_label(), code.new_label(), code.new_label());
+        code.push(Insn::Label(start));
+        code.push(Insn::Simple(RETURN));
+        code.push(Insn::Simple(ICONST_0));
+        code.push(Insn::Simple(POP));
+        code.push(Insn::Label(end));
+        code.push(Insn::Label(handler));
+        code.push(Insn::Local(ASTORE, 1));
+        code.push(Insn::Simple(RETURN));
+        code.add_exception_handler(start, end, handler, None);
+        let class = compute("(I)V", code).unwrap();
+
+        let code = code_of(&class);
+        assert_eq!(code.code[..3], [RETURN, NOP, ATHROW]);
+        assert_eq!((code.max_stack, code.max_locals), (1, 2));
+        let range: Vec<_> = code
+            .exception_tables
+            .iter()
+            .map(|handler| (handler.start_pc, handler.end_pc, handler.handler_pc))
+            .collect();
+        assert_eq!(range, [(0, 1, 3)]);
+        let throwable = object(&class, THROWABLE);
+        let frames = stack_map(&class);
+        assert!(matches!(
+            &frames[0],
+            StackMapFrame::FullFrame { offset_delta: 1, locals, stack, .. }
+                if locals.is_empty() && stack == std::slice::from_ref(&throwable)
+        ));
+        assert!(matches!(
+            &frames[1],
+            StackMapFrame::FullFrame { offset_delta: 1, locals, stack, .. }
+                if locals == &[VerificationTypeInfo::IntegerVariable] && stack == &[throwable]
+        ));
+    }
+
+    #[test]
+    fn test_reject_inconsistent_stacks() {
+        // The join is reached with one int on the stack and with none.
+        let mut code = CodeBuilder::new(0, 0);
+        let join = code.new_label();
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::Jump(IFEQ, join));
+        code.push(Insn::Simple(ICONST_1));
+        code.push(Insn::Label(join));
+        code.push(Insn::Simple(IRETURN));
+        let Err(error) = compute("(I)I", code) else {
+            panic!("the stack heights differ");
+        };
+        assert!(error.to_string().contains("InvalidFrame: pc 6"), "{error}");
+    }
+}
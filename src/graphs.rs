@@ -0,0 +1,422 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::Write;
+
+use crate::access_flags::ClassAccessFlags;
+use crate::cfg::{ControlFlowGraph, EdgeKind};
+use crate::class_file::{AttributeInfo, ClassFile};
+use crate::javap::{Javap, JavapOptions};
+
+/// How the `--dot` and `--json` options print a graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// [The DOT Language](https://graphviz.org/doc/info/lang.html)
+    Dot,
+    /// One JSON object per line, as [JSON Lines](https://jsonlines.org/).
+    Json,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    /// Unique within its graph.
+    pub(crate) id: String,
+    pub(crate) label: String,
+    /// What the node stands for: `block`, `class`, `interface`, or `external` for a class
+    /// that is referred to but not among those drawn. Dependency graphs also have `package`
+    /// and `module` nodes, and `internal` and `missing` ones for what should not be used.
+    pub(crate) kind: &'static str,
+    /// Text drawn under the label, such as the instructions of a basic block.
+    pub(crate) lines: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub(crate) from: String,
+    pub(crate) to: String,
+    /// `fallthrough`, `jump`, `switch` or `exception` between blocks, `extends` or
+    /// `implements` between classes, and `uses` between dependencies.
+    pub(crate) kind: &'static str,
+    /// The key of a switch case or the class an exception handler catches.
+    pub(crate) label: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Graph {
+    pub(crate) name: String,
+    pub(crate) nodes: Vec<Node>,
+    pub(crate) edges: Vec<Edge>,
+}
+
+/**
+ * The control flow graph of each method with code, named by the method's name and
+ * descriptor. Each basic block lists its instructions as `javap -c` prints them.
+ */
+pub fn control_flow_graphs(class: &ClassFile) -> Result<Vec<Graph>, Box<dyn Error>> {
+    let options = JavapOptions::default();
+    let javap = Javap::new(class, &options);
+    let mut graphs = vec![];
+    for method in &class.methods {
+        let Some(code) = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Code(code) => Some(code),
+                _ => None,
+            })
+        else {
+            continue;
+        };
+        let cfg = ControlFlowGraph::new(code, &class.constant_pool)?;
+        let mut graph = Graph {
+            name: format!(
+                "{}{}",
+                method.get_name(&class.constant_pool),
+                method.get_descriptor(&class.constant_pool)
+            ),
+            ..Default::default()
+        };
+        for (index, block) in cfg.get_blocks().iter().enumerate() {
+            let lines = cfg
+                .get_instructions(index)
+                .iter()
+                .flat_map(|instruction| {
+                    let text = javap.format_instruction(instruction);
+                    // javap pads the mnemonic even when no operands follow.
+                    text.lines()
+                        .map(|line| line.trim_end().to_string())
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            graph.nodes.push(Node {
+                id: format!("B{index}"),
+                label: format!("B{index} [{}, {})", block.start_pc, block.end_pc),
+                kind: "block",
+                lines,
+            });
+            for edge in &block.successors {
+                let (kind, label) = match &edge.kind {
+                    EdgeKind::FallThrough => ("fallthrough", None),
+                    EdgeKind::Jump => ("jump", None),
+                    EdgeKind::Switch(key) => (
+                        "switch",
+                        Some(key.map_or("default".into(), |key| key.to_string())),
+                    ),
+                    EdgeKind::Exception(catch_type) => (
+                        "exception",
+                        Some(catch_type.as_deref().unwrap_or("any").replace('/', ".")),
+                    ),
+                };
+                graph.edges.push(Edge {
+                    from: format!("B{index}"),
+                    to: format!("B{}", edge.target),
+                    kind,
+                    label,
+                });
+            }
+        }
+        graphs.push(graph);
+    }
+    Ok(graphs)
+}
+
+/**
+ * The classes and interfaces of `classes` with an edge to their direct superclass and
+ * each direct superinterface. Supertypes that are not in `classes` are drawn as well, but
+ * not theirs in turn.
+ */
+pub fn class_hierarchy(name: &str, classes: &[ClassFile]) -> Graph {
+    let mut graph = Graph {
+        name: name.into(),
+        ..Default::default()
+    };
+    let defined: HashSet<String> = classes.iter().map(|class| class.get_name()).collect();
+    let mut external = HashSet::new();
+    for class in classes {
+        let name = class.get_name();
+        let is_interface = class.access_flags.contains(&ClassAccessFlags::AccInterface);
+        graph.nodes.push(Node {
+            id: name.clone(),
+            label: name.clone(),
+            kind: if is_interface { "interface" } else { "class" },
+            lines: vec![],
+        });
+        // An interface extends its superinterfaces rather than implementing them.
+        let implements = if is_interface {
+            "extends"
+        } else {
+            "implements"
+        };
+        let supertypes = class
+            .get_super_name()
+            .map(|super_name| (super_name, "extends"))
+            .into_iter()
+            .chain(
+                class
+                    .get_interface_names()
+                    .into_iter()
+                    .map(|interface| (interface, implements)),
+            );
+        for (supertype, kind) in supertypes {
+            if !defined.contains(&supertype) && external.insert(supertype.clone()) {
+                graph.nodes.push(Node {
+                    id: supertype.clone(),
+                    label: supertype.clone(),
+                    kind: "external",
+                    lines: vec![],
+                });
+            }
+            graph.edges.push(Edge {
+                from: name.clone(),
+                to: supertype,
+                kind,
+                label: None,
+            });
+        }
+    }
+    graph
+}
+
+/**
+ * Writes graphs as one Graphviz `digraph`. Several graphs, such as the methods of a class,
+ * are each drawn in a cluster of their own.
+ */
+pub fn to_dot(name: &str, graphs: &[Graph]) -> String {
+    let mut dot = format!("digraph {} {{\n", dot_id(name));
+    dot.push_str("  node [shape=box, fontname=\"Courier\"];\n");
+    let clustered = graphs.len() > 1;
+    for (index, graph) in graphs.iter().enumerate() {
+        let indent = if clustered { "    " } else { "  " };
+        // Node ids only need to be unique within a graph, so a cluster prefixes them.
+        let id = |node: &str| match clustered {
+            true => dot_id(&format!("{index}:{node}")),
+            false => dot_id(node),
+        };
+        if clustered {
+            let _ = writeln!(dot, "  subgraph {} {{", dot_id(&format!("cluster_{index}")));
+            let _ = writeln!(dot, "{indent}label={};", dot_id(&graph.name));
+        }
+        for node in &graph.nodes {
+            let mut label = dot_escape(&node.label);
+            if !node.lines.is_empty() {
+                label.push_str("\\n");
+                for line in &node.lines {
+                    label.push_str(&dot_escape(line));
+                    label.push_str("\\l");
+                }
+            }
+            let style = match node.kind {
+                "interface" => ", style=rounded",
+                "external" => ", style=dashed",
+                "internal" => ", color=red",
+                "missing" => ", style=dashed, color=red",
+                _ => "",
+            };
+            let _ = writeln!(dot, "{indent}{} [label=\"{label}\"{style}];", id(&node.id));
+        }
+        for edge in &graph.edges {
+            let mut attributes = vec![];
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label=\"{}\"", dot_escape(label)));
+            }
+            match edge.kind {
+                "exception" => attributes.push("style=dashed".into()),
+                "extends" => attributes.push("arrowhead=empty".into()),
+                "implements" => attributes.push("arrowhead=empty, style=dashed".into()),
+                _ => {}
+            }
+            let attributes = match attributes.is_empty() {
+                true => String::new(),
+                false => format!(" [{}]", attributes.join(", ")),
+            };
+            let _ = writeln!(
+                dot,
+                "{indent}{} -> {}{attributes};",
+                id(&edge.from),
+                id(&edge.to)
+            );
+        }
+        if clustered {
+            dot.push_str("  }\n");
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Writes graphs as a JSON object on one line, `{"name": ..., "graphs": [...]}`.
+pub fn to_json(name: &str, graphs: &[Graph]) -> String {
+    let graphs: Vec<String> = graphs
+        .iter()
+        .map(|graph| {
+            let nodes: Vec<String> = graph
+                .nodes
+                .iter()
+                .map(|node| {
+                    let lines: Vec<String> =
+                        node.lines.iter().map(|line| json_string(line)).collect();
+                    format!(
+                        "{{\"id\":{},\"label\":{},\"kind\":{},\"lines\":[{}]}}",
+                        json_string(&node.id),
+                        json_string(&node.label),
+                        json_string(node.kind),
+                        lines.join(",")
+                    )
+                })
+                .collect();
+            let edges: Vec<String> = graph
+                .edges
+                .iter()
+                .map(|edge| {
+                    format!(
+                        "{{\"from\":{},\"to\":{},\"kind\":{},\"label\":{}}}",
+                        json_string(&edge.from),
+                        json_string(&edge.to),
+                        json_string(edge.kind),
+                        edge.label.as_deref().map_or("null".into(), json_string)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"name\":{},\"nodes\":[{}],\"edges\":[{}]}}",
+                json_string(&graph.name),
+                nodes.join(","),
+                edges.join(",")
+            )
+        })
+        .collect();
+    format!(
+        "{{\"name\":{},\"graphs\":[{}]}}\n",
+        json_string(name),
+        graphs.join(",")
+    )
+}
+
+/// A quoted DOT identifier, which may hold any characters.
+fn dot_id(text: &str) -> String {
+    format!("\"{}\"", dot_escape(text))
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_builder::tests::{class, read_back, single_method};
+    use crate::class_builder::{CodeBuilder, Insn};
+    use crate::instructions::*;
+
+    #[test]
+    fn test_control_flow_graphs() -> Result<(), Box<dyn Error>> {
+        // static int run(int n) { return n == 0 ? 1 : 2; }
+        let mut code = CodeBuilder::new(1, 1);
+        let other = code.new_label();
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::Jump(IFNE, other));
+        code.push(Insn::Simple(ICONST_1));
+        code.push(Insn::Simple(IRETURN));
+        code.push(Insn::Label(other));
+        code.push(Insn::Simple(ICONST_2));
+        code.push(Insn::Simple(IRETURN));
+        let class = read_back(single_method("test/Graphs", "(I)I", code));
+
+        let graphs = control_flow_graphs(&class)?;
+        assert_eq!(graphs.len(), 1);
+        assert_eq!(graphs[0].name, "run(I)I");
+        assert_eq!(
+            graphs[0].nodes[0].lines,
+            ["   0: iload         0", "   2: ifne          7"]
+        );
+        let dot = to_dot("test.Graphs", &graphs);
+        assert!(dot.starts_with("digraph \"test.Graphs\" {\n"));
+        assert!(dot.contains(
+            "  \"B0\" [label=\"B0 [0, 5)\\n   0: iload         0\\l   2: ifne          7\\l\"];\n"
+        ));
+        assert!(dot.contains("  \"B0\" -> \"B2\";\n  \"B0\" -> \"B1\";\n"));
+        let json = to_json("test.Graphs", &graphs);
+        assert!(json.starts_with("{\"name\":\"test.Graphs\",\"graphs\":[{\"name\":\"run(I)I\""));
+        assert!(json.contains("{\"from\":\"B0\",\"to\":\"B2\",\"kind\":\"jump\",\"label\":null}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_class_hierarchy() -> Result<(), Box<dyn Error>> {
+        let base = class(&[], "test/Base", "java/lang/Object", &[]);
+        let named = class(
+            &[
+                ClassAccessFlags::AccInterface,
+                ClassAccessFlags::AccAbstract,
+            ],
+            "test/Named",
+            "java/lang/Object",
+            &["java/lang/Comparable"],
+        );
+        let derived = class(&[], "test/Derived", "test/Base", &["test/Named"]);
+        let classes = [base.build()?, named.build()?, derived.build()?];
+
+        let graph = class_hierarchy("test.jar", &classes);
+        let nodes: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.id.as_str(), node.kind))
+            .collect();
+        assert_eq!(
+            nodes,
+            [
+                ("test.Base", "class"),
+                ("java.lang.Object", "external"),
+                ("test.Named", "interface"),
+                ("java.lang.Comparable", "external"),
+                ("test.Derived", "class"),
+            ]
+        );
+        let edges: Vec<_> = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from.as_str(), edge.to.as_str(), edge.kind))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                ("test.Base", "java.lang.Object", "extends"),
+                ("test.Named", "java.lang.Object", "extends"),
+                ("test.Named", "java.lang.Comparable", "extends"),
+                ("test.Derived", "test.Base", "extends"),
+                ("test.Derived", "test.Named", "implements"),
+            ]
+        );
+        let dot = to_dot("test.jar", &[graph]);
+        assert!(
+            dot.contains("  \"test.Derived\" -> \"test.Named\" [arrowhead=empty, style=dashed];\n")
+        );
+        assert!(
+            dot.contains("  \"java.lang.Object\" [label=\"java.lang.Object\", style=dashed];\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}é"), "\"a\\\"b\\\\c\\n\\u0001é\"");
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::error::Error;
+
+use crate::access_flags::ClassAccessFlags;
+use crate::class_file::ClassFile;
+use crate::class_path::ClassPath;
+use crate::constants::get_raw_class_name;
+use crate::errors::class_writing::{WritingCause, WritingError};
+use crate::frames::ClassHierarchy;
+
+const OBJECT: &str = "java/lang/Object";
+
+/// The supertypes of every array type, after JLS 4.10.3.
+const ARRAY_SUPERTYPES: [&str; 3] = [OBJECT, "java/lang/Cloneable", "java/io/Serializable"];
+
+/// What the index keeps of a class.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    is_interface: bool,
+}
+
+/**
+ * The superclasses and superinterfaces of a set of classes, indexed both ways so that the
+ * subtypes of a class can be found as well as its supertypes.\
+ *  Classes are named by their binary names in internal form, such as `java/lang/String`,
+ *  and arrays by their descriptors. A class can name a supertype that is not in the index;
+ *  the queries then answer as far as the classes that are go, and say when that is not far
+ *  enough to answer.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct HierarchyIndex {
+    classes: BTreeMap<String, Entry>,
+    /// The classes that name each class as their superclass or as a superinterface.
+    direct_subtypes: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl HierarchyIndex {
+    pub fn new(classes: &[ClassFile]) -> HierarchyIndex {
+        let mut index = HierarchyIndex::default();
+        for class in classes {
+            index.add(class);
+        }
+        index
+    }
+
+    /**
+     * Indexes every class on a class path. As when classes are loaded, a class that an
+     * earlier entry also has is hidden by it.
+     */
+    pub fn from_class_path(class_path: &ClassPath) -> Result<HierarchyIndex, Box<dyn Error>> {
+        let mut index = HierarchyIndex::default();
+        for entry in 0..class_path.get_entries().len() {
+            for class in class_path.load_classes(entry)? {
+                index.add(&class);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Adds a class, unless one of the same name is already in the index.
+    pub fn add(&mut self, class: &ClassFile) {
+        let pool = &class.constant_pool;
+        let Some(name) = get_raw_class_name(pool, class.this_class) else {
+            return;
+        };
+        if self.classes.contains_key(&name) {
+            return;
+        }
+        let entry = Entry {
+            super_class: get_raw_class_name(pool, class.super_class),
+            interfaces: class
+                .interfaces
+                .iter()
+                .filter_map(|index| get_raw_class_name(pool, *index))
+                .collect(),
+            is_interface: class.access_flags.contains(&ClassAccessFlags::AccInterface),
+        };
+        for supertype in entry.super_class.iter().chain(&entry.interfaces) {
+            self.direct_subtypes
+                .entry(supertype.clone())
+                .or_default()
+                .insert(name.clone());
+        }
+        self.classes.insert(name, entry);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.classes.contains_key(name)
+    }
+
+    /// Whether a class is an interface, `None` if it is not in the index.
+    pub fn is_interface(&self, name: &str) -> Option<bool> {
+        self.classes.get(name).map(|entry| entry.is_interface)
+    }
+
+    /**
+     * The direct superclass of a class, which is `java/lang/Object` for an interface. `None`
+     * for `java/lang/Object` itself and for classes that are not in the index.
+     */
+    pub fn get_super_class(&self, name: &str) -> Option<&str> {
+        self.classes.get(name)?.super_class.as_deref()
+    }
+
+    /**
+     * Every superclass and superinterface of a class, direct or not, nearest first: the
+     * superclasses in order, then the superinterfaces breadth first. Those of a supertype that
+     * is not in the index are missing, which [`get_missing`](Self::get_missing) tells.
+     */
+    pub fn get_supertypes(&self, name: &str) -> Vec<String> {
+        if name.starts_with('[') {
+            return ARRAY_SUPERTYPES
+                .iter()
+                .map(|name| name.to_string())
+                .collect();
+        }
+        let mut supertypes = self.get_super_classes(name);
+        let mut queue: VecDeque<&str> = std::iter::once(name)
+            .chain(supertypes.iter().map(String::as_str))
+            .collect();
+        let mut interfaces = vec![];
+        while let Some(class) = queue.pop_front() {
+            let entry = self.classes.get(class);
+            for interface in entry.into_iter().flat_map(|entry| &entry.interfaces) {
+                if interface != name && !interfaces.contains(interface) {
+                    interfaces.push(interface.clone());
+                    queue.push_back(interface);
+                }
+            }
+        }
+        supertypes.extend(interfaces);
+        supertypes
+    }
+
+    /// The superclasses of a class, nearest first, as far as they are in the index.
+    fn get_super_classes(&self, name: &str) -> Vec<String> {
+        let mut super_classes: Vec<String> = vec![];
+        let mut class = self.get_super_class(name);
+        while let Some(super_class) = class {
+            // A broken hierarchy can have a cycle.
+            if super_class == name || super_classes.iter().any(|known| known == super_class) {
+                break;
+            }
+            super_classes.push(super_class.into());
+            class = self.get_super_class(super_class);
+        }
+        super_classes
+    }
+
+    /**
+     * The classes and interfaces in the index that extend or implement a class or interface,
+     * directly or not, sorted.
+     */
+    pub fn get_subtypes(&self, name: &str) -> BTreeSet<String> {
+        let mut subtypes = BTreeSet::new();
+        let mut queue = VecDeque::from([name]);
+        while let Some(class) = queue.pop_front() {
+            for subtype in self.direct_subtypes.get(class).into_iter().flatten() {
+                if subtype != name && subtypes.insert(subtype.clone()) {
+                    queue.push_back(subtype);
+                }
+            }
+        }
+        subtypes
+    }
+
+    /// The classes among the subtypes of an interface or class, leaving out interfaces.
+    pub fn get_implementors(&self, name: &str) -> BTreeSet<String> {
+        let mut implementors = self.get_subtypes(name);
+        implementors.retain(|subtype| self.is_interface(subtype) == Some(false));
+        implementors
+    }
+
+    /**
+     * Whether a value of type `from` can be assigned to a variable of type `to`, by widening
+     * reference conversion. `None` when a class that is not in the index leaves it open.
+     */
+    pub fn is_assignable(&self, from: &str, to: &str) -> Option<bool> {
+        if from == to || to == OBJECT {
+            return Some(true);
+        }
+        if let Some(from_component) = from.strip_prefix('[') {
+            return match to.strip_prefix('[') {
+                // Arrays of references are covariant, arrays of primitives are not.
+                Some(to_component) => match (reference(from_component), reference(to_component)) {
+                    (Some(from), Some(to)) => self.is_assignable(from, to),
+                    _ => Some(false),
+                },
+                None => Some(ARRAY_SUPERTYPES.contains(&to)),
+            };
+        }
+        if to.starts_with('[') {
+            return Some(false);
+        }
+        if self
+            .get_supertypes(from)
+            .iter()
+            .any(|supertype| supertype == to)
+        {
+            return Some(true);
+        }
+        match self.is_complete(from) {
+            true => Some(false),
+            false => None,
+        }
+    }
+
+    /**
+     * The most specific class that both classes extend, `java/lang/Object` if either is an
+     * interface. `None` when a class that is not in the index leaves it open.
+     */
+    pub fn get_common_super_class(&self, a: &str, b: &str) -> Option<String> {
+        if a == b {
+            return Some(a.into());
+        }
+        if self.is_interface(a)? || self.is_interface(b)? {
+            return Some(OBJECT.into());
+        }
+        let chain = |name: &str| {
+            let mut chain = vec![name.to_string()];
+            chain.extend(self.get_super_classes(name));
+            chain
+        };
+        let a_chain = chain(a);
+        let b_chain = chain(b);
+        if let Some(common) = a_chain.iter().find(|class| b_chain.contains(class)) {
+            return Some(common.clone());
+        }
+        // Without a common class below it, Object is only known to be the answer when both
+        // chains reach it.
+        match a_chain.last()? == OBJECT && b_chain.last()? == OBJECT {
+            true => Some(OBJECT.into()),
+            false => None,
+        }
+    }
+
+    /**
+     * The supertypes that classes in the index name but that are not in it themselves, whose
+     * own supertypes are therefore unknown.
+     */
+    pub fn get_missing(&self) -> BTreeSet<String> {
+        self.direct_subtypes
+            .keys()
+            .filter(|name| !self.classes.contains_key(*name))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether every supertype of a class, direct or not, is in the index.
+    fn is_complete(&self, name: &str) -> bool {
+        self.contains(name)
+            && self
+                .get_supertypes(name)
+                .iter()
+                .all(|supertype| self.contains(supertype))
+    }
+}
+
+/// For frame computation, which needs a class's superclass and whether it is an interface.
+impl ClassHierarchy for HierarchyIndex {
+    fn get_super_class(&self, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+        match self.classes.get(name) {
+            Some(entry) => Ok(entry.super_class.clone()),
+            None => Err(missing_class(name)),
+        }
+    }
+
+    fn is_interface(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        HierarchyIndex::is_interface(self, name).ok_or_else(|| missing_class(name))
+    }
+}
+
+/// The reference type an array component descriptor names, `None` for a primitive type.
+fn reference(component: &str) -> Option<&str> {
+    match component.starts_with('[') {
+        true => Some(component),
+        false => component.strip_prefix('L')?.strip_suffix(';'),
+    }
+}
+
+fn missing_class(name: &str) -> Box<dyn Error> {
+    Box::new(WritingError::new(
+        WritingCause::MissingClass(name.into()),
+        "the class is not in the hierarchy index",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_builder::tests::{class, read_back};
+
+    /// `test/Shape` and `test/Round`, which extends it, and the classes `test/Base`,
+    /// `test/Circle` extending it and implementing `test/Round`, and `test/Square`, which
+    /// extends `test/Missing` that is not in the index.
+    fn index() -> HierarchyIndex {
+        let interface = [
+            ClassAccessFlags::AccInterface,
+            ClassAccessFlags::AccAbstract,
+        ];
+        let class_flags = [ClassAccessFlags::AccSuper];
+        HierarchyIndex::new(
+            &[
+                class(&interface, "test/Shape", OBJECT, &[]),
+                class(&interface, "test/Round", OBJECT, &["test/Shape"]),
+                class(&class_flags, "test/Base", OBJECT, &[]),
+                class(&class_flags, "test/Circle", "test/Base", &["test/Round"]),
+                class(&class_flags, "test/Square", "test/Missing", &["test/Shape"]),
+                class(&class_flags, OBJECT, "", &[]),
+            ]
+            .map(read_back),
+        )
+    }
+
+    #[test]
+    fn test_supertypes_and_subtypes() {
+        let index = index();
+        assert_eq!(
+            index.get_supertypes("test/Circle"),
+            ["test/Base", OBJECT, "test/Round", "test/Shape"]
+        );
+        assert_eq!(index.get_supertypes("test/Round"), [OBJECT, "test/Shape"]);
+        assert_eq!(
+            index.get_subtypes("test/Shape"),
+            BTreeSet::from(["test/Circle", "test/Round", "test/Square"].map(String::from))
+        );
+        assert_eq!(
+            index.get_implementors("test/Shape"),
+            BTreeSet::from(["test/Circle", "test/Square"].map(String::from))
+        );
+        assert_eq!(index.get_missing(), BTreeSet::from(["test/Missing".into()]));
+    }
+
+    #[test]
+    fn test_is_assignable() {
+        // Only a class missing from the index leaves the answer open.
+        let index = index();
+        assert_eq!(index.is_assignable("test/Circle", "test/Shape"), Some(true));
+        assert_eq!(
+            index.is_assignable("test/Circle", "test/Square"),
+            Some(false)
+        );
+        assert_eq!(index.is_assignable("test/Square", "test/Shape"), Some(true));
+        assert_eq!(index.is_assignable("test/Square", "test/Base"), None);
+        assert_eq!(index.is_assignable("test/Unknown", "test/Base"), None);
+        assert_eq!(
+            index.is_assignable("[Ltest/Circle;", "[Ltest/Shape;"),
+            Some(true)
+        );
+        assert_eq!(
+            index.is_assignable("[[I", "[Ljava/lang/Cloneable;"),
+            Some(true)
+        );
+        assert_eq!(index.is_assignable("[I", "[J"), Some(false));
+        assert_eq!(
+            index.is_assignable("[I", "java/io/Serializable"),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_common_super_class() {
+        let index = index();
+        let common = |a, b| index.get_common_super_class(a, b);
+        assert_eq!(common("test/Circle", "test/Base"), Some("test/Base".into()));
+        assert_eq!(common("test/Circle", "test/Round"), Some(OBJECT.into()));
+        assert_eq!(common("test/Circle", "test/Square"), None);
+        assert_eq!(
+            ClassHierarchy::get_super_class(&index, "test/Circle").unwrap(),
+            Some("test/Base".into())
+        );
+        assert!(ClassHierarchy::is_interface(&index, "test/Missing").is_err());
+    }
+}
@@ -0,0 +1,720 @@
+use byteorder::{ReadBytesExt, BE};
+use std::error::Error;
+use std::io::Cursor;
+
+use crate::attributes::Code;
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+
+pub const NOP: u8 = 0x00;
+pub const ACONST_NULL: u8 = 0x01;
+pub const ICONST_M1: u8 = 0x02;
+pub const ICONST_0: u8 = 0x03;
+pub const ICONST_1: u8 = 0x04;
+pub const ICONST_2: u8 = 0x05;
+pub const ICONST_3: u8 = 0x06;
+pub const ICONST_4: u8 = 0x07;
+pub const ICONST_5: u8 = 0x08;
+pub const LCONST_0: u8 = 0x09;
+pub const LCONST_1: u8 = 0x0a;
+pub const FCONST_0: u8 = 0x0b;
+pub const FCONST_1: u8 = 0x0c;
+pub const FCONST_2: u8 = 0x0d;
+pub const DCONST_0: u8 = 0x0e;
+pub const DCONST_1: u8 = 0x0f;
+pub const BIPUSH: u8 = 0x10;
+pub const SIPUSH: u8 = 0x11;
+pub const LDC: u8 = 0x12;
+pub const LDC_W: u8 = 0x13;
+pub const LDC2_W: u8 = 0x14;
+pub const ILOAD: u8 = 0x15;
+pub const LLOAD: u8 = 0x16;
+pub const FLOAD: u8 = 0x17;
+pub const DLOAD: u8 = 0x18;
+pub const ALOAD: u8 = 0x19;
+pub const ILOAD_0: u8 = 0x1a;
+pub const ILOAD_1: u8 = 0x1b;
+pub const ILOAD_2: u8 = 0x1c;
+pub const ILOAD_3: u8 = 0x1d;
+pub const LLOAD_0: u8 = 0x1e;
+pub const LLOAD_1: u8 = 0x1f;
+pub const LLOAD_2: u8 = 0x20;
+pub const LLOAD_3: u8 = 0x21;
+pub const FLOAD_0: u8 = 0x22;
+pub const FLOAD_1: u8 = 0x23;
+pub const FLOAD_2: u8 = 0x24;
+pub const FLOAD_3: u8 = 0x25;
+pub const DLOAD_0: u8 = 0x26;
+pub const DLOAD_1: u8 = 0x27;
+pub const DLOAD_2: u8 = 0x28;
+pub const DLOAD_3: u8 = 0x29;
+pub const ALOAD_0: u8 = 0x2a;
+pub const ALOAD_1: u8 = 0x2b;
+pub const ALOAD_2: u8 = 0x2c;
+pub const ALOAD_3: u8 = 0x2d;
+pub const IALOAD: u8 = 0x2e;
+pub const LALOAD: u8 = 0x2f;
+pub const FALOAD: u8 = 0x30;
+pub const DALOAD: u8 = 0x31;
+pub const AALOAD: u8 = 0x32;
+pub const BALOAD: u8 = 0x33;
+pub const CALOAD: u8 = 0x34;
+pub const SALOAD: u8 = 0x35;
+pub const ISTORE: u8 = 0x36;
+pub const LSTORE: u8 = 0x37;
+pub const FSTORE: u8 = 0x38;
+pub const DSTORE: u8 = 0x39;
+pub const ASTORE: u8 = 0x3a;
+pub const ISTORE_0: u8 = 0x3b;
+pub const ISTORE_1: u8 = 0x3c;
+pub const ISTORE_2: u8 = 0x3d;
+pub const ISTORE_3: u8 = 0x3e;
+pub const LSTORE_0: u8 = 0x3f;
+pub const LSTORE_1: u8 = 0x40;
+pub const LSTORE_2: u8 = 0x41;
+pub const LSTORE_3: u8 = 0x42;
+pub const FSTORE_0: u8 = 0x43;
+pub const FSTORE_1: u8 = 0x44;
+pub const FSTORE_2: u8 = 0x45;
+pub const FSTORE_3: u8 = 0x46;
+pub const DSTORE_0: u8 = 0x47;
+pub const DSTORE_1: u8 = 0x48;
+pub const DSTORE_2: u8 = 0x49;
+pub const DSTORE_3: u8 = 0x4a;
+pub const ASTORE_0: u8 = 0x4b;
+pub const ASTORE_1: u8 = 0x4c;
+pub const ASTORE_2: u8 = 0x4d;
+pub const ASTORE_3: u8 = 0x4e;
+pub const IASTORE: u8 = 0x4f;
+pub const LASTORE: u8 = 0x50;
+pub const FASTORE: u8 = 0x51;
+pub const DASTORE: u8 = 0x52;
+pub const AASTORE: u8 = 0x53;
+pub const BASTORE: u8 = 0x54;
+pub const CASTORE: u8 = 0x55;
+pub const SASTORE: u8 = 0x56;
+pub const POP: u8 = 0x57;
+pub const POP2: u8 = 0x58;
+pub const DUP: u8 = 0x59;
+pub const DUP_X1: u8 = 0x5a;
+pub const DUP_X2: u8 = 0x5b;
+pub const DUP2: u8 = 0x5c;
+pub const DUP2_X1: u8 = 0x5d;
+pub const DUP2_X2: u8 = 0x5e;
+pub const SWAP: u8 = 0x5f;
+pub const IADD: u8 = 0x60;
+pub const LADD: u8 = 0x61;
+pub const FADD: u8 = 0x62;
+pub const DADD: u8 = 0x63;
+pub const ISUB: u8 = 0x64;
+pub const LSUB: u8 = 0x65;
+pub const FSUB: u8 = 0x66;
+pub const DSUB: u8 = 0x67;
+pub const IMUL: u8 = 0x68;
+pub const LMUL: u8 = 0x69;
+pub const FMUL: u8 = 0x6a;
+pub const DMUL: u8 = 0x6b;
+pub const IDIV: u8 = 0x6c;
+pub const LDIV: u8 = 0x6d;
+pub const FDIV: u8 = 0x6e;
+pub const DDIV: u8 = 0x6f;
+pub const IREM: u8 = 0x70;
+pub const LREM: u8 = 0x71;
+pub const FREM: u8 = 0x72;
+pub const DREM: u8 = 0x73;
+pub const INEG: u8 = 0x74;
+pub const LNEG: u8 = 0x75;
+pub const FNEG: u8 = 0x76;
+pub const DNEG: u8 = 0x77;
+pub const ISHL: u8 = 0x78;
+pub const LSHL: u8 = 0x79;
+pub const ISHR: u8 = 0x7a;
+pub const LSHR: u8 = 0x7b;
+pub const IUSHR: u8 = 0x7c;
+pub const LUSHR: u8 = 0x7d;
+pub const IAND: u8 = 0x7e;
+pub const LAND: u8 = 0x7f;
+pub const IOR: u8 = 0x80;
+pub const LOR: u8 = 0x81;
+pub const IXOR: u8 = 0x82;
+pub const LXOR: u8 = 0x83;
+pub const IINC: u8 = 0x84;
+pub const I2L: u8 = 0x85;
+pub const I2F: u8 = 0x86;
+pub const I2D: u8 = 0x87;
+pub const L2I: u8 = 0x88;
+pub const L2F: u8 = 0x89;
+pub const L2D: u8 = 0x8a;
+pub const F2I: u8 = 0x8b;
+pub const F2L: u8 = 0x8c;
+pub const F2D: u8 = 0x8d;
+pub const D2I: u8 = 0x8e;
+pub const D2L: u8 = 0x8f;
+pub const D2F: u8 = 0x90;
+pub const I2B: u8 = 0x91;
+pub const I2C: u8 = 0x92;
+pub const I2S: u8 = 0x93;
+pub const LCMP: u8 = 0x94;
+pub const FCMPL: u8 = 0x95;
+pub const FCMPG: u8 = 0x96;
+pub const DCMPL: u8 = 0x97;
+pub const DCMPG: u8 = 0x98;
+pub const IFEQ: u8 = 0x99;
+pub const IFNE: u8 = 0x9a;
+pub const IFLT: u8 = 0x9b;
+pub const IFGE: u8 = 0x9c;
+pub const IFGT: u8 = 0x9d;
+pub const IFLE: u8 = 0x9e;
+pub const IF_ICMPEQ: u8 = 0x9f;
+pub const IF_ICMPNE: u8 = 0xa0;
+pub const IF_ICMPLT: u8 = 0xa1;
+pub const IF_ICMPGE: u8 = 0xa2;
+pub const IF_ICMPGT: u8 = 0xa3;
+pub const IF_ICMPLE: u8 = 0xa4;
+pub const IF_ACMPEQ: u8 = 0xa5;
+pub const IF_ACMPNE: u8 = 0xa6;
+pub const GOTO: u8 = 0xa7;
+pub const JSR: u8 = 0xa8;
+pub const RET: u8 = 0xa9;
+pub const TABLESWITCH: u8 = 0xaa;
+pub const LOOKUPSWITCH: u8 = 0xab;
+pub const IRETURN: u8 = 0xac;
+pub const LRETURN: u8 = 0xad;
+pub const FRETURN: u8 = 0xae;
+pub const DRETURN: u8 = 0xaf;
+pub const ARETURN: u8 = 0xb0;
+pub const RETURN: u8 = 0xb1;
+pub const GETSTATIC: u8 = 0xb2;
+pub const PUTSTATIC: u8 = 0xb3;
+pub const GETFIELD: u8 = 0xb4;
+pub const PUTFIELD: u8 = 0xb5;
+pub const INVOKEVIRTUAL: u8 = 0xb6;
+pub const INVOKESPECIAL: u8 = 0xb7;
+pub const INVOKESTATIC: u8 = 0xb8;
+pub const INVOKEINTERFACE: u8 = 0xb9;
+pub const INVOKEDYNAMIC: u8 = 0xba;
+pub const NEW: u8 = 0xbb;
+pub const NEWARRAY: u8 = 0xbc;
+pub const ANEWARRAY: u8 = 0xbd;
+pub const ARRAYLENGTH: u8 = 0xbe;
+pub const ATHROW: u8 = 0xbf;
+pub const CHECKCAST: u8 = 0xc0;
+pub const INSTANCEOF: u8 = 0xc1;
+pub const MONITORENTER: u8 = 0xc2;
+pub const MONITOREXIT: u8 = 0xc3;
+pub const WIDE: u8 = 0xc4;
+pub const MULTIANEWARRAY: u8 = 0xc5;
+pub const IFNULL: u8 = 0xc6;
+pub const IFNONNULL: u8 = 0xc7;
+pub const GOTO_W: u8 = 0xc8;
+pub const JSR_W: u8 = 0xc9;
+
+const MNEMONICS: [&str; 202] = [
+    "nop",
+    "aconst_null",
+    "iconst_m1",
+    "iconst_0",
+    "iconst_1",
+    "iconst_2",
+    "iconst_3",
+    "iconst_4",
+    "iconst_5",
+    "lconst_0",
+    "lconst_1",
+    "fconst_0",
+    "fconst_1",
+    "fconst_2",
+    "dconst_0",
+    "dconst_1",
+    "bipush",
+    "sipush",
+    "ldc",
+    "ldc_w",
+    "ldc2_w",
+    "iload",
+    "lload",
+    "fload",
+    "dload",
+    "aload",
+    "iload_0",
+    "iload_1",
+    "iload_2",
+    "iload_3",
+    "lload_0",
+    "lload_1",
+    "lload_2",
+    "lload_3",
+    "fload_0",
+    "fload_1",
+    "fload_2",
+    "fload_3",
+    "dload_0",
+    "dload_1",
+    "dload_2",
+    "dload_3",
+    "aload_0",
+    "aload_1",
+    "aload_2",
+    "aload_3",
+    "iaload",
+    "laload",
+    "faload",
+    "daload",
+    "aaload",
+    "baload",
+    "caload",
+    "saload",
+    "istore",
+    "lstore",
+    "fstore",
+    "dstore",
+    "astore",
+    "istore_0",
+    "istore_1",
+    "istore_2",
+    "istore_3",
+    "lstore_0",
+    "lstore_1",
+    "lstore_2",
+    "lstore_3",
+    "fstore_0",
+    "fstore_1",
+    "fstore_2",
+    "fstore_3",
+    "dstore_0",
+    "dstore_1",
+    "dstore_2",
+    "dstore_3",
+    "astore_0",
+    "astore_1",
+    "astore_2",
+    "astore_3",
+    "iastore",
+    "lastore",
+    "fastore",
+    "dastore",
+    "aastore",
+    "bastore",
+    "castore",
+    "sastore",
+    "pop",
+    "pop2",
+    "dup",
+    "dup_x1",
+    "dup_x2",
+    "dup2",
+    "dup2_x1",
+    "dup2_x2",
+    "swap",
+    "iadd",
+    "ladd",
+    "fadd",
+    "dadd",
+    "isub",
+    "lsub",
+    "fsub",
+    "dsub",
+    "imul",
+    "lmul",
+    "fmul",
+    "dmul",
+    "idiv",
+    "ldiv",
+    "fdiv",
+    "ddiv",
+    "irem",
+    "lrem",
+    "frem",
+    "drem",
+    "ineg",
+    "lneg",
+    "fneg",
+    "dneg",
+    "ishl",
+    "lshl",
+    "ishr",
+    "lshr",
+    "iushr",
+    "lushr",
+    "iand",
+    "land",
+    "ior",
+    "lor",
+    "ixor",
+    "lxor",
+    "iinc",
+    "i2l",
+    "i2f",
+    "i2d",
+    "l2i",
+    "l2f",
+    "l2d",
+    "f2i",
+    "f2l",
+    "f2d",
+    "d2i",
+    "d2l",
+    "d2f",
+    "i2b",
+    "i2c",
+    "i2s",
+    "lcmp",
+    "fcmpl",
+    "fcmpg",
+    "dcmpl",
+    "dcmpg",
+    "ifeq",
+    "ifne",
+    "iflt",
+    "ifge",
+    "ifgt",
+    "ifle",
+    "if_icmpeq",
+    "if_icmpne",
+    "if_icmplt",
+    "if_icmpge",
+    "if_icmpgt",
+    "if_icmple",
+    "if_acmpeq",
+    "if_acmpne",
+    "goto",
+    "jsr",
+    "ret",
+    "tableswitch",
+    "lookupswitch",
+    "ireturn",
+    "lreturn",
+    "freturn",
+    "dreturn",
+    "areturn",
+    "return",
+    "getstatic",
+    "putstatic",
+    "getfield",
+    "putfield",
+    "invokevirtual",
+    "invokespecial",
+    "invokestatic",
+    "invokeinterface",
+    "invokedynamic",
+    "new",
+    "newarray",
+    "anewarray",
+    "arraylength",
+    "athrow",
+    "checkcast",
+    "instanceof",
+    "monitorenter",
+    "monitorexit",
+    "wide",
+    "multianewarray",
+    "ifnull",
+    "ifnonnull",
+    "goto_w",
+    "jsr_w",
+];
+
+/// The mnemonic of `opcode` as used by the JVM specification and javap.
+pub fn get_mnemonic(opcode: u8) -> Option<&'static str> {
+    MNEMONICS.get(opcode as usize).copied()
+}
+
+pub fn from_mnemonic(mnemonic: &str) -> Option<u8> {
+    MNEMONICS
+        .iter()
+        .position(|name| *name == mnemonic)
+        .map(|opcode| opcode as u8)
+}
+
+/**
+ * **atype**\
+ *  The atype operand of newarray is a code that indicates the type of array to create.
+ */
+pub fn get_array_type_name(atype: u8) -> Option<&'static str> {
+    Some(match atype {
+        4 => "boolean",
+        5 => "char",
+        6 => "float",
+        7 => "double",
+        8 => "byte",
+        9 => "short",
+        10 => "int",
+        11 => "long",
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operands {
+    None,
+    /// The sign extended value pushed by bipush and sipush.
+    Immediate(i32),
+    /// A constant pool index, as used by ldc, the field and method instructions, new,
+    /// anewarray, checkcast and instanceof.
+    Constant(u16),
+    /// A local variable index, as used by the load and store instructions and ret.
+    Local(u16),
+    Increment {
+        index: u16,
+        value: i16,
+    },
+    /// The absolute pc of a branch target.
+    Branch(u32),
+    TableSwitch {
+        default: u32,
+        low: i32,
+        high: i32,
+        targets: Vec<u32>,
+    },
+    LookupSwitch {
+        default: u32,
+        pairs: Vec<(i32, u32)>,
+    },
+    InvokeInterface {
+        index: u16,
+        count: u8,
+    },
+    InvokeDynamic(u16),
+    NewArray(u8),
+    MultiANewArray {
+        index: u16,
+        dimensions: u8,
+    },
+}
+
+/// [Instructions](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub pc: u32,
+    pub opcode: u8,
+    /// Set when the instruction was prefixed by `wide`, widening its local variable index
+    /// (and the increment of iinc) to two bytes.
+    pub wide: bool,
+    pub operands: Operands,
+}
+
+impl Instruction {
+    /// The mnemonic javap prints for this instruction, e.g. `iinc_w` for a widened iinc.
+    pub fn get_mnemonic(&self) -> String {
+        let mnemonic = get_mnemonic(self.opcode).unwrap_or("unknown");
+        if self.wide {
+            format!("{mnemonic}_w")
+        } else {
+            mnemonic.into()
+        }
+    }
+}
+
+/// Decodes a Code attribute's code array into its instructions, in pc order.
+pub fn decode(code: &[u8]) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    let mut cursor = Cursor::new(code);
+    let mut instructions = vec![];
+    while (cursor.position() as usize) < code.len() {
+        let pc = cursor.position() as u32;
+        let mut opcode = cursor.read_u8()?;
+        let wide = opcode == WIDE;
+        if wide {
+            opcode = cursor.read_u8()?;
+            if !matches!(opcode, ILOAD..=ALOAD | ISTORE..=ASTORE | IINC | RET) {
+                return Err(invalid(pc, "wide does not modify this opcode"));
+            }
+        }
+        let branch = |offset: i32| -> Result<u32, Box<dyn Error>> {
+            let target = pc as i64 + offset as i64;
+            if target < 0 || target as usize >= code.len() {
+                return Err(invalid(pc, "branch target is outside of the code array"));
+            }
+            Ok(target as u32)
+        };
+        let operands = match opcode {
+            BIPUSH => Operands::Immediate(cursor.read_i8()? as i32),
+            SIPUSH => Operands::Immediate(cursor.read_i16::<BE>()? as i32),
+            LDC => Operands::Constant(cursor.read_u8()? as u16),
+            LDC_W | LDC2_W => Operands::Constant(cursor.read_u16::<BE>()?),
+            ILOAD..=ALOAD | ISTORE..=ASTORE | RET => Operands::Local(if wide {
+                cursor.read_u16::<BE>()?
+            } else {
+                cursor.read_u8()? as u16
+            }),
+            IINC => {
+                if wide {
+                    Operands::Increment {
+                        index: cursor.read_u16::<BE>()?,
+                        value: cursor.read_i16::<BE>()?,
+                    }
+                } else {
+                    Operands::Increment {
+                        index: cursor.read_u8()? as u16,
+                        value: cursor.read_i8()? as i16,
+                    }
+                }
+            }
+            IFEQ..=JSR | IFNULL | IFNONNULL => {
+                Operands::Branch(branch(cursor.read_i16::<BE>()? as i32)?)
+            }
+            GOTO_W | JSR_W => Operands::Branch(branch(cursor.read_i32::<BE>()?)?),
+            TABLESWITCH => {
+                // 0-3 bytes of padding align the operands to a multiple of four from the
+                // start of the code array.
+                cursor.set_position((pc as u64 + 4) & !3);
+                let default = branch(cursor.read_i32::<BE>()?)?;
+                let low = cursor.read_i32::<BE>()?;
+                let high = cursor.read_i32::<BE>()?;
+                if low > high {
+                    return Err(invalid(pc, "tableswitch low is greater than high"));
+                }
+                // Checked before allocating, as a malformed count can be billions of entries.
+                let count = (high as i64 - low as i64 + 1) as usize;
+                if count > remaining(&cursor) / 4 {
+                    return Err(invalid(pc, "tableswitch has more targets than bytes left"));
+                }
+                let mut targets = Vec::with_capacity(count);
+                for _ in low..=high {
+                    targets.push(branch(cursor.read_i32::<BE>()?)?);
+                }
+                Operands::TableSwitch {
+                    default,
+                    low,
+                    high,
+                    targets,
+                }
+            }
+            LOOKUPSWITCH => {
+                cursor.set_position((pc as u64 + 4) & !3);
+                let default = branch(cursor.read_i32::<BE>()?)?;
+                let npairs = cursor.read_i32::<BE>()?;
+                if npairs < 0 {
+                    return Err(invalid(pc, "lookupswitch has a negative npairs"));
+                }
+                if npairs as usize > remaining(&cursor) / 8 {
+                    return Err(invalid(pc, "lookupswitch has more pairs than bytes left"));
+                }
+                let mut pairs = Vec::with_capacity(npairs as usize);
+                for _ in 0..npairs {
+                    let key = cursor.read_i32::<BE>()?;
+                    pairs.push((key, branch(cursor.read_i32::<BE>()?)?));
+                }
+                Operands::LookupSwitch { default, pairs }
+            }
+            GETSTATIC..=INVOKESTATIC | NEW | ANEWARRAY | CHECKCAST | INSTANCEOF => {
+                Operands::Constant(cursor.read_u16::<BE>()?)
+            }
+            INVOKEINTERFACE => {
+                let index = cursor.read_u16::<BE>()?;
+                let count = cursor.read_u8()?;
+                cursor.read_u8()?;
+                Operands::InvokeInterface { index, count }
+            }
+            INVOKEDYNAMIC => {
+                let index = cursor.read_u16::<BE>()?;
+                cursor.read_u16::<BE>()?;
+                Operands::InvokeDynamic(index)
+            }
+            NEWARRAY => Operands::NewArray(cursor.read_u8()?),
+            MULTIANEWARRAY => Operands::MultiANewArray {
+                index: cursor.read_u16::<BE>()?,
+                dimensions: cursor.read_u8()?,
+            },
+            WIDE..=u8::MAX => {
+                return Err(invalid(pc, &format!("invalid opcode {opcode:#04x}")));
+            }
+            _ => Operands::None,
+        };
+        instructions.push(Instruction {
+            pc,
+            opcode,
+            wide,
+            operands,
+        });
+    }
+    Ok(instructions)
+}
+
+impl Code {
+    pub fn get_instructions(&self) -> Result<Vec<Instruction>, Box<dyn Error>> {
+        decode(&self.code)
+    }
+}
+
+/// The number of bytes of the code array after the cursor.
+fn remaining(cursor: &Cursor<&[u8]>) -> usize {
+    cursor
+        .get_ref()
+        .len()
+        .saturating_sub(cursor.position() as usize)
+}
+
+fn invalid(pc: u32, msg: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(LoadingCause::InvalidInstruction(pc), msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_operands() -> Result<(), Box<dyn Error>> {
+        #[rustfmt::skip]
+        let code = [
+            BIPUSH, 0xff,
+            WIDE, IINC, 0x01, 0x00, 0xff, 0x7f,
+            ILOAD_1,
+            TABLESWITCH, 0, 0,
+            0, 0, 0, 19,
+            0, 0, 0, 1,
+            0, 0, 0, 1,
+            0, 0, 0, 19,
+            RETURN,
+        ];
+        let instructions = decode(&code)?;
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(instructions[0].operands, Operands::Immediate(-1));
+        assert_eq!(instructions[1].get_mnemonic(), "iinc_w");
+        assert_eq!(
+            instructions[1].operands,
+            Operands::Increment {
+                index: 256,
+                value: -129
+            }
+        );
+        assert_eq!(
+            instructions[3].operands,
+            Operands::TableSwitch {
+                default: 28,
+                low: 1,
+                high: 1,
+                targets: vec![28]
+            }
+        );
+        assert_eq!(instructions[4].pc, 28);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_invalid_code() {
+        assert!(decode(&[GOTO, 0x00, 0x10]).is_err());
+        assert!(decode(&[WIDE, BIPUSH, 0x01]).is_err());
+        assert!(decode(&[0xca]).is_err());
+        assert_eq!(from_mnemonic("invokedynamic"), Some(INVOKEDYNAMIC));
+    }
+
+    #[test]
+    fn test_reject_oversized_switches() {
+        // The counts claim billions of entries in a 16-byte method.
+        #[rustfmt::skip]
+        let tableswitch = [
+            TABLESWITCH, 0, 0, 0,
+            0, 0, 0, 0,
+            0x80, 0, 0, 0,
+            0x7f, 0xff, 0xff, 0xff,
+        ];
+        assert!(decode(&tableswitch).is_err());
+        #[rustfmt::skip]
+        let lookupswitch = [
+            LOOKUPSWITCH, 0, 0, 0,
+            0, 0, 0, 0,
+            0x7f, 0xff, 0xff, 0xff,
+            0, 0, 0, 0,
+        ];
+        assert!(decode(&lookupswitch).is_err());
+    }
+}
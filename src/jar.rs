@@ -0,0 +1,559 @@
+use std::{collections::HashMap, error::Error, fs, io::Cursor, path::Path};
+
+use byteorder::{ReadBytesExt, LE};
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+
+use crate::class_file::ClassFile;
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+use crate::manifest::{Manifest, MANIFEST_NAME};
+
+const LOCAL_FILE_HEADER: u32 = 0x04034b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x06064b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR: u32 = 0x07064b50;
+const ZIP64_EXTENDED_INFORMATION: u16 = 0x0001;
+
+/// The end of central directory record is 22 bytes followed by a comment of at most 65535.
+const MAX_END_OF_CENTRAL_DIRECTORY: usize = 22 + u16::MAX as usize;
+
+const VERSIONS_PREFIX: &str = "META-INF/versions/";
+/// Versioned directories below Java 9 are ignored, as multi-release jars were added in 9.
+const FIRST_VERSION: u16 = 9;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+const ENCRYPTED: u16 = 0x0001;
+
+/// An entry of a jar with the class parsed from it, or the reason it could not be.
+pub type JarClass<'a> = (&'a JarEntry, Result<ClassFile, Box<dyn Error>>);
+
+/// A file in the central directory of an archive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JarEntry {
+    pub(crate) name: String,
+    /// 0 for stored and 8 for deflated entries, the only methods jars use.
+    pub(crate) method: u16,
+    pub(crate) flags: u16,
+    pub(crate) crc32: u32,
+    pub(crate) compressed_size: u64,
+    pub(crate) uncompressed_size: u64,
+    pub(crate) local_header_offset: u64,
+}
+
+impl JarEntry {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    pub fn is_class(&self) -> bool {
+        self.name.ends_with(".class")
+    }
+}
+
+/// [ZIP File Format](https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT)
+#[derive(Debug)]
+pub struct Jar {
+    bytes: Vec<u8>,
+    entries: Vec<JarEntry>,
+}
+
+impl Jar {
+    pub fn open(path: &Path) -> Result<Jar, Box<dyn Error>> {
+        Jar::from_bytes(fs::read(path)?)
+    }
+
+    /// Reads the central directory; entries are only decompressed when they are read.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Jar, Box<dyn Error>> {
+        let end = find_end_of_central_directory(&bytes)?;
+        let mut cursor = Cursor::new(&bytes[end..]);
+        cursor.set_position(10);
+        let mut entry_count = cursor.read_u16::<LE>()? as u64;
+        let mut directory_size = cursor.read_u32::<LE>()? as u64;
+        let mut directory_offset = cursor.read_u32::<LE>()? as u64;
+
+        if entry_count == u16::MAX as u64
+            || directory_size == u32::MAX as u64
+            || directory_offset == u32::MAX as u64
+        {
+            let zip64_end = find_zip64_end_of_central_directory(&bytes, end)?;
+            let mut cursor = Cursor::new(&bytes[zip64_end..]);
+            cursor.set_position(32);
+            entry_count = cursor.read_u64::<LE>()?;
+            directory_size = cursor.read_u64::<LE>()?;
+            directory_offset = cursor.read_u64::<LE>()?;
+        }
+
+        let directory = usize::try_from(directory_offset)
+            .ok()
+            .zip(usize::try_from(directory_size).ok())
+            .and_then(|(offset, size)| bytes.get(offset..offset.checked_add(size)?))
+            .ok_or_else(|| invalid("central directory", "lies outside of the archive"))?;
+        let mut cursor = Cursor::new(directory);
+        let mut entries = Vec::new();
+        for _ in 0..entry_count {
+            entries.push(read_central_directory_header(&mut cursor)?);
+        }
+        Ok(Jar { bytes, entries })
+    }
+
+    pub fn get_entries(&self) -> &[JarEntry] {
+        &self.entries
+    }
+
+    pub fn get_entry(&self, name: &str) -> Option<&JarEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// The uncompressed contents of an entry, checked against its CRC-32.
+    pub fn read_entry(&self, entry: &JarEntry) -> Result<Vec<u8>, Box<dyn Error>> {
+        if entry.flags & ENCRYPTED != 0 {
+            return Err(invalid(&entry.name, "is encrypted"));
+        }
+        let header = usize::try_from(entry.local_header_offset)
+            .ok()
+            .and_then(|offset| self.bytes.get(offset..))
+            .ok_or_else(|| invalid(&entry.name, "local header lies outside of the archive"))?;
+        let mut cursor = Cursor::new(header);
+        if cursor.read_u32::<LE>()? != LOCAL_FILE_HEADER {
+            return Err(invalid(&entry.name, "local header signature is incorrect"));
+        }
+        // The sizes in the local header may be deferred to a data descriptor, so only the
+        // lengths of the variable fields are taken from it.
+        cursor.set_position(26);
+        let name_length = cursor.read_u16::<LE>()? as usize;
+        let extra_length = cursor.read_u16::<LE>()? as usize;
+        let start = 30 + name_length + extra_length;
+        let data = usize::try_from(entry.compressed_size)
+            .ok()
+            .and_then(|size| header.get(start..start.checked_add(size)?))
+            .ok_or_else(|| invalid(&entry.name, "data lies outside of the archive"))?;
+
+        let contents = match entry.method {
+            STORED => data.to_vec(),
+            DEFLATED => {
+                let limit = usize::try_from(entry.uncompressed_size).unwrap_or(usize::MAX);
+                decompress_to_vec_with_limit(data, limit)
+                    .map_err(|e| invalid(&entry.name, &format!("cannot be inflated: {e}")))?
+            }
+            method => {
+                return Err(invalid(
+                    &entry.name,
+                    &format!("uses unsupported compression method {method}"),
+                ))
+            }
+        };
+        if contents.len() as u64 != entry.uncompressed_size {
+            return Err(invalid(&entry.name, "uncompressed size is incorrect"));
+        }
+        if crc32(&contents) != entry.crc32 {
+            return Err(invalid(&entry.name, "CRC-32 is incorrect"));
+        }
+        Ok(contents)
+    }
+
+    /// Every `.class` entry in the order of the central directory.
+    pub fn get_classes(&self) -> Vec<JarClass<'_>> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_class())
+            .map(|entry| {
+                let class = self
+                    .read_entry(entry)
+                    .and_then(|bytes| ClassFile::from_bytes(&bytes));
+                (entry, class)
+            })
+            .collect()
+    }
+
+    pub fn get_manifest(&self) -> Result<Option<Manifest>, Box<dyn Error>> {
+        match self.get_entry(MANIFEST_NAME) {
+            Some(entry) => {
+                let bytes = self.read_entry(entry)?;
+                Ok(Some(Manifest::parse(&String::from_utf8_lossy(&bytes))?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /**
+     * The entries a Java `version` runtime sees, under the names it looks them up by.\
+     *  In a multi-release jar an entry under `META-INF/versions/N/` overrides the base entry
+     *  of the same name when 9 <= N <= version, and the highest such N wins. Versioned
+     *  entries outside of that range are hidden. Other jars are seen as they are.
+     */
+    pub fn get_versioned_entries(
+        &self,
+        version: u16,
+    ) -> Result<Vec<(&str, &JarEntry)>, Box<dyn Error>> {
+        let multi_release = self
+            .get_manifest()?
+            .is_some_and(|manifest| manifest.is_multi_release());
+        if !multi_release {
+            return Ok(self
+                .entries
+                .iter()
+                .map(|entry| (entry.name.as_str(), entry))
+                .collect());
+        }
+
+        let mut entries: Vec<(&str, &JarEntry, u16)> = Vec::new();
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for entry in &self.entries {
+            let (name, entry_version) = match entry.name.strip_prefix(VERSIONS_PREFIX) {
+                Some(versioned) => {
+                    let Some((directory, name)) = versioned.split_once('/') else {
+                        continue;
+                    };
+                    match directory.parse::<u16>() {
+                        Ok(n) if (FIRST_VERSION..=version).contains(&n) && !name.is_empty() => {
+                            (name, n)
+                        }
+                        _ => continue,
+                    }
+                }
+                None => (entry.name.as_str(), 0),
+            };
+            match positions.get(name) {
+                Some(&i) if entries[i].2 < entry_version => {
+                    entries[i] = (name, entry, entry_version)
+                }
+                Some(_) => {}
+                None => {
+                    positions.insert(name, entries.len());
+                    entries.push((name, entry, entry_version));
+                }
+            }
+        }
+        Ok(entries
+            .into_iter()
+            .map(|(name, entry, _)| (name, entry))
+            .collect())
+    }
+
+    /// The entry a Java `version` runtime loads for `name`, such as `com/example/A.class`.
+    pub fn get_versioned_entry(
+        &self,
+        name: &str,
+        version: u16,
+    ) -> Result<Option<&JarEntry>, Box<dyn Error>> {
+        Ok(self
+            .get_versioned_entries(version)?
+            .into_iter()
+            .find(|(entry_name, _)| *entry_name == name)
+            .map(|(_, entry)| entry))
+    }
+
+    /// The classes a Java `version` runtime sees, as get_classes does for every entry.
+    pub fn get_versioned_classes(&self, version: u16) -> Result<Vec<JarClass<'_>>, Box<dyn Error>> {
+        Ok(self
+            .get_versioned_entries(version)?
+            .into_iter()
+            .filter(|(name, _)| name.ends_with(".class"))
+            .map(|(_, entry)| {
+                let class = self
+                    .read_entry(entry)
+                    .and_then(|bytes| ClassFile::from_bytes(&bytes));
+                (entry, class)
+            })
+            .collect())
+    }
+}
+
+fn find_end_of_central_directory(bytes: &[u8]) -> Result<usize, Box<dyn Error>> {
+    let search_start = bytes.len().saturating_sub(MAX_END_OF_CENTRAL_DIRECTORY);
+    let signature = END_OF_CENTRAL_DIRECTORY.to_le_bytes();
+    (search_start..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&i| bytes[i..i + 4] == signature)
+        .ok_or_else(|| invalid("archive", "has no end of central directory record"))
+}
+
+fn find_zip64_end_of_central_directory(bytes: &[u8], end: usize) -> Result<usize, Box<dyn Error>> {
+    let locator = end
+        .checked_sub(20)
+        .ok_or_else(|| invalid("archive", "has no Zip64 end of central directory locator"))?;
+    let mut cursor = Cursor::new(&bytes[locator..]);
+    if cursor.read_u32::<LE>()? != ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR {
+        return Err(invalid(
+            "archive",
+            "has no Zip64 end of central directory locator",
+        ));
+    }
+    cursor.set_position(8);
+    let offset = usize::try_from(cursor.read_u64::<LE>()?)?;
+    let mut cursor = Cursor::new(
+        bytes
+            .get(offset..)
+            .ok_or_else(|| invalid("archive", "Zip64 end of central directory is missing"))?,
+    );
+    if cursor.read_u32::<LE>()? != ZIP64_END_OF_CENTRAL_DIRECTORY {
+        return Err(invalid(
+            "archive",
+            "Zip64 end of central directory signature is incorrect",
+        ));
+    }
+    Ok(offset)
+}
+
+fn read_central_directory_header(cursor: &mut Cursor<&[u8]>) -> Result<JarEntry, Box<dyn Error>> {
+    if cursor.read_u32::<LE>()? != CENTRAL_DIRECTORY_HEADER {
+        return Err(invalid(
+            "central directory",
+            "header signature is incorrect",
+        ));
+    }
+    let _version_made_by = cursor.read_u16::<LE>()?;
+    let _version_needed = cursor.read_u16::<LE>()?;
+    let flags = cursor.read_u16::<LE>()?;
+    let method = cursor.read_u16::<LE>()?;
+    let _last_modified = cursor.read_u32::<LE>()?;
+    let crc32 = cursor.read_u32::<LE>()?;
+    let mut compressed_size = cursor.read_u32::<LE>()? as u64;
+    let mut uncompressed_size = cursor.read_u32::<LE>()? as u64;
+    let name_length = cursor.read_u16::<LE>()? as usize;
+    let extra_length = cursor.read_u16::<LE>()? as usize;
+    let comment_length = cursor.read_u16::<LE>()? as u64;
+    let _disk_number = cursor.read_u16::<LE>()?;
+    let _internal_attributes = cursor.read_u16::<LE>()?;
+    let _external_attributes = cursor.read_u32::<LE>()?;
+    let mut local_header_offset = cursor.read_u32::<LE>()? as u64;
+
+    let start = cursor.position() as usize;
+    let variable = cursor.get_ref();
+    let name = variable
+        .get(start..start + name_length)
+        .ok_or_else(|| invalid("central directory", "name is truncated"))?;
+    // Jars are written with UTF-8 names whether or not general purpose bit 11 is set.
+    let name = String::from_utf8_lossy(name).into_owned();
+    let extra = variable
+        .get(start + name_length..start + name_length + extra_length)
+        .ok_or_else(|| invalid(&name, "extra field is truncated"))?;
+    cursor.set_position((start + name_length + extra_length) as u64 + comment_length);
+
+    // §4.5.3: the Zip64 field only holds the values whose header fields are saturated,
+    // in this order.
+    let mut extra = Cursor::new(extra);
+    while (extra.position() as usize) + 4 <= extra.get_ref().len() {
+        let id = extra.read_u16::<LE>()?;
+        let size = extra.read_u16::<LE>()? as u64;
+        let next = extra.position() + size;
+        if id == ZIP64_EXTENDED_INFORMATION {
+            if uncompressed_size == u32::MAX as u64 {
+                uncompressed_size = extra.read_u64::<LE>()?;
+            }
+            if compressed_size == u32::MAX as u64 {
+                compressed_size = extra.read_u64::<LE>()?;
+            }
+            if local_header_offset == u32::MAX as u64 {
+                local_header_offset = extra.read_u64::<LE>()?;
+            }
+        }
+        extra.set_position(next);
+    }
+
+    Ok(JarEntry {
+        name,
+        method,
+        flags,
+        crc32,
+        compressed_size,
+        uncompressed_size,
+        local_header_offset,
+    })
+}
+
+/// The CRC-32 of §4.4.7, using the reflected polynomial 0xEDB88320.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb88320 & 0u32.wrapping_sub(crc & 1));
+        }
+    }
+    !crc
+}
+
+fn invalid(name: &str, msg: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(
+        LoadingCause::InvalidArchive(name.into()),
+        msg,
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use miniz_oxide::deflate::compress_to_vec;
+
+    /// A class with only a this_class and super_class, which is all from_bytes needs.
+    #[rustfmt::skip]
+    pub(crate) const EMPTY_CLASS: [u8; 53] = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x34, 0x00, 0x05,
+        0x07, 0x00, 0x02,
+        0x01, 0x00, 0x01, b'A',
+        0x07, 0x00, 0x04,
+        0x01, 0x00, 0x10, b'j', b'a', b'v', b'a', b'/', b'l', b'a', b'n', b'g', b'/',
+        b'O', b'b', b'j', b'e', b'c', b't',
+        0x00, 0x21, 0x00, 0x01, 0x00, 0x03,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Writes a zip of `(name, method, contents)` entries, with Zip64 records if asked to.
+    pub(crate) fn write_zip(files: &[(&str, u16, &[u8])], zip64: bool) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut directory = Vec::new();
+        for (name, method, contents) in files {
+            let data = match *method {
+                DEFLATED => compress_to_vec(contents, 6),
+                _ => contents.to_vec(),
+            };
+            let offset = zip.len() as u32;
+            zip.extend(LOCAL_FILE_HEADER.to_le_bytes());
+            zip.extend([20, 0, 0, 0]);
+            zip.extend(method.to_le_bytes());
+            zip.extend([0; 16]);
+            zip.extend((name.len() as u16).to_le_bytes());
+            zip.extend([0, 0]);
+            zip.extend(name.as_bytes());
+            zip.extend(&data);
+
+            directory.extend(CENTRAL_DIRECTORY_HEADER.to_le_bytes());
+            directory.extend([20, 0, 20, 0, 0, 0]);
+            directory.extend(method.to_le_bytes());
+            directory.extend([0; 4]);
+            directory.extend(crc32(contents).to_le_bytes());
+            if zip64 {
+                directory.extend([0xff; 8]);
+            } else {
+                directory.extend((data.len() as u32).to_le_bytes());
+                directory.extend((contents.len() as u32).to_le_bytes());
+            }
+            directory.extend((name.len() as u16).to_le_bytes());
+            directory.extend((if zip64 { 28u16 } else { 0 }).to_le_bytes());
+            directory.extend([0; 10]);
+            directory.extend((if zip64 { u32::MAX } else { offset }).to_le_bytes());
+            directory.extend(name.as_bytes());
+            if zip64 {
+                directory.extend(ZIP64_EXTENDED_INFORMATION.to_le_bytes());
+                directory.extend(24u16.to_le_bytes());
+                directory.extend((contents.len() as u64).to_le_bytes());
+                directory.extend((data.len() as u64).to_le_bytes());
+                directory.extend((offset as u64).to_le_bytes());
+            }
+        }
+        let directory_offset = zip.len() as u64;
+        zip.extend(&directory);
+        if zip64 {
+            let end = zip.len() as u64;
+            zip.extend(ZIP64_END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+            zip.extend(44u64.to_le_bytes());
+            zip.extend([45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            zip.extend((files.len() as u64).to_le_bytes());
+            zip.extend((files.len() as u64).to_le_bytes());
+            zip.extend((directory.len() as u64).to_le_bytes());
+            zip.extend(directory_offset.to_le_bytes());
+            zip.extend(ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR.to_le_bytes());
+            zip.extend([0; 4]);
+            zip.extend(end.to_le_bytes());
+            zip.extend(1u32.to_le_bytes());
+            zip.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+            zip.extend([0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+            zip.extend([0xff; 8]);
+        } else {
+            zip.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+            zip.extend([0; 4]);
+            zip.extend((files.len() as u16).to_le_bytes());
+            zip.extend((files.len() as u16).to_le_bytes());
+            zip.extend((directory.len() as u32).to_le_bytes());
+            zip.extend((directory_offset as u32).to_le_bytes());
+        }
+        zip.extend([0, 0]);
+        zip
+    }
+
+    #[test]
+    fn test_read_classes() {
+        for zip64 in [false, true] {
+            let zip = write_zip(
+                &[
+                    ("META-INF/", STORED, b""),
+                    ("A.class", DEFLATED, &EMPTY_CLASS),
+                    ("B.class", STORED, &EMPTY_CLASS),
+                    ("Broken.class", DEFLATED, b"not a class"),
+                    ("readme.txt", STORED, b"hello"),
+                ],
+                zip64,
+            );
+            let jar = Jar::from_bytes(zip).unwrap();
+            assert_eq!(jar.get_entries().len(), 5);
+            assert!(jar.get_entry("META-INF/").unwrap().is_directory());
+            let readme = jar.get_entry("readme.txt").unwrap();
+            assert_eq!(jar.read_entry(readme).unwrap(), b"hello");
+
+            let classes = jar.get_classes();
+            let names: Vec<&str> = classes.iter().map(|(entry, _)| entry.get_name()).collect();
+            assert_eq!(names, ["A.class", "B.class", "Broken.class"]);
+            assert!(classes[0].1.is_ok());
+            assert!(classes[1].1.is_ok());
+            assert!(classes[2].1.is_err());
+        }
+    }
+
+    #[test]
+    fn test_multi_release_lookup() {
+        let manifest = b"Manifest-Version: 1.0\r\nMulti-Release: true\r\n\r\n";
+        let files: [(&str, u16, &[u8]); 6] = [
+            (MANIFEST_NAME, STORED, manifest),
+            ("A.class", STORED, b"base"),
+            ("META-INF/versions/11/A.class", STORED, b"11"),
+            ("META-INF/versions/9/A.class", STORED, b"9"),
+            ("META-INF/versions/17/B.class", STORED, b"17"),
+            ("META-INF/versions/8/A.class", STORED, b"8"),
+        ];
+        let jar = Jar::from_bytes(write_zip(&files, false)).unwrap();
+        let read = |name, version| {
+            let entry = jar.get_versioned_entry(name, version).unwrap();
+            entry.map(|entry| jar.read_entry(entry).unwrap())
+        };
+        assert_eq!(read("A.class", 8).unwrap(), b"base");
+        assert_eq!(read("A.class", 10).unwrap(), b"9");
+        assert_eq!(read("A.class", 17).unwrap(), b"11");
+        assert_eq!(read("B.class", 11), None);
+        assert_eq!(read("B.class", 17).unwrap(), b"17");
+
+        let names: Vec<&str> = jar
+            .get_versioned_entries(17)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, [MANIFEST_NAME, "A.class", "B.class"]);
+
+        // Without Multi-Release the versioned directories are ordinary entries.
+        let jar = Jar::from_bytes(write_zip(&files[1..], false)).unwrap();
+        assert_eq!(jar.get_versioned_entries(17).unwrap().len(), 5);
+        let entry = jar.get_versioned_entry("A.class", 17).unwrap().unwrap();
+        assert_eq!(jar.read_entry(entry).unwrap(), b"base");
+    }
+
+    #[test]
+    fn test_reject_corrupt_archives() {
+        assert!(Jar::from_bytes(b"PK\x03\x04".to_vec()).is_err());
+        let mut zip = write_zip(&[("A.class", STORED, &EMPTY_CLASS)], false);
+        // Flip a byte of the stored class so that its CRC-32 no longer matches.
+        zip[40] ^= 0xff;
+        let jar = Jar::from_bytes(zip).unwrap();
+        assert!(jar.read_entry(&jar.get_entries()[0]).is_err());
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+}
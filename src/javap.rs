@@ -0,0 +1,1844 @@
+use std::error::Error;
+
+use crate::access_flags::{
+    ClassAccessFlags, FieldAccessFlags, MethodAccessFlags, ModuleFlags, ParameterAccessFlags,
+};
+use crate::attributes::{
+    Annotation, Code, Element, ElementPairs, Module, ParamAnnotation, StackMapFrame, TargetInfo,
+    TypeAnnotation, VerificationTypeInfo,
+};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool, FieldInfo, MethodInfo};
+use crate::constants::{get_raw_class_name, get_raw_utf8};
+use crate::descriptors::{FieldType, MethodDescriptor};
+use crate::floating_decimal::{java_double, java_float};
+use crate::instructions::{self, Instruction, Operands};
+use crate::signatures::{ClassSignature, MethodSignature, TypeParameter, TypeSignature};
+
+/// The column `// comments` are aligned to, counted from the current indentation.
+const TAB_COLUMN: usize = 40;
+
+const CLASS_MODIFIERS: [(u16, &str); 3] = [
+    (ClassAccessFlags::AccPublic as u16, "public"),
+    (ClassAccessFlags::AccFinal as u16, "final"),
+    (ClassAccessFlags::AccAbstract as u16, "abstract"),
+];
+pub(crate) const INNER_CLASS_MODIFIERS: [(u16, &str); 6] = [
+    (ClassAccessFlags::AccPublic as u16, "public"),
+    (ClassAccessFlags::AccPrivate as u16, "private"),
+    (ClassAccessFlags::AccProtected as u16, "protected"),
+    (ClassAccessFlags::AccStatic as u16, "static"),
+    (ClassAccessFlags::AccFinal as u16, "final"),
+    (ClassAccessFlags::AccAbstract as u16, "abstract"),
+];
+pub(crate) const FIELD_MODIFIERS: [(u16, &str); 7] = [
+    (FieldAccessFlags::AccPublic as u16, "public"),
+    (FieldAccessFlags::AccPrivate as u16, "private"),
+    (FieldAccessFlags::AccProtected as u16, "protected"),
+    (FieldAccessFlags::AccStatic as u16, "static"),
+    (FieldAccessFlags::AccFinal as u16, "final"),
+    (FieldAccessFlags::AccVolatile as u16, "volatile"),
+    (FieldAccessFlags::AccTransient as u16, "transient"),
+];
+pub(crate) const METHOD_MODIFIERS: [(u16, &str); 9] = [
+    (MethodAccessFlags::AccPublic as u16, "public"),
+    (MethodAccessFlags::AccPrivate as u16, "private"),
+    (MethodAccessFlags::AccProtected as u16, "protected"),
+    (MethodAccessFlags::AccStatic as u16, "static"),
+    (MethodAccessFlags::AccFinal as u16, "final"),
+    (MethodAccessFlags::AccSynchronized as u16, "synchronized"),
+    (MethodAccessFlags::AccNative as u16, "native"),
+    (MethodAccessFlags::AccAbstract as u16, "abstract"),
+    (MethodAccessFlags::AccStrict as u16, "strictfp"),
+];
+
+/// The flags javap names in the comments of a Module attribute, in its order.
+const MODULE_FLAGS: [(u16, &str); 3] = [
+    (0x0020, "ACC_OPEN"),
+    (0x8000, "ACC_MANDATED"),
+    (0x1000, "ACC_SYNTHETIC"),
+];
+const REQUIRES_FLAGS: [(u16, &str); 4] = [
+    (0x0020, "ACC_TRANSITIVE"),
+    (0x0040, "ACC_STATIC_PHASE"),
+    (0x1000, "ACC_SYNTHETIC"),
+    (0x8000, "ACC_MANDATED"),
+];
+const EXPORTS_FLAGS: [(u16, &str); 2] = [(0x8000, "ACC_MANDATED"), (0x1000, "ACC_SYNTHETIC")];
+
+/// The least accessible members that are shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessFilter {
+    /// `-public`
+    Public,
+    /// `-protected`
+    Protected,
+    /// `-package`, the default: everything but private members.
+    #[default]
+    Package,
+    /// `-p` or `-private`
+    Private,
+}
+
+impl AccessFilter {
+    /// The visibility flags sit at the same bits for classes, fields and methods.
+    fn shows(&self, flags: u16) -> bool {
+        let flags = ClassAccessFlags::from_u16(flags);
+        let is_public = flags.contains(&ClassAccessFlags::AccPublic);
+        match self {
+            AccessFilter::Public => is_public,
+            AccessFilter::Protected => is_public || flags.contains(&ClassAccessFlags::AccProtected),
+            AccessFilter::Package => !flags.contains(&ClassAccessFlags::AccPrivate),
+            AccessFilter::Private => true,
+        }
+    }
+}
+
+/// [javap](https://docs.oracle.com/en/java/javase/17/docs/specs/man/javap.html)
+#[derive(Clone, Debug, Default)]
+pub struct JavapOptions {
+    pub access: AccessFilter,
+    /// `-c`, print the instructions of each method.
+    pub disassemble: bool,
+    /// `-v`, print the constant pool, flags and every attribute.
+    pub verbose: bool,
+    /// `-l`, print line number and local variable tables.
+    pub lines: bool,
+    /// `-s`, print the internal type descriptor of each member.
+    pub descriptors: bool,
+    /// `-constants`, print the values of static final constants.
+    pub constants: bool,
+}
+
+/// Writes a class file the way javap does for the same options.
+pub struct Javap<'a> {
+    class: &'a ClassFile,
+    options: &'a JavapOptions,
+    output: String,
+    indent: usize,
+    pending_newline: bool,
+}
+
+impl<'a> Javap<'a> {
+    pub fn new(class: &'a ClassFile, options: &'a JavapOptions) -> Javap<'a> {
+        Javap {
+            class,
+            options,
+            output: String::new(),
+            indent: 0,
+            pending_newline: false,
+        }
+    }
+
+    /**
+     * Everything javap prints for the class after the `Classfile` header, which depends on
+     * where the class was read from and is left to the caller.
+     */
+    pub fn disassemble(mut self) -> Result<String, Box<dyn Error>> {
+        self.write_class()?;
+        Ok(self.output)
+    }
+
+    fn println(&mut self, text: &str) {
+        for line in text.split('\n') {
+            let line = line.trim_end();
+            if !line.is_empty() {
+                self.output.push_str(&" ".repeat(self.indent * 2));
+                self.output.push_str(line);
+            }
+            self.output.push('\n');
+        }
+    }
+
+    fn write_class(&mut self) -> Result<(), Box<dyn Error>> {
+        let class = self.class;
+        let verbose = self.options.verbose;
+        let flags = ClassAccessFlags::to_u16(&class.access_flags);
+        let is_interface = class.access_flags.contains(&ClassAccessFlags::AccInterface);
+
+        self.indent = verbose as usize;
+        if let Some(source_file) = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::SourceFile(source) => Some(self.utf8(source.sourcefile_index)),
+                _ => None,
+            })
+        {
+            self.println(&format!("Compiled from \"{source_file}\""));
+        }
+        self.indent = 0;
+
+        let modifier_flags = if is_interface {
+            flags & !(ClassAccessFlags::AccAbstract as u16)
+        } else {
+            flags
+        };
+        let mut line = get_modifiers(modifier_flags, &CLASS_MODIFIERS);
+        let module = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Module(module) => Some(module),
+                _ => None,
+            })
+            .filter(|_| class.access_flags.contains(&ClassAccessFlags::AccModule));
+        if let Some(module) = module {
+            let flags = ModuleFlags::ModuleAccessFlags::to_u16(&module.module_flags);
+            if flags & 0x0020 != 0 {
+                line.push_str("open ");
+            }
+            line.push_str("module ");
+            line.push_str(&java_name(&self.module_name(module.module_name_index)));
+            if module.module_version_index != 0 {
+                line.push('@');
+                line.push_str(&self.utf8(module.module_version_index));
+            }
+        } else {
+            line.push_str(if is_interface { "interface " } else { "class " });
+            line.push_str(&java_name(&self.class_name(class.this_class)));
+        }
+        match self
+            .get_signature(&class.attributes)
+            .map(|s| ClassSignature::parse(&s))
+        {
+            _ if module.is_some() => {}
+            Some(Ok(signature)) => {
+                line.push_str(&self.type_parameters(&signature.type_parameters));
+                let is_object = signature.superclass.get_binary_name() == "java/lang/Object";
+                if !is_interface && (verbose || !is_object) {
+                    line.push_str(&format!(" extends {}", signature.superclass));
+                }
+                for (i, interface) in signature.interfaces.iter().enumerate() {
+                    line.push_str(match (i, is_interface) {
+                        (0, true) => " extends ",
+                        (0, false) => " implements ",
+                        _ => ", ",
+                    });
+                    line.push_str(&interface.to_string());
+                }
+            }
+            _ => {
+                let super_name = java_name(&self.class_name(class.super_class));
+                if !is_interface && class.super_class != 0 && super_name != "java.lang.Object" {
+                    line.push_str(&format!(" extends {super_name}"));
+                }
+                for (i, interface) in class.interfaces.iter().enumerate() {
+                    line.push_str(match (i, is_interface) {
+                        (0, true) => " extends ",
+                        (0, false) => " implements ",
+                        _ => ",",
+                    });
+                    line.push_str(&java_name(&self.class_name(*interface)));
+                }
+            }
+        }
+
+        if verbose {
+            self.println(&line);
+            self.indent = 1;
+            self.println(&format!("minor version: {}", class.minor_version));
+            self.println(&format!("major version: {}", class.major_version));
+            let names: Vec<String> = class.access_flags.iter().map(|f| f.get_string()).collect();
+            self.println(&get_flags_line(flags, &names));
+            self.println(&with_comment(
+                &format!("this_class: #{}", class.this_class),
+                &self.string_value(class.this_class),
+            ));
+            if class.super_class == 0 {
+                self.println("super_class: #0");
+            } else {
+                self.println(&with_comment(
+                    &format!("super_class: #{}", class.super_class),
+                    &self.string_value(class.super_class),
+                ));
+            }
+            self.println(&format!(
+                "interfaces: {}, fields: {}, methods: {}, attributes: {}",
+                class.interfaces_count,
+                class.field_count,
+                class.methods_count,
+                class.attributes_count
+            ));
+            self.indent = 0;
+            self.write_constant_pool();
+            self.println("{");
+        } else {
+            self.println(&format!("{line} {{"));
+        }
+
+        self.indent = 1;
+        if let Some(module) = module.filter(|_| !verbose) {
+            self.write_directives(module);
+        }
+        for field in &class.fields {
+            self.write_field(field);
+        }
+        for method in &class.methods {
+            self.write_method(method)?;
+        }
+        self.indent = 0;
+        self.println("}");
+
+        if verbose {
+            for attribute in &class.attributes {
+                self.write_attribute(attribute, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The declarations of a module-info class, as they would appear in its source.
+    fn write_directives(&mut self, module: &Module) {
+        for requires in &module.requires {
+            let flags = ModuleFlags::RequiresAccessFlags::to_u16(&requires.requires_flags);
+            let mut line = String::from("requires");
+            if flags & 0x0040 != 0 {
+                line.push_str(" static");
+            }
+            if flags & 0x0020 != 0 {
+                line.push_str(" transitive");
+            }
+            let name = self.module_name(requires.requires_index);
+            self.println(&format!("{line} {name};"));
+        }
+        for exports in &module.exports {
+            let package = java_name(&self.package_name(exports.exports_index));
+            let targets: Vec<String> = exports
+                .exports_to_index
+                .iter()
+                .map(|index| self.module_name(*index))
+                .collect();
+            self.write_directive(&format!("exports {package}"), " to", &targets);
+        }
+        for opens in &module.opens {
+            let package = java_name(&self.package_name(opens.opens_index));
+            let targets: Vec<String> = opens
+                .opens_to_index
+                .iter()
+                .map(|index| self.module_name(*index))
+                .collect();
+            self.write_directive(&format!("opens {package}"), " to", &targets);
+        }
+        for uses in &module.uses_index {
+            let service = java_name(&self.class_name(*uses));
+            self.println(&format!("uses {service};"));
+        }
+        for provides in &module.provides {
+            let implementations: Vec<String> = provides
+                .provides_with_index
+                .iter()
+                .map(|index| java_name(&self.class_name(*index)))
+                .collect();
+            // javap prints two spaces after `provides`.
+            let service = java_name(&self.class_name(provides.provides_index));
+            self.write_directive(&format!("provides  {service}"), " with", &implementations);
+        }
+    }
+
+    /// `directive` followed by `keyword` and the names one per line, if there are any.
+    fn write_directive(&mut self, directive: &str, keyword: &str, names: &[String]) {
+        if names.is_empty() {
+            self.println(&format!("{directive};"));
+            return;
+        }
+        self.println(&format!("{directive}{keyword}"));
+        self.indent += 1;
+        self.println(&format!("{};", names.join(",\n")));
+        self.indent -= 1;
+    }
+
+    fn write_constant_pool(&mut self) {
+        self.println("Constant pool:");
+        self.indent = 1;
+        let pool = &self.class.constant_pool;
+        let width = self.class.constant_pool_count.to_string().len() + 1;
+        // The pool also holds index 0 and a trailing StackMapTable name, neither of which
+        // are part of the class file's constant_pool table.
+        for index in 1..self.class.constant_pool_count {
+            let (kind, text, comment) = match &pool[index as usize] {
+                ConstantPool::Utf8(_) => ("Utf8", self.string_value(index), None),
+                ConstantPool::Integer(_) => ("Integer", self.string_value(index), None),
+                ConstantPool::Float(_) => ("Float", self.string_value(index), None),
+                ConstantPool::Long(_) => ("Long", self.string_value(index), None),
+                ConstantPool::Double(_) => ("Double", self.string_value(index), None),
+                ConstantPool::Class(class) => ("Class", format!("#{}", class.name_index), None),
+                ConstantPool::String(string) => {
+                    ("String", format!("#{}", string.string_index), None)
+                }
+                ConstantPool::Fieldref(field) => (
+                    "Fieldref",
+                    format!("#{}.#{}", field.class_index, field.name_and_type_index),
+                    None,
+                ),
+                ConstantPool::Methodref(method) => (
+                    "Methodref",
+                    format!("#{}.#{}", method.class_index, method.name_and_type_index),
+                    None,
+                ),
+                ConstantPool::InterfaceMethodRef(method) => (
+                    "InterfaceMethodref",
+                    format!("#{}.#{}", method.class_index, method.name_and_type_index),
+                    None,
+                ),
+                ConstantPool::NameAndType(name_and_type) => (
+                    "NameAndType",
+                    format!(
+                        "#{}:#{}",
+                        name_and_type.name_index, name_and_type.descriptor_index
+                    ),
+                    None,
+                ),
+                ConstantPool::MethodHandle(handle) => (
+                    "MethodHandle",
+                    format!("{}:#{}", handle.reference_kind, handle.reference_index),
+                    None,
+                ),
+                // javap separates the descriptor of a MethodType with an extra space.
+                ConstantPool::MethodType(method_type) => (
+                    "MethodType",
+                    format!("#{}", method_type.descriptor_index),
+                    Some(format!(" {}", self.string_value(index))),
+                ),
+                ConstantPool::Dynamic(dynamic) => (
+                    "Dynamic",
+                    format!(
+                        "#{}:#{}",
+                        dynamic.bootstrap_method_attr_index, dynamic.name_and_type_index
+                    ),
+                    None,
+                ),
+                ConstantPool::InvokeDynamic(dynamic) => (
+                    "InvokeDynamic",
+                    format!(
+                        "#{}:#{}",
+                        dynamic.bootstrap_method_attr_index, dynamic.name_and_type_index
+                    ),
+                    None,
+                ),
+                ConstantPool::Module(module) => ("Module", format!("#{}", module.name_index), None),
+                ConstantPool::Package(package) => {
+                    ("Package", format!("#{}", package.name_index), None)
+                }
+                // The unusable entry following a long or double.
+                ConstantPool::Unknown => continue,
+            };
+            let line = format!("{:>width$} = {kind:<18} {text}", format!("#{index}"));
+            let line = match &pool[index as usize] {
+                ConstantPool::Utf8(_)
+                | ConstantPool::Integer(_)
+                | ConstantPool::Float(_)
+                | ConstantPool::Long(_)
+                | ConstantPool::Double(_) => line,
+                _ => with_comment(&line, &comment.unwrap_or_else(|| self.string_value(index))),
+            };
+            self.println(&line);
+        }
+        self.indent = 0;
+    }
+
+    fn write_field(&mut self, field: &FieldInfo) {
+        let flags = FieldAccessFlags::to_u16(&field.access_flags);
+        if !self.options.access.shows(flags) {
+            return;
+        }
+        let descriptor = self.utf8(field.descriptor_index);
+        let mut line = get_modifiers(flags, &FIELD_MODIFIERS);
+        match self
+            .get_signature(&field.attributes)
+            .and_then(|signature| TypeSignature::parse(&signature).ok())
+        {
+            Some(signature) => line.push_str(&signature.to_string()),
+            None => line.push_str(&java_type(&descriptor)),
+        }
+        line.push(' ');
+        line.push_str(&self.utf8(field.name_index));
+        if self.options.constants {
+            if let Some(index) = field
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    AttributeInfo::ConstantValue(value) => Some(value.constantvalue_index),
+                    _ => None,
+                })
+            {
+                line.push_str(" = ");
+                line.push_str(&self.get_constant_value(&descriptor, index));
+            }
+        }
+        line.push(';');
+        self.println(&line);
+
+        self.indent += 1;
+        if self.options.descriptors || self.options.verbose {
+            self.println(&format!("descriptor: {descriptor}"));
+        }
+        if self.options.verbose {
+            let names: Vec<String> = field.access_flags.iter().map(|f| f.get_string()).collect();
+            self.println(&get_flags_line(flags, &names));
+            for attribute in &field.attributes {
+                // Field attributes never contain code, so this cannot fail.
+                let _ = self.write_attribute(attribute, None);
+            }
+        }
+        self.indent -= 1;
+        if self.options.verbose || self.options.disassemble || self.options.lines {
+            self.println("");
+        }
+    }
+
+    fn write_method(&mut self, method: &MethodInfo) -> Result<(), Box<dyn Error>> {
+        let flags = MethodAccessFlags::to_u16(&method.access_flags);
+        if !self.options.access.shows(flags) {
+            return Ok(());
+        }
+        if self.pending_newline {
+            self.println("");
+        }
+        let name = self.utf8(method.name_index);
+        let descriptor = self.utf8(method.descriptor_index);
+        let signature = self
+            .get_signature(&method.attributes)
+            .and_then(|signature| MethodSignature::parse(&signature).ok());
+
+        let mut line = get_modifiers(flags, &METHOD_MODIFIERS);
+        let is_interface = self
+            .class
+            .access_flags
+            .contains(&ClassAccessFlags::AccInterface);
+        let is_default = [
+            MethodAccessFlags::AccAbstract,
+            MethodAccessFlags::AccStatic,
+            MethodAccessFlags::AccPrivate,
+        ]
+        .iter()
+        .all(|flag| !method.access_flags.contains(flag));
+        if is_interface && is_default && name != "<clinit>" {
+            line.push_str("default ");
+        }
+        let (mut parameters, return_type) = match &signature {
+            Some(signature) => {
+                if !signature.type_parameters.is_empty() {
+                    line.push_str(&self.type_parameters(&signature.type_parameters));
+                    line.push(' ');
+                }
+                let parameters: Vec<String> =
+                    signature.parameters.iter().map(|p| p.to_string()).collect();
+                let return_type = match &signature.return_type {
+                    Some(return_type) => return_type.to_string(),
+                    None => "void".into(),
+                };
+                (parameters.join(", "), return_type)
+            }
+            None => match MethodDescriptor::from_descriptor(&descriptor) {
+                Ok(parsed) => {
+                    let parameters: Vec<String> = parsed
+                        .parameters
+                        .iter()
+                        .map(|p| p.get_java_name())
+                        .collect();
+                    (parameters.join(", "), parsed.get_return_java_name())
+                }
+                Err(_) => (descriptor.clone(), String::new()),
+            },
+        };
+        if method.access_flags.contains(&MethodAccessFlags::AccVarArgs) {
+            if let Some(i) = parameters.rfind("[]") {
+                parameters.replace_range(i..i + 2, "...");
+            }
+        }
+        match name.as_str() {
+            "<init>" => line.push_str(&format!(
+                "{}({parameters})",
+                java_name(&self.class_name(self.class.this_class))
+            )),
+            "<clinit>" => line.push_str("{}"),
+            _ => line.push_str(&format!("{return_type} {name}({parameters})")),
+        }
+        if let Some(exceptions) = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Exceptions(exceptions) => Some(exceptions),
+                _ => None,
+            })
+        {
+            let throws: Vec<String> = match &signature {
+                Some(signature) if !signature.throws.is_empty() => {
+                    signature.throws.iter().map(|t| t.to_string()).collect()
+                }
+                _ => exceptions
+                    .exception_index_table
+                    .iter()
+                    .map(|index| java_name(&self.class_name(*index)))
+                    .collect(),
+            };
+            line.push_str(" throws ");
+            line.push_str(&throws.join(", "));
+        }
+        line.push(';');
+        self.println(&line);
+
+        self.indent += 1;
+        if self.options.descriptors || self.options.verbose {
+            self.println(&format!("descriptor: {descriptor}"));
+        }
+        if self.options.verbose {
+            let names: Vec<String> = method.access_flags.iter().map(|f| f.get_string()).collect();
+            self.println(&get_flags_line(flags, &names));
+            for attribute in &method.attributes {
+                self.write_attribute(attribute, Some(method))?;
+            }
+        } else if let Some(code) = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Code(code) => Some(code),
+                _ => None,
+            })
+        {
+            if self.options.disassemble {
+                self.println("Code:");
+                self.write_instructions(code)?;
+                self.write_exception_table(code);
+            }
+            if self.options.lines {
+                for attribute in &code.attributes {
+                    if let AttributeInfo::LineNumberTable(_)
+                    | AttributeInfo::LocalVariableTable(_) = attribute
+                    {
+                        self.write_attribute(attribute, None)?;
+                    }
+                }
+            }
+        }
+        self.indent -= 1;
+        self.pending_newline = self.options.descriptors
+            || self.options.verbose
+            || self.options.disassemble
+            || self.options.lines;
+        Ok(())
+    }
+
+    fn write_instructions(&mut self, code: &Code) -> Result<(), Box<dyn Error>> {
+        for instruction in code.get_instructions()? {
+            let line = self.format_instruction(&instruction);
+            self.println(&line);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn format_instruction(&self, instruction: &Instruction) -> String {
+        let head = format!("{:4}: {:<13} ", instruction.pc, instruction.get_mnemonic());
+        // Continuation lines of a switch are indented past the `pc: ` column.
+        let continuation = " ".repeat(6);
+        match &instruction.operands {
+            Operands::None => head,
+            Operands::Immediate(value) => format!("{head}{value}"),
+            Operands::Local(index) => format!("{head}{index}"),
+            Operands::Branch(target) => format!("{head}{target}"),
+            Operands::Increment { index, value } => format!("{head}{index}, {value}"),
+            Operands::Constant(index) => {
+                with_comment(&format!("{head}#{index}"), &self.constant_comment(*index))
+            }
+            Operands::InvokeInterface { index, count } => with_comment(
+                &format!("{head}#{index},  {count}"),
+                &self.constant_comment(*index),
+            ),
+            Operands::InvokeDynamic(index) => with_comment(
+                &format!("{head}#{index},  0"),
+                &self.constant_comment(*index),
+            ),
+            Operands::MultiANewArray { index, dimensions } => with_comment(
+                &format!("{head}#{index},  {dimensions}"),
+                &self.constant_comment(*index),
+            ),
+            Operands::NewArray(atype) => match instructions::get_array_type_name(*atype) {
+                Some(name) => format!("{head} {name}"),
+                None => format!("{head} {atype}"),
+            },
+            Operands::TableSwitch {
+                default,
+                low,
+                high,
+                targets,
+            } => {
+                let mut text = format!("{head}{{ // {low} to {high}");
+                for (key, target) in (*low..=*high).zip(targets) {
+                    text.push_str(&format!("\n{continuation}{key:12}: {target}"));
+                }
+                text.push_str(&format!(
+                    "\n{continuation}{:>12}: {default}\n{continuation}}}",
+                    "default"
+                ));
+                text
+            }
+            Operands::LookupSwitch { default, pairs } => {
+                let mut text = format!("{head}{{ // {}", pairs.len());
+                for (key, target) in pairs {
+                    text.push_str(&format!("\n{continuation}{key:12}: {target}"));
+                }
+                text.push_str(&format!(
+                    "\n{continuation}{:>12}: {default}\n{continuation}}}",
+                    "default"
+                ));
+                text
+            }
+        }
+    }
+
+    fn write_exception_table(&mut self, code: &Code) {
+        if code.exception_tables.is_empty() {
+            return;
+        }
+        self.println("Exception table:");
+        self.println("   from    to  target type");
+        for entry in &code.exception_tables {
+            let catch_type = if entry.catch_type == 0 {
+                "any".to_string()
+            } else {
+                format!("Class {}", self.string_value(entry.catch_type))
+            };
+            self.println(&format!(
+                "   {:5} {:5} {:5}   {catch_type}",
+                entry.start_pc, entry.end_pc, entry.handler_pc
+            ));
+        }
+    }
+
+    /// Writes a single attribute as `javap -v` does. `method` is given for method attributes.
+    fn write_attribute(
+        &mut self,
+        attribute: &AttributeInfo,
+        method: Option<&MethodInfo>,
+    ) -> Result<(), Box<dyn Error>> {
+        match attribute {
+            AttributeInfo::Code(code) => {
+                self.println("Code:");
+                self.indent += 1;
+                let mut args_size = method
+                    .and_then(|method| {
+                        MethodDescriptor::from_descriptor(&self.utf8(method.descriptor_index)).ok()
+                    })
+                    .map(|descriptor| descriptor.parameters.len())
+                    .unwrap_or_default();
+                if let Some(method) = method {
+                    if !method.access_flags.contains(&MethodAccessFlags::AccStatic) {
+                        args_size += 1;
+                    }
+                }
+                self.println(&format!(
+                    "stack={}, locals={}, args_size={args_size}",
+                    code.max_stack, code.max_locals
+                ));
+                self.write_instructions(code)?;
+                self.write_exception_table(code);
+                for attribute in &code.attributes {
+                    self.write_attribute(attribute, None)?;
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::ConstantValue(value) => {
+                let constant = self.constant_comment(value.constantvalue_index);
+                self.println(&format!("ConstantValue: {constant}"));
+            }
+            AttributeInfo::Signature(signature) => {
+                self.println(&with_comment(
+                    &format!("Signature: #{}", signature.signature_index),
+                    &self.utf8(signature.signature_index),
+                ));
+            }
+            AttributeInfo::SourceFile(source) => {
+                let source_file = self.utf8(source.sourcefile_index);
+                self.println(&format!("SourceFile: \"{source_file}\""));
+            }
+            AttributeInfo::Exceptions(exceptions) => {
+                let throws: Vec<String> = exceptions
+                    .exception_index_table
+                    .iter()
+                    .map(|index| java_name(&self.class_name(*index)))
+                    .collect();
+                self.println("Exceptions:");
+                self.indent += 1;
+                self.println(&format!("throws {}", throws.join(", ")));
+                self.indent -= 1;
+            }
+            AttributeInfo::LineNumberTable(table) => {
+                self.println("LineNumberTable:");
+                self.indent += 1;
+                for line in &table.line_number_table {
+                    self.println(&format!("line {}: {}", line.line_number, line.start_pc));
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::LocalVariableTable(table) => {
+                self.println("LocalVariableTable:");
+                self.indent += 1;
+                self.println("Start  Length  Slot  Name   Signature");
+                for local in &table.local_variable_table {
+                    self.println(&format!(
+                        "{:5} {:7} {:5} {:>5}   {}",
+                        local.start_pc,
+                        local.length,
+                        local.index,
+                        self.utf8(local.name_index),
+                        self.utf8(local.descriptor_index)
+                    ));
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::LocalVariableTypeTable(table) => {
+                self.println("LocalVariableTypeTable:");
+                self.indent += 1;
+                self.println("Start  Length  Slot  Name   Signature");
+                for local in &table.local_variable_type_table {
+                    self.println(&format!(
+                        "{:5} {:7} {:5} {:>5}   {}",
+                        local.start_pc,
+                        local.length,
+                        local.index,
+                        self.utf8(local.name_index),
+                        self.utf8(local.signature_index)
+                    ));
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::StackMapTable(table) => {
+                // Skip the implicit table the reader adds to Code attributes that have none.
+                if table.attribute_name_index < self.class.constant_pool_count {
+                    self.write_stack_map_table(&table.entries);
+                }
+            }
+            AttributeInfo::InnerClasses(inner_classes) => {
+                let mut first = true;
+                for info in &inner_classes.classes {
+                    let flags = info.inner_class_access_flags;
+                    if !self.options.access.shows(flags) {
+                        continue;
+                    }
+                    if first {
+                        self.println("InnerClasses:");
+                        self.indent += 1;
+                        first = false;
+                    }
+                    let is_interface =
+                        ClassAccessFlags::from_u16(flags).contains(&ClassAccessFlags::AccInterface);
+                    let modifier_flags = if is_interface {
+                        flags & !(ClassAccessFlags::AccAbstract as u16)
+                    } else {
+                        flags
+                    };
+                    let mut line = get_modifiers(modifier_flags, &INNER_CLASS_MODIFIERS);
+                    let mut comment = String::new();
+                    if info.inner_name_index != 0 {
+                        line.push_str(&format!("#{}= ", info.inner_name_index));
+                        comment.push_str(&format!("{}=", self.utf8(info.inner_name_index)));
+                    }
+                    line.push_str(&format!("#{}", info.inner_class_info_index));
+                    comment.push_str(&self.constant_comment(info.inner_class_info_index));
+                    if info.outer_class_info_index != 0 {
+                        line.push_str(&format!(" of #{}", info.outer_class_info_index));
+                        comment.push_str(" of ");
+                        comment.push_str(&self.constant_comment(info.outer_class_info_index));
+                    }
+                    line.push(';');
+                    self.println(&with_comment(&line, &comment));
+                }
+                if !first {
+                    self.indent -= 1;
+                }
+            }
+            AttributeInfo::EnclosingMethod(enclosing) => {
+                let mut comment = java_name(&self.class_name(enclosing.class_index));
+                if let Some(ConstantPool::NameAndType(method)) = self
+                    .class
+                    .constant_pool
+                    .get(enclosing.method_index as usize)
+                {
+                    comment.push('.');
+                    comment.push_str(&self.utf8(method.name_index));
+                }
+                self.println(&with_comment(
+                    &format!(
+                        "EnclosingMethod: #{}.#{}",
+                        enclosing.class_index, enclosing.method_index
+                    ),
+                    &comment,
+                ));
+            }
+            AttributeInfo::NestHost(host) => {
+                let host = self.string_value(host.host_class_index);
+                self.println(&format!("NestHost: class {host}"));
+            }
+            AttributeInfo::NestMembers(members) => {
+                self.write_class_list("NestMembers", &members.classes);
+            }
+            AttributeInfo::PermittedSubclasses(permitted) => {
+                self.write_class_list("PermittedSubclasses", &permitted.classes);
+            }
+            AttributeInfo::BootstrapMethods(bootstrap) => {
+                self.println("BootstrapMethods:");
+                self.indent += 1;
+                for (i, method) in bootstrap.bootstrap_methods.iter().enumerate() {
+                    self.println(&format!(
+                        "{i}: #{} {}",
+                        method.bootstrap_method_ref,
+                        self.string_value(method.bootstrap_method_ref)
+                    ));
+                    self.indent += 1;
+                    self.println("Method arguments:");
+                    self.indent += 1;
+                    for argument in &method.bootstrap_arguments {
+                        self.println(&format!("#{argument} {}", self.string_value(*argument)));
+                    }
+                    self.indent -= 2;
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::SourceDebugExtension(extension) => {
+                self.println("SourceDebugExtension:");
+                self.indent += 1;
+                for line in extension.debug_extension.lines() {
+                    self.println(line);
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::Deprecated(_) => self.println("Deprecated: true"),
+            AttributeInfo::Synthetic(_) => self.println("Synthetic: true"),
+            AttributeInfo::RuntimeVisibleAnnotations(annotations) => {
+                self.write_annotations("RuntimeVisibleAnnotations", &annotations.annotations);
+            }
+            AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+                self.write_annotations("RuntimeInvisibleAnnotations", &annotations.annotations);
+            }
+            AttributeInfo::AnnotationDefault(default) => {
+                self.println("AnnotationDefault:");
+                self.indent += 1;
+                self.println(&format!(
+                    "default_value: {}",
+                    element_indices(&default.default_value)
+                ));
+                self.indent += 1;
+                self.println(&self.element_value(&default.default_value));
+                self.indent -= 2;
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations(annotations) => {
+                self.write_parameter_annotations(
+                    "RuntimeVisibleParameterAnnotations",
+                    &annotations.parameter_annotations,
+                );
+            }
+            AttributeInfo::RuntimeInvisibleParameterAnnotations(annotations) => {
+                self.write_parameter_annotations(
+                    "RuntimeInvisibleParameterAnnotations",
+                    &annotations.parameter_annotations,
+                );
+            }
+            AttributeInfo::RuntimeVisibleTypeAnnotations(annotations) => {
+                self.write_type_annotations(
+                    "RuntimeVisibleTypeAnnotations",
+                    &annotations.type_annotations,
+                );
+            }
+            AttributeInfo::RuntimeInvisibleTypeAnnotations(annotations) => {
+                self.write_type_annotations(
+                    "RuntimeInvisibleTypeAnnotations",
+                    &annotations.type_annotations,
+                );
+            }
+            AttributeInfo::MethodParameters(parameters) => {
+                self.println("MethodParameters:");
+                self.indent += 1;
+                self.println(&format!("{:<31}Flags", "Name"));
+                for parameter in &parameters.parameters {
+                    let name = match parameter.name_index {
+                        0 => "<no name>".into(),
+                        index => self.utf8(index),
+                    };
+                    let flags = ParameterAccessFlags::to_u16(&parameter.access_flags);
+                    let flags: String = [
+                        (0x0010, "final "),
+                        (0x8000, "mandated "),
+                        (0x1000, "synthetic"),
+                    ]
+                    .iter()
+                    .filter(|(flag, _)| flags & flag != 0)
+                    .map(|(_, name)| *name)
+                    .collect();
+                    self.println(&format!("{name:<31}{flags}"));
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::Record(record) => {
+                self.println("Record:");
+                self.indent += 1;
+                for component in &record.components {
+                    let descriptor = self.utf8(component.descriptor_index);
+                    let java_type = match self
+                        .get_signature(&component.attributes)
+                        .and_then(|signature| TypeSignature::parse(&signature).ok())
+                    {
+                        Some(signature) => signature.to_string(),
+                        None => java_type(&descriptor),
+                    };
+                    let name = self.utf8(component.name_index);
+                    self.println(&format!("{java_type} {name};"));
+                    self.indent += 1;
+                    self.println(&format!("descriptor: {descriptor}"));
+                    for attribute in &component.attributes {
+                        self.write_attribute(attribute, None)?;
+                    }
+                    self.indent -= 1;
+                    self.println("");
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::Module(module) => self.write_module(module),
+            AttributeInfo::ModulePackages(packages) => {
+                self.println("ModulePackages:");
+                self.indent += 1;
+                for package in &packages.package_index {
+                    let name = java_name(&self.string_value(*package));
+                    self.println(&with_comment(&format!("#{package}"), &name));
+                }
+                self.indent -= 1;
+            }
+            AttributeInfo::ModuleMainClass(main_class) => {
+                let name = java_name(&self.string_value(main_class.main_class_index));
+                self.println(&with_comment(
+                    &format!("ModuleMainClass: #{}", main_class.main_class_index),
+                    &name,
+                ));
+            }
+            AttributeInfo::Unknown(unknown) => {
+                self.println(&format!("{}: (unknown attribute)", unknown.get_name()));
+            }
+            _ => self.println(&format!("{}:", attribute.get_name())),
+        }
+        Ok(())
+    }
+
+    /// The Module attribute with the constant pool indices of each directive.
+    fn write_module(&mut self, module: &Module) {
+        self.println("Module:");
+        self.indent += 1;
+        let flags = ModuleFlags::ModuleAccessFlags::to_u16(&module.module_flags);
+        self.println(&with_comment(
+            &format!("#{},{flags:x}", module.module_name_index),
+            &format!(
+                "{}{}",
+                self.string_value(module.module_name_index),
+                flag_names(flags, &MODULE_FLAGS)
+            ),
+        ));
+        self.write_version(module.module_version_index);
+
+        self.println(&with_comment(
+            &module.requires.len().to_string(),
+            "requires",
+        ));
+        self.indent += 1;
+        for requires in &module.requires {
+            let flags = ModuleFlags::RequiresAccessFlags::to_u16(&requires.requires_flags);
+            self.println(&with_comment(
+                &format!("#{},{flags:x}", requires.requires_index),
+                &format!(
+                    "{}{}",
+                    self.string_value(requires.requires_index),
+                    flag_names(flags, &REQUIRES_FLAGS)
+                ),
+            ));
+            self.write_version(requires.requires_version_index);
+        }
+        self.indent -= 1;
+
+        self.println(&with_comment(&module.exports.len().to_string(), "exports"));
+        self.indent += 1;
+        for exports in &module.exports {
+            let flags = ModuleFlags::ExportsAccessFlags::to_u16(&exports.exports_flags);
+            self.write_module_entry(
+                exports.exports_index,
+                flags,
+                "to",
+                &exports.exports_to_index,
+            );
+        }
+        self.indent -= 1;
+
+        self.println(&with_comment(&module.opens.len().to_string(), "opens"));
+        self.indent += 1;
+        for opens in &module.opens {
+            let flags = ModuleFlags::OpensAccessFlags::to_u16(&opens.opens_flags);
+            self.write_module_entry(opens.opens_index, flags, "to", &opens.opens_to_index);
+        }
+        self.indent -= 1;
+
+        self.println(&with_comment(&module.uses_index.len().to_string(), "uses"));
+        self.indent += 1;
+        for uses in &module.uses_index {
+            self.println(&with_comment(
+                &format!("#{uses}"),
+                &self.string_value(*uses),
+            ));
+        }
+        self.indent -= 1;
+
+        self.println(&with_comment(
+            &module.provides.len().to_string(),
+            "provides",
+        ));
+        self.indent += 1;
+        for provides in &module.provides {
+            self.write_module_entry(
+                provides.provides_index,
+                0,
+                "with",
+                &provides.provides_with_index,
+            );
+        }
+        self.indent -= 2;
+    }
+
+    fn write_version(&mut self, index: u16) {
+        match index {
+            0 => self.println("#0"),
+            _ => self.println(&with_comment(
+                &format!("#{index}"),
+                &self.string_value(index),
+            )),
+        }
+    }
+
+    /**
+     * An exports, opens or provides directive: `#index,flags` with the package or service, then
+     * the modules or implementations it lists. Provides has no flags.
+     */
+    fn write_module_entry(&mut self, index: u16, flags: u16, keyword: &str, targets: &[u16]) {
+        let mut text = format!("#{index}");
+        let mut comment = self.string_value(index);
+        if keyword != "with" {
+            text.push_str(&format!(",{flags:x}"));
+            comment.push_str(&flag_names(flags, &EXPORTS_FLAGS));
+        }
+        if !targets.is_empty() {
+            comment.push_str(&format!(" {keyword} ... {}", targets.len()));
+        }
+        self.println(&with_comment(&text, &comment));
+        self.indent += 1;
+        for target in targets {
+            let comment = format!("... {keyword} {}", self.string_value(*target));
+            self.println(&with_comment(&format!("#{target}"), &comment));
+        }
+        self.indent -= 1;
+    }
+
+    fn write_parameter_annotations(&mut self, title: &str, parameters: &[ParamAnnotation]) {
+        self.println(&format!("{title}:"));
+        self.indent += 1;
+        for (i, parameter) in parameters.iter().enumerate() {
+            self.println(&format!("parameter {i}:"));
+            self.indent += 1;
+            self.write_annotation_list(&parameter.annotations);
+            self.indent -= 1;
+        }
+        self.indent -= 1;
+    }
+
+    /// Each type annotation with where it applies, then the annotation itself.
+    fn write_type_annotations(&mut self, title: &str, annotations: &[TypeAnnotation]) {
+        self.println(&format!("{title}:"));
+        self.indent += 1;
+        for (i, annotation) in annotations.iter().enumerate() {
+            let indices =
+                annotation_indices(annotation.type_index, &annotation.element_value_pairs);
+            self.println(&format!(
+                "{i}: {indices}: {}",
+                type_annotation_target(annotation)
+            ));
+            self.indent += 1;
+            self.write_annotation_value(annotation.type_index, &annotation.element_value_pairs);
+            self.indent -= 1;
+        }
+        self.indent -= 1;
+    }
+
+    fn write_class_list(&mut self, title: &str, classes: &[u16]) {
+        self.println(&format!("{title}:"));
+        self.indent += 1;
+        for class in classes {
+            let name = self.string_value(*class);
+            self.println(&name);
+        }
+        self.indent -= 1;
+    }
+
+    fn write_stack_map_table(&mut self, frames: &[StackMapFrame]) {
+        self.println(&format!(
+            "StackMapTable: number_of_entries = {}",
+            frames.len()
+        ));
+        self.indent += 1;
+        for frame in frames {
+            let (frame_type, kind) = match frame {
+                StackMapFrame::SameFrame { frame_type } => (frame_type, "same"),
+                StackMapFrame::SameLocals1StackItemFrame { frame_type, .. } => {
+                    (frame_type, "same_locals_1_stack_item")
+                }
+                StackMapFrame::SameLocals1StackItemFrameExtended { frame_type, .. } => {
+                    (frame_type, "same_locals_1_stack_item_frame_extended")
+                }
+                StackMapFrame::ChopFrame { frame_type, .. } => (frame_type, "chop"),
+                StackMapFrame::SameFrameExtended { frame_type, .. } => {
+                    (frame_type, "same_frame_extended")
+                }
+                StackMapFrame::AppendFrame { frame_type, .. } => (frame_type, "append"),
+                StackMapFrame::FullFrame { frame_type, .. } => (frame_type, "full_frame"),
+                StackMapFrame::Unused { frame_type } => (frame_type, "unknown"),
+            };
+            self.println(&format!("frame_type = {frame_type} /* {kind} */"));
+            self.indent += 1;
+            match frame {
+                StackMapFrame::SameLocals1StackItemFrame { stack, .. } => {
+                    self.println(&self.verification_types("stack", stack));
+                }
+                StackMapFrame::SameLocals1StackItemFrameExtended {
+                    offset_delta,
+                    stack,
+                    ..
+                } => {
+                    self.println(&format!("offset_delta = {offset_delta}"));
+                    self.println(&self.verification_types("stack", stack));
+                }
+                StackMapFrame::ChopFrame { offset_delta, .. }
+                | StackMapFrame::SameFrameExtended { offset_delta, .. } => {
+                    self.println(&format!("offset_delta = {offset_delta}"));
+                }
+                StackMapFrame::AppendFrame {
+                    offset_delta,
+                    locals,
+                    ..
+                } => {
+                    self.println(&format!("offset_delta = {offset_delta}"));
+                    self.println(&self.verification_types("locals", locals));
+                }
+                StackMapFrame::FullFrame {
+                    offset_delta,
+                    locals,
+                    stack,
+                    ..
+                } => {
+                    self.println(&format!("offset_delta = {offset_delta}"));
+                    self.println(&self.verification_types("locals", locals));
+                    self.println(&self.verification_types("stack", stack));
+                }
+                StackMapFrame::SameFrame { .. } | StackMapFrame::Unused { .. } => {}
+            }
+            self.indent -= 1;
+        }
+        self.indent -= 1;
+    }
+
+    fn verification_types(&self, name: &str, types: &[VerificationTypeInfo]) -> String {
+        if types.is_empty() {
+            return format!("{name} = []");
+        }
+        let types: Vec<String> = types
+            .iter()
+            .map(|info| match info {
+                VerificationTypeInfo::TopVariable => "top".into(),
+                VerificationTypeInfo::IntegerVariable => "int".into(),
+                VerificationTypeInfo::FloatVariable => "float".into(),
+                VerificationTypeInfo::LongVariable => "long".into(),
+                VerificationTypeInfo::DoubleVariable => "double".into(),
+                VerificationTypeInfo::NullVariable => "null".into(),
+                VerificationTypeInfo::UnitializedThisVariable => "this".into(),
+                VerificationTypeInfo::ObjectVariable(index) => {
+                    format!("class {}", self.string_value(*index))
+                }
+                VerificationTypeInfo::UnintializedVariable(offset) => {
+                    format!("uninitialized {offset}")
+                }
+            })
+            .collect();
+        format!("{name} = [ {} ]", types.join(", "))
+    }
+
+    fn write_annotations(&mut self, title: &str, annotations: &[Annotation]) {
+        self.println(&format!("{title}:"));
+        self.indent += 1;
+        self.write_annotation_list(annotations);
+        self.indent -= 1;
+    }
+
+    fn write_annotation_list(&mut self, annotations: &[Annotation]) {
+        for (i, annotation) in annotations.iter().enumerate() {
+            let indices =
+                annotation_indices(annotation.type_index, &annotation.element_value_pairs);
+            self.println(&format!("{i}: {indices}"));
+            self.indent += 1;
+            self.write_annotation_value(annotation.type_index, &annotation.element_value_pairs);
+            self.indent -= 1;
+        }
+    }
+
+    /// The annotation type, then its elements one per line in parentheses.
+    fn write_annotation_value(&mut self, type_index: u16, pairs: &[ElementPairs]) {
+        let type_name = java_type(&self.utf8(type_index));
+        if pairs.is_empty() {
+            self.println(&type_name);
+            return;
+        }
+        self.println(&format!("{type_name}("));
+        self.indent += 1;
+        for pair in pairs {
+            let value = self.element_value(&pair.value);
+            let name = self.utf8(pair.element_name_index);
+            self.println(&format!("{name}={value}"));
+        }
+        self.indent -= 1;
+        self.println(")");
+    }
+
+    fn element_value(&self, element: &Element) -> String {
+        match element {
+            Element::ConstValueIndex(tag, index) => {
+                let value = self.string_value(*index);
+                match tag {
+                    'B' => format!("(byte) {value}"),
+                    'S' => format!("(short) {value}"),
+                    'C' => format!(
+                        "'{}'",
+                        self.get_constant_value("C", *index).trim_matches('\'')
+                    ),
+                    'Z' => (value != "0").to_string(),
+                    's' => format!("\"{value}\""),
+                    _ => value,
+                }
+            }
+            Element::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => format!(
+                "{}.{}",
+                self.utf8(*type_name_index),
+                self.utf8(*const_name_index)
+            ),
+            // javap leaves class literals as descriptors, as in `class [Ljava/lang/String;`.
+            Element::ClassInfoIndex(index) => format!("class {}", self.utf8(*index)),
+            // A nested annotation takes a line for each element, like one at the top level.
+            Element::Annotation(annotation) => {
+                let type_name = java_type(&self.utf8(annotation.type_index));
+                if annotation.element_value_pairs.is_empty() {
+                    return format!("@{type_name}");
+                }
+                let mut text = format!("@{type_name}(\n");
+                for pair in &annotation.element_value_pairs {
+                    let name = self.utf8(pair.element_name_index);
+                    let value = self.element_value(&pair.value);
+                    for line in format!("{name}={value}").split('\n') {
+                        text.push_str(&format!("  {line}\n"));
+                    }
+                }
+                text.push(')');
+                text
+            }
+            Element::ArrayValue { values, .. } => {
+                let values: Vec<String> = values.iter().map(|v| self.element_value(v)).collect();
+                format!("[{}]", values.join(","))
+            }
+            Element::Unknown(tag) => tag.to_string(),
+        }
+    }
+
+    fn get_signature(&self, attributes: &[AttributeInfo]) -> Option<String> {
+        attributes.iter().find_map(|attribute| match attribute {
+            AttributeInfo::Signature(signature) => Some(self.utf8(signature.signature_index)),
+            _ => None,
+        })
+    }
+
+    /// `<T extends Bound>`; in verbose mode javap keeps bounds of `java.lang.Object`.
+    fn type_parameters(&self, parameters: &[TypeParameter]) -> String {
+        if parameters.is_empty() {
+            return String::new();
+        }
+        let parameters: Vec<String> = parameters
+            .iter()
+            .map(|parameter| match &parameter.class_bound {
+                Some(bound) if self.options.verbose => {
+                    let bounds: Vec<String> = std::iter::once(bound)
+                        .chain(&parameter.interface_bounds)
+                        .map(|bound| bound.to_string())
+                        .collect();
+                    format!("{} extends {}", parameter.name, bounds.join(" & "))
+                }
+                _ => parameter.to_string(),
+            })
+            .collect();
+        format!("<{}>", parameters.join(", "))
+    }
+
+    /// The modified UTF-8 text of a CONSTANT_Utf8_info entry, with `/` left in place.
+    fn utf8(&self, index: u16) -> String {
+        get_raw_utf8(&self.class.constant_pool, index).unwrap_or_default()
+    }
+
+    fn class_name(&self, index: u16) -> String {
+        get_raw_class_name(&self.class.constant_pool, index).unwrap_or_default()
+    }
+
+    fn module_name(&self, index: u16) -> String {
+        match self.class.constant_pool.get(index as usize) {
+            Some(ConstantPool::Module(module)) => self.utf8(module.name_index),
+            _ => String::new(),
+        }
+    }
+
+    fn package_name(&self, index: u16) -> String {
+        match self.class.constant_pool.get(index as usize) {
+            Some(ConstantPool::Package(package)) => self.utf8(package.name_index),
+            _ => String::new(),
+        }
+    }
+
+    /// The value of a constant as javap prints it in constant pool comments.
+    fn string_value(&self, index: u16) -> String {
+        match self.class.constant_pool.get(index as usize) {
+            Some(ConstantPool::Utf8(_)) => escape(&self.utf8(index)),
+            Some(ConstantPool::Integer(integer)) => integer.get_value().to_string(),
+            Some(ConstantPool::Float(float)) => format!("{}f", java_float(float.get_value())),
+            Some(ConstantPool::Long(long)) => format!("{}l", long.get_value()),
+            Some(ConstantPool::Double(double)) => format!("{}d", java_double(double.get_value())),
+            Some(ConstantPool::Class(class)) => check_name(&self.utf8(class.name_index)),
+            Some(ConstantPool::String(string)) => escape(&self.utf8(string.string_index)),
+            Some(ConstantPool::Fieldref(field)) => format!(
+                "{}.{}",
+                self.string_value(field.class_index),
+                self.string_value(field.name_and_type_index)
+            ),
+            Some(ConstantPool::Methodref(method)) => format!(
+                "{}.{}",
+                self.string_value(method.class_index),
+                self.string_value(method.name_and_type_index)
+            ),
+            Some(ConstantPool::InterfaceMethodRef(method)) => format!(
+                "{}.{}",
+                self.string_value(method.class_index),
+                self.string_value(method.name_and_type_index)
+            ),
+            Some(ConstantPool::NameAndType(name_and_type)) => format!(
+                "{}:{}",
+                check_name(&self.utf8(name_and_type.name_index)),
+                self.utf8(name_and_type.descriptor_index)
+            ),
+            Some(ConstantPool::MethodHandle(handle)) => format!(
+                "{} {}",
+                handle.get_kind_name(),
+                self.string_value(handle.reference_index)
+            ),
+            Some(ConstantPool::MethodType(method_type)) => self.utf8(method_type.descriptor_index),
+            Some(ConstantPool::Dynamic(dynamic)) => format!(
+                "#{}:{}",
+                dynamic.bootstrap_method_attr_index,
+                self.string_value(dynamic.name_and_type_index)
+            ),
+            Some(ConstantPool::InvokeDynamic(dynamic)) => format!(
+                "#{}:{}",
+                dynamic.bootstrap_method_attr_index,
+                self.string_value(dynamic.name_and_type_index)
+            ),
+            Some(ConstantPool::Module(module)) => check_name(&self.utf8(module.name_index)),
+            Some(ConstantPool::Package(package)) => check_name(&self.utf8(package.name_index)),
+            Some(ConstantPool::Unknown) | None => format!("#{index}"),
+        }
+    }
+
+    /**
+     * A constant as javap prints it after an instruction, e.g. `Method java/lang/Object."<init>":()V`.\
+     *  References to members of this class leave out the class name.
+     */
+    fn constant_comment(&self, index: u16) -> String {
+        let (kind, reference) = match self.class.constant_pool.get(index as usize) {
+            Some(ConstantPool::Utf8(_)) => ("Utf8", None),
+            Some(ConstantPool::Integer(_)) => ("int", None),
+            Some(ConstantPool::Float(_)) => ("float", None),
+            Some(ConstantPool::Long(_)) => ("long", None),
+            Some(ConstantPool::Double(_)) => ("double", None),
+            Some(ConstantPool::Class(_)) => ("class", None),
+            Some(ConstantPool::String(_)) => ("String", None),
+            Some(ConstantPool::Fieldref(field)) => (
+                "Field",
+                Some((field.class_index, field.name_and_type_index)),
+            ),
+            Some(ConstantPool::Methodref(method)) => (
+                "Method",
+                Some((method.class_index, method.name_and_type_index)),
+            ),
+            Some(ConstantPool::InterfaceMethodRef(method)) => (
+                "InterfaceMethod",
+                Some((method.class_index, method.name_and_type_index)),
+            ),
+            Some(ConstantPool::NameAndType(_)) => ("NameAndType", None),
+            Some(ConstantPool::MethodHandle(_)) => ("MethodHandle", None),
+            Some(ConstantPool::MethodType(_)) => ("MethodType", None),
+            Some(ConstantPool::Dynamic(_)) => ("Dynamic", None),
+            Some(ConstantPool::InvokeDynamic(_)) => ("InvokeDynamic", None),
+            Some(ConstantPool::Module(_)) => ("Module", None),
+            Some(ConstantPool::Package(_)) => ("Package", None),
+            Some(ConstantPool::Unknown) | None => return format!("#{index}"),
+        };
+        match reference {
+            Some((class_index, name_and_type_index)) if class_index == self.class.this_class => {
+                format!("{kind} {}", self.string_value(name_and_type_index))
+            }
+            _ => format!("{kind} {}", self.string_value(index)),
+        }
+    }
+
+    /// The value of a ConstantValue attribute as Java source would write it.
+    pub(crate) fn get_constant_value(&self, descriptor: &str, index: u16) -> String {
+        let pool = &self.class.constant_pool;
+        match (descriptor, pool.get(index as usize)) {
+            ("Z", Some(ConstantPool::Integer(value))) => (value.get_value() != 0).to_string(),
+            ("C", Some(ConstantPool::Integer(value))) => {
+                format!("'{}'", quote(&[value.get_value() as u16], '\''))
+            }
+            (_, Some(ConstantPool::String(string))) => match pool.get(string.string_index as usize)
+            {
+                Some(ConstantPool::Utf8(utf8)) => format!("\"{}\"", quote(&utf8.get_utf16(), '"')),
+                _ => String::new(),
+            },
+            _ => self.string_value(index),
+        }
+    }
+}
+
+fn annotation_indices(type_index: u16, pairs: &[ElementPairs]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|pair| {
+            format!(
+                "#{}={}",
+                pair.element_name_index,
+                element_indices(&pair.value)
+            )
+        })
+        .collect();
+    format!("#{type_index}({})", pairs.join(","))
+}
+
+fn element_indices(element: &Element) -> String {
+    match element {
+        Element::ConstValueIndex(tag, index) => format!("{tag}#{index}"),
+        Element::EnumConstValue {
+            type_name_index,
+            const_name_index,
+        } => format!("e#{type_name_index}.#{const_name_index}"),
+        Element::ClassInfoIndex(index) => format!("c#{index}"),
+        Element::Annotation(annotation) => format!(
+            "@{}",
+            annotation_indices(annotation.type_index, &annotation.element_value_pairs)
+        ),
+        Element::ArrayValue { values, .. } => {
+            let values: Vec<String> = values.iter().map(element_indices).collect();
+            format!("[{}]", values.join(","))
+        }
+        Element::Unknown(tag) => tag.to_string(),
+    }
+}
+
+/**
+ * Where a type annotation applies as javap prints it, e.g.
+ * `METHOD_FORMAL_PARAMETER, param_index=1, location=[TYPE_ARGUMENT(0)]`.
+ */
+fn type_annotation_target(annotation: &TypeAnnotation) -> String {
+    let kind = match annotation.target_type {
+        0x00 => "CLASS_TYPE_PARAMETER",
+        0x01 => "METHOD_TYPE_PARAMETER",
+        0x10 => "CLASS_EXTENDS",
+        0x11 => "CLASS_TYPE_PARAMETER_BOUND",
+        0x12 => "METHOD_TYPE_PARAMETER_BOUND",
+        0x13 => "FIELD",
+        0x14 => "METHOD_RETURN",
+        0x15 => "METHOD_RECEIVER",
+        0x16 => "METHOD_FORMAL_PARAMETER",
+        0x17 => "THROWS",
+        0x40 => "LOCAL_VARIABLE",
+        0x41 => "RESOURCE_VARIABLE",
+        0x42 => "EXCEPTION_PARAMETER",
+        0x43 => "INSTANCEOF",
+        0x44 => "NEW",
+        0x45 => "CONSTRUCTOR_REFERENCE",
+        0x46 => "METHOD_REFERENCE",
+        0x47 => "CAST",
+        0x48 => "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT",
+        0x49 => "METHOD_INVOCATION_TYPE_ARGUMENT",
+        0x4A => "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT",
+        0x4B => "METHOD_REFERENCE_TYPE_ARGUMENT",
+        _ => "UNKNOWN",
+    };
+    let mut text = kind.to_string();
+    match &annotation.target_info {
+        TargetInfo::TypeParameterTarget {
+            type_parameter_index,
+        } => text.push_str(&format!(", param_index={type_parameter_index}")),
+        TargetInfo::SupertypeTarget { supertype_index } => {
+            text.push_str(&format!(", type_index={supertype_index}"))
+        }
+        TargetInfo::TypeParameterBoundTarget {
+            type_parameter_index,
+            bound_index,
+        } => text.push_str(&format!(
+            ", param_index={type_parameter_index}, bound_index={bound_index}"
+        )),
+        TargetInfo::EmptyTarget => {}
+        TargetInfo::FormalParameterTarget {
+            formal_parameter_index,
+        } => text.push_str(&format!(", param_index={formal_parameter_index}")),
+        TargetInfo::ThrowsTarget { throws_type_index } => {
+            text.push_str(&format!(", type_index={throws_type_index}"))
+        }
+        TargetInfo::LocalvarTarget { table, .. } => {
+            let ranges: Vec<String> = table
+                .iter()
+                .map(|local| {
+                    format!(
+                        "start_pc={}, length={}, index={}",
+                        local.start_pc, local.length, local.index
+                    )
+                })
+                .collect();
+            text.push_str(&format!(", {{{}}}", ranges.join("; ")));
+        }
+        TargetInfo::CatchTarget {
+            exception_table_index,
+        } => text.push_str(&format!(", exception_index={exception_table_index}")),
+        TargetInfo::OffsetTarget { offset } => text.push_str(&format!(", offset={offset}")),
+        TargetInfo::TypeArgumentTarget {
+            offset,
+            type_argument_index,
+        } => text.push_str(&format!(
+            ", offset={offset}, type_index={type_argument_index}"
+        )),
+    }
+    if !annotation.target_path.path.is_empty() {
+        let path: Vec<String> = annotation
+            .target_path
+            .path
+            .iter()
+            .map(|step| match step.type_path_kind {
+                0 => "ARRAY".into(),
+                1 => "INNER_TYPE".into(),
+                2 => "WILDCARD".into(),
+                _ => format!("TYPE_ARGUMENT({})", step.type_argument_index),
+            })
+            .collect();
+        text.push_str(&format!(", location=[{}]", path.join(", ")));
+    }
+    text
+}
+
+/// ` ACC_NAME` for each of the `names` set in `flags`.
+fn flag_names(flags: u16, names: &[(u16, &str)]) -> String {
+    names
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| format!(" {name}"))
+        .collect()
+}
+
+/// The source modifiers for `flags`, each followed by a space.
+pub(crate) fn get_modifiers(flags: u16, modifiers: &[(u16, &str)]) -> String {
+    modifiers
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, modifier)| format!("{modifier} "))
+        .collect()
+}
+
+/// `flags: (0x0021) ACC_PUBLIC, ACC_SUPER`
+fn get_flags_line(flags: u16, names: &[String]) -> String {
+    let names: Vec<String> = names
+        .iter()
+        .map(|name| format!("ACC_{}", name.to_uppercase()))
+        .collect();
+    format!("flags: (0x{flags:04x}) {}", names.join(", "))
+}
+
+/// Pads `text` to the comment column and appends `// comment`.
+fn with_comment(text: &str, comment: &str) -> String {
+    let column = text.chars().count();
+    let padding = if column < TAB_COLUMN {
+        TAB_COLUMN - column
+    } else {
+        1
+    };
+    format!("{text}{}// {comment}", " ".repeat(padding))
+}
+
+fn java_name(internal_name: &str) -> String {
+    internal_name.replace('/', ".")
+}
+
+/// The Java type of a field descriptor, or the descriptor itself if it is malformed.
+fn java_type(descriptor: &str) -> String {
+    match FieldType::from_descriptor(descriptor) {
+        Ok(field_type) => field_type.get_java_name(),
+        Err(_) => descriptor.into(),
+    }
+}
+
+/// Escapes the characters javap escapes in constant pool text.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '"' => escaped.push_str("\\\""),
+            '\'' => escaped.push_str("\\'"),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quotes UTF-16 text as a Java literal delimited by `quote`, escaping anything outside ASCII.
+pub(crate) fn quote(text: &[u16], quote: char) -> String {
+    let mut quoted = String::with_capacity(text.len());
+    for unit in text {
+        match *unit {
+            0x08 => quoted.push_str("\\b"),
+            0x09 => quoted.push_str("\\t"),
+            0x0a => quoted.push_str("\\n"),
+            0x0c => quoted.push_str("\\f"),
+            0x0d => quoted.push_str("\\r"),
+            0x5c => quoted.push_str("\\\\"),
+            unit if unit == quote as u16 => {
+                quoted.push('\\');
+                quoted.push(quote);
+            }
+            0x20..=0x7e => quoted.push(*unit as u8 as char),
+            unit => quoted.push_str(&format!("\\u{unit:04x}")),
+        }
+    }
+    quoted
+}
+
+/// Quotes names that are not a `/` separated sequence of Java identifiers, such as `<init>`.
+fn check_name(name: &str) -> String {
+    let mut previous = '/';
+    for c in name.chars() {
+        let start = c.is_alphabetic() || c == '$' || c == '_';
+        if (previous == '/' && !start) || (c != '/' && !(start || c.is_alphanumeric())) {
+            return format!("\"{}\"", escape(name));
+        }
+        previous = c;
+    }
+    if name.is_empty() {
+        return "\"\"".into();
+    }
+    escape(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PATH: &str = "test_verified_output/";
+
+    /**
+     * Disassembles the class compiled from `class_writer_test/Sample.java` with `options` and
+     * checks it against what javap printed for `flag`, less the `Classfile` header of `-v`.
+     */
+    fn assert_javap(flag: &str, options: JavapOptions) {
+        let bytes = std::fs::read(format!("{TEST_PATH}class_writer_test/Sample.class")).unwrap();
+        let class = ClassFile::from_bytes(&bytes).unwrap();
+        let output = Javap::new(&class, &options).disassemble().unwrap();
+        let expected =
+            std::fs::read_to_string(format!("{TEST_PATH}javap_test/Sample-{flag}.txt")).unwrap();
+        assert_eq!(output, expected, "-{flag}");
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let options = JavapOptions {
+            disassemble: true,
+            ..Default::default()
+        };
+        assert_javap("c", options);
+    }
+
+    #[test]
+    fn test_verbose() {
+        // Nested annotations, class literals, type annotations and every constant kind.
+        let options = JavapOptions {
+            verbose: true,
+            ..Default::default()
+        };
+        assert_javap("v", options);
+    }
+
+    #[test]
+    fn test_member_options() {
+        let private = JavapOptions {
+            access: AccessFilter::Private,
+            ..Default::default()
+        };
+        assert_javap("p", private);
+        let lines = JavapOptions {
+            lines: true,
+            ..Default::default()
+        };
+        assert_javap("l", lines);
+        let descriptors = JavapOptions {
+            descriptors: true,
+            ..Default::default()
+        };
+        assert_javap("s", descriptors);
+        let constants = JavapOptions {
+            constants: true,
+            ..Default::default()
+        };
+        assert_javap("constants", constants);
+    }
+
+    #[test]
+    fn test_type_annotation_target() {
+        let bytes = [
+            0x40, 0, 1, 0, 28, 0, 4, 0, 5, 2, 3, 0, 3, 1, 0, 58, 0, 0, //
+            0x47, 0, 1, 0, 0, 0, 58, 0, 0,
+        ];
+        let mut cursor = std::io::Cursor::new(&bytes[..]);
+        let local = TypeAnnotation::new(&mut cursor).unwrap();
+        assert_eq!(
+            type_annotation_target(&local),
+            "LOCAL_VARIABLE, {start_pc=28, length=4, index=5}, \
+             location=[TYPE_ARGUMENT(0), TYPE_ARGUMENT(1)]"
+        );
+        let cast = TypeAnnotation::new(&mut cursor).unwrap();
+        assert_eq!(
+            type_annotation_target(&cast),
+            "CAST, offset=1, type_index=0"
+        );
+        assert_eq!(
+            flag_names(0x8020, &REQUIRES_FLAGS),
+            " ACC_TRANSITIVE ACC_MANDATED"
+        );
+    }
+
+    #[test]
+    fn test_constant_text() {
+        assert_eq!(check_name("java/lang/Object"), "java/lang/Object");
+        assert_eq!(check_name("<init>"), "\"<init>\"");
+        assert_eq!(check_name("[[I"), "\"[[I\"");
+        assert_eq!(escape("a\u{1}\"b\""), "a\\u0001\\\"b\\\"");
+        let text: Vec<u16> = "'é\"".encode_utf16().collect();
+        assert_eq!(quote(&text, '"'), "'\\u00e9\\\"");
+        assert_eq!(with_comment("#1", "x"), format!("#1{}// x", " ".repeat(38)));
+    }
+}
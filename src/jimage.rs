@@ -0,0 +1,569 @@
+use std::{collections::BTreeSet, error::Error, fs, path::Path};
+
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+
+use crate::class_file::ClassFile;
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+
+const MAGIC: u32 = 0xcafedada;
+const MAJOR_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 7 * 4;
+const HASH_MULTIPLIER: u32 = 0x01000193;
+
+const ATTRIBUTE_MODULE: usize = 1;
+const ATTRIBUTE_PARENT: usize = 2;
+const ATTRIBUTE_BASE: usize = 3;
+const ATTRIBUTE_EXTENSION: usize = 4;
+const ATTRIBUTE_OFFSET: usize = 5;
+const ATTRIBUTE_COMPRESSED: usize = 6;
+const ATTRIBUTE_UNCOMPRESSED: usize = 7;
+const ATTRIBUTE_COUNT: usize = 8;
+
+const COMPRESSED_RESOURCE_MAGIC: u32 = 0xcafefafa;
+const COMPRESSED_RESOURCE_HEADER_SIZE: usize = 29;
+
+/// The pseudo-modules jlink adds to describe the module and package directories.
+const DIRECTORY_MODULES: [&str; 2] = ["modules", "packages"];
+
+/// A resource in a jimage, named `/module/parent/base.extension`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageLocation {
+    pub(crate) module: String,
+    /// The directory of the resource inside its module, such as `java/lang`.
+    pub(crate) parent: String,
+    pub(crate) base: String,
+    pub(crate) extension: String,
+    /// The offset of the content from the end of the index.
+    pub(crate) content_offset: u64,
+    /// Zero when the content is stored uncompressed.
+    pub(crate) compressed_size: u64,
+    pub(crate) uncompressed_size: u64,
+}
+
+impl ImageLocation {
+    pub fn get_module(&self) -> &str {
+        &self.module
+    }
+
+    /// The name of the resource inside its module, such as `java/lang/String.class`.
+    pub fn get_resource_name(&self) -> String {
+        let mut name = String::new();
+        if !self.parent.is_empty() {
+            name.push_str(&self.parent);
+            name.push('/');
+        }
+        name.push_str(&self.base);
+        if !self.extension.is_empty() {
+            name.push('.');
+            name.push_str(&self.extension);
+        }
+        name
+    }
+
+    /// The name the image indexes the resource by, such as `/java.base/java/lang/String.class`.
+    pub fn get_full_name(&self) -> String {
+        if self.module.is_empty() {
+            return self.get_resource_name();
+        }
+        format!("/{}/{}", self.module, self.get_resource_name())
+    }
+
+    /// Whether this is a resource of a module, rather than one of the jimage's directories.
+    pub fn is_resource(&self) -> bool {
+        !self.module.is_empty() && !DIRECTORY_MODULES.contains(&self.module.as_str())
+    }
+
+    pub fn is_class(&self) -> bool {
+        self.extension == "class" && self.is_resource()
+    }
+}
+
+/**
+ * [jimage](https://github.com/openjdk/jdk17u/blob/master/src/java.base/share/classes/jdk/internal/jimage/BasicImageReader.java)\
+ *  The module image a JDK ships its class libraries in, `lib/modules`. It is written in the
+ *  byte order of the platform that linked it and laid out as:
+ *
+ *  header {
+ *      u4 magic;
+ *      u4 version;
+ *      u4 flags;
+ *      u4 resource_count;
+ *      u4 table_length;
+ *      u4 locations_size;
+ *      u4 strings_size;
+ *  }
+ *  s4 redirect\[table_length\];
+ *  u4 offsets\[table_length\];
+ *  u1 locations\[locations_size\];
+ *  u1 strings\[strings_size\];
+ *  u1 resources\[\];
+ */
+#[derive(Debug)]
+pub struct JImage {
+    bytes: Vec<u8>,
+    big_endian: bool,
+    table_length: u32,
+    locations_size: usize,
+    strings_size: usize,
+}
+
+impl JImage {
+    pub fn open(path: &Path) -> Result<JImage, Box<dyn Error>> {
+        JImage::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<JImage, Box<dyn Error>> {
+        let magic = bytes
+            .get(..4)
+            .ok_or_else(|| invalid("header", "is truncated"))?;
+        let big_endian = match u32::from_le_bytes(magic.try_into()?) {
+            MAGIC => false,
+            _ if u32::from_be_bytes(magic.try_into()?) == MAGIC => true,
+            magic => {
+                return Err(invalid(
+                    "header",
+                    &format!("magic {magic:#010x} is incorrect"),
+                ))
+            }
+        };
+        let mut image = JImage {
+            bytes,
+            big_endian,
+            table_length: 0,
+            locations_size: 0,
+            strings_size: 0,
+        };
+        let version = image.read_u32(4)?;
+        if (version >> 16) as u16 != MAJOR_VERSION {
+            return Err(invalid(
+                "header",
+                &format!(
+                    "version {}.{} is not supported",
+                    version >> 16,
+                    version & 0xffff
+                ),
+            ));
+        }
+        image.table_length = image.read_u32(16)?;
+        image.locations_size = image.read_u32(20)? as usize;
+        image.strings_size = image.read_u32(24)? as usize;
+        if image.get_index_size() > image.bytes.len() {
+            return Err(invalid("index", "is truncated"));
+        }
+        Ok(image)
+    }
+
+    /// Every resource in the image, including the `/modules` and `/packages` directories.
+    pub fn get_locations(&self) -> Result<Vec<ImageLocation>, Box<dyn Error>> {
+        (0..self.table_length)
+            .map(|index| self.get_location(index))
+            .collect()
+    }
+
+    /// The location of a resource by its full name, such as `/java.base/java/lang/Object.class`.
+    pub fn find_location(&self, name: &str) -> Result<Option<ImageLocation>, Box<dyn Error>> {
+        if self.table_length == 0 {
+            return Ok(None);
+        }
+        let bucket = hash_code(name, HASH_MULTIPLIER) % self.table_length;
+        // Redirects are negative for buckets with a single name, which is stored at
+        // -redirect - 1, and positive for buckets whose names are rehashed with it as the seed.
+        let redirect = self.read_u32(HEADER_SIZE + bucket as usize * 4)? as i32;
+        let index = match redirect {
+            0 => return Ok(None),
+            redirect if redirect < 0 => (-(redirect as i64) - 1) as u32,
+            seed => hash_code(name, seed as u32) % self.table_length,
+        };
+        if index >= self.table_length {
+            return Err(invalid(name, "redirect lies outside of the table"));
+        }
+        let location = self.get_location(index)?;
+        Ok((location.get_full_name() == name).then_some(location))
+    }
+
+    /// The uncompressed content of a resource.
+    pub fn read_resource(&self, location: &ImageLocation) -> Result<Vec<u8>, Box<dyn Error>> {
+        let name = location.get_full_name();
+        let size = if location.compressed_size != 0 {
+            location.compressed_size
+        } else {
+            location.uncompressed_size
+        };
+        let content = usize::try_from(location.content_offset)
+            .ok()
+            .zip(usize::try_from(size).ok())
+            .and_then(|(offset, size)| {
+                let start = self.get_index_size().checked_add(offset)?;
+                self.bytes.get(start..start.checked_add(size)?)
+            })
+            .ok_or_else(|| invalid(&name, "content lies outside of the image"))?;
+        if location.compressed_size == 0 {
+            return Ok(content.to_vec());
+        }
+
+        // jlink may stack several compressors, each adding a header to the one before.
+        let mut content = content.to_vec();
+        while content.len() >= COMPRESSED_RESOURCE_HEADER_SIZE
+            && self.get_u32(&content, 0) == COMPRESSED_RESOURCE_MAGIC
+        {
+            let uncompressed_size = self.get_u64(&content, 12);
+            let decompressor = self.get_string(self.get_u32(&content, 20) as usize)?;
+            let data = &content[COMPRESSED_RESOURCE_HEADER_SIZE..];
+            content = match decompressor.as_str() {
+                "zip" => {
+                    let limit = usize::try_from(uncompressed_size)?;
+                    decompress_to_vec_zlib_with_limit(data, limit)
+                        .map_err(|e| invalid(&name, &format!("cannot be inflated: {e}")))?
+                }
+                _ => {
+                    return Err(invalid(
+                        &name,
+                        &format!("uses unsupported decompressor {decompressor}"),
+                    ))
+                }
+            };
+        }
+        if content.len() as u64 != location.uncompressed_size {
+            return Err(invalid(&name, "uncompressed size is incorrect"));
+        }
+        Ok(content)
+    }
+
+    /// The names of the modules in the image, sorted.
+    pub fn get_modules(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut modules = BTreeSet::new();
+        for location in self.get_locations()? {
+            if location.is_resource() {
+                modules.insert(location.module);
+            }
+        }
+        Ok(modules.into_iter().collect())
+    }
+
+    /// The packages of a module that contain classes, sorted and dotted like `java.lang`.
+    pub fn get_packages(&self, module: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut packages = BTreeSet::new();
+        for location in self.get_locations()? {
+            if location.module == module && location.is_class() && !location.parent.is_empty() {
+                packages.insert(location.parent.replace('/', "."));
+            }
+        }
+        Ok(packages.into_iter().collect())
+    }
+
+    /// Parses a class of a module by its binary name, such as `java/lang/String`.
+    pub fn get_class(
+        &self,
+        module: &str,
+        binary_name: &str,
+    ) -> Result<Option<ClassFile>, Box<dyn Error>> {
+        match self.find_location(&format!("/{module}/{binary_name}.class"))? {
+            Some(location) => Ok(Some(ClassFile::from_bytes(
+                &self.read_resource(&location)?,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_index_size(&self) -> usize {
+        HEADER_SIZE + self.table_length as usize * 8 + self.locations_size + self.strings_size
+    }
+
+    fn get_location(&self, index: u32) -> Result<ImageLocation, Box<dyn Error>> {
+        let offsets = HEADER_SIZE + self.table_length as usize * 4;
+        let offset = self.read_u32(offsets + index as usize * 4)? as usize;
+        let locations_start = HEADER_SIZE + self.table_length as usize * 8;
+        let locations = &self.bytes[locations_start..locations_start + self.locations_size];
+
+        // Each attribute is a byte holding kind << 3 | (length - 1) followed by a big-endian
+        // value of that length, until a byte below 8 ends the location.
+        let mut attributes = [0u64; ATTRIBUTE_COUNT];
+        let mut position = offset;
+        loop {
+            let data = *locations
+                .get(position)
+                .ok_or_else(|| invalid("locations", "attribute stream is truncated"))?;
+            position += 1;
+            if data <= 0x7 {
+                break;
+            }
+            let kind = (data >> 3) as usize;
+            let length = (data & 0x7) as usize + 1;
+            let value = locations
+                .get(position..position + length)
+                .ok_or_else(|| invalid("locations", "attribute value is truncated"))?;
+            position += length;
+            if kind >= ATTRIBUTE_COUNT {
+                return Err(invalid(
+                    "locations",
+                    &format!("attribute kind {kind} is unknown"),
+                ));
+            }
+            attributes[kind] = value
+                .iter()
+                .fold(0, |value, byte| value << 8 | *byte as u64);
+        }
+
+        Ok(ImageLocation {
+            module: self.get_string(attributes[ATTRIBUTE_MODULE] as usize)?,
+            parent: self.get_string(attributes[ATTRIBUTE_PARENT] as usize)?,
+            base: self.get_string(attributes[ATTRIBUTE_BASE] as usize)?,
+            extension: self.get_string(attributes[ATTRIBUTE_EXTENSION] as usize)?,
+            content_offset: attributes[ATTRIBUTE_OFFSET],
+            compressed_size: attributes[ATTRIBUTE_COMPRESSED],
+            uncompressed_size: attributes[ATTRIBUTE_UNCOMPRESSED],
+        })
+    }
+
+    /// A NUL terminated string of the strings table.
+    fn get_string(&self, offset: usize) -> Result<String, Box<dyn Error>> {
+        let strings_start = self.get_index_size() - self.strings_size;
+        let strings = &self.bytes[strings_start..self.get_index_size()];
+        let string = strings
+            .get(offset..)
+            .and_then(|string| string.split(|byte| *byte == 0).next())
+            .ok_or_else(|| invalid("strings", "offset lies outside of the table"))?;
+        Ok(String::from_utf8_lossy(string).into_owned())
+    }
+
+    fn read_u32(&self, offset: usize) -> Result<u32, Box<dyn Error>> {
+        if offset + 4 > self.bytes.len() {
+            return Err(invalid("index", "is truncated"));
+        }
+        Ok(self.get_u32(&self.bytes, offset))
+    }
+
+    fn get_u32(&self, bytes: &[u8], offset: usize) -> u32 {
+        let value = bytes[offset..offset + 4].try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(value)
+        } else {
+            u32::from_le_bytes(value)
+        }
+    }
+
+    fn get_u64(&self, bytes: &[u8], offset: usize) -> u64 {
+        let value = bytes[offset..offset + 8].try_into().unwrap();
+        if self.big_endian {
+            u64::from_be_bytes(value)
+        } else {
+            u64::from_le_bytes(value)
+        }
+    }
+}
+
+/// The FNV-1 style hash of ImageStringsReader, over the UTF-8 bytes of `name`.
+fn hash_code(name: &str, seed: u32) -> u32 {
+    let hash = name.bytes().fold(seed, |hash, byte| {
+        hash.wrapping_mul(HASH_MULTIPLIER) ^ byte as u32
+    });
+    hash & 0x7fffffff
+}
+
+fn invalid(name: &str, msg: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(
+        LoadingCause::InvalidImage(name.into()),
+        msg,
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::jar::tests::EMPTY_CLASS;
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+
+    /// `(module, parent, base, extension, content, zip)`
+    pub(crate) type Resource<'a> = (&'a str, &'a str, &'a str, &'a str, &'a [u8], bool);
+
+    /// Links a little-endian image, building the redirect table as jlink's PerfectHashBuilder does.
+    pub(crate) fn write_image(resources: &[Resource]) -> Vec<u8> {
+        let mut strings = vec![0u8];
+        let mut add_string = |string: &str| -> u64 {
+            if string.is_empty() {
+                return 0;
+            }
+            let offset = strings.len() as u64;
+            strings.extend(string.as_bytes());
+            strings.push(0);
+            offset
+        };
+        let zip_name = add_string("zip");
+
+        let mut locations = Vec::new();
+        let mut location_offsets = Vec::new();
+        let mut content = Vec::new();
+        let mut names = Vec::new();
+        for (module, parent, base, extension, data, zip) in resources {
+            let mut stored = data.to_vec();
+            let mut compressed_size = 0;
+            if *zip {
+                let deflated = compress_to_vec_zlib(data, 6);
+                stored = COMPRESSED_RESOURCE_MAGIC.to_le_bytes().to_vec();
+                stored.extend((deflated.len() as u64).to_le_bytes());
+                stored.extend((data.len() as u64).to_le_bytes());
+                stored.extend((zip_name as u32).to_le_bytes());
+                stored.extend(0u32.to_le_bytes());
+                stored.push(1);
+                stored.extend(deflated);
+                compressed_size = stored.len() as u64;
+            }
+            let attributes = [
+                (ATTRIBUTE_MODULE, add_string(module)),
+                (ATTRIBUTE_PARENT, add_string(parent)),
+                (ATTRIBUTE_BASE, add_string(base)),
+                (ATTRIBUTE_EXTENSION, add_string(extension)),
+                (ATTRIBUTE_OFFSET, content.len() as u64),
+                (ATTRIBUTE_COMPRESSED, compressed_size),
+                (ATTRIBUTE_UNCOMPRESSED, data.len() as u64),
+            ];
+            location_offsets.push(locations.len() as u32);
+            for (kind, value) in attributes {
+                let length = (8 - value.leading_zeros() as usize / 8).max(1);
+                locations.push((kind << 3 | (length - 1)) as u8);
+                locations.extend(&value.to_be_bytes()[8 - length..]);
+            }
+            locations.push(0);
+            content.extend(stored);
+            let location = ImageLocation {
+                module: module.to_string(),
+                parent: parent.to_string(),
+                base: base.to_string(),
+                extension: extension.to_string(),
+                content_offset: 0,
+                compressed_size: 0,
+                uncompressed_size: 0,
+            };
+            names.push(location.get_full_name());
+        }
+
+        let count = names.len() as u32;
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); count as usize];
+        for (i, name) in names.iter().enumerate() {
+            buckets[(hash_code(name, HASH_MULTIPLIER) % count) as usize].push(i);
+        }
+        let mut order: Vec<usize> = (0..count as usize).collect();
+        order.sort_by_key(|bucket| std::cmp::Reverse(buckets[*bucket].len()));
+        let mut redirect = vec![0i32; count as usize];
+        let mut slots: Vec<Option<usize>> = vec![None; count as usize];
+        for bucket in order {
+            match buckets[bucket].as_slice() {
+                [] => {}
+                [single] => {
+                    let slot = slots.iter().position(Option::is_none).unwrap();
+                    slots[slot] = Some(*single);
+                    redirect[bucket] = -(slot as i32) - 1;
+                }
+                members => {
+                    let seed = (1..)
+                        .find(|seed| {
+                            let mut taken: Vec<u32> = members
+                                .iter()
+                                .map(|i| hash_code(&names[*i], *seed) % count)
+                                .collect();
+                            let all = taken.len();
+                            taken.sort();
+                            taken.dedup();
+                            taken.len() == all && taken.iter().all(|s| slots[*s as usize].is_none())
+                        })
+                        .unwrap();
+                    for i in members {
+                        slots[(hash_code(&names[*i], seed) % count) as usize] = Some(*i);
+                    }
+                    redirect[bucket] = seed as i32;
+                }
+            }
+        }
+
+        let mut image = Vec::new();
+        image.extend(MAGIC.to_le_bytes());
+        image.extend((1u32 << 16).to_le_bytes());
+        image.extend(0u32.to_le_bytes());
+        image.extend(count.to_le_bytes());
+        image.extend(count.to_le_bytes());
+        image.extend((locations.len() as u32).to_le_bytes());
+        image.extend((strings.len() as u32).to_le_bytes());
+        for value in redirect {
+            image.extend(value.to_le_bytes());
+        }
+        for slot in slots {
+            image.extend(location_offsets[slot.unwrap()].to_le_bytes());
+        }
+        image.extend(locations);
+        image.extend(strings);
+        image.extend(content);
+        image
+    }
+
+    fn fixture() -> JImage {
+        JImage::from_bytes(write_image(&[
+            (
+                "java.base",
+                "java/lang",
+                "Object",
+                "class",
+                &EMPTY_CLASS,
+                false,
+            ),
+            (
+                "java.base",
+                "java/lang",
+                "String",
+                "class",
+                &EMPTY_CLASS,
+                true,
+            ),
+            (
+                "java.base",
+                "java/util",
+                "List",
+                "class",
+                &EMPTY_CLASS,
+                false,
+            ),
+            ("java.base", "", "module-info", "class", &EMPTY_CLASS, false),
+            ("java.base", "jdk/internal", "icu", "dat", b"data", true),
+            ("java.sql", "java/sql", "Date", "class", &EMPTY_CLASS, false),
+            ("packages", "java.lang", "java.base", "", &[0; 8], false),
+            ("modules", "", "java.base", "", b"", false),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_resources() {
+        let image = fixture();
+        assert_eq!(image.get_locations().unwrap().len(), 8);
+        assert_eq!(image.get_modules().unwrap(), ["java.base", "java.sql"]);
+        assert_eq!(
+            image.get_packages("java.base").unwrap(),
+            ["java.lang", "java.util"]
+        );
+        for name in ["java/lang/Object", "java/lang/String", "java/util/List"] {
+            assert!(image.get_class("java.base", name).unwrap().is_some());
+        }
+        assert!(image
+            .get_class("java.base", "java/sql/Date")
+            .unwrap()
+            .is_none());
+        assert!(image
+            .get_class("java.sql", "java/sql/Date")
+            .unwrap()
+            .is_some());
+
+        let icu = image
+            .find_location("/java.base/jdk/internal/icu.dat")
+            .unwrap()
+            .unwrap();
+        assert_eq!(icu.get_resource_name(), "jdk/internal/icu.dat");
+        assert_eq!(image.read_resource(&icu).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_reject_invalid_images() {
+        assert!(JImage::from_bytes(vec![0xca, 0xfe, 0xba, 0xbe]).is_err());
+        let mut image = write_image(&[("java.base", "java/lang", "Object", "class", b"", false)]);
+        image[4..8].copy_from_slice(&(2u32 << 16).to_le_bytes());
+        assert!(JImage::from_bytes(image).is_err());
+    }
+}
@@ -0,0 +1,1317 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::attributes::Element;
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+
+/// The `k` element of `@kotlin.Metadata`, which decides how `d1` and `d2` are read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MetadataKind {
+    /// 1, a class, interface, object or annotation class; `d1` holds a `Class` message.
+    Class,
+    /// 2, the facade class generated for top level declarations of a file.
+    FileFacade,
+    /// 3, a lambda or other class kotlinc generates that has no source declaration.
+    SyntheticClass,
+    /// 4, the facade of a `@JvmMultifileClass`; `d1` names the part classes.
+    MultiFileClassFacade,
+    /// 5, one of the parts of a multi-file class.
+    MultiFileClassPart,
+    Unknown(i32),
+}
+
+impl MetadataKind {
+    pub fn from_kind(kind: i32) -> MetadataKind {
+        match kind {
+            1 => MetadataKind::Class,
+            2 => MetadataKind::FileFacade,
+            3 => MetadataKind::SyntheticClass,
+            4 => MetadataKind::MultiFileClassFacade,
+            5 => MetadataKind::MultiFileClassPart,
+            _ => MetadataKind::Unknown(kind),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    Internal,
+    Private,
+    Protected,
+    Public,
+    PrivateToThis,
+    Local,
+}
+
+impl Visibility {
+    fn from_flags(flags: i32) -> Visibility {
+        match (flags >> 1) & 0x7 {
+            0 => Visibility::Internal,
+            1 => Visibility::Private,
+            2 => Visibility::Protected,
+            4 => Visibility::PrivateToThis,
+            5 => Visibility::Local,
+            _ => Visibility::Public,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Modality {
+    Final,
+    Open,
+    Abstract,
+    Sealed,
+}
+
+impl Modality {
+    fn from_flags(flags: i32) -> Modality {
+        match (flags >> 4) & 0x3 {
+            0 => Modality::Final,
+            1 => Modality::Open,
+            2 => Modality::Abstract,
+            _ => Modality::Sealed,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClassKind {
+    Class,
+    Interface,
+    EnumClass,
+    EnumEntry,
+    AnnotationClass,
+    Object,
+    CompanionObject,
+}
+
+impl ClassKind {
+    fn from_flags(flags: i32) -> ClassKind {
+        match (flags >> 6) & 0x7 {
+            1 => ClassKind::Interface,
+            2 => ClassKind::EnumClass,
+            3 => ClassKind::EnumEntry,
+            4 => ClassKind::AnnotationClass,
+            5 => ClassKind::Object,
+            6 => ClassKind::CompanionObject,
+            _ => ClassKind::Class,
+        }
+    }
+}
+
+/// Whether a callable was written in source or produced by the compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemberKind {
+    Declaration,
+    FakeOverride,
+    Delegation,
+    Synthesized,
+}
+
+impl MemberKind {
+    fn from_flags(flags: i32) -> MemberKind {
+        match (flags >> 6) & 0x3 {
+            0 => MemberKind::Declaration,
+            1 => MemberKind::FakeOverride,
+            2 => MemberKind::Delegation,
+            _ => MemberKind::Synthesized,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Variance {
+    In,
+    Out,
+    Invariant,
+}
+
+impl Variance {
+    fn from_value(value: i32) -> Variance {
+        match value {
+            0 => Variance::In,
+            1 => Variance::Out,
+            _ => Variance::Invariant,
+        }
+    }
+}
+
+/// What a type refers to. Class names use the Kotlin form `kotlin/collections/Map.Entry`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KotlinClassifier {
+    Class(String),
+    TypeParameter(String),
+    TypeAlias(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KotlinTypeProjection {
+    Star,
+    Type(Variance, KotlinType),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KotlinType {
+    pub classifier: KotlinClassifier,
+    pub arguments: Vec<KotlinTypeProjection>,
+    pub is_nullable: bool,
+    /// The upper bound of a platform type such as `String!`, the type itself being the lower.
+    pub flexible_upper_bound: Option<Box<KotlinType>>,
+    /// The type of the enclosing class when this is an inner class, e.g. `Outer<T>.Inner`.
+    pub outer_type: Option<Box<KotlinType>>,
+    pub flags: i32,
+}
+
+impl KotlinType {
+    pub fn is_suspend(&self) -> bool {
+        self.flags & 0x1 != 0
+    }
+
+    /// `T & Any`, a type parameter that is known not to be null.
+    pub fn is_definitely_non_null(&self) -> bool {
+        self.flags & 0x2 != 0
+    }
+}
+
+impl Display for KotlinType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(upper) = &self.flexible_upper_bound {
+            let lower = KotlinType {
+                flexible_upper_bound: None,
+                ..self.clone()
+            };
+            return write!(f, "({lower}..{upper})");
+        }
+        if self.is_suspend() {
+            write!(f, "suspend ")?;
+        }
+        match &self.classifier {
+            KotlinClassifier::Class(name)
+            | KotlinClassifier::TypeParameter(name)
+            | KotlinClassifier::TypeAlias(name) => write!(f, "{name}")?,
+        }
+        if !self.arguments.is_empty() {
+            let arguments: Vec<String> = self
+                .arguments
+                .iter()
+                .map(|argument| match argument {
+                    KotlinTypeProjection::Star => "*".to_string(),
+                    KotlinTypeProjection::Type(Variance::In, t) => format!("in {t}"),
+                    KotlinTypeProjection::Type(Variance::Out, t) => format!("out {t}"),
+                    KotlinTypeProjection::Type(Variance::Invariant, t) => t.to_string(),
+                })
+                .collect();
+            write!(f, "<{}>", arguments.join(", "))?;
+        }
+        if self.is_nullable {
+            write!(f, "?")?;
+        }
+        if self.is_definitely_non_null() {
+            write!(f, " & Any")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KotlinTypeParameter {
+    pub id: i32,
+    pub name: String,
+    pub is_reified: bool,
+    pub variance: Variance,
+    pub upper_bounds: Vec<KotlinType>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KotlinValueParameter {
+    pub name: String,
+    pub flags: i32,
+    pub value_type: KotlinType,
+    /// The element type `T` of a `vararg` parameter, whose [value_type](Self::value_type)
+    /// is then the array type.
+    pub vararg_element_type: Option<KotlinType>,
+}
+
+impl KotlinValueParameter {
+    pub fn declares_default_value(&self) -> bool {
+        self.flags & (1 << 1) != 0
+    }
+
+    pub fn is_crossinline(&self) -> bool {
+        self.flags & (1 << 2) != 0
+    }
+
+    pub fn is_noinline(&self) -> bool {
+        self.flags & (1 << 3) != 0
+    }
+
+    pub fn is_vararg(&self) -> bool {
+        self.vararg_element_type.is_some()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KotlinConstructor {
+    pub flags: i32,
+    pub visibility: Visibility,
+    pub value_parameters: Vec<KotlinValueParameter>,
+}
+
+impl KotlinConstructor {
+    pub fn is_secondary(&self) -> bool {
+        self.flags & (1 << 4) != 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KotlinFunction {
+    pub name: String,
+    pub flags: i32,
+    pub visibility: Visibility,
+    pub modality: Modality,
+    pub member_kind: MemberKind,
+    pub type_parameters: Vec<KotlinTypeParameter>,
+    /// The receiver of an extension function.
+    pub receiver_type: Option<KotlinType>,
+    pub value_parameters: Vec<KotlinValueParameter>,
+    pub return_type: KotlinType,
+    /// The JVM name and descriptor of the method, e.g. `plus(Lcom/example/Point;)I`, when
+    /// they differ from what the declaration alone implies.
+    pub jvm_signature: Option<String>,
+}
+
+impl KotlinFunction {
+    pub fn is_operator(&self) -> bool {
+        self.flags & (1 << 8) != 0
+    }
+
+    pub fn is_infix(&self) -> bool {
+        self.flags & (1 << 9) != 0
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.flags & (1 << 10) != 0
+    }
+
+    pub fn is_tailrec(&self) -> bool {
+        self.flags & (1 << 11) != 0
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.flags & (1 << 12) != 0
+    }
+
+    pub fn is_suspend(&self) -> bool {
+        self.flags & (1 << 13) != 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KotlinProperty {
+    pub name: String,
+    pub flags: i32,
+    pub visibility: Visibility,
+    pub modality: Modality,
+    pub member_kind: MemberKind,
+    pub type_parameters: Vec<KotlinTypeParameter>,
+    /// The receiver of an extension property.
+    pub receiver_type: Option<KotlinType>,
+    pub return_type: KotlinType,
+    pub setter_parameter: Option<KotlinValueParameter>,
+}
+
+impl KotlinProperty {
+    pub fn is_var(&self) -> bool {
+        self.flags & (1 << 8) != 0
+    }
+
+    pub fn has_getter(&self) -> bool {
+        self.flags & (1 << 9) != 0
+    }
+
+    pub fn has_setter(&self) -> bool {
+        self.flags & (1 << 10) != 0
+    }
+
+    pub fn is_const(&self) -> bool {
+        self.flags & (1 << 11) != 0
+    }
+
+    pub fn is_lateinit(&self) -> bool {
+        self.flags & (1 << 12) != 0
+    }
+
+    pub fn has_constant(&self) -> bool {
+        self.flags & (1 << 13) != 0
+    }
+
+    pub fn is_delegated(&self) -> bool {
+        self.flags & (1 << 15) != 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KotlinClass {
+    /// The Kotlin class name, nested classes are separated by `.`, e.g. `a/b/Outer.Inner`.
+    pub name: String,
+    pub flags: i32,
+    pub visibility: Visibility,
+    pub modality: Modality,
+    pub kind: ClassKind,
+    pub type_parameters: Vec<KotlinTypeParameter>,
+    pub supertypes: Vec<KotlinType>,
+    pub constructors: Vec<KotlinConstructor>,
+    pub functions: Vec<KotlinFunction>,
+    pub properties: Vec<KotlinProperty>,
+    /// Simple names of the nested classes.
+    pub nested_classes: Vec<String>,
+    pub enum_entries: Vec<String>,
+    pub sealed_subclasses: Vec<String>,
+    /// Simple name of the companion object.
+    pub companion_object: Option<String>,
+    /// The property wrapped by a value class.
+    pub inline_class_underlying_property: Option<String>,
+}
+
+impl KotlinClass {
+    pub fn is_inner(&self) -> bool {
+        self.flags & (1 << 9) != 0
+    }
+
+    pub fn is_data(&self) -> bool {
+        self.flags & (1 << 10) != 0
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.flags & (1 << 11) != 0
+    }
+
+    pub fn is_expect(&self) -> bool {
+        self.flags & (1 << 12) != 0
+    }
+
+    /// An `inline class` or `value class`.
+    pub fn is_inline(&self) -> bool {
+        self.flags & (1 << 13) != 0
+    }
+
+    pub fn is_fun_interface(&self) -> bool {
+        self.flags & (1 << 14) != 0
+    }
+}
+
+/// The top level declarations of a file facade or multi-file class part.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct KotlinPackage {
+    pub functions: Vec<KotlinFunction>,
+    pub properties: Vec<KotlinProperty>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KotlinDeclarations {
+    Class(KotlinClass),
+    Package(KotlinPackage),
+    /// The internal names of the parts of a multi-file class facade.
+    MultiFileFacade(Vec<String>),
+    /// The function a lambda class implements, if kotlinc recorded one.
+    SyntheticClass(Option<KotlinFunction>),
+    /// Declarations of an unknown kind, or of a metadata version this reader cannot decode.
+    Unknown,
+}
+
+/// [Kotlin Metadata](https://kotlinlang.org/api/latest/jvm/stdlib/kotlin/-metadata/)
+/**
+ * The `@kotlin.Metadata` annotation kotlinc places on every class it generates.\
+ *  Declarations are serialised as protocol buffers in `d1`, packed into strings with
+ *  BitEncoding, and refer to names by index into the string table made of `d2` and the
+ *  `StringTableTypes` message that starts `d1`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KotlinMetadata {
+    pub kind: MetadataKind,
+    /// `mv`, the version of the metadata format, e.g. `[1, 7, 1]`.
+    pub metadata_version: Vec<i32>,
+    /// `xs`, the name of the facade class for a multi-file class part.
+    pub extra_string: Option<String>,
+    /// `pn`, the Kotlin package when it differs from the JVM package.
+    pub package_name: Option<String>,
+    /// `xi`, flags such as whether the class was compiled with pre-release features.
+    pub extra_int: i32,
+    pub declarations: KotlinDeclarations,
+}
+
+impl KotlinMetadata {
+    pub fn new(
+        kind: i32,
+        metadata_version: Vec<i32>,
+        data1: &[Vec<u16>],
+        data2: &[String],
+        extra_string: Option<String>,
+        package_name: Option<String>,
+        extra_int: i32,
+    ) -> Result<KotlinMetadata, Box<dyn Error>> {
+        let kind = MetadataKind::from_kind(kind);
+        let declarations = match kind {
+            MetadataKind::MultiFileClassFacade => KotlinDeclarations::MultiFileFacade(
+                data1
+                    .iter()
+                    .map(|part| String::from_utf16_lossy(part))
+                    .collect(),
+            ),
+            MetadataKind::Unknown(_) => KotlinDeclarations::Unknown,
+            _ if data1.is_empty() => match kind {
+                MetadataKind::SyntheticClass => KotlinDeclarations::SyntheticClass(None),
+                _ => KotlinDeclarations::Unknown,
+            },
+            _ => {
+                let bytes = decode_bytes(data1);
+                let mut position = 0;
+                let length = read_varint(&bytes, &mut position)? as usize;
+                let table = bytes
+                    .get(position..position + length)
+                    .ok_or_else(|| invalid("string table is truncated"))?;
+                let resolver = NameResolver::new(&Message::parse(table)?, data2);
+                let message = Message::parse(&bytes[position + length..])?;
+                let mut decoder = Decoder::new(&resolver);
+                match kind {
+                    MetadataKind::Class => KotlinDeclarations::Class(decoder.class(&message)?),
+                    MetadataKind::SyntheticClass => {
+                        KotlinDeclarations::SyntheticClass(Some(decoder.function(&message)?))
+                    }
+                    _ => KotlinDeclarations::Package(decoder.package(&message)?),
+                }
+            }
+        };
+        Ok(KotlinMetadata {
+            kind,
+            metadata_version,
+            extra_string,
+            package_name,
+            extra_int,
+            declarations,
+        })
+    }
+}
+
+impl ClassFile {
+    /// Decodes the `@kotlin.Metadata` annotation, `None` if the class was not written by kotlinc.
+    pub fn get_kotlin_metadata(&self) -> Result<Option<KotlinMetadata>, Box<dyn Error>> {
+        let cp = &self.constant_pool;
+        let annotation = self
+            .attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                AttributeInfo::RuntimeVisibleAnnotations(a) => Some(&a.annotations),
+                _ => None,
+            })
+            .flatten()
+            .find(|annotation| annotation.get_type(cp) == "Lkotlin/Metadata;");
+        let annotation = match annotation {
+            Some(annotation) => annotation,
+            None => return Ok(None),
+        };
+        let kind = annotation
+            .get_element("k", cp)
+            .and_then(|k| get_int(k, cp))
+            .unwrap_or(1);
+        let metadata_version = match annotation.get_element("mv", cp) {
+            Some(Element::ArrayValue { values, .. }) => {
+                values.iter().filter_map(|v| get_int(v, cp)).collect()
+            }
+            _ => vec![],
+        };
+        let data1 = get_strings(annotation.get_element("d1", cp), cp);
+        let data2: Vec<String> = get_strings(annotation.get_element("d2", cp), cp)
+            .iter()
+            .map(|units| String::from_utf16_lossy(units))
+            .collect();
+        let get_text = |name: &str| {
+            annotation
+                .get_element(name, cp)
+                .and_then(|element| get_string(element, cp))
+                .map(|units| String::from_utf16_lossy(&units))
+                .filter(|text| !text.is_empty())
+        };
+        let extra_int = annotation
+            .get_element("xi", cp)
+            .and_then(|xi| get_int(xi, cp))
+            .unwrap_or(0);
+        Ok(Some(KotlinMetadata::new(
+            kind,
+            metadata_version,
+            &data1,
+            &data2,
+            get_text("xs"),
+            get_text("pn"),
+            extra_int,
+        )?))
+    }
+
+    pub fn is_kotlin(&self) -> bool {
+        matches!(self.get_kotlin_metadata(), Ok(Some(_)))
+    }
+}
+
+fn get_int(element: &Element, cp: &[ConstantPool]) -> Option<i32> {
+    match element {
+        Element::ConstValueIndex('I', index) => match cp.get(*index as usize) {
+            Some(ConstantPool::Integer(integer)) => Some(integer.bytes as i32),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn get_string(element: &Element, cp: &[ConstantPool]) -> Option<Vec<u16>> {
+    match element {
+        Element::ConstValueIndex('s', index) => match cp.get(*index as usize) {
+            Some(ConstantPool::Utf8(utf8)) => Some(utf8.get_utf16()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn get_strings(element: Option<&Element>, cp: &[ConstantPool]) -> Vec<Vec<u16>> {
+    match element {
+        Some(Element::ArrayValue { values, .. }) => {
+            values.iter().filter_map(|v| get_string(v, cp)).collect()
+        }
+        _ => vec![],
+    }
+}
+
+/**
+ * Undoes BitEncoding.\
+ *  Since Kotlin 1.4 the strings start with a `\u0000` marker and every char holds one byte.
+ *  Older compilers split the bytes into 7 bit groups, offset by one so that no char is zero.
+ */
+fn decode_bytes(data: &[Vec<u16>]) -> Vec<u8> {
+    let units = data.iter().flatten();
+    if data.first().and_then(|first| first.first()) == Some(&0) {
+        return units.skip(1).map(|unit| *unit as u8).collect();
+    }
+    let bytes: Vec<u8> = units
+        .map(|unit| (*unit as u8).wrapping_add(0x7F) & 0x7F)
+        .collect();
+    let mut result = Vec::with_capacity(7 * bytes.len() / 8);
+    let mut index = 0;
+    let mut bit = 0;
+    for _ in 0..7 * bytes.len() / 8 {
+        let first = (bytes[index] as u32) >> bit;
+        index += 1;
+        let second = ((bytes[index] as u32) & ((1 << (bit + 1)) - 1)) << (7 - bit);
+        result.push((first + second) as u8);
+        if bit == 6 {
+            index += 1;
+            bit = 0;
+        } else {
+            bit += 1;
+        }
+    }
+    result
+}
+
+/// Strings that a `StringTableTypes` record may refer to instead of repeating them in `d2`.
+const PREDEFINED_STRINGS: [&str; 44] = [
+    "kotlin/Any",
+    "kotlin/Nothing",
+    "kotlin/Unit",
+    "kotlin/Throwable",
+    "kotlin/Number",
+    "kotlin/Byte",
+    "kotlin/Double",
+    "kotlin/Float",
+    "kotlin/Int",
+    "kotlin/Long",
+    "kotlin/Short",
+    "kotlin/Boolean",
+    "kotlin/Char",
+    "kotlin/CharSequence",
+    "kotlin/String",
+    "kotlin/Comparable",
+    "kotlin/Enum",
+    "kotlin/Array",
+    "kotlin/ByteArray",
+    "kotlin/DoubleArray",
+    "kotlin/FloatArray",
+    "kotlin/IntArray",
+    "kotlin/LongArray",
+    "kotlin/ShortArray",
+    "kotlin/BooleanArray",
+    "kotlin/CharArray",
+    "kotlin/Cloneable",
+    "kotlin/Annotation",
+    "kotlin/collections/Iterable",
+    "kotlin/collections/MutableIterable",
+    "kotlin/collections/Collection",
+    "kotlin/collections/MutableCollection",
+    "kotlin/collections/List",
+    "kotlin/collections/MutableList",
+    "kotlin/collections/Set",
+    "kotlin/collections/MutableSet",
+    "kotlin/collections/Map",
+    "kotlin/collections/MutableMap",
+    "kotlin/collections/Map.Entry",
+    "kotlin/collections/MutableMap.MutableEntry",
+    "kotlin/collections/Iterator",
+    "kotlin/collections/MutableIterator",
+    "kotlin/collections/ListIterator",
+    "kotlin/collections/MutableListIterator",
+];
+
+/// Resolves string indices through the records of `StringTableTypes`, as JvmNameResolver does.
+struct NameResolver<'a> {
+    records: Vec<Message>,
+    strings: &'a [String],
+}
+
+impl<'a> NameResolver<'a> {
+    fn new(types: &Message, strings: &'a [String]) -> NameResolver<'a> {
+        let mut records = vec![];
+        for record in types.get_messages(1).unwrap_or_default() {
+            let range = record.get_int(1).unwrap_or(1).max(0);
+            for _ in 0..range {
+                records.push(record.clone());
+            }
+        }
+        NameResolver { records, strings }
+    }
+
+    fn get(&self, index: i32) -> Result<String, Box<dyn Error>> {
+        let index = usize::try_from(index).map_err(|_| invalid("negative string index"))?;
+        let record = match self.records.get(index) {
+            Some(record) => record,
+            None => {
+                return self
+                    .strings
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| invalid("string index out of range"))
+            }
+        };
+        let predefined = record
+            .get_int(2)
+            .and_then(|i| PREDEFINED_STRINGS.get(usize::try_from(i).ok()?));
+        let mut string = match (record.get_bytes(6), predefined) {
+            (Some(bytes), _) => String::from_utf8_lossy(bytes).into_owned(),
+            (None, Some(predefined)) => predefined.to_string(),
+            (None, None) => self
+                .strings
+                .get(index)
+                .cloned()
+                .ok_or_else(|| invalid("string index out of range"))?,
+        };
+        let substring = record.get_ints(4);
+        if substring.len() >= 2 {
+            let units: Vec<u16> = string.encode_utf16().collect();
+            let range =
+                (substring[0] as usize).min(units.len())..(substring[1] as usize).min(units.len());
+            string = String::from_utf16_lossy(units.get(range).unwrap_or_default());
+        }
+        let replace = record.get_ints(5);
+        if replace.len() >= 2 {
+            if let (Some(from), Some(to)) = (
+                char::from_u32(replace[0] as u32),
+                char::from_u32(replace[1] as u32),
+            ) {
+                string = string.replace(from, &to.to_string());
+            }
+        }
+        match record.get_int(3).unwrap_or(0) {
+            // INTERNAL_TO_CLASS_ID
+            1 => string = string.replace('$', "."),
+            // DESC_TO_CLASS_ID
+            2 => {
+                if string.chars().count() >= 2 {
+                    let mut chars = string.chars();
+                    chars.next();
+                    chars.next_back();
+                    string = chars.as_str().to_string();
+                }
+                string = string.replace('$', ".");
+            }
+            _ => {}
+        }
+        Ok(string)
+    }
+}
+
+/// Builds the declaration models, tracking the type table and type parameters in scope.
+struct Decoder<'a> {
+    resolver: &'a NameResolver<'a>,
+    type_table: Vec<Message>,
+    first_nullable: Option<usize>,
+    type_parameters: Vec<(i32, String)>,
+}
+
+struct Scope {
+    type_table: Option<(Vec<Message>, Option<usize>)>,
+    type_parameters: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(resolver: &'a NameResolver<'a>) -> Decoder<'a> {
+        Decoder {
+            resolver,
+            type_table: vec![],
+            first_nullable: None,
+            type_parameters: vec![],
+        }
+    }
+
+    /// Brings the type table (field 30) and type parameters (`type_parameter_field`) of
+    /// `message` into scope, returning them decoded along with what to restore afterwards.
+    fn enter(
+        &mut self,
+        message: &Message,
+        type_parameter_field: u32,
+    ) -> Result<(Scope, Vec<KotlinTypeParameter>), Box<dyn Error>> {
+        let mut scope = Scope {
+            type_table: None,
+            type_parameters: self.type_parameters.len(),
+        };
+        if let Some(table) = message.get_message(30)? {
+            let first_nullable = table.get_int(2).and_then(|i| usize::try_from(i).ok());
+            scope.type_table = Some((
+                std::mem::replace(&mut self.type_table, table.get_messages(1)?),
+                std::mem::replace(&mut self.first_nullable, first_nullable),
+            ));
+        }
+        let parameters = message.get_messages(type_parameter_field)?;
+        for parameter in &parameters {
+            let id = parameter.get_int(1).unwrap_or(0);
+            let name = self.resolver.get(parameter.get_int(2).unwrap_or(0))?;
+            self.type_parameters.push((id, name));
+        }
+        let mut type_parameters = vec![];
+        for parameter in &parameters {
+            type_parameters.push(self.type_parameter(parameter)?);
+        }
+        Ok((scope, type_parameters))
+    }
+
+    fn leave(&mut self, scope: Scope) {
+        self.type_parameters.truncate(scope.type_parameters);
+        if let Some((type_table, first_nullable)) = scope.type_table {
+            self.type_table = type_table;
+            self.first_nullable = first_nullable;
+        }
+    }
+
+    fn class(&mut self, message: &Message) -> Result<KotlinClass, Box<dyn Error>> {
+        let (scope, type_parameters) = self.enter(message, 5)?;
+        let flags = message.get_int(1).unwrap_or(6);
+        let mut supertypes = vec![];
+        for supertype in message.get_messages(6)? {
+            supertypes.push(self.kotlin_type(&supertype)?);
+        }
+        for id in message.get_ints(2) {
+            supertypes.push(self.type_by_id(id)?);
+        }
+        let mut constructors = vec![];
+        for constructor in message.get_messages(8)? {
+            let flags = constructor.get_int(1).unwrap_or(6);
+            constructors.push(KotlinConstructor {
+                flags,
+                visibility: Visibility::from_flags(flags),
+                value_parameters: self.value_parameters(&constructor, 2)?,
+            });
+        }
+        let mut functions = vec![];
+        for function in message.get_messages(9)? {
+            functions.push(self.function(&function)?);
+        }
+        let mut properties = vec![];
+        for property in message.get_messages(10)? {
+            properties.push(self.property(&property)?);
+        }
+        let names = |field: u32| -> Result<Vec<String>, Box<dyn Error>> {
+            message
+                .get_ints(field)
+                .into_iter()
+                .map(|index| self.resolver.get(index))
+                .collect()
+        };
+        let nested_classes = names(7)?;
+        let sealed_subclasses = names(16)?;
+        let mut enum_entries = vec![];
+        for entry in message.get_messages(13)? {
+            enum_entries.push(self.resolver.get(entry.get_int(1).unwrap_or(0))?);
+        }
+        let class = KotlinClass {
+            name: self.resolver.get(message.require_int(3)?)?,
+            flags,
+            visibility: Visibility::from_flags(flags),
+            modality: Modality::from_flags(flags),
+            kind: ClassKind::from_flags(flags),
+            type_parameters,
+            supertypes,
+            constructors,
+            functions,
+            properties,
+            nested_classes,
+            enum_entries,
+            sealed_subclasses,
+            companion_object: self.optional_name(message, 4)?,
+            inline_class_underlying_property: self.optional_name(message, 17)?,
+        };
+        self.leave(scope);
+        Ok(class)
+    }
+
+    fn package(&mut self, message: &Message) -> Result<KotlinPackage, Box<dyn Error>> {
+        let (scope, _) = self.enter(message, 0)?;
+        let mut package = KotlinPackage::default();
+        for function in message.get_messages(3)? {
+            package.functions.push(self.function(&function)?);
+        }
+        for property in message.get_messages(4)? {
+            package.properties.push(self.property(&property)?);
+        }
+        self.leave(scope);
+        Ok(package)
+    }
+
+    fn function(&mut self, message: &Message) -> Result<KotlinFunction, Box<dyn Error>> {
+        let (scope, type_parameters) = self.enter(message, 4)?;
+        let flags = get_flags(message, 9, 1, 6);
+        let name = self.resolver.get(message.require_int(2)?)?;
+        // JvmProtoBuf.methodSignature extension
+        let jvm_signature = match message.get_message(100)? {
+            Some(signature) => match signature.get_int(2) {
+                Some(descriptor) => Some(format!(
+                    "{}{}",
+                    self.optional_name(&signature, 1)?
+                        .unwrap_or_else(|| name.clone()),
+                    self.resolver.get(descriptor)?
+                )),
+                None => None,
+            },
+            None => None,
+        };
+        let function = KotlinFunction {
+            name,
+            flags,
+            visibility: Visibility::from_flags(flags),
+            modality: Modality::from_flags(flags),
+            member_kind: MemberKind::from_flags(flags),
+            type_parameters,
+            receiver_type: self.optional_type(message, 5, 8)?,
+            value_parameters: self.value_parameters(message, 6)?,
+            return_type: self.required_type(message, 3, 7)?,
+            jvm_signature,
+        };
+        self.leave(scope);
+        Ok(function)
+    }
+
+    fn property(&mut self, message: &Message) -> Result<KotlinProperty, Box<dyn Error>> {
+        let (scope, type_parameters) = self.enter(message, 4)?;
+        let flags = get_flags(message, 11, 1, 518);
+        let property = KotlinProperty {
+            name: self.resolver.get(message.require_int(2)?)?,
+            flags,
+            visibility: Visibility::from_flags(flags),
+            modality: Modality::from_flags(flags),
+            member_kind: MemberKind::from_flags(flags),
+            type_parameters,
+            receiver_type: self.optional_type(message, 5, 10)?,
+            return_type: self.required_type(message, 3, 9)?,
+            setter_parameter: self.value_parameters(message, 6)?.pop(),
+        };
+        self.leave(scope);
+        Ok(property)
+    }
+
+    fn value_parameters(
+        &mut self,
+        message: &Message,
+        field: u32,
+    ) -> Result<Vec<KotlinValueParameter>, Box<dyn Error>> {
+        let mut parameters = vec![];
+        for parameter in message.get_messages(field)? {
+            parameters.push(KotlinValueParameter {
+                name: self.resolver.get(parameter.require_int(2)?)?,
+                flags: parameter.get_int(1).unwrap_or(0),
+                value_type: self.required_type(&parameter, 3, 5)?,
+                vararg_element_type: self.optional_type(&parameter, 4, 6)?,
+            });
+        }
+        Ok(parameters)
+    }
+
+    fn type_parameter(&mut self, message: &Message) -> Result<KotlinTypeParameter, Box<dyn Error>> {
+        let mut upper_bounds = vec![];
+        for bound in message.get_messages(5)? {
+            upper_bounds.push(self.kotlin_type(&bound)?);
+        }
+        for id in message.get_ints(6) {
+            upper_bounds.push(self.type_by_id(id)?);
+        }
+        Ok(KotlinTypeParameter {
+            id: message.get_int(1).unwrap_or(0),
+            name: self.resolver.get(message.get_int(2).unwrap_or(0))?,
+            is_reified: message.get_int(3).unwrap_or(0) != 0,
+            variance: Variance::from_value(message.get_int(4).unwrap_or(2)),
+            upper_bounds,
+        })
+    }
+
+    fn optional_name(
+        &self,
+        message: &Message,
+        field: u32,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        message
+            .get_int(field)
+            .map(|index| self.resolver.get(index))
+            .transpose()
+    }
+
+    /// A type given either inline in `field` or by index into the type table in `id_field`.
+    fn optional_type(
+        &mut self,
+        message: &Message,
+        field: u32,
+        id_field: u32,
+    ) -> Result<Option<KotlinType>, Box<dyn Error>> {
+        if let Some(inline) = message.get_message(field)? {
+            return Ok(Some(self.kotlin_type(&inline)?));
+        }
+        message
+            .get_int(id_field)
+            .map(|id| self.type_by_id(id))
+            .transpose()
+    }
+
+    fn required_type(
+        &mut self,
+        message: &Message,
+        field: u32,
+        id_field: u32,
+    ) -> Result<KotlinType, Box<dyn Error>> {
+        self.optional_type(message, field, id_field)?
+            .ok_or_else(|| invalid("declaration is missing its type"))
+    }
+
+    fn type_by_id(&mut self, id: i32) -> Result<KotlinType, Box<dyn Error>> {
+        let index = usize::try_from(id).map_err(|_| invalid("negative type id"))?;
+        let message = self
+            .type_table
+            .get(index)
+            .cloned()
+            .ok_or_else(|| invalid("type id out of range"))?;
+        let mut kotlin_type = self.kotlin_type(&message)?;
+        if self.first_nullable.is_some_and(|first| index >= first) {
+            kotlin_type.is_nullable = true;
+        }
+        Ok(kotlin_type)
+    }
+
+    fn kotlin_type(&mut self, message: &Message) -> Result<KotlinType, Box<dyn Error>> {
+        let classifier = if let Some(name) = message.get_int(6) {
+            KotlinClassifier::Class(self.resolver.get(name)?)
+        } else if let Some(id) = message.get_int(7) {
+            let name = self
+                .type_parameters
+                .iter()
+                .rev()
+                .find(|(parameter, _)| *parameter == id)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| format!("T#{id}"));
+            KotlinClassifier::TypeParameter(name)
+        } else if let Some(name) = message.get_int(9) {
+            KotlinClassifier::TypeParameter(self.resolver.get(name)?)
+        } else if let Some(name) = message.get_int(12) {
+            KotlinClassifier::TypeAlias(self.resolver.get(name)?)
+        } else {
+            return Err(invalid("type has no classifier"));
+        };
+        let mut arguments = vec![];
+        for argument in message.get_messages(2)? {
+            arguments.push(match argument.get_int(1).unwrap_or(2) {
+                3 => KotlinTypeProjection::Star,
+                projection => KotlinTypeProjection::Type(
+                    Variance::from_value(projection),
+                    self.required_type(&argument, 2, 3)?,
+                ),
+            });
+        }
+        Ok(KotlinType {
+            classifier,
+            arguments,
+            is_nullable: message.get_int(3).unwrap_or(0) != 0,
+            flexible_upper_bound: self.optional_type(message, 5, 8)?.map(Box::new),
+            outer_type: self.optional_type(message, 10, 11)?.map(Box::new),
+            flags: message.get_int(1).unwrap_or(0),
+        })
+    }
+}
+
+/// Flags written before metadata 1.1 used a different layout, found in `old_field`.
+fn get_flags(message: &Message, field: u32, old_field: u32, default: i32) -> i32 {
+    match (message.get_int(field), message.get_int(old_field)) {
+        (Some(flags), _) => flags,
+        (None, Some(old)) => (old & 0x3f) + ((old >> 8) << 6),
+        (None, None) => default,
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Varint(u64),
+    Fixed64,
+    Bytes(Vec<u8>),
+    Fixed32,
+}
+
+/// A protocol buffer message decoded without a schema, keeping fields in wire order.
+#[derive(Clone, Debug, Default)]
+struct Message {
+    fields: Vec<(u32, Value)>,
+}
+
+impl Message {
+    fn parse(bytes: &[u8]) -> Result<Message, Box<dyn Error>> {
+        let mut fields = vec![];
+        let mut position = 0;
+        while position < bytes.len() {
+            let key = read_varint(bytes, &mut position)?;
+            let number = (key >> 3) as u32;
+            let value = match key & 0x7 {
+                0 => Value::Varint(read_varint(bytes, &mut position)?),
+                1 => {
+                    position += 8;
+                    Value::Fixed64
+                }
+                2 => {
+                    let length = read_varint(bytes, &mut position)? as usize;
+                    let value = bytes
+                        .get(position..position + length)
+                        .ok_or_else(|| invalid("length delimited field is truncated"))?;
+                    position += length;
+                    Value::Bytes(value.to_vec())
+                }
+                5 => {
+                    position += 4;
+                    Value::Fixed32
+                }
+                wire_type => return Err(invalid(&format!("unsupported wire type {wire_type}"))),
+            };
+            if position > bytes.len() {
+                return Err(invalid("fixed width field is truncated"));
+            }
+            fields.push((number, value));
+        }
+        Ok(Message { fields })
+    }
+
+    /// The last value of a scalar field, protobuf int32 values being sign extended varints.
+    fn get_int(&self, number: u32) -> Option<i32> {
+        self.fields.iter().rev().find_map(|(n, value)| match value {
+            Value::Varint(v) if *n == number => Some(*v as i32),
+            _ => None,
+        })
+    }
+
+    fn require_int(&self, number: u32) -> Result<i32, Box<dyn Error>> {
+        self.get_int(number)
+            .ok_or_else(|| invalid(&format!("required field {number} is missing")))
+    }
+
+    /// All values of a repeated int32 field, whether or not it was written packed.
+    fn get_ints(&self, number: u32) -> Vec<i32> {
+        let mut values = vec![];
+        for (n, value) in &self.fields {
+            match value {
+                _ if *n != number => {}
+                Value::Varint(v) => values.push(*v as i32),
+                Value::Bytes(packed) => {
+                    let mut position = 0;
+                    while let Ok(v) = read_varint(packed, &mut position) {
+                        values.push(v as i32);
+                    }
+                }
+                _ => {}
+            }
+        }
+        values
+    }
+
+    fn get_bytes(&self, number: u32) -> Option<&[u8]> {
+        self.fields.iter().rev().find_map(|(n, value)| match value {
+            Value::Bytes(bytes) if *n == number => Some(bytes.as_slice()),
+            _ => None,
+        })
+    }
+
+    fn get_message(&self, number: u32) -> Result<Option<Message>, Box<dyn Error>> {
+        self.get_bytes(number).map(Message::parse).transpose()
+    }
+
+    fn get_messages(&self, number: u32) -> Result<Vec<Message>, Box<dyn Error>> {
+        self.fields
+            .iter()
+            .filter_map(|(n, value)| match value {
+                Value::Bytes(bytes) if *n == number => Some(Message::parse(bytes)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u64, Box<dyn Error>> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes
+            .get(*position)
+            .ok_or_else(|| invalid("varint is truncated"))?;
+        *position += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint is longer than 10 bytes"))
+}
+
+fn invalid(reason: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(
+        LoadingCause::InvalidKotlinMetadata(reason.into()),
+        "@kotlin.Metadata could not be decoded",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn int_field(number: u32, value: i32) -> Vec<u8> {
+        let mut out = vec![];
+        varint((number as u64) << 3, &mut out);
+        varint(value as i64 as u64, &mut out);
+        out
+    }
+
+    fn message_field(number: u32, message: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        varint(((number as u64) << 3) | 2, &mut out);
+        varint(message.len() as u64, &mut out);
+        out.extend_from_slice(message);
+        out
+    }
+
+    fn class_type(name: i32) -> Vec<u8> {
+        int_field(6, name)
+    }
+
+    /// Packs bytes the way kotlinc 1.4 and later does, behind the `\u0000` marker.
+    fn encode(bytes: &[u8]) -> Vec<Vec<u16>> {
+        let mut units = vec![0];
+        units.extend(bytes.iter().map(|byte| *byte as u16));
+        vec![units]
+    }
+
+    /**
+     * data class Point(val x: Int, var label: String?) {
+     *     operator fun plus(other: Point): Point
+     * }
+     */
+    fn point_metadata() -> Result<KotlinMetadata, Box<dyn Error>> {
+        let d2: Vec<String> = [
+            "com/example/Point",
+            "x",
+            "",
+            "label",
+            "kotlin/String",
+            "plus",
+            "other",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        // Index 2 is kotlin/Int, taken from the predefined strings.
+        let record = [int_field(1, 2), int_field(2, 8)].concat();
+        let table = message_field(1, &[int_field(1, 2), Vec::new()].concat());
+        let table = [table, message_field(1, &record)].concat();
+
+        let x = [int_field(2, 1), message_field(3, &class_type(2))].concat();
+        let label = [
+            int_field(11, 518 | (1 << 8)),
+            int_field(2, 3),
+            message_field(3, &[class_type(4), int_field(3, 1)].concat()),
+        ]
+        .concat();
+        let other = [int_field(2, 6), message_field(3, &class_type(0))].concat();
+        let plus = [
+            int_field(9, 6 | (1 << 8)),
+            int_field(2, 5),
+            message_field(6, &other),
+            message_field(3, &class_type(0)),
+        ]
+        .concat();
+        let constructor = [message_field(2, &other)].concat();
+        let class = [
+            int_field(1, 6 | (1 << 10)),
+            int_field(3, 0),
+            message_field(8, &constructor),
+            message_field(9, &plus),
+            message_field(10, &x),
+            message_field(10, &label),
+        ]
+        .concat();
+
+        let mut d1 = vec![];
+        varint(table.len() as u64, &mut d1);
+        d1.extend(table);
+        d1.extend(class);
+        KotlinMetadata::new(1, vec![1, 7, 1], &encode(&d1), &d2, None, None, 0)
+    }
+
+    #[test]
+    fn test_decode_class() -> Result<(), Box<dyn Error>> {
+        let metadata = point_metadata()?;
+        assert_eq!(metadata.kind, MetadataKind::Class);
+        let class = match metadata.declarations {
+            KotlinDeclarations::Class(class) => class,
+            other => panic!("expected a class, found {other:?}"),
+        };
+        assert_eq!(class.name, "com/example/Point");
+        assert!(class.is_data());
+        assert_eq!(class.kind, ClassKind::Class);
+        assert_eq!(class.modality, Modality::Final);
+        assert_eq!(class.visibility, Visibility::Public);
+        assert_eq!(class.constructors[0].value_parameters[0].name, "other");
+
+        let plus = &class.functions[0];
+        assert_eq!(plus.name, "plus");
+        assert!(plus.is_operator());
+        assert_eq!(plus.return_type.to_string(), "com/example/Point");
+
+        let x = &class.properties[0];
+        assert_eq!((x.name.as_str(), x.is_var()), ("x", false));
+        assert_eq!(x.return_type.to_string(), "kotlin/Int");
+        let label = &class.properties[1];
+        assert!(label.is_var() && label.has_getter());
+        assert_eq!(label.return_type.to_string(), "kotlin/String?");
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_bit_encoding() {
+        // Every 7 bit group is stored plus one, so 0x01 is a group of zero bits.
+        assert_eq!(decode_bytes(&[vec![0x01; 8]]), vec![0; 7]);
+        assert_eq!(decode_bytes(&[vec![0x80; 8]]), vec![0xFF; 7]);
+    }
+}
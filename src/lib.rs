@@ -0,0 +1,79 @@
+/*!
+ * Reading, writing and analysing Java class files, as the `jvm_rs` command does.
+ */
+
+pub mod access_flags;
+/// [Jasmin](https://jasmin.sourceforge.net/guide.html)
+pub mod assembler;
+/// [Attributes](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A1244%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C564%2Cnull%5D)
+pub mod attributes;
+/// [Call Graph Construction](https://dl.acm.org/doi/10.1145/236337.236371)
+pub mod callgraph;
+/// [Control Flow Analysis](https://www.cs.rice.edu/~keith/EMBED/dom.pdf)
+pub mod cfg;
+/// [Class File Format](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html)
+pub mod class_builder;
+/// [Class File Format](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A376%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C590%2Cnull%5D)
+pub mod class_file;
+/// [Class Path](https://docs.oracle.com/en/java/javase/17/docs/specs/man/java.html#standard-options-for-java)
+pub mod class_path;
+/// [Class File Format](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html)
+pub mod class_writer;
+/// [Binary Compatibility](https://docs.oracle.com/javase/specs/jls/se17/html/jls-13.html)
+pub mod compat;
+/// [The Constant Pool](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4)
+pub mod constant_pool;
+/// [Constants](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A2201%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C256%2Cnull%5D)
+pub mod constants;
+/// [Java Language Specification](https://docs.oracle.com/javase/specs/jls/se17/html/index.html)
+pub mod decompiler;
+/// [jdeps](https://docs.oracle.com/en/java/javase/17/docs/specs/man/jdeps.html)
+pub mod deps;
+/// [Descriptors](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#page=98)
+pub mod descriptors;
+pub mod errors;
+/// [Double.toString](https://docs.oracle.com/en/java/javase/17/docs/api/java.base/java/lang/Double.html#toString(double))
+pub mod floating_decimal;
+/// [Verification by Type Checking](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1)
+pub mod frames;
+/// [Graphviz](https://graphviz.org/doc/info/lang.html)
+pub mod graphs;
+/// [Subtyping](https://docs.oracle.com/javase/specs/jls/se17/html/jls-4.html#jls-4.10)
+pub mod hierarchy;
+/// [Instructions](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5)
+pub mod instructions;
+/// [JAR File Specification](https://docs.oracle.com/en/java/javase/17/docs/specs/jar/jar.html)
+pub mod jar;
+/// [javap](https://docs.oracle.com/en/java/javase/17/docs/specs/man/javap.html)
+pub mod javap;
+/// [jimage](https://openjdk.org/jeps/220)
+pub mod jimage;
+/// [Kotlin Metadata](https://kotlinlang.org/api/latest/jvm/stdlib/kotlin/-metadata/)
+pub mod kotlin;
+/// [Manifest](https://docs.oracle.com/en/java/javase/17/docs/specs/jar/jar.html#jar-manifest)
+pub mod manifest;
+/// [ProGuard Mapping](https://www.guardsquare.com/manual/tools/retrace)
+pub mod mappings;
+/// [Record Classes](https://docs.oracle.com/javase/specs/jls/se17/html/jls-8.html#jls-8.10)
+pub mod records;
+/// [ASM Remapper](https://asm.ow2.io/javadoc/org/objectweb/asm/commons/Remapper.html)
+pub mod remapper;
+/// [Scala Signatures](https://github.com/scala/scala/blob/2.13.x/src/reflect/scala/reflect/internal/pickling/PickleFormat.scala)
+pub mod scala;
+/// [Sealed Classes](https://docs.oracle.com/javase/specs/jls/se17/html/jls-8.html#jls-8.1.1.2)
+pub mod sealed;
+/// [Secure Hash Standard](https://csrc.nist.gov/pubs/fips/180-4/upd1/final)
+pub mod sha256;
+/// [Signatures](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.9.1)
+pub mod signatures;
+/// [Source Map](https://jcp.org/en/jsr/detail?id=45)
+pub mod smap;
+/// [Static Single Assignment Form](https://c9x.me/compile/bib/braun13cc.pdf)
+pub mod ssa;
+/// [Java Language Specification](https://docs.oracle.com/javase/specs/jls/se17/html/jls-8.html)
+pub mod stubs;
+/// [Symbolic References](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.1)
+pub mod usages;
+
+/// [JVM Spec](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf)
+pub struct VirtualMachine {}
@@ -1,51 +1,705 @@
-//FIXME: This isn't ideal
-#![feature(cursor_remaining)]
 use std::{
+    collections::BTreeSet,
     env::args,
-    fmt,
-    fs::File,
-    io::Read,
+    error::Error,
+    fs,
+    io::{self, stdout, Write},
     path::{Path, PathBuf},
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use crate::access_flags::MethodAccessFlags;
-
-mod access_flags;
-/// [Attributes](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A1244%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C564%2Cnull%5D)
-mod attributes;
-/// [Class File Format](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A376%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C590%2Cnull%5D)
-mod class_file;
-/// [Constants](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A2201%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C256%2Cnull%5D)
-mod constants;
-/// [Data Types](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#%5B%7B%22num%22%3A62%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C72%2C590%2Cnull%5D)
-mod data_types;
-mod errors;
-
-
-/// [JVM Spec](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf)
-struct VirtualMachine {}
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut args = args();
-    args.next().unwrap();
-    if args.len() != 1 {
-        panic!("You must provide the path to a java classfile");
-    }
-    let file_path = PathBuf::from(args.next().unwrap());
-    if let Some(ext) = file_path.extension() {
-        if ext != "class" {
-            panic!("File provided was not a java class file");
-        }
-        let mut class_file: File = File::open(file_path).expect("Failed to open file");
-        let mut contents = vec![00; class_file.metadata().unwrap().len() as usize];
-        class_file
-            .read_exact(&mut contents)
-            .expect("Failed to read bytes");
-        let class = class_file::ClassFile::from_bytes(&contents)?;
-        println!("{}", class.to_pretty_fmt());
+use jvm_rs::callgraph::{self, Analysis, CallGraph, MethodRef};
+use jvm_rs::class_file::ClassFile;
+use jvm_rs::class_path::{ClassPath, ClassPathEntry};
+use jvm_rs::compat::ApiDiff;
+use jvm_rs::decompiler::Decompiler;
+use jvm_rs::deps::{Dependencies, DependencyLevel, Origin};
+use jvm_rs::graphs::{self, Graph, GraphFormat};
+use jvm_rs::jar::Jar;
+use jvm_rs::javap::{AccessFilter, Javap, JavapOptions};
+use jvm_rs::sha256;
+use jvm_rs::stubs::StubGenerator;
+use jvm_rs::usages::{MemberSpec, Usages};
+
+const USAGE: &str = "Usage: javap <options> <classes>
+where possible options include:
+  -help  --help  -?                Print this help message
+  -v  -verbose                     Print additional information
+  -l                               Print line number and local variable tables
+  -public                          Show only public classes and members
+  -protected                       Show protected/public classes and members
+  -package                         Show package/protected/public classes
+                                   and members (default)
+  -p  -private                     Show all classes and members
+  -c                               Disassemble the code
+  -s                               Print internal type signatures
+  -constants                       Show final constants
+  --dot                            Print the control flow graph of each method, or the
+                                   class hierarchy of a jar, in Graphviz dot
+  --json                           Print the same graphs as JSON
+  --decompile                      Print Java-like source instead of the disassembly
+  --stubs <directory>              Write Java source stubs to compile against
+  --compare <old>                  Report the API changes since an older class or jar
+                                   that can break code built against it
+  --deps <class|package|module>    Report what a class or jar depends on, flagging what is
+                                   not found and JDK internal API, or draw it with --dot
+                                   or --json. Without --system, JDK classes and their
+                                   modules are only told by package
+  --call-graph <cha|rta>           Print the methods the entry points can reach and the
+                                   calls between them, with virtual calls resolved by
+                                   class hierarchy or rapid type analysis
+  --entry <method|@annotation>     Start the call graph from a method like a/B.run()V, or
+                                   from the methods with an annotation, instead of from
+                                   the main methods
+  --usages <member>                List the instructions that use a class, field or method
+                                   like java/lang/System.exit(I)V, with their source lines,
+                                   including method handles and strings that name it
+  --multi-release <version>        Specify the version to use in multi-release JAR files
+  -cp <path>  -classpath <path>  --class-path <path>
+                                   Specify where to find user class files
+  --system <jdk>                   Specify where to find system classes
+";
+
+/// What the command line asks for, with each option as given.
+#[derive(Default)]
+struct Arguments {
+    options: JavapOptions,
+    classes: Vec<String>,
+    release: Option<u16>,
+    user_class_path: Option<String>,
+    system: Option<PathBuf>,
+    graph: Option<GraphFormat>,
+    decompile: bool,
+    stubs: Option<PathBuf>,
+    compare: Option<String>,
+    deps: Option<DependencyLevel>,
+    call_graph: Option<Analysis>,
+    entries: Vec<String>,
+    usages: Option<MemberSpec>,
+}
+
+/// `None` when the help is asked for, else the arguments, or what is wrong with them.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Arguments>, String> {
+    let mut parsed = Arguments::default();
+    let options = &mut parsed.options;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-help" | "--help" | "-h" | "-?" => return Ok(None),
+            "-v" | "-verbose" => options.verbose = true,
+            "-l" => options.lines = true,
+            "-public" => options.access = AccessFilter::Public,
+            "-protected" => options.access = AccessFilter::Protected,
+            "-package" => options.access = AccessFilter::Package,
+            "-p" | "-private" => options.access = AccessFilter::Private,
+            "-c" => options.disassemble = true,
+            "-s" => options.descriptors = true,
+            "-constants" => options.constants = true,
+            "--dot" => parsed.graph = Some(GraphFormat::Dot),
+            "--json" => parsed.graph = Some(GraphFormat::Json),
+            "--decompile" => parsed.decompile = true,
+            "--stubs" => match args.next() {
+                Some(directory) => parsed.stubs = Some(PathBuf::from(directory)),
+                None => return Err("no directory given for option: --stubs".into()),
+            },
+            "--compare" => match args.next() {
+                Some(old) => parsed.compare = Some(old),
+                None => return Err("no class or jar given for option: --compare".into()),
+            },
+            "--deps" => match args.next().as_deref().and_then(DependencyLevel::parse) {
+                Some(level) => parsed.deps = Some(level),
+                None => return Err("invalid argument for option: --deps".into()),
+            },
+            "--call-graph" => match args.next().as_deref().and_then(Analysis::parse) {
+                Some(analysis) => parsed.call_graph = Some(analysis),
+                None => return Err("invalid argument for option: --call-graph".into()),
+            },
+            "--entry" => match args.next() {
+                Some(entry) => parsed.entries.push(entry),
+                None => return Err("no method given for option: --entry".into()),
+            },
+            "--usages" => match args.next().as_deref().and_then(MemberSpec::parse) {
+                Some(spec) => parsed.usages = Some(spec),
+                None => return Err("invalid argument for option: --usages".into()),
+            },
+            "--multi-release" => match args.next().and_then(|version| version.parse().ok()) {
+                Some(version) => parsed.release = Some(version),
+                None => return Err("invalid argument for option: --multi-release".into()),
+            },
+            "-cp" | "-classpath" | "--class-path" | "--system" => match args.next() {
+                Some(value) if arg == "--system" => parsed.system = Some(PathBuf::from(value)),
+                Some(value) => parsed.user_class_path = Some(value),
+                None => return Err(format!("no path given for option: {arg}")),
+            },
+            flag if flag.starts_with('-') => {
+                return Err(format!("invalid flag: {flag}\n{}", USAGE.trim_end()));
+            }
+            _ => parsed.classes.push(arg),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+fn main() -> ExitCode {
+    let arguments = match parse_args(args().skip(1)) {
+        Ok(Some(arguments)) => arguments,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("Error: {message}");
+            return ExitCode::from(2);
+        }
+    };
+    let Arguments {
+        options,
+        classes,
+        release,
+        user_class_path,
+        system,
+        graph,
+        decompile,
+        stubs,
+        compare,
+        deps,
+        call_graph,
+        entries,
+        usages,
+    } = arguments;
+    if classes.is_empty() {
+        eprint!("{USAGE}");
+        return ExitCode::from(2);
+    }
+
+    // Like javap, classes are found in the working directory unless a class path is given.
+    let mut class_path = ClassPath::parse(user_class_path.as_deref().unwrap_or("."));
+    if let Some(jdk) = system {
+        class_path.push(ClassPathEntry::Image(jdk.join("lib").join("modules")));
+    }
+    if let Some(version) = release {
+        class_path.set_release(version);
+    }
+
+    let whole_class_path = user_class_path.is_some();
+    let mut out = stdout().lock();
+    let mut failed = false;
+    for class in &classes {
+        let result = match (&stubs, &compare, deps, call_graph, &usages) {
+            (Some(directory), ..) => write_stubs(class, &class_path, release, directory),
+            (None, Some(old), ..) => print_api_changes(&mut out, old, class, &class_path, release),
+            (None, None, Some(level), ..) => {
+                print_dependencies(&mut out, class, &class_path, release, level, graph)
+            }
+            (None, None, None, Some(analysis), _) => {
+                read_program(class, &class_path, release, whole_class_path).and_then(|classes| {
+                    let (program, entries) = (&classes, &entries);
+                    print_call_graph(
+                        &mut out,
+                        class,
+                        program,
+                        &class_path,
+                        analysis,
+                        entries,
+                        graph,
+                    )
+                })
+            }
+            (None, None, None, None, Some(spec)) => {
+                read_program(class, &class_path, release, whole_class_path)
+                    .and_then(|classes| print_usages(&mut out, spec, &classes))
+            }
+            (None, None, None, None, None) if is_archive(class) => print_jar(
+                &mut out,
+                Path::new(class),
+                release,
+                &options,
+                graph,
+                decompile,
+            ),
+            (None, None, None, None, None) => {
+                print_class(&mut out, class, &class_path, &options, graph, decompile)
+            }
+        };
+        match result {
+            Ok(()) => {}
+            // Like other command line tools, stop quietly once the reader, such as head, is done.
+            Err(error) if is_broken_pipe(error.as_ref()) => return ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("Error: {class}: {error}");
+                failed = true;
+            }
+        }
+    }
+    if out
+        .flush()
+        .is_err_and(|error| error.kind() != io::ErrorKind::BrokenPipe)
+    {
+        failed = true;
+    }
+    if failed {
+        ExitCode::FAILURE
     } else {
-        panic!("File provided did not have an extension.");
+        ExitCode::SUCCESS
     }
+}
+
+/// Prints a class given either as a path or as a binary name to look up on the class path.
+fn print_class(
+    out: &mut impl Write,
+    class: &str,
+    class_path: &ClassPath,
+    options: &JavapOptions,
+    graph: Option<GraphFormat>,
+    decompile: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (location, file, bytes) = read_class(class, class_path)?;
+    let class_file = ClassFile::from_bytes(&bytes)?;
+    if let Some(format) = graph {
+        let graphs = graphs::control_flow_graphs(&class_file)?;
+        return print_graphs(out, format, &class_file.get_name(), &graphs);
+    }
+    if decompile {
+        write!(out, "{}", Decompiler::new(&class_file).decompile()?)?;
+        return Ok(());
+    }
+    let output = Javap::new(&class_file, options).disassemble()?;
+    if options.verbose {
+        print_classfile_header(out, &location, &file, &bytes)?;
+    }
+    write!(out, "{output}")?;
     Ok(())
+}
 
+/**
+ * Reads a class given either as a path or as a binary name to look up on the class path,
+ * returning where it was found, the file that holds it, and its bytes.
+ */
+fn read_class(
+    class: &str,
+    class_path: &ClassPath,
+) -> Result<(String, PathBuf, Vec<u8>), Box<dyn Error>> {
+    Ok(if Path::new(class).is_file() {
+        let path = fs::canonicalize(class)?;
+        (path.display().to_string(), path.clone(), fs::read(&path)?)
+    } else {
+        let resource = class_path
+            .find_class(&class.replace('.', "/"))?
+            .ok_or("class not found")?;
+        match &class_path.get_entries()[resource.entry] {
+            ClassPathEntry::Directory(_) => {
+                let path = fs::canonicalize(&resource.location)?;
+                (path.display().to_string(), path, resource.bytes)
+            }
+            entry => (resource.location, entry.get_path().into(), resource.bytes),
+        }
+    })
+}
+
+/**
+ * Reads a class, or every class in a jar, failing on the first entry that cannot be read.\
+ *  For the tools that need all the classes to give a correct answer.
+ */
+fn read_classes(
+    class: &str,
+    class_path: &ClassPath,
+    release: Option<u16>,
+) -> Result<Vec<ClassFile>, Box<dyn Error>> {
+    Ok(if is_archive(class) {
+        let jar = Jar::open(Path::new(class))?;
+        let classes = match release {
+            Some(version) => jar.get_versioned_classes(version)?,
+            None => jar.get_classes(),
+        };
+        classes
+            .into_iter()
+            .map(|(entry, class)| class.map_err(|error| format!("{}: {error}", entry.get_name())))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        let (_, _, bytes) = read_class(class, class_path)?;
+        vec![ClassFile::from_bytes(&bytes)?]
+    })
+}
+
+/// Writes the stubs of a class, or of every class in a jar, as source files under `directory`.
+fn write_stubs(
+    class: &str,
+    class_path: &ClassPath,
+    release: Option<u16>,
+    directory: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let classes = read_classes(class, class_path, release)?;
+    for (path, source) in StubGenerator::new(&classes).get_stubs()? {
+        let path = directory.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, source)?;
+    }
+    Ok(())
+}
+
+/**
+ * Prints the changes from the `old` version of a class or jar to the `new` one that can break
+ * code built against it, failing if any does more than change behavior.
+ */
+fn print_api_changes(
+    out: &mut impl Write,
+    old: &str,
+    new: &str,
+    class_path: &ClassPath,
+    release: Option<u16>,
+) -> Result<(), Box<dyn Error>> {
+    let old_classes = read_classes(old, class_path, release)?;
+    let new_classes = read_classes(new, class_path, release)?;
+    let diff = ApiDiff::new(&old_classes, &new_classes, class_path)?;
+    for change in &diff.changes {
+        writeln!(out, "{change}")?;
+    }
+    if !diff.is_compatible() {
+        return Err(format!("not compatible with {old}").into());
+    }
+    Ok(())
+}
+
+/**
+ * Prints what a class, or the classes of a jar, depend on at a `level`, as text or as a
+ * graph. The classes that are not found or are JDK internal API are counted in a warning.
+ */
+fn print_dependencies(
+    out: &mut impl Write,
+    class: &str,
+    class_path: &ClassPath,
+    release: Option<u16>,
+    level: DependencyLevel,
+    graph: Option<GraphFormat>,
+) -> Result<(), Box<dyn Error>> {
+    let classes = read_classes(class, class_path, release)?;
+    let dependencies = Dependencies::new(class, &classes, class_path)?;
+    match graph {
+        Some(format) => print_graphs(out, format, class, &[dependencies.to_graph(level)])?,
+        None => write!(out, "{}", dependencies.to_text(level))?,
+    }
+    let (mut missing, mut internal) = (BTreeSet::new(), BTreeSet::new());
+    for dependency in dependencies.get_dependencies(DependencyLevel::Class) {
+        match dependency.origin {
+            Origin::NotFound => missing.insert(dependency.to),
+            origin if origin.is_flagged() => internal.insert(dependency.to),
+            _ => false,
+        };
+    }
+    if !missing.is_empty() {
+        let count = missing.len();
+        eprintln!("Warning: {class} refers to {count} classes that are not found");
+    }
+    if !internal.is_empty() {
+        let count = internal.len();
+        eprintln!("Warning: {class} uses {count} classes of JDK internal API");
+    }
+    Ok(())
+}
+
+/**
+ * Reads a class or the classes of a jar, and with `whole_class_path` the classes of the jars
+ * and directories on the class path along with them.
+ */
+fn read_program(
+    class: &str,
+    class_path: &ClassPath,
+    release: Option<u16>,
+    whole_class_path: bool,
+) -> Result<Vec<ClassFile>, Box<dyn Error>> {
+    let mut classes = read_classes(class, class_path, release)?;
+    if whole_class_path {
+        let mut names: BTreeSet<String> = classes.iter().map(|class| class.get_name()).collect();
+        for (entry, path) in class_path.get_entries().iter().enumerate() {
+            if !matches!(path, ClassPathEntry::Image(_)) {
+                // Like the class loader, the first class of a name hides the others.
+                let entry_classes = class_path.load_classes(entry)?;
+                classes.extend(
+                    entry_classes
+                        .into_iter()
+                        .filter(|class| names.insert(class.get_name())),
+                );
+            }
+        }
+    }
+    Ok(classes)
+}
+
+/**
+ * Prints the call graph of `classes` from the `entries` given as methods or as `@` and an
+ * annotation, or else from their main methods.
+ */
+fn print_call_graph(
+    out: &mut impl Write,
+    name: &str,
+    classes: &[ClassFile],
+    class_path: &ClassPath,
+    analysis: Analysis,
+    entries: &[String],
+    graph: Option<GraphFormat>,
+) -> Result<(), Box<dyn Error>> {
+    let mut entry_points = vec![];
+    for entry in entries {
+        match entry.strip_prefix('@') {
+            Some(annotation) => {
+                entry_points.extend(callgraph::get_annotated_methods(classes, annotation))
+            }
+            None => {
+                let method =
+                    MethodRef::parse(entry).ok_or_else(|| format!("invalid method: {entry}"))?;
+                entry_points.push(method);
+            }
+        }
+    }
+    if entries.is_empty() {
+        entry_points = callgraph::get_main_methods(classes);
+    }
+    if entry_points.is_empty() {
+        return Err("no entry points found".into());
+    }
+    let call_graph = CallGraph::new(classes, class_path, analysis, &entry_points)?;
+    match graph {
+        Some(format) => print_graphs(out, format, name, &[call_graph.to_graph(name)]),
+        None => Ok(write!(out, "{}", call_graph.to_text())?),
+    }
+}
+
+/// Prints the uses of a class or member in `classes`, one per line.
+fn print_usages(
+    out: &mut impl Write,
+    spec: &MemberSpec,
+    classes: &[ClassFile],
+) -> Result<(), Box<dyn Error>> {
+    write!(out, "{}", Usages::new(spec, classes)?.to_text())?;
+    Ok(())
+}
+
+fn is_archive(path: &str) -> bool {
+    let path = Path::new(path);
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    path.is_file() && matches!(extension, Some("jar" | "zip" | "JAR" | "ZIP"))
+}
+
+/**
+ * Prints every class in a jar, reporting the entries that cannot be read as it goes.\
+ *  With a `release`, only the classes that version of Java would load are printed. With a
+ *  `graph` format, the hierarchy of the classes is printed instead, and with `decompile` their
+ *  Java-like source.
+ */
+fn print_jar(
+    out: &mut impl Write,
+    path: &Path,
+    release: Option<u16>,
+    options: &JavapOptions,
+    graph: Option<GraphFormat>,
+    decompile: bool,
+) -> Result<(), Box<dyn Error>> {
+    let jar = Jar::open(path)?;
+    let jar_path = fs::canonicalize(path)?;
+    let classes = match release {
+        Some(version) => jar.get_versioned_classes(version)?,
+        None => jar.get_classes(),
+    };
+    let mut failures = 0;
+    let mut hierarchy = vec![];
+    for (entry, class) in classes {
+        if graph.is_some() {
+            match class {
+                Ok(class_file) => hierarchy.push(class_file),
+                Err(error) => {
+                    eprintln!("Error: {}: {}: {error}", path.display(), entry.get_name());
+                    failures += 1;
+                }
+            }
+            continue;
+        }
+        let output = class.and_then(|class_file| match decompile {
+            true => Decompiler::new(&class_file).decompile(),
+            false => Javap::new(&class_file, options).disassemble(),
+        });
+        match output {
+            Ok(output) => {
+                if options.verbose && !decompile {
+                    let bytes = jar.read_entry(entry)?;
+                    let location = format!("jar:file:{}!/{}", jar_path.display(), entry.get_name());
+                    print_classfile_header(out, &location, &jar_path, &bytes)?;
+                }
+                write!(out, "{output}")?;
+            }
+            Err(error) => {
+                eprintln!("Error: {}: {}: {error}", path.display(), entry.get_name());
+                failures += 1;
+            }
+        }
+    }
+    if let Some(format) = graph {
+        let name = path.display().to_string();
+        print_graphs(
+            out,
+            format,
+            &name,
+            &[graphs::class_hierarchy(&name, &hierarchy)],
+        )?;
+    }
+    if failures > 0 {
+        return Err(format!("{failures} classes could not be read").into());
+    }
+    Ok(())
+}
+
+fn print_graphs(
+    out: &mut impl Write,
+    format: GraphFormat,
+    name: &str,
+    graphs: &[Graph],
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        GraphFormat::Dot => write!(out, "{}", graphs::to_dot(name, graphs))?,
+        GraphFormat::Json => write!(out, "{}", graphs::to_json(name, graphs))?,
+    }
+    Ok(())
+}
+
+/// Whether an error is a write to a pipe whose reader has gone.
+fn is_broken_pipe(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|error| error.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn print_classfile_header(
+    out: &mut impl Write,
+    location: &str,
+    path: &Path,
+    bytes: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    writeln!(out, "Classfile {location}")?;
+    let modified: SystemTime = fs::metadata(path)?.modified()?;
+    let seconds = modified.duration_since(UNIX_EPOCH)?.as_secs();
+    writeln!(
+        out,
+        "  Last modified {}; size {} bytes",
+        format_date(seconds),
+        bytes.len()
+    )?;
+    let checksum: String = sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    writeln!(out, "  SHA-256 checksum {checksum}")?;
+    Ok(())
+}
+
+/// Formats seconds since the epoch as a UTC date like `Jan 2, 2006`.
+fn format_date(seconds: u64) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    // Civil date from days since 1970-01-01, counting in 400 year eras starting in March.
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{} {day}, {year}", MONTHS[month as usize - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Arguments>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_javap_options() {
+        let arguments = parse(&["-c", "-private", "-s", "a/B", "-l", "C.class"])
+            .unwrap()
+            .unwrap();
+        let options = &arguments.options;
+        assert!(options.disassemble && options.descriptors && options.lines);
+        assert!(!options.verbose && !options.constants);
+        assert_eq!(options.access, AccessFilter::Private);
+        assert_eq!(arguments.classes, ["a/B", "C.class"]);
+        assert!(parse(&["-v", "-?"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_option_values() {
+        let arguments = parse(&[
+            "--deps",
+            "package",
+            "--multi-release",
+            "11",
+            "-cp",
+            "lib.jar",
+            "--system",
+            "/jdk",
+            "--entry",
+            "a/B.run()V",
+            "--entry",
+            "@Test",
+            "--dot",
+            "app.jar",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(arguments.deps, Some(DependencyLevel::Package));
+        assert_eq!(arguments.release, Some(11));
+        assert_eq!(arguments.user_class_path.as_deref(), Some("lib.jar"));
+        assert_eq!(arguments.system, Some(PathBuf::from("/jdk")));
+        assert_eq!(arguments.entries, ["a/B.run()V", "@Test"]);
+        assert_eq!(arguments.graph, Some(GraphFormat::Dot));
+        assert_eq!(arguments.classes, ["app.jar"]);
+    }
+
+    #[test]
+    fn test_classfile_header() {
+        let path = Path::new("test_verified_output/class_writer_test/Sample.class");
+        let bytes = fs::read(path).unwrap();
+        let mut out = vec![];
+        print_classfile_header(&mut out, "Sample.class", path, &bytes).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines[0], "Classfile Sample.class");
+        assert!(lines[1].ends_with("; size 3146 bytes"), "{}", lines[1]);
+        assert_eq!(
+            lines[2],
+            "  SHA-256 checksum e31e3b5f858a62f64341f2a678695ea4b6871c25fcf56a89f7573a9d1fe88b81"
+        );
+    }
+
+    #[test]
+    fn test_reject_bad_arguments() {
+        let error = |args: &[&str]| parse(args).err().unwrap();
+        assert_eq!(
+            error(&["--stubs"]),
+            "no directory given for option: --stubs"
+        );
+        assert_eq!(
+            error(&["--deps", "method"]),
+            "invalid argument for option: --deps"
+        );
+        assert_eq!(
+            error(&["--call-graph", "x"]),
+            "invalid argument for option: --call-graph"
+        );
+        assert_eq!(
+            error(&["--multi-release", "nine"]),
+            "invalid argument for option: --multi-release"
+        );
+        assert_eq!(
+            error(&["a/B", "-classpath"]),
+            "no path given for option: -classpath"
+        );
+        assert!(error(&["-x"]).starts_with("invalid flag: -x\nUsage: javap"));
+    }
 }
@@ -0,0 +1,197 @@
+use std::error::Error;
+
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+
+pub const MANIFEST_NAME: &str = "META-INF/MANIFEST.MF";
+
+/// The `name: value` pairs of a section, in the order they were written.
+pub type Attributes = Vec<(String, String)>;
+
+/**
+ * A package a module opens to every unnamed module, from an `Add-Opens` entry such as
+ * `java.base/java.lang`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddOpens {
+    pub module: String,
+    pub package: String,
+}
+
+/// [Manifest](https://docs.oracle.com/en/java/javase/17/docs/specs/jar/jar.html#jar-manifest)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub(crate) main_attributes: Attributes,
+    /// The per-entry sections, keyed by their `Name` attribute.
+    pub(crate) entries: Vec<(String, Attributes)>,
+}
+
+impl Manifest {
+    /**
+     * Parses the sections of a manifest. Each line is a header of the form `name: value`;
+     * lines starting with a single space continue the value of the previous header, and a
+     * blank line ends a section. Every section after the main section starts with `Name`.
+     */
+    pub fn parse(text: &str) -> Result<Manifest, Box<dyn Error>> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut sections: Vec<Attributes> = vec![Vec::new()];
+        // The line each section starts on, for errors about a missing Name.
+        let mut section_lines = vec![1];
+        let mut in_section = true;
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.is_empty() {
+                in_section = false;
+                continue;
+            }
+            if !in_section {
+                sections.push(Vec::new());
+                section_lines.push(line_number);
+                in_section = true;
+            }
+            let section = sections.last_mut().unwrap();
+            if let Some(continuation) = line.strip_prefix(' ') {
+                match section.last_mut() {
+                    Some((_, value)) => value.push_str(continuation),
+                    None => return Err(invalid(line_number, "continuation line without a header")),
+                }
+                continue;
+            }
+            let (name, value) = line
+                .split_once(": ")
+                .ok_or_else(|| invalid(line_number, "header is not of the form `name: value`"))?;
+            if !is_header_name(name) {
+                return Err(invalid(line_number, "header name is not alphanumeric"));
+            }
+            section.push((name.into(), value.into()));
+        }
+
+        let mut sections = sections.into_iter().zip(section_lines);
+        let main_attributes = sections
+            .next()
+            .map(|(section, _)| section)
+            .unwrap_or_default();
+        let mut entries = Vec::new();
+        for (section, line_number) in sections {
+            let name = get(&section, "Name")
+                .ok_or_else(|| invalid(line_number, "section does not start with a Name"))?;
+            entries.push((name.to_string(), section));
+        }
+        Ok(Manifest {
+            main_attributes,
+            entries,
+        })
+    }
+
+    /// A main attribute; header names are case-insensitive.
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        get(&self.main_attributes, name)
+    }
+
+    /// An attribute of the section for `entry`, such as `Sealed` for a package directory.
+    pub fn get_entry_attribute(&self, entry: &str, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(entry_name, _)| entry_name == entry)
+            .and_then(|(_, attributes)| get(attributes, name))
+    }
+
+    pub fn get_main_class(&self) -> Option<&str> {
+        self.get_attribute("Main-Class").map(str::trim)
+    }
+
+    /// The space separated URLs of `Class-Path`, relative to the jar.
+    pub fn get_class_path(&self) -> Vec<&str> {
+        self.get_attribute("Class-Path")
+            .map(|class_path| class_path.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_multi_release(&self) -> bool {
+        self.get_attribute("Multi-Release")
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+    }
+
+    pub fn get_automatic_module_name(&self) -> Option<&str> {
+        self.get_attribute("Automatic-Module-Name").map(str::trim)
+    }
+
+    /// The `module/package` pairs of `Add-Opens`; malformed pairs are skipped as the launcher does.
+    pub fn get_add_opens(&self) -> Vec<AddOpens> {
+        self.get_attribute("Add-Opens")
+            .map(|add_opens| {
+                add_opens
+                    .split_whitespace()
+                    .filter_map(|pair| pair.split_once('/'))
+                    .filter(|(module, package)| !module.is_empty() && !package.is_empty())
+                    .map(|(module, package)| AddOpens {
+                        module: module.into(),
+                        package: package.into(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn get<'a>(attributes: &'a Attributes, name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// `alphanum *headerchar`, where a headerchar is alphanumeric, `-` or `_`.
+fn is_header_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn invalid(line: usize, msg: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(LoadingCause::InvalidManifest(line), msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = Manifest::parse(
+            "Manifest-Version: 1.0\r\n\
+             Main-Class: com.example.Main\r\n\
+             Class-Path: lib/a.jar lib/b\r\n \
+             .jar\r\n\
+             multi-release: true\r\n\
+             Automatic-Module-Name: com.example\r\n\
+             Add-Opens: java.base/java.lang java.base/java.util bad\r\n\
+             \r\n\
+             Name: com/example/\r\n\
+             Sealed: true\r\n",
+        )
+        .unwrap();
+        assert_eq!(manifest.get_main_class(), Some("com.example.Main"));
+        assert_eq!(manifest.get_class_path(), ["lib/a.jar", "lib/b.jar"]);
+        assert!(manifest.is_multi_release());
+        assert_eq!(manifest.get_automatic_module_name(), Some("com.example"));
+        let add_opens = manifest.get_add_opens();
+        assert_eq!(add_opens.len(), 2);
+        assert_eq!(add_opens[1].module, "java.base");
+        assert_eq!(add_opens[1].package, "java.util");
+        assert_eq!(
+            manifest.get_entry_attribute("com/example/", "sealed"),
+            Some("true")
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_manifest() {
+        assert!(Manifest::parse(" continued\n").is_err());
+        assert!(Manifest::parse("Main-Class com.example.Main\n").is_err());
+        assert!(Manifest::parse("Manifest-Version: 1.0\n\nSealed: true\n").is_err());
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+
+/// A field or method by the internal name of its owner, its name and its descriptor.
+pub type MemberKey = (String, String, String);
+
+/**
+ * New names for classes, fields and methods, keyed by the names they have in the classes
+ * being renamed.\
+ *  Classes are named by internal names such as `com/example/Foo`, and the descriptors in
+ *  member keys are written with the old class names.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mappings {
+    pub(crate) classes: HashMap<String, String>,
+    pub(crate) fields: HashMap<MemberKey, String>,
+    pub(crate) methods: HashMap<MemberKey, String>,
+}
+
+impl Mappings {
+    pub fn new() -> Mappings {
+        Mappings::default()
+    }
+
+    pub fn add_class(&mut self, from: &str, to: &str) {
+        self.classes.insert(from.into(), to.into());
+    }
+
+    pub fn add_field(&mut self, owner: &str, name: &str, descriptor: &str, to: &str) {
+        let key = (owner.into(), name.into(), descriptor.into());
+        self.fields.insert(key, to.into());
+    }
+
+    pub fn add_method(&mut self, owner: &str, name: &str, descriptor: &str, to: &str) {
+        let key = (owner.into(), name.into(), descriptor.into());
+        self.methods.insert(key, to.into());
+    }
+
+    pub fn get_class(&self, name: &str) -> Option<&str> {
+        self.classes.get(name).map(String::as_str)
+    }
+
+    /// The new name of a field declared by `owner`; inherited fields are not looked up.
+    pub fn get_field(&self, owner: &str, name: &str, descriptor: &str) -> Option<&str> {
+        let key = (owner.into(), name.into(), descriptor.into());
+        self.fields.get(&key).map(String::as_str)
+    }
+
+    /// The new name of a method declared by `owner`; inherited methods are not looked up.
+    pub fn get_method(&self, owner: &str, name: &str, descriptor: &str) -> Option<&str> {
+        let key = (owner.into(), name.into(), descriptor.into());
+        self.methods.get(&key).map(String::as_str)
+    }
+
+    /// The new internal name of a class, or of an array class given by its descriptor.
+    pub fn map_class(&self, name: &str) -> String {
+        if name.starts_with('[') {
+            return self.map_descriptor(name);
+        }
+        self.get_class(name).unwrap_or(name).into()
+    }
+
+    /**
+     * Renames the classes in a field or method descriptor.\
+     *  Outside of class names a descriptor is made of single characters, so each `L` starts
+     *  a class name that ends at the next `;`.
+     */
+    pub fn map_descriptor(&self, descriptor: &str) -> String {
+        let mut mapped = String::with_capacity(descriptor.len());
+        let mut rest = descriptor;
+        while let Some(start) = rest.find('L') {
+            let Some(end) = rest[start..].find(';') else {
+                break;
+            };
+            mapped.push_str(&rest[..=start]);
+            mapped.push_str(&self.map_class(&rest[start + 1..start + end]));
+            mapped.push(';');
+            rest = &rest[start + end + 1..];
+        }
+        mapped.push_str(rest);
+        mapped
+    }
+
+    /**
+     * The mappings that undo these, e.g. to turn the original-to-obfuscated names of a
+     * ProGuard mapping file into a way to deobfuscate.
+     */
+    pub fn reverse(&self) -> Mappings {
+        let mut reversed = Mappings::new();
+        for (from, to) in &self.classes {
+            reversed.add_class(to, from);
+        }
+        for ((owner, name, descriptor), to) in &self.fields {
+            let descriptor = self.map_descriptor(descriptor);
+            reversed.add_field(&self.map_class(owner), to, &descriptor, name);
+        }
+        for ((owner, name, descriptor), to) in &self.methods {
+            let descriptor = self.map_descriptor(descriptor);
+            reversed.add_method(&self.map_class(owner), to, &descriptor, name);
+        }
+        reversed
+    }
+
+    /**
+     * Parses a ProGuard or R8 mapping file, which maps the original names to the obfuscated
+     * ones; `reverse` gives the mappings that deobfuscate.\
+     *  A class line such as `com.example.Foo -> a:` is followed by indented member lines:
+     *  `int count -> b` for a field and `1:4:void run(java.lang.String):12:15 -> c` for a
+     *  method, its line numbers optional.
+     */
+    pub fn parse_proguard(text: &str) -> Result<Mappings, Box<dyn Error>> {
+        let mut mappings = Mappings::new();
+        let mut owner: Option<String> = None;
+        // R8 writes the methods inlined into a method first, on lines with its range and
+        // new name; only the last line of such a run names the method itself.
+        let mut method: Option<(&str, &str, MemberKey)> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if !line.starts_with(char::is_whitespace) {
+                let (from, to) = trimmed
+                    .strip_suffix(':')
+                    .and_then(|class| class.split_once(" -> "))
+                    .ok_or_else(|| invalid(line_number, "expected `class -> new name:`"))?;
+                if let Some((_, to, key)) = method.take() {
+                    insert_method(&mut mappings, key, to);
+                }
+                let from = from.trim().replace('.', "/");
+                mappings.add_class(&from, &to.trim().replace('.', "/"));
+                owner = Some(from);
+                continue;
+            }
+            let owner = owner
+                .as_deref()
+                .ok_or_else(|| invalid(line_number, "member outside of a class"))?;
+            let (member, to) = trimmed
+                .split_once(" -> ")
+                .ok_or_else(|| invalid(line_number, "expected `member -> new name`"))?;
+            // The range of lines the member's code now has, when it has code.
+            let mut range = "";
+            let mut member = member;
+            if member.starts_with(|c: char| c.is_ascii_digit()) {
+                let mut parts = member.splitn(3, ':');
+                let (Some(start), Some(end), Some(rest)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(invalid(line_number, "expected `start:end:` line numbers"));
+                };
+                range = &member[..start.len() + end.len() + 1];
+                member = rest;
+            }
+            let (java_type, name) = member
+                .split_once(' ')
+                .ok_or_else(|| invalid(line_number, "expected a type and a name"))?;
+            let Some(open) = name.find('(') else {
+                let descriptor = java_descriptor(java_type);
+                mappings.add_field(owner, name, &descriptor, to);
+                continue;
+            };
+            let close = name
+                .find(')')
+                .ok_or_else(|| invalid(line_number, "unclosed parameter list"))?;
+            let mut descriptor = String::from("(");
+            for parameter in name[open + 1..close].split(',').filter(|p| !p.is_empty()) {
+                descriptor.push_str(&java_descriptor(parameter.trim()));
+            }
+            descriptor.push(')');
+            descriptor.push_str(&java_descriptor(java_type));
+            let key = (owner.to_string(), name[..open].to_string(), descriptor);
+            if let Some((last_range, last_to, last_key)) = method.take() {
+                if range.is_empty() || last_range != range || last_to != to {
+                    insert_method(&mut mappings, last_key, last_to);
+                }
+            }
+            method = Some((range, to, key));
+        }
+        if let Some((_, to, key)) = method {
+            insert_method(&mut mappings, key, to);
+        }
+        Ok(mappings)
+    }
+
+    /**
+     * Parses a Tiny v1 or v2 mapping file, mapping the names of namespace `from` to those of
+     * namespace `to`, e.g. `official` to `named`.\
+     *  Tiny files write descriptors with the class names of their first namespace, which are
+     *  translated to those of `from`. Parameters, local variables and comments are skipped.
+     */
+    pub fn parse_tiny(text: &str, from: &str, to: &str) -> Result<Mappings, Box<dyn Error>> {
+        let mut lines = text
+            .lines()
+            .map(|line| line.strip_suffix('\r').unwrap_or(line));
+        let header: Vec<&str> = lines.next().unwrap_or_default().split('\t').collect();
+        let (version, namespaces) = match header.as_slice() {
+            ["v1", namespaces @ ..] => (1, namespaces),
+            ["tiny", "2", _, namespaces @ ..] => (2, namespaces),
+            _ => return Err(invalid(1, "expected a Tiny v1 or v2 header")),
+        };
+        let column = |namespace: &str| {
+            namespaces
+                .iter()
+                .position(|name| *name == namespace)
+                .ok_or_else(|| invalid(1, &format!("no namespace {namespace}")))
+        };
+        let (from, to) = (column(from)?, column(to)?);
+
+        // Each class, field and method with its names in every namespace.
+        let mut classes: Vec<Vec<String>> = vec![];
+        let mut fields: Vec<(String, String, Vec<String>)> = vec![];
+        let mut methods: Vec<(String, String, Vec<String>)> = vec![];
+        let mut escaped = false;
+        let names = |columns: &[&str], line_number: usize, escaped: bool| {
+            if columns.len() < namespaces.len() || columns[0].is_empty() {
+                return Err(invalid(line_number, "expected a name for each namespace"));
+            }
+            // An empty name is the same as the one in the first namespace.
+            let columns = columns.iter().map(|name| match *name {
+                "" => columns[0],
+                name => name,
+            });
+            Ok(columns
+                .take(namespaces.len())
+                .map(|name| if escaped { unescape(name) } else { name.into() })
+                .collect::<Vec<String>>())
+        };
+        for (i, line) in lines.enumerate() {
+            let line_number = i + 2;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let depth = line.len() - line.trim_start_matches('\t').len();
+            let columns: Vec<&str> = line[depth..].split('\t').collect();
+            match (version, depth, columns.as_slice()) {
+                (1, 0, ["CLASS", names @ ..]) => {
+                    classes.push(tiny_v1_names(names, line_number)?);
+                }
+                (1, 0, [kind @ ("FIELD" | "METHOD"), owner, descriptor, rest @ ..]) => {
+                    let member = (
+                        owner.to_string(),
+                        descriptor.to_string(),
+                        tiny_v1_names(rest, line_number)?,
+                    );
+                    if *kind == "FIELD" {
+                        fields.push(member);
+                    } else {
+                        methods.push(member);
+                    }
+                }
+                (2, 0, ["c", rest @ ..]) => classes.push(names(rest, line_number, escaped)?),
+                (2, 1, _) if classes.is_empty() => {
+                    // The properties following the header.
+                    escaped |= columns[0] == "escaped-names";
+                }
+                (2, 1, [kind @ ("f" | "m"), descriptor, rest @ ..]) => {
+                    let owner = classes[classes.len() - 1][0].clone();
+                    let descriptor = if escaped {
+                        unescape(descriptor)
+                    } else {
+                        descriptor.to_string()
+                    };
+                    let member = (owner, descriptor, names(rest, line_number, escaped)?);
+                    if *kind == "f" {
+                        fields.push(member);
+                    } else {
+                        methods.push(member);
+                    }
+                }
+                // Comments, parameters and local variables.
+                (2, 1, ["c", ..]) | (2, 2.., _) => {}
+                _ => return Err(invalid(line_number, "unexpected Tiny entry")),
+            }
+        }
+
+        // The descriptors are translated from the first namespace to `from`.
+        let mut first = Mappings::new();
+        let mut mappings = Mappings::new();
+        for names in &classes {
+            first.add_class(&names[0], &names[from]);
+            if names[from] != names[to] {
+                mappings.add_class(&names[from], &names[to]);
+            }
+        }
+        for (owner, descriptor, names) in &fields {
+            if names[from] != names[to] {
+                let (owner, descriptor) =
+                    (first.map_class(owner), first.map_descriptor(descriptor));
+                mappings.add_field(&owner, &names[from], &descriptor, &names[to]);
+            }
+        }
+        for (owner, descriptor, names) in &methods {
+            if names[from] != names[to] {
+                let (owner, descriptor) =
+                    (first.map_class(owner), first.map_descriptor(descriptor));
+                mappings.add_method(&owner, &names[from], &descriptor, &names[to]);
+            }
+        }
+        Ok(mappings)
+    }
+}
+
+/// Tiny v1 names, which are never empty or escaped.
+fn tiny_v1_names(names: &[&str], line_number: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    if names.is_empty() || names.iter().any(|name| name.is_empty()) {
+        return Err(invalid(line_number, "expected a name for each namespace"));
+    }
+    Ok(names.iter().map(|name| name.to_string()).collect())
+}
+
+/// Methods whose name is qualified by a class were inlined from it, and are not renamed.
+fn insert_method(mappings: &mut Mappings, key: MemberKey, to: &str) {
+    if !key.1.contains('.') {
+        mappings.methods.insert(key, to.into());
+    }
+}
+
+/// The descriptor of a type written as in Java source, e.g. `java.lang.String[]`.
+fn java_descriptor(java_type: &str) -> String {
+    if let Some(component) = java_type.strip_suffix("[]") {
+        return format!("[{}", java_descriptor(component));
+    }
+    match java_type {
+        "void" => "V".into(),
+        "boolean" => "Z".into(),
+        "byte" => "B".into(),
+        "char" => "C".into(),
+        "short" => "S".into(),
+        "int" => "I".into(),
+        "long" => "J".into(),
+        "float" => "F".into(),
+        "double" => "D".into(),
+        name => format!("L{};", name.replace('.', "/")),
+    }
+}
+
+/// Undoes the escapes of a Tiny v2 file with the `escaped-names` property.
+fn unescape(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('0') => unescaped.push('\0'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn invalid(line: usize, msg: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(LoadingCause::InvalidMappings(line), msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proguard() -> Result<(), Box<dyn Error>> {
+        let mappings = Mappings::parse_proguard(
+            "# compiler: R8\n\
+             com.example.Foo -> a:\n\
+             \x20   java.lang.String name -> a\n\
+             \x20   int[] counts -> b\n\
+             \x20   1:1:void <init>() -> <init>\n\
+             \x20   2:3:void com.example.Bar.helper():10:11 -> c\n\
+             \x20   2:3:boolean check(com.example.Foo,int):20 -> c\n\
+             \x20   java.lang.String toString() -> toString\n\
+             com.example.Foo$Inner -> a$a:\n",
+        )?;
+        assert_eq!(mappings.get_class("com/example/Foo$Inner"), Some("a$a"));
+        assert_eq!(
+            mappings.get_field("com/example/Foo", "name", "Ljava/lang/String;"),
+            Some("a")
+        );
+        assert_eq!(
+            mappings.get_field("com/example/Foo", "counts", "[I"),
+            Some("b")
+        );
+        assert_eq!(
+            mappings.get_method("com/example/Foo", "check", "(Lcom/example/Foo;I)Z"),
+            Some("c")
+        );
+        assert_eq!(mappings.methods.len(), 3);
+
+        // Reversed, the keys are the obfuscated names and descriptors.
+        let reversed = mappings.reverse();
+        assert_eq!(reversed.get_class("a"), Some("com/example/Foo"));
+        assert_eq!(reversed.get_method("a", "c", "(La;I)Z"), Some("check"));
+        assert_eq!(
+            reversed.map_descriptor("([La$a;J)La;"),
+            "([Lcom/example/Foo$Inner;J)Lcom/example/Foo;"
+        );
+        assert!(Mappings::parse_proguard("    int count -> a\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tiny_v1() -> Result<(), Box<dyn Error>> {
+        let mappings = Mappings::parse_tiny(
+            "v1\tofficial\tnamed\n\
+             CLASS\ta\tcom/example/Foo\n\
+             FIELD\ta\tLa;\tb\tparent\n\
+             METHOD\ta\t(La;)V\tc\tattach\n",
+            "official",
+            "named",
+        )?;
+        assert_eq!(mappings.get_class("a"), Some("com/example/Foo"));
+        assert_eq!(mappings.get_field("a", "b", "La;"), Some("parent"));
+        assert_eq!(mappings.get_method("a", "c", "(La;)V"), Some("attach"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tiny_v2_between_any_namespaces() -> Result<(), Box<dyn Error>> {
+        let text = "tiny\t2\t0\tofficial\tintermediary\tnamed\n\
+                    \tescaped-names\n\
+                    c\ta\tclass_1\tcom/example/Foo\n\
+                    \tc\tA class comment.\n\
+                    \tf\tLa;\tb\tfield_2\tparent\n\
+                    \tm\t(La;)V\tc\tmethod_3\t\n\
+                    \t\tp\t1\t\t\tchild\n\
+                    c\tb\tclass_4\tcom/example/Tab\\tName\n";
+        let mappings = Mappings::parse_tiny(text, "intermediary", "named")?;
+        assert_eq!(mappings.get_class("class_1"), Some("com/example/Foo"));
+        assert_eq!(mappings.get_class("class_4"), Some("com/example/Tab\tName"));
+        assert_eq!(
+            mappings.get_field("class_1", "field_2", "Lclass_1;"),
+            Some("parent")
+        );
+        // The named name of method_3 is empty, so it is `c` as in the first namespace.
+        assert_eq!(
+            mappings.get_method("class_1", "method_3", "(Lclass_1;)V"),
+            Some("c")
+        );
+        assert!(Mappings::parse_tiny(text, "official", "mojang").is_err());
+        Ok(())
+    }
+}
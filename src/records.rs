@@ -0,0 +1,287 @@
+use crate::attributes::{Annotation, RecordComponentInfo};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::constants::get_raw_utf8;
+use crate::descriptors::FieldType;
+use crate::signatures::TypeSignature;
+
+/// A record component resolved against the constant pool of its record class.
+#[derive(Clone, Debug)]
+pub struct RecordComponent {
+    pub name: String,
+    /// The field descriptor of the component, e.g. `Ljava/lang/String;`.
+    pub descriptor: String,
+    /// The parsed form of [descriptor](RecordComponent::descriptor), if it is well formed.
+    pub field_type: Option<FieldType>,
+    /// The field signature from the component's `Signature` attribute, if it is generic, e.g.
+    /// `Ljava/util/List<Ljava/lang/String;>;`.
+    pub signature: Option<String>,
+    pub visible_annotations: Vec<Annotation>,
+    pub invisible_annotations: Vec<Annotation>,
+    /// Index into [ClassFile::methods] of the accessor method `name()` returning the component.
+    pub accessor: Option<usize>,
+    /// Index into [ClassFile::fields] of the private final field backing the component.
+    pub field: Option<usize>,
+}
+
+impl RecordComponent {
+    fn new(
+        component: &RecordComponentInfo,
+        class: &ClassFile,
+        constant_pool: &[ConstantPool],
+    ) -> RecordComponent {
+        let name = get_raw_utf8(constant_pool, component.name_index).unwrap_or_default();
+        let descriptor =
+            get_raw_utf8(constant_pool, component.descriptor_index).unwrap_or_default();
+        let mut signature = None;
+        let mut visible_annotations = vec![];
+        let mut invisible_annotations = vec![];
+        for attribute in &component.attributes {
+            match attribute {
+                AttributeInfo::Signature(s) => {
+                    signature = get_raw_utf8(constant_pool, s.signature_index)
+                }
+                AttributeInfo::RuntimeVisibleAnnotations(a) => {
+                    visible_annotations.extend(a.annotations.iter().cloned())
+                }
+                AttributeInfo::RuntimeInvisibleAnnotations(a) => {
+                    invisible_annotations.extend(a.annotations.iter().cloned())
+                }
+                _ => {}
+            }
+        }
+        let accessor_descriptor = format!("(){descriptor}");
+        let accessor = class.methods.iter().position(|method| {
+            get_raw_utf8(constant_pool, method.name_index).as_deref() == Some(&name)
+                && get_raw_utf8(constant_pool, method.descriptor_index).as_deref()
+                    == Some(&accessor_descriptor)
+        });
+        let field = class.fields.iter().position(|field| {
+            get_raw_utf8(constant_pool, field.name_index).as_deref() == Some(&name)
+                && get_raw_utf8(constant_pool, field.descriptor_index).as_deref()
+                    == Some(&descriptor)
+        });
+        RecordComponent {
+            field_type: FieldType::from_descriptor(&descriptor).ok(),
+            name,
+            descriptor,
+            signature,
+            visible_annotations,
+            invisible_annotations,
+            accessor,
+            field,
+        }
+    }
+
+    /**
+     * The component type as written in Java source, e.g. `java.util.List<java.lang.String>`,
+     * preferring the generic signature to the descriptor.
+     */
+    pub fn get_java_type(&self) -> String {
+        let signature = self
+            .signature
+            .as_deref()
+            .and_then(|signature| TypeSignature::parse(signature).ok());
+        match (signature, &self.field_type) {
+            (Some(signature), _) => signature.to_string(),
+            (None, Some(field_type)) => field_type.get_java_name(),
+            (None, None) => self.descriptor.clone(),
+        }
+    }
+}
+
+/// [Record](https://docs.oracle.com/javase/specs/jls/se17/html/jls-8.html#jls-8.10)
+/**
+ * The record components of a class together with the members javac derives from them.
+ * The canonical constructor is the `<init>` method whose parameter types are exactly the
+ * component types, in declaration order.
+ */
+#[derive(Clone, Debug)]
+pub struct RecordModel {
+    pub name: String,
+    pub components: Vec<RecordComponent>,
+    /// Index into [ClassFile::methods] of the canonical constructor.
+    pub canonical_constructor: Option<usize>,
+}
+
+impl RecordModel {
+    pub fn get_canonical_descriptor(&self) -> String {
+        let mut descriptor = String::from("(");
+        for component in &self.components {
+            descriptor.push_str(&component.descriptor);
+        }
+        descriptor.push_str(")V");
+        descriptor
+    }
+
+    /// The names of the components with no accessor method or canonical constructor to match.
+    pub fn get_unmatched(&self) -> Vec<String> {
+        let mut unmatched: Vec<String> = self
+            .components
+            .iter()
+            .filter(|component| component.accessor.is_none())
+            .map(|component| format!("{}()", component.name))
+            .collect();
+        if self.canonical_constructor.is_none() {
+            unmatched.push(format!("<init>{}", self.get_canonical_descriptor()));
+        }
+        unmatched
+    }
+}
+
+impl ClassFile {
+    /// Resolves the `Record` attribute of this class, `None` if it is not a record.
+    pub fn get_record(&self) -> Option<RecordModel> {
+        let record = self
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Record(record) => Some(record),
+                _ => None,
+            })?;
+        let components: Vec<RecordComponent> = record
+            .components
+            .iter()
+            .map(|component| RecordComponent::new(component, self, &self.constant_pool))
+            .collect();
+        let mut model = RecordModel {
+            name: self.get_name(),
+            components,
+            canonical_constructor: None,
+        };
+        let canonical = model.get_canonical_descriptor();
+        let pool = &self.constant_pool;
+        model.canonical_constructor = self.methods.iter().position(|method| {
+            get_raw_utf8(pool, method.name_index).as_deref() == Some("<init>")
+                && get_raw_utf8(pool, method.descriptor_index).as_deref() == Some(&canonical)
+        });
+        Some(model)
+    }
+
+    pub fn is_record(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| matches!(attribute, AttributeInfo::Record(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::access_flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+    use crate::attributes::Record;
+    use crate::class_builder::tests::{class, read_back};
+    use crate::class_builder::{FieldBuilder, MethodBuilder};
+
+    /**
+     * The record `test/Point` with `components` given by name, descriptor and signature, and
+     * with a backing field for each but only the `methods` given by name and descriptor.
+     */
+    fn record(components: &[(&str, &str, Option<&str>)], methods: &[(&str, &str)]) -> ClassFile {
+        let flags = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccFinal];
+        let mut class = class(&flags, "test/Point", "java/lang/Record", &[]);
+        for (name, descriptor, _) in components {
+            let flags = [FieldAccessFlags::AccPrivate, FieldAccessFlags::AccFinal];
+            class
+                .add_field(FieldBuilder::new(&flags, name, descriptor))
+                .unwrap();
+        }
+        for (name, descriptor) in methods {
+            let method = MethodBuilder::new(&[MethodAccessFlags::AccPublic], name, descriptor);
+            class.add_method(method).unwrap();
+        }
+        let pool = class.get_constant_pool();
+        let record_name = pool.add_utf8("Record").unwrap();
+        let signature_name = pool.add_utf8("Signature").unwrap();
+        let mut bytes = vec![];
+        for (name, descriptor, signature) in components {
+            bytes.extend(pool.add_utf8(name).unwrap().to_be_bytes());
+            bytes.extend(pool.add_utf8(descriptor).unwrap().to_be_bytes());
+            bytes.extend((signature.is_some() as u16).to_be_bytes());
+            if let Some(signature) = signature {
+                bytes.extend(signature_name.to_be_bytes());
+                bytes.extend(2u32.to_be_bytes());
+                bytes.extend(pool.add_utf8(signature).unwrap().to_be_bytes());
+            }
+        }
+        let record = Record::new(
+            record_name,
+            bytes.len() as u32 + 2,
+            components.len() as u16,
+            pool.get_constants(),
+            None,
+            &mut Cursor::new(&bytes[..]),
+        )
+        .unwrap();
+        class.add_attribute(AttributeInfo::Record(record));
+        read_back(class)
+    }
+
+    #[test]
+    fn test_get_record() {
+        let components = [
+            ("x", "I", None),
+            (
+                "names",
+                "Ljava/util/List;",
+                Some("Ljava/util/List<Ljava/lang/String;>;"),
+            ),
+        ];
+        let methods = [
+            ("<init>", "(ILjava/util/List;)V"),
+            ("x", "()I"),
+            ("names", "()Ljava/util/List;"),
+        ];
+        let class = record(&components, &methods);
+        assert!(class.is_record());
+        let record = class.get_record().unwrap();
+
+        assert_eq!(record.name, "test.Point");
+        let [x, names] = &record.components[..] else {
+            panic!("{:?}", record.components);
+        };
+        assert_eq!((x.name.as_str(), x.descriptor.as_str()), ("x", "I"));
+        assert_eq!(x.field_type, Some(FieldType::Int));
+        assert_eq!(x.get_java_type(), "int");
+        assert_eq!(names.descriptor, "Ljava/util/List;");
+        assert_eq!(
+            names.signature.as_deref(),
+            Some("Ljava/util/List<Ljava/lang/String;>;")
+        );
+        assert_eq!(names.get_java_type(), "java.util.List<java.lang.String>");
+        assert_eq!((x.field, names.field), (Some(0), Some(1)));
+        assert_eq!((x.accessor, names.accessor), (Some(1), Some(2)));
+        assert_eq!(record.canonical_constructor, Some(0));
+        assert_eq!(record.get_canonical_descriptor(), "(ILjava/util/List;)V");
+        assert!(record.get_unmatched().is_empty());
+    }
+
+    #[test]
+    fn test_get_unmatched() {
+        let components = [("x", "I", None), ("name", "Ljava/lang/String;", None)];
+        // The accessor of `name` returns the wrong type, and no constructor takes both.
+        let methods = [
+            ("<init>", "(I)V"),
+            ("x", "()I"),
+            ("name", "()Ljava/lang/Object;"),
+        ];
+        let record = record(&components, &methods).get_record().unwrap();
+
+        assert_eq!(record.components[0].accessor, Some(1));
+        assert_eq!(record.components[1].accessor, None);
+        assert_eq!(record.canonical_constructor, None);
+        assert_eq!(
+            record.get_unmatched(),
+            ["name()", "<init>(ILjava/lang/String;)V"]
+        );
+    }
+
+    #[test]
+    fn test_get_record_of_a_class() {
+        let flags = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper];
+        let class = read_back(class(&flags, "test/Plain", "java/lang/Object", &[]));
+        assert!(!class.is_record());
+        assert!(class.get_record().is_none());
+    }
+}
@@ -0,0 +1,903 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+use crate::access_flags::MethodAccessFlags;
+use crate::attributes::{Element, ElementPairs};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::constants::{NameAndType, Tags, Utf8};
+use crate::errors::class_writing::{WritingCause, WritingError};
+use crate::mappings::{Mappings, MemberKey};
+use crate::signatures::{
+    ClassSignature, ClassTypeSignature, MethodSignature, TypeArgument, TypeParameter, TypeSignature,
+};
+
+const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+
+/**
+ * Renames the classes, fields and methods of `classes` as `mappings` says, along with every
+ * reference to them: Class constants, member references, descriptors, Signature
+ * attributes, InnerClasses and EnclosingMethod, annotations and the bootstrap arguments of
+ * invokedynamic, whose lambdas are renamed with the interface method they implement.\
+ *  A reference to a member inherited from another of `classes` is renamed with the member
+ *  it resolves to, as is a method overriding one that is renamed. Either every class is
+ *  renamed or, on an error, none is.\
+ *  Renamed entries are added to the constant pool and the old ones are left unused, for
+ *  `constant_pool::compact` to drop.
+ */
+pub fn remap(classes: &mut [ClassFile], mappings: &Mappings) -> Result<(), Box<dyn Error>> {
+    let remapper = Remapper::new(classes, mappings)?;
+    let renamed = classes
+        .iter()
+        .map(|class| remapper.remap_class(class))
+        .collect::<Result<Vec<ClassFile>, Box<dyn Error>>>()?;
+    for (class, renamed) in classes.iter_mut().zip(renamed) {
+        *class = renamed;
+    }
+    Ok(())
+}
+
+/// Where a Signature attribute is found, which decides its grammar.
+#[derive(Clone, Copy)]
+enum Site {
+    Class,
+    Field,
+    Method,
+}
+
+struct Remapper<'a> {
+    mappings: &'a Mappings,
+    /// The direct superclass and superinterfaces of each class being renamed.
+    supertypes: HashMap<String, Vec<String>>,
+    /// The new names of annotation elements, which are named without their descriptor.
+    elements: HashMap<(&'a str, &'a str), &'a str>,
+}
+
+impl<'a> Remapper<'a> {
+    fn new(classes: &[ClassFile], mappings: &'a Mappings) -> Result<Remapper<'a>, Box<dyn Error>> {
+        let mut supertypes = HashMap::new();
+        for class in classes {
+            let pool = &class.constant_pool;
+            let mut names = vec![];
+            if class.super_class != 0 {
+                names.push(get_class(pool, class.super_class)?);
+            }
+            for interface in &class.interfaces {
+                names.push(get_class(pool, *interface)?);
+            }
+            supertypes.insert(get_class(pool, class.this_class)?, names);
+        }
+        let elements = mappings
+            .methods
+            .iter()
+            .filter(|((_, _, descriptor), _)| descriptor.starts_with("()"))
+            .map(|((owner, name, _), to)| ((owner.as_str(), name.as_str()), to.as_str()))
+            .collect();
+        Ok(Remapper {
+            mappings,
+            supertypes,
+            elements,
+        })
+    }
+
+    /// The new name of a member, declared by `owner` or inherited from one of its supertypes.
+    fn map_member(
+        &self,
+        members: &'a HashMap<MemberKey, String>,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> String {
+        let mut pending = vec![owner.to_string()];
+        let mut seen = HashSet::new();
+        while let Some(owner) = pending.pop() {
+            if !seen.insert(owner.clone()) {
+                continue;
+            }
+            if let Some(supertypes) = self.supertypes.get(&owner) {
+                // The superclass is searched before the interfaces.
+                pending.extend(supertypes.iter().rev().cloned());
+            }
+            let key = (owner, name.to_string(), descriptor.to_string());
+            if let Some(to) = members.get(&key) {
+                return to.clone();
+            }
+        }
+        name.into()
+    }
+
+    fn map_field(&self, owner: &str, name: &str, descriptor: &str) -> String {
+        self.map_member(&self.mappings.fields, owner, name, descriptor)
+    }
+
+    fn map_method(&self, owner: &str, name: &str, descriptor: &str) -> String {
+        self.map_member(&self.mappings.methods, owner, name, descriptor)
+    }
+
+    fn remap_class(&self, class: &ClassFile) -> Result<ClassFile, Box<dyn Error>> {
+        let mut class = class.clone();
+        let this = get_class(&class.constant_pool, class.this_class)?;
+        let mut pool = Pool::new(&mut class);
+
+        // Members and attributes first, while the Class entries still hold the old names.
+        for field in &mut class.fields {
+            let name = pool.get_utf8(field.name_index)?;
+            let descriptor = pool.get_utf8(field.descriptor_index)?;
+            let new_name = self.mappings.get_field(&this, &name, &descriptor);
+            pool.set_utf8(&mut field.name_index, new_name.unwrap_or(&name))?;
+            pool.set_utf8(
+                &mut field.descriptor_index,
+                &self.mappings.map_descriptor(&descriptor),
+            )?;
+            self.remap_attributes(&mut field.attributes, &mut pool, &this, Site::Field)?;
+        }
+        for method in &mut class.methods {
+            let name = pool.get_utf8(method.name_index)?;
+            let descriptor = pool.get_utf8(method.descriptor_index)?;
+            // Only these methods can not override one declared by a supertype.
+            let new_name = if name.starts_with('<')
+                || method.access_flags.contains(&MethodAccessFlags::AccPrivate)
+                || method.access_flags.contains(&MethodAccessFlags::AccStatic)
+            {
+                let new_name = self.mappings.get_method(&this, &name, &descriptor);
+                new_name.unwrap_or(&name).to_string()
+            } else {
+                self.map_method(&this, &name, &descriptor)
+            };
+            pool.set_utf8(&mut method.name_index, &new_name)?;
+            pool.set_utf8(
+                &mut method.descriptor_index,
+                &self.mappings.map_descriptor(&descriptor),
+            )?;
+            self.remap_attributes(&mut method.attributes, &mut pool, &this, Site::Method)?;
+        }
+        self.remap_attributes(&mut class.attributes, &mut pool, &this, Site::Class)?;
+        self.remap_constants(&mut pool, &class.attributes)?;
+        pool.finish(&mut class)?;
+        Ok(class)
+    }
+
+    fn remap_attributes(
+        &self,
+        attributes: &mut [AttributeInfo],
+        pool: &mut Pool,
+        this: &str,
+        site: Site,
+    ) -> Result<(), Box<dyn Error>> {
+        for attribute in attributes {
+            match attribute {
+                AttributeInfo::Code(code) => {
+                    self.remap_attributes(&mut code.attributes, pool, this, site)?;
+                }
+                AttributeInfo::InnerClasses(inner_classes) => {
+                    for class in &mut inner_classes.classes {
+                        if class.inner_name_index == 0 {
+                            continue;
+                        }
+                        let inner = pool.get_class(class.inner_class_info_index)?;
+                        let mapped = self.mappings.map_class(&inner);
+                        if mapped == inner {
+                            continue;
+                        }
+                        let outer = match class.outer_class_info_index {
+                            0 => None,
+                            index => Some(self.mappings.map_class(&pool.get_class(index)?)),
+                        };
+                        let member_name = outer.and_then(|outer| {
+                            let name = mapped.strip_prefix(&outer)?.strip_prefix('$')?;
+                            Some(name.to_string())
+                        });
+                        let name = member_name.unwrap_or_else(|| local_name(&mapped));
+                        pool.set_utf8(&mut class.inner_name_index, &name)?;
+                    }
+                }
+                AttributeInfo::EnclosingMethod(enclosing) if enclosing.method_index != 0 => {
+                    let owner = pool.get_class(enclosing.class_index)?;
+                    let (name, descriptor) = pool.get_name_and_type(enclosing.method_index)?;
+                    enclosing.method_index = pool.add_name_and_type(
+                        &self.map_method(&owner, &name, &descriptor),
+                        &self.mappings.map_descriptor(&descriptor),
+                    )?;
+                }
+                AttributeInfo::Signature(signature) => {
+                    let old = pool.get_utf8(signature.signature_index)?;
+                    let new = self.map_signature(&old, site)?;
+                    pool.set_utf8(&mut signature.signature_index, &new)?;
+                }
+                AttributeInfo::LocalVariableTable(table) => {
+                    for local in &mut table.local_variable_table {
+                        let descriptor = pool.get_utf8(local.descriptor_index)?;
+                        let descriptor = self.mappings.map_descriptor(&descriptor);
+                        pool.set_utf8(&mut local.descriptor_index, &descriptor)?;
+                    }
+                }
+                AttributeInfo::LocalVariableTypeTable(table) => {
+                    for local in &mut table.local_variable_type_table {
+                        let signature = pool.get_utf8(local.signature_index)?;
+                        let signature = self.map_signature(&signature, Site::Field)?;
+                        pool.set_utf8(&mut local.signature_index, &signature)?;
+                    }
+                }
+                AttributeInfo::RuntimeVisibleAnnotations(annotations) => {
+                    for annotation in &mut annotations.annotations {
+                        let pairs = &mut annotation.element_value_pairs;
+                        self.remap_annotation(&mut annotation.type_index, pairs, pool)?;
+                    }
+                }
+                AttributeInfo::RuntimeInvisibleAnnotations(annotations) => {
+                    for annotation in &mut annotations.annotations {
+                        let pairs = &mut annotation.element_value_pairs;
+                        self.remap_annotation(&mut annotation.type_index, pairs, pool)?;
+                    }
+                }
+                AttributeInfo::RuntimeVisibleParameterAnnotations(parameters) => {
+                    for parameter in &mut parameters.parameter_annotations {
+                        for annotation in &mut parameter.annotations {
+                            let pairs = &mut annotation.element_value_pairs;
+                            self.remap_annotation(&mut annotation.type_index, pairs, pool)?;
+                        }
+                    }
+                }
+                AttributeInfo::RuntimeInvisibleParameterAnnotations(parameters) => {
+                    for parameter in &mut parameters.parameter_annotations {
+                        for annotation in &mut parameter.annotations {
+                            let pairs = &mut annotation.element_value_pairs;
+                            self.remap_annotation(&mut annotation.type_index, pairs, pool)?;
+                        }
+                    }
+                }
+                AttributeInfo::RuntimeVisibleTypeAnnotations(annotations) => {
+                    for annotation in &mut annotations.type_annotations {
+                        let pairs = &mut annotation.element_value_pairs;
+                        self.remap_annotation(&mut annotation.type_index, pairs, pool)?;
+                    }
+                }
+                AttributeInfo::RuntimeInvisibleTypeAnnotations(annotations) => {
+                    for annotation in &mut annotations.type_annotations {
+                        let pairs = &mut annotation.element_value_pairs;
+                        self.remap_annotation(&mut annotation.type_index, pairs, pool)?;
+                    }
+                }
+                AttributeInfo::AnnotationDefault(default) => {
+                    self.remap_element(&mut default.default_value, pool)?;
+                }
+                AttributeInfo::Record(record) => {
+                    for component in &mut record.components {
+                        let name = pool.get_utf8(component.name_index)?;
+                        let descriptor = pool.get_utf8(component.descriptor_index)?;
+                        let new_name = self.mappings.get_field(this, &name, &descriptor);
+                        pool.set_utf8(&mut component.name_index, new_name.unwrap_or(&name))?;
+                        pool.set_utf8(
+                            &mut component.descriptor_index,
+                            &self.mappings.map_descriptor(&descriptor),
+                        )?;
+                        let attributes = &mut component.attributes;
+                        self.remap_attributes(attributes, pool, this, Site::Field)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Renames an annotation's type, given by its descriptor, and the elements it names.
+    fn remap_annotation(
+        &self,
+        type_index: &mut u16,
+        pairs: &mut [ElementPairs],
+        pool: &mut Pool,
+    ) -> Result<(), Box<dyn Error>> {
+        let descriptor = pool.get_utf8(*type_index)?;
+        let owner = class_of(&descriptor);
+        for pair in pairs {
+            let name = pool.get_utf8(pair.element_name_index)?;
+            if let Some(new_name) = self.elements.get(&(owner, name.as_str())) {
+                pool.set_utf8(&mut pair.element_name_index, new_name)?;
+            }
+            self.remap_element(&mut pair.value, pool)?;
+        }
+        pool.set_utf8(type_index, &self.mappings.map_descriptor(&descriptor))
+    }
+
+    fn remap_element(&self, element: &mut Element, pool: &mut Pool) -> Result<(), Box<dyn Error>> {
+        match element {
+            Element::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => {
+                let descriptor = pool.get_utf8(*type_name_index)?;
+                let name = pool.get_utf8(*const_name_index)?;
+                let new_name = self
+                    .mappings
+                    .get_field(class_of(&descriptor), &name, &descriptor);
+                pool.set_utf8(const_name_index, new_name.unwrap_or(&name))?;
+                pool.set_utf8(type_name_index, &self.mappings.map_descriptor(&descriptor))?;
+            }
+            Element::ClassInfoIndex(index) => {
+                let descriptor = pool.get_utf8(*index)?;
+                pool.set_utf8(index, &self.mappings.map_descriptor(&descriptor))?;
+            }
+            Element::Annotation(annotation) => {
+                let pairs = &mut annotation.element_value_pairs;
+                self.remap_annotation(&mut annotation.type_index, pairs, pool)?;
+            }
+            Element::ArrayValue { values, .. } => {
+                for value in values {
+                    self.remap_element(value, pool)?;
+                }
+            }
+            Element::ConstValueIndex(..) | Element::Unknown(_) => {}
+        }
+        Ok(())
+    }
+
+    /**
+     * Renames the classes, member references, method types and call sites in the constant
+     * pool. The new names are all worked out before any entry changes, as a member
+     * reference is looked up by the old name of its class.
+     */
+    fn remap_constants(
+        &self,
+        pool: &mut Pool,
+        attributes: &[AttributeInfo],
+    ) -> Result<(), Box<dyn Error>> {
+        let bootstrap_methods = attributes.iter().find_map(|attribute| match attribute {
+            AttributeInfo::BootstrapMethods(bootstrap_methods) => Some(bootstrap_methods),
+            _ => None,
+        });
+        let mut classes = vec![];
+        let mut name_and_types = vec![];
+        let mut descriptors = vec![];
+        for index in 1..pool.count {
+            let (name, descriptor) = match pool.get(index) {
+                Some(ConstantPool::Class(class)) => {
+                    let name = pool.get_utf8(class.name_index)?;
+                    let mapped = self.mappings.map_class(&name);
+                    if mapped != name {
+                        classes.push((index, mapped));
+                    }
+                    continue;
+                }
+                Some(ConstantPool::MethodType(method_type)) => {
+                    let descriptor = pool.get_utf8(method_type.descriptor_index)?;
+                    let mapped = self.mappings.map_descriptor(&descriptor);
+                    if mapped != descriptor {
+                        descriptors.push((index, mapped));
+                    }
+                    continue;
+                }
+                Some(ConstantPool::Fieldref(field)) => {
+                    let owner = pool.get_class(field.class_index)?;
+                    let (name, descriptor) = pool.get_name_and_type(field.name_and_type_index)?;
+                    (self.map_field(&owner, &name, &descriptor), descriptor)
+                }
+                Some(ConstantPool::Methodref(method)) => {
+                    let owner = pool.get_class(method.class_index)?;
+                    let (name, descriptor) = pool.get_name_and_type(method.name_and_type_index)?;
+                    (self.map_method(&owner, &name, &descriptor), descriptor)
+                }
+                Some(ConstantPool::InterfaceMethodRef(method)) => {
+                    let owner = pool.get_class(method.class_index)?;
+                    let (name, descriptor) = pool.get_name_and_type(method.name_and_type_index)?;
+                    (self.map_method(&owner, &name, &descriptor), descriptor)
+                }
+                Some(ConstantPool::Dynamic(dynamic)) => {
+                    pool.get_name_and_type(dynamic.name_and_type_index)?
+                }
+                Some(ConstantPool::InvokeDynamic(dynamic)) => {
+                    let (name, descriptor) = pool.get_name_and_type(dynamic.name_and_type_index)?;
+                    let bootstrap_method = bootstrap_methods.and_then(|bootstrap_methods| {
+                        let index = dynamic.bootstrap_method_attr_index as usize;
+                        bootstrap_methods.bootstrap_methods.get(index)
+                    });
+                    let name = match bootstrap_method {
+                        Some(method) => self.map_lambda(pool, method, &name, &descriptor)?,
+                        None => name,
+                    };
+                    (name, descriptor)
+                }
+                _ => continue,
+            };
+            name_and_types.push((index, name, self.mappings.map_descriptor(&descriptor)));
+        }
+
+        for (index, name) in classes {
+            let name_index = pool.add_utf8(&name)?;
+            if let Some(ConstantPool::Class(class)) = pool.get_mut(index) {
+                class.name_index = name_index;
+            }
+        }
+        for (index, descriptor) in descriptors {
+            let descriptor_index = pool.add_utf8(&descriptor)?;
+            if let Some(ConstantPool::MethodType(method_type)) = pool.get_mut(index) {
+                method_type.descriptor_index = descriptor_index;
+            }
+        }
+        for (index, name, descriptor) in name_and_types {
+            // Entries may share a NameAndType, so a renamed one gets its own.
+            let name_and_type = pool.add_name_and_type(&name, &descriptor)?;
+            match pool.get_mut(index) {
+                Some(ConstantPool::Fieldref(field)) => field.name_and_type_index = name_and_type,
+                Some(ConstantPool::Methodref(method)) => method.name_and_type_index = name_and_type,
+                Some(ConstantPool::InterfaceMethodRef(method)) => {
+                    method.name_and_type_index = name_and_type
+                }
+                Some(ConstantPool::Dynamic(dynamic)) => dynamic.name_and_type_index = name_and_type,
+                Some(ConstantPool::InvokeDynamic(dynamic)) => {
+                    dynamic.name_and_type_index = name_and_type
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * The name of a call site bootstrapped by `LambdaMetafactory`, which is the name of the
+     * interface method the lambda implements. The call site returns the interface, and the
+     * first bootstrap argument is the method's erased type.
+     */
+    fn map_lambda(
+        &self,
+        pool: &Pool,
+        bootstrap_method: &crate::attributes::Methods,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let Some(ConstantPool::MethodHandle(handle)) =
+            pool.get(bootstrap_method.bootstrap_method_ref)
+        else {
+            return Ok(name.into());
+        };
+        let owner = match pool.get(handle.reference_index) {
+            Some(ConstantPool::Methodref(method)) => pool.get_class(method.class_index)?,
+            _ => return Ok(name.into()),
+        };
+        let interface = descriptor
+            .rsplit_once(')')
+            .map(|(_, result)| class_of(result));
+        let method_type = bootstrap_method
+            .bootstrap_arguments
+            .first()
+            .and_then(|index| pool.get(*index));
+        match (interface, method_type) {
+            (Some(interface), Some(ConstantPool::MethodType(method_type)))
+                if owner == LAMBDA_METAFACTORY =>
+            {
+                let erased = pool.get_utf8(method_type.descriptor_index)?;
+                Ok(self.map_method(interface, name, &erased))
+            }
+            _ => Ok(name.into()),
+        }
+    }
+
+    fn map_signature(&self, signature: &str, site: Site) -> Result<String, Box<dyn Error>> {
+        Ok(match site {
+            Site::Class => {
+                let mut signature = ClassSignature::parse(signature)?;
+                self.map_type_parameters(&mut signature.type_parameters);
+                self.map_class_type(&mut signature.superclass);
+                for interface in &mut signature.interfaces {
+                    self.map_class_type(interface);
+                }
+                signature.to_signature()
+            }
+            Site::Method => {
+                let mut signature = MethodSignature::parse(signature)?;
+                self.map_type_parameters(&mut signature.type_parameters);
+                let types = signature.parameters.iter_mut();
+                for java_type in types
+                    .chain(&mut signature.return_type)
+                    .chain(&mut signature.throws)
+                {
+                    self.map_type(java_type);
+                }
+                signature.to_signature()
+            }
+            Site::Field => {
+                let mut signature = TypeSignature::parse(signature)?;
+                self.map_type(&mut signature);
+                signature.to_signature()
+            }
+        })
+    }
+
+    fn map_type_parameters(&self, type_parameters: &mut [TypeParameter]) {
+        for parameter in type_parameters {
+            let bounds = parameter.class_bound.iter_mut();
+            for bound in bounds.chain(&mut parameter.interface_bounds) {
+                self.map_type(bound);
+            }
+        }
+    }
+
+    fn map_type(&self, java_type: &mut TypeSignature) {
+        match java_type {
+            TypeSignature::Class(class) => self.map_class_type(class),
+            TypeSignature::Array(component) => self.map_type(component),
+            TypeSignature::Base(_) | TypeSignature::TypeVariable(_) => {}
+        }
+    }
+
+    /**
+     * Renames the class of a signature. Each `.` suffix names a member class by its simple
+     * name, which is what follows the new name of its outer class and a `$`.
+     */
+    fn map_class_type(&self, class: &mut ClassTypeSignature) {
+        let mut old = class.class.name.clone();
+        let mut new = self.mappings.map_class(&old);
+        class.class.name = new.clone();
+        self.map_type_arguments(&mut class.class.type_arguments);
+        for inner in &mut class.inner {
+            old = format!("{old}${}", inner.name);
+            let mapped = self.mappings.map_class(&old);
+            inner.name = match mapped
+                .strip_prefix(&new)
+                .and_then(|name| name.strip_prefix('$'))
+            {
+                Some(name) => name.into(),
+                None => local_name(&mapped),
+            };
+            new = mapped;
+            self.map_type_arguments(&mut inner.type_arguments);
+        }
+    }
+
+    fn map_type_arguments(&self, arguments: &mut [TypeArgument]) {
+        for argument in arguments {
+            match argument {
+                TypeArgument::Exact(bound)
+                | TypeArgument::Extends(bound)
+                | TypeArgument::Super(bound) => self.map_type(bound),
+                TypeArgument::Any => {}
+            }
+        }
+    }
+}
+
+/**
+ * The constant pool of the class being renamed, taken out of it so that its members can
+ * be changed at the same time. New entries are kept apart and added when it is finished.
+ */
+struct Pool {
+    constants: Vec<ConstantPool>,
+    count: u16,
+    added: Vec<ConstantPool>,
+    utf8s: HashMap<String, u16>,
+    name_and_types: HashMap<(u16, u16), u16>,
+}
+
+impl Pool {
+    fn new(class: &mut ClassFile) -> Pool {
+        let constants = std::mem::take(&mut class.constant_pool);
+        let mut utf8s = HashMap::new();
+        let mut name_and_types = HashMap::new();
+        for index in 1..class.constant_pool_count {
+            match constants.get(index as usize) {
+                Some(ConstantPool::Utf8(utf8)) => {
+                    utf8s.entry(utf8.get_raw_string()).or_insert(index);
+                }
+                Some(ConstantPool::NameAndType(name_and_type)) => {
+                    let key = (name_and_type.name_index, name_and_type.descriptor_index);
+                    name_and_types.entry(key).or_insert(index);
+                }
+                _ => {}
+            }
+        }
+        Pool {
+            constants,
+            count: class.constant_pool_count,
+            added: vec![],
+            utf8s,
+            name_and_types,
+        }
+    }
+
+    fn get(&self, index: u16) -> Option<&ConstantPool> {
+        match index.checked_sub(self.count) {
+            Some(added) => self.added.get(added as usize),
+            None => self.constants.get(index as usize),
+        }
+    }
+
+    fn get_mut(&mut self, index: u16) -> Option<&mut ConstantPool> {
+        match index.checked_sub(self.count) {
+            Some(added) => self.added.get_mut(added as usize),
+            None => self.constants.get_mut(index as usize),
+        }
+    }
+
+    /// A Utf8 entry as stored, with the `/`s of internal names kept.
+    fn get_utf8(&self, index: u16) -> Result<String, Box<dyn Error>> {
+        match self.get(index) {
+            Some(ConstantPool::Utf8(utf8)) => Ok(utf8.get_raw_string()),
+            _ => Err(missing_constant(index, "Utf8")),
+        }
+    }
+
+    fn get_class(&self, index: u16) -> Result<String, Box<dyn Error>> {
+        match self.get(index) {
+            Some(ConstantPool::Class(class)) => self.get_utf8(class.name_index),
+            _ => Err(missing_constant(index, "Class")),
+        }
+    }
+
+    fn get_name_and_type(&self, index: u16) -> Result<(String, String), Box<dyn Error>> {
+        match self.get(index) {
+            Some(ConstantPool::NameAndType(name_and_type)) => Ok((
+                self.get_utf8(name_and_type.name_index)?,
+                self.get_utf8(name_and_type.descriptor_index)?,
+            )),
+            _ => Err(missing_constant(index, "NameAndType")),
+        }
+    }
+
+    fn add(&mut self, constant: ConstantPool) -> Result<u16, Box<dyn Error>> {
+        let index = self.count as usize + self.added.len();
+        if index >= u16::MAX as usize {
+            return Err(Box::new(WritingError::new(
+                WritingCause::TooLarge("constant_pool".into()),
+                "a constant pool holds at most 65534 entries",
+            )));
+        }
+        self.added.push(constant);
+        Ok(index as u16)
+    }
+
+    fn add_utf8(&mut self, value: &str) -> Result<u16, Box<dyn Error>> {
+        if let Some(index) = self.utf8s.get(value) {
+            return Ok(*index);
+        }
+        let index = self.add(ConstantPool::Utf8(Utf8::from(value)))?;
+        self.utf8s.insert(value.into(), index);
+        Ok(index)
+    }
+
+    /// Points `index` at a Utf8 entry holding `value`, unless it already holds it.
+    fn set_utf8(&mut self, index: &mut u16, value: &str) -> Result<(), Box<dyn Error>> {
+        if self.get_utf8(*index)? != value {
+            *index = self.add_utf8(value)?;
+        }
+        Ok(())
+    }
+
+    fn add_name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, Box<dyn Error>> {
+        let key = (self.add_utf8(name)?, self.add_utf8(descriptor)?);
+        if let Some(index) = self.name_and_types.get(&key) {
+            return Ok(*index);
+        }
+        let index = self.add(ConstantPool::NameAndType(NameAndType::new(
+            Tags::NameAndType,
+            key.0,
+            key.1,
+        )))?;
+        self.name_and_types.insert(key, index);
+        Ok(index)
+    }
+
+    fn finish(self, class: &mut ClassFile) -> Result<(), Box<dyn Error>> {
+        class.constant_pool = self.constants;
+        for constant in self.added {
+            class.add_constant(constant)?;
+        }
+        Ok(())
+    }
+}
+
+fn get_class(constant_pool: &[ConstantPool], index: u16) -> Result<String, Box<dyn Error>> {
+    match constant_pool.get(index as usize) {
+        Some(ConstantPool::Class(class)) => match constant_pool.get(class.name_index as usize) {
+            Some(ConstantPool::Utf8(utf8)) => Ok(utf8.get_raw_string()),
+            _ => Err(missing_constant(class.name_index, "Utf8")),
+        },
+        _ => Err(missing_constant(index, "Class")),
+    }
+}
+
+/// The class named by an object type descriptor such as `Ljava/lang/String;`.
+fn class_of(descriptor: &str) -> &str {
+    descriptor
+        .strip_prefix('L')
+        .and_then(|name| name.strip_suffix(';'))
+        .unwrap_or(descriptor)
+}
+
+/// The simple name of a class without an enclosing class, e.g. `Local` for `Outer$1Local`.
+fn local_name(name: &str) -> String {
+    let name = name.rsplit(['/', '$']).next().unwrap_or(name);
+    let simple = name.trim_start_matches(|c: char| c.is_ascii_digit());
+    if simple.is_empty() { name } else { simple }.into()
+}
+
+fn missing_constant(index: u16, kind: &str) -> Box<dyn Error> {
+    Box::new(WritingError::new(
+        WritingCause::MissingConstant(index.to_string()),
+        &format!("expected a {kind} entry"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access_flags::{ClassAccessFlags, FieldAccessFlags};
+    use crate::class_builder::tests::class;
+    use crate::class_builder::{CodeBuilder, Constant, FieldBuilder, Handle, Insn, MethodBuilder};
+    use crate::constants::{self, get_raw_utf8};
+    use crate::instructions::*;
+
+    const OBJECT: &str = "java/lang/Object";
+
+    fn mappings() -> Mappings {
+        let mut mappings = Mappings::new();
+        mappings.add_class("a", "pkg/Base");
+        mappings.add_class("d", "pkg/Derived");
+        mappings.add_class("f", "pkg/Listener");
+        mappings.add_field("a", "b", "La;", "parent");
+        mappings.add_method("a", "c", "(La;)V", "attach");
+        mappings.add_method("f", "g", "(La;)V", "onEvent");
+        mappings
+    }
+
+    /// `a` declares field `b` and method `c`, which `d`, its subclass, uses.
+    fn classes() -> Result<Vec<ClassFile>, Box<dyn Error>> {
+        let mut base = class(&[ClassAccessFlags::AccPublic], "a", OBJECT, &[]);
+        let mut field = FieldBuilder::new(&[FieldAccessFlags::AccProtected], "b", "La;");
+        field.set_signature("Ljava/util/List<+La;>;");
+        base.add_field(field)?;
+        let mut code = CodeBuilder::new(0, 2);
+        code.push(Insn::Simple(RETURN));
+        let mut method = MethodBuilder::new(&[MethodAccessFlags::AccPublic], "c", "(La;)V");
+        method.set_code(code);
+        base.add_method(method)?;
+
+        let mut derived = class(&[ClassAccessFlags::AccPublic], "d", "a", &[]);
+        let lambda = Handle {
+            kind: 6,
+            owner: LAMBDA_METAFACTORY.into(),
+            name: "metafactory".into(),
+            descriptor: "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;".into(),
+            interface: false,
+        };
+        let implementation = Handle {
+            kind: 6,
+            owner: "d".into(),
+            name: "lambda$0".into(),
+            descriptor: "(La;)V".into(),
+            interface: false,
+        };
+        let bootstrap = derived.add_bootstrap_method(
+            &lambda,
+            &[
+                Constant::MethodType("(La;)V".into()),
+                Constant::MethodHandle(implementation),
+                Constant::MethodType("(La;)V".into()),
+            ],
+        )?;
+        let mut code = CodeBuilder::new(2, 1);
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Method {
+            opcode: INVOKEVIRTUAL,
+            owner: "d".into(),
+            name: "c".into(),
+            descriptor: "(La;)V".into(),
+            interface: false,
+        });
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Field {
+            opcode: GETFIELD,
+            owner: "d".into(),
+            name: "b".into(),
+            descriptor: "La;".into(),
+        });
+        code.push(Insn::Simple(POP));
+        code.push(Insn::InvokeDynamic {
+            bootstrap,
+            name: "g".into(),
+            descriptor: "()Lf;".into(),
+        });
+        code.push(Insn::Simple(POP));
+        code.push(Insn::Simple(RETURN));
+        let mut method = MethodBuilder::new(&[MethodAccessFlags::AccPublic], "run", "()V");
+        method.set_code(code);
+        derived.add_method(method)?;
+        // Overrides `c`, so it is renamed with it.
+        let mut code = CodeBuilder::new(0, 2);
+        code.push(Insn::Simple(RETURN));
+        let mut method = MethodBuilder::new(&[MethodAccessFlags::AccPublic], "c", "(La;)V");
+        method.set_code(code);
+        derived.add_method(method)?;
+        Ok(vec![base.build()?, derived.build()?])
+    }
+
+    fn utf8(class: &ClassFile, index: u16) -> String {
+        get_raw_utf8(&class.constant_pool, index).unwrap_or_default()
+    }
+
+    /// The owner, name and descriptor of the member or call site an instruction refers to.
+    fn reference(class: &ClassFile, index: u16) -> (String, String, String) {
+        let pool = &class.constant_pool;
+        let (owner, name_and_type) = match &pool[index as usize] {
+            ConstantPool::Fieldref(field) => (
+                get_class(pool, field.class_index).unwrap(),
+                field.name_and_type_index,
+            ),
+            ConstantPool::Methodref(method) => (
+                get_class(pool, method.class_index).unwrap(),
+                method.name_and_type_index,
+            ),
+            ConstantPool::InvokeDynamic(dynamic) => (String::new(), dynamic.name_and_type_index),
+            constant => panic!("unexpected {constant:?}"),
+        };
+        let ConstantPool::NameAndType(name_and_type) = &pool[name_and_type as usize] else {
+            panic!("expected a NameAndType");
+        };
+        (
+            owner,
+            utf8(class, name_and_type.name_index),
+            utf8(class, name_and_type.descriptor_index),
+        )
+    }
+
+    #[test]
+    fn test_rename_declarations_and_references() -> Result<(), Box<dyn Error>> {
+        let mut classes = classes()?;
+        remap(&mut classes, &mappings())?;
+        let base = ClassFile::from_bytes(&classes[0].to_bytes()?)?;
+        let derived = ClassFile::from_bytes(&classes[1].to_bytes()?)?;
+
+        assert_eq!(get_class(&base.constant_pool, base.this_class)?, "pkg/Base");
+        let field = &base.fields[0];
+        assert_eq!(utf8(&base, field.name_index), "parent");
+        assert_eq!(utf8(&base, field.descriptor_index), "Lpkg/Base;");
+        let AttributeInfo::Signature(signature) = &field.attributes[0] else {
+            panic!("expected the field's signature");
+        };
+        assert_eq!(
+            utf8(&base, signature.signature_index),
+            "Ljava/util/List<+Lpkg/Base;>;"
+        );
+        assert_eq!(utf8(&base, base.methods[0].name_index), "attach");
+
+        assert_eq!(
+            get_class(&derived.constant_pool, derived.super_class)?,
+            "pkg/Base"
+        );
+        assert_eq!(utf8(&derived, derived.methods[1].name_index), "attach");
+        let AttributeInfo::Code(code) = &derived.methods[0].attributes[0] else {
+            panic!("expected the code of run");
+        };
+        let references: Vec<_> = decode(&code.code)?
+            .iter()
+            .filter_map(|instruction| match instruction.operands {
+                Operands::Constant(index) | Operands::InvokeDynamic(index) => {
+                    Some(reference(&derived, index))
+                }
+                _ => None,
+            })
+            .collect();
+        let reference = |owner: &str, name: &str, descriptor: &str| {
+            (owner.to_string(), name.to_string(), descriptor.to_string())
+        };
+        assert_eq!(
+            references,
+            [
+                reference("pkg/Derived", "attach", "(Lpkg/Base;)V"),
+                reference("pkg/Derived", "parent", "Lpkg/Base;"),
+                reference("", "onEvent", "()Lpkg/Listener;"),
+            ]
+        );
+        assert_eq!(constants::get_utf8(&derived.constant_pool, 0), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_leave_classes_unchanged_on_error() {
+        let mut classes = classes().unwrap();
+        // The name of `run` points at its class instead of a Utf8 entry.
+        classes[1].methods[0].name_index = classes[1].this_class;
+        let before = classes[0].to_bytes().unwrap();
+        assert!(remap(&mut classes, &mappings()).is_err());
+        assert_eq!(classes[0].to_bytes().unwrap(), before);
+    }
+}
@@ -0,0 +1,1014 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::attributes::{Element, ScalaInlineInfo};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+
+// Symbol flags as scalac holds them in memory (scala.reflect.internal.Flags).
+const PROTECTED: u64 = 1 << 0;
+const OVERRIDE: u64 = 1 << 1;
+const PRIVATE: u64 = 1 << 2;
+const ABSTRACT: u64 = 1 << 3;
+const DEFERRED: u64 = 1 << 4;
+const FINAL: u64 = 1 << 5;
+const METHOD: u64 = 1 << 6;
+const INTERFACE: u64 = 1 << 7;
+const MODULE: u64 = 1 << 8;
+const IMPLICIT: u64 = 1 << 9;
+const SEALED: u64 = 1 << 10;
+const CASE: u64 = 1 << 11;
+const MUTABLE: u64 = 1 << 12;
+const PARAM: u64 = 1 << 13;
+const PACKAGE: u64 = 1 << 14;
+const COVARIANT: u64 = 1 << 16;
+const CONTRAVARIANT: u64 = 1 << 17;
+const SYNTHETIC: u64 = 1 << 21;
+const STABLE: u64 = 1 << 22;
+const TRAIT: u64 = 1 << 25;
+const ACCESSOR: u64 = 1 << 27;
+const LAZY: u64 = 1 << 31;
+
+/// The low 12 bits of pickled flags are stored in this order rather than the in-memory one.
+const PICKLED_FLAGS: [u64; 12] = [
+    METHOD, PRIVATE, FINAL, PROTECTED, CASE, DEFERRED, MODULE, OVERRIDE, INTERFACE, IMPLICIT,
+    SEALED, ABSTRACT,
+];
+
+fn pickled_to_raw_flags(pickled: u64) -> u64 {
+    let mut flags = pickled & !0xFFF;
+    for (bit, flag) in PICKLED_FLAGS.iter().enumerate() {
+        if pickled & (1 << bit) != 0 {
+            flags |= flag;
+        }
+    }
+    flags
+}
+
+/// The kind of symbol entry, named after the pickle tags `TYPEsym` to `VALsym`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScalaSymbolKind {
+    /// An abstract type member or a type parameter.
+    Type,
+    /// A type alias, `type T = ...`.
+    Alias,
+    /// A class, trait, or the module class of an object.
+    Class,
+    /// The term of an object.
+    Module,
+    /// A method, val, var or parameter.
+    Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScalaLiteral {
+    Unit,
+    Boolean(bool),
+    Byte(i8),
+    Short(i16),
+    Char(u16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    /// Index of the name entry holding the string.
+    String(usize),
+    Null,
+    /// Index of the type entry of `classOf[T]`.
+    Class(usize),
+    /// Index of the symbol entry of the enum constant.
+    Enum(usize),
+}
+
+/// One entry of the pickle's symbol table. Fields of type `usize` index other entries.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScalaEntry {
+    TermName(String),
+    TypeName(String),
+    NoSymbol,
+    Symbol(ScalaSymbol),
+    /// A symbol defined outside the pickle, found by name inside `owner`.
+    ExternalSymbol {
+        name: usize,
+        owner: Option<usize>,
+        is_module_class: bool,
+    },
+    NoType,
+    NoPrefix,
+    ThisType(usize),
+    SingleType {
+        prefix: usize,
+        symbol: usize,
+    },
+    ConstantType(usize),
+    TypeRef {
+        prefix: usize,
+        symbol: usize,
+        arguments: Vec<usize>,
+    },
+    TypeBounds {
+        lower: usize,
+        upper: usize,
+    },
+    RefinedType {
+        class: usize,
+        parents: Vec<usize>,
+    },
+    ClassInfoType {
+        class: usize,
+        parents: Vec<usize>,
+    },
+    MethodType {
+        result: usize,
+        parameters: Vec<usize>,
+    },
+    /// Also used for parameterless methods, with no type parameters.
+    PolyType {
+        result: usize,
+        type_parameters: Vec<usize>,
+    },
+    AnnotatedType {
+        underlying: usize,
+    },
+    ExistentialType {
+        underlying: usize,
+        quantified: Vec<usize>,
+    },
+    Literal(ScalaLiteral),
+    /// Annotations, trees and other entries that do not take part in signatures.
+    Other(u8),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScalaSymbol {
+    /// Index of this symbol's entry.
+    pub index: usize,
+    pub kind: ScalaSymbolKind,
+    /// Index of the name entry.
+    pub name: usize,
+    /// Index of the owner symbol entry.
+    pub owner: usize,
+    pub flags: u64,
+    /// Index of the symbol given in `private[X]`.
+    pub private_within: Option<usize>,
+    /// Index of the type entry.
+    pub info: usize,
+}
+
+impl ScalaSymbol {
+    pub fn is_trait(&self) -> bool {
+        self.kind == ScalaSymbolKind::Class && self.flags & TRAIT != 0
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.kind == ScalaSymbolKind::Module
+    }
+
+    pub fn is_module_class(&self) -> bool {
+        self.kind == ScalaSymbolKind::Class && self.flags & MODULE != 0
+    }
+
+    pub fn is_class(&self) -> bool {
+        self.kind == ScalaSymbolKind::Class && self.flags & (TRAIT | MODULE) == 0
+    }
+
+    pub fn is_method(&self) -> bool {
+        self.kind == ScalaSymbolKind::Value && self.flags & METHOD != 0
+    }
+
+    pub fn is_parameter(&self) -> bool {
+        self.flags & PARAM != 0
+    }
+
+    pub fn is_case(&self) -> bool {
+        self.flags & CASE != 0
+    }
+
+    pub fn is_implicit(&self) -> bool {
+        self.flags & IMPLICIT != 0
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.flags & SYNTHETIC != 0
+    }
+}
+
+/// [Scala Pickle Format](https://github.com/scala/scala/blob/2.13.x/src/reflect/scala/reflect/internal/pickling/PickleFormat.scala)
+/**
+ * The signature scalac 2 pickles into the class files of top level classes and objects.\
+ *  It is a table of entries that refer to each other by index: names, symbols with their
+ *  owners and flags, and the types Scala sees, which javac's view of the class file erases.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScalaSignature {
+    pub major_version: u64,
+    pub minor_version: u64,
+    pub entries: Vec<ScalaEntry>,
+}
+
+impl ScalaSignature {
+    pub fn parse(bytes: &[u8]) -> Result<ScalaSignature, Box<dyn Error>> {
+        let mut position = 0;
+        let major_version = read_nat(bytes, &mut position)?;
+        let minor_version = read_nat(bytes, &mut position)?;
+        let count = read_nat(bytes, &mut position)? as usize;
+        let mut raw: Vec<(u8, &[u8])> = Vec::with_capacity(count.min(bytes.len()));
+        for _ in 0..count {
+            let tag = *bytes
+                .get(position)
+                .ok_or_else(|| invalid("entry is truncated"))?;
+            position += 1;
+            let length = read_nat(bytes, &mut position)? as usize;
+            let data = bytes
+                .get(position..position + length)
+                .ok_or_else(|| invalid("entry is truncated"))?;
+            position += length;
+            raw.push((tag, data));
+        }
+        let is_symbol = |index: u64| {
+            raw.get(index as usize)
+                .is_some_and(|(tag, _)| (3..=10).contains(tag))
+        };
+        let mut entries = Vec::with_capacity(raw.len());
+        for (index, (tag, data)) in raw.iter().enumerate() {
+            let mut reader = Reader { data, position: 0 };
+            let entry = match tag {
+                1 => ScalaEntry::TermName(String::from_utf8_lossy(data).into_owned()),
+                2 => ScalaEntry::TypeName(String::from_utf8_lossy(data).into_owned()),
+                3 => ScalaEntry::NoSymbol,
+                4..=8 => {
+                    let name = reader.nat()?;
+                    let owner = reader.nat()?;
+                    let flags = pickled_to_raw_flags(reader.nat()? as u64);
+                    let mut info = reader.nat()?;
+                    let mut private_within = None;
+                    if is_symbol(info as u64) && !reader.at_end() {
+                        private_within = Some(info);
+                        info = reader.nat()?;
+                    }
+                    ScalaEntry::Symbol(ScalaSymbol {
+                        index,
+                        kind: match tag {
+                            4 => ScalaSymbolKind::Type,
+                            5 => ScalaSymbolKind::Alias,
+                            6 => ScalaSymbolKind::Class,
+                            7 => ScalaSymbolKind::Module,
+                            _ => ScalaSymbolKind::Value,
+                        },
+                        name,
+                        owner,
+                        flags,
+                        private_within,
+                        info,
+                    })
+                }
+                9 | 10 => {
+                    let name = reader.nat()?;
+                    ScalaEntry::ExternalSymbol {
+                        name,
+                        owner: if !reader.at_end() {
+                            Some(reader.nat()?)
+                        } else {
+                            None
+                        },
+                        is_module_class: *tag == 10,
+                    }
+                }
+                11 => ScalaEntry::NoType,
+                12 => ScalaEntry::NoPrefix,
+                13 => ScalaEntry::ThisType(reader.nat()?),
+                14 => ScalaEntry::SingleType {
+                    prefix: reader.nat()?,
+                    symbol: reader.nat()?,
+                },
+                15 => ScalaEntry::ConstantType(reader.nat()?),
+                16 => ScalaEntry::TypeRef {
+                    prefix: reader.nat()?,
+                    symbol: reader.nat()?,
+                    arguments: reader.refs()?,
+                },
+                17 => ScalaEntry::TypeBounds {
+                    lower: reader.nat()?,
+                    upper: reader.nat()?,
+                },
+                18 | 19 => {
+                    let class = reader.nat()?;
+                    let parents = reader.refs()?;
+                    if *tag == 18 {
+                        ScalaEntry::RefinedType { class, parents }
+                    } else {
+                        ScalaEntry::ClassInfoType { class, parents }
+                    }
+                }
+                20 | 22 => ScalaEntry::MethodType {
+                    result: reader.nat()?,
+                    parameters: reader.refs()?,
+                },
+                21 => ScalaEntry::PolyType {
+                    result: reader.nat()?,
+                    type_parameters: reader.refs()?,
+                },
+                24..=36 => ScalaEntry::Literal(read_literal(*tag, data)?),
+                42 => ScalaEntry::AnnotatedType {
+                    underlying: reader.nat()?,
+                },
+                48 => ScalaEntry::ExistentialType {
+                    underlying: reader.nat()?,
+                    quantified: reader.refs()?,
+                },
+                _ => ScalaEntry::Other(*tag),
+            };
+            entries.push(entry);
+        }
+        Ok(ScalaSignature {
+            major_version,
+            minor_version,
+            entries,
+        })
+    }
+
+    /**
+     * Parses the strings of a `ScalaSignature` or `ScalaLongSignature` annotation.\
+     *  scalac packs the pickle into 7 bit groups and adds one to each so that the modified
+     *  UTF-8 encoding stays compact, storing 0x7F as a zero char.
+     */
+    pub fn from_annotation_strings(strings: &[Vec<u16>]) -> Result<ScalaSignature, Box<dyn Error>> {
+        let groups: Vec<u8> = strings
+            .iter()
+            .flatten()
+            .map(|unit| if *unit == 0 { 0x7F } else { (*unit - 1) as u8 })
+            .collect();
+        let mut bytes = Vec::with_capacity(groups.len() * 7 / 8);
+        let mut buffer: u32 = 0;
+        let mut bits = 0;
+        for group in groups {
+            buffer |= ((group & 0x7F) as u32) << bits;
+            bits += 7;
+            if bits >= 8 {
+                bytes.push(buffer as u8);
+                buffer >>= 8;
+                bits -= 8;
+            }
+        }
+        ScalaSignature::parse(&bytes)
+    }
+
+    pub fn get_symbol(&self, index: usize) -> Option<&ScalaSymbol> {
+        match self.entries.get(index) {
+            Some(ScalaEntry::Symbol(symbol)) => Some(symbol),
+            _ => None,
+        }
+    }
+
+    pub fn get_symbols(&self) -> Vec<&ScalaSymbol> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry {
+                ScalaEntry::Symbol(symbol) => Some(symbol),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The classes, traits and objects owned by a package rather than by another symbol.
+    pub fn get_top_level(&self) -> Vec<&ScalaSymbol> {
+        self.get_symbols()
+            .into_iter()
+            .filter(|symbol| {
+                !symbol.is_module_class()
+                    && matches!(
+                        self.entries.get(symbol.owner),
+                        Some(ScalaEntry::ExternalSymbol { .. }) | Some(ScalaEntry::NoSymbol)
+                    )
+            })
+            .collect()
+    }
+
+    /// The members declared in a class or object, leaving out parameters.
+    pub fn get_members(&self, symbol: &ScalaSymbol) -> Vec<&ScalaSymbol> {
+        let owner = match symbol.kind {
+            ScalaSymbolKind::Module => match self.entries.get(symbol.info) {
+                Some(ScalaEntry::TypeRef { symbol, .. }) => *symbol,
+                _ => symbol.index,
+            },
+            _ => symbol.index,
+        };
+        self.get_symbols()
+            .into_iter()
+            .filter(|member| member.owner == owner && !member.is_parameter())
+            .collect()
+    }
+
+    pub fn get_name(&self, index: usize) -> String {
+        match self.entries.get(index) {
+            Some(ScalaEntry::TermName(name)) | Some(ScalaEntry::TypeName(name)) => name.clone(),
+            Some(ScalaEntry::Symbol(symbol)) => self.get_name(symbol.name),
+            Some(ScalaEntry::ExternalSymbol { name, .. }) => self.get_name(*name),
+            _ => String::new(),
+        }
+    }
+
+    /// The fully qualified name of a symbol entry, e.g. `scala.Predef.String`.
+    pub fn get_qualified_name(&self, index: usize) -> String {
+        let (name, owner) = match self.entries.get(index) {
+            Some(ScalaEntry::Symbol(symbol)) => (self.get_name(symbol.name), Some(symbol.owner)),
+            Some(ScalaEntry::ExternalSymbol { name, owner, .. }) => (self.get_name(*name), *owner),
+            _ => return String::new(),
+        };
+        if name == "<root>" || name == "<empty>" {
+            return String::new();
+        }
+        match owner.filter(|owner| *owner != index) {
+            Some(owner) => {
+                let prefix = self.get_qualified_name(owner);
+                if prefix.is_empty() {
+                    name
+                } else {
+                    format!("{prefix}.{name}")
+                }
+            }
+            None => name,
+        }
+    }
+
+    /// The type entry at `index` written the way Scala source would write it.
+    pub fn get_type(&self, index: usize) -> String {
+        self.type_to_string(index, 0)
+    }
+
+    fn type_to_string(&self, index: usize, depth: usize) -> String {
+        if depth > 64 {
+            return "...".into();
+        }
+        let depth = depth + 1;
+        let list = |indices: &[usize], separator: &str| -> String {
+            indices
+                .iter()
+                .map(|i| self.type_to_string(*i, depth))
+                .collect::<Vec<String>>()
+                .join(separator)
+        };
+        match self.entries.get(index) {
+            Some(ScalaEntry::TypeRef {
+                symbol, arguments, ..
+            }) => {
+                let name = match self.get_symbol(*symbol) {
+                    Some(local) if local.kind == ScalaSymbolKind::Type => self.get_name(local.name),
+                    _ => self.get_qualified_name(*symbol),
+                };
+                match (name.as_str(), arguments.as_slice()) {
+                    ("scala.<repeated>", [argument]) => {
+                        format!("{}*", self.type_to_string(*argument, depth))
+                    }
+                    ("scala.<byname>", [argument]) => {
+                        format!("=> {}", self.type_to_string(*argument, depth))
+                    }
+                    (_, []) => name,
+                    (_, arguments) => format!("{name}[{}]", list(arguments, ", ")),
+                }
+            }
+            Some(ScalaEntry::ThisType(symbol)) | Some(ScalaEntry::SingleType { symbol, .. }) => {
+                format!("{}.type", self.get_qualified_name(*symbol))
+            }
+            Some(ScalaEntry::ConstantType(literal)) => self.literal_to_string(*literal, depth),
+            Some(ScalaEntry::TypeBounds { .. }) => self.bounds_to_string(index, depth),
+            Some(ScalaEntry::RefinedType { parents, .. })
+            | Some(ScalaEntry::ClassInfoType { parents, .. }) => list(parents, " with "),
+            Some(ScalaEntry::MethodType { .. }) | Some(ScalaEntry::PolyType { .. }) => {
+                self.signature_tail(index, depth)
+            }
+            Some(ScalaEntry::AnnotatedType { underlying })
+            | Some(ScalaEntry::ExistentialType { underlying, .. }) => {
+                self.type_to_string(*underlying, depth)
+            }
+            Some(ScalaEntry::NoType) | Some(ScalaEntry::NoPrefix) => String::new(),
+            _ => "?".into(),
+        }
+    }
+
+    fn bounds_to_string(&self, index: usize, depth: usize) -> String {
+        let mut output = String::new();
+        if let Some(ScalaEntry::TypeBounds { lower, upper }) = self.entries.get(index) {
+            let lower = self.type_to_string(*lower, depth);
+            let upper = self.type_to_string(*upper, depth);
+            if lower != "scala.Nothing" && !lower.is_empty() {
+                output.push_str(&format!(" >: {lower}"));
+            }
+            if upper != "scala.Any" && !upper.is_empty() {
+                output.push_str(&format!(" <: {upper}"));
+            }
+        }
+        output
+    }
+
+    fn literal_to_string(&self, index: usize, depth: usize) -> String {
+        match self.entries.get(index) {
+            Some(ScalaEntry::Literal(literal)) => match literal {
+                ScalaLiteral::Unit => "()".into(),
+                ScalaLiteral::Boolean(value) => value.to_string(),
+                ScalaLiteral::Byte(value) => value.to_string(),
+                ScalaLiteral::Short(value) => value.to_string(),
+                ScalaLiteral::Char(value) => format!(
+                    "'{}'",
+                    char::from_u32(*value as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+                ),
+                ScalaLiteral::Int(value) => value.to_string(),
+                ScalaLiteral::Long(value) => format!("{value}L"),
+                ScalaLiteral::Float(value) => format!("{value:?}f"),
+                ScalaLiteral::Double(value) => format!("{value:?}"),
+                ScalaLiteral::String(name) => format!("{:?}", self.get_name(*name)),
+                ScalaLiteral::Null => "null".into(),
+                ScalaLiteral::Class(class) => {
+                    format!("classOf[{}]", self.type_to_string(*class, depth))
+                }
+                ScalaLiteral::Enum(symbol) => self.get_qualified_name(*symbol),
+            },
+            _ => "?".into(),
+        }
+    }
+
+    /// Type and value parameters followed by `: Result`, as in `[A](x: A): A`.
+    fn signature_tail(&self, index: usize, depth: usize) -> String {
+        match self.entries.get(index) {
+            Some(ScalaEntry::PolyType {
+                result,
+                type_parameters,
+            }) => {
+                if type_parameters.is_empty() {
+                    return format!(": {}", self.type_to_string(*result, depth + 1));
+                }
+                format!(
+                    "{}{}",
+                    self.type_parameters_to_string(type_parameters, depth),
+                    self.signature_tail(*result, depth + 1)
+                )
+            }
+            Some(ScalaEntry::MethodType { result, parameters }) => {
+                let implicit = parameters
+                    .first()
+                    .and_then(|parameter| self.get_symbol(*parameter))
+                    .is_some_and(ScalaSymbol::is_implicit);
+                let parameters: Vec<String> = parameters
+                    .iter()
+                    .map(|parameter| match self.get_symbol(*parameter) {
+                        Some(symbol) => format!(
+                            "{}: {}",
+                            self.get_name(symbol.name),
+                            self.type_to_string(symbol.info, depth + 1)
+                        ),
+                        None => "?".into(),
+                    })
+                    .collect();
+                format!(
+                    "({}{}){}",
+                    if implicit { "implicit " } else { "" },
+                    parameters.join(", "),
+                    self.signature_tail(*result, depth + 1)
+                )
+            }
+            _ => format!(": {}", self.type_to_string(index, depth + 1)),
+        }
+    }
+
+    fn type_parameters_to_string(&self, type_parameters: &[usize], depth: usize) -> String {
+        let parameters: Vec<String> = type_parameters
+            .iter()
+            .filter_map(|parameter| self.get_symbol(*parameter))
+            .map(|symbol| {
+                let variance = if symbol.flags & COVARIANT != 0 {
+                    "+"
+                } else if symbol.flags & CONTRAVARIANT != 0 {
+                    "-"
+                } else {
+                    ""
+                };
+                format!(
+                    "{variance}{}{}",
+                    self.get_name(symbol.name),
+                    self.bounds_to_string(symbol.info, depth + 1)
+                )
+            })
+            .collect();
+        format!("[{}]", parameters.join(", "))
+    }
+
+    /**
+     * The declaration of a symbol as Scala source would write it, without its body.\
+     *  For example `object test extends scala.AnyRef` or
+     *  `def main(args: scala.Array[scala.Predef.String]): scala.Unit`.
+     */
+    pub fn get_declaration(&self, symbol: &ScalaSymbol) -> String {
+        let mut output = String::new();
+        let flags = symbol.flags;
+        if let Some(within) = symbol.private_within {
+            let qualifier = self.get_name(within);
+            if flags & PROTECTED != 0 {
+                output.push_str(&format!("protected[{qualifier}] "));
+            } else {
+                output.push_str(&format!("private[{qualifier}] "));
+            }
+        } else if flags & PRIVATE != 0 {
+            output.push_str("private ");
+        } else if flags & PROTECTED != 0 {
+            output.push_str("protected ");
+        }
+        if flags & OVERRIDE != 0 {
+            output.push_str("override ");
+        }
+        if flags & IMPLICIT != 0 {
+            output.push_str("implicit ");
+        }
+        if flags & FINAL != 0 && !symbol.is_object() && !symbol.is_module_class() {
+            output.push_str("final ");
+        }
+        if flags & SEALED != 0 {
+            output.push_str("sealed ");
+        }
+        if flags & ABSTRACT != 0 && !symbol.is_trait() {
+            output.push_str("abstract ");
+        }
+        if flags & CASE != 0 && symbol.kind != ScalaSymbolKind::Value {
+            output.push_str("case ");
+        }
+        if flags & LAZY != 0 {
+            output.push_str("lazy ");
+        }
+        let name = self.get_name(symbol.name);
+        match symbol.kind {
+            ScalaSymbolKind::Class | ScalaSymbolKind::Module => {
+                let keyword = if symbol.is_trait() {
+                    "trait"
+                } else if symbol.is_object() || symbol.is_module_class() {
+                    "object"
+                } else {
+                    "class"
+                };
+                output.push_str(&format!("{keyword} {name}"));
+                let mut info = symbol.info;
+                if let Some(ScalaEntry::TypeRef { symbol, .. }) = self.entries.get(info) {
+                    if let Some(class) = self.get_symbol(*symbol) {
+                        info = class.info;
+                    }
+                }
+                if let Some(ScalaEntry::PolyType {
+                    result,
+                    type_parameters,
+                }) = self.entries.get(info)
+                {
+                    output.push_str(&self.type_parameters_to_string(type_parameters, 0));
+                    info = *result;
+                }
+                if let Some(ScalaEntry::ClassInfoType { parents, .. }) = self.entries.get(info) {
+                    let parents: Vec<String> = parents
+                        .iter()
+                        .map(|parent| self.get_type(*parent))
+                        .collect();
+                    if !parents.is_empty() {
+                        output.push_str(&format!(" extends {}", parents.join(" with ")));
+                    }
+                }
+            }
+            ScalaSymbolKind::Value => {
+                let keyword =
+                    if flags & METHOD != 0 && flags & (STABLE | ACCESSOR) == STABLE | ACCESSOR {
+                        "val"
+                    } else if flags & METHOD != 0 {
+                        "def"
+                    } else if flags & MUTABLE != 0 {
+                        "var"
+                    } else {
+                        "val"
+                    };
+                let name = if name == "<init>" { "this" } else { &name };
+                output.push_str(&format!(
+                    "{keyword} {name}{}",
+                    self.signature_tail(symbol.info, 0)
+                ));
+            }
+            ScalaSymbolKind::Type => {
+                output.push_str(&format!("type {name}{}", self.get_type(symbol.info)));
+            }
+            ScalaSymbolKind::Alias => {
+                output.push_str(&format!("type {name} = {}", self.get_type(symbol.info)));
+            }
+        }
+        output
+    }
+}
+
+impl Display for ScalaSignature {
+    /// Lists the top level declarations and their members, like scalap.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for symbol in self.get_top_level() {
+            let package = self.get_qualified_name(symbol.owner);
+            if !package.is_empty() {
+                writeln!(f, "package {package}")?;
+            }
+            if symbol.flags & PACKAGE != 0 {
+                continue;
+            }
+            writeln!(f, "{} {{", self.get_declaration(symbol))?;
+            for member in self.get_members(symbol) {
+                // Fields backing vals are named with a trailing space and never visible.
+                if self.get_name(member.name).ends_with(' ') {
+                    continue;
+                }
+                writeln!(f, "  {}", self.get_declaration(member))?;
+            }
+            writeln!(f, "}}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ClassFile {
+    /**
+     * Decodes the Scala 2 pickle of this class, `None` if it has none.\
+     *  Only top level classes and objects carry a pickle, objects carrying theirs on the
+     *  mirror class `test` rather than the module class `test$`.
+     */
+    pub fn get_scala_signature(&self) -> Result<Option<ScalaSignature>, Box<dyn Error>> {
+        for attribute in &self.attributes {
+            if let AttributeInfo::ScalaSig(sig) = attribute {
+                if sig.has_pickle() {
+                    return Ok(Some(ScalaSignature::parse(&sig.bytes)?));
+                }
+            }
+        }
+        let cp = &self.constant_pool;
+        let annotations = self
+            .attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                AttributeInfo::RuntimeVisibleAnnotations(a) => Some(&a.annotations),
+                _ => None,
+            })
+            .flatten();
+        for annotation in annotations {
+            let annotation_type = annotation.get_type(cp);
+            if annotation_type != "Lscala/reflect/ScalaSignature;"
+                && annotation_type != "Lscala/reflect/ScalaLongSignature;"
+            {
+                continue;
+            }
+            let strings = match annotation.get_element("bytes", cp) {
+                Some(Element::ArrayValue { values, .. }) => values
+                    .iter()
+                    .filter_map(|value| get_string(value, cp))
+                    .collect(),
+                Some(value) => get_string(value, cp).into_iter().collect(),
+                None => vec![],
+            };
+            return Ok(Some(ScalaSignature::from_annotation_strings(&strings)?));
+        }
+        Ok(None)
+    }
+
+    pub fn get_scala_inline_info(&self) -> Option<&ScalaInlineInfo> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::ScalaInlineInfo(info) => Some(info),
+                _ => None,
+            })
+    }
+
+    /// The UUID of the `.tasty` file Scala 3 wrote alongside this class.
+    pub fn get_tasty_uuid(&self) -> Option<String> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Tasty(tasty) => Some(tasty.get_uuid()),
+                _ => None,
+            })
+    }
+
+    pub fn is_scala(&self) -> bool {
+        self.attributes.iter().any(|attribute| {
+            matches!(
+                attribute,
+                AttributeInfo::ScalaSig(_) | AttributeInfo::Scala(_) | AttributeInfo::Tasty(_)
+            )
+        })
+    }
+}
+
+fn get_string(element: &Element, cp: &[ConstantPool]) -> Option<Vec<u16>> {
+    match element {
+        Element::ConstValueIndex('s', index) => match cp.get(*index as usize) {
+            Some(ConstantPool::Utf8(utf8)) => Some(utf8.get_utf16()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A natural number in big endian groups of 7 bits, the high bit marking all but the last.
+fn read_nat(bytes: &[u8], position: &mut usize) -> Result<u64, Box<dyn Error>> {
+    let mut value = 0u64;
+    loop {
+        let byte = *bytes
+            .get(*position)
+            .ok_or_else(|| invalid("number is truncated"))?;
+        *position += 1;
+        value = (value << 7) | (byte & 0x7F) as u64;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// Reads the references to other entries in the data of one entry.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn nat(&mut self) -> Result<usize, Box<dyn Error>> {
+        Ok(read_nat(self.data, &mut self.position)? as usize)
+    }
+
+    /// The references up to the end of the entry.
+    fn refs(&mut self) -> Result<Vec<usize>, Box<dyn Error>> {
+        let mut refs = vec![];
+        while !self.at_end() {
+            refs.push(self.nat()?);
+        }
+        Ok(refs)
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.data.len()
+    }
+}
+
+fn read_literal(tag: u8, data: &[u8]) -> Result<ScalaLiteral, Box<dyn Error>> {
+    // Constant values are signed big endian numbers using as few bytes as possible.
+    let mut long: i64 = 0;
+    for byte in data.iter().take(8) {
+        long = (long << 8) | *byte as i64;
+    }
+    let unused = 64 - 8 * data.len().min(8) as u32;
+    let long = if unused < 64 {
+        (long << unused) >> unused
+    } else {
+        0
+    };
+    let reference = || {
+        let mut position = 0;
+        read_nat(data, &mut position).map(|nat| nat as usize)
+    };
+    Ok(match tag {
+        24 => ScalaLiteral::Unit,
+        25 => ScalaLiteral::Boolean(long != 0),
+        26 => ScalaLiteral::Byte(long as i8),
+        27 => ScalaLiteral::Short(long as i16),
+        28 => ScalaLiteral::Char(long as u16),
+        29 => ScalaLiteral::Int(long as i32),
+        30 => ScalaLiteral::Long(long),
+        31 => ScalaLiteral::Float(f32::from_bits(long as u32)),
+        32 => ScalaLiteral::Double(f64::from_bits(long as u64)),
+        33 => ScalaLiteral::String(reference()?),
+        34 => ScalaLiteral::Null,
+        35 => ScalaLiteral::Class(reference()?),
+        _ => ScalaLiteral::Enum(reference()?),
+    })
+}
+
+fn invalid(reason: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(
+        LoadingCause::InvalidScalaSignature(reason.into()),
+        "Scala signature could not be decoded",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nat(mut value: usize, out: &mut Vec<u8>) {
+        let mut groups = vec![(value & 0x7F) as u8];
+        value >>= 7;
+        while value > 0 {
+            groups.push((value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        out.extend(groups.iter().rev());
+    }
+
+    #[derive(Default)]
+    struct Pickle {
+        entries: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl Pickle {
+        fn refs(&mut self, tag: u8, refs: &[usize]) -> usize {
+            let mut data = vec![];
+            for r in refs {
+                nat(*r, &mut data);
+            }
+            self.entries.push((tag, data));
+            self.entries.len() - 1
+        }
+
+        fn name(&mut self, tag: u8, name: &str) -> usize {
+            self.entries.push((tag, name.as_bytes().to_vec()));
+            self.entries.len() - 1
+        }
+
+        fn bytes(&self) -> Vec<u8> {
+            let mut out = vec![];
+            nat(5, &mut out);
+            nat(0, &mut out);
+            nat(self.entries.len(), &mut out);
+            for (tag, data) in &self.entries {
+                out.push(*tag);
+                nat(data.len(), &mut out);
+                out.extend(data);
+            }
+            out
+        }
+    }
+
+    /// package p; object test { def main(args: Array[String]): Unit }
+    fn test_object() -> Vec<u8> {
+        let mut p = Pickle::default();
+        let no_prefix = p.refs(12, &[]);
+        let scala_name = p.name(1, "scala");
+        let scala = p.refs(9, &[scala_name]);
+        let extern_type = |p: &mut Pickle, name: &str, owner: usize| {
+            let name = p.name(2, name);
+            let symbol = p.refs(9, &[name, owner]);
+            p.refs(16, &[no_prefix, symbol])
+        };
+        let any_ref = extern_type(&mut p, "AnyRef", scala);
+        let unit = extern_type(&mut p, "Unit", scala);
+        let predef_name = p.name(1, "Predef");
+        let predef = p.refs(9, &[predef_name, scala]);
+        let string = extern_type(&mut p, "String", predef);
+        let array_name = p.name(2, "Array");
+        let array = p.refs(9, &[array_name, scala]);
+        let array_of_string = p.refs(16, &[no_prefix, array, string]);
+
+        let package_name = p.name(1, "p");
+        let package = p.refs(9, &[package_name]);
+        let term = p.name(1, "test");
+        let class_name = p.name(2, "test");
+        // MODULE_PKL and FINAL_PKL
+        let module_class = p.refs(6, &[class_name, package, 0x44, 0]);
+        let class_info = p.refs(19, &[module_class, any_ref]);
+        p.entries[module_class].1.pop();
+        nat(class_info, &mut p.entries[module_class].1);
+        let module_type = p.refs(16, &[no_prefix, module_class]);
+        p.refs(7, &[term, package, 0x40, module_type]);
+
+        let main_name = p.name(1, "main");
+        let args_name = p.name(1, "args");
+        // METHOD_PKL
+        let main = p.refs(8, &[main_name, module_class, 0x1, 0]);
+        let args = p.refs(8, &[args_name, main, PARAM as usize, array_of_string]);
+        let method = p.refs(20, &[unit, args]);
+        p.entries[main].1.pop();
+        nat(method, &mut p.entries[main].1);
+        p.bytes()
+    }
+
+    #[test]
+    fn test_object_signature() -> Result<(), Box<dyn Error>> {
+        let signature = ScalaSignature::parse(&test_object())?;
+        let top_level = signature.get_top_level();
+        assert_eq!(top_level.len(), 1);
+        assert!(top_level[0].is_object());
+        assert_eq!(
+            signature.to_string(),
+            "package p\nobject test extends scala.AnyRef {\n  \
+             def main(args: scala.Array[scala.Predef.String]): scala.Unit\n}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotation_encoding() -> Result<(), Box<dyn Error>> {
+        let bytes = test_object();
+        // Split into 7 bit groups, least significant first, then add one to each group.
+        let mut units = vec![];
+        let mut buffer: u32 = 0;
+        let mut bits = 0;
+        for byte in &bytes {
+            buffer |= (*byte as u32) << bits;
+            bits += 8;
+            while bits >= 7 {
+                units.push(((buffer & 0x7F) as u16 + 1) & 0x7F);
+                buffer >>= 7;
+                bits -= 7;
+            }
+        }
+        if bits > 0 {
+            units.push(((buffer & 0x7F) as u16 + 1) & 0x7F);
+        }
+        let decoded = ScalaSignature::from_annotation_strings(&[units])?;
+        assert_eq!(decoded, ScalaSignature::parse(&bytes)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::access_flags::ClassAccessFlags;
+use crate::class_file::{AttributeInfo, ClassFile};
+
+/// How a permitted subclass continues (or ends) the sealed hierarchy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Sealing {
+    /// ACC_FINAL is set; nothing may extend it.
+    Final,
+    /// It has its own PermittedSubclasses attribute.
+    Sealed,
+    /**
+     * Neither final nor sealed.\
+     *  The class file format has no flag for the `non-sealed` modifier, so any permitted
+     *  subclass that is neither final nor sealed is treated as non-sealed.
+     */
+    NonSealed,
+}
+
+#[derive(Clone, Debug)]
+pub struct PermittedSubclass {
+    pub name: String,
+    /// `None` when the subclass is not part of the analysed set of classes.
+    pub sealing: Option<Sealing>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SealedType {
+    pub name: String,
+    pub is_interface: bool,
+    pub permitted: Vec<PermittedSubclass>,
+}
+
+/// [Sealed Class Checks](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#page=377)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SealedViolation {
+    /// A class named in PermittedSubclasses could not be found.
+    MissingSubclass { sealed: String, subclass: String },
+    /// A permitted subclass does not directly extend or implement the sealed type.
+    NotDirectSubtype { sealed: String, subclass: String },
+    /// A class directly extends or implements a sealed type that does not permit it.
+    NotPermitted { sealed: String, subclass: String },
+    /// A class has a PermittedSubclasses attribute but also has ACC_FINAL set.
+    FinalSealedType { sealed: String },
+}
+
+impl Display for SealedViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SealedViolation::MissingSubclass { sealed, subclass } => {
+                write!(
+                    f,
+                    "MissingSubclass: {subclass} permitted by {sealed} was not found"
+                )
+            }
+            SealedViolation::NotDirectSubtype { sealed, subclass } => write!(
+                f,
+                "NotDirectSubtype: {subclass} is permitted by {sealed} but does not extend it"
+            ),
+            SealedViolation::NotPermitted { sealed, subclass } => {
+                write!(
+                    f,
+                    "NotPermitted: {subclass} extends {sealed} without being permitted"
+                )
+            }
+            SealedViolation::FinalSealedType { sealed } => {
+                write!(
+                    f,
+                    "FinalSealedType: {sealed} is final but declares permitted subclasses"
+                )
+            }
+        }
+    }
+}
+
+/// The sealed types found in a set of classes, with their permitted subclasses resolved.
+#[derive(Clone, Debug, Default)]
+pub struct SealedHierarchy {
+    pub types: Vec<SealedType>,
+    pub violations: Vec<SealedViolation>,
+}
+
+impl SealedHierarchy {
+    pub fn new(classes: &[ClassFile]) -> SealedHierarchy {
+        let by_name: HashMap<String, &ClassFile> = classes
+            .iter()
+            .map(|class| (class.get_name(), class))
+            .collect();
+        let mut hierarchy = SealedHierarchy::default();
+
+        for class in classes {
+            let permitted = match class.get_permitted_subclasses() {
+                Some(permitted) => permitted,
+                None => continue,
+            };
+            let name = class.get_name();
+            if class.access_flags.contains(&ClassAccessFlags::AccFinal) {
+                hierarchy.violations.push(SealedViolation::FinalSealedType {
+                    sealed: name.clone(),
+                });
+            }
+            let mut sealed = SealedType {
+                name: name.clone(),
+                is_interface: class.access_flags.contains(&ClassAccessFlags::AccInterface),
+                permitted: Vec::with_capacity(permitted.len()),
+            };
+            for subclass in permitted {
+                let sealing = match by_name.get(&subclass) {
+                    Some(sub) => {
+                        if !is_direct_subtype(sub, &name) {
+                            hierarchy
+                                .violations
+                                .push(SealedViolation::NotDirectSubtype {
+                                    sealed: name.clone(),
+                                    subclass: subclass.clone(),
+                                });
+                        }
+                        Some(get_sealing(sub))
+                    }
+                    None => {
+                        hierarchy.violations.push(SealedViolation::MissingSubclass {
+                            sealed: name.clone(),
+                            subclass: subclass.clone(),
+                        });
+                        None
+                    }
+                };
+                sealed.permitted.push(PermittedSubclass {
+                    name: subclass,
+                    sealing,
+                });
+            }
+            hierarchy.types.push(sealed);
+        }
+
+        // §5.3.5: a class may only directly extend or implement a sealed type that names it.
+        for class in classes {
+            let name = class.get_name();
+            let supertypes = class
+                .get_super_name()
+                .into_iter()
+                .chain(class.get_interface_names());
+            for supertype in supertypes {
+                if let Some(sealed) = hierarchy.get_type(&supertype) {
+                    if !sealed.permitted.iter().any(|p| p.name == name) {
+                        hierarchy.violations.push(SealedViolation::NotPermitted {
+                            sealed: supertype.clone(),
+                            subclass: name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        hierarchy
+    }
+
+    pub fn get_type(&self, name: &str) -> Option<&SealedType> {
+        self.types.iter().find(|sealed| sealed.name == name)
+    }
+
+    /// The sealed types that are not themselves permitted subclasses of another sealed type.
+    pub fn get_roots(&self) -> Vec<&SealedType> {
+        self.types
+            .iter()
+            .filter(|sealed| {
+                !self
+                    .types
+                    .iter()
+                    .any(|other| other.permitted.iter().any(|p| p.name == sealed.name))
+            })
+            .collect()
+    }
+
+    /// Every class reachable from `name` through permitted subclasses, in breadth first order.
+    pub fn get_all_permitted(&self, name: &str) -> Vec<String> {
+        let mut found: Vec<String> = vec![];
+        let mut queue = vec![name.to_string()];
+        while let Some(current) = queue.pop() {
+            if let Some(sealed) = self.get_type(&current) {
+                for permitted in &sealed.permitted {
+                    if !found.contains(&permitted.name) {
+                        found.push(permitted.name.clone());
+                        queue.insert(0, permitted.name.clone());
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl ClassFile {
+    /// The names in the PermittedSubclasses attribute, `None` if this class is not sealed.
+    pub fn get_permitted_subclasses(&self) -> Option<Vec<String>> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::PermittedSubclasses(permitted) => Some(
+                    permitted
+                        .classes
+                        .iter()
+                        .filter_map(|index| {
+                            crate::constants::get_class_name(&self.constant_pool, *index)
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            })
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.get_permitted_subclasses().is_some()
+    }
+}
+
+fn is_direct_subtype(class: &ClassFile, supertype: &str) -> bool {
+    class.get_super_name().as_deref() == Some(supertype)
+        || class.get_interface_names().iter().any(|i| i == supertype)
+}
+
+fn get_sealing(class: &ClassFile) -> Sealing {
+    if class.access_flags.contains(&ClassAccessFlags::AccFinal) {
+        Sealing::Final
+    } else if class.is_sealed() {
+        Sealing::Sealed
+    } else {
+        Sealing::NonSealed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::attributes::PermittedSubclasses;
+    use crate::class_builder::tests::{class, read_back};
+
+    const OBJECT: &str = "java/lang/Object";
+    const INTERFACE: [ClassAccessFlags; 3] = [
+        ClassAccessFlags::AccPublic,
+        ClassAccessFlags::AccInterface,
+        ClassAccessFlags::AccAbstract,
+    ];
+
+    /// A class that extends `super_name` and implements `interfaces`, sealed if it `permits`.
+    fn build(
+        name: &str,
+        flags: &[ClassAccessFlags],
+        super_name: &str,
+        interfaces: &[&str],
+        permits: Option<&[&str]>,
+    ) -> ClassFile {
+        let mut class = class(flags, name, super_name, interfaces);
+        if let Some(permits) = permits {
+            let pool = class.get_constant_pool();
+            let attribute_name = pool.add_utf8("PermittedSubclasses").unwrap();
+            let mut bytes = vec![];
+            for subclass in permits {
+                bytes.extend(pool.add_class(subclass).unwrap().to_be_bytes());
+            }
+            let permitted = PermittedSubclasses::new(
+                attribute_name,
+                bytes.len() as u32 + 2,
+                permits.len() as u16,
+                &mut Cursor::new(&bytes[..]),
+            )
+            .unwrap();
+            class.add_attribute(AttributeInfo::PermittedSubclasses(permitted));
+        }
+        read_back(class)
+    }
+
+    #[test]
+    fn test_sealed_hierarchy() {
+        let abstract_class = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccAbstract];
+        let classes = [
+            build(
+                "test/Shape",
+                &INTERFACE,
+                OBJECT,
+                &[],
+                Some(&["test/Circle", "test/Polygon"]),
+            ),
+            build(
+                "test/Circle",
+                &[ClassAccessFlags::AccPublic, ClassAccessFlags::AccFinal],
+                OBJECT,
+                &["test/Shape"],
+                None,
+            ),
+            build(
+                "test/Polygon",
+                &abstract_class,
+                OBJECT,
+                &["test/Shape"],
+                Some(&["test/Square"]),
+            ),
+            build(
+                "test/Square",
+                &[ClassAccessFlags::AccPublic],
+                "test/Polygon",
+                &[],
+                None,
+            ),
+        ];
+        let hierarchy = SealedHierarchy::new(&classes);
+
+        assert!(hierarchy.is_valid(), "{:?}", hierarchy.violations);
+        let shape = hierarchy.get_type("test.Shape").unwrap();
+        assert!(shape.is_interface);
+        let sealing: Vec<(&str, Option<Sealing>)> = shape
+            .permitted
+            .iter()
+            .map(|permitted| (permitted.name.as_str(), permitted.sealing.clone()))
+            .collect();
+        assert_eq!(
+            sealing,
+            [
+                ("test.Circle", Some(Sealing::Final)),
+                ("test.Polygon", Some(Sealing::Sealed)),
+            ]
+        );
+        let polygon = hierarchy.get_type("test.Polygon").unwrap();
+        assert!(!polygon.is_interface);
+        assert_eq!(polygon.permitted[0].sealing, Some(Sealing::NonSealed));
+        let roots: Vec<&str> = hierarchy
+            .get_roots()
+            .iter()
+            .map(|sealed| sealed.name.as_str())
+            .collect();
+        assert_eq!(roots, ["test.Shape"]);
+        assert_eq!(
+            hierarchy.get_all_permitted("test.Shape"),
+            ["test.Circle", "test.Polygon", "test.Square"]
+        );
+    }
+
+    #[test]
+    fn test_sealed_violations() {
+        let public = [ClassAccessFlags::AccPublic];
+        let final_class = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccFinal];
+        let classes = [
+            build(
+                "test/Shape",
+                &INTERFACE,
+                OBJECT,
+                &[],
+                Some(&["test/Circle", "test/Gone"]),
+            ),
+            // Permitted, but does not implement Shape.
+            build("test/Circle", &final_class, OBJECT, &[], None),
+            // Implements Shape without being permitted.
+            build("test/Triangle", &final_class, OBJECT, &["test/Shape"], None),
+            // Final, so no subclass could ever be permitted.
+            build(
+                "test/Closed",
+                &final_class,
+                OBJECT,
+                &[],
+                Some(&["test/Open"]),
+            ),
+            build("test/Open", &public, "test/Closed", &[], None),
+        ];
+        let hierarchy = SealedHierarchy::new(&classes);
+
+        assert!(!hierarchy.is_valid());
+        let violations: Vec<String> = hierarchy
+            .violations
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                "NotDirectSubtype: test.Circle is permitted by test.Shape but does not extend it",
+                "MissingSubclass: test.Gone permitted by test.Shape was not found",
+                "FinalSealedType: test.Closed is final but declares permitted subclasses",
+                "NotPermitted: test.Triangle extends test.Shape without being permitted",
+            ]
+        );
+        let gone = &hierarchy.get_type("test.Shape").unwrap().permitted[1];
+        assert_eq!(gone.sealing, None);
+    }
+}
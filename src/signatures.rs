@@ -0,0 +1,611 @@
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::descriptors::FieldType;
+use crate::errors::class_format_check::{FormatCause, FormatError};
+
+/**
+ * JavaTypeSignature:\
+ *  ReferenceTypeSignature\
+ *  BaseType
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TypeSignature {
+    /// One of the primitive FieldTypes.
+    Base(FieldType),
+    Class(ClassTypeSignature),
+    /// T Identifier ;
+    TypeVariable(String),
+    /// \[ JavaTypeSignature
+    Array(Box<TypeSignature>),
+}
+
+/**
+ * SimpleClassTypeSignature:\
+ *  Identifier \[TypeArguments\]
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SimpleClassTypeSignature {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+/**
+ * ClassTypeSignature:\
+ *  L \[PackageSpecifier\] SimpleClassTypeSignature {ClassTypeSignatureSuffix} ;
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ClassTypeSignature {
+    /// The outermost class, its name including the package specifier.
+    pub class: SimpleClassTypeSignature,
+    /// The member classes selected by each `.` suffix, outermost first.
+    pub inner: Vec<SimpleClassTypeSignature>,
+}
+
+impl ClassTypeSignature {
+    /// The binary name of the class this signature refers to, e.g. `java/util/Map$Entry`.
+    pub fn get_binary_name(&self) -> String {
+        let mut name = self.class.name.clone();
+        for inner in &self.inner {
+            name.push('$');
+            name.push_str(&inner.name);
+        }
+        name
+    }
+}
+
+/**
+ * TypeArgument:\
+ *  \[WildcardIndicator\] ReferenceTypeSignature\
+ *  \*
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TypeArgument {
+    /// `*`, the unbounded wildcard `?`.
+    Any,
+    Exact(TypeSignature),
+    /// `+`, `? extends`.
+    Extends(TypeSignature),
+    /// `-`, `? super`.
+    Super(TypeSignature),
+}
+
+/**
+ * TypeParameter:\
+ *  Identifier ClassBound {InterfaceBound}
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TypeParameter {
+    pub name: String,
+    /// Absent when the ClassBound is empty, as it is for type variables bounded by interfaces.
+    pub class_bound: Option<TypeSignature>,
+    pub interface_bounds: Vec<TypeSignature>,
+}
+
+/// [Class Signature](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#page=155)
+/**
+ * A class signature encodes type information about a (possibly generic) class or interface
+ * declaration.\
+ * ClassSignature:\
+ *  \[TypeParameters\] SuperclassSignature {SuperinterfaceSignature}
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub superclass: ClassTypeSignature,
+    pub interfaces: Vec<ClassTypeSignature>,
+}
+
+/// [Method Signature](https://docs.oracle.com/javase/specs/jvms/se17/jvms17.pdf#page=156)
+/**
+ * A method signature encodes type information about a (possibly generic) method
+ * declaration.\
+ * MethodSignature:\
+ *  \[TypeParameters\] ( {JavaTypeSignature} ) Result {ThrowsSignature}
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<TypeSignature>,
+    /// `None` represents the VoidDescriptor `V`.
+    pub return_type: Option<TypeSignature>,
+    pub throws: Vec<TypeSignature>,
+}
+
+impl TypeSignature {
+    /// Parses a FieldSignature, or any other single JavaTypeSignature.
+    pub fn parse(signature: &str) -> Result<TypeSignature, FormatError> {
+        let mut parser = Parser::new(signature);
+        let parsed = parser.java_type()?;
+        parser.end()?;
+        Ok(parsed)
+    }
+
+    /// The descriptor of this type's erasure, with every type variable erased to Object.
+    pub fn get_erasure(&self) -> FieldType {
+        match self {
+            TypeSignature::Base(base) => base.clone(),
+            TypeSignature::Class(class) => FieldType::Object(class.get_binary_name()),
+            TypeSignature::TypeVariable(_) => FieldType::Object("java/lang/Object".into()),
+            TypeSignature::Array(component) => FieldType::Array(Box::new(component.get_erasure())),
+        }
+    }
+
+    /// Writes the type back in the signature grammar, the inverse of `parse`.
+    pub fn to_signature(&self) -> String {
+        match self {
+            TypeSignature::Base(base) => base.to_descriptor(),
+            TypeSignature::Class(class) => class.to_signature(),
+            TypeSignature::TypeVariable(name) => format!("T{name};"),
+            TypeSignature::Array(component) => format!("[{}", component.to_signature()),
+        }
+    }
+}
+
+impl SimpleClassTypeSignature {
+    pub fn to_signature(&self) -> String {
+        let mut signature = self.name.clone();
+        if !self.type_arguments.is_empty() {
+            signature.push('<');
+            for argument in &self.type_arguments {
+                signature.push_str(&argument.to_signature());
+            }
+            signature.push('>');
+        }
+        signature
+    }
+}
+
+impl ClassTypeSignature {
+    pub fn to_signature(&self) -> String {
+        let mut signature = format!("L{}", self.class.to_signature());
+        for inner in &self.inner {
+            signature.push('.');
+            signature.push_str(&inner.to_signature());
+        }
+        signature.push(';');
+        signature
+    }
+}
+
+impl TypeArgument {
+    pub fn to_signature(&self) -> String {
+        match self {
+            TypeArgument::Any => "*".into(),
+            TypeArgument::Exact(bound) => bound.to_signature(),
+            TypeArgument::Extends(bound) => format!("+{}", bound.to_signature()),
+            TypeArgument::Super(bound) => format!("-{}", bound.to_signature()),
+        }
+    }
+}
+
+impl TypeParameter {
+    pub fn to_signature(&self) -> String {
+        let mut signature = format!("{}:", self.name);
+        if let Some(class_bound) = &self.class_bound {
+            signature.push_str(&class_bound.to_signature());
+        }
+        for bound in &self.interface_bounds {
+            signature.push(':');
+            signature.push_str(&bound.to_signature());
+        }
+        signature
+    }
+}
+
+impl ClassSignature {
+    pub fn parse(signature: &str) -> Result<ClassSignature, FormatError> {
+        let mut parser = Parser::new(signature);
+        let type_parameters = parser.type_parameters()?;
+        let superclass = parser.class_type()?;
+        let mut interfaces = vec![];
+        while parser.peek().is_some() {
+            interfaces.push(parser.class_type()?);
+        }
+        Ok(ClassSignature {
+            type_parameters,
+            superclass,
+            interfaces,
+        })
+    }
+
+    /// Writes the signature back in the grammar it was parsed from.
+    pub fn to_signature(&self) -> String {
+        let mut signature = type_parameters_signature(&self.type_parameters);
+        signature.push_str(&self.superclass.to_signature());
+        for interface in &self.interfaces {
+            signature.push_str(&interface.to_signature());
+        }
+        signature
+    }
+}
+
+impl MethodSignature {
+    pub fn parse(signature: &str) -> Result<MethodSignature, FormatError> {
+        let mut parser = Parser::new(signature);
+        let type_parameters = parser.type_parameters()?;
+        parser.expect('(')?;
+        let mut parameters = vec![];
+        while parser.peek() != Some(')') {
+            parameters.push(parser.java_type()?);
+        }
+        parser.expect(')')?;
+        let return_type = if parser.peek() == Some('V') {
+            parser.next();
+            None
+        } else {
+            Some(parser.java_type()?)
+        };
+        let mut throws = vec![];
+        while parser.peek() == Some('^') {
+            parser.next();
+            throws.push(parser.reference_type()?);
+        }
+        parser.end()?;
+        Ok(MethodSignature {
+            type_parameters,
+            parameters,
+            return_type,
+            throws,
+        })
+    }
+
+    /// Writes the signature back in the grammar it was parsed from.
+    pub fn to_signature(&self) -> String {
+        let mut signature = type_parameters_signature(&self.type_parameters);
+        signature.push('(');
+        for parameter in &self.parameters {
+            signature.push_str(&parameter.to_signature());
+        }
+        signature.push(')');
+        match &self.return_type {
+            Some(return_type) => signature.push_str(&return_type.to_signature()),
+            None => signature.push('V'),
+        }
+        for throws in &self.throws {
+            signature.push('^');
+            signature.push_str(&throws.to_signature());
+        }
+        signature
+    }
+}
+
+fn type_parameters_signature(type_parameters: &[TypeParameter]) -> String {
+    if type_parameters.is_empty() {
+        return String::new();
+    }
+    let parameters: Vec<String> = type_parameters
+        .iter()
+        .map(TypeParameter::to_signature)
+        .collect();
+    format!("<{}>", parameters.concat())
+}
+
+struct Parser<'a> {
+    signature: &'a str,
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(signature: &'a str) -> Parser<'a> {
+        Parser {
+            signature,
+            chars: signature.chars().peekable(),
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), FormatError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(invalid(self.signature)),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), FormatError> {
+        match self.next() {
+            None => Ok(()),
+            Some(_) => Err(invalid(self.signature)),
+        }
+    }
+
+    /// Reads an Identifier, which ends at any of `.;[/<>:`.
+    fn identifier(&mut self) -> Result<String, FormatError> {
+        let mut identifier = String::new();
+        while let Some(c) = self.peek() {
+            if matches!(c, '.' | ';' | '[' | '/' | '<' | '>' | ':') {
+                break;
+            }
+            identifier.push(c);
+            self.next();
+        }
+        if identifier.is_empty() {
+            return Err(invalid(self.signature));
+        }
+        Ok(identifier)
+    }
+
+    fn type_parameters(&mut self) -> Result<Vec<TypeParameter>, FormatError> {
+        let mut parameters = vec![];
+        if self.peek() != Some('<') {
+            return Ok(parameters);
+        }
+        self.next();
+        while self.peek() != Some('>') {
+            let name = self.identifier()?;
+            self.expect(':')?;
+            let class_bound = match self.peek() {
+                Some('L' | 'T' | '[') => Some(self.reference_type()?),
+                _ => None,
+            };
+            let mut interface_bounds = vec![];
+            while self.peek() == Some(':') {
+                self.next();
+                interface_bounds.push(self.reference_type()?);
+            }
+            parameters.push(TypeParameter {
+                name,
+                class_bound,
+                interface_bounds,
+            });
+        }
+        self.next();
+        if parameters.is_empty() {
+            return Err(invalid(self.signature));
+        }
+        Ok(parameters)
+    }
+
+    fn java_type(&mut self) -> Result<TypeSignature, FormatError> {
+        let base = match self.peek() {
+            Some('B') => FieldType::Byte,
+            Some('C') => FieldType::Char,
+            Some('D') => FieldType::Double,
+            Some('F') => FieldType::Float,
+            Some('I') => FieldType::Int,
+            Some('J') => FieldType::Long,
+            Some('S') => FieldType::Short,
+            Some('Z') => FieldType::Boolean,
+            _ => return self.reference_type(),
+        };
+        self.next();
+        Ok(TypeSignature::Base(base))
+    }
+
+    fn reference_type(&mut self) -> Result<TypeSignature, FormatError> {
+        match self.peek() {
+            Some('L') => Ok(TypeSignature::Class(self.class_type()?)),
+            Some('T') => {
+                self.next();
+                let name = self.identifier()?;
+                self.expect(';')?;
+                Ok(TypeSignature::TypeVariable(name))
+            }
+            Some('[') => {
+                self.next();
+                Ok(TypeSignature::Array(Box::new(self.java_type()?)))
+            }
+            _ => Err(invalid(self.signature)),
+        }
+    }
+
+    fn class_type(&mut self) -> Result<ClassTypeSignature, FormatError> {
+        self.expect('L')?;
+        let mut name = self.identifier()?;
+        while self.peek() == Some('/') {
+            self.next();
+            name.push('/');
+            name.push_str(&self.identifier()?);
+        }
+        let class = SimpleClassTypeSignature {
+            name,
+            type_arguments: self.type_arguments()?,
+        };
+        let mut inner = vec![];
+        while self.peek() == Some('.') {
+            self.next();
+            inner.push(SimpleClassTypeSignature {
+                name: self.identifier()?,
+                type_arguments: self.type_arguments()?,
+            });
+        }
+        self.expect(';')?;
+        Ok(ClassTypeSignature { class, inner })
+    }
+
+    fn type_arguments(&mut self) -> Result<Vec<TypeArgument>, FormatError> {
+        let mut arguments = vec![];
+        if self.peek() != Some('<') {
+            return Ok(arguments);
+        }
+        self.next();
+        while self.peek() != Some('>') {
+            arguments.push(match self.peek() {
+                Some('*') => {
+                    self.next();
+                    TypeArgument::Any
+                }
+                Some('+') => {
+                    self.next();
+                    TypeArgument::Extends(self.reference_type()?)
+                }
+                Some('-') => {
+                    self.next();
+                    TypeArgument::Super(self.reference_type()?)
+                }
+                _ => TypeArgument::Exact(self.reference_type()?),
+            });
+        }
+        self.next();
+        if arguments.is_empty() {
+            return Err(invalid(self.signature));
+        }
+        Ok(arguments)
+    }
+}
+
+fn write_list<T: Display>(
+    f: &mut std::fmt::Formatter<'_>,
+    prefix: &str,
+    items: &[T],
+    suffix: &str,
+) -> std::fmt::Result {
+    if items.is_empty() {
+        return Ok(());
+    }
+    write!(f, "{prefix}")?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    write!(f, "{suffix}")
+}
+
+/// Types are written as in Java source, e.g. `java.util.Map$Entry<? extends K, V>[]`.
+impl Display for TypeSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeSignature::Base(base) => write!(f, "{base}"),
+            TypeSignature::Class(class) => write!(f, "{class}"),
+            TypeSignature::TypeVariable(name) => write!(f, "{name}"),
+            TypeSignature::Array(component) => write!(f, "{component}[]"),
+        }
+    }
+}
+
+impl Display for SimpleClassTypeSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name.replace('/', "."))?;
+        write_list(f, "<", &self.type_arguments, ">")
+    }
+}
+
+impl Display for ClassTypeSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.class)?;
+        for inner in &self.inner {
+            write!(f, ".{inner}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for TypeArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeArgument::Any => write!(f, "?"),
+            TypeArgument::Exact(bound) => write!(f, "{bound}"),
+            TypeArgument::Extends(bound) => write!(f, "? extends {bound}"),
+            TypeArgument::Super(bound) => write!(f, "? super {bound}"),
+        }
+    }
+}
+
+/// A class bound of `java.lang.Object` is left out, as it would be in source.
+impl Display for TypeParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        let mut separator = " extends ";
+        let object = TypeSignature::Class(ClassTypeSignature {
+            class: SimpleClassTypeSignature {
+                name: "java/lang/Object".into(),
+                type_arguments: vec![],
+            },
+            inner: vec![],
+        });
+        let class_bound = self.class_bound.iter().filter(|bound| **bound != object);
+        for bound in class_bound.chain(&self.interface_bounds) {
+            write!(f, "{separator}{bound}")?;
+            separator = " & ";
+        }
+        Ok(())
+    }
+}
+
+impl Display for ClassSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_list(f, "<", &self.type_parameters, ">")?;
+        write!(f, " extends {}", self.superclass)?;
+        write_list(f, " implements ", &self.interfaces, "")
+    }
+}
+
+impl Display for MethodSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_list(f, "<", &self.type_parameters, "> ")?;
+        match &self.return_type {
+            Some(return_type) => write!(f, "{return_type}")?,
+            None => write!(f, "void")?,
+        }
+        write!(f, " (")?;
+        write_list(f, "", &self.parameters, "")?;
+        write!(f, ")")?;
+        write_list(f, " throws ", &self.throws, "")
+    }
+}
+
+fn invalid(signature: &str) -> FormatError {
+    FormatError::new(
+        FormatCause::InvalidSignature(signature.into()),
+        "signature does not match the grammar in §4.7.9.1",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_generic_method() -> Result<(), FormatError> {
+        let signature = MethodSignature::parse(
+            "<R:Ljava/lang/Object;>(Ljava/util/function/Function<-TT;+TR;>;TT;)TR;^TE;",
+        )?;
+        assert_eq!(signature.type_parameters.len(), 1);
+        assert_eq!(
+            signature.parameters[0].to_string(),
+            "java.util.function.Function<? super T, ? extends R>"
+        );
+        assert_eq!(
+            signature.to_string(),
+            "<R> R (java.util.function.Function<? super T, ? extends R>, T) throws E"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_class_signature() -> Result<(), FormatError> {
+        let signature = ClassSignature::parse(
+            "<K:Ljava/lang/Number;:Ljava/lang/Comparable<TK;>;V:Ljava/lang/Object;>Ljava/util/AbstractMap<TK;TV;>;Ljava/io/Serializable;",
+        )?;
+        assert_eq!(
+            signature.to_string(),
+            "<K extends java.lang.Number & java.lang.Comparable<K>, V> extends java.util.AbstractMap<K, V> implements java.io.Serializable"
+        );
+        let inner = TypeSignature::parse("LG<TK;TV;>.In<Ljava/lang/String;>;")?;
+        assert_eq!(inner.to_string(), "G<K, V>.In<java.lang.String>");
+        assert_eq!(inner.get_erasure().to_descriptor(), "LG$In;");
+        assert!(TypeSignature::parse("Ljava/util/List<>;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_signatures_round_trip() -> Result<(), FormatError> {
+        let method = "<R:Ljava/lang/Object;>(Ljava/util/function/Function<-TT;+TR;>;[I)TR;^TE;";
+        assert_eq!(MethodSignature::parse(method)?.to_signature(), method);
+        let class = "<K::Ljava/lang/Comparable<TK;>;>Ljava/lang/Object;Ljava/util/List<*>;";
+        assert_eq!(ClassSignature::parse(class)?.to_signature(), class);
+        let field = "LG<TK;>.In<[Ljava/lang/String;>;";
+        assert_eq!(TypeSignature::parse(field)?.to_signature(), field);
+        Ok(())
+    }
+}
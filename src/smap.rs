@@ -0,0 +1,307 @@
+use std::error::Error;
+
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+
+/// A file declared in the `*F` section of a stratum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmapFile {
+    pub id: u32,
+    pub name: String,
+    /// The path given on the line following a `+` entry.
+    pub path: Option<String>,
+}
+
+/**
+ * One entry of a `*L` section:\
+ *  InputStartLine \[ # LineFileID \] \[ , RepeatCount \] : OutputStartLine \[ , OutputLineIncrement \]
+ *
+ * Input line `input_start_line + n` maps to the output lines
+ * `output_start_line + n * output_line_increment` up to, but not including,
+ * `output_start_line + (n + 1) * output_line_increment` for every n below `repeat_count`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineInfo {
+    pub input_start_line: u32,
+    pub line_file_id: u32,
+    pub repeat_count: u32,
+    pub output_start_line: u32,
+    pub output_line_increment: u32,
+}
+
+impl LineInfo {
+    /// The input line that `output_line` was generated from, if it is covered by this entry.
+    pub fn map_output_line(&self, output_line: u32) -> Option<u32> {
+        if self.output_line_increment == 0 || output_line < self.output_start_line {
+            return None;
+        }
+        let n = (output_line - self.output_start_line) / self.output_line_increment;
+        if n < self.repeat_count {
+            // A line past u32::MAX cannot be mapped to.
+            self.input_start_line.checked_add(n)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stratum {
+    pub id: String,
+    pub files: Vec<SmapFile>,
+    pub lines: Vec<LineInfo>,
+}
+
+impl Stratum {
+    pub fn get_file(&self, id: u32) -> Option<&SmapFile> {
+        self.files.iter().find(|file| file.id == id)
+    }
+}
+
+/// A source position in one of a stratum's input files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub file_name: String,
+    pub path: Option<String>,
+    pub line: u32,
+}
+
+/// [JSR-45 Source Map](https://jcp.org/en/jsr/detail?id=45)
+/**
+ * The SMAP carried in a SourceDebugExtension attribute by JSP compilers and by kotlinc for
+ * inlined functions. It maps line numbers of the generated output file (the lines found in
+ * LineNumberTable attributes) back to the lines of the original input files, per stratum.
+ *
+ * Embedded SMAPs (`*O`/`*C` sections) are expected to have been resolved by the compiler
+ * and are skipped, as are vendor (`*V`) and unknown sections.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Smap {
+    pub output_file: String,
+    pub default_stratum: String,
+    pub strata: Vec<Stratum>,
+}
+
+enum Section {
+    None,
+    Files,
+    Lines,
+    Skipped,
+}
+
+impl Smap {
+    pub fn parse(input: &str) -> Result<Smap, Box<dyn Error>> {
+        let mut lines = input
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .enumerate();
+        match lines.next() {
+            Some((_, "SMAP")) => {}
+            _ => return Err(invalid(1)),
+        }
+        let output_file = lines.next().ok_or_else(|| invalid(2))?.1.to_string();
+        let default_stratum = lines.next().ok_or_else(|| invalid(3))?.1.to_string();
+
+        let mut strata: Vec<Stratum> = vec![];
+        let mut section = Section::None;
+        let mut embedded_depth = 0;
+        let mut line_file_id = 0;
+        while let Some((number, line)) = lines.next() {
+            let number = number + 1;
+            if let Some(header) = line.strip_prefix('*') {
+                let mut header = header.split_whitespace();
+                match header.next() {
+                    Some("O") => embedded_depth += 1,
+                    Some("C") => embedded_depth -= 1,
+                    _ if embedded_depth > 0 => {}
+                    Some("S") => {
+                        let id = header.next().ok_or_else(|| invalid(number))?;
+                        strata.push(Stratum {
+                            id: id.to_string(),
+                            files: vec![],
+                            lines: vec![],
+                        });
+                        section = Section::None;
+                        line_file_id = 0;
+                    }
+                    Some("F") => section = Section::Files,
+                    Some("L") => section = Section::Lines,
+                    Some("E") => section = Section::None,
+                    _ => section = Section::Skipped,
+                }
+                continue;
+            }
+            if embedded_depth > 0 || line.is_empty() {
+                continue;
+            }
+            match section {
+                Section::Files => {
+                    let stratum = strata.last_mut().ok_or_else(|| invalid(number))?;
+                    let (has_path, entry) = match line.strip_prefix('+') {
+                        Some(entry) => (true, entry.trim_start()),
+                        None => (false, line),
+                    };
+                    let (id, name) = entry.split_once(' ').ok_or_else(|| invalid(number))?;
+                    let path = if has_path {
+                        Some(
+                            lines
+                                .next()
+                                .ok_or_else(|| invalid(number + 1))?
+                                .1
+                                .to_string(),
+                        )
+                    } else {
+                        None
+                    };
+                    stratum.files.push(SmapFile {
+                        id: id.parse().map_err(|_| invalid(number))?,
+                        name: name.to_string(),
+                        path,
+                    });
+                }
+                Section::Lines => {
+                    let stratum = strata.last_mut().ok_or_else(|| invalid(number))?;
+                    let info =
+                        parse_line_info(line, line_file_id).ok_or_else(|| invalid(number))?;
+                    line_file_id = info.line_file_id;
+                    stratum.lines.push(info);
+                }
+                Section::None | Section::Skipped => {}
+            }
+        }
+        Ok(Smap {
+            output_file,
+            default_stratum,
+            strata,
+        })
+    }
+
+    pub fn get_stratum(&self, id: &str) -> Option<&Stratum> {
+        self.strata.iter().find(|stratum| stratum.id == id)
+    }
+
+    /**
+     * Maps a line of the output file back to its input file and line.\
+     *  `stratum` selects the stratum to map into, `None` uses the default stratum.
+     */
+    pub fn map_line(&self, output_line: u32, stratum: Option<&str>) -> Option<SourcePosition> {
+        let stratum = self.get_stratum(stratum.unwrap_or(&self.default_stratum))?;
+        stratum.lines.iter().find_map(|info| {
+            let line = info.map_output_line(output_line)?;
+            let file = stratum.get_file(info.line_file_id)?;
+            Some(SourcePosition {
+                file_name: file.name.clone(),
+                path: file.path.clone(),
+                line,
+            })
+        })
+    }
+}
+
+fn parse_line_info(line: &str, previous_file_id: u32) -> Option<LineInfo> {
+    let (input, output) = line.split_once(':')?;
+    let (input, repeat_count) = match input.split_once(',') {
+        Some((input, repeat)) => (input, repeat.trim().parse().ok()?),
+        None => (input, 1),
+    };
+    let (input_start_line, line_file_id) = match input.split_once('#') {
+        Some((start, id)) => (start.trim().parse().ok()?, id.trim().parse().ok()?),
+        None => (input.trim().parse().ok()?, previous_file_id),
+    };
+    let (output_start_line, output_line_increment) = match output.split_once(',') {
+        Some((start, increment)) => (start.trim().parse().ok()?, increment.trim().parse().ok()?),
+        None => (output.trim().parse().ok()?, 1),
+    };
+    Some(LineInfo {
+        input_start_line,
+        line_file_id,
+        repeat_count,
+        output_start_line,
+        output_line_increment,
+    })
+}
+
+fn invalid(line: usize) -> Box<dyn Error> {
+    Box::new(LoadingError::new(
+        LoadingCause::InvalidSmap(line),
+        "SourceDebugExtension is not a well formed SMAP",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KOTLIN_SMAP: &str = "SMAP
+Test.kt
+Kotlin
+*S Kotlin
+*F
++ 1 Test.kt
+TestKt
++ 2 Inline.kt
+InlineKt
+*L
+1#1,12:1
+4#2,3:13
+*E
+*S KotlinDebug
+*F
++ 1 Test.kt
+TestKt
+*L
+7#1:13,3
+*E
+";
+
+    #[test]
+    fn test_parse_kotlin_smap() -> Result<(), Box<dyn Error>> {
+        let smap = Smap::parse(KOTLIN_SMAP)?;
+        assert_eq!(smap.output_file, "Test.kt");
+        assert_eq!(smap.default_stratum, "Kotlin");
+        assert_eq!(smap.strata.len(), 2);
+        let kotlin = smap.get_stratum("Kotlin").unwrap();
+        assert_eq!(kotlin.files[1].path.as_deref(), Some("InlineKt"));
+        assert_eq!(kotlin.lines[1].repeat_count, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_inlined_line() -> Result<(), Box<dyn Error>> {
+        let smap = Smap::parse(KOTLIN_SMAP)?;
+        let position = smap.map_line(14, None).unwrap();
+        assert_eq!(position.file_name, "Inline.kt");
+        assert_eq!(position.line, 5);
+        assert_eq!(smap.map_line(3, None).unwrap().line, 3);
+        let call_site = smap.map_line(15, Some("KotlinDebug")).unwrap();
+        assert_eq!(
+            (call_site.file_name.as_str(), call_site.line),
+            ("Test.kt", 7)
+        );
+        assert!(smap.map_line(16, None).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_jsp_line_increment() -> Result<(), Box<dyn Error>> {
+        let smap =
+            Smap::parse("SMAP\nindex_jsp.java\nJSP\n*S JSP\n*F\n0 index.jsp\n*L\n2,2:40,3\n*E\n")?;
+        assert_eq!(smap.map_line(40, None).unwrap().line, 2);
+        assert_eq!(smap.map_line(42, None).unwrap().line, 2);
+        assert_eq!(smap.map_line(43, None).unwrap().line, 3);
+        assert!(smap.map_line(46, None).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_input_line_overflow() {
+        let info = LineInfo {
+            input_start_line: u32::MAX - 1,
+            line_file_id: 0,
+            repeat_count: 3,
+            output_start_line: 10,
+            output_line_increment: 1,
+        };
+        assert_eq!(info.map_output_line(11), Some(u32::MAX));
+        assert_eq!(info.map_output_line(12), None);
+    }
+}
@@ -0,0 +1,1052 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::access_flags::MethodAccessFlags;
+use crate::attributes::Code;
+use crate::cfg::{ControlFlowGraph, EdgeKind};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool, MethodInfo};
+use crate::constants::get_raw_utf8;
+use crate::descriptors::{FieldType, MethodDescriptor};
+use crate::errors::class_loading::{LoadingCause, LoadingError};
+use crate::instructions::*;
+
+/// A virtual register, assigned exactly once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Value(pub(crate) u32);
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+/// [Computational Types](https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.11.1)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// Also boolean, byte, char and short, which the instructions compute with as int.
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference,
+}
+
+impl Type {
+    fn from_field_type(field_type: &FieldType) -> Type {
+        match field_type {
+            FieldType::Long => Type::Long,
+            FieldType::Float => Type::Float,
+            FieldType::Double => Type::Double,
+            FieldType::Object(_) | FieldType::Array(_) => Type::Reference,
+            _ => Type::Int,
+        }
+    }
+
+    /// The number of stack or local variable slots a value of this type takes.
+    fn get_size(&self) -> usize {
+        match self {
+            Type::Long | Type::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Where a value is assigned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Definition {
+    /// A parameter, or `this`, by the local variable it is passed in.
+    Parameter(u16),
+    /// The exception caught by the handler at the start of the block.
+    Exception(usize),
+    /// A phi at the start of the block.
+    Phi(usize),
+    /// A statement of the block.
+    Statement(usize),
+    /// A local variable that is not assigned on some path to a phi using it.
+    Undefined,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValueInfo {
+    pub(crate) value_type: Type,
+    pub(crate) definition: Definition,
+}
+
+/// Selects the value of one predecessor, by the edge control came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Phi {
+    pub(crate) result: Value,
+    /**
+     * The block each value comes from, `None` for the entry to the method.\
+     *  A block with an edge to an exception handler may give several values, one for each
+     *  the local variable holds while the block runs, as any instruction may throw.
+     */
+    pub(crate) operands: Vec<(Option<usize>, Value)>,
+}
+
+/// An instruction with the operand stack and local variables replaced by values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statement {
+    pub(crate) pc: u32,
+    /// The line of the source file, from the LineNumberTable.
+    pub(crate) line: Option<u16>,
+    /**
+     * The opcode of the instruction. Loads, stores, stack manipulation and goto have no
+     * statements, and ldc_w and ldc2_w become ldc.
+     */
+    pub(crate) opcode: u8,
+    /// The operands that are not values, such as a constant pool index or branch targets.
+    pub(crate) operands: Operands,
+    /// The values the instruction pops, the deepest first.
+    pub(crate) arguments: Vec<Value>,
+    pub(crate) result: Option<Value>,
+}
+
+/// The code of a basic block of the control flow graph with the same index.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Block {
+    pub(crate) phis: Vec<Phi>,
+    /// Set for an exception handler, which starts with the exception it caught.
+    pub(crate) exception: Option<Value>,
+    pub(crate) statements: Vec<Statement>,
+}
+
+/**
+ * A method body in static single assignment form, lifted from its bytecode.\
+ *  The operand stack and local variables become values, each assigned once, with phis
+ *  where control flow joins. Control flow, including the edges to exception handlers, is
+ *  that of the [ControlFlowGraph]. Blocks that cannot be reached are left empty.
+ */
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub(crate) cfg: ControlFlowGraph,
+    pub(crate) blocks: Vec<Block>,
+    pub(crate) values: Vec<ValueInfo>,
+}
+
+impl Function {
+    pub fn new(class: &ClassFile, method: &MethodInfo) -> Result<Function, Box<dyn Error>> {
+        let constant_pool = &class.constant_pool;
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::Code(code) => Some(code),
+                _ => None,
+            })
+            .ok_or_else(|| invalid(0, "the method has no code"))?;
+        let descriptor = get_raw_utf8(constant_pool, method.descriptor_index).unwrap_or_default();
+        let descriptor = MethodDescriptor::from_descriptor(&descriptor)?;
+        let cfg = ControlFlowGraph::new(code, constant_pool)?;
+
+        let mut lifter = Lifter::new(&cfg, constant_pool, code);
+        let mut slot = 0;
+        if !method.access_flags.contains(&MethodAccessFlags::AccStatic) {
+            lifter.add_parameter(slot, Type::Reference);
+            slot += 1;
+        }
+        for parameter in &descriptor.parameters {
+            lifter.add_parameter(slot, Type::from_field_type(parameter));
+            slot += parameter.get_size();
+        }
+        for block in cfg.get_reverse_postorder() {
+            lifter.lift_block(block)?;
+        }
+        lifter.resolve();
+        Ok(lifter.finish(cfg.clone()))
+    }
+
+    pub fn get_cfg(&self) -> &ControlFlowGraph {
+        &self.cfg
+    }
+
+    pub fn get_blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn get_type(&self, value: Value) -> Type {
+        self.values[value.0 as usize].value_type
+    }
+
+    pub fn get_definition(&self, value: Value) -> &Definition {
+        &self.values[value.0 as usize].definition
+    }
+
+    fn format_operands(&self, statement: &Statement) -> (String, String) {
+        let block = |pc: &u32| match self.cfg.get_block_at(*pc) {
+            Some(block) => format!("B{block}"),
+            None => format!("{pc}"),
+        };
+        match &statement.operands {
+            Operands::None => (String::new(), String::new()),
+            Operands::Immediate(value) => (format!(" {value}"), String::new()),
+            Operands::Constant(index)
+            | Operands::InvokeDynamic(index)
+            | Operands::InvokeInterface { index, .. } => (format!(" #{index}"), String::new()),
+            Operands::Local(index) => (format!(" {index}"), String::new()),
+            Operands::Increment { value, .. } => (format!(" {value}"), String::new()),
+            Operands::NewArray(atype) => (
+                format!(" {}", get_array_type_name(*atype).unwrap_or("?")),
+                String::new(),
+            ),
+            Operands::MultiANewArray { index, dimensions } => {
+                (format!(" #{index}, {dimensions}"), String::new())
+            }
+            Operands::Branch(target) => (String::new(), format!(" -> {}", block(target))),
+            Operands::TableSwitch {
+                default,
+                low,
+                high,
+                targets,
+            } => {
+                let cases: Vec<String> = (*low..=*high)
+                    .zip(targets)
+                    .map(|(key, target)| format!("{key}: {}", block(target)))
+                    .collect();
+                let cases = cases.join(", ");
+                (
+                    String::new(),
+                    format!(" -> {{{cases}, default: {}}}", block(default)),
+                )
+            }
+            Operands::LookupSwitch { default, pairs } => {
+                let cases: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, target)| format!("{key}: {}", block(target)))
+                    .collect();
+                let cases = cases.join(", ");
+                (
+                    String::new(),
+                    format!(" -> {{{cases}, default: {}}}", block(default)),
+                )
+            }
+        }
+    }
+}
+
+/// Writes the blocks with one phi or statement a line, marking where source lines start.
+impl Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut line = None;
+        for (index, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "B{index}:")?;
+            if let Some(exception) = block.exception {
+                writeln!(f, "  {exception} = exception")?;
+            }
+            for phi in &block.phis {
+                let operands: Vec<String> = phi
+                    .operands
+                    .iter()
+                    .map(|(block, value)| match block {
+                        Some(block) => format!("B{block}: {value}"),
+                        None => format!("entry: {value}"),
+                    })
+                    .collect();
+                writeln!(f, "  {} = phi [{}]", phi.result, operands.join(", "))?;
+            }
+            for statement in &block.statements {
+                if statement.line.is_some() && statement.line != line {
+                    line = statement.line;
+                    writeln!(f, "  // line {}", line.unwrap_or_default())?;
+                }
+                write!(f, "  ")?;
+                if let Some(result) = statement.result {
+                    write!(f, "{result} = ")?;
+                }
+                let (before, after) = self.format_operands(statement);
+                let arguments: Vec<String> =
+                    statement.arguments.iter().map(Value::to_string).collect();
+                let mnemonic = get_mnemonic(statement.opcode).unwrap_or("unknown");
+                match arguments.is_empty() {
+                    true => writeln!(f, "{mnemonic}{before}{after}")?,
+                    false => writeln!(f, "{mnemonic}{before} {}{after}", arguments.join(", "))?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A local variable or operand stack slot, the variables that become values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Variable {
+    Local(u16),
+    /// A stack entry by its depth, counted in values rather than slots.
+    Stack(usize),
+}
+
+/// The operand stack and local variables while a block is lifted.
+#[derive(Default)]
+struct State {
+    stack: Vec<Value>,
+    /// The locals the block has written.
+    locals: HashMap<u16, Value>,
+    /// The locals read before the block writes them, which hold their value on entry.
+    reads: HashMap<u16, Value>,
+}
+
+/**
+ * Lifts each block on its own, reading the variables it does not define from placeholders,
+ * then resolves them from the predecessors as in
+ * [Simple and Efficient Construction of Static Single Assignment Form](https://c9x.me/compile/bib/braun13cc.pdf).
+ */
+struct Lifter<'a> {
+    cfg: &'a ControlFlowGraph,
+    constant_pool: &'a [ConstantPool],
+    /// The lines of the LineNumberTable, by the pc they start at.
+    lines: Vec<(u32, u16)>,
+    values: Vec<ValueInfo>,
+    blocks: Vec<Block>,
+    parameters: HashMap<u16, Value>,
+    /// The types on the operand stack on entry to each block, once a predecessor is lifted.
+    entry_stacks: Vec<Option<Vec<Type>>>,
+    /// The variables each block writes, with the value they hold at its end.
+    written: Vec<HashMap<Variable, Value>>,
+    /// Every value each block writes to each local variable, in order.
+    assignments: Vec<HashMap<u16, Vec<Value>>>,
+    /// The block, variable and placeholder of each read of a variable on entry to a block.
+    placeholders: Vec<(usize, Variable, Value)>,
+    /// The value of each variable on entry to each block, once resolved.
+    entry_values: HashMap<(usize, Variable), Value>,
+    /// The value that replaces a placeholder or trivial phi.
+    replaced: HashMap<Value, Value>,
+}
+
+impl<'a> Lifter<'a> {
+    fn new(cfg: &'a ControlFlowGraph, constant_pool: &'a [ConstantPool], code: &Code) -> Self {
+        let mut lines: Vec<(u32, u16)> = code
+            .attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                AttributeInfo::LineNumberTable(table) => Some(&table.line_number_table),
+                _ => None,
+            })
+            .flatten()
+            .map(|line| (line.start_pc as u32, line.line_number))
+            .collect();
+        lines.sort();
+        let count = cfg.get_blocks().len();
+        let mut entry_stacks = vec![None; count];
+        if count > 0 {
+            entry_stacks[0] = Some(vec![]);
+        }
+        Lifter {
+            cfg,
+            constant_pool,
+            lines,
+            values: vec![],
+            blocks: vec![Block::default(); count],
+            parameters: HashMap::new(),
+            entry_stacks,
+            written: vec![HashMap::new(); count],
+            assignments: vec![HashMap::new(); count],
+            placeholders: vec![],
+            entry_values: HashMap::new(),
+            replaced: HashMap::new(),
+        }
+    }
+
+    fn new_value(&mut self, value_type: Type, definition: Definition) -> Value {
+        self.values.push(ValueInfo {
+            value_type,
+            definition,
+        });
+        Value(self.values.len() as u32 - 1)
+    }
+
+    fn add_parameter(&mut self, slot: u16, value_type: Type) {
+        let value = self.new_value(value_type, Definition::Parameter(slot));
+        self.parameters.insert(slot, value);
+    }
+
+    fn get_line(&self, pc: u32) -> Option<u16> {
+        let index = self.lines.partition_point(|(start_pc, _)| *start_pc <= pc);
+        index.checked_sub(1).map(|index| self.lines[index].1)
+    }
+
+    fn lift_block(&mut self, block: usize) -> Result<(), Box<dyn Error>> {
+        let cfg = self.cfg;
+        let mut state = State::default();
+        let is_handler = cfg.get_blocks().iter().any(|other| {
+            other
+                .successors
+                .iter()
+                .any(|edge| edge.target == block && matches!(edge.kind, EdgeKind::Exception(_)))
+        });
+        if is_handler {
+            let exception = self.new_value(Type::Reference, Definition::Exception(block));
+            self.blocks[block].exception = Some(exception);
+            state.stack.push(exception);
+        } else {
+            let start_pc = cfg.get_blocks()[block].start_pc;
+            let types = self.entry_stacks[block]
+                .clone()
+                .ok_or_else(|| invalid(start_pc, "the stack on entry is unknown"))?;
+            for (depth, value_type) in types.into_iter().enumerate() {
+                let placeholder = self.new_value(value_type, Definition::Undefined);
+                self.placeholders
+                    .push((block, Variable::Stack(depth), placeholder));
+                state.stack.push(placeholder);
+            }
+        }
+
+        for instruction in cfg.get_instructions(block) {
+            self.lift_instruction(block, &mut state, instruction)?;
+        }
+
+        let types: Vec<Type> = state
+            .stack
+            .iter()
+            .map(|value| self.values[value.0 as usize].value_type)
+            .collect();
+        for edge in &cfg.get_blocks()[block].successors {
+            if matches!(edge.kind, EdgeKind::Exception(_)) {
+                continue;
+            }
+            match &self.entry_stacks[edge.target] {
+                Some(entry) if entry != &types => {
+                    let pc = cfg.get_blocks()[edge.target].start_pc;
+                    return Err(invalid(pc, "the stack differs between predecessors"));
+                }
+                Some(_) => {}
+                None => self.entry_stacks[edge.target] = Some(types.clone()),
+            }
+        }
+        let written = &mut self.written[block];
+        for (depth, value) in state.stack.iter().enumerate() {
+            written.insert(Variable::Stack(depth), *value);
+        }
+        for (local, value) in &state.locals {
+            written.insert(Variable::Local(*local), *value);
+        }
+        Ok(())
+    }
+
+    fn lift_instruction(
+        &mut self,
+        block: usize,
+        state: &mut State,
+        instruction: &Instruction,
+    ) -> Result<(), Box<dyn Error>> {
+        const TYPES: [Type; 5] = [
+            Type::Int,
+            Type::Long,
+            Type::Float,
+            Type::Double,
+            Type::Reference,
+        ];
+        let pc = instruction.pc;
+        let opcode = instruction.opcode;
+        let operands = instruction.operands.clone();
+        let local = match instruction.operands {
+            Operands::Local(index) | Operands::Increment { index, .. } => index,
+            _ => 0,
+        };
+        match opcode {
+            NOP | GOTO | GOTO_W => {}
+            ACONST_NULL => self.emit(block, state, instruction, 0, Some(Type::Reference))?,
+            ICONST_M1..=ICONST_5 | BIPUSH | SIPUSH => {
+                self.emit(block, state, instruction, 0, Some(Type::Int))?
+            }
+            LCONST_0 | LCONST_1 => self.emit(block, state, instruction, 0, Some(Type::Long))?,
+            FCONST_0..=FCONST_2 => self.emit(block, state, instruction, 0, Some(Type::Float))?,
+            DCONST_0 | DCONST_1 => self.emit(block, state, instruction, 0, Some(Type::Double))?,
+            LDC | LDC_W | LDC2_W => {
+                let Operands::Constant(index) = operands else {
+                    return Err(invalid(pc, "ldc without a constant"));
+                };
+                let value_type = self.constant_type(index, pc)?;
+                let ldc = Instruction {
+                    opcode: LDC,
+                    ..instruction.clone()
+                };
+                self.emit(block, state, &ldc, 0, Some(value_type))?;
+            }
+            ILOAD..=ALOAD => {
+                let value = self.read_local(block, state, local, TYPES[(opcode - ILOAD) as usize]);
+                state.stack.push(value);
+            }
+            ILOAD_0..=ALOAD_3 => {
+                let index = (opcode - ILOAD_0) as usize;
+                let value = self.read_local(block, state, index as u16 % 4, TYPES[index / 4]);
+                state.stack.push(value);
+            }
+            IALOAD..=SALOAD => {
+                let value_type = match opcode {
+                    LALOAD => Type::Long,
+                    FALOAD => Type::Float,
+                    DALOAD => Type::Double,
+                    AALOAD => Type::Reference,
+                    _ => Type::Int,
+                };
+                self.emit(block, state, instruction, 2, Some(value_type))?;
+            }
+            ISTORE..=ASTORE => {
+                let value = pop(state, pc)?;
+                self.write_local(block, state, local, value);
+            }
+            ISTORE_0..=ASTORE_3 => {
+                let value = pop(state, pc)?;
+                self.write_local(block, state, (opcode - ISTORE_0) as u16 % 4, value);
+            }
+            IASTORE..=SASTORE => self.emit(block, state, instruction, 3, None)?,
+            POP => {
+                self.pop_slots(state, 1, pc)?;
+            }
+            POP2 => {
+                self.pop_slots(state, 2, pc)?;
+            }
+            DUP..=DUP2_X2 => {
+                // The slots that are copied and those they are inserted under.
+                let (copied, under) = match opcode {
+                    DUP => (1, 0),
+                    DUP_X1 => (1, 1),
+                    DUP_X2 => (1, 2),
+                    DUP2 => (2, 0),
+                    DUP2_X1 => (2, 1),
+                    _ => (2, 2),
+                };
+                let top = self.pop_slots(state, copied, pc)?;
+                let below = self.pop_slots(state, under, pc)?;
+                state.stack.extend(top.iter().chain(&below).chain(&top));
+            }
+            SWAP => {
+                let top = self.pop_slots(state, 1, pc)?;
+                let below = self.pop_slots(state, 1, pc)?;
+                state.stack.extend(top.iter().chain(&below));
+            }
+            IADD..=DREM => {
+                let value_type = TYPES[((opcode - IADD) % 4) as usize];
+                self.emit(block, state, instruction, 2, Some(value_type))?;
+            }
+            INEG..=DNEG => {
+                let value_type = TYPES[((opcode - INEG) % 4) as usize];
+                self.emit(block, state, instruction, 1, Some(value_type))?;
+            }
+            ISHL..=LXOR => {
+                let value_type = TYPES[((opcode - ISHL) % 2) as usize];
+                self.emit(block, state, instruction, 2, Some(value_type))?;
+            }
+            IINC => {
+                let value = self.read_local(block, state, local, Type::Int);
+                state.stack.push(value);
+                self.emit(block, state, instruction, 1, Some(Type::Int))?;
+                let value = pop(state, pc)?;
+                self.write_local(block, state, local, value);
+            }
+            I2L..=I2S => {
+                let value_type = match opcode {
+                    I2L | F2L | D2L => Type::Long,
+                    I2F | L2F | D2F => Type::Float,
+                    I2D | L2D | F2D => Type::Double,
+                    _ => Type::Int,
+                };
+                self.emit(block, state, instruction, 1, Some(value_type))?;
+            }
+            LCMP..=DCMPG => self.emit(block, state, instruction, 2, Some(Type::Int))?,
+            IFEQ..=IFLE | IFNULL | IFNONNULL | TABLESWITCH | LOOKUPSWITCH => {
+                self.emit(block, state, instruction, 1, None)?
+            }
+            IF_ICMPEQ..=IF_ACMPNE => self.emit(block, state, instruction, 2, None)?,
+            JSR | RET | JSR_W => {
+                return Err(invalid(pc, "subroutines are not supported"));
+            }
+            IRETURN..=ARETURN | ATHROW | MONITORENTER | MONITOREXIT | PUTSTATIC => {
+                self.emit(block, state, instruction, 1, None)?
+            }
+            RETURN => self.emit(block, state, instruction, 0, None)?,
+            GETSTATIC | GETFIELD => {
+                let descriptor = self.descriptor(&operands, pc)?;
+                let field_type = FieldType::from_descriptor(&descriptor)?;
+                let pops = (opcode == GETFIELD) as usize;
+                let value_type = Type::from_field_type(&field_type);
+                self.emit(block, state, instruction, pops, Some(value_type))?;
+            }
+            PUTFIELD => self.emit(block, state, instruction, 2, None)?,
+            INVOKEVIRTUAL..=INVOKEDYNAMIC => {
+                let descriptor = self.descriptor(&operands, pc)?;
+                let descriptor = MethodDescriptor::from_descriptor(&descriptor)?;
+                let receiver = !matches!(opcode, INVOKESTATIC | INVOKEDYNAMIC) as usize;
+                let pops = descriptor.parameters.len() + receiver;
+                let value_type = descriptor.return_type.as_ref().map(Type::from_field_type);
+                self.emit(block, state, instruction, pops, value_type)?;
+            }
+            NEW => self.emit(block, state, instruction, 0, Some(Type::Reference))?,
+            NEWARRAY | ANEWARRAY | CHECKCAST => {
+                self.emit(block, state, instruction, 1, Some(Type::Reference))?
+            }
+            ARRAYLENGTH | INSTANCEOF => self.emit(block, state, instruction, 1, Some(Type::Int))?,
+            MULTIANEWARRAY => {
+                let Operands::MultiANewArray { dimensions, .. } = operands else {
+                    return Err(invalid(pc, "multianewarray without dimensions"));
+                };
+                let pops = dimensions as usize;
+                self.emit(block, state, instruction, pops, Some(Type::Reference))?;
+            }
+            _ => return Err(invalid(pc, "unknown opcode")),
+        }
+        Ok(())
+    }
+
+    /// Adds a statement popping `pops` values and pushing a value of `result`, if any.
+    fn emit(
+        &mut self,
+        block: usize,
+        state: &mut State,
+        instruction: &Instruction,
+        pops: usize,
+        result: Option<Type>,
+    ) -> Result<(), Box<dyn Error>> {
+        if state.stack.len() < pops {
+            return Err(invalid(instruction.pc, "the operand stack underflows"));
+        }
+        let arguments = state.stack.split_off(state.stack.len() - pops);
+        let result = result.map(|value_type| {
+            let value = self.new_value(value_type, Definition::Statement(block));
+            state.stack.push(value);
+            value
+        });
+        let statement = Statement {
+            pc: instruction.pc,
+            line: self.get_line(instruction.pc),
+            opcode: instruction.opcode,
+            operands: instruction.operands.clone(),
+            arguments,
+            result,
+        };
+        self.blocks[block].statements.push(statement);
+        Ok(())
+    }
+
+    /// Pops the values taking the top `slots` slots, the deepest first.
+    fn pop_slots(
+        &self,
+        state: &mut State,
+        slots: usize,
+        pc: u32,
+    ) -> Result<Vec<Value>, Box<dyn Error>> {
+        let mut values = vec![];
+        let mut popped = 0;
+        while popped < slots {
+            let value = pop(state, pc)?;
+            popped += self.values[value.0 as usize].value_type.get_size();
+            values.insert(0, value);
+        }
+        if popped != slots {
+            return Err(invalid(pc, "the instruction splits a long or double"));
+        }
+        Ok(values)
+    }
+
+    fn read_local(
+        &mut self,
+        block: usize,
+        state: &mut State,
+        local: u16,
+        value_type: Type,
+    ) -> Value {
+        if let Some(value) = state.locals.get(&local).or(state.reads.get(&local)) {
+            return *value;
+        }
+        let placeholder = self.new_value(value_type, Definition::Undefined);
+        self.placeholders
+            .push((block, Variable::Local(local), placeholder));
+        state.reads.insert(local, placeholder);
+        placeholder
+    }
+
+    fn write_local(&mut self, block: usize, state: &mut State, local: u16, value: Value) {
+        state.locals.insert(local, value);
+        self.assignments[block]
+            .entry(local)
+            .or_default()
+            .push(value);
+    }
+
+    /// Replaces each placeholder with the value of its variable, adding phis as needed.
+    fn resolve(&mut self) {
+        for (block, variable, placeholder) in std::mem::take(&mut self.placeholders) {
+            let value_type = self.values[placeholder.0 as usize].value_type;
+            let value = self.read_entry(block, variable, value_type);
+            self.replaced.insert(placeholder, value);
+        }
+        self.remove_trivial_phis();
+    }
+
+    /// The value of a variable on entry to a block, which is a phi unless it turns out trivial.
+    fn read_entry(&mut self, block: usize, variable: Variable, value_type: Type) -> Value {
+        if let Some(value) = self.entry_values.get(&(block, variable)) {
+            return *value;
+        }
+        let cfg = self.cfg;
+        let predecessors = &cfg.get_blocks()[block].predecessors;
+        if block == 0 && predecessors.is_empty() {
+            let value = self.initial_value(variable, value_type);
+            self.entry_values.insert((block, variable), value);
+            return value;
+        }
+        let phi = self.new_value(value_type, Definition::Phi(block));
+        self.entry_values.insert((block, variable), phi);
+        let mut operands = vec![];
+        if block == 0 {
+            operands.push((None, self.initial_value(variable, value_type)));
+        }
+        for predecessor in predecessors {
+            let edges = cfg.get_blocks()[*predecessor]
+                .successors
+                .iter()
+                .filter(|edge| edge.target == block);
+            let mut normal = false;
+            let mut exceptional = false;
+            for edge in edges {
+                match edge.kind {
+                    EdgeKind::Exception(_) => exceptional = true,
+                    _ => normal = true,
+                }
+            }
+            if normal {
+                let value = match self.written[*predecessor].get(&variable) {
+                    Some(value) => *value,
+                    None => self.read_entry(*predecessor, variable, value_type),
+                };
+                operands.push((Some(*predecessor), value));
+            }
+            if let (true, Variable::Local(local)) = (exceptional, variable) {
+                let value = self.read_entry(*predecessor, variable, value_type);
+                operands.push((Some(*predecessor), value));
+                let assignments = self.assignments[*predecessor].get(&local);
+                for value in assignments.into_iter().flatten() {
+                    operands.push((Some(*predecessor), *value));
+                }
+            }
+        }
+        self.blocks[block].phis.push(Phi {
+            result: phi,
+            operands,
+        });
+        phi
+    }
+
+    fn initial_value(&mut self, variable: Variable, value_type: Type) -> Value {
+        match variable {
+            Variable::Local(local) => match self.parameters.get(&local) {
+                Some(value) => *value,
+                None => self.new_value(value_type, Definition::Undefined),
+            },
+            Variable::Stack(_) => self.new_value(value_type, Definition::Undefined),
+        }
+    }
+
+    fn find(&self, mut value: Value) -> Value {
+        while let Some(next) = self.replaced.get(&value) {
+            value = *next;
+        }
+        value
+    }
+
+    fn is_undefined(&self, value: Value) -> bool {
+        self.values[value.0 as usize].definition == Definition::Undefined
+    }
+
+    /**
+     * Removes the phis whose operands are all the same value, ignoring the phi itself and
+     * undefined values, until none is left.
+     */
+    fn remove_trivial_phis(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in 0..self.blocks.len() {
+                let mut phis = std::mem::take(&mut self.blocks[block].phis);
+                phis.retain(|phi| {
+                    let mut operands = phi
+                        .operands
+                        .iter()
+                        .map(|(_, value)| self.find(*value))
+                        .filter(|value| *value != phi.result && !self.is_undefined(*value));
+                    let Some(first) = operands.next() else {
+                        return true;
+                    };
+                    if operands.all(|value| value == first) {
+                        self.replaced.insert(phi.result, first);
+                        changed = true;
+                        return false;
+                    }
+                    true
+                });
+                self.blocks[block].phis = phis;
+            }
+        }
+    }
+
+    /// Substitutes the replaced values and numbers the values in the order they are defined.
+    fn finish(mut self, cfg: ControlFlowGraph) -> Function {
+        let mut numbers: HashMap<Value, Value> = HashMap::new();
+        let mut values = vec![];
+        let mut number = |value: Value, values: &mut Vec<ValueInfo>, all: &[ValueInfo]| {
+            *numbers.entry(value).or_insert_with(|| {
+                values.push(all[value.0 as usize].clone());
+                Value(values.len() as u32 - 1)
+            })
+        };
+        let mut parameters: Vec<(u16, Value)> = self.parameters.clone().into_iter().collect();
+        parameters.sort();
+        for (_, value) in parameters {
+            number(value, &mut values, &self.values);
+        }
+        let mut blocks = std::mem::take(&mut self.blocks);
+        for block in &mut blocks {
+            if let Some(exception) = &mut block.exception {
+                *exception = number(*exception, &mut values, &self.values);
+            }
+            for phi in &mut block.phis {
+                phi.result = number(phi.result, &mut values, &self.values);
+            }
+            for statement in &mut block.statements {
+                for argument in &mut statement.arguments {
+                    *argument = number(self.find(*argument), &mut values, &self.values);
+                }
+                if let Some(result) = &mut statement.result {
+                    *result = number(*result, &mut values, &self.values);
+                }
+            }
+        }
+        for block in &mut blocks {
+            for phi in &mut block.phis {
+                for (_, operand) in &mut phi.operands {
+                    *operand = number(self.find(*operand), &mut values, &self.values);
+                }
+            }
+        }
+        Function {
+            cfg,
+            blocks,
+            values,
+        }
+    }
+
+    fn constant_type(&self, index: u16, pc: u32) -> Result<Type, Box<dyn Error>> {
+        Ok(match self.constant_pool.get(index as usize) {
+            Some(ConstantPool::Integer(_)) => Type::Int,
+            Some(ConstantPool::Float(_)) => Type::Float,
+            Some(ConstantPool::Long(_)) => Type::Long,
+            Some(ConstantPool::Double(_)) => Type::Double,
+            Some(
+                ConstantPool::String(_)
+                | ConstantPool::Class(_)
+                | ConstantPool::MethodType(_)
+                | ConstantPool::MethodHandle(_),
+            ) => Type::Reference,
+            Some(ConstantPool::Dynamic(_)) => {
+                let descriptor = self.descriptor(&Operands::Constant(index), pc)?;
+                Type::from_field_type(&FieldType::from_descriptor(&descriptor)?)
+            }
+            _ => return Err(invalid(pc, "ldc of a constant that is not loadable")),
+        })
+    }
+
+    /// The descriptor of the field, method, call site or dynamic constant an instruction uses.
+    fn descriptor(&self, operands: &Operands, pc: u32) -> Result<String, Box<dyn Error>> {
+        let index = match operands {
+            Operands::Constant(index)
+            | Operands::InvokeDynamic(index)
+            | Operands::InvokeInterface { index, .. } => *index,
+            _ => return Err(invalid(pc, "the instruction has no constant")),
+        };
+        let name_and_type_index = match self.constant_pool.get(index as usize) {
+            Some(ConstantPool::Fieldref(field)) => field.name_and_type_index,
+            Some(ConstantPool::Methodref(method)) => method.name_and_type_index,
+            Some(ConstantPool::InterfaceMethodRef(method)) => method.name_and_type_index,
+            Some(ConstantPool::InvokeDynamic(call_site)) => call_site.name_and_type_index,
+            Some(ConstantPool::Dynamic(dynamic)) => dynamic.name_and_type_index,
+            _ => return Err(invalid(pc, "the operand is not a member reference")),
+        };
+        match self.constant_pool.get(name_and_type_index as usize) {
+            Some(ConstantPool::NameAndType(name_and_type)) => {
+                get_raw_utf8(self.constant_pool, name_and_type.descriptor_index)
+                    .ok_or_else(|| invalid(pc, "the member has no descriptor"))
+            }
+            _ => Err(invalid(pc, "the member has no name and type")),
+        }
+    }
+}
+
+fn pop(state: &mut State, pc: u32) -> Result<Value, Box<dyn Error>> {
+    state
+        .stack
+        .pop()
+        .ok_or_else(|| invalid(pc, "the operand stack underflows"))
+}
+
+fn invalid(pc: u32, msg: &str) -> Box<dyn Error> {
+    Box::new(LoadingError::new(LoadingCause::InvalidInstruction(pc), msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_builder::tests::{single_method, sum_loop};
+    use crate::class_builder::{CodeBuilder, Insn};
+
+    /// Builds a class with one static method and lifts it.
+    fn lift(descriptor: &str, code: CodeBuilder) -> Function {
+        let class = single_method("test/Lift", descriptor, code);
+        let class = ClassFile::from_bytes(&class.into_bytes().unwrap()).unwrap();
+        Function::new(&class, &class.methods[0]).unwrap()
+    }
+
+    #[test]
+    fn test_lift_loops_with_phis() {
+        let function = lift("(I)I", sum_loop());
+
+        assert_eq!(
+            function.to_string(),
+            "\
+B0:
+  v1 = iconst_0
+  v2 = iconst_0
+B1:
+  v3 = phi [B0: v2, B2: v6]
+  v4 = phi [B0: v1, B2: v5]
+  if_icmpge v3, v0 -> B3
+B2:
+  v5 = iadd v4, v3
+  v6 = iinc 1 v3
+B3:
+  ireturn v4
+"
+        );
+        assert_eq!(function.get_definition(Value(0)), &Definition::Parameter(0));
+        assert_eq!(function.get_type(Value(4)), Type::Int);
+    }
+
+    #[test]
+    fn test_lift_values_left_on_the_stack() {
+        // static long run(boolean b, long x, Object o) { return b ? x : (long) o.hashCode(); }
+        let mut code = CodeBuilder::new(4, 4);
+        let (other, join) = (code.new_label(), code.new_label());
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::Jump(IFEQ, other));
+        code.push(Insn::Local(LLOAD, 1));
+        code.push(Insn::Jump(GOTO, join));
+        code.push(Insn::Label(other));
+        code.push(Insn::Local(ALOAD, 3));
+        code.push(Insn::Simple(DUP));
+        code.push(Insn::Simple(POP));
+        code.push(Insn::Method {
+            opcode: INVOKEVIRTUAL,
+            owner: "java/lang/Object".into(),
+            name: "hashCode".into(),
+            descriptor: "()I".into(),
+            interface: false,
+        });
+        code.push(Insn::Simple(I2L));
+        code.push(Insn::Label(join));
+        code.push(Insn::Simple(DUP2));
+        code.push(Insn::Simple(POP2));
+        code.push(Insn::Simple(LRETURN));
+        let function = lift("(ZJLjava/lang/Object;)J", code);
+
+        assert_eq!(
+            function.to_string(),
+            "\
+B0:
+  ifeq v0 -> B2
+B1:
+B2:
+  v3 = invokevirtual #8 v2
+  v4 = i2l v3
+B3:
+  v5 = phi [B1: v1, B2: v4]
+  lreturn v5
+"
+        );
+        assert_eq!(function.get_type(Value(5)), Type::Long);
+    }
+
+    #[test]
+    fn test_lift_switch_keys_up_to_max() {
+        // static void run(int key) { switch (key) { case MAX - 1: case MAX: } }
+        let mut code = CodeBuilder::new(1, 1);
+        let (case, end) = (code.new_label(), code.new_label());
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::TableSwitch {
+            default: end,
+            low: i32::MAX - 1,
+            targets: vec![case, case],
+        });
+        code.push(Insn::Label(case));
+        code.push(Insn::Label(end));
+        code.push(Insn::Simple(RETURN));
+        let function = lift("(I)V", code);
+
+        assert_eq!(
+            function.to_string(),
+            "\
+B0:
+  tableswitch v0 -> {2147483646: B1, 2147483647: B1, default: B1}
+B1:
+  return
+"
+        );
+    }
+
+    #[test]
+    fn test_lift_exception_handlers_and_lines() {
+        // static int run(int x) { int r = 0; try { r = 1; r = work(x); }
+        // catch (RuntimeException e) { return r; } return r; }
+        let mut code = CodeBuilder::new(1, 3);
+        let labels: Vec<_> = (0..5).map(|_| code.new_label()).collect();
+        let (start, end, handler, join) = (labels[0], labels[1], labels[2], labels[3]);
+        code.push(Insn::Simple(ICONST_0));
+        code.push(Insn::Local(ISTORE, 1));
+        code.push(Insn::Label(start));
+        code.push(Insn::Simple(ICONST_1));
+        code.push(Insn::Local(ISTORE, 1));
+        code.push(Insn::Local(ILOAD, 0));
+        code.push(Insn::Method {
+            opcode: INVOKESTATIC,
+            owner: "test/Lift".into(),
+            name: "work".into(),
+            descriptor: "(I)I".into(),
+            interface: false,
+        });
+        code.push(Insn::Local(ISTORE, 1));
+        code.push(Insn::Label(end));
+        code.push(Insn::Jump(GOTO, join));
+        code.push(Insn::Label(handler));
+        code.push(Insn::Local(ASTORE, 2));
+        code.push(Insn::Label(labels[4]));
+        code.push(Insn::Local(ILOAD, 1));
+        code.push(Insn::Simple(IRETURN));
+        code.push(Insn::Label(join));
+        code.push(Insn::Local(ILOAD, 1));
+        code.push(Insn::Simple(IRETURN));
+        code.add_exception_handler(start, end, handler, Some("java/lang/RuntimeException"));
+        code.add_line_number(start, 1);
+        code.add_line_number(labels[4], 2);
+        let function = lift("(I)I", code);
+
+        assert_eq!(
+            function.to_string(),
+            "\
+B0:
+  v1 = iconst_0
+B1:
+  // line 1
+  v2 = iconst_1
+  v3 = invokestatic #8 v0
+B2:
+B3:
+  v4 = exception
+  v5 = phi [B1: v1, B1: v2, B1: v3]
+  // line 2
+  ireturn v5
+B4:
+  ireturn v3
+"
+        );
+        assert_eq!(function.get_definition(Value(4)), &Definition::Exception(3));
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use crate::access_flags::ClassAccessFlags;
+use crate::attributes::InnerClassInfo;
+use crate::class_file::{AttributeInfo, ClassFile};
+use crate::constants::get_class_name;
+use crate::decompiler::{Decompiler, Stub};
+
+/**
+ * Java source stubs for a set of classes, to compile other code against when only the class
+ * files are at hand.\
+ *  Each top level class gets a source file with its package, annotations, generic signatures,
+ *  fields and their constant values, and methods whose bodies throw. Member classes are
+ *  declared inside the classes that enclose them, while anonymous, local, synthetic and
+ *  private classes and members are left out, as no other code can refer to them.
+ */
+pub struct StubGenerator<'a> {
+    /// The classes by binary name.
+    classes: BTreeMap<String, &'a ClassFile>,
+}
+
+impl<'a> StubGenerator<'a> {
+    pub fn new(classes: &'a [ClassFile]) -> StubGenerator<'a> {
+        StubGenerator {
+            classes: classes
+                .iter()
+                .map(|class| (class.get_name(), class))
+                .collect(),
+        }
+    }
+
+    /// The stubs of the top level classes, by the path of their source file, e.g. `a/b/C.java`.
+    pub fn get_stubs(&self) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+        let mut stubs = BTreeMap::new();
+        for (name, class) in &self.classes {
+            let simple_name = name.rsplit(['.', '/']).next().unwrap_or_default();
+            let is_declaration = !matches!(simple_name, "module-info" | "package-info");
+            if is_declaration && is_visible(class) && get_inner_class(class, name).is_none() {
+                let path = format!("{}.java", name.replace('.', "/"));
+                stubs.insert(path, self.stub(class)?);
+            }
+        }
+        Ok(stubs)
+    }
+
+    /// The stub of a class, with the stubs of its member classes inside it.
+    fn stub(&self, class: &ClassFile) -> Result<String, Box<dyn Error>> {
+        let name = class.get_name();
+        let mut members = vec![];
+        for (member, inner_name) in get_inner_classes(class) {
+            let Some(member_class) = self.classes.get(&member) else {
+                continue;
+            };
+            let is_member = get_inner_class(member_class, &member).is_some_and(|(outer, flags)| {
+                outer == name && !flags.contains(&ClassAccessFlags::AccPrivate)
+            });
+            if is_member && inner_name && member != name && is_visible(member_class) {
+                members.push(self.stub(member_class)?);
+            }
+        }
+        let supertypes = class.get_super_name().into_iter();
+        let non_sealed = supertypes
+            .chain(class.get_interface_names())
+            .filter_map(|supertype| self.classes.get(&supertype))
+            .any(|supertype| supertype.is_sealed())
+            && !class.is_sealed();
+        Decompiler::new_stub(
+            class,
+            Stub {
+                members,
+                non_sealed,
+            },
+        )
+        .decompile()
+    }
+}
+
+fn is_visible(class: &ClassFile) -> bool {
+    !class.access_flags.contains(&ClassAccessFlags::AccSynthetic)
+}
+
+/**
+ * The classes the InnerClasses attribute of `class` lists, each with whether it has a simple
+ * name, which anonymous classes do not.
+ */
+fn get_inner_classes(class: &ClassFile) -> Vec<(String, bool)> {
+    inner_class_entries(class)
+        .map(|info| {
+            let name = get_class_name(&class.constant_pool, info.inner_class_info_index);
+            (name.unwrap_or_default(), info.inner_name_index != 0)
+        })
+        .collect()
+}
+
+/**
+ * The class `name` is declared in and its flags as declared, from the InnerClasses attribute
+ * of `class`. `None` for a top level class, and an empty outer name for a local or anonymous
+ * class.
+ */
+fn get_inner_class(class: &ClassFile, name: &str) -> Option<(String, Vec<ClassAccessFlags>)> {
+    let pool = &class.constant_pool;
+    inner_class_entries(class)
+        .find(|info| get_class_name(pool, info.inner_class_info_index).as_deref() == Some(name))
+        .map(|info| {
+            let outer = match info.inner_name_index {
+                0 => None,
+                _ => get_class_name(pool, info.outer_class_info_index),
+            };
+            let flags = ClassAccessFlags::from_u16(info.inner_class_access_flags);
+            (outer.unwrap_or_default(), flags)
+        })
+}
+
+fn inner_class_entries(class: &ClassFile) -> impl Iterator<Item = &InnerClassInfo> {
+    class
+        .attributes
+        .iter()
+        .filter_map(|attribute| match attribute {
+            AttributeInfo::InnerClasses(inner) => Some(&inner.classes),
+            _ => None,
+        })
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::access_flags::{FieldAccessFlags, MethodAccessFlags};
+    use crate::attributes::InnerClasses;
+    use crate::class_builder::tests::class;
+    use crate::class_builder::{
+        ClassBuilder, CodeBuilder, Constant, FieldBuilder, Insn, MethodBuilder,
+    };
+    use crate::instructions::*;
+
+    /// A class with a constructor that calls `super()`, and no other members.
+    fn empty_class(flags: &[ClassAccessFlags], name: &str, super_name: &str) -> ClassBuilder {
+        let mut class = class(flags, name, super_name, &[]);
+        let (descriptor, extra) = match name.strip_suffix("$Inner") {
+            Some(outer) => (format!("(L{outer};)V"), 1),
+            None => ("()V".into(), 0),
+        };
+        let mut code = CodeBuilder::new(1, 1 + extra);
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Method {
+            opcode: INVOKESPECIAL,
+            owner: super_name.into(),
+            name: "<init>".into(),
+            descriptor: "()V".into(),
+            interface: false,
+        });
+        code.push(Insn::Simple(RETURN));
+        let mut method = MethodBuilder::new(&[MethodAccessFlags::AccPublic], "<init>", &descriptor);
+        method.set_code(code);
+        class.add_method(method).unwrap();
+        class
+    }
+
+    /**
+     * Builds a class, with an InnerClasses attribute for `inner` classes given by name,
+     * enclosing class, simple name and flags.
+     */
+    fn build(mut class: ClassBuilder, inner: &[(&str, &str, &str, u16)]) -> ClassFile {
+        let pool = class.get_constant_pool();
+        let name_index = pool.add_utf8("InnerClasses").unwrap();
+        let mut bytes = (inner.len() as u16).to_be_bytes().to_vec();
+        for (name, outer, simple_name, flags) in inner {
+            bytes.extend(pool.add_class(name).unwrap().to_be_bytes());
+            bytes.extend(pool.add_class(outer).unwrap().to_be_bytes());
+            bytes.extend(pool.add_utf8(simple_name).unwrap().to_be_bytes());
+            bytes.extend(flags.to_be_bytes());
+        }
+        let mut class = class.build().unwrap();
+        if !inner.is_empty() {
+            let length = bytes.len() as u32;
+            let mut cursor = Cursor::new(bytes.as_slice());
+            let attribute =
+                InnerClasses::new(name_index, length, &class.constant_pool, &mut cursor).unwrap();
+            class
+                .attributes
+                .push(AttributeInfo::InnerClasses(attribute));
+        }
+        ClassFile::from_bytes(&class.to_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_bodies_throw() {
+        let mut class = class(
+            &[ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper],
+            "test/Widget",
+            "test/Base",
+            &[],
+        );
+        let public_static_final = [
+            FieldAccessFlags::AccPublic,
+            FieldAccessFlags::AccStatic,
+            FieldAccessFlags::AccFinal,
+        ];
+        let mut size = FieldBuilder::new(&public_static_final, "SIZE", "I");
+        size.set_constant_value(Constant::Integer(3));
+        class.add_field(size).unwrap();
+        let seed = FieldBuilder::new(&public_static_final, "SEED", "J");
+        class.add_field(seed).unwrap();
+        let count = FieldBuilder::new(&[FieldAccessFlags::AccPrivate], "count", "I");
+        class.add_field(count).unwrap();
+
+        // public Widget(String name) throws IOException { super(name, 1); }
+        let mut code = CodeBuilder::new(3, 2);
+        code.push(Insn::Local(ALOAD, 0));
+        code.push(Insn::Local(ALOAD, 1));
+        code.push(Insn::Simple(ICONST_1));
+        code.push(Insn::Method {
+            opcode: INVOKESPECIAL,
+            owner: "test/Base".into(),
+            name: "<init>".into(),
+            descriptor: "(Ljava/lang/String;I)V".into(),
+            interface: false,
+        });
+        code.push(Insn::Simple(RETURN));
+        let flags = [MethodAccessFlags::AccPublic];
+        let mut method = MethodBuilder::new(&flags, "<init>", "(Ljava/lang/String;)V");
+        method.set_code(code);
+        method.add_exception("java/io/IOException");
+        class.add_method(method).unwrap();
+
+        let mut code = CodeBuilder::new(1, 1);
+        code.push(Insn::Simple(RETURN));
+        let mut helper = MethodBuilder::new(&[MethodAccessFlags::AccPrivate], "helper", "()V");
+        helper.set_code(code);
+        class.add_method(helper).unwrap();
+        let abstract_method = MethodBuilder::new(
+            &[MethodAccessFlags::AccPublic, MethodAccessFlags::AccAbstract],
+            "size",
+            "()I",
+        );
+        class.add_method(abstract_method).unwrap();
+
+        let classes = [build(class, &[])];
+        let stubs = StubGenerator::new(&classes).get_stubs().unwrap();
+        assert_eq!(stubs.keys().collect::<Vec<_>>(), ["test/Widget.java"]);
+        assert_eq!(
+            stubs["test/Widget.java"],
+            "\
+package test;
+
+public class Widget extends Base {
+    public static final int SIZE = 3;
+    public static final long SEED = Long.valueOf(0L);
+
+    public Widget(String arg0) throws java.io.IOException {
+        super((String) null, 0);
+        throw new RuntimeException(\"Stub!\");
+    }
+
+    public abstract int size();
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_nest_member_classes() {
+        let public = [ClassAccessFlags::AccPublic, ClassAccessFlags::AccSuper];
+        let object = "java/lang/Object";
+        let members = [
+            ("test/Outer$Inner", "test/Outer", "Inner", 0x0001),
+            ("test/Outer$Hidden", "test/Outer", "Hidden", 0x000a),
+        ];
+        let classes = [
+            build(empty_class(&public, "test/Outer", object), &members),
+            build(
+                empty_class(&[ClassAccessFlags::AccSuper], "test/Outer$Inner", object),
+                &members[..1],
+            ),
+            build(
+                empty_class(&[ClassAccessFlags::AccSuper], "test/Outer$Hidden", object),
+                &members[1..],
+            ),
+        ];
+        let stubs = StubGenerator::new(&classes).get_stubs().unwrap();
+
+        assert_eq!(stubs.keys().collect::<Vec<_>>(), ["test/Outer.java"]);
+        assert_eq!(
+            stubs["test/Outer.java"],
+            "\
+package test;
+
+public class Outer {
+    public Outer() {
+        throw new RuntimeException(\"Stub!\");
+    }
+
+    public class Inner {
+        public Inner() {
+            throw new RuntimeException(\"Stub!\");
+        }
+    }
+}
+"
+        );
+    }
+}
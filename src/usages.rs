@@ -0,0 +1,577 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt::{Display, Write};
+
+use crate::attributes::{Code, Methods};
+use crate::class_file::{AttributeInfo, ClassFile, ConstantPool};
+use crate::constants::get_raw_utf8;
+use crate::hierarchy::HierarchyIndex;
+use crate::instructions::*;
+
+/**
+ * What to search for: a class, or a field or method of it, with or without its descriptor.\
+ *  Written like `java/lang/System.exit(I)V` for a method, `java/lang/System.out` or
+ *  `java/lang/System.out:Ljava/io/PrintStream;` for a field, and `java/lang/System` or
+ *  `java.lang.System` for a class. Without a descriptor, a member spec matches the fields
+ *  and every overload of the methods of that name.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemberSpec {
+    pub owner: String,
+    pub name: Option<String>,
+    pub descriptor: Option<String>,
+}
+
+impl MemberSpec {
+    pub fn parse(spec: &str) -> Option<MemberSpec> {
+        let (member, descriptor) = match spec.find(['(', ':']) {
+            Some(index) => {
+                let (member, descriptor) = spec.split_at(index);
+                let descriptor = descriptor.strip_prefix(':').unwrap_or(descriptor);
+                (member, Some(descriptor))
+            }
+            None => (spec, None),
+        };
+        // A dotted name without a descriptor names a class, as it does on the command line.
+        let member = match member.rsplit_once('.') {
+            Some((owner, name)) if descriptor.is_some() || owner.contains('/') => {
+                Some((owner, name))
+            }
+            _ => None,
+        };
+        let spec = match member {
+            Some((owner, name)) => MemberSpec {
+                owner: owner.replace('.', "/"),
+                name: Some(name.into()),
+                descriptor: descriptor.map(String::from),
+            },
+            None if descriptor.is_none() => MemberSpec {
+                owner: spec.replace('.', "/"),
+                name: None,
+                descriptor: None,
+            },
+            None => return None,
+        };
+        let is_empty = |part: &Option<String>| part.as_deref().is_some_and(str::is_empty);
+        match spec.owner.is_empty() || is_empty(&spec.name) || is_empty(&spec.descriptor) {
+            true => None,
+            false => Some(spec),
+        }
+    }
+}
+
+impl Display for MemberSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.owner)?;
+        if let Some(name) = &self.name {
+            write!(f, ".{name}")?;
+        }
+        match self.descriptor.as_deref() {
+            Some(descriptor) if descriptor.starts_with('(') => write!(f, "{descriptor}"),
+            Some(descriptor) => write!(f, ":{descriptor}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How a use site refers to what was searched for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UsageKind {
+    /**
+     * A symbolic reference an instruction resolves: an invoke or field access of a member,
+     * or one of a class, which also new, checkcast, instanceof and the array instructions
+     * make.
+     */
+    Reference,
+    /// A method handle that ldc loads or a bootstrap method takes, as method references are.
+    MethodHandle,
+    /// A class constant that ldc loads or a bootstrap method takes, as `Foo.class` is.
+    ClassConstant,
+    /**
+     * A string constant with the name, as reflection takes it. The name of a member only
+     * counts in a method that also names its class.
+     */
+    String,
+}
+
+impl UsageKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            UsageKind::Reference => "reference",
+            UsageKind::MethodHandle => "method handle",
+            UsageKind::ClassConstant => "class constant",
+            UsageKind::String => "string",
+        }
+    }
+}
+
+/// An instruction that uses what was searched for.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Usage {
+    /// The internal name of the class the instruction is in.
+    pub class: String,
+    /// The name and descriptor of the method the instruction is in.
+    pub method: String,
+    pub pc: u32,
+    /// The source line, from the LineNumberTable of the method.
+    pub line: Option<u16>,
+    pub mnemonic: String,
+    /// The class, member or string the instruction refers to.
+    pub target: String,
+    pub kind: UsageKind,
+}
+
+/// The uses of a class or member across a set of classes.
+#[derive(Clone, Debug)]
+pub struct Usages {
+    pub(crate) usages: Vec<Usage>,
+}
+
+impl Usages {
+    /**
+     * Finds the uses of `spec` in the code of `classes`. A member referred to through a
+     * subclass among `classes`, like an inherited method called on it, is used too.
+     */
+    pub fn new(spec: &MemberSpec, classes: &[ClassFile]) -> Result<Usages, Box<dyn Error>> {
+        let mut search = Search {
+            spec,
+            hierarchy: HierarchyIndex::new(classes),
+            usages: BTreeSet::new(),
+        };
+        for class in classes {
+            search.search_class(class)?;
+        }
+        Ok(Usages {
+            usages: search.usages.into_iter().collect(),
+        })
+    }
+
+    /// The uses found, by class, method and pc.
+    pub fn get_usages(&self) -> &[Usage] {
+        &self.usages
+    }
+
+    /// One line per use, giving where it is, the instruction and what it refers to.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for usage in &self.usages {
+            let line = match usage.line {
+                Some(line) => format!(" line {line}"),
+                None => String::new(),
+            };
+            let kind = match usage.kind {
+                UsageKind::Reference => String::new(),
+                kind => format!(" ({})", kind.get_name()),
+            };
+            let _ = writeln!(
+                text,
+                "{}.{} pc {}{line}: {} {}{kind}",
+                usage.class, usage.method, usage.pc, usage.mnemonic, usage.target
+            );
+        }
+        text
+    }
+}
+
+/// A constant an instruction refers to, as far as it can name what was searched for.
+enum Referred {
+    /// A field or method, with its class, name and descriptor.
+    Member(String, String, String),
+    Class(String),
+    MethodHandle(String, String, String),
+    String(String),
+}
+
+struct Search<'a> {
+    spec: &'a MemberSpec,
+    hierarchy: HierarchyIndex,
+    usages: BTreeSet<Usage>,
+}
+
+impl Search<'_> {
+    fn search_class(&mut self, class: &ClassFile) -> Result<(), Box<dyn Error>> {
+        let pool = &class.constant_pool;
+        let this_class = get_class(pool, class.this_class).unwrap_or_default();
+        let bootstrap_methods = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::BootstrapMethods(methods) => Some(&methods.bootstrap_methods[..]),
+                _ => None,
+            })
+            .unwrap_or_default();
+        for method in &class.methods {
+            let Some(code) = method
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    AttributeInfo::Code(code) => Some(code),
+                    _ => None,
+                })
+            else {
+                continue;
+            };
+            let name = get_raw_utf8(pool, method.name_index).unwrap_or_default()
+                + &get_raw_utf8(pool, method.descriptor_index).unwrap_or_default();
+            self.search_code(pool, bootstrap_methods, code, &this_class, &name)?;
+        }
+        Ok(())
+    }
+
+    fn search_code(
+        &mut self,
+        pool: &[ConstantPool],
+        bootstrap_methods: &[Methods],
+        code: &Code,
+        class: &str,
+        method: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let instructions = code.get_instructions()?;
+        let referred: Vec<(&Instruction, Vec<Referred>)> = instructions
+            .iter()
+            .map(|instruction| {
+                (
+                    instruction,
+                    get_referred(pool, bootstrap_methods, instruction),
+                )
+            })
+            .collect();
+        let names_owner = referred.iter().any(|(_, referred)| {
+            referred.iter().any(|referred| match referred {
+                Referred::Class(name) => *name == self.spec.owner,
+                Referred::String(string) => self.names_owner(string),
+                _ => false,
+            })
+        });
+        for (instruction, referred) in referred {
+            for referred in referred {
+                let (target, kind) = match referred {
+                    Referred::Member(owner, name, descriptor)
+                        if self.matches_member(&owner, &name, &descriptor) =>
+                    {
+                        (member(&owner, &name, &descriptor), UsageKind::Reference)
+                    }
+                    Referred::MethodHandle(owner, name, descriptor)
+                        if self.matches_member(&owner, &name, &descriptor) =>
+                    {
+                        (member(&owner, &name, &descriptor), UsageKind::MethodHandle)
+                    }
+                    // A member of the class refers to the class as well.
+                    Referred::Member(owner, name, descriptor)
+                        if self.spec.name.is_none() && self.matches_class(&owner) =>
+                    {
+                        (member(&owner, &name, &descriptor), UsageKind::Reference)
+                    }
+                    Referred::MethodHandle(owner, name, descriptor)
+                        if self.spec.name.is_none() && self.matches_class(&owner) =>
+                    {
+                        (member(&owner, &name, &descriptor), UsageKind::MethodHandle)
+                    }
+                    Referred::Class(name)
+                        if self.spec.name.is_none() && self.matches_class(&name) =>
+                    {
+                        let kind = match instruction.opcode {
+                            LDC | LDC_W | INVOKEDYNAMIC => UsageKind::ClassConstant,
+                            _ => UsageKind::Reference,
+                        };
+                        (name, kind)
+                    }
+                    Referred::String(string) if self.matches_string(&string, names_owner) => {
+                        (format!("{string:?}"), UsageKind::String)
+                    }
+                    _ => continue,
+                };
+                self.usages.insert(Usage {
+                    class: class.into(),
+                    method: method.into(),
+                    pc: instruction.pc,
+                    line: code.line_for_pc(instruction.pc as u16),
+                    mnemonic: instruction.get_mnemonic(),
+                    target,
+                    kind,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a reference is to the member searched for, directly or through a subclass.
+    fn matches_member(&self, owner: &str, name: &str, descriptor: &str) -> bool {
+        let spec = self.spec;
+        spec.name.as_deref() == Some(name)
+            && spec
+                .descriptor
+                .as_deref()
+                .is_none_or(|spec| spec == descriptor)
+            && (owner == spec.owner
+                || self.hierarchy.is_assignable(owner, &spec.owner) == Some(true))
+    }
+
+    /// Whether a class, or the element type of an array class, is the class searched for.
+    fn matches_class(&self, name: &str) -> bool {
+        let element = name.trim_start_matches('[');
+        let element = match element.len() < name.len() {
+            true => element
+                .strip_prefix('L')
+                .and_then(|element| element.strip_suffix(';')),
+            false => Some(name),
+        };
+        element == Some(self.spec.owner.as_str())
+    }
+
+    fn matches_string(&self, string: &str, names_owner: bool) -> bool {
+        match &self.spec.name {
+            Some(name) => names_owner && string == name,
+            None => self.names_owner(string),
+        }
+    }
+
+    /// Whether a string is the binary name of the class searched for, dotted or not.
+    fn names_owner(&self, string: &str) -> bool {
+        string == self.spec.owner || string.replace('.', "/") == self.spec.owner
+    }
+}
+
+/// The constants an instruction refers to, including those of its bootstrap method.
+fn get_referred(
+    pool: &[ConstantPool],
+    bootstrap_methods: &[Methods],
+    instruction: &Instruction,
+) -> Vec<Referred> {
+    let index = match instruction.operands {
+        Operands::Constant(index)
+        | Operands::InvokeInterface { index, .. }
+        | Operands::InvokeDynamic(index)
+        | Operands::MultiANewArray { index, .. } => index,
+        _ => return vec![],
+    };
+    let bootstrap_method_attr_index = match pool.get(index as usize) {
+        Some(ConstantPool::InvokeDynamic(call_site)) => call_site.bootstrap_method_attr_index,
+        Some(ConstantPool::Dynamic(constant)) => constant.bootstrap_method_attr_index,
+        _ => return get_constant(pool, index).into_iter().collect(),
+    };
+    let Some(bootstrap) = bootstrap_methods.get(bootstrap_method_attr_index as usize) else {
+        return vec![];
+    };
+    std::iter::once(bootstrap.bootstrap_method_ref)
+        .chain(bootstrap.bootstrap_arguments.iter().copied())
+        .filter_map(|index| get_constant(pool, index))
+        .collect()
+}
+
+fn get_constant(pool: &[ConstantPool], index: u16) -> Option<Referred> {
+    match pool.get(index as usize)? {
+        ConstantPool::Class(class) => Some(Referred::Class(
+            get_raw_utf8(pool, class.name_index).unwrap_or_default(),
+        )),
+        ConstantPool::String(string) => Some(Referred::String(
+            get_raw_utf8(pool, string.string_index).unwrap_or_default(),
+        )),
+        ConstantPool::MethodHandle(handle) => {
+            let Referred::Member(owner, name, descriptor) =
+                get_constant(pool, handle.reference_index)?
+            else {
+                return None;
+            };
+            Some(Referred::MethodHandle(owner, name, descriptor))
+        }
+        ConstantPool::Fieldref(field) => {
+            get_member(pool, field.class_index, field.name_and_type_index)
+        }
+        ConstantPool::Methodref(method) => {
+            get_member(pool, method.class_index, method.name_and_type_index)
+        }
+        ConstantPool::InterfaceMethodRef(method) => {
+            get_member(pool, method.class_index, method.name_and_type_index)
+        }
+        _ => None,
+    }
+}
+
+fn get_member(
+    pool: &[ConstantPool],
+    class_index: u16,
+    name_and_type_index: u16,
+) -> Option<Referred> {
+    let ConstantPool::NameAndType(name_and_type) = pool.get(name_and_type_index as usize)? else {
+        return None;
+    };
+    Some(Referred::Member(
+        get_class(pool, class_index)?,
+        get_raw_utf8(pool, name_and_type.name_index).unwrap_or_default(),
+        get_raw_utf8(pool, name_and_type.descriptor_index).unwrap_or_default(),
+    ))
+}
+
+/// A member as javap writes it, with a `:` before the descriptor of a field.
+fn member(owner: &str, name: &str, descriptor: &str) -> String {
+    match descriptor.starts_with('(') {
+        true => format!("{owner}.{name}{descriptor}"),
+        false => format!("{owner}.{name}:{descriptor}"),
+    }
+}
+
+/// The internal name of a class entry.
+fn get_class(pool: &[ConstantPool], index: u16) -> Option<String> {
+    match pool.get(index as usize)? {
+        ConstantPool::Class(class) => {
+            Some(get_raw_utf8(pool, class.name_index).unwrap_or_default())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access_flags::{ClassAccessFlags, MethodAccessFlags};
+    use crate::class_builder::tests::{class, read_back};
+    use crate::class_builder::{CodeBuilder, Constant, Handle, Insn, MethodBuilder};
+
+    const EXIT: &str = "java/lang/System.exit(I)V";
+
+    fn exit() -> Insn {
+        Insn::Method {
+            opcode: INVOKESTATIC,
+            owner: "java/lang/System".into(),
+            name: "exit".into(),
+            descriptor: "(I)V".into(),
+            interface: false,
+        }
+    }
+
+    /**
+     * `test/Main`, whose `run` calls `System.exit` on line 10, loads a handle to it on line
+     * 11 and looks it up by reflection on line 12, and whose `quit` calls it on line 20.
+     */
+    fn main_class() -> ClassFile {
+        let mut class = class(
+            &[ClassAccessFlags::AccSuper],
+            "test/Main",
+            "java/lang/Object",
+            &[],
+        );
+        let flags = [MethodAccessFlags::AccStatic];
+
+        let mut code = CodeBuilder::new(2, 0);
+        for (line, instructions) in [
+            (10, vec![Insn::Simple(ICONST_0), exit()]),
+            (
+                11,
+                vec![
+                    Insn::Ldc(Constant::MethodHandle(Handle {
+                        kind: 6,
+                        owner: "java/lang/System".into(),
+                        name: "exit".into(),
+                        descriptor: "(I)V".into(),
+                        interface: false,
+                    })),
+                    Insn::Simple(POP),
+                ],
+            ),
+            (
+                12,
+                vec![
+                    Insn::Ldc(Constant::String("java.lang.System".into())),
+                    Insn::Ldc(Constant::String("exit".into())),
+                    Insn::Simple(POP2),
+                    Insn::Simple(RETURN),
+                ],
+            ),
+        ] {
+            let label = code.new_label();
+            code.push(Insn::Label(label));
+            code.add_line_number(label, line);
+            for instruction in instructions {
+                code.push(instruction);
+            }
+        }
+        let mut run = MethodBuilder::new(&flags, "run", "()V");
+        run.set_code(code);
+        class.add_method(run).unwrap();
+
+        let mut code = CodeBuilder::new(1, 0);
+        let label = code.new_label();
+        code.push(Insn::Label(label));
+        code.add_line_number(label, 20);
+        code.push(Insn::Simple(ICONST_1));
+        code.push(exit());
+        code.push(Insn::Simple(RETURN));
+        let mut quit = MethodBuilder::new(&flags, "quit", "()V");
+        quit.set_code(code);
+        class.add_method(quit).unwrap();
+        read_back(class)
+    }
+
+    #[test]
+    fn test_member_spec() {
+        let spec = |owner: &str, name: Option<&str>, descriptor: Option<&str>| MemberSpec {
+            owner: owner.into(),
+            name: name.map(String::from),
+            descriptor: descriptor.map(String::from),
+        };
+        assert_eq!(
+            MemberSpec::parse(EXIT),
+            Some(spec("java/lang/System", Some("exit"), Some("(I)V")))
+        );
+        assert_eq!(
+            MemberSpec::parse("java.lang.System.out:Ljava/io/PrintStream;"),
+            Some(spec(
+                "java/lang/System",
+                Some("out"),
+                Some("Ljava/io/PrintStream;")
+            ))
+        );
+        assert_eq!(
+            MemberSpec::parse("java/lang/System.out"),
+            Some(spec("java/lang/System", Some("out"), None))
+        );
+        assert_eq!(
+            MemberSpec::parse("java.lang.System"),
+            Some(spec("java/lang/System", None, None))
+        );
+        assert_eq!(MemberSpec::parse("java/lang/System.exit:"), None);
+        assert_eq!(MemberSpec::parse("exit(I)V"), None);
+        assert_eq!(MemberSpec::parse(EXIT).unwrap().to_string(), EXIT);
+    }
+
+    #[test]
+    fn test_uses_with_lines() {
+        let spec = MemberSpec::parse(EXIT).unwrap();
+        let usages = Usages::new(&spec, &[main_class()]).unwrap();
+        let found: Vec<(&str, u32, Option<u16>, UsageKind)> = usages
+            .get_usages()
+            .iter()
+            .map(|usage| (usage.method.as_str(), usage.pc, usage.line, usage.kind))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("quit()V", 1, Some(20), UsageKind::Reference),
+                ("run()V", 1, Some(10), UsageKind::Reference),
+                ("run()V", 4, Some(11), UsageKind::MethodHandle),
+                ("run()V", 9, Some(12), UsageKind::String),
+            ]
+        );
+        assert_eq!(
+            usages.to_text().lines().next(),
+            Some("test/Main.quit()V pc 1 line 20: invokestatic java/lang/System.exit(I)V")
+        );
+    }
+
+    #[test]
+    fn test_class_uses() {
+        let spec = MemberSpec::parse("java.lang.System").unwrap();
+        let usages = Usages::new(&spec, &[main_class()]).unwrap();
+        let kinds: Vec<UsageKind> = usages.get_usages().iter().map(|usage| usage.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                UsageKind::Reference,
+                UsageKind::Reference,
+                UsageKind::MethodHandle,
+                UsageKind::String,
+            ]
+        );
+    }
+}
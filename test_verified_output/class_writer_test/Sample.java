@@ -0,0 +1,56 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.util.List;
+import java.util.Map;
+import java.util.function.Supplier;
+
+@Deprecated
+public class Sample<T extends Comparable<T>> {
+    @Retention(RetentionPolicy.RUNTIME)
+    @Target({ElementType.TYPE_USE, ElementType.TYPE_PARAMETER})
+    @interface Checked {
+        String value() default "";
+        int[] codes() default {1, 2};
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Tagged {
+        ElementType kind();
+        Class<?> type();
+        Checked nested();
+    }
+
+    record Point(int x, @Checked("y") long y) {}
+
+    static final String NAME = "caf\u00e9 \0 \ud83d\ude00";
+    static final double RATIO = 0.5;
+
+    @Tagged(kind = ElementType.FIELD, type = String[].class, nested = @Checked(codes = {3}))
+    Map<@Checked("key") String, List<@Checked String[]>> table;
+
+    <@Checked U extends T> int compare(U left, final T right) throws IllegalStateException {
+        int result = 0;
+        for (int i = 0; i < 3; i++) {
+            try {
+                result += left.compareTo(right);
+            } catch (ClassCastException | NullPointerException e) {
+                result--;
+            }
+        }
+        switch (result) {
+            case 1: return 10;
+            case 2: return 20;
+            case 300: return 30;
+            default: return result;
+        }
+    }
+
+    Supplier<String> supplier(long value) {
+        Object lock = new Object();
+        synchronized (lock) {
+            return () -> NAME + value + RATIO;
+        }
+    }
+}
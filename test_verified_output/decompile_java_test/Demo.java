@@ -0,0 +1,79 @@
+import java.util.*;
+import java.util.function.*;
+
+public class Demo {
+    static int classify(int value) {
+        int result;
+        switch (value) {
+            case 0:
+                result = 10;
+                break;
+            case 1:
+            case 2:
+                result = 20;
+                break;
+            case 7:
+                return 7;
+            default:
+                result = -1;
+        }
+        return result + 1;
+    }
+
+    static String name(String key) {
+        switch (key) {
+            case "a":
+                return "alpha";
+            case "b":
+                return "beta";
+            default:
+                return "other";
+        }
+    }
+
+    static int parse(String text) {
+        int value;
+        try {
+            value = Integer.parseInt(text);
+        } catch (NumberFormatException e) {
+            value = -1;
+        }
+        System.out.println(value);
+        return value;
+    }
+
+    static void run() {
+        Runnable r = () -> System.out.println("hi");
+        r.run();
+        Function<String, Integer> length = String::length;
+        System.out.println(length.apply("abc"));
+    }
+
+    static <T extends Comparable<T>> T max(List<T> items) {
+        T best = null;
+        for (T item : items) {
+            if (best == null || item.compareTo(best) > 0) {
+                best = item;
+            }
+        }
+        return best;
+    }
+
+    static <T> Supplier<T> constant(T value) {
+        Supplier<T> supplier = () -> value;
+        return supplier;
+    }
+
+    public static void main(String[] args) {
+        for (String arg : args) {
+            try {
+                System.out.println(classify(Integer.parseInt(arg)));
+            } catch (NumberFormatException e) {
+                System.out.println(name(arg));
+            } finally {
+                System.out.println("done");
+            }
+        }
+        run();
+    }
+}